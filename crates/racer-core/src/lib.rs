@@ -4,13 +4,22 @@
 //!
 //! This crate provides:
 //! - [`Message`] trait for custom consensus payloads
+//! - Built-in payloads in [`message`], for prototyping without a schema
 //! - [`ValidationError`] for field validation
+//! - [`GenerateRandom`] for schema-aware random payloads
+//! - JSON Schema export and protobuf rendering in [`schema`]
 //! - Common error types
 
 pub mod error;
 pub mod message;
+pub mod random;
+pub mod schema;
+pub mod serde_array;
 pub mod validation;
 
 pub use error::RacerError;
 pub use message::Message;
+pub use random::GenerateRandom;
 pub use validation::{FieldValidator, ValidationError, ValidationResult};
+/// Type of `uuid` fields in `racer_message` schemas.
+pub use uuid::Uuid;
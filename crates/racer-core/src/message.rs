@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
+
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::validation::ValidationResult;
+use crate::validation::{ValidationError, ValidationResult};
 
 /// Trait for consensus message payloads.
 ///
@@ -33,9 +35,30 @@ pub trait Message: Clone + Send + Sync + Serialize + DeserializeOwned + 'static
         serde_json::to_vec(self).unwrap_or_default()
     }
 
+    /// SHA-256 of [`merkle_bytes`](Self::merkle_bytes): the message's leaf in
+    /// its batch's merkle tree. Equal content gives an equal hash.
+    fn content_hash(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(self.merkle_bytes()).into()
+    }
+
     fn validate(&self) -> ValidationResult {
         Ok(())
     }
+
+    /// JSON Schema of the serialized payload. The default, `{}`, accepts any
+    /// JSON; `racer_message` types describe their fields and constraints.
+    fn schema() -> crate::schema::Value {
+        serde_json::json!({})
+    }
+}
+
+/// Milliseconds since the Unix epoch, or 0 if the clock is before it.
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
 }
 
 #[derive(Clone, Debug, Default, Serialize, serde::Deserialize)]
@@ -47,10 +70,7 @@ pub struct DefaultMessage {
 impl DefaultMessage {
     pub fn new() -> Self {
         Self {
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_millis() as u64)
-                .unwrap_or(0),
+            timestamp: now_millis(),
             padding: 0,
         }
     }
@@ -66,6 +86,213 @@ impl Message for DefaultMessage {
     fn id(&self) -> u64 {
         self.timestamp
     }
+
+    fn schema() -> crate::schema::Value {
+        serde_json::json!({
+            "$schema": crate::schema::DRAFT,
+            "title": "DefaultMessage",
+            "type": "object",
+            "properties": {
+                "timestamp": { "type": "integer", "minimum": 0, "maximum": u64::MAX },
+                "padding": { "type": "integer", "minimum": 0, "maximum": u64::MAX }
+            },
+            "required": ["timestamp", "padding"]
+        })
+    }
+}
+
+/// 64-bit FNV-1a of the message's serialized form.
+///
+/// The built-in payloads below use it as their [`Message::id`]: a retry of
+/// the same message keeps its id on every node and build, while two
+/// messages created in the same millisecond do not share one.
+fn content_id<M: Message>(message: &M) -> u64 {
+    message
+        .merkle_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// String map schema shared by [`KeyValueMessage`] and [`MetricMessage`] tags.
+fn string_map_schema() -> crate::schema::Value {
+    serde_json::json!({ "type": "object", "additionalProperties": { "type": "string" } })
+}
+
+/// Rejects maps with an empty key.
+fn check_keys(field: &str, map: &BTreeMap<String, String>) -> ValidationResult {
+    if map.keys().any(|key| key.is_empty()) {
+        return Err(ValidationError::custom(field, format!("field '{}' has an empty key", field)));
+    }
+    Ok(())
+}
+
+/// A map of string entries, for configuration and state updates.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+pub struct KeyValueMessage {
+    pub timestamp: u64,
+    /// Sorted, so every node serializes, and hashes, the same bytes.
+    pub entries: BTreeMap<String, String>,
+}
+
+impl KeyValueMessage {
+    pub fn new() -> Self {
+        Self {
+            timestamp: now_millis(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.entries.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+}
+
+impl Message for KeyValueMessage {
+    fn id(&self) -> u64 {
+        content_id(self)
+    }
+
+    /// Needs at least one entry, and no empty keys.
+    fn validate(&self) -> ValidationResult {
+        if self.entries.is_empty() {
+            return Err(ValidationError::required("entries"));
+        }
+        check_keys("entries", &self.entries)
+    }
+
+    fn schema() -> crate::schema::Value {
+        serde_json::json!({
+            "$schema": crate::schema::DRAFT,
+            "title": "KeyValueMessage",
+            "type": "object",
+            "properties": {
+                "timestamp": { "type": "integer", "minimum": 0, "maximum": u64::MAX },
+                "entries": string_map_schema()
+            },
+            "required": ["timestamp", "entries"]
+        })
+    }
+}
+
+/// Opaque bytes tagged with a MIME type.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+pub struct BlobMessage {
+    pub timestamp: u64,
+    /// MIME type of `data`, such as `application/octet-stream`.
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl BlobMessage {
+    pub fn new(content_type: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            timestamp: now_millis(),
+            content_type: content_type.into(),
+            data: data.into(),
+        }
+    }
+}
+
+impl Message for BlobMessage {
+    fn id(&self) -> u64 {
+        content_id(self)
+    }
+
+    /// Needs a `type/subtype` content type. Empty data is allowed.
+    fn validate(&self) -> ValidationResult {
+        match self.content_type.split_once('/') {
+            Some((kind, subtype)) if !kind.is_empty() && !subtype.is_empty() && !subtype.contains('/') => Ok(()),
+            _ if self.content_type.is_empty() => Err(ValidationError::required("content_type")),
+            _ => Err(ValidationError::pattern("content_type", "type/subtype")),
+        }
+    }
+
+    fn schema() -> crate::schema::Value {
+        serde_json::json!({
+            "$schema": crate::schema::DRAFT,
+            "title": "BlobMessage",
+            "type": "object",
+            "properties": {
+                "timestamp": { "type": "integer", "minimum": 0, "maximum": u64::MAX },
+                "content_type": { "type": "string", "minLength": 3 },
+                "data": { "type": "array", "items": { "type": "integer", "minimum": 0, "maximum": 255 } }
+            },
+            "required": ["timestamp", "content_type", "data"]
+        })
+    }
+}
+
+/// A named measurement, with an optional unit and tags.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, serde::Deserialize)]
+pub struct MetricMessage {
+    pub timestamp: u64,
+    pub name: String,
+    pub value: f64,
+    /// Empty for dimensionless values.
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl MetricMessage {
+    pub fn new(name: impl Into<String>, value: f64) -> Self {
+        Self {
+            timestamp: now_millis(),
+            name: name.into(),
+            value,
+            unit: String::new(),
+            tags: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = unit.into();
+        self
+    }
+
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+}
+
+impl Message for MetricMessage {
+    fn id(&self) -> u64 {
+        content_id(self)
+    }
+
+    /// Needs a name and a finite value, and no empty tag keys.
+    fn validate(&self) -> ValidationResult {
+        if self.name.is_empty() {
+            return Err(ValidationError::required("name"));
+        }
+        if !self.value.is_finite() {
+            return Err(ValidationError::custom("value", "field 'value' must be finite"));
+        }
+        check_keys("tags", &self.tags)
+    }
+
+    fn schema() -> crate::schema::Value {
+        serde_json::json!({
+            "$schema": crate::schema::DRAFT,
+            "title": "MetricMessage",
+            "type": "object",
+            "properties": {
+                "timestamp": { "type": "integer", "minimum": 0, "maximum": u64::MAX },
+                "name": { "type": "string", "minLength": 1 },
+                "value": { "type": "number" },
+                "unit": { "type": "string" },
+                "tags": string_map_schema()
+            },
+            "required": ["timestamp", "name", "value"]
+        })
+    }
 }
 
 #[cfg(test)]
@@ -88,4 +315,55 @@ mod tests {
         let parsed: DefaultMessage = serde_json::from_slice(&bytes).unwrap();
         assert_eq!(parsed.padding, 42);
     }
+
+    #[test]
+    fn test_content_hash_follows_content() {
+        let msg = DefaultMessage::with_padding(42);
+        assert_eq!(msg.content_hash(), msg.clone().content_hash());
+        assert_ne!(msg.content_hash(), DefaultMessage { padding: 43, ..msg.clone() }.content_hash());
+    }
+
+    #[test]
+    fn test_default_message_schema_lists_fields() {
+        let schema = DefaultMessage::schema();
+        assert_eq!(schema["title"], "DefaultMessage");
+        assert_eq!(schema["properties"]["timestamp"]["type"], "integer");
+        assert!(crate::schema::to_proto(&schema).contains("uint64 padding = 1;"));
+    }
+
+    #[test]
+    fn test_key_value_message() {
+        let msg = KeyValueMessage::new().with("mode", "eco").with("level", "3");
+        assert_eq!(msg.get("mode"), Some("eco"));
+        assert!(msg.validate().is_ok());
+        assert!(KeyValueMessage::new().validate().is_err());
+        assert!(KeyValueMessage::new().with("", "x").validate().is_err());
+
+        let parsed: KeyValueMessage = serde_json::from_slice(&msg.merkle_bytes()).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(parsed.id(), msg.id());
+        assert_ne!(msg.clone().with("mode", "boost").id(), msg.id());
+    }
+
+    #[test]
+    fn test_blob_message_needs_content_type() {
+        assert!(BlobMessage::new("image/png", vec![0x89, b'P']).validate().is_ok());
+        assert!(BlobMessage::new("application/octet-stream", Vec::new()).validate().is_ok());
+        for content_type in ["", "png", "image/", "/png", "a/b/c"] {
+            assert!(BlobMessage::new(content_type, vec![1]).validate().is_err(), "{}", content_type);
+        }
+        assert!(crate::schema::to_proto(&BlobMessage::schema()).contains("bytes data = 2;"));
+    }
+
+    #[test]
+    fn test_metric_message() {
+        let msg = MetricMessage::new("cpu.load", 0.75).with_unit("ratio").with_tag("host", "edge-1");
+        assert!(msg.validate().is_ok());
+        assert!(MetricMessage::new("", 1.0).validate().is_err());
+        assert!(MetricMessage::new("cpu.load", f64::NAN).validate().is_err());
+
+        let parsed: MetricMessage = serde_json::from_str(r#"{"timestamp":1,"name":"up","value":1.0}"#).unwrap();
+        assert!(parsed.unit.is_empty() && parsed.tags.is_empty());
+        assert!(crate::schema::to_proto(&MetricMessage::schema()).contains("map<string, string> tags = 2;"));
+    }
 }
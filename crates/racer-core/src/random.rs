@@ -0,0 +1,144 @@
+//! Schema-aware random payload generation.
+//!
+//! Types produced by `racer_message` implement [`GenerateRandom`], yielding
+//! values that satisfy the TOML constraints. Benches, simulations and
+//! property tests can use it to build realistic payloads for any schema.
+
+pub use rand::Rng;
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+/// Extra repetitions allowed beyond the minimum of `*`, `+` and `{n,m}`.
+const MAX_EXTRA_REPEATS: u32 = 8;
+
+pub trait GenerateRandom: Sized {
+    /// Returns a random value satisfying every declared constraint.
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self;
+
+    /// Returns a random value violating exactly one declared constraint,
+    /// or `None` when the schema has no constraint that can be broken.
+    fn random_invalid<R: Rng + ?Sized>(rng: &mut R) -> Option<Self> {
+        let _ = rng;
+        None
+    }
+}
+
+pub fn alphanumeric_string<R: Rng + ?Sized>(rng: &mut R, len: usize) -> String {
+    (0..len)
+        .map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
+        .collect()
+}
+
+pub fn random_bytes<R: Rng + ?Sized>(rng: &mut R, len: usize) -> Vec<u8> {
+    (0..len).map(|_| rng.gen::<u8>()).collect()
+}
+
+/// Returns a random version 4 UUID drawn from `rng`.
+pub fn uuid_v4<R: Rng + ?Sized>(rng: &mut R) -> uuid::Uuid {
+    uuid::Builder::from_random_bytes(rng.gen()).into_uuid()
+}
+
+/// Returns a random string matched by `pattern`.
+///
+/// Anchors and word boundaries are not enforced; everything else in the
+/// pattern is. Large repetition counts are capped at `min + 8`.
+///
+/// # Panics
+///
+/// Panics if `pattern` is not a valid regex (`racer_message` rejects those at
+/// compile time).
+pub fn string_matching<R: Rng + ?Sized>(rng: &mut R, pattern: &str) -> String {
+    let hir = regex_syntax::parse(pattern).expect("invalid pattern");
+    let mut out = String::new();
+    push_matching(rng, &hir, &mut out);
+    out
+}
+
+fn push_matching<R: Rng + ?Sized>(rng: &mut R, hir: &Hir, out: &mut String) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(literal) => out.push_str(&String::from_utf8_lossy(&literal.0)),
+        HirKind::Class(Class::Unicode(class)) => {
+            let total: u32 = class
+                .ranges()
+                .iter()
+                .map(|r| r.end() as u32 - r.start() as u32 + 1)
+                .sum();
+            if total == 0 {
+                return;
+            }
+            let mut pick = rng.gen_range(0..total);
+            for range in class.ranges() {
+                let size = range.end() as u32 - range.start() as u32 + 1;
+                if pick < size {
+                    out.push(char::from_u32(range.start() as u32 + pick).unwrap_or(range.start()));
+                    return;
+                }
+                pick -= size;
+            }
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let ranges = class.ranges();
+            let range = &ranges[rng.gen_range(0..ranges.len())];
+            out.push(rng.gen_range(range.start()..=range.end()) as char);
+        }
+        HirKind::Repetition(rep) => {
+            let max = rep
+                .max
+                .unwrap_or(u32::MAX)
+                .min(rep.min.saturating_add(MAX_EXTRA_REPEATS));
+            for _ in 0..rng.gen_range(rep.min..=max) {
+                push_matching(rng, &rep.sub, out);
+            }
+        }
+        HirKind::Capture(capture) => push_matching(rng, &capture.sub, out),
+        HirKind::Concat(subs) => {
+            for sub in subs {
+                push_matching(rng, sub, out);
+            }
+        }
+        HirKind::Alternation(subs) => {
+            let idx = rng.gen_range(0..subs.len());
+            push_matching(rng, &subs[idx], out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alphanumeric_string_length() {
+        let mut rng = rand::thread_rng();
+        let s = alphanumeric_string(&mut rng, 12);
+        assert_eq!(s.len(), 12);
+        assert!(s.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_string_matching_satisfies_pattern() {
+        let mut rng = rand::thread_rng();
+        for pattern in [r"^[A-Z]{3}-\d+$", r"^(foo|bar)_[a-f0-9]{4,6}$", r"^x?y*z+$"] {
+            let re = regex::Regex::new(pattern).unwrap();
+            for _ in 0..100 {
+                let s = string_matching(&mut rng, pattern);
+                assert!(re.is_match(&s), "{:?} should match {}", s, pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_uuid_v4_sets_version() {
+        let mut rng = rand::thread_rng();
+        let id = uuid_v4(&mut rng);
+        assert_eq!(id.get_version_num(), 4);
+        assert_ne!(id, uuid_v4(&mut rng));
+    }
+
+    #[test]
+    fn test_random_bytes_length() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(random_bytes(&mut rng, 7).len(), 7);
+        assert!(random_bytes(&mut rng, 0).is_empty());
+    }
+}
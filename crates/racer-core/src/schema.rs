@@ -0,0 +1,205 @@
+//! JSON Schema descriptions of message payloads.
+//!
+//! [`Message::schema`](crate::Message::schema) describes the JSON a message
+//! serializes to, so non-Rust consumers of delivered batches can validate
+//! payloads. [`to_proto`] renders such a schema as a proto3 definition for
+//! generating bindings.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+pub use serde_json::Value;
+
+/// Draft the generated schemas conform to.
+pub const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Parses a schema embedded as a JSON string by `racer_message`.
+pub fn from_embedded(json: &str) -> Value {
+    serde_json::from_str(json).expect("racer_message embeds valid JSON")
+}
+
+/// Renders `schema` as a proto3 file.
+///
+/// Objects become messages named after their `title`, and a top-level
+/// `oneOf` of single-key objects (the shape of a `racer_messages!` enum)
+/// becomes a wrapper message with a `oneof`. Field numbers follow property
+/// order, which is alphabetical, so the output suits the proto3 JSON mapping
+/// rather than binary compatibility across schema changes. Properties without
+/// a protobuf equivalent are emitted as comments.
+pub fn to_proto(schema: &Value) -> String {
+    let name = schema.get("title").and_then(Value::as_str).unwrap_or("Message");
+    let mut out = String::from("syntax = \"proto3\";\n");
+
+    let Some(variants) = schema.get("oneOf").and_then(Value::as_array) else {
+        out.push('\n');
+        render_message(&mut out, name, schema);
+        return out;
+    };
+
+    let mut cases = Vec::new();
+    for variant in variants {
+        let Some((tag, inner)) = variant
+            .get("properties")
+            .and_then(Value::as_object)
+            .and_then(|props| props.iter().next())
+        else {
+            continue;
+        };
+        out.push('\n');
+        render_message(&mut out, tag, inner);
+        cases.push(tag.as_str());
+    }
+
+    let _ = write!(out, "\nmessage {} {{\n  oneof payload {{\n", name);
+    for (idx, tag) in cases.iter().enumerate() {
+        let _ = writeln!(out, "    {} {} = {};", tag, snake_case(tag), idx + 1);
+    }
+    out.push_str("  }\n}\n");
+    out
+}
+
+fn render_message(out: &mut String, name: &str, schema: &Value) {
+    let required: HashSet<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let _ = writeln!(out, "message {} {{", name);
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (idx, (field, prop)) in properties.into_iter().flatten().enumerate() {
+        let number = idx + 1;
+        let line = match prop.get("type").and_then(Value::as_str) {
+            Some("array") if scalar_type(prop).is_none() => {
+                repeated_type(prop).map(|item| format!("repeated {} {} = {};", item, field, number))
+            }
+            Some("object") => prop
+                .get("additionalProperties")
+                .and_then(scalar_type)
+                .map(|value| format!("map<string, {}> {} = {};", value, field, number)),
+            _ => scalar_type(prop).map(|ty| {
+                let label = if required.contains(field.as_str()) { "" } else { "optional " };
+                format!("{}{} {} = {};", label, ty, field, number)
+            }),
+        };
+        match line {
+            Some(line) => {
+                let _ = writeln!(out, "  {}", line);
+            }
+            None => {
+                let _ = writeln!(out, "  // {}: no protobuf equivalent", field);
+            }
+        }
+    }
+    out.push_str("}\n");
+}
+
+fn repeated_type(array: &Value) -> Option<&'static str> {
+    array.get("items").and_then(scalar_type)
+}
+
+/// Protobuf scalar for a schema; byte arrays map to `bytes`.
+fn scalar_type(schema: &Value) -> Option<&'static str> {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+
+    match schema.get("type").and_then(Value::as_str)? {
+        "boolean" => Some("bool"),
+        "number" => Some("double"),
+        "string" => Some("string"),
+        "integer" => {
+            let (min, max) = (bound("minimum"), bound("maximum"));
+            let unsigned = min.is_some_and(|m| m >= 0.0);
+            Some(match (unsigned, max) {
+                (true, Some(max)) if max <= u32::MAX as f64 => "uint32",
+                (true, _) => "uint64",
+                (false, Some(max)) if max <= i32::MAX as f64 && min.is_some_and(|m| m >= i32::MIN as f64) => {
+                    "int32"
+                }
+                (false, _) => "int64",
+            })
+        }
+        "array" => {
+            let items = schema.get("items")?;
+            let is_byte = items.get("type").and_then(Value::as_str) == Some("integer")
+                && items.get("minimum").and_then(Value::as_f64) == Some(0.0)
+                && items.get("maximum").and_then(Value::as_f64) == Some(255.0);
+            is_byte.then_some("bytes")
+        }
+        _ => None,
+    }
+}
+
+/// `EngineTelemetry` → `engine_telemetry`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (idx, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if idx > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn reading_schema() -> Value {
+        json!({
+            "title": "Reading",
+            "type": "object",
+            "properties": {
+                "raw": { "type": "array", "items": { "type": "integer", "minimum": 0, "maximum": 255 } },
+                "samples": { "type": "array", "items": { "type": "number" } },
+                "level": { "type": "integer", "minimum": 0, "maximum": 255 },
+                "note": { "type": "string" },
+                "offset": { "type": "integer", "minimum": -9223372036854775808i64, "maximum": 9223372036854775807i64 },
+                "tags": { "type": "object", "additionalProperties": { "type": "integer", "minimum": 0, "maximum": 4294967295u32 } },
+                "timestamp": { "type": "integer", "minimum": 0, "maximum": 18446744073709551615u64 },
+                "nested": { "type": "array", "items": { "type": "array", "items": { "type": "boolean" } } }
+            },
+            "required": ["raw", "samples", "level", "offset", "tags", "timestamp", "nested"]
+        })
+    }
+
+    #[test]
+    fn test_to_proto_maps_json_types() {
+        let proto = to_proto(&reading_schema());
+        assert!(proto.starts_with("syntax = \"proto3\";\n\nmessage Reading {\n"));
+        assert!(proto.contains("  uint32 level = 1;"));
+        assert!(proto.contains("  // nested: no protobuf equivalent"));
+        assert!(proto.contains("  optional string note = 3;"));
+        assert!(proto.contains("  int64 offset = 4;"));
+        assert!(proto.contains("  bytes raw = 5;"));
+        assert!(proto.contains("  repeated double samples = 6;"));
+        assert!(proto.contains("  map<string, uint32> tags = 7;"));
+        assert!(proto.contains("  uint64 timestamp = 8;"));
+    }
+
+    #[test]
+    fn test_to_proto_renders_one_of_as_wrapper() {
+        let schema = json!({
+            "title": "FleetMessage",
+            "oneOf": [
+                { "type": "object", "properties": { "GpsFix": { "type": "object", "properties": { "lat": { "type": "number" } }, "required": ["lat"] } } },
+                { "type": "object", "properties": { "EngineTelemetry": { "type": "object", "properties": {} } } }
+            ]
+        });
+
+        let proto = to_proto(&schema);
+        assert!(proto.contains("message GpsFix {\n  double lat = 1;\n}"));
+        assert!(proto.contains("message EngineTelemetry {\n}"));
+        assert!(proto.contains("message FleetMessage {\n  oneof payload {\n    GpsFix gps_fix = 1;\n    EngineTelemetry engine_telemetry = 2;\n  }\n}"));
+    }
+
+    #[test]
+    fn test_from_embedded_parses_json() {
+        assert_eq!(from_embedded(r#"{"type":"object"}"#), json!({ "type": "object" }));
+    }
+}
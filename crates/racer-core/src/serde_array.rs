@@ -0,0 +1,135 @@
+//! Serde support for `[T; N]` of any length.
+//!
+//! `racer_message` fields declared with `length = N` are generated as fixed
+//! arrays and serialized through this module, since serde's own impls stop
+//! at 32 elements. Input of any other length fails to deserialize.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+pub fn serialize<S, T, const N: usize>(value: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    let mut tuple = serializer.serialize_tuple(N)?;
+    for item in value {
+        tuple.serialize_element(item)?;
+    }
+    tuple.end()
+}
+
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+}
+
+struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+impl<'de, T, const N: usize> Visitor<'de> for ArrayVisitor<T, N>
+where
+    T: Deserialize<'de>,
+{
+    type Value = [T; N];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of length {}", N)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut items = Vec::with_capacity(N);
+        while let Some(item) = seq.next_element()? {
+            if items.len() == N {
+                return Err(de::Error::invalid_length(N + 1, &self));
+            }
+            items.push(item);
+        }
+        items
+            .try_into()
+            .map_err(|items: Vec<T>| de::Error::invalid_length(items.len(), &self))
+    }
+}
+
+/// The same for `Option<[T; N]>`, used by `optional` fixed-length fields.
+pub mod option {
+    use super::*;
+
+    struct Borrowed<'a, T, const N: usize>(&'a [T; N]);
+
+    impl<T: Serialize, const N: usize> Serialize for Borrowed<'_, T, N> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::serialize(self.0, serializer)
+        }
+    }
+
+    struct Owned<T, const N: usize>([T; N]);
+
+    impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for Owned<T, N> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            super::deserialize(deserializer).map(Owned)
+        }
+    }
+
+    pub fn serialize<S, T, const N: usize>(value: &Option<[T; N]>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        match value {
+            Some(array) => serializer.serialize_some(&Borrowed(array)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<Option<[T; N]>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        Option::<Owned<T, N>>::deserialize(deserializer).map(|value| value.map(|owned| owned.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Signed {
+        #[serde(with = "crate::serde_array")]
+        signature: [u8; 64],
+        #[serde(default, with = "crate::serde_array::option")]
+        channels: Option<[f32; 3]>,
+    }
+
+    #[test]
+    fn test_round_trips_arrays_longer_than_32() {
+        let signed = Signed { signature: [7; 64], channels: Some([1.0, 2.0, 3.0]) };
+        let json = serde_json::to_string(&signed).unwrap();
+        assert_eq!(serde_json::from_str::<Signed>(&json).unwrap(), signed);
+    }
+
+    #[test]
+    fn test_rejects_wrong_lengths() {
+        let short = format!(r#"{{"signature":{:?}}}"#, [0u8; 63]);
+        assert!(serde_json::from_str::<Signed>(&short).is_err());
+
+        let long = format!(r#"{{"signature":{:?}}}"#, [0u8; 65]);
+        assert!(serde_json::from_str::<Signed>(&long).is_err());
+
+        let channels = format!(r#"{{"signature":{:?},"channels":[1.0]}}"#, [0u8; 64]);
+        assert!(serde_json::from_str::<Signed>(&channels).is_err());
+    }
+
+    #[test]
+    fn test_missing_option_is_none() {
+        let json = format!(r#"{{"signature":{:?}}}"#, [0u8; 64]);
+        assert_eq!(serde_json::from_str::<Signed>(&json).unwrap().channels, None);
+    }
+}
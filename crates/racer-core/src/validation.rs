@@ -1,5 +1,8 @@
 use std::fmt;
 
+/// Re-exported so generated `validate()` bodies can compile `pattern` constraints.
+pub use regex::Regex;
+
 pub type ValidationResult = Result<(), ValidationError>;
 
 #[derive(Debug, Clone)]
@@ -58,6 +61,42 @@ impl ValidationError {
             ValidationKind::MaxLength { max, actual },
         )
     }
+
+    pub fn pattern(field: impl Into<String>, pattern: impl Into<String>) -> Self {
+        let field = field.into();
+        let pattern = pattern.into();
+        Self::new(
+            &field,
+            format!("field '{}' must match pattern '{}'", field, pattern),
+            ValidationKind::Pattern { pattern },
+        )
+    }
+
+    pub fn one_of(field: impl Into<String>, allowed: &[f64], actual: f64) -> Self {
+        let field = field.into();
+        Self::new(
+            &field,
+            format!("field '{}' must be one of {:?} (got {})", field, allowed, actual),
+            ValidationKind::OneOf {
+                allowed: allowed.to_vec(),
+                actual,
+            },
+        )
+    }
+
+    pub fn rule(field: impl Into<String>, expr: impl Into<String>) -> Self {
+        let field = field.into();
+        let expr = expr.into();
+        Self::new(
+            &field,
+            format!("field '{}' violates rule '{}'", field, expr),
+            ValidationKind::Rule { expr },
+        )
+    }
+
+    pub fn custom(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(field, message, ValidationKind::Custom)
+    }
 }
 
 impl fmt::Display for ValidationError {
@@ -75,6 +114,10 @@ pub enum ValidationKind {
     MaxValue { max: f64, actual: f64 },
     MinLength { min: usize, actual: usize },
     MaxLength { max: usize, actual: usize },
+    Pattern { pattern: String },
+    OneOf { allowed: Vec<f64>, actual: f64 },
+    Rule { expr: String },
+    Custom,
 }
 
 pub trait FieldValidator {
@@ -99,6 +142,30 @@ impl<K, V> FieldValidator for std::collections::HashMap<K, V> {
     }
 }
 
+impl<T, const N: usize> FieldValidator for [T; N] {
+    fn is_empty(&self) -> bool {
+        N == 0
+    }
+}
+
+/// The nil UUID counts as empty.
+impl FieldValidator for uuid::Uuid {
+    fn is_empty(&self) -> bool {
+        self.is_nil()
+    }
+}
+
+/// `None` counts as empty, so `required` on an optional field means present
+/// and non-empty.
+impl<T: FieldValidator> FieldValidator for Option<T> {
+    fn is_empty(&self) -> bool {
+        match self {
+            Some(value) => value.is_empty(),
+            None => true,
+        }
+    }
+}
+
 macro_rules! impl_field_validator_numeric {
     ($($t:ty),*) => {
         $(
@@ -124,11 +191,45 @@ mod tests {
         assert!(err.to_string().contains("required"));
     }
 
+    #[test]
+    fn test_pattern_and_one_of_errors() {
+        let err = ValidationError::pattern("code", "^[A-Z]{3}$");
+        assert!(err.to_string().contains("^[A-Z]{3}$"));
+
+        let err = ValidationError::one_of("channels", &[1.0, 2.0, 4.0], 3.0);
+        assert_eq!(
+            err.kind,
+            ValidationKind::OneOf {
+                allowed: vec![1.0, 2.0, 4.0],
+                actual: 3.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_rule_error_includes_expression() {
+        let err = ValidationError::rule("end_time", "end_time >= start_time");
+        assert_eq!(err.kind, ValidationKind::Rule { expr: "end_time >= start_time".into() });
+        assert!(err.to_string().contains("end_time >= start_time"));
+    }
+
+    #[test]
+    fn test_custom_error_keeps_message() {
+        let err = ValidationError::custom("low", "low must not exceed high");
+        assert_eq!(err.kind, ValidationKind::Custom);
+        assert_eq!(err.to_string(), "low must not exceed high");
+    }
+
     #[test]
     fn test_field_validator() {
         assert!(String::new().is_empty());
         assert!(!String::from("hello").is_empty());
         assert!(Vec::<u8>::new().is_empty());
         assert!(!42u64.is_empty());
+        assert!(None::<u64>.is_empty());
+        assert!(Some(String::new()).is_empty());
+        assert!(!Some(0u64).is_empty());
+        assert!(uuid::Uuid::nil().is_empty());
+        assert!(!uuid::Uuid::new_v4().is_empty());
     }
 }
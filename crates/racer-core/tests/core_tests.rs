@@ -25,6 +25,18 @@ mod message_tests {
             assert!(msg.timestamp <= after, "timestamp should be <= time after creation");
         }
 
+        #[test]
+        fn now_millis_should_match_system_clock() {
+            let expected = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+
+            let now = racer_core::message::now_millis();
+
+            assert!(now >= expected && now - expected < 1_000, "now_millis should be epoch millis");
+        }
+
         #[test]
         fn new_should_set_padding_to_zero() {
             let msg = DefaultMessage::new();
@@ -262,6 +274,11 @@ mod message_tests {
             
             assert!(msg.validate().is_ok(), "default validate() should return Ok");
         }
+
+        #[test]
+        fn custom_message_should_use_permissive_default_schema() {
+            assert_eq!(CustomMessage::schema(), serde_json::json!({}), "default schema() should accept anything");
+        }
     }
 }
 
@@ -329,6 +346,43 @@ mod validation_tests {
             assert!(err.message.contains("500"), "message should contain max length");
             assert!(err.message.contains("750"), "message should contain actual length");
         }
+
+        #[test]
+        fn pattern_should_include_pattern_in_message() {
+            let err = ValidationError::pattern("code", "^[A-Z]{3}$");
+
+            assert_eq!(err.field, "code");
+            assert_eq!(err.kind, ValidationKind::Pattern { pattern: "^[A-Z]{3}$".into() });
+            assert!(err.message.contains("^[A-Z]{3}$"), "message should contain the pattern");
+        }
+
+        #[test]
+        fn one_of_should_include_allowed_values_in_message() {
+            let err = ValidationError::one_of("channels", &[1.0, 2.0], 3.0);
+
+            assert_eq!(err.field, "channels");
+            assert!(matches!(err.kind, ValidationKind::OneOf { actual, .. } if actual == 3.0));
+            assert!(err.message.contains("[1.0, 2.0]"), "message should list allowed values");
+            assert!(err.message.contains("3"), "message should contain actual value");
+        }
+
+        #[test]
+        fn rule_should_include_expression_in_message() {
+            let err = ValidationError::rule("end_time", "end_time >= start_time");
+
+            assert_eq!(err.field, "end_time");
+            assert_eq!(err.kind, ValidationKind::Rule { expr: "end_time >= start_time".into() });
+            assert!(err.message.contains("end_time >= start_time"), "message should contain the rule");
+        }
+
+        #[test]
+        fn custom_should_use_message_verbatim() {
+            let err = ValidationError::custom("low", "low must not exceed high");
+
+            assert_eq!(err.field, "low");
+            assert_eq!(err.kind, ValidationKind::Custom);
+            assert_eq!(err.message, "low must not exceed high");
+        }
     }
 
     mod validation_error_display {
@@ -527,6 +581,25 @@ mod validation_tests {
             assert!(!true.is_empty());
             assert!(!false.is_empty());
         }
+
+        #[test]
+        fn nil_uuid_should_be_empty() {
+            assert!(racer_core::Uuid::nil().is_empty());
+            assert!(!racer_core::Uuid::new_v4().is_empty());
+        }
+
+        #[test]
+        fn none_should_be_empty() {
+            assert!(None::<u32>.is_empty());
+            assert!(None::<String>.is_empty());
+        }
+
+        #[test]
+        fn some_should_defer_to_inner_value() {
+            assert!(!Some(0u32).is_empty(), "present numerics are never empty");
+            assert!(Some(String::new()).is_empty(), "present but empty string is empty");
+            assert!(!Some(String::from("x")).is_empty());
+        }
     }
 
     mod validation_result {
@@ -0,0 +1,78 @@
+/*
+ * C interface to a RACER consensus node.
+ *
+ * Link against libracer_ffi (cdylib or staticlib, built with
+ * `cargo build -p racer-ffi --release`). Every function returns RACER_OK,
+ * RACER_EMPTY or a negative RACER_ERR_* code; racer_last_error() describes
+ * the last failure on the calling thread. Strings are NUL-terminated UTF-8
+ * and all buffers are owned by the caller.
+ */
+
+#ifndef RACER_H
+#define RACER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RACER_OK 0
+#define RACER_EMPTY 1
+#define RACER_ERR_INVALID_ARGUMENT (-1)
+#define RACER_ERR_BUFFER_TOO_SMALL (-2)
+#define RACER_ERR_CONFIG (-3)
+#define RACER_ERR_KEY (-4)
+#define RACER_ERR_NODE (-5)
+#define RACER_ERR_JSON (-6)
+#define RACER_ERR_PANIC (-7)
+
+#define RACER_SECRET_KEY_LEN 32
+#define RACER_PUBLIC_KEY_LEN 33
+
+typedef struct RacerNode RacerNode;
+
+/* Writes a fresh secret key (RACER_SECRET_KEY_LEN bytes). */
+int32_t racer_key_generate(uint8_t *secret_out);
+
+/* Derives the compressed public key (RACER_PUBLIC_KEY_LEN bytes) of `secret`. */
+int32_t racer_key_public(const uint8_t *secret, uint8_t *public_out);
+
+/*
+ * Creates and starts a node. `config_toml` is a racer.toml document, or NULL
+ * for the minimal config. `secret` (RACER_SECRET_KEY_LEN bytes) sets the node
+ * identity; NULL uses node.key_file or a fresh key. Release with
+ * racer_node_free().
+ */
+int32_t racer_node_new(const char *config_toml, const uint8_t *secret, RacerNode **node_out);
+
+/* Writes the node's public key (RACER_PUBLIC_KEY_LEN bytes). */
+int32_t racer_node_public_key(const RacerNode *node, uint8_t *public_out);
+
+/*
+ * Submits a JSON document and blocks until its gossip round ends. When
+ * `batch_id_out` is not NULL it receives the batch id and must hold the node
+ * id plus 22 bytes.
+ */
+int32_t racer_node_submit_json(const RacerNode *node, const char *json, char *batch_id_out, size_t capacity);
+
+/*
+ * Takes the oldest delivered batch as JSON. Returns RACER_EMPTY when none is
+ * waiting. `*len_out` receives the JSON length without the terminator; after
+ * RACER_ERR_BUFFER_TOO_SMALL the batch stays queued for a retry with at least
+ * `*len_out + 1` bytes.
+ */
+int32_t racer_node_poll_delivered(const RacerNode *node, char *buf, size_t capacity, size_t *len_out);
+
+/* Stops and frees the node. NULL is ignored. */
+void racer_node_free(RacerNode *node);
+
+/* Copies the last error message, truncated to fit; returns its full length. */
+size_t racer_last_error(char *buf, size_t capacity);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RACER_H */
@@ -0,0 +1,421 @@
+//! C ABI for embedding a RACER node.
+//!
+//! Gateway firmware links `libracer_ffi` (shared or static) and includes
+//! `include/racer.h`; no Rust toolchain is needed in its own build. Each
+//! [`RacerNode`] owns a Tokio runtime that drives the node in the
+//! background, so the C side only makes plain blocking calls.
+//!
+//! Conventions, also spelled out in the header:
+//! - functions return `RACER_OK` (0), `RACER_EMPTY` (1) or a negative
+//!   `RACER_ERR_*` code; [`racer_last_error`] describes the last failure on
+//!   the calling thread;
+//! - strings crossing the boundary are NUL-terminated UTF-8; output buffers
+//!   are caller-owned;
+//! - messages are arbitrary JSON. Each submission is wrapped as
+//!   `{"id": <u64>, "payload": <json>}`, and a delivered batch is reported
+//!   as the JSON of the whole `BatchedMessages` carrying such messages.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use racer::config::RacerConfig;
+use racer::crypto::KeyPair;
+use racer::node::sink::ChannelSink;
+use racer::node::Node;
+use racer::protocol::BatchedMessages;
+use racer_core::message::now_millis;
+use racer_core::Message;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+
+pub const RACER_OK: i32 = 0;
+/// Nothing to report, e.g. no delivered batch waiting.
+pub const RACER_EMPTY: i32 = 1;
+pub const RACER_ERR_INVALID_ARGUMENT: i32 = -1;
+/// The output buffer cannot hold the result; nothing was consumed.
+pub const RACER_ERR_BUFFER_TOO_SMALL: i32 = -2;
+pub const RACER_ERR_CONFIG: i32 = -3;
+pub const RACER_ERR_KEY: i32 = -4;
+pub const RACER_ERR_NODE: i32 = -5;
+pub const RACER_ERR_JSON: i32 = -6;
+/// A Rust panic was caught at the boundary.
+pub const RACER_ERR_PANIC: i32 = -7;
+
+pub const RACER_SECRET_KEY_LEN: usize = 32;
+/// SEC1 compressed P-256 point.
+pub const RACER_PUBLIC_KEY_LEN: usize = 33;
+
+/// Delivered batches buffered for [`racer_node_poll_delivered`]; later ones
+/// are dropped until the C side catches up.
+const DELIVERED_CAPACITY: usize = 1024;
+
+/// A message submitted over the C ABI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FfiMessage {
+    /// Unique per submission, so identical payloads still form distinct batches.
+    pub id: u64,
+    pub payload: serde_json::Value,
+}
+
+impl Message for FfiMessage {
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl FfiMessage {
+    fn new(payload: serde_json::Value) -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let seq = NEXT.fetch_add(1, Ordering::Relaxed);
+        Self {
+            id: (now_millis() << 16) | (seq & 0xffff),
+            payload,
+        }
+    }
+}
+
+/// Opaque handle returned by [`racer_node_new`].
+pub struct RacerNode {
+    node: Node<FfiMessage>,
+    delivered: Mutex<Delivered>,
+    /// Dropped last, after everything that may hold tasks on it.
+    runtime: Runtime,
+}
+
+struct Delivered {
+    receiver: mpsc::Receiver<BatchedMessages<FfiMessage>>,
+    /// An encoded batch the caller's last buffer was too small for.
+    pending: Option<Vec<u8>>,
+}
+
+struct FfiError {
+    code: i32,
+    message: String,
+}
+
+impl FfiError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Runs `f`, recording any failure for [`racer_last_error`] and keeping
+/// panics from unwinding into C.
+fn guard(f: impl FnOnce() -> Result<i32, FfiError>) -> i32 {
+    let result = catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(FfiError::new(RACER_ERR_PANIC, "panic in racer")));
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = e.message);
+            e.code
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::new(RACER_ERR_INVALID_ARGUMENT, format!("{} is null", name)));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| FfiError::new(RACER_ERR_INVALID_ARGUMENT, format!("{} is not UTF-8", name)))
+}
+
+unsafe fn node_arg<'a>(node: *const RacerNode) -> Result<&'a RacerNode, FfiError> {
+    node.as_ref()
+        .ok_or_else(|| FfiError::new(RACER_ERR_INVALID_ARGUMENT, "node is null"))
+}
+
+/// Copies `bytes` plus a NUL terminator into `out`, which holds `capacity` bytes.
+unsafe fn write_str(bytes: &[u8], out: *mut c_char, capacity: usize) -> Result<(), FfiError> {
+    if out.is_null() || capacity < bytes.len() + 1 {
+        return Err(FfiError::new(
+            RACER_ERR_BUFFER_TOO_SMALL,
+            format!("need {} bytes, have {}", bytes.len() + 1, capacity),
+        ));
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out as *mut u8, bytes.len());
+    *out.add(bytes.len()) = 0;
+    Ok(())
+}
+
+/// Writes a fresh secret key to `secret_out` (`RACER_SECRET_KEY_LEN` bytes).
+///
+/// # Safety
+/// `secret_out` must be null or valid for `RACER_SECRET_KEY_LEN` writes.
+#[no_mangle]
+pub unsafe extern "C" fn racer_key_generate(secret_out: *mut u8) -> i32 {
+    guard(|| {
+        if secret_out.is_null() {
+            return Err(FfiError::new(RACER_ERR_INVALID_ARGUMENT, "secret_out is null"));
+        }
+        let secret = KeyPair::generate().to_bytes();
+        ptr::copy_nonoverlapping(secret.as_ptr(), secret_out, RACER_SECRET_KEY_LEN);
+        Ok(RACER_OK)
+    })
+}
+
+/// Derives the public key for `secret` into `public_out`
+/// (`RACER_PUBLIC_KEY_LEN` bytes).
+///
+/// # Safety
+/// `secret` must be null or valid for `RACER_SECRET_KEY_LEN` reads, and
+/// `public_out` null or valid for `RACER_PUBLIC_KEY_LEN` writes.
+#[no_mangle]
+pub unsafe extern "C" fn racer_key_public(secret: *const u8, public_out: *mut u8) -> i32 {
+    guard(|| {
+        if secret.is_null() || public_out.is_null() {
+            return Err(FfiError::new(RACER_ERR_INVALID_ARGUMENT, "secret or public_out is null"));
+        }
+        let keys = KeyPair::from_bytes(std::slice::from_raw_parts(secret, RACER_SECRET_KEY_LEN))
+            .map_err(|e| FfiError::new(RACER_ERR_KEY, e.to_string()))?;
+        let public = keys.public_key();
+        ptr::copy_nonoverlapping(public.as_bytes().as_ptr(), public_out, RACER_PUBLIC_KEY_LEN);
+        Ok(RACER_OK)
+    })
+}
+
+/// Creates and starts a node from a TOML config (null for
+/// `RacerConfig::minimal()`). With a non-null `secret` the node uses that
+/// identity; otherwise `node.key_file` or a fresh key. On success
+/// `*node_out` owns the node until [`racer_node_free`].
+///
+/// # Safety
+/// `config_toml` must be null or a NUL-terminated string, `secret` null or
+/// valid for `RACER_SECRET_KEY_LEN` reads, and `node_out` valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn racer_node_new(
+    config_toml: *const c_char,
+    secret: *const u8,
+    node_out: *mut *mut RacerNode,
+) -> i32 {
+    guard(|| {
+        if node_out.is_null() {
+            return Err(FfiError::new(RACER_ERR_INVALID_ARGUMENT, "node_out is null"));
+        }
+        let config = if config_toml.is_null() {
+            RacerConfig::minimal()
+        } else {
+            RacerConfig::from_toml(str_arg(config_toml, "config_toml")?)
+                .map_err(|e| FfiError::new(RACER_ERR_CONFIG, e.to_string()))?
+        };
+        let keys = if secret.is_null() {
+            None
+        } else {
+            Some(
+                KeyPair::from_bytes(std::slice::from_raw_parts(secret, RACER_SECRET_KEY_LEN))
+                    .map_err(|e| FfiError::new(RACER_ERR_KEY, e.to_string()))?,
+            )
+        };
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| FfiError::new(RACER_ERR_NODE, e.to_string()))?;
+        let node = runtime
+            .block_on(async {
+                let node = match keys {
+                    Some(keys) => Node::with_keys(config, keys).await?,
+                    None => Node::new(config).await?,
+                };
+                node.start().await?;
+                Ok::<_, racer::node::NodeError>(node)
+            })
+            .map_err(|e| FfiError::new(RACER_ERR_NODE, e.to_string()))?;
+
+        let (sink, receiver) = ChannelSink::new(DELIVERED_CAPACITY);
+        node.add_sink(sink);
+
+        *node_out = Box::into_raw(Box::new(RacerNode {
+            node,
+            delivered: Mutex::new(Delivered {
+                receiver,
+                pending: None,
+            }),
+            runtime,
+        }));
+        Ok(RACER_OK)
+    })
+}
+
+/// Writes the node's public key to `public_out` (`RACER_PUBLIC_KEY_LEN` bytes).
+///
+/// # Safety
+/// `node` must come from [`racer_node_new`], and `public_out` be null or
+/// valid for `RACER_PUBLIC_KEY_LEN` writes.
+#[no_mangle]
+pub unsafe extern "C" fn racer_node_public_key(node: *const RacerNode, public_out: *mut u8) -> i32 {
+    guard(|| {
+        let node = node_arg(node)?;
+        if public_out.is_null() {
+            return Err(FfiError::new(RACER_ERR_INVALID_ARGUMENT, "public_out is null"));
+        }
+        let public = node.node.public_key();
+        ptr::copy_nonoverlapping(public.as_bytes().as_ptr(), public_out, RACER_PUBLIC_KEY_LEN);
+        Ok(RACER_OK)
+    })
+}
+
+/// Submits the JSON document `json` and blocks until its gossip round ends.
+/// The batch id is written to `batch_id_out` when that is non-null; the
+/// buffer is checked before submitting, and `capacity` must allow for the
+/// node id plus 22 bytes.
+///
+/// # Safety
+/// `node` must come from [`racer_node_new`], `json` be a NUL-terminated
+/// string, and `batch_id_out` null or valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn racer_node_submit_json(
+    node: *const RacerNode,
+    json: *const c_char,
+    batch_id_out: *mut c_char,
+    capacity: usize,
+) -> i32 {
+    guard(|| {
+        let node = node_arg(node)?;
+        let payload: serde_json::Value = serde_json::from_str(str_arg(json, "json")?)
+            .map_err(|e| FfiError::new(RACER_ERR_JSON, e.to_string()))?;
+        if !batch_id_out.is_null() && capacity < node.node.id().len() + 22 {
+            return Err(FfiError::new(
+                RACER_ERR_BUFFER_TOO_SMALL,
+                format!("batch_id_out needs {} bytes", node.node.id().len() + 22),
+            ));
+        }
+
+        let batch_id = node
+            .runtime
+            .block_on(node.node.submit(FfiMessage::new(payload)))
+            .map_err(|e| FfiError::new(RACER_ERR_NODE, e.to_string()))?;
+        if !batch_id_out.is_null() {
+            write_str(batch_id.as_bytes(), batch_id_out, capacity)?;
+        }
+        Ok(RACER_OK)
+    })
+}
+
+/// Takes the oldest delivered batch as JSON into `buf`. Returns
+/// `RACER_EMPTY` when none is waiting. `*len_out` receives the JSON length
+/// without its NUL terminator; on `RACER_ERR_BUFFER_TOO_SMALL` the batch
+/// stays queued and `*len_out` tells how large a retry must be, minus one.
+///
+/// # Safety
+/// `node` must come from [`racer_node_new`], `buf` be null or valid for
+/// `capacity` writes, and `len_out` null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn racer_node_poll_delivered(
+    node: *const RacerNode,
+    buf: *mut c_char,
+    capacity: usize,
+    len_out: *mut usize,
+) -> i32 {
+    guard(|| {
+        let node = node_arg(node)?;
+        let mut delivered = node.delivered.lock().unwrap_or_else(|e| e.into_inner());
+        let encoded = match delivered.pending.take() {
+            Some(encoded) => encoded,
+            None => match delivered.receiver.try_recv() {
+                Ok(batch) => {
+                    serde_json::to_vec(&batch).map_err(|e| FfiError::new(RACER_ERR_JSON, e.to_string()))?
+                }
+                Err(_) => return Ok(RACER_EMPTY),
+            },
+        };
+
+        if !len_out.is_null() {
+            *len_out = encoded.len();
+        }
+        if let Err(e) = write_str(&encoded, buf, capacity) {
+            delivered.pending = Some(encoded);
+            return Err(e);
+        }
+        Ok(RACER_OK)
+    })
+}
+
+/// Stops the node and releases it. Null is ignored.
+///
+/// # Safety
+/// `node` must be null or come from [`racer_node_new`], and not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn racer_node_free(node: *mut RacerNode) {
+    guard(|| {
+        if !node.is_null() {
+            let node = Box::from_raw(node);
+            node.runtime.block_on(node.node.stop());
+        }
+        Ok(RACER_OK)
+    });
+}
+
+/// Copies the calling thread's last error message into `buf`, truncated
+/// to fit, and returns its full length without the NUL terminator.
+///
+/// # Safety
+/// `buf` must be null or valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn racer_last_error(buf: *mut c_char, capacity: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        if !buf.is_null() && capacity > 0 {
+            let n = last.len().min(capacity - 1);
+            ptr::copy_nonoverlapping(last.as_ptr(), buf as *mut u8, n);
+            *buf.add(n) = 0;
+        }
+        last.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        let mut buf = [0 as c_char; 128];
+        unsafe {
+            racer_last_error(buf.as_mut_ptr(), buf.len());
+            CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned()
+        }
+    }
+
+    #[test]
+    fn test_key_roundtrip() {
+        let mut secret = [0u8; RACER_SECRET_KEY_LEN];
+        let mut public = [0u8; RACER_PUBLIC_KEY_LEN];
+        unsafe {
+            assert_eq!(racer_key_generate(secret.as_mut_ptr()), RACER_OK);
+            assert_eq!(racer_key_public(secret.as_ptr(), public.as_mut_ptr()), RACER_OK);
+        }
+        let keys = KeyPair::from_bytes(&secret).unwrap();
+        assert_eq!(keys.public_key().as_bytes(), public);
+    }
+
+    #[test]
+    fn test_errors_are_reported_not_raised() {
+        let mut node = ptr::null_mut();
+        let config = b"[node\0".as_ptr() as *const c_char;
+        unsafe {
+            assert_eq!(racer_node_new(config, ptr::null(), &mut node), RACER_ERR_CONFIG);
+            assert!(node.is_null());
+            assert_eq!(
+                racer_node_poll_delivered(ptr::null(), ptr::null_mut(), 0, ptr::null_mut()),
+                RACER_ERR_INVALID_ARGUMENT
+            );
+            racer_node_free(ptr::null_mut());
+        }
+        assert_eq!(last_error(), "node is null");
+    }
+}
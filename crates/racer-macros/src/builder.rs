@@ -0,0 +1,184 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::codegen::{auto_value, default_fn, field_type, value_type};
+use crate::parser::FieldDef;
+
+/// A field the caller has to set before `build()` is available: no default,
+/// not optional and not filled automatically.
+fn is_mandatory(field: &FieldDef) -> bool {
+    !field.optional && !field.auto && field.default.is_none()
+}
+
+/// Generates `<Name>Builder`. Each mandatory field is tracked by a type
+/// parameter that starts as `()` and becomes the field type once its setter
+/// runs; `build()` only exists when every parameter is set.
+pub fn generate(vis: &syn::Visibility, struct_name: &syn::Ident, fields: &[FieldDef]) -> TokenStream {
+    let builder_name = format_ident!("{}Builder", struct_name);
+    let doc = format!(
+        "Builder for [`{}`]. `build()` is available once every field that is not optional, auto or defaulted has been set.",
+        struct_name
+    );
+
+    let mandatory: Vec<_> = fields.iter().filter(|f| is_mandatory(f)).collect();
+    let params: Vec<_> = (0..mandatory.len())
+        .map(|idx| format_ident!("__RacerS{}", idx))
+        .collect();
+    let set_types: Vec<_> = mandatory.iter().map(|f| field_type(f)).collect();
+
+    let names: Vec<_> = fields.iter().map(|f| format_ident!("{}", f.name)).collect();
+    let storage = fields.iter().map(|field| {
+        let name = format_ident!("{}", field.name);
+        match mandatory.iter().position(|m| m.name == field.name) {
+            Some(idx) => {
+                let param = &params[idx];
+                quote! { #name: #param }
+            }
+            None => {
+                let ty = value_type(field);
+                quote! { #name: Option<#ty> }
+            }
+        }
+    });
+    let empty = fields.iter().map(|field| {
+        let name = format_ident!("{}", field.name);
+        if is_mandatory(field) {
+            quote! { #name: () }
+        } else {
+            quote! { #name: None }
+        }
+    });
+
+    let setters = fields.iter().map(|field| {
+        let name = format_ident!("{}", field.name);
+        let ty = value_type(field);
+        let Some(idx) = mandatory.iter().position(|m| m.name == field.name) else {
+            return quote! {
+                pub fn #name(mut self, #name: #ty) -> Self {
+                    self.#name = Some(#name);
+                    self
+                }
+            };
+        };
+
+        let mut next = params.iter().map(|p| quote! { #p }).collect::<Vec<_>>();
+        next[idx] = ty.clone();
+        let others = names.iter().filter(|n| **n != name);
+        quote! {
+            pub fn #name(self, #name: #ty) -> #builder_name<#(#next),*> {
+                #builder_name {
+                    #name,
+                    #(#others: self.#others,)*
+                }
+            }
+        }
+    });
+
+    let finals = fields.iter().map(|field| {
+        let name = format_ident!("{}", field.name);
+        let value = if is_mandatory(field) {
+            quote! { self.#name }
+        } else if field.default.is_some() {
+            let ctor = format_ident!("{}", default_fn(field));
+            if field.optional {
+                quote! { self.#name.or_else(#struct_name::#ctor) }
+            } else {
+                quote! { self.#name.unwrap_or_else(#struct_name::#ctor) }
+            }
+        } else if field.auto {
+            let value = auto_value(field);
+            quote! { self.#name.unwrap_or_else(|| #value) }
+        } else {
+            quote! { self.#name }
+        };
+        quote! { #name: #value, }
+    });
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug)]
+        #[must_use]
+        #vis struct #builder_name<#(#params = ()),*> {
+            #(#storage,)*
+        }
+
+        impl #struct_name {
+            /// Starts a builder with no fields set.
+            pub fn builder() -> #builder_name {
+                #builder_name {
+                    #(#empty,)*
+                }
+            }
+        }
+
+        impl<#(#params),*> #builder_name<#(#params),*> {
+            #(#setters)*
+        }
+
+        impl #builder_name<#(#set_types),*> {
+            /// Assembles the message and runs its validation.
+            pub fn build(self) -> Result<#struct_name, racer_core::ValidationError> {
+                let msg = #struct_name {
+                    #(#finals)*
+                };
+                racer_core::Message::validate(&msg)?;
+                Ok(msg)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(toml: &str) -> Vec<FieldDef> {
+        crate::parser::parse_toml(toml).unwrap().message.fields
+    }
+
+    #[test]
+    fn only_fields_without_fallback_should_be_mandatory() {
+        let fields = fields(
+            r#"
+            [message]
+            name = "T"
+            [[message.fields]]
+            name = "value"
+            type = "f64"
+            [[message.fields]]
+            name = "note"
+            type = "string"
+            optional = true
+            [[message.fields]]
+            name = "retries"
+            type = "u8"
+            default = 3
+            [[message.fields]]
+            name = "created_at"
+            type = "timestamp"
+            auto = true
+        "#,
+        );
+        let mandatory: Vec<_> = fields.iter().filter(|f| is_mandatory(f)).map(|f| f.name.as_str()).collect();
+        assert_eq!(mandatory, ["value"]);
+    }
+
+    #[test]
+    fn build_should_require_every_mandatory_type() {
+        let fields = fields(
+            r#"
+            [message]
+            name = "T"
+            [[message.fields]]
+            name = "timestamp"
+            type = "u64"
+            [[message.fields]]
+            name = "value"
+            type = "f64"
+        "#,
+        );
+        let tokens = generate(&syn::parse_quote!(pub), &format_ident!("T"), &fields).to_string();
+        assert!(tokens.contains("pub struct TBuilder < __RacerS0 = () , __RacerS1 = () >"));
+        assert!(tokens.contains("impl TBuilder < u64 , f64 >"));
+    }
+}
@@ -1,31 +1,23 @@
 use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, ToTokens};
 use syn::{ItemStruct, LitStr};
 
-use crate::parser::{self, FieldDef, MessageConfig};
+use crate::builder;
+use crate::parser::{self, FieldDef, MessageConfig, MessageDef};
+use crate::random;
+use crate::rules;
+use crate::schema;
 use crate::types;
 
 pub fn generate(path_lit: &LitStr, input: &ItemStruct) -> Result<TokenStream, syn::Error> {
-    let toml_path = path_lit.value();
-    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|_| {
-        syn::Error::new_spanned(path_lit, "CARGO_MANIFEST_DIR not set")
-    })?;
+    let schema_file = read_schema(path_lit)?;
 
-    let full_path = std::path::Path::new(&manifest_dir).join(&toml_path);
-
-    let toml_content = std::fs::read_to_string(&full_path).map_err(|e| {
-        syn::Error::new_spanned(
-            path_lit,
-            format!("failed to read '{}': {}", full_path.display(), e),
-        )
-    })?;
-
-    let config: MessageConfig = parser::parse_toml(&toml_content).map_err(|e| {
+    let config: MessageConfig = parser::parse_toml(&schema_file.content).map_err(|e| {
         syn::Error::new_spanned(path_lit, format!("invalid TOML: {}", e))
     })?;
 
     let struct_name = &input.ident;
-    if config.message.name != struct_name.to_string() {
+    if *struct_name != config.message.name {
         return Err(syn::Error::new_spanned(
             struct_name,
             format!(
@@ -35,10 +27,112 @@ pub fn generate(path_lit: &LitStr, input: &ItemStruct) -> Result<TokenStream, sy
         ));
     }
 
-    let fields = generate_fields(&config.message.fields);
+    let message = generate_message(path_lit, &input.attrs, &input.vis, struct_name, &config.message)?;
+    let tracking = schema_file.tracking;
+
+    Ok(quote! {
+        #message
+        #tracking
+    })
+}
+
+/// Overrides the directory relative schema paths are resolved against.
+const SCHEMA_DIR_VAR: &str = "RACER_SCHEMA_DIR";
+
+/// A schema file read at expansion time.
+pub struct SchemaFile {
+    pub content: String,
+    /// Items making the invoking crate rebuild when the file or
+    /// `RACER_SCHEMA_DIR` changes.
+    pub tracking: TokenStream,
+}
+
+/// Reads the schema file named by `path_lit`; see [`resolve_schema_path`].
+pub fn read_schema(path_lit: &LitStr) -> Result<SchemaFile, syn::Error> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+        syn::Error::new_spanned(path_lit, "CARGO_MANIFEST_DIR not set")
+    })?;
+    let schema_dir = std::env::var(SCHEMA_DIR_VAR).ok();
+
+    let full_path = resolve_schema_path(
+        std::path::Path::new(&manifest_dir),
+        schema_dir.as_deref(),
+        &path_lit.value(),
+    );
+
+    let content = std::fs::read_to_string(&full_path).map_err(|e| {
+        syn::Error::new_spanned(
+            path_lit,
+            format!("failed to read '{}': {}", full_path.display(), e),
+        )
+    })?;
+
+    let tracked_path = full_path.to_string_lossy();
+    let tracking = quote! {
+        const _: &str = include_str!(#tracked_path);
+        const _: Option<&str> = option_env!(#SCHEMA_DIR_VAR);
+    };
+
+    Ok(SchemaFile { content, tracking })
+}
+
+/// Relative paths resolve against `RACER_SCHEMA_DIR` when set, itself taken
+/// relative to the invoking crate's `Cargo.toml` unless absolute, and
+/// against that `Cargo.toml`'s directory otherwise.
+pub fn resolve_schema_path(
+    manifest_dir: &std::path::Path,
+    schema_dir: Option<&str>,
+    path: &str,
+) -> std::path::PathBuf {
+    match schema_dir.filter(|dir| !dir.is_empty()) {
+        Some(dir) => manifest_dir.join(dir).join(path),
+        None => manifest_dir.join(path),
+    }
+}
+
+/// Struct, `Message` impl and helpers for one message definition.
+pub fn generate_message(
+    path_lit: &LitStr,
+    attrs: &[syn::Attribute],
+    vis: &syn::Visibility,
+    struct_name: &syn::Ident,
+    message: &MessageDef,
+) -> Result<TokenStream, syn::Error> {
+    let fields = generate_fields(struct_name, &message.fields);
+    let (derive_default, default_impl) = generate_defaults(path_lit, struct_name, &message.fields)?;
+
+    let message_impl = message_impl(path_lit, struct_name, message)?;
+    let random_impl = random::generate(struct_name, &message.fields);
+    let constructor = generate_constructor(struct_name, &message.fields);
+    let builder_impl = builder::generate(vis, struct_name, &message.fields);
 
-    let id_field = config
-        .message
+    Ok(quote! {
+        #(#attrs)*
+        #[derive(Clone, Debug, #derive_default serde::Serialize, serde::Deserialize)]
+        #vis struct #struct_name {
+            #fields
+        }
+
+        #default_impl
+
+        #constructor
+
+        #builder_impl
+
+        #message_impl
+
+        #random_impl
+    })
+}
+
+/// `Message` impl for a message definition, shared by `racer_message`,
+/// `racer_messages!` and `#[derive(RacerMessage)]`. Errors point at `span`.
+pub fn message_impl<S: ToTokens>(
+    span: &S,
+    struct_name: &syn::Ident,
+    message: &MessageDef,
+) -> Result<TokenStream, syn::Error> {
+    let id_field = message
         .fields
         .iter()
         .find(|f| f.id_field)
@@ -47,11 +141,10 @@ pub fn generate(path_lit: &LitStr, input: &ItemStruct) -> Result<TokenStream, sy
     let id_impl = if let Some(id_field) = id_field {
         quote! { self.#id_field }
     } else {
-        let first_u64 = config
-            .message
+        let first_u64 = message
             .fields
             .iter()
-            .find(|f| f.field_type == "u64")
+            .find(|f| matches!(f.field_type.as_str(), "u64" | "timestamp") && !f.optional)
             .map(|f| format_ident!("{}", f.name));
 
         if let Some(field) = first_u64 {
@@ -61,18 +154,30 @@ pub fn generate(path_lit: &LitStr, input: &ItemStruct) -> Result<TokenStream, sy
         }
     };
 
-    let validation = generate_validation(&config.message.fields);
+    for field in &message.fields {
+        check_constraints(field).map_err(|e| {
+            syn::Error::new_spanned(span, format!("field '{}': {}", field.name, e))
+        })?;
+    }
+    if let Some(validator) = &message.validator {
+        validator_path(validator)
+            .map_err(|e| syn::Error::new_spanned(span, format!("message validator: {}", e)))?;
+    }
 
-    let vis = &input.vis;
-    let attrs = &input.attrs;
+    let validation = generate_validation(&message.fields);
+    let rule_checks = message
+        .rules
+        .iter()
+        .map(|rule| rules::generate(rule, &message.fields))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| syn::Error::new_spanned(span, e))?;
+    let message_validation = message.validator.as_deref().map(|validator| {
+        let path = validator_path(validator).expect("validator checked above");
+        quote! { #path(self)?; }
+    });
+    let schema_json = schema::document(message).to_string();
 
     Ok(quote! {
-        #(#attrs)*
-        #[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
-        #vis struct #struct_name {
-            #fields
-        }
-
         impl racer_core::Message for #struct_name {
             fn id(&self) -> u64 {
                 #id_impl
@@ -81,19 +186,47 @@ pub fn generate(path_lit: &LitStr, input: &ItemStruct) -> Result<TokenStream, sy
             fn validate(&self) -> racer_core::ValidationResult {
                 use racer_core::FieldValidator;
                 #validation
+                #(#rule_checks)*
+                #message_validation
                 Ok(())
             }
+
+            fn schema() -> racer_core::schema::Value {
+                racer_core::schema::from_embedded(#schema_json)
+            }
         }
     })
 }
 
-fn generate_fields(fields: &[FieldDef]) -> TokenStream {
+fn generate_fields(struct_name: &syn::Ident, fields: &[FieldDef]) -> TokenStream {
     let field_tokens: Vec<_> = fields
         .iter()
         .map(|field| {
             let name = format_ident!("{}", field.name);
-            let ty = types::parse_type(&field.field_type);
+            let ty = field_type(field);
+            let mut serde_args = Vec::new();
+            match (&field.default, field.optional) {
+                (Some(_), _) => {
+                    let path = format!("{}::{}", struct_name, default_fn(field));
+                    serde_args.push(quote! { default = #path });
+                }
+                (None, true) => serde_args.push(quote! { default }),
+                (None, false) => {}
+            }
+            if field.optional {
+                serde_args.push(quote! { skip_serializing_if = "Option::is_none" });
+            }
+            if field.length.is_some() {
+                let with = if field.optional {
+                    "racer_core::serde_array::option"
+                } else {
+                    "racer_core::serde_array"
+                };
+                serde_args.push(quote! { with = #with });
+            }
+            let serde_attr = (!serde_args.is_empty()).then(|| quote! { #[serde(#(#serde_args),*)] });
             quote! {
+                #serde_attr
                 pub #name: #ty,
             }
         })
@@ -102,11 +235,195 @@ fn generate_fields(fields: &[FieldDef]) -> TokenStream {
     quote! { #(#field_tokens)* }
 }
 
+/// Without TOML defaults or fixed-length fields `Default` is derived.
+/// Otherwise each defaulted field gets a hidden constructor (also used by
+/// serde for missing fields) and `Default` is implemented by hand, since std
+/// only implements it for arrays of up to 32 elements.
+fn generate_defaults(
+    path_lit: &LitStr,
+    struct_name: &syn::Ident,
+    fields: &[FieldDef],
+) -> Result<(TokenStream, TokenStream), syn::Error> {
+    if fields.iter().all(|f| f.default.is_none() && f.length.is_none()) {
+        return Ok((quote! { Default, }, quote! {}));
+    }
+
+    let mut constructors = Vec::new();
+    let mut initializers = Vec::new();
+    for field in fields {
+        let name = format_ident!("{}", field.name);
+        let Some(value) = &field.default else {
+            let value = if field.optional {
+                quote! { None }
+            } else if field.length.is_some() {
+                quote! { std::array::from_fn(|_| Default::default()) }
+            } else {
+                types::default_value(&field.field_type)
+            };
+            initializers.push(quote! { #name: #value, });
+            continue;
+        };
+
+        let literal = match field.length {
+            Some(len) => types::fixed_default_literal(&field.field_type, len, value),
+            None => types::default_literal(&field.field_type, value),
+        };
+        let mut literal = literal.map_err(|e| {
+            syn::Error::new_spanned(path_lit, format!("field '{}': {}", field.name, e))
+        })?;
+        if field.optional {
+            literal = quote! { Some(#literal) };
+        }
+        let ty = field_type(field);
+        let ctor = format_ident!("{}", default_fn(field));
+        constructors.push(quote! {
+            #[doc(hidden)]
+            fn #ctor() -> #ty {
+                #literal
+            }
+        });
+        initializers.push(quote! { #name: Self::#ctor(), });
+    }
+
+    Ok((
+        quote! {},
+        quote! {
+            impl #struct_name {
+                #(#constructors)*
+            }
+
+            impl Default for #struct_name {
+                fn default() -> Self {
+                    Self {
+                        #(#initializers)*
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// `new()` taking every field except `auto` ones, which it fills itself.
+/// Only generated when the schema has an `auto` field.
+fn generate_constructor(struct_name: &syn::Ident, fields: &[FieldDef]) -> TokenStream {
+    if !fields.iter().any(|f| f.auto) {
+        return quote! {};
+    }
+
+    let mut params = Vec::new();
+    let mut initializers = Vec::new();
+    for field in fields {
+        let name = format_ident!("{}", field.name);
+        if field.auto {
+            let value = auto_value(field);
+            initializers.push(quote! { #name: #value, });
+        } else {
+            let ty = field_type(field);
+            params.push(quote! { #name: #ty });
+            initializers.push(quote! { #name, });
+        }
+    }
+
+    quote! {
+        impl #struct_name {
+            #[allow(clippy::too_many_arguments)]
+            pub fn new(#(#params),*) -> Self {
+                Self {
+                    #(#initializers)*
+                }
+            }
+        }
+    }
+}
+
+/// Expression filling an `auto` field.
+pub fn auto_value(field: &FieldDef) -> TokenStream {
+    match field.field_type.trim() {
+        "timestamp" => quote! { racer_core::message::now_millis() },
+        _ => quote! { racer_core::Uuid::new_v4() },
+    }
+}
+
+/// Type of a present value: `[T; N]` for fixed-length fields.
+pub fn value_type(field: &FieldDef) -> TokenStream {
+    match field.length {
+        Some(len) => types::fixed_array_type(&field.field_type, len),
+        None => types::parse_type(&field.field_type),
+    }
+}
+
+pub fn field_type(field: &FieldDef) -> TokenStream {
+    let ty = value_type(field);
+    if field.optional {
+        quote! { Option<#ty> }
+    } else {
+        ty
+    }
+}
+
+pub fn default_fn(field: &FieldDef) -> String {
+    format!("__racer_default_{}", field.name)
+}
+
+/// Rejects constraints that cannot apply to the field's type.
+fn check_constraints(field: &FieldDef) -> Result<(), String> {
+    let type_str = field.field_type.trim();
+
+    if field.optional && field.id_field {
+        return Err("id_field cannot be optional".into());
+    }
+
+    if field.auto {
+        if !matches!(type_str, "timestamp" | "uuid") {
+            return Err(format!("auto is only supported on timestamp and uuid fields, not '{}'", type_str));
+        }
+        if field.optional || field.default.is_some() {
+            return Err("auto fields cannot be optional or have a default".into());
+        }
+    }
+
+    if field.length.is_some() {
+        if types::element_type(type_str).is_none() {
+            return Err(format!("length is only supported on bytes and array fields, not '{}'", type_str));
+        }
+        if field.min_length.is_some() || field.max_length.is_some() {
+            return Err("length cannot be combined with min_length or max_length".into());
+        }
+    }
+
+    if let Some(pattern) = &field.pattern {
+        if type_str != "string" {
+            return Err(format!("pattern is only supported on string fields, not '{}'", type_str));
+        }
+        regex::Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+    }
+
+    if let Some(allowed) = &field.one_of {
+        if !types::is_numeric_type(type_str) {
+            return Err(format!("one_of is only supported on numeric fields, not '{}'", type_str));
+        }
+        if allowed.is_empty() {
+            return Err("one_of must list at least one value".into());
+        }
+    }
+
+    if let Some(validator) = &field.validator {
+        validator_path(validator)?;
+    }
+
+    Ok(())
+}
+
+/// Parses a `validator = "..."` value into the function path it names.
+fn validator_path(validator: &str) -> Result<syn::Path, String> {
+    syn::parse_str(validator).map_err(|_| format!("invalid validator path '{}'", validator))
+}
+
 fn generate_validation(fields: &[FieldDef]) -> TokenStream {
     let validations: Vec<_> = fields
         .iter()
         .filter(|f| f.has_validation())
-        .map(|field| generate_field_validation(field))
+        .map(generate_field_validation)
         .collect();
 
     quote! { #(#validations)* }
@@ -116,24 +433,30 @@ fn generate_field_validation(field: &FieldDef) -> TokenStream {
     let name = format_ident!("{}", field.name);
     let name_str = &field.name;
     let mut checks = Vec::new();
+    // Inside the `if let Some(value)` below for optional fields.
+    let value = if field.optional {
+        quote! { (*value) }
+    } else {
+        quote! { self.#name }
+    };
 
-    // Required check
-    if field.required {
-        checks.push(quote! {
+    // Required: present (for optional fields) and non-empty
+    let required = field.required.then(|| {
+        quote! {
             if self.#name.is_empty() {
                 return Err(racer_core::ValidationError::required(#name_str));
             }
-        });
-    }
+        }
+    });
 
     if types::is_numeric_type(&field.field_type) {
         if let Some(min) = field.min {
             checks.push(quote! {
-                if (self.#name as f64) < #min {
+                if (#value as f64) < #min {
                     return Err(racer_core::ValidationError::min_value(
                         #name_str,
                         #min,
-                        self.#name as f64,
+                        #value as f64,
                     ));
                 }
             });
@@ -141,25 +464,54 @@ fn generate_field_validation(field: &FieldDef) -> TokenStream {
 
         if let Some(max) = field.max {
             checks.push(quote! {
-                if (self.#name as f64) > #max {
+                if (#value as f64) > #max {
                     return Err(racer_core::ValidationError::max_value(
                         #name_str,
                         #max,
-                        self.#name as f64,
+                        #value as f64,
                     ));
                 }
             });
         }
+
+        if let Some(allowed) = &field.one_of {
+            checks.push(quote! {
+                {
+                    const ALLOWED: &[f64] = &[#(#allowed),*];
+                    if !ALLOWED.contains(&(#value as f64)) {
+                        return Err(racer_core::ValidationError::one_of(
+                            #name_str,
+                            ALLOWED,
+                            #value as f64,
+                        ));
+                    }
+                }
+            });
+        }
+    }
+
+    if let Some(pattern) = &field.pattern {
+        checks.push(quote! {
+            {
+                static PATTERN: std::sync::OnceLock<racer_core::validation::Regex> = std::sync::OnceLock::new();
+                let regex = PATTERN.get_or_init(|| {
+                    racer_core::validation::Regex::new(#pattern).expect("pattern checked by racer_message")
+                });
+                if !regex.is_match(&#value) {
+                    return Err(racer_core::ValidationError::pattern(#name_str, #pattern));
+                }
+            }
+        });
     }
 
     if types::supports_length_validation(&field.field_type) {
         if let Some(min_len) = field.min_length {
             checks.push(quote! {
-                if self.#name.len() < #min_len {
+                if #value.len() < #min_len {
                     return Err(racer_core::ValidationError::min_length(
                         #name_str,
                         #min_len,
-                        self.#name.len(),
+                        #value.len(),
                     ));
                 }
             });
@@ -167,16 +519,82 @@ fn generate_field_validation(field: &FieldDef) -> TokenStream {
 
         if let Some(max_len) = field.max_length {
             checks.push(quote! {
-                if self.#name.len() > #max_len {
+                if #value.len() > #max_len {
                     return Err(racer_core::ValidationError::max_length(
                         #name_str,
                         #max_len,
-                        self.#name.len(),
+                        #value.len(),
                     ));
                 }
             });
         }
     }
 
-    quote! { #(#checks)* }
+    if let Some(validator) = &field.validator {
+        let path = validator_path(validator).expect("validator checked by check_constraints");
+        checks.push(quote! {
+            #path(&#value)?;
+        });
+    }
+
+    if field.optional && !checks.is_empty() {
+        return quote! {
+            #required
+            if let Some(value) = &self.#name {
+                #(#checks)*
+            }
+        };
+    }
+
+    quote! {
+        #required
+        #(#checks)*
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn field(toml: &str) -> FieldDef {
+        let mut config = parser::parse_toml(&format!(
+            "[message]\nname = \"M\"\n[[message.fields]]\nname = \"f\"\n{}",
+            toml
+        ))
+        .unwrap();
+        config.message.fields.remove(0)
+    }
+
+    #[test]
+    fn length_should_only_apply_to_bytes_and_arrays() {
+        assert!(check_constraints(&field("type = \"bytes\"\nlength = 32")).is_ok());
+        assert!(check_constraints(&field("type = \"array<f32>\"\nlength = 3")).is_ok());
+        assert!(check_constraints(&field("type = \"string\"\nlength = 3")).is_err());
+        assert!(check_constraints(&field("type = \"bytes\"\nlength = 3\nmax_length = 4")).is_err());
+    }
+
+    #[test]
+    fn schema_path_should_default_to_manifest_dir() {
+        let path = resolve_schema_path(Path::new("/ws/app"), None, "schemas/sensor.toml");
+        assert_eq!(path, PathBuf::from("/ws/app/schemas/sensor.toml"));
+    }
+
+    #[test]
+    fn schema_dir_should_resolve_relative_to_manifest_dir() {
+        let path = resolve_schema_path(Path::new("/ws/app"), Some("../schemas"), "sensor.toml");
+        assert_eq!(path, PathBuf::from("/ws/app/../schemas/sensor.toml"));
+    }
+
+    #[test]
+    fn absolute_schema_dir_should_be_used_as_is() {
+        let path = resolve_schema_path(Path::new("/ws/app"), Some("/ws/schemas"), "sensor.toml");
+        assert_eq!(path, PathBuf::from("/ws/schemas/sensor.toml"));
+    }
+
+    #[test]
+    fn empty_schema_dir_should_be_ignored() {
+        let path = resolve_schema_path(Path::new("/ws/app"), Some(""), "sensor.toml");
+        assert_eq!(path, PathBuf::from("/ws/app/sensor.toml"));
+    }
 }
@@ -0,0 +1,256 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{Data, DeriveInput, Expr, Fields, GenericArgument, Lit, PathArguments, Type, UnOp};
+
+use crate::codegen;
+use crate::parser::{FieldDef, MessageDef, RuleDef};
+
+/// `Message` impl for a hand-written struct, described by `#[racer(...)]`
+/// attributes instead of a TOML file.
+pub fn generate(input: &DeriveInput) -> Result<TokenStream, syn::Error> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "RacerMessage cannot be derived for generic structs",
+        ));
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "RacerMessage can only be derived for structs"));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "RacerMessage requires a struct with named fields",
+        ));
+    };
+
+    let mut message = MessageDef {
+        name: input.ident.to_string(),
+        validator: None,
+        fields: Vec::new(),
+        rules: Vec::new(),
+    };
+    parse_message_attrs(&input.attrs, &mut message)?;
+    for field in &named.named {
+        let ident = field.ident.as_ref().expect("named field");
+        message.fields.push(field_def(&ident.to_string(), &field.ty, &field.attrs)?);
+    }
+
+    codegen::message_impl(&input.ident, &input.ident, &message)
+}
+
+/// `#[racer(validator = "path")]` and `#[racer(rule = "a >= b", message = "...")]`.
+fn parse_message_attrs(attrs: &[syn::Attribute], message: &mut MessageDef) -> Result<(), syn::Error> {
+    for attr in attrs.iter().filter(|a| a.path().is_ident("racer")) {
+        let mut rule = None;
+        let mut rule_message = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("validator") {
+                message.validator = Some(string(&meta)?);
+            } else if meta.path.is_ident("rule") {
+                rule = Some(string(&meta)?);
+            } else if meta.path.is_ident("message") {
+                rule_message = Some(string(&meta)?);
+            } else {
+                return Err(meta.error("expected `validator`, `rule` or `message`"));
+            }
+            Ok(())
+        })?;
+
+        match (rule, rule_message) {
+            (Some(expr), message_text) => message.rules.push(RuleDef { expr, message: message_text }),
+            (None, Some(_)) => return Err(syn::Error::new_spanned(attr, "`message` requires a `rule`")),
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+fn field_def(name: &str, ty: &Type, attrs: &[syn::Attribute]) -> Result<FieldDef, syn::Error> {
+    let (ty, optional) = match generic_args(ty, "Option").as_deref() {
+        Some([inner]) => (inner.clone(), true),
+        _ => (ty.clone(), false),
+    };
+    let (field_type, length) = match &ty {
+        Type::Array(array) => {
+            let Expr::Lit(syn::ExprLit { lit: Lit::Int(len), .. }) = &array.len else {
+                return Err(syn::Error::new_spanned(&array.len, "array length must be an integer literal"));
+            };
+            let element = type_string(&array.elem);
+            let field_type = if element == "u8" { "bytes".into() } else { format!("array<{}>", element) };
+            (field_type, Some(len.base10_parse()?))
+        }
+        other => (type_string(other), None),
+    };
+
+    let mut field = FieldDef {
+        name: name.to_string(),
+        field_type,
+        id_field: false,
+        required: false,
+        optional,
+        auto: false,
+        min: None,
+        max: None,
+        min_length: None,
+        max_length: None,
+        length,
+        pattern: None,
+        one_of: None,
+        validator: None,
+        default: None,
+    };
+
+    for attr in attrs.iter().filter(|a| a.path().is_ident("racer")) {
+        attr.parse_nested_meta(|meta| {
+            let key = meta.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+            match key.as_str() {
+                "id" => field.id_field = true,
+                "required" => field.required = true,
+                "min" => field.min = Some(number(&meta.value()?.parse()?)?),
+                "max" => field.max = Some(number(&meta.value()?.parse()?)?),
+                "min_length" => field.min_length = Some(meta.value()?.parse::<syn::LitInt>()?.base10_parse()?),
+                "max_length" => field.max_length = Some(meta.value()?.parse::<syn::LitInt>()?.base10_parse()?),
+                "pattern" => field.pattern = Some(string(&meta)?),
+                "one_of" => {
+                    let Expr::Array(values) = meta.value()?.parse::<Expr>()? else {
+                        return Err(meta.error("one_of expects a list, e.g. `one_of = [1, 2, 4]`"));
+                    };
+                    field.one_of = Some(values.elems.iter().map(number).collect::<Result<_, _>>()?);
+                }
+                "validator" => field.validator = Some(string(&meta)?),
+                _ => return Err(meta.error("unsupported racer attribute")),
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(field)
+}
+
+/// TOML type string for a Rust type, so the shared codegen treats it the same
+/// way. Types without a TOML spelling keep their Rust spelling and are only
+/// checked by `required` and `validator`.
+fn type_string(ty: &Type) -> String {
+    const SCALARS: &[&str] = &["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "f32", "f64", "bool"];
+
+    let Type::Path(path) = ty else {
+        return quote!(#ty).to_string();
+    };
+    let Some(last) = path.path.segments.last() else {
+        return quote!(#ty).to_string();
+    };
+    let name = last.ident.to_string();
+
+    match (name.as_str(), generic_args(ty, &name).as_deref()) {
+        (scalar, None) if SCALARS.contains(&scalar) => name,
+        ("String", None) => "string".into(),
+        ("Uuid", None) => "uuid".into(),
+        ("Vec", Some([inner])) => match type_string(inner).as_str() {
+            "u8" => "bytes".into(),
+            inner => format!("array<{}>", inner),
+        },
+        ("HashMap", Some([key, value])) => format!("map<{}, {}>", type_string(key), type_string(value)),
+        _ => quote!(#ty).to_string(),
+    }
+}
+
+/// Type arguments of `ty` if its last path segment is `name<...>`.
+fn generic_args(ty: &Type, name: &str) -> Option<Vec<Type>> {
+    let Type::Path(path) = ty else { return None };
+    let last = path.path.segments.last()?;
+    if last.ident != name {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    Some(
+        args.args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty.clone()),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+fn string(meta: &ParseNestedMeta) -> Result<String, syn::Error> {
+    Ok(meta.value()?.parse::<syn::LitStr>()?.value())
+}
+
+/// A numeric literal, optionally negated.
+fn number(expr: &Expr) -> Result<f64, syn::Error> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Int(i), .. }) => i.base10_parse(),
+        Expr::Lit(syn::ExprLit { lit: Lit::Float(f), .. }) => f.base10_parse(),
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => number(&unary.expr).map(|n| -n),
+        other => Err(syn::Error::new_spanned(other, "expected a number")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(tokens: TokenStream) -> FieldDef {
+        let item: syn::ItemStruct = syn::parse2(quote! { struct M { #tokens } }).unwrap();
+        let field = item.fields.iter().next().unwrap();
+        field_def(&field.ident.as_ref().unwrap().to_string(), &field.ty, &field.attrs).unwrap()
+    }
+
+    #[test]
+    fn rust_types_should_map_to_toml_types() {
+        assert_eq!(type_string(&syn::parse_quote!(u32)), "u32");
+        assert_eq!(type_string(&syn::parse_quote!(String)), "string");
+        assert_eq!(type_string(&syn::parse_quote!(Vec<u8>)), "bytes");
+        assert_eq!(type_string(&syn::parse_quote!(Vec<String>)), "array<string>");
+        assert_eq!(type_string(&syn::parse_quote!(racer_core::Uuid)), "uuid");
+        assert_eq!(
+            type_string(&syn::parse_quote!(std::collections::HashMap<String, f64>)),
+            "map<string, f64>"
+        );
+        assert_eq!(type_string(&syn::parse_quote!(Reading)), "Reading");
+    }
+
+    #[test]
+    fn option_and_arrays_should_set_optional_and_length() {
+        let note = field(quote! { note: Option<String> });
+        assert!(note.optional);
+        assert_eq!(note.field_type, "string");
+
+        let digest = field(quote! { digest: [u8; 32] });
+        assert_eq!(digest.field_type, "bytes");
+        assert_eq!(digest.length, Some(32));
+    }
+
+    #[test]
+    fn attributes_should_set_constraints() {
+        let level = field(quote! {
+            #[racer(required, min = -5, max = 10.5, one_of = [-5, 0, 10])]
+            level: i32
+        });
+        assert!(level.required);
+        assert_eq!(level.min, Some(-5.0));
+        assert_eq!(level.max, Some(10.5));
+        assert_eq!(level.one_of, Some(vec![-5.0, 0.0, 10.0]));
+
+        let code = field(quote! {
+            #[racer(max_length = 8, pattern = "^[A-Z]+$", validator = "checks::code")]
+            code: String
+        });
+        assert_eq!(code.max_length, Some(8));
+        assert_eq!(code.pattern.as_deref(), Some("^[A-Z]+$"));
+        assert_eq!(code.validator.as_deref(), Some("checks::code"));
+    }
+
+    #[test]
+    fn unknown_attribute_should_fail() {
+        let item: syn::ItemStruct = syn::parse2(quote! { struct M { #[racer(nope)] x: u8 } }).unwrap();
+        let field = item.fields.iter().next().unwrap();
+        assert!(field_def("x", &field.ty, &field.attrs).is_err());
+    }
+}
@@ -15,12 +15,19 @@
 //! - Struct fields based on the configuration
 //! - `Message` trait implementation
 //! - Validation logic for field constraints
+//! - `GenerateRandom` implementation respecting those constraints
 
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, ItemStruct, LitStr};
+use syn::{parse_macro_input, DeriveInput, ItemStruct, LitStr};
 
+mod builder;
 mod codegen;
+mod derive;
+mod messages;
 mod parser;
+mod random;
+mod rules;
+mod schema;
 mod types;
 
 /// Generates a message struct from a TOML configuration file.
@@ -28,7 +35,13 @@ mod types;
 /// # Arguments
 ///
 /// The attribute takes a single string literal: the path to the TOML file
-/// relative to the crate's `Cargo.toml`.
+/// relative to the crate's `Cargo.toml`, or to `RACER_SCHEMA_DIR` when that
+/// environment variable is set (e.g. via `[env]` in `.cargo/config.toml`, so
+/// every crate in a workspace can share one schema directory). A relative
+/// `RACER_SCHEMA_DIR` is itself taken relative to the crate's `Cargo.toml`.
+///
+/// The generated code `include_str!`s the file, so editing the schema
+/// rebuilds the crate.
 ///
 /// # TOML Schema
 ///
@@ -52,13 +65,82 @@ mod types;
 /// # Supported Types
 ///
 /// - Primitives: `u8`-`u64`, `i8`-`i64`, `f32`, `f64`, `bool`, `string`, `bytes`
+/// - Identity: `timestamp` (epoch millis as `u64`), `uuid` (`racer_core::Uuid`)
 /// - Collections: `array<T>`, `map<K, V>`
+/// - Fixed length: `length = N` on `bytes` or `array<T>` generates `[u8; N]` /
+///   `[T; N]`, and input of any other length fails to deserialize
 ///
 /// # Validation Attributes
 ///
-/// - `required`: Field cannot be empty
+/// - `required`: Field cannot be empty (and, if `optional`, must be present)
 /// - `min` / `max`: Numeric range validation
 /// - `min_length` / `max_length`: Length bounds for strings/arrays
+/// - `pattern`: Regex a string must match, e.g. `"^[A-Z]{3}-\\d+$"`
+/// - `one_of`: Allowed values for a numeric field, e.g. `[1, 2, 4, 8]`
+/// - `validator`: Path of a `fn(&T) -> racer_core::ValidationResult` called
+///   with the field value, after the declarative checks
+///
+/// # Optional Fields
+///
+/// `optional = true` generates an `Option<T>` field that deserializes to
+/// `None` when missing and is skipped when serializing `None`. Constraints
+/// other than `required` apply only to a present value.
+///
+/// # Auto Fields
+///
+/// `auto = true` on a `timestamp` or `uuid` field generates
+/// `new(<other fields>)`, which fills it with the current epoch millis or a
+/// fresh v4 UUID so callers never populate identity fields by hand.
+///
+/// # Cross-Field Rules
+///
+/// Each `[[message.rules]]` entry compares two numeric fields, or a field and
+/// a literal, with `==`, `!=`, `<`, `<=`, `>` or `>=`:
+///
+/// ```toml
+/// [[message.rules]]
+/// expr = "end_time >= start_time"
+/// message = "window must not end before it starts"   # optional
+/// ```
+///
+/// Two integer fields compare exactly; anything involving a float field or a
+/// literal compares as `f64`. Rules run after the per-field checks and fail
+/// with `ValidationKind::Rule`.
+///
+/// # Custom Validators
+///
+/// `validator = "my_crate::check_reading"` under `[message]` names a
+/// `fn(&Self) -> racer_core::ValidationResult` that `validate()` calls once
+/// every field check has passed, for constraints spanning several fields.
+///
+/// # Field Defaults
+///
+/// `default = 42` / `default = "unknown"` gives a field a value used both by
+/// the generated `Default` impl and by serde when the field is missing, so new
+/// fields can be added without breaking older senders. Scalars, strings,
+/// `bytes` and `array<T>` of scalars are supported.
+///
+/// # Builders
+///
+/// `SensorReading::builder()` returns a `SensorReadingBuilder` with a setter
+/// per field. Fields that are not `optional`, `auto` or defaulted must be set
+/// before `build()` exists, so forgetting one is a compile error; `build()`
+/// then runs `validate()`.
+///
+/// # Schema Export
+///
+/// `Message::schema()` returns a JSON Schema (draft 2020-12) of the serialized
+/// struct: field types, numeric ranges, lengths, `pattern`, `one_of`,
+/// defaults and which fields must be present. Rules and custom validators are
+/// not expressible there. `racer_core::schema::to_proto` renders it as proto3.
+///
+/// # Random Generation
+///
+/// The generated struct implements `racer_core::GenerateRandom`: `random()`
+/// yields values satisfying every constraint above, `random_invalid()` yields
+/// values breaking exactly one of the per-field constraints. Rules and custom
+/// validators are opaque to the generator, so `random()` may produce values
+/// they reject.
 #[proc_macro_attribute]
 pub fn racer_message(attr: TokenStream, item: TokenStream) -> TokenStream {
     let path_lit = parse_macro_input!(attr as LitStr);
@@ -69,3 +151,79 @@ pub fn racer_message(attr: TokenStream, item: TokenStream) -> TokenStream {
         Err(e) => e.to_compile_error().into(),
     }
 }
+
+/// Generates every message declared in a multi-message TOML file, plus an enum
+/// wrapping them.
+///
+/// ```ignore
+/// racer_messages!("config/fleet.toml");
+///
+/// let node: Node<FleetMessage> = /* ... */;
+/// ```
+///
+/// ```toml
+/// name = "FleetMessage"   # optional; defaults to the file stem + "Message"
+///
+/// [[messages]]
+/// name = "GpsFix"
+///
+/// [[messages.fields]]
+/// name = "lat"
+/// type = "f64"
+///
+/// [[messages]]
+/// name = "EngineTelemetry"
+/// # ...
+/// ```
+///
+/// Each `[[messages]]` entry accepts everything a `[message]` table does for
+/// [`macro@racer_message`] and produces the same `pub` struct and impls. The
+/// enum has one variant per message, `From` conversions, and implements
+/// `Message` and `GenerateRandom` by delegating to the wrapped struct; its
+/// `schema()` is a `oneOf` over the tagged variants.
+#[proc_macro]
+pub fn racer_messages(input: TokenStream) -> TokenStream {
+    let path_lit = parse_macro_input!(input as LitStr);
+
+    match messages::generate(&path_lit) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implements `Message` for a hand-written struct from `#[racer(...)]`
+/// attributes, for types that do not warrant a TOML file.
+///
+/// ```ignore
+/// #[derive(Clone, Debug, Serialize, Deserialize, RacerMessage)]
+/// #[racer(rule = "high >= low")]
+/// pub struct SensorReading {
+///     #[racer(id)]
+///     pub timestamp: u64,
+///     #[racer(min = -40.0, max = 100.0)]
+///     pub low: f64,
+///     #[racer(min = -40.0, max = 100.0)]
+///     pub high: f64,
+///     #[racer(required, max_length = 16, pattern = "^[a-z-]+$")]
+///     pub sensor: String,
+/// }
+/// ```
+///
+/// Field attributes mirror the TOML keys of [`macro@racer_message`]: `id`,
+/// `required`, `min`, `max`, `min_length`, `max_length`, `pattern`, `one_of`
+/// and `validator`. On the struct, `validator = "path"` and
+/// `rule = "expr"` (with an optional `message = "..."`) add message-level
+/// checks. `Option<T>` fields are treated as `optional` and `[T; N]` fields
+/// as `length = N`. The `Message` impl, including `validate()` and
+/// `schema()`, is the one `racer_message` generates; the struct, its serde
+/// attributes and any `Default` or builder are left to the caller, and no
+/// `GenerateRandom` impl is derived.
+#[proc_macro_derive(RacerMessage, attributes(racer))]
+pub fn derive_racer_message(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match derive::generate(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
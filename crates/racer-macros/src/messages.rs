@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::LitStr;
+
+use crate::codegen;
+use crate::parser::{self, MessagesConfig};
+use crate::schema;
+
+pub fn generate(path_lit: &LitStr) -> Result<TokenStream, syn::Error> {
+    let schema_file = codegen::read_schema(path_lit)?;
+
+    let config: MessagesConfig = parser::parse_messages_toml(&schema_file.content).map_err(|e| {
+        syn::Error::new_spanned(path_lit, format!("invalid TOML: {}", e))
+    })?;
+
+    if config.messages.is_empty() {
+        return Err(syn::Error::new_spanned(path_lit, "no [[messages]] declared"));
+    }
+
+    let enum_name = config
+        .name
+        .clone()
+        .unwrap_or_else(|| default_enum_name(&path_lit.value()));
+    let enum_name = parse_ident(path_lit, &enum_name)?;
+
+    let vis: syn::Visibility = syn::parse_quote!(pub);
+    let mut structs = Vec::new();
+    let mut variants = Vec::new();
+    for message in &config.messages {
+        let name = parse_ident(path_lit, &message.name)?;
+        if variants.contains(&name) {
+            return Err(syn::Error::new_spanned(
+                path_lit,
+                format!("message '{}' is declared twice", message.name),
+            ));
+        }
+        structs.push(codegen::generate_message(path_lit, &[], &vis, &name, message)?);
+        variants.push(name);
+    }
+
+    let schema_json = schema::enum_document(&enum_name.to_string(), &config.messages).to_string();
+    let message_enum = generate_enum(&enum_name, &variants, &schema_json);
+
+    let tracking = schema_file.tracking;
+
+    Ok(quote! {
+        #(#structs)*
+        #message_enum
+        #tracking
+    })
+}
+
+/// `fleet.toml` → `FleetMessage`, `sensor_fleet.toml` → `SensorFleetMessage`.
+fn default_enum_name(path: &str) -> String {
+    let stem = std::path::Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+
+    let mut name: String = stem
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    name.push_str("Message");
+    name
+}
+
+fn parse_ident(path_lit: &LitStr, name: &str) -> Result<syn::Ident, syn::Error> {
+    syn::parse_str(name).map_err(|_| {
+        syn::Error::new_spanned(path_lit, format!("'{}' is not a valid type name", name))
+    })
+}
+
+/// One variant per message, with `Message` and `GenerateRandom` delegating to
+/// the wrapped struct.
+fn generate_enum(enum_name: &syn::Ident, variants: &[syn::Ident], schema_json: &str) -> TokenStream {
+    let doc = format!(
+        "Any of the messages declared alongside it, so one `Node<{}>` can carry them all.",
+        enum_name
+    );
+    let count = variants.len();
+    let indices: Vec<_> = (0..count).collect();
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+        pub enum #enum_name {
+            #(#variants(#variants),)*
+        }
+
+        #(
+            impl From<#variants> for #enum_name {
+                fn from(msg: #variants) -> Self {
+                    Self::#variants(msg)
+                }
+            }
+        )*
+
+        impl racer_core::Message for #enum_name {
+            fn id(&self) -> u64 {
+                match self {
+                    #(Self::#variants(msg) => racer_core::Message::id(msg),)*
+                }
+            }
+
+            fn validate(&self) -> racer_core::ValidationResult {
+                match self {
+                    #(Self::#variants(msg) => racer_core::Message::validate(msg),)*
+                }
+            }
+
+            fn schema() -> racer_core::schema::Value {
+                racer_core::schema::from_embedded(#schema_json)
+            }
+        }
+
+        impl racer_core::GenerateRandom for #enum_name {
+            fn random<R: racer_core::random::Rng + ?Sized>(rng: &mut R) -> Self {
+                use racer_core::random::Rng;
+                match rng.gen_range(0..#count) {
+                    #(#indices => Self::#variants(racer_core::GenerateRandom::random(rng)),)*
+                    _ => unreachable!(),
+                }
+            }
+
+            fn random_invalid<R: racer_core::random::Rng + ?Sized>(rng: &mut R) -> Option<Self> {
+                use racer_core::random::Rng;
+                // Start at a random variant and fall through those without
+                // breakable constraints.
+                let start = rng.gen_range(0..#count);
+                for offset in 0..#count {
+                    let invalid = match (start + offset) % #count {
+                        #(#indices => racer_core::GenerateRandom::random_invalid(rng).map(Self::#variants),)*
+                        _ => unreachable!(),
+                    };
+                    if invalid.is_some() {
+                        return invalid;
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::format_ident;
+
+    #[test]
+    fn default_enum_name_should_pascal_case_the_file_stem() {
+        assert_eq!(default_enum_name("fleet.toml"), "FleetMessage");
+        assert_eq!(default_enum_name("config/sensor_fleet.toml"), "SensorFleetMessage");
+        assert_eq!(default_enum_name("my-devices.toml"), "MyDevicesMessage");
+    }
+
+    #[test]
+    fn enum_should_have_one_variant_per_message() {
+        let variants = vec![format_ident!("GpsFix"), format_ident!("EngineTelemetry")];
+        let tokens = generate_enum(&format_ident!("FleetMessage"), &variants, "{}").to_string();
+        assert!(tokens.contains("pub enum FleetMessage { GpsFix (GpsFix) , EngineTelemetry (EngineTelemetry) , }"));
+        assert!(tokens.contains("impl From < GpsFix > for FleetMessage"));
+    }
+}
@@ -5,10 +5,30 @@ pub struct MessageConfig {
     pub message: MessageDef,
 }
 
+/// A file declaring several `[[messages]]` for `racer_messages!`.
+#[derive(Debug, Deserialize)]
+pub struct MessagesConfig {
+    /// Name of the generated enum; defaults to the file stem plus `Message`.
+    pub name: Option<String>,
+    pub messages: Vec<MessageDef>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MessageDef {
     pub name: String,
+    /// Path of a `fn(&Self) -> ValidationResult` run after every field check.
+    pub validator: Option<String>,
     pub fields: Vec<FieldDef>,
+    #[serde(default)]
+    pub rules: Vec<RuleDef>,
+}
+
+/// A cross-field check such as `expr = "end_time >= start_time"`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleDef {
+    pub expr: String,
+    /// Replaces the generated error message when the rule fails.
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -20,10 +40,27 @@ pub struct FieldDef {
     pub id_field: bool,
     #[serde(default)]
     pub required: bool,
+    /// Generates `Option<T>`, omitted on the wire when `None`.
+    #[serde(default)]
+    pub optional: bool,
+    /// Filled by the generated `new()` instead of taken as an argument
+    /// (`timestamp` and `uuid` fields only).
+    #[serde(default)]
+    pub auto: bool,
     pub min: Option<f64>,
     pub max: Option<f64>,
     pub min_length: Option<usize>,
     pub max_length: Option<usize>,
+    /// Exact element count of a `bytes` or `array<T>` field, generated as `[T; N]`.
+    pub length: Option<usize>,
+    /// Regex a `string` field must match (unanchored unless the pattern says so).
+    pub pattern: Option<String>,
+    /// Values a numeric field is restricted to.
+    pub one_of: Option<Vec<f64>>,
+    /// Path of a `fn(&FieldType) -> ValidationResult` run after the declarative checks.
+    pub validator: Option<String>,
+    /// Value used when the field is missing on the wire and by `Default`.
+    pub default: Option<toml::Value>,
 }
 
 impl FieldDef {
@@ -33,6 +70,9 @@ impl FieldDef {
             || self.max.is_some()
             || self.min_length.is_some()
             || self.max_length.is_some()
+            || self.pattern.is_some()
+            || self.one_of.is_some()
+            || self.validator.is_some()
     }
 }
 
@@ -40,6 +80,10 @@ pub fn parse_toml(content: &str) -> Result<MessageConfig, toml::de::Error> {
     toml::from_str(content)
 }
 
+pub fn parse_messages_toml(content: &str) -> Result<MessagesConfig, toml::de::Error> {
+    toml::from_str(content)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,6 +116,45 @@ mod tests {
         assert_eq!(config.message.name, "MyCustomMessage");
     }
 
+    #[test]
+    fn should_parse_multiple_messages() {
+        let toml = r#"
+            name = "FleetMessage"
+            [[messages]]
+            name = "GpsFix"
+            [[messages.fields]]
+            name = "lat"
+            type = "f64"
+            [[messages]]
+            name = "EngineTelemetry"
+            [[messages.fields]]
+            name = "rpm"
+            type = "u32"
+            [[messages.rules]]
+            expr = "rpm < 9000"
+        "#;
+
+        let config = parse_messages_toml(toml).unwrap();
+        assert_eq!(config.name.as_deref(), Some("FleetMessage"));
+        assert_eq!(config.messages.len(), 2);
+        assert_eq!(config.messages[0].name, "GpsFix");
+        assert_eq!(config.messages[1].fields[0].name, "rpm");
+        assert_eq!(config.messages[1].rules.len(), 1);
+    }
+
+    #[test]
+    fn should_fail_on_missing_messages_array() {
+        let toml = r#"
+            [message]
+            name = "Single"
+            [[message.fields]]
+            name = "id"
+            type = "u64"
+        "#;
+
+        assert!(parse_messages_toml(toml).is_err());
+    }
+
     #[test]
     fn should_fail_on_missing_message_section() {
         let toml = r#"
@@ -217,6 +300,54 @@ mod tests {
         assert!(!config.message.fields[0].required);
     }
 
+    #[test]
+    fn optional_should_default_to_false() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "value"
+            type = "string"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert!(!config.message.fields[0].optional);
+    }
+
+    #[test]
+    fn optional_should_be_settable_to_true() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "value"
+            type = "string"
+            optional = true
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert!(config.message.fields[0].optional);
+    }
+
+    #[test]
+    fn auto_should_default_to_false_and_be_settable() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "created_at"
+            type = "timestamp"
+            auto = true
+            [[message.fields]]
+            name = "value"
+            type = "f64"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert!(config.message.fields[0].auto);
+        assert!(!config.message.fields[1].auto);
+    }
+
     #[test]
     fn required_should_be_settable_to_true() {
         let toml = r#"
@@ -401,10 +532,17 @@ mod tests {
             field_type: "u64".into(),
             id_field: false,
             required,
+            optional: false,
+            auto: false,
             min,
             max,
             min_length,
             max_length,
+            length: None,
+            pattern: None,
+            one_of: None,
+            validator: None,
+            default: None,
         }
     }
 
@@ -444,6 +582,122 @@ mod tests {
         assert!(field.has_validation());
     }
 
+    #[test]
+    fn should_parse_pattern_and_one_of() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "code"
+            type = "string"
+            pattern = "^[A-Z]{3}-\\d+$"
+            [[message.fields]]
+            name = "channels"
+            type = "u8"
+            one_of = [1, 2, 4, 8]
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert_eq!(config.message.fields[0].pattern.as_deref(), Some(r"^[A-Z]{3}-\d+$"));
+        assert_eq!(config.message.fields[1].one_of, Some(vec![1.0, 2.0, 4.0, 8.0]));
+        assert!(config.message.fields[0].has_validation());
+        assert!(config.message.fields[1].has_validation());
+    }
+
+    #[test]
+    fn should_parse_field_and_message_validators() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            validator = "crate::checks::reading"
+            [[message.fields]]
+            name = "unit"
+            type = "string"
+            validator = "crate::checks::unit"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert_eq!(config.message.validator.as_deref(), Some("crate::checks::reading"));
+        assert_eq!(config.message.fields[0].validator.as_deref(), Some("crate::checks::unit"));
+        assert!(config.message.fields[0].has_validation());
+    }
+
+    #[test]
+    fn should_parse_rules() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "start_time"
+            type = "u64"
+            [[message.fields]]
+            name = "end_time"
+            type = "u64"
+            [[message.rules]]
+            expr = "end_time >= start_time"
+            [[message.rules]]
+            expr = "start_time > 0"
+            message = "start_time must be set"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert_eq!(config.message.rules.len(), 2);
+        assert_eq!(config.message.rules[0].expr, "end_time >= start_time");
+        assert_eq!(config.message.rules[0].message, None);
+        assert_eq!(config.message.rules[1].message.as_deref(), Some("start_time must be set"));
+    }
+
+    #[test]
+    fn rules_should_default_to_empty() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "id"
+            type = "u64"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert!(config.message.rules.is_empty());
+    }
+
+    #[test]
+    fn default_should_default_to_none() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "value"
+            type = "u64"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert!(config.message.fields[0].default.is_none());
+    }
+
+    #[test]
+    fn default_should_parse_integer_and_string() {
+        let toml = r#"
+            [message]
+            name = "Test"
+            [[message.fields]]
+            name = "count"
+            type = "u32"
+            default = 42
+            [[message.fields]]
+            name = "unit"
+            type = "string"
+            default = "unknown"
+        "#;
+
+        let config = parse_toml(toml).unwrap();
+        assert_eq!(config.message.fields[0].default, Some(toml::Value::Integer(42)));
+        assert_eq!(
+            config.message.fields[1].default,
+            Some(toml::Value::String("unknown".into()))
+        );
+    }
+
     #[test]
     fn should_parse_array_of_primitives() {
         let toml = r#"
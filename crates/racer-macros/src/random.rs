@@ -0,0 +1,350 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::parser::FieldDef;
+use crate::types::{self, int_literal};
+
+const DEFAULT_LENGTH_SPAN: usize = 16;
+const DEFAULT_FLOAT_SPAN: f64 = 1000.0;
+
+pub fn generate(struct_name: &syn::Ident, fields: &[FieldDef]) -> TokenStream {
+    let initializers: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let name = format_ident!("{}", field.name);
+            let value = field_value(field);
+            quote! { #name: #value, }
+        })
+        .collect();
+
+    let violations: Vec<_> = fields.iter().flat_map(field_violations).collect();
+
+    let invalid_impl = if violations.is_empty() {
+        quote! {}
+    } else {
+        let count = violations.len();
+        let arms = violations.iter().enumerate().map(|(idx, violation)| {
+            quote! { #idx => { #violation } }
+        });
+        quote! {
+            fn random_invalid<R: racer_core::random::Rng + ?Sized>(rng: &mut R) -> Option<Self> {
+                use racer_core::random::Rng;
+                let mut msg = <Self as racer_core::GenerateRandom>::random(rng);
+                match rng.gen_range(0..#count) {
+                    #(#arms)*
+                    _ => unreachable!(),
+                }
+                Some(msg)
+            }
+        }
+    };
+
+    quote! {
+        impl racer_core::GenerateRandom for #struct_name {
+            #[allow(unused_variables)]
+            fn random<R: racer_core::random::Rng + ?Sized>(rng: &mut R) -> Self {
+                #[allow(unused_imports)]
+                use racer_core::random::Rng;
+                Self {
+                    #(#initializers)*
+                }
+            }
+
+            #invalid_impl
+        }
+    }
+}
+
+fn field_value(field: &FieldDef) -> TokenStream {
+    let value = present_value(field);
+    match (field.optional, field.required) {
+        (false, _) => value,
+        (true, true) => quote! { Some(#value) },
+        (true, false) => quote! {
+            if rng.gen::<bool>() { Some(#value) } else { None }
+        },
+    }
+}
+
+fn present_value(field: &FieldDef) -> TokenStream {
+    let type_str = field.field_type.trim();
+
+    if types::is_numeric_type(type_str) {
+        if let Some(allowed) = &field.one_of {
+            let ty = types::parse_type(type_str);
+            return quote! {
+                {
+                    const ALLOWED: &[f64] = &[#(#allowed),*];
+                    ALLOWED[rng.gen_range(0..ALLOWED.len())] as #ty
+                }
+            };
+        }
+        return numeric_value(type_str, field.min, field.max);
+    }
+
+    if let Some(pattern) = &field.pattern {
+        return quote! { racer_core::random::string_matching(rng, #pattern) };
+    }
+
+    if let (Some(_), Some(element)) = (field.length, types::element_type(type_str)) {
+        let element = value_expr(element);
+        return quote! { std::array::from_fn(|_| #element) };
+    }
+
+    if types::supports_length_validation(type_str) || type_str.starts_with("map<") {
+        let min_len = field
+            .min_length
+            .unwrap_or(0)
+            .max(if field.required { 1 } else { 0 });
+        let max_len = field
+            .max_length
+            .unwrap_or(min_len + DEFAULT_LENGTH_SPAN)
+            .max(min_len);
+        let len = quote! { rng.gen_range(#min_len..=#max_len) };
+        return sized_value(type_str, len);
+    }
+
+    value_expr(type_str)
+}
+
+/// Unconstrained value for nested element types.
+fn value_expr(type_str: &str) -> TokenStream {
+    let type_str = type_str.trim();
+
+    if types::is_numeric_type(type_str) {
+        return numeric_value(type_str, None, None);
+    }
+
+    match type_str {
+        "bool" => quote! { rng.gen::<bool>() },
+        "uuid" => quote! { racer_core::random::uuid_v4(rng) },
+        "string" | "bytes" => sized_value(type_str, quote! { rng.gen_range(0..=#DEFAULT_LENGTH_SPAN) }),
+        s if s.starts_with("array<") || s.starts_with("map<") => {
+            sized_value(s, quote! { rng.gen_range(0..=#DEFAULT_LENGTH_SPAN) })
+        }
+        other => {
+            let ident = syn::Ident::new(other, proc_macro2::Span::call_site());
+            quote! { <#ident as racer_core::GenerateRandom>::random(rng) }
+        }
+    }
+}
+
+fn sized_value(type_str: &str, len: TokenStream) -> TokenStream {
+    match type_str {
+        "string" => quote! {
+            {
+                let len = #len;
+                racer_core::random::alphanumeric_string(rng, len)
+            }
+        },
+        "bytes" => quote! {
+            {
+                let len = #len;
+                racer_core::random::random_bytes(rng, len)
+            }
+        },
+        s if s.starts_with("array<") && s.ends_with('>') => {
+            let inner = value_expr(&s[6..s.len() - 1]);
+            quote! {
+                {
+                    let len = #len;
+                    (0..len).map(|_| #inner).collect::<Vec<_>>()
+                }
+            }
+        }
+        s if s.starts_with("map<") && s.ends_with('>') => {
+            let inner = &s[4..s.len() - 1];
+            let (key, value) = inner.split_once(',').unwrap_or(("string", "string"));
+            let key = value_expr(key);
+            let value = value_expr(value);
+            quote! {
+                {
+                    let len = #len;
+                    let mut map = std::collections::HashMap::with_capacity(len);
+                    for _ in 0..len {
+                        let key = #key;
+                        let value = #value;
+                        map.insert(key, value);
+                    }
+                    map
+                }
+            }
+        }
+        other => value_expr(other),
+    }
+}
+
+fn numeric_value(type_str: &str, min: Option<f64>, max: Option<f64>) -> TokenStream {
+    if let Some((type_min, type_max)) = types::integer_bounds(type_str) {
+        if min.is_none() && max.is_none() {
+            let ty = types::parse_type(type_str);
+            return quote! { rng.gen::<#ty>() };
+        }
+
+        let lo = min
+            .map(|m| (m.ceil() as i128).max(type_min))
+            .unwrap_or(type_min);
+        let hi = max
+            .map(|m| (m.floor() as i128).min(type_max))
+            .unwrap_or(type_max)
+            .max(lo);
+        let lo = int_literal(lo, type_str);
+        let hi = int_literal(hi, type_str);
+        return quote! { rng.gen_range(#lo..=#hi) };
+    }
+
+    let (lo, hi) = match (min, max) {
+        (Some(lo), Some(hi)) => (lo, hi.max(lo)),
+        (Some(lo), None) => (lo, lo + DEFAULT_FLOAT_SPAN),
+        (None, Some(hi)) => (hi - DEFAULT_FLOAT_SPAN, hi),
+        (None, None) => (-DEFAULT_FLOAT_SPAN, DEFAULT_FLOAT_SPAN),
+    };
+    let ty = types::parse_type(type_str);
+    quote! { (rng.gen_range(#lo..=#hi) as #ty) }
+}
+
+/// Assignments to `msg` that each break one declared constraint.
+fn field_violations(field: &FieldDef) -> Vec<TokenStream> {
+    let name = format_ident!("{}", field.name);
+    let mut violations: Vec<_> = invalid_values(field)
+        .into_iter()
+        .map(|value| {
+            if field.optional {
+                quote! { msg.#name = Some(#value); }
+            } else {
+                quote! { msg.#name = #value; }
+            }
+        })
+        .collect();
+
+    if field.optional && field.required {
+        violations.push(quote! { msg.#name = None; });
+    }
+
+    violations
+}
+
+/// Values that each break one declared constraint on the field.
+fn invalid_values(field: &FieldDef) -> Vec<TokenStream> {
+    let type_str = field.field_type.trim();
+    let mut violations = Vec::new();
+
+    // A fixed-length array is only ever empty when its length is zero.
+    if field.required && !types::is_numeric_type(type_str) && type_str != "bool" && field.length.is_none() {
+        violations.push(quote! { Default::default() });
+    }
+
+    if types::is_numeric_type(type_str) {
+        let ty = types::parse_type(type_str);
+        if let Some((type_min, type_max)) = types::integer_bounds(type_str) {
+            if let Some(min) = field.min {
+                let below = min.ceil() as i128 - 1;
+                if below >= type_min {
+                    let lit = int_literal(below, type_str);
+                    violations.push(quote! { #lit });
+                }
+            }
+            if let Some(max) = field.max {
+                let above = max.floor() as i128 + 1;
+                if above <= type_max {
+                    let lit = int_literal(above, type_str);
+                    violations.push(quote! { #lit });
+                }
+            }
+        } else {
+            if let Some(min) = field.min {
+                let below = min - 1.0;
+                violations.push(quote! { #below as #ty });
+            }
+            if let Some(max) = field.max {
+                let above = max + 1.0;
+                violations.push(quote! { #above as #ty });
+            }
+        }
+    }
+
+    if let Some(allowed) = &field.one_of {
+        if let Some(outside) = value_outside(type_str, allowed, field.min, field.max) {
+            let ty = types::parse_type(type_str);
+            violations.push(quote! { #outside as #ty });
+        }
+    }
+
+    if let Some(pattern) = &field.pattern {
+        if let Some(mismatch) = string_not_matching(field, pattern) {
+            violations.push(quote! { String::from(#mismatch) });
+        }
+    }
+
+    if types::supports_length_validation(type_str) {
+        if let Some(min_len) = field.min_length.filter(|&m| m > 0) {
+            let value = sized_value(type_str, quote! { #min_len - 1 });
+            violations.push(quote! { #value });
+        }
+        if let Some(max_len) = field.max_length {
+            let value = sized_value(type_str, quote! { #max_len + 1 });
+            violations.push(quote! { #value });
+        }
+    }
+
+    violations
+}
+
+/// A value within `min`/`max` (and the type's range) that is not in `allowed`.
+fn value_outside(type_str: &str, allowed: &[f64], min: Option<f64>, max: Option<f64>) -> Option<f64> {
+    let (type_min, type_max) = types::integer_bounds(type_str)
+        .map(|(lo, hi)| (lo as f64, hi as f64))
+        .unwrap_or((f64::MIN, f64::MAX));
+    let lo = min.map_or(type_min, |m| m.max(type_min));
+    let hi = max.map_or(type_max, |m| m.min(type_max));
+
+    let candidates: Vec<f64> = if types::integer_bounds(type_str).is_some() {
+        let start = lo.ceil();
+        (0..=allowed.len()).map(|i| start + i as f64).collect()
+    } else {
+        std::iter::once(lo.max(-DEFAULT_FLOAT_SPAN))
+            .chain(allowed.iter().map(|a| a + 0.5))
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .find(|c| (lo..=hi).contains(c) && !allowed.contains(c))
+}
+
+/// A string that fails `pattern` but no other constraint on the field.
+fn string_not_matching(field: &FieldDef, pattern: &str) -> Option<&'static str> {
+    const CANDIDATES: &[&str] = &["", "!", " ", "0", "a", "A", "_-_", "\u{7f}"];
+
+    let regex = regex::Regex::new(pattern).ok()?;
+    let min_len = field.min_length.unwrap_or(0).max(if field.required { 1 } else { 0 });
+    let max_len = field.max_length.unwrap_or(usize::MAX);
+
+    CANDIDATES
+        .iter()
+        .copied()
+        .find(|c| (min_len..=max_len).contains(&c.len()) && !regex.is_match(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_outside_should_skip_allowed_values_and_respect_bounds() {
+        assert_eq!(value_outside("u8", &[1.0, 2.0, 4.0], Some(1.0), None), Some(3.0));
+        assert_eq!(value_outside("u8", &[0.0, 1.0], Some(0.0), Some(1.0)), None);
+        assert_eq!(value_outside("f64", &[0.5], Some(0.5), Some(1.0)), Some(1.0));
+    }
+
+    #[test]
+    fn unconstrained_integer_should_use_full_range() {
+        assert_eq!(numeric_value("u32", None, None).to_string(), "rng . gen :: < u32 > ()");
+    }
+
+    #[test]
+    fn constrained_integer_should_round_bounds_inward() {
+        let tokens = numeric_value("i16", Some(-1.5), Some(10.5)).to_string();
+        assert_eq!(tokens, "rng . gen_range (- 1i16 ..= 10i16)");
+    }
+}
@@ -0,0 +1,256 @@
+//! Cross-field rules: `[[message.rules]]` entries of the form
+//! `<operand> <op> <operand>`, where an operand is a numeric field name or a
+//! numeric literal and `op` is one of `==`, `!=`, `<`, `<=`, `>`, `>=`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::parser::{FieldDef, RuleDef};
+use crate::types;
+
+/// Checked longest-first so `>=` is not read as `>` followed by `=`.
+const OPERATORS: &[&str] = &[">=", "<=", "==", "!=", ">", "<"];
+
+#[derive(Debug, PartialEq)]
+pub enum Operand {
+    Field(String),
+    Literal(f64),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Comparison {
+    pub lhs: Operand,
+    pub op: &'static str,
+    pub rhs: Operand,
+}
+
+impl Comparison {
+    /// The first field named by the rule, reported as the failing field.
+    fn first_field(&self) -> Option<&str> {
+        [&self.lhs, &self.rhs].into_iter().find_map(|operand| match operand {
+            Operand::Field(name) => Some(name.as_str()),
+            Operand::Literal(_) => None,
+        })
+    }
+}
+
+pub fn parse(expr: &str) -> Result<Comparison, String> {
+    let (idx, op) = OPERATORS
+        .iter()
+        .find_map(|op| expr.find(op).map(|idx| (idx, *op)))
+        .ok_or_else(|| format!("rule '{}' has no comparison operator", expr))?;
+
+    let lhs = parse_operand(&expr[..idx])
+        .ok_or_else(|| format!("rule '{}': invalid left operand", expr))?;
+    let rhs = parse_operand(&expr[idx + op.len()..])
+        .ok_or_else(|| format!("rule '{}': invalid right operand", expr))?;
+
+    let comparison = Comparison { lhs, op, rhs };
+    if comparison.first_field().is_none() {
+        return Err(format!("rule '{}' must reference at least one field", expr));
+    }
+    Ok(comparison)
+}
+
+fn parse_operand(text: &str) -> Option<Operand> {
+    let text = text.trim();
+    let first = text.chars().next()?;
+
+    if first.is_ascii_digit() || first == '-' || first == '.' {
+        return text.parse::<f64>().ok().filter(|v| v.is_finite()).map(Operand::Literal);
+    }
+
+    let is_ident = (first.is_alphabetic() || first == '_')
+        && text.chars().all(|c| c.is_alphanumeric() || c == '_');
+    is_ident.then(|| Operand::Field(text.to_string()))
+}
+
+/// Generates the check for one rule, rejecting unknown or non-numeric fields.
+pub fn generate(rule: &RuleDef, fields: &[FieldDef]) -> Result<TokenStream, String> {
+    let comparison = parse(&rule.expr)?;
+    let lhs_type = operand_type(&comparison.lhs, &rule.expr, fields)?;
+    let rhs_type = operand_type(&comparison.rhs, &rule.expr, fields)?;
+    // Integer fields are compared exactly: through f64, u64 and i64 values
+    // past 2^53 round together.
+    let cast = match (lhs_type.map(integer_type), rhs_type.map(integer_type)) {
+        (Some(Some(lhs)), Some(Some(rhs))) if lhs == rhs => None,
+        (Some(Some(_)), Some(Some(_))) => Some(quote! { i128 }),
+        _ => Some(quote! { f64 }),
+    };
+    let lhs = operand_tokens(&comparison.lhs, cast.as_ref());
+    let rhs = operand_tokens(&comparison.rhs, cast.as_ref());
+    let op: TokenStream = comparison.op.parse().expect("operator is a valid token");
+
+    let field = comparison.first_field().expect("checked by parse");
+    let expr = &rule.expr;
+    let error = match &rule.message {
+        Some(message) => quote! {
+            racer_core::ValidationError::new(
+                #field,
+                #message,
+                racer_core::validation::ValidationKind::Rule { expr: #expr.into() },
+            )
+        },
+        None => quote! { racer_core::ValidationError::rule(#field, #expr) },
+    };
+
+    Ok(quote! {
+        {
+            let holds = #lhs #op #rhs;
+            if !holds {
+                return Err(#error);
+            }
+        }
+    })
+}
+
+/// The type of a field operand, `None` for a literal.
+fn operand_type<'a>(operand: &Operand, expr: &str, fields: &'a [FieldDef]) -> Result<Option<&'a str>, String> {
+    match operand {
+        Operand::Literal(_) => Ok(None),
+        Operand::Field(name) => {
+            let field = fields
+                .iter()
+                .find(|f| f.name == *name)
+                .ok_or_else(|| format!("rule '{}' references unknown field '{}'", expr, name))?;
+            if field.optional {
+                return Err(format!("rule '{}': field '{}' is optional", expr, name));
+            }
+            if !types::is_numeric_type(field.field_type.trim()) {
+                return Err(format!(
+                    "rule '{}': field '{}' is '{}', only numeric fields can be compared",
+                    expr, name, field.field_type
+                ));
+            }
+            Ok(Some(field.field_type.trim()))
+        }
+    }
+}
+
+/// The Rust integer type behind `field_type`, if it is one.
+fn integer_type(field_type: &str) -> Option<&str> {
+    types::integer_bounds(field_type)?;
+    Some(if field_type == "timestamp" { "u64" } else { field_type })
+}
+
+/// A field read as `cast`, or as itself without one; literals are always
+/// f64, so a rule with one compares as f64.
+fn operand_tokens(operand: &Operand, cast: Option<&TokenStream>) -> TokenStream {
+    match operand {
+        Operand::Literal(value) => quote! { #value },
+        Operand::Field(name) => {
+            let ident = format_ident!("{}", name);
+            match cast {
+                Some(cast) => quote! { (self.#ident as #cast) },
+                None => quote! { self.#ident },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, field_type: &str) -> FieldDef {
+        crate::parser::parse_toml(&format!(
+            "[message]\nname = \"T\"\n[[message.fields]]\nname = \"{}\"\ntype = \"{}\"",
+            name, field_type
+        ))
+        .unwrap()
+        .message
+        .fields
+        .remove(0)
+    }
+
+    fn rule(expr: &str) -> RuleDef {
+        RuleDef {
+            expr: expr.to_string(),
+            message: None,
+        }
+    }
+
+    #[test]
+    fn should_parse_field_to_field_comparison() {
+        assert_eq!(
+            parse("end_time >= start_time").unwrap(),
+            Comparison {
+                lhs: Operand::Field("end_time".into()),
+                op: ">=",
+                rhs: Operand::Field("start_time".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn should_parse_literals_on_either_side() {
+        let comparison = parse("-1.5<offset").unwrap();
+        assert_eq!(comparison.lhs, Operand::Literal(-1.5));
+        assert_eq!(comparison.op, "<");
+        assert_eq!(comparison.rhs, Operand::Field("offset".into()));
+    }
+
+    #[test]
+    fn should_prefer_two_character_operators() {
+        assert_eq!(parse("a <= b").unwrap().op, "<=");
+        assert_eq!(parse("a != b").unwrap().op, "!=");
+        assert_eq!(parse("a == b").unwrap().op, "==");
+    }
+
+    #[test]
+    fn should_reject_malformed_expressions() {
+        assert!(parse("end_time start_time").is_err());
+        assert!(parse("a => b").is_err());
+        assert!(parse("1 < 2").is_err());
+        assert!(parse("a + 1 > b").is_err());
+        assert!(parse("a > 1e999").is_err());
+        assert!(parse(" > b").is_err());
+    }
+
+    #[test]
+    fn generate_should_reject_unknown_fields() {
+        let fields = vec![field("start_time", "u64")];
+        let err = generate(&rule("end_time >= start_time"), &fields).unwrap_err();
+        assert!(err.contains("unknown field 'end_time'"));
+    }
+
+    #[test]
+    fn generate_should_reject_non_numeric_fields() {
+        let fields = vec![field("name", "string"), field("count", "u32")];
+        let err = generate(&rule("name > count"), &fields).unwrap_err();
+        assert!(err.contains("only numeric fields"));
+    }
+
+    #[test]
+    fn generate_should_reject_optional_fields() {
+        let mut end_time = field("end_time", "u64");
+        end_time.optional = true;
+        let fields = vec![field("start_time", "u64"), end_time];
+        let err = generate(&rule("end_time >= start_time"), &fields).unwrap_err();
+        assert!(err.contains("optional"));
+    }
+
+    #[test]
+    fn generate_should_compare_same_type_integers_natively() {
+        let fields = vec![field("start_time", "timestamp"), field("end_time", "u64")];
+        let tokens = generate(&rule("end_time >= start_time"), &fields).unwrap().to_string();
+        assert!(tokens.contains("self . end_time >= self . start_time"));
+        assert!(tokens.contains("ValidationError :: rule (\"end_time\""));
+    }
+
+    #[test]
+    fn generate_should_widen_mixed_integers_to_i128() {
+        let fields = vec![field("offset", "i32"), field("count", "u64")];
+        let tokens = generate(&rule("offset < count"), &fields).unwrap().to_string();
+        assert!(tokens.contains("(self . offset as i128) < (self . count as i128)"));
+    }
+
+    #[test]
+    fn generate_should_compare_floats_and_literals_as_f64() {
+        let fields = vec![field("ratio", "f32"), field("count", "u64")];
+        let tokens = generate(&rule("ratio <= count"), &fields).unwrap().to_string();
+        assert!(tokens.contains("(self . ratio as f64) <= (self . count as f64)"));
+        let tokens = generate(&rule("count > 1"), &fields).unwrap().to_string();
+        assert!(tokens.contains("(self . count as f64) > 1f64"));
+    }
+}
@@ -0,0 +1,291 @@
+use serde_json::{json, Map, Value};
+
+use crate::parser::{FieldDef, MessageDef};
+use crate::types;
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Top-level schema document for one message.
+pub fn document(message: &MessageDef) -> Value {
+    let mut schema = message_schema(message);
+    schema["$schema"] = json!(DRAFT);
+    schema
+}
+
+/// Schema for an externally tagged enum of `messages`, as serde encodes it.
+pub fn enum_document(name: &str, messages: &[MessageDef]) -> Value {
+    let variants: Vec<_> = messages
+        .iter()
+        .map(|message| {
+            json!({
+                "type": "object",
+                "properties": { message.name.clone(): message_schema(message) },
+                "required": [message.name],
+                "additionalProperties": false,
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": DRAFT,
+        "title": name,
+        "oneOf": variants,
+    })
+}
+
+/// Object schema for the fields of `message`. Rules and custom validators
+/// have no JSON Schema equivalent and are left out.
+fn message_schema(message: &MessageDef) -> Value {
+    let properties: Map<_, _> = message
+        .fields
+        .iter()
+        .map(|field| (field.name.clone(), field_schema(field)))
+        .collect();
+    let required: Vec<_> = message
+        .fields
+        .iter()
+        .filter(|f| !f.optional && f.default.is_none())
+        .map(|f| f.name.as_str())
+        .collect();
+
+    json!({
+        "title": message.name,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn field_schema(field: &FieldDef) -> Value {
+    let type_str = field.field_type.trim();
+    let mut schema = type_schema(type_str);
+    let min_len = field.min_length.unwrap_or(0).max(usize::from(field.required));
+
+    if types::is_numeric_type(type_str) {
+        if let Some(min) = field.min {
+            let type_min = schema.get("minimum").and_then(Value::as_f64).unwrap_or(f64::MIN);
+            if min > type_min {
+                schema["minimum"] = number(type_str, min.ceil(), min);
+            }
+        }
+        if let Some(max) = field.max {
+            let type_max = schema.get("maximum").and_then(Value::as_f64).unwrap_or(f64::MAX);
+            if max < type_max {
+                schema["maximum"] = number(type_str, max.floor(), max);
+            }
+        }
+        if let Some(allowed) = &field.one_of {
+            schema["enum"] = allowed.iter().map(|v| number(type_str, *v, *v)).collect();
+        }
+    }
+
+    match type_str {
+        "string" => {
+            if min_len > 0 {
+                schema["minLength"] = json!(min_len);
+            }
+            if let Some(max_len) = field.max_length {
+                schema["maxLength"] = json!(max_len);
+            }
+            if let Some(pattern) = &field.pattern {
+                schema["pattern"] = json!(pattern);
+            }
+        }
+        s if s == "bytes" || s.starts_with("array<") => {
+            if let Some(len) = field.length {
+                schema["minItems"] = json!(len);
+                schema["maxItems"] = json!(len);
+            } else if min_len > 0 {
+                schema["minItems"] = json!(min_len);
+            }
+            if let Some(max_len) = field.max_length {
+                schema["maxItems"] = json!(max_len);
+            }
+        }
+        s if s.starts_with("map<") && field.required => {
+            schema["minProperties"] = json!(1);
+        }
+        _ => {}
+    }
+
+    if let Some(default) = &field.default {
+        if let Ok(default) = serde_json::to_value(default) {
+            schema["default"] = default;
+        }
+    }
+
+    schema
+}
+
+/// Integers stay integers in the schema; floats keep their exact bound.
+fn number(type_str: &str, as_int: f64, as_float: f64) -> Value {
+    if types::integer_bounds(type_str).is_some() {
+        if as_int >= 0.0 {
+            json!(as_int as u64)
+        } else {
+            json!(as_int as i64)
+        }
+    } else {
+        json!(as_float)
+    }
+}
+
+/// Unconstrained schema for a TOML type string.
+fn type_schema(type_str: &str) -> Value {
+    let type_str = type_str.trim();
+
+    if let Some((lo, hi)) = types::integer_bounds(type_str) {
+        let mut schema = json!({ "type": "integer", "minimum": lo as i64 });
+        schema["maximum"] = if hi > i64::MAX as i128 { json!(hi as u64) } else { json!(hi as i64) };
+        if type_str == "timestamp" {
+            schema["description"] = json!("milliseconds since the Unix epoch");
+        }
+        return schema;
+    }
+
+    match type_str {
+        "f32" | "f64" => json!({ "type": "number" }),
+        "bool" => json!({ "type": "boolean" }),
+        "string" => json!({ "type": "string" }),
+        "uuid" => json!({ "type": "string", "format": "uuid" }),
+        "bytes" => json!({ "type": "array", "items": type_schema("u8") }),
+        s if s.starts_with("array<") && s.ends_with('>') => {
+            json!({ "type": "array", "items": type_schema(&s[6..s.len() - 1]) })
+        }
+        s if s.starts_with("map<") && s.ends_with('>') => {
+            let value = s[4..s.len() - 1].split_once(',').map_or("string", |(_, v)| v);
+            json!({ "type": "object", "additionalProperties": type_schema(value) })
+        }
+        // Nested custom types describe themselves through their own schema().
+        _ => json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(toml: &str) -> MessageDef {
+        crate::parser::parse_toml(toml).unwrap().message
+    }
+
+    #[test]
+    fn integer_types_should_carry_their_range() {
+        assert_eq!(type_schema("u8"), json!({ "type": "integer", "minimum": 0, "maximum": 255 }));
+        assert_eq!(type_schema("u64")["maximum"], json!(u64::MAX));
+        assert_eq!(type_schema("i64")["minimum"], json!(i64::MIN));
+    }
+
+    #[test]
+    fn collections_should_nest_item_schemas() {
+        assert_eq!(
+            type_schema("array<bool>"),
+            json!({ "type": "array", "items": { "type": "boolean" } })
+        );
+        assert_eq!(
+            type_schema("map<string, f64>"),
+            json!({ "type": "object", "additionalProperties": { "type": "number" } })
+        );
+    }
+
+    #[test]
+    fn constraints_should_map_to_keywords() {
+        let message = message(
+            r#"
+            [message]
+            name = "Reading"
+            [[message.fields]]
+            name = "level"
+            type = "u8"
+            min = 10
+            max = 20
+            [[message.fields]]
+            name = "code"
+            type = "string"
+            required = true
+            max_length = 8
+            pattern = "^[A-Z]+$"
+            [[message.fields]]
+            name = "channels"
+            type = "u8"
+            one_of = [1, 2, 4]
+            [[message.fields]]
+            name = "samples"
+            type = "array<f64>"
+            min_length = 2
+        "#,
+        );
+
+        let schema = message_schema(&message);
+        let props = &schema["properties"];
+        assert_eq!(props["level"]["minimum"], json!(10));
+        assert_eq!(props["level"]["maximum"], json!(20));
+        assert_eq!(props["code"]["minLength"], json!(1));
+        assert_eq!(props["code"]["maxLength"], json!(8));
+        assert_eq!(props["code"]["pattern"], json!("^[A-Z]+$"));
+        assert_eq!(props["channels"]["enum"], json!([1, 2, 4]));
+        assert_eq!(props["samples"]["minItems"], json!(2));
+    }
+
+    #[test]
+    fn fixed_length_should_pin_item_count() {
+        let message = message(
+            r#"
+            [message]
+            name = "Frame"
+            [[message.fields]]
+            name = "digest"
+            type = "bytes"
+            length = 32
+        "#,
+        );
+
+        let digest = &message_schema(&message)["properties"]["digest"];
+        assert_eq!(digest["minItems"], json!(32));
+        assert_eq!(digest["maxItems"], json!(32));
+    }
+
+    #[test]
+    fn optional_and_defaulted_fields_should_not_be_required() {
+        let message = message(
+            r#"
+            [message]
+            name = "Reading"
+            [[message.fields]]
+            name = "timestamp"
+            type = "timestamp"
+            [[message.fields]]
+            name = "note"
+            type = "string"
+            optional = true
+            [[message.fields]]
+            name = "retries"
+            type = "u8"
+            default = 3
+        "#,
+        );
+
+        let schema = document(&message);
+        assert_eq!(schema["$schema"], json!(DRAFT));
+        assert_eq!(schema["required"], json!(["timestamp"]));
+        assert_eq!(schema["properties"]["retries"]["default"], json!(3));
+    }
+
+    #[test]
+    fn enum_document_should_wrap_each_message_under_its_tag() {
+        let message = message(
+            r#"
+            [message]
+            name = "GpsFix"
+            [[message.fields]]
+            name = "lat"
+            type = "f64"
+        "#,
+        );
+
+        let schema = enum_document("FleetMessage", &[message]);
+        assert_eq!(schema["title"], json!("FleetMessage"));
+        assert_eq!(schema["oneOf"][0]["required"], json!(["GpsFix"]));
+        assert_eq!(schema["oneOf"][0]["properties"]["GpsFix"]["properties"]["lat"]["type"], json!("number"));
+    }
+}
@@ -6,7 +6,7 @@ pub fn parse_type(type_str: &str) -> TokenStream {
         "u8" => quote! { u8 },
         "u16" => quote! { u16 },
         "u32" => quote! { u32 },
-        "u64" => quote! { u64 },
+        "u64" | "timestamp" => quote! { u64 },
         "i8" => quote! { i8 },
         "i16" => quote! { i16 },
         "i32" => quote! { i32 },
@@ -16,6 +16,7 @@ pub fn parse_type(type_str: &str) -> TokenStream {
         "bool" => quote! { bool },
         "string" => quote! { String },
         "bytes" => quote! { Vec<u8> },
+        "uuid" => quote! { racer_core::Uuid },
 
         s if s.starts_with("array<") && s.ends_with('>') => {
             let inner = &s[6..s.len() - 1];
@@ -44,10 +45,121 @@ pub fn parse_type(type_str: &str) -> TokenStream {
 pub fn is_numeric_type(type_str: &str) -> bool {
     matches!(
         type_str,
-        "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" | "f32" | "f64"
+        "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" | "f32" | "f64" | "timestamp"
     )
 }
 
+pub fn integer_bounds(type_str: &str) -> Option<(i128, i128)> {
+    match type_str {
+        "u8" => Some((0, u8::MAX as i128)),
+        "u16" => Some((0, u16::MAX as i128)),
+        "u32" => Some((0, u32::MAX as i128)),
+        "u64" | "timestamp" => Some((0, u64::MAX as i128)),
+        "i8" => Some((i8::MIN as i128, i8::MAX as i128)),
+        "i16" => Some((i16::MIN as i128, i16::MAX as i128)),
+        "i32" => Some((i32::MIN as i128, i32::MAX as i128)),
+        "i64" => Some((i64::MIN as i128, i64::MAX as i128)),
+        _ => None,
+    }
+}
+
+/// Integer literal with the type's suffix, e.g. `-5i32`.
+pub fn int_literal(value: i128, type_str: &str) -> TokenStream {
+    let suffix = if type_str == "timestamp" { "u64" } else { type_str };
+    let lit = syn::LitInt::new(
+        &format!("{}{}", value.unsigned_abs(), suffix),
+        proc_macro2::Span::call_site(),
+    );
+    if value < 0 {
+        quote! { -#lit }
+    } else {
+        quote! { #lit }
+    }
+}
+
+/// Expression for a TOML `default` value of the given field type.
+pub fn default_literal(type_str: &str, value: &toml::Value) -> Result<TokenStream, String> {
+    let type_str = type_str.trim();
+
+    if let Some((type_min, type_max)) = integer_bounds(type_str) {
+        let toml::Value::Integer(i) = value else {
+            return Err(format!("default for '{}' must be an integer", type_str));
+        };
+        let i = *i as i128;
+        if i < type_min || i > type_max {
+            return Err(format!("default {} is out of range for '{}'", i, type_str));
+        }
+        return Ok(int_literal(i, type_str));
+    }
+
+    match (type_str, value) {
+        ("f32" | "f64", toml::Value::Float(_) | toml::Value::Integer(_)) => {
+            let f = match value {
+                toml::Value::Float(f) => *f,
+                toml::Value::Integer(i) => *i as f64,
+                _ => unreachable!(),
+            };
+            if !f.is_finite() {
+                return Err(format!("default for '{}' must be finite", type_str));
+            }
+            let lit = syn::LitFloat::new(
+                &format!("{:?}{}", f.abs(), type_str),
+                proc_macro2::Span::call_site(),
+            );
+            Ok(if f < 0.0 { quote! { -#lit } } else { quote! { #lit } })
+        }
+        ("bool", toml::Value::Boolean(b)) => Ok(quote! { #b }),
+        ("string", toml::Value::String(s)) => Ok(quote! { String::from(#s) }),
+        ("bytes", toml::Value::Array(items)) => {
+            let items = items
+                .iter()
+                .map(|item| default_literal("u8", item))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(quote! { vec![#(#items),*] })
+        }
+        (s, toml::Value::Array(items)) if s.starts_with("array<") && s.ends_with('>') => {
+            let inner = &s[6..s.len() - 1];
+            let items = items
+                .iter()
+                .map(|item| default_literal(inner, item))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(quote! { vec![#(#items),*] })
+        }
+        _ => Err(format!("unsupported default {} for type '{}'", value, type_str)),
+    }
+}
+
+/// Element type of `bytes` and `array<T>`.
+pub fn element_type(type_str: &str) -> Option<&str> {
+    match type_str.trim() {
+        "bytes" => Some("u8"),
+        s if s.starts_with("array<") && s.ends_with('>') => Some(s[6..s.len() - 1].trim()),
+        _ => None,
+    }
+}
+
+/// `[T; len]` for a `bytes` or `array<T>` field with a fixed `length`.
+pub fn fixed_array_type(type_str: &str, len: usize) -> TokenStream {
+    let element = parse_type(element_type(type_str).unwrap_or(type_str));
+    quote! { [#element; #len] }
+}
+
+/// Expression for a TOML `default` of a fixed-length field, which must list
+/// exactly `len` elements.
+pub fn fixed_default_literal(type_str: &str, len: usize, value: &toml::Value) -> Result<TokenStream, String> {
+    let (Some(element), toml::Value::Array(items)) = (element_type(type_str), value) else {
+        return Err(format!("unsupported default {} for type '{}'", value, type_str.trim()));
+    };
+    if items.len() != len {
+        return Err(format!("default has {} elements, expected length {}", items.len(), len));
+    }
+    let items = items
+        .iter()
+        .map(|item| default_literal(element, item))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(quote! { [#(#items),*] })
+}
+
 pub fn supports_length_validation(type_str: &str) -> bool {
     type_str == "string" || type_str == "bytes" || type_str.starts_with("array<")
 }
@@ -145,6 +257,18 @@ mod tests {
         assert_eq!(tokens.to_string(), "Vec < u8 >");
     }
 
+    #[test]
+    fn timestamp_should_map_to_u64() {
+        let tokens = parse_type("timestamp");
+        assert_eq!(tokens.to_string(), "u64");
+    }
+
+    #[test]
+    fn uuid_should_map_to_racer_core_uuid() {
+        let tokens = parse_type("uuid");
+        assert_eq!(tokens.to_string(), "racer_core :: Uuid");
+    }
+
     #[test]
     fn array_of_u64_should_map_to_vec_u64() {
         let tokens = parse_type("array<u64>");
@@ -291,6 +415,17 @@ mod tests {
         assert!(is_numeric_type("f64"));
     }
 
+    #[test]
+    fn timestamp_should_be_numeric() {
+        assert!(is_numeric_type("timestamp"));
+        assert_eq!(integer_bounds("timestamp"), integer_bounds("u64"));
+    }
+
+    #[test]
+    fn uuid_should_not_be_numeric() {
+        assert!(!is_numeric_type("uuid"));
+    }
+
     #[test]
     fn string_should_not_be_numeric() {
         assert!(!is_numeric_type("string"));
@@ -321,6 +456,84 @@ mod tests {
         assert!(!is_numeric_type("CustomType"));
     }
 
+    #[test]
+    fn integer_bounds_should_match_rust_types() {
+        assert_eq!(integer_bounds("u8"), Some((0, 255)));
+        assert_eq!(integer_bounds("i8"), Some((-128, 127)));
+        assert_eq!(integer_bounds("u64"), Some((0, u64::MAX as i128)));
+        assert_eq!(integer_bounds("f64"), None);
+        assert_eq!(integer_bounds("string"), None);
+    }
+
+    #[test]
+    fn int_literal_should_keep_sign_and_suffix() {
+        assert_eq!(int_literal(5, "u8").to_string(), "5u8");
+        assert_eq!(int_literal(-5, "i32").to_string(), "- 5i32");
+        assert_eq!(int_literal(-128, "i8").to_string(), "- 128i8");
+        assert_eq!(int_literal(7, "timestamp").to_string(), "7u64");
+    }
+
+    #[test]
+    fn integer_default_should_be_suffixed_literal() {
+        let tokens = default_literal("u32", &toml::Value::Integer(42)).unwrap();
+        assert_eq!(tokens.to_string(), "42u32");
+    }
+
+    #[test]
+    fn integer_default_out_of_range_should_fail() {
+        assert!(default_literal("u8", &toml::Value::Integer(256)).is_err());
+        assert!(default_literal("u64", &toml::Value::Integer(-1)).is_err());
+    }
+
+    #[test]
+    fn float_default_should_accept_integers() {
+        let tokens = default_literal("f64", &toml::Value::Integer(-3)).unwrap();
+        assert_eq!(tokens.to_string(), "- 3.0f64");
+    }
+
+    #[test]
+    fn string_default_should_build_string() {
+        let tokens = default_literal("string", &toml::Value::String("unknown".into())).unwrap();
+        assert_eq!(tokens.to_string(), "String :: from (\"unknown\")");
+    }
+
+    #[test]
+    fn array_default_should_build_vec() {
+        let value = toml::Value::Array(vec![toml::Value::Integer(1), toml::Value::Integer(2)]);
+        let tokens = default_literal("array<u8>", &value).unwrap();
+        assert_eq!(tokens.to_string(), "vec ! [1u8 , 2u8]");
+    }
+
+    #[test]
+    fn mismatched_default_should_fail() {
+        assert!(default_literal("string", &toml::Value::Integer(1)).is_err());
+        assert!(default_literal("bool", &toml::Value::String("yes".into())).is_err());
+        assert!(default_literal("map<string, u64>", &toml::Value::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn element_type_should_unwrap_bytes_and_arrays() {
+        assert_eq!(element_type("bytes"), Some("u8"));
+        assert_eq!(element_type("array< f32 >"), Some("f32"));
+        assert_eq!(element_type("string"), None);
+        assert_eq!(element_type("map<string, u8>"), None);
+    }
+
+    #[test]
+    fn fixed_bytes_should_map_to_u8_array() {
+        assert_eq!(fixed_array_type("bytes", 32).to_string(), "[u8 ; 32usize]");
+        assert_eq!(fixed_array_type("array<f32>", 3).to_string(), "[f32 ; 3usize]");
+    }
+
+    #[test]
+    fn fixed_default_should_build_array_of_exact_length() {
+        let value = toml::Value::Array(vec![toml::Value::Integer(1), toml::Value::Integer(2)]);
+        let tokens = fixed_default_literal("array<u16>", 2, &value).unwrap();
+        assert_eq!(tokens.to_string(), "[1u16 , 2u16]");
+        assert!(fixed_default_literal("array<u16>", 3, &value).is_err());
+        assert!(fixed_default_literal("bytes", 1, &toml::Value::Integer(1)).is_err());
+    }
+
     #[test]
     fn string_should_support_length_validation() {
         assert!(supports_length_validation("string"));
@@ -0,0 +1,743 @@
+#![cfg(test)]
+
+use racer_core::{GenerateRandom, Message};
+use racer_macros::{racer_message, racer_messages, RacerMessage};
+
+#[racer_message("tests/fixtures/constrained.toml")]
+pub struct ConstrainedReading;
+
+#[racer_message("tests/fixtures/defaults.toml")]
+pub struct VersionedReading;
+
+#[racer_message("tests/fixtures/validated.toml")]
+pub struct CheckedRange;
+
+#[racer_message("tests/fixtures/rules.toml")]
+pub struct TimeWindow;
+
+#[racer_message("tests/fixtures/optional.toml")]
+pub struct PartialReading;
+
+#[racer_message("tests/fixtures/auto.toml")]
+pub struct TaggedEvent;
+
+#[racer_message("tests/fixtures/fixed.toml")]
+pub struct SignedFrame;
+
+racer_messages!("tests/fixtures/fleet.toml");
+
+mod checks {
+    use racer_core::validation::{ValidationError, ValidationResult};
+
+    pub fn known_unit(unit: &str) -> ValidationResult {
+        match unit {
+            "C" | "F" | "K" => Ok(()),
+            other => Err(ValidationError::custom("unit", format!("unknown unit '{}'", other))),
+        }
+    }
+
+    pub fn low_not_above_high(msg: &super::CheckedRange) -> ValidationResult {
+        if msg.low > msg.high {
+            return Err(ValidationError::custom("low", "low must not exceed high"));
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// RANDOM GENERATION TESTS
+// =============================================================================
+mod random_generation {
+    use super::*;
+
+    #[test]
+    fn random_should_always_pass_validation() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let msg = ConstrainedReading::random(&mut rng);
+            assert!(msg.validate().is_ok(), "random value should be valid: {:?}", msg);
+        }
+    }
+
+    #[test]
+    fn random_should_respect_integer_bounds() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let msg = ConstrainedReading::random(&mut rng);
+            assert!((10..=20).contains(&msg.level));
+            assert!((-5..=5).contains(&msg.offset));
+        }
+    }
+
+    #[test]
+    fn random_should_respect_length_bounds() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let msg = ConstrainedReading::random(&mut rng);
+            assert!(!msg.sensor_id.is_empty() && msg.sensor_id.len() <= 8);
+            assert!((2..=4).contains(&msg.samples.len()));
+            assert!(msg.raw.len() <= 16);
+        }
+    }
+
+    #[test]
+    fn random_invalid_should_always_fail_validation() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let msg = ConstrainedReading::random_invalid(&mut rng)
+                .expect("schema has breakable constraints");
+            assert!(msg.validate().is_err(), "invalid value should fail: {:?}", msg);
+        }
+    }
+}
+
+// =============================================================================
+// PATTERN AND ONE_OF TESTS
+// =============================================================================
+mod pattern_and_one_of {
+    use super::*;
+    use racer_core::validation::ValidationKind;
+
+    fn valid() -> ConstrainedReading {
+        ConstrainedReading::random(&mut rand::thread_rng())
+    }
+
+    #[test]
+    fn random_should_match_pattern_and_allowed_values() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let msg = ConstrainedReading::random(&mut rng);
+            let (prefix, digits) = msg.code.split_once('-').expect("code has a dash");
+            assert!(prefix.len() == 3 && prefix.chars().all(|c| c.is_ascii_uppercase()));
+            assert!(!digits.is_empty() && digits.chars().all(char::is_numeric));
+            assert!([1, 2, 4, 8].contains(&msg.channels));
+        }
+    }
+
+    #[test]
+    fn non_matching_string_should_fail_with_pattern_kind() {
+        let mut msg = valid();
+        msg.code = "ab-12".into();
+        let err = msg.validate().unwrap_err();
+        assert_eq!(err.field, "code");
+        assert!(matches!(err.kind, ValidationKind::Pattern { .. }));
+    }
+
+    #[test]
+    fn value_outside_set_should_fail_with_one_of_kind() {
+        let mut msg = valid();
+        msg.channels = 3;
+        let err = msg.validate().unwrap_err();
+        assert_eq!(err.field, "channels");
+        assert!(matches!(err.kind, ValidationKind::OneOf { actual, .. } if actual == 3.0));
+    }
+}
+
+// =============================================================================
+// CUSTOM VALIDATOR TESTS
+// =============================================================================
+mod custom_validators {
+    use super::*;
+
+    fn checked(low: f64, high: f64, unit: &str) -> CheckedRange {
+        CheckedRange {
+            timestamp: 1,
+            low,
+            high,
+            unit: unit.into(),
+        }
+    }
+
+    #[test]
+    fn passing_validators_should_validate() {
+        assert!(checked(1.0, 2.0, "C").validate().is_ok());
+    }
+
+    #[test]
+    fn field_validator_should_receive_field_value() {
+        let err = checked(1.0, 2.0, "parsecs").validate().unwrap_err();
+        assert_eq!(err.field, "unit");
+        assert!(err.message.contains("parsecs"));
+    }
+
+    #[test]
+    fn message_validator_should_check_across_fields() {
+        let err = checked(3.0, 2.0, "K").validate().unwrap_err();
+        assert_eq!(err.field, "low");
+    }
+
+    #[test]
+    fn declarative_checks_should_run_before_validators() {
+        let err = checked(-1.0, -2.0, "").validate().unwrap_err();
+        assert_eq!(err.field, "low");
+        assert!(matches!(err.kind, racer_core::validation::ValidationKind::MinValue { .. }));
+    }
+}
+
+// =============================================================================
+// CROSS-FIELD RULE TESTS
+// =============================================================================
+mod cross_field_rules {
+    use super::*;
+    use racer_core::validation::ValidationKind;
+
+    fn window(start_time: u64, end_time: u64, sample_rate: f32) -> TimeWindow {
+        TimeWindow {
+            start_time,
+            end_time,
+            sample_rate,
+        }
+    }
+
+    #[test]
+    fn satisfied_rules_should_validate() {
+        assert!(window(10, 20, 1.0).validate().is_ok());
+        assert!(window(10, 10, 0.5).validate().is_ok());
+    }
+
+    #[test]
+    fn field_comparison_should_fail_with_rule_kind() {
+        let err = window(20, 10, 1.0).validate().unwrap_err();
+        assert_eq!(err.field, "end_time");
+        assert_eq!(err.kind, ValidationKind::Rule { expr: "end_time >= start_time".into() });
+    }
+
+    #[test]
+    fn literal_comparison_should_use_declared_message() {
+        let err = window(10, 20, 0.0).validate().unwrap_err();
+        assert_eq!(err.field, "sample_rate");
+        assert_eq!(err.message, "sample_rate must be positive");
+    }
+}
+
+// =============================================================================
+// OPTIONAL FIELD TESTS
+// =============================================================================
+mod optional_fields {
+    use super::*;
+    use racer_core::validation::ValidationKind;
+
+    fn reading() -> PartialReading {
+        PartialReading {
+            timestamp: 1,
+            note: None,
+            battery: None,
+            label: Some("lab".into()),
+            firmware: None,
+        }
+    }
+
+    #[test]
+    fn absent_optional_fields_should_validate() {
+        assert!(reading().validate().is_ok());
+    }
+
+    #[test]
+    fn absent_fields_should_be_skipped_on_the_wire() {
+        let json = serde_json::to_string(&reading()).unwrap();
+        assert_eq!(json, r#"{"timestamp":1,"label":"lab"}"#);
+    }
+
+    #[test]
+    fn missing_fields_should_deserialize_to_none_or_default() {
+        let msg: PartialReading = serde_json::from_str(r#"{"timestamp":1}"#).unwrap();
+        assert_eq!(msg.note, None);
+        assert_eq!(msg.label, None);
+        assert_eq!(msg.firmware.as_deref(), Some("1.0"));
+    }
+
+    #[test]
+    fn constraints_should_apply_to_present_values() {
+        let mut msg = reading();
+        msg.battery = Some(101);
+        let err = msg.validate().unwrap_err();
+        assert_eq!(err.field, "battery");
+        assert!(matches!(err.kind, ValidationKind::MaxValue { .. }));
+    }
+
+    #[test]
+    fn required_optional_field_should_reject_absent_and_empty() {
+        let mut msg = reading();
+        msg.label = None;
+        assert_eq!(msg.validate().unwrap_err().kind, ValidationKind::Required);
+
+        msg.label = Some(String::new());
+        assert_eq!(msg.validate().unwrap_err().kind, ValidationKind::Required);
+    }
+
+    #[test]
+    fn random_should_respect_optional_constraints() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let msg = PartialReading::random(&mut rng);
+            assert!(msg.validate().is_ok(), "random value should be valid: {:?}", msg);
+            let invalid = PartialReading::random_invalid(&mut rng).unwrap();
+            assert!(invalid.validate().is_err(), "invalid value should fail: {:?}", invalid);
+        }
+    }
+}
+
+// =============================================================================
+// AUTO FIELD TESTS
+// =============================================================================
+mod auto_fields {
+    use super::*;
+
+    #[test]
+    fn new_should_fill_auto_fields() {
+        let before = racer_core::message::now_millis();
+        let event = TaggedEvent::new("door".into(), 1.5);
+
+        assert_eq!(event.event_id.get_version_num(), 4);
+        assert!(event.created_at >= before);
+        assert_eq!(event.source, "door");
+        assert_eq!(event.value, 1.5);
+        assert!(event.validate().is_ok());
+    }
+
+    #[test]
+    fn new_should_generate_distinct_uuids() {
+        let a = TaggedEvent::new("a".into(), 0.0);
+        let b = TaggedEvent::new("a".into(), 0.0);
+        assert_ne!(a.event_id, b.event_id);
+    }
+
+    #[test]
+    fn timestamp_should_serve_as_fallback_id() {
+        let event = TaggedEvent::new("door".into(), 1.5);
+        assert_eq!(event.id(), event.created_at);
+    }
+
+    #[test]
+    fn uuid_should_roundtrip_as_string() {
+        let event = TaggedEvent::new("door".into(), 1.5);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event_id"], event.event_id.to_string());
+
+        let parsed: TaggedEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.event_id, event.event_id);
+    }
+
+    #[test]
+    fn nil_uuid_should_fail_required() {
+        let mut event = TaggedEvent::new("door".into(), 1.5);
+        event.event_id = racer_core::Uuid::nil();
+        assert_eq!(event.validate().unwrap_err().field, "event_id");
+    }
+
+    #[test]
+    fn random_should_produce_valid_events() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let event = TaggedEvent::random(&mut rng);
+            assert!(event.validate().is_ok());
+            assert!(TaggedEvent::random_invalid(&mut rng).unwrap().validate().is_err());
+        }
+    }
+}
+
+// =============================================================================
+// BUILDER TESTS
+// =============================================================================
+mod builders {
+    use super::*;
+    use racer_core::validation::ValidationKind;
+
+    #[test]
+    fn build_should_fill_defaults_for_unset_fields() {
+        let msg = VersionedReading::builder().value(2.5).timestamp(7).build().unwrap();
+        assert_eq!(msg.timestamp, 7);
+        assert_eq!(msg.value, 2.5);
+        assert_eq!(msg.retries, 3);
+        assert_eq!(msg.unit, "unknown");
+    }
+
+    #[test]
+    fn setters_should_override_defaults() {
+        let msg = VersionedReading::builder()
+            .timestamp(1)
+            .value(0.0)
+            .retries(9)
+            .unit("°C".into())
+            .build()
+            .unwrap();
+        assert_eq!(msg.retries, 9);
+        assert_eq!(msg.unit, "°C");
+    }
+
+    #[test]
+    fn optional_fields_should_default_to_none() {
+        let msg = PartialReading::builder()
+            .timestamp(1)
+            .label("lab".into())
+            .battery(80)
+            .build()
+            .unwrap();
+        assert_eq!(msg.note, None);
+        assert_eq!(msg.battery, Some(80));
+        assert_eq!(msg.firmware.as_deref(), Some("1.0"));
+    }
+
+    #[test]
+    fn auto_fields_should_be_filled_unless_set() {
+        let event = TaggedEvent::builder().source("door".into()).value(1.0).build().unwrap();
+        assert_eq!(event.event_id.get_version_num(), 4);
+        assert!(event.created_at > 0);
+
+        let event = TaggedEvent::builder()
+            .source("door".into())
+            .value(1.0)
+            .created_at(42)
+            .build()
+            .unwrap();
+        assert_eq!(event.created_at, 42);
+    }
+
+    #[test]
+    fn build_should_run_validation() {
+        let err = PartialReading::builder().timestamp(1).build().unwrap_err();
+        assert_eq!(err.field, "label");
+        assert_eq!(err.kind, ValidationKind::Required);
+    }
+}
+
+// =============================================================================
+// MULTI-MESSAGE TESTS
+// =============================================================================
+mod multi_message {
+    use super::*;
+
+    fn fix() -> GpsFix {
+        GpsFix {
+            timestamp: 11,
+            lat: 48.8,
+            lon: 2.3,
+        }
+    }
+
+    fn telemetry() -> EngineTelemetry {
+        EngineTelemetry {
+            sampled_at: 22,
+            rpm: 3000,
+            engine_id: "e1".into(),
+        }
+    }
+
+    #[test]
+    fn enum_should_delegate_id_and_validate() {
+        let msg: FleetMessage = fix().into();
+        assert_eq!(msg.id(), 11);
+        assert!(msg.validate().is_ok());
+
+        let msg = FleetMessage::from(telemetry());
+        assert_eq!(msg.id(), 22);
+
+        let mut bad = telemetry();
+        bad.rpm = 9001;
+        assert_eq!(FleetMessage::from(bad).validate().unwrap_err().field, "rpm");
+    }
+
+    #[test]
+    fn enum_should_roundtrip_through_serde() {
+        let json = serde_json::to_string(&FleetMessage::from(fix())).unwrap();
+        let parsed: FleetMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(parsed, FleetMessage::GpsFix(f) if f.lat == 48.8));
+    }
+
+    #[test]
+    fn structs_should_get_full_single_message_api() {
+        let msg = EngineTelemetry::builder()
+            .sampled_at(1)
+            .rpm(100)
+            .engine_id("e2".into())
+            .build()
+            .unwrap();
+        assert_eq!(msg.engine_id, "e2");
+        assert!(GpsFix::random(&mut rand::thread_rng()).validate().is_ok());
+    }
+
+    #[test]
+    fn random_should_cover_every_variant_and_stay_valid() {
+        let mut rng = rand::thread_rng();
+        let (mut fixes, mut telemetry) = (0, 0);
+        for _ in 0..200 {
+            match FleetMessage::random(&mut rng) {
+                msg @ FleetMessage::GpsFix(_) => {
+                    fixes += 1;
+                    assert!(msg.validate().is_ok());
+                }
+                msg @ FleetMessage::EngineTelemetry(_) => {
+                    telemetry += 1;
+                    assert!(msg.validate().is_ok());
+                }
+            }
+            assert!(FleetMessage::random_invalid(&mut rng).unwrap().validate().is_err());
+        }
+        assert!(fixes > 0 && telemetry > 0);
+    }
+}
+
+// =============================================================================
+// SCHEMA EXPORT TESTS
+// =============================================================================
+mod schema_export {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn schema_should_describe_fields_and_constraints() {
+        let schema = ConstrainedReading::schema();
+        assert_eq!(schema["title"], json!("ConstrainedReading"));
+        assert_eq!(schema["properties"]["level"]["minimum"], json!(10));
+        assert_eq!(schema["properties"]["code"]["pattern"], json!(r"^[A-Z]{3}-\d+$"));
+        assert_eq!(schema["properties"]["channels"]["enum"], json!([1, 2, 4, 8]));
+    }
+
+    #[test]
+    fn serialized_messages_should_match_schema_shape() {
+        let msg = PartialReading::builder().timestamp(5).label("lab".into()).build().unwrap();
+        let schema = PartialReading::schema();
+        let json = serde_json::to_value(&msg).unwrap();
+
+        for required in schema["required"].as_array().unwrap() {
+            assert!(json.get(required.as_str().unwrap()).is_some(), "{} missing", required);
+        }
+        for key in json.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{} not in schema", key);
+        }
+    }
+
+    #[test]
+    fn enum_schema_should_render_as_proto_oneof() {
+        let proto = racer_core::schema::to_proto(&FleetMessage::schema());
+        assert!(proto.contains("message GpsFix {"));
+        assert!(proto.contains("  string engine_id = 1;"));
+        assert!(proto.contains("    EngineTelemetry engine_telemetry = 2;"));
+    }
+}
+
+// =============================================================================
+// FIELD DEFAULT TESTS
+// =============================================================================
+mod field_defaults {
+    use super::*;
+
+    #[test]
+    fn default_should_use_declared_values() {
+        let msg = VersionedReading::default();
+        assert_eq!(msg.timestamp, 0);
+        assert_eq!(msg.retries, 3);
+        assert_eq!(msg.offset, -7);
+        assert_eq!(msg.scale, 1.5);
+        assert_eq!(msg.unit, "unknown");
+        assert!(msg.calibrated);
+        assert_eq!(msg.channels, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn missing_fields_should_deserialize_to_defaults() {
+        let msg: VersionedReading = serde_json::from_str(r#"{"timestamp":7,"value":2.5}"#).unwrap();
+        assert_eq!(msg.id(), 7);
+        assert_eq!(msg.value, 2.5);
+        assert_eq!(msg.retries, 3);
+        assert_eq!(msg.unit, "unknown");
+        assert_eq!(msg.channels, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn fields_without_default_should_stay_required_on_the_wire() {
+        let result = serde_json::from_str::<VersionedReading>(r#"{"timestamp":7}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn present_fields_should_override_defaults() {
+        let msg: VersionedReading =
+            serde_json::from_str(r#"{"timestamp":1,"value":0.0,"retries":9,"unit":"°C"}"#).unwrap();
+        assert_eq!(msg.retries, 9);
+        assert_eq!(msg.unit, "°C");
+    }
+}
+
+// =============================================================================
+// FIXED-LENGTH FIELD TESTS
+// =============================================================================
+mod fixed_length {
+    use super::*;
+
+    fn frame() -> SignedFrame {
+        SignedFrame {
+            timestamp: 1,
+            signature: [7; 64],
+            digest: [0; 32],
+            channels: [1.0, 2.0, 3.0],
+            calibration: None,
+        }
+    }
+
+    #[test]
+    fn fields_should_be_fixed_arrays() {
+        let msg = frame();
+        let _: [u8; 64] = msg.signature;
+        let _: [u8; 32] = msg.digest;
+        let _: [f32; 3] = msg.channels;
+        let _: Option<[u16; 2]> = msg.calibration;
+        assert!(msg.validate().is_ok());
+    }
+
+    #[test]
+    fn default_should_zero_fill_and_use_declared_values() {
+        let msg = SignedFrame::default();
+        assert_eq!(msg.signature, [0; 64]);
+        assert_eq!(msg.channels, [0.0, 0.0, 1.0]);
+        assert_eq!(msg.calibration, None);
+    }
+
+    #[test]
+    fn serde_should_round_trip() {
+        let mut msg = frame();
+        msg.calibration = Some([10, 20]);
+        let json = serde_json::to_string(&msg).unwrap();
+        let back: SignedFrame = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.signature, msg.signature);
+        assert_eq!(back.calibration, Some([10, 20]));
+    }
+
+    #[test]
+    fn wrong_lengths_should_fail_to_deserialize() {
+        let mut value = serde_json::to_value(frame()).unwrap();
+        value["digest"] = serde_json::json!(vec![0u8; 31]);
+        assert!(serde_json::from_value::<SignedFrame>(value.clone()).is_err());
+
+        value["digest"] = serde_json::json!(vec![0u8; 32]);
+        value["calibration"] = serde_json::json!([1, 2, 3]);
+        assert!(serde_json::from_value::<SignedFrame>(value).is_err());
+    }
+
+    #[test]
+    fn random_should_fill_every_element() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let msg = SignedFrame::random(&mut rng);
+            assert!(msg.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn schema_should_pin_lengths() {
+        let schema = SignedFrame::schema();
+        assert_eq!(schema["properties"]["signature"]["minItems"], serde_json::json!(64));
+        assert_eq!(schema["properties"]["channels"]["maxItems"], serde_json::json!(3));
+    }
+
+    #[test]
+    fn builder_should_take_arrays() {
+        let msg = SignedFrame::builder()
+            .timestamp(5)
+            .signature([1; 64])
+            .digest([2; 32])
+            .calibration([3, 4])
+            .build()
+            .unwrap();
+        assert_eq!(msg.calibration, Some([3, 4]));
+        assert_eq!(msg.channels, [0.0, 0.0, 1.0]);
+    }
+}
+
+// =============================================================================
+// DERIVE TESTS
+// =============================================================================
+mod derive_macro {
+    use super::*;
+    use racer_core::validation::ValidationKind;
+
+    #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, RacerMessage)]
+    #[racer(rule = "high >= low", message = "high must not be below low")]
+    #[racer(validator = "not_all_zero")]
+    pub struct DerivedReading {
+        pub sequence: u64,
+        #[racer(id)]
+        pub timestamp: u64,
+        #[racer(min = -40.0, max = 100.0)]
+        pub low: f64,
+        #[racer(min = -40.0, max = 100.0)]
+        pub high: f64,
+        #[racer(required, max_length = 8, pattern = "^[a-z-]+$")]
+        pub sensor: String,
+        #[racer(one_of = [1, 2, 4])]
+        pub channels: u8,
+        #[racer(max = 100)]
+        pub battery: Option<u8>,
+        pub digest: [u8; 4],
+    }
+
+    pub fn not_all_zero(msg: &DerivedReading) -> racer_core::ValidationResult {
+        if msg.digest == [0; 4] {
+            return Err(racer_core::ValidationError::custom("digest", "digest must be set"));
+        }
+        Ok(())
+    }
+
+    fn reading() -> DerivedReading {
+        DerivedReading {
+            sequence: 9,
+            timestamp: 42,
+            low: 10.0,
+            high: 20.0,
+            sensor: "probe-a".into(),
+            channels: 2,
+            battery: None,
+            digest: [1, 2, 3, 4],
+        }
+    }
+
+    #[test]
+    fn id_attribute_should_select_the_id_field() {
+        assert_eq!(reading().id(), 42);
+        assert!(reading().validate().is_ok());
+    }
+
+    #[test]
+    fn field_attributes_should_validate() {
+        let mut msg = reading();
+        msg.low = -41.0;
+        assert!(matches!(msg.validate().unwrap_err().kind, ValidationKind::MinValue { .. }));
+
+        let mut msg = reading();
+        msg.sensor = String::new();
+        assert_eq!(msg.validate().unwrap_err().kind, ValidationKind::Required);
+
+        let mut msg = reading();
+        msg.sensor = "Probe".into();
+        assert!(matches!(msg.validate().unwrap_err().kind, ValidationKind::Pattern { .. }));
+
+        let mut msg = reading();
+        msg.channels = 3;
+        assert!(matches!(msg.validate().unwrap_err().kind, ValidationKind::OneOf { .. }));
+
+        let mut msg = reading();
+        msg.battery = Some(101);
+        assert_eq!(msg.validate().unwrap_err().field, "battery");
+    }
+
+    #[test]
+    fn struct_attributes_should_validate() {
+        let mut msg = reading();
+        msg.high = 5.0;
+        let err = msg.validate().unwrap_err();
+        assert_eq!(err.message, "high must not be below low");
+
+        let mut msg = reading();
+        msg.digest = [0; 4];
+        assert_eq!(msg.validate().unwrap_err().field, "digest");
+    }
+
+    #[test]
+    fn schema_should_describe_the_struct() {
+        let schema = DerivedReading::schema();
+        assert_eq!(schema["title"], serde_json::json!("DerivedReading"));
+        assert_eq!(schema["properties"]["sensor"]["maxLength"], serde_json::json!(8));
+        assert_eq!(schema["properties"]["digest"]["maxItems"], serde_json::json!(4));
+        assert!(!schema["required"].as_array().unwrap().contains(&serde_json::json!("battery")));
+    }
+}
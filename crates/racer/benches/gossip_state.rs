@@ -0,0 +1,107 @@
+//! Round bookkeeping throughput behind one lock versus sharded locks.
+//!
+//! Each task drives rounds the way a node does: start the round, record an
+//! Echo and a Ready response per peer, each under its own write lock, then
+//! mark it delivered. Run with `cargo bench --bench gossip_state`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use racer::protocol::{GossipState, ShardedGossipState, DEFAULT_GOSSIP_SHARDS};
+use racer_core::message::DefaultMessage;
+use tokio::sync::RwLock;
+
+const TASKS: usize = 64;
+const ROUNDS_PER_TASK: usize = 200;
+const PEERS: usize = 8;
+
+fn round_hash(task: usize, round: usize) -> String {
+    racer::crypto::sha256_hex(format!("{}-{}", task, round).as_bytes())
+}
+
+async fn single_lock() -> Duration {
+    let state = Arc::new(RwLock::new(GossipState::<DefaultMessage>::new()));
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let state = Arc::clone(&state);
+            tokio::spawn(async move {
+                for round in 0..ROUNDS_PER_TASK {
+                    let hash = round_hash(task, round);
+                    state.write().await.start_round(hash.as_str());
+                    for peer in 0..PEERS {
+                        let peer = peer.to_string();
+                        if let Some(round) = state.write().await.get_round_mut(&hash) {
+                            round.record_echo(&peer);
+                        }
+                        tokio::task::yield_now().await;
+                        if let Some(round) = state.write().await.get_round_mut(&hash) {
+                            round.record_ready(&peer);
+                        }
+                        tokio::task::yield_now().await;
+                    }
+                    state.write().await.mark_delivered(&hash);
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    start.elapsed()
+}
+
+async fn sharded(shards: usize) -> Duration {
+    let state = Arc::new(ShardedGossipState::<DefaultMessage>::new(shards));
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let state = Arc::clone(&state);
+            tokio::spawn(async move {
+                for round in 0..ROUNDS_PER_TASK {
+                    let hash = round_hash(task, round);
+                    state.rounds(&hash).write().await.start_round(hash.as_str());
+                    for peer in 0..PEERS {
+                        let peer = peer.to_string();
+                        if let Some(round) = state.rounds(&hash).write().await.get_round_mut(&hash) {
+                            round.record_echo(&peer);
+                        }
+                        tokio::task::yield_now().await;
+                        if let Some(round) = state.rounds(&hash).write().await.get_round_mut(&hash) {
+                            round.record_ready(&peer);
+                        }
+                        tokio::task::yield_now().await;
+                    }
+                    state.mark_delivered(&hash).await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let rounds = (TASKS * ROUNDS_PER_TASK) as f64;
+    println!(
+        "{:<20} {:>8.1} ms  {:>10.0} rounds/s",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        rounds / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        // Warm up the allocator and worker threads.
+        single_lock().await;
+
+        report("single lock", single_lock().await);
+        for shards in [4, DEFAULT_GOSSIP_SHARDS, 64] {
+            report(&format!("{} shards", shards), sharded(shards).await);
+        }
+    });
+}
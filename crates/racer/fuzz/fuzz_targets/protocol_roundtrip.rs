@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use racer::protocol::ProtocolMessage;
+
+fuzz_target!(|message: ProtocolMessage<String>| {
+    racer::fuzz::roundtrip(message);
+});
@@ -16,6 +16,17 @@ enum Commands {
     Run(racer::cli::run::Args),
     Config(racer::cli::config::Args),
     Keygen(racer::cli::keygen::Args),
+    Submit(racer::cli::submit::Args),
+    Status(racer::cli::status::Args),
+    Peers(racer::cli::peers::Args),
+    Topo(racer::cli::topo::Args),
+    Export(racer::cli::export::Args),
+    Import(racer::cli::import::Args),
+    Quarantine(racer::cli::quarantine::Args),
+    Bench(racer::cli::bench::Args),
+    Soak(racer::cli::soak::Args),
+    PlatoSim(racer::cli::plato_sim::Args),
+    Conformance(racer::cli::conformance::Args),
 }
 
 #[tokio::main]
@@ -26,5 +37,16 @@ async fn main() -> anyhow::Result<()> {
         Commands::Run(args) => racer::cli::run::execute(args).await,
         Commands::Config(args) => racer::cli::config::execute(args),
         Commands::Keygen(args) => racer::cli::keygen::execute(args),
+        Commands::Submit(args) => racer::cli::submit::execute(args).await,
+        Commands::Status(args) => racer::cli::status::execute(args).await,
+        Commands::Peers(args) => racer::cli::peers::execute(args).await,
+        Commands::Topo(args) => racer::cli::topo::execute(args).await,
+        Commands::Export(args) => racer::cli::export::execute(args).await,
+        Commands::Import(args) => racer::cli::import::execute(args).await,
+        Commands::Quarantine(args) => racer::cli::quarantine::execute(args).await,
+        Commands::Bench(args) => racer::cli::bench::execute(args).await,
+        Commands::Soak(args) => racer::cli::soak::execute(args).await,
+        Commands::PlatoSim(args) => racer::cli::plato_sim::execute(args),
+        Commands::Conformance(args) => racer::cli::conformance::execute(args),
     }
 }
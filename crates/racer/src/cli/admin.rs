@@ -0,0 +1,60 @@
+//! Shared plumbing for subcommands that talk to a running node's admin API.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args as ClapArgs;
+
+use crate::config::RacerConfig;
+use crate::node::admin::{self, AdminRequest, AdminResponse};
+
+#[derive(ClapArgs, Debug)]
+pub struct AdminArgs {
+    #[arg(short, long, default_value = "racer.toml")]
+    pub config: PathBuf,
+
+    /// Admin API address; defaults to `node.admin_bind` from the config.
+    #[arg(long, env = "RACER_ADMIN")]
+    pub admin: Option<String>,
+}
+
+/// `--timeout-secs` as a duration; it must be positive and finite.
+pub fn timeout(secs: f64) -> anyhow::Result<Duration> {
+    match Duration::try_from_secs_f64(secs) {
+        Ok(timeout) if !timeout.is_zero() => Ok(timeout),
+        _ => anyhow::bail!("--timeout-secs must be positive and finite, got {}", secs),
+    }
+}
+
+impl AdminArgs {
+    pub fn address(&self) -> anyhow::Result<String> {
+        if let Some(address) = &self.admin {
+            return Ok(address.clone());
+        }
+        if !self.config.exists() {
+            anyhow::bail!(
+                "Config file {} not found; pass --admin <host:port>",
+                self.config.display()
+            );
+        }
+        RacerConfig::from_file(&self.config)?
+            .node
+            .admin_bind
+            .ok_or_else(|| anyhow::anyhow!("node.admin_bind is not set; pass --admin <host:port>"))
+    }
+
+    pub async fn request(&self, request: AdminRequest) -> anyhow::Result<AdminResponse> {
+        self.request_with_timeout(request, admin::ADMIN_TIMEOUT).await
+    }
+
+    pub async fn request_with_timeout(
+        &self,
+        request: AdminRequest,
+        timeout: Duration,
+    ) -> anyhow::Result<AdminResponse> {
+        match admin::request_with_timeout(&self.address()?, &request, timeout).await? {
+            AdminResponse::Error { message } => anyhow::bail!("node returned an error: {}", message),
+            response => Ok(response),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! `racer bench` subcommand implementation.
+//!
+//! Spins up an in-process cluster over loopback ZMQ, submits messages at a
+//! fixed rate for a fixed duration and reports delivery throughput, delivery
+//! latency percentiles and how PLATO's latency estimate moved during the run.
+//! Consensus and PLATO settings come from the config file, so the same file
+//! can be tuned here and then deployed.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+use crate::config::RacerConfig;
+use crate::network::PeerInfo;
+use crate::node::Node;
+use racer_core::message::DefaultMessage;
+
+/// Publisher ports are offset from router ports by this much.
+pub(crate) const PUBLISHER_PORT_OFFSET: u16 = 1000;
+
+/// Time given to the mesh to connect before the first submission.
+const CONNECT_SETTLE: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[arg(short, long, default_value = "racer.toml")]
+    pub config: PathBuf,
+
+    #[arg(short, long, default_value_t = 8)]
+    pub nodes: usize,
+
+    /// First router port; publishers start at `base_port + 1000`.
+    #[arg(long, default_value_t = 24000)]
+    pub base_port: u16,
+
+    /// Messages per second across the whole cluster.
+    #[arg(long, default_value_t = 10.0)]
+    pub rate: f64,
+
+    #[arg(long, default_value_t = 10.0)]
+    pub duration_secs: f64,
+
+    /// How often PLATO's latency estimate is sampled.
+    #[arg(long, default_value_t = 1.0)]
+    pub sample_interval_secs: f64,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub nodes: usize,
+    pub submitted: usize,
+    pub delivered: usize,
+    pub failed: usize,
+    pub errors: usize,
+    pub elapsed_secs: f64,
+    pub throughput_per_sec: f64,
+    pub latency_p50_secs: f64,
+    pub latency_p99_secs: f64,
+    pub latency_max_secs: f64,
+    pub plato: PlatoSummary,
+}
+
+/// Cluster-mean PLATO latency estimate over the run.
+#[derive(Debug, Serialize)]
+pub struct PlatoSummary {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub min_secs: f64,
+    pub max_secs: f64,
+}
+
+enum Outcome {
+    Delivered(Duration),
+    Failed,
+    Error,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    if args.nodes < 2 {
+        anyhow::bail!("--nodes must be at least 2");
+    }
+    if args.rate <= 0.0 || args.duration_secs <= 0.0 || args.sample_interval_secs <= 0.0 {
+        anyhow::bail!("--rate, --duration-secs and --sample-interval-secs must be positive");
+    }
+    let last_port = args.base_port as usize + PUBLISHER_PORT_OFFSET as usize + args.nodes;
+    if last_port > u16::MAX as usize {
+        anyhow::bail!("--base-port {} leaves no room for {} nodes", args.base_port, args.nodes);
+    }
+
+    let base = if args.config.exists() {
+        RacerConfig::from_file(&args.config)?
+    } else {
+        RacerConfig::default()
+    };
+    base.validate()?;
+
+    let nodes = spawn_cluster(&base, &args).await?;
+    let report = run(&nodes, &args).await;
+    for node in &nodes {
+        node.stop().await;
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Cluster: {} nodes", report.nodes);
+    println!();
+    println!("Messages:");
+    println!("  Submitted: {}", report.submitted);
+    println!("  Delivered: {}", report.delivered);
+    println!("  Failed: {}", report.failed);
+    println!("  Errors: {}", report.errors);
+    println!();
+    println!("Throughput: {:.2} msg/s over {:.1}s", report.throughput_per_sec, report.elapsed_secs);
+    println!();
+    println!("Delivery latency:");
+    println!("  p50: {:.3}s", report.latency_p50_secs);
+    println!("  p99: {:.3}s", report.latency_p99_secs);
+    println!("  max: {:.3}s", report.latency_max_secs);
+    println!();
+    println!("PLATO latency:");
+    println!("  Start: {:.3}s", report.plato.start_secs);
+    println!("  End: {:.3}s", report.plato.end_secs);
+    println!("  Range: {:.3}s - {:.3}s", report.plato.min_secs, report.plato.max_secs);
+
+    Ok(())
+}
+
+async fn spawn_cluster(base: &RacerConfig, args: &Args) -> anyhow::Result<Vec<Arc<Node<DefaultMessage>>>> {
+    let mut nodes = Vec::with_capacity(args.nodes);
+    for i in 0..args.nodes {
+        let port = args.base_port + i as u16;
+        let mut config = base.clone();
+        config.node.id = Some(format!("bench-{}", i));
+        config.node.router_bind = format!("tcp://127.0.0.1:{}", port);
+        config.node.publisher_bind = format!("tcp://127.0.0.1:{}", port + PUBLISHER_PORT_OFFSET);
+        config.node.websocket_bind = None;
+        config.node.admin_bind = None;
+        config.node.key_file = None;
+        config.node.store_path = None;
+        config.peers.routers.clear();
+        config.peers.pinned_keys.clear();
+        config.logging.enabled = false;
+
+        let node = Node::<DefaultMessage>::new(config).await?;
+        node.start().await?;
+        nodes.push(Arc::new(node));
+    }
+
+    for node in &nodes {
+        for (j, peer) in nodes.iter().enumerate() {
+            if peer.id() == node.id() {
+                continue;
+            }
+            let port = args.base_port + j as u16;
+            node.add_peer(PeerInfo::new(
+                peer.id(),
+                peer.public_key(),
+                format!("tcp://127.0.0.1:{}", port),
+                format!("tcp://127.0.0.1:{}", port + PUBLISHER_PORT_OFFSET),
+            ))
+            .await;
+        }
+    }
+    tokio::time::sleep(CONNECT_SETTLE).await;
+
+    Ok(nodes)
+}
+
+async fn run(nodes: &[Arc<Node<DefaultMessage>>], args: &Args) -> BenchReport {
+    let interval = Duration::from_secs_f64(1.0 / args.rate);
+    let duration = Duration::from_secs_f64(args.duration_secs);
+    let sample_interval = Duration::from_secs_f64(args.sample_interval_secs);
+
+    let samples = Arc::new(Mutex::new(vec![mean_plato_latency(nodes).await]));
+    let sampler = {
+        let nodes = nodes.to_vec();
+        let samples = Arc::clone(&samples);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(sample_interval).await;
+                let latency = mean_plato_latency(&nodes).await;
+                samples.lock().await.push(latency);
+            }
+        })
+    };
+
+    let started = Instant::now();
+    let mut rounds = JoinSet::new();
+    let mut ticker = tokio::time::interval(interval);
+    let mut submitted = 0usize;
+
+    while started.elapsed() < duration {
+        ticker.tick().await;
+        let node = Arc::clone(&nodes[submitted % nodes.len()]);
+        let message = DefaultMessage::with_padding(submitted as u64);
+        rounds.spawn(async move {
+            let sent = Instant::now();
+            match node.submit_and_wait(message).await {
+                Ok((_, true)) => Outcome::Delivered(sent.elapsed()),
+                Ok((_, false)) => Outcome::Failed,
+                Err(_) => Outcome::Error,
+            }
+        });
+        submitted += 1;
+    }
+
+    let mut latencies = Vec::with_capacity(submitted);
+    let (mut failed, mut errors) = (0, 0);
+    while let Some(result) = rounds.join_next().await {
+        match result {
+            Ok(Outcome::Delivered(latency)) => latencies.push(latency.as_secs_f64()),
+            Ok(Outcome::Failed) => failed += 1,
+            Ok(Outcome::Error) | Err(_) => errors += 1,
+        }
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    sampler.abort();
+    let mut samples = samples.lock().await.clone();
+    samples.push(mean_plato_latency(nodes).await);
+
+    latencies.sort_by(f64::total_cmp);
+    BenchReport {
+        nodes: nodes.len(),
+        submitted,
+        delivered: latencies.len(),
+        failed,
+        errors,
+        elapsed_secs: elapsed,
+        throughput_per_sec: latencies.len() as f64 / elapsed,
+        latency_p50_secs: percentile(&latencies, 0.50),
+        latency_p99_secs: percentile(&latencies, 0.99),
+        latency_max_secs: latencies.last().copied().unwrap_or(0.0),
+        plato: PlatoSummary {
+            start_secs: samples[0],
+            end_secs: samples[samples.len() - 1],
+            min_secs: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max_secs: samples.iter().copied().fold(0.0, f64::max),
+        },
+    }
+}
+
+async fn mean_plato_latency(nodes: &[Arc<Node<DefaultMessage>>]) -> f64 {
+    let mut total = 0.0;
+    for node in nodes {
+        total += node.plato_stats().await.current_latency;
+    }
+    total / nodes.len() as f64
+}
+
+/// Nearest-rank percentile of an ascending slice; 0 when empty.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -1,13 +1,25 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use racer_core::message::DefaultMessage;
+use racer_core::Message;
 
-use crate::config::RacerConfig;
+use super::bench::PUBLISHER_PORT_OFFSET;
+use crate::config::{ChannelConfig, ConfigError, NetworkProfile, RacerConfig};
+use crate::crypto::KeyPair;
+
+/// Admin API ports are offset from router ports by this much.
+const ADMIN_PORT_OFFSET: u16 = 2000;
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    #[arg(short, long)]
-    pub config: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(short, long, required = true)]
+    pub config: Option<PathBuf>,
 
     #[arg(long)]
     pub dump: bool,
@@ -16,28 +28,129 @@ pub struct Args {
     pub dump_toml: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Write a documented starter config.
+    Init(InitArgs),
+    /// Validate a config file and print warnings about risky settings.
+    Check(CheckArgs),
+    /// Write per-node configs and key files for a local test cluster.
+    GenCluster(GenClusterArgs),
+    /// Print the payload schema of the messages `racer run` nodes carry.
+    ExportSchema(ExportSchemaArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct InitArgs {
+    /// Network preset to start from.
+    #[arg(long, value_enum)]
+    pub profile: Option<NetworkProfile>,
+
+    #[arg(short, long, default_value = "racer.toml")]
+    pub output: PathBuf,
+
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct CheckArgs {
+    #[arg(short, long)]
+    pub config: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+pub struct GenClusterArgs {
+    #[arg(short, long, default_value_t = 10)]
+    pub nodes: usize,
+
+    /// Router port of the first node; publishers start at `base_port + 1000`
+    /// and admin APIs at `base_port + 2000`.
+    #[arg(long, default_value_t = 20000)]
+    pub base_port: u16,
+
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    #[arg(short, long, default_value = "cluster")]
+    pub out_dir: PathBuf,
+
+    /// Config whose consensus, PLATO and logging settings every node inherits.
+    #[arg(short, long)]
+    pub template: Option<PathBuf>,
+
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Default)]
+pub enum SchemaFormat {
+    /// JSON Schema (draft 2020-12).
+    #[default]
+    Json,
+    /// proto3 definition.
+    Proto,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportSchemaArgs {
+    #[arg(long, value_enum, default_value = "json")]
+    pub format: SchemaFormat,
+
+    /// Write to this file instead of stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
 pub fn execute(args: Args) -> anyhow::Result<()> {
-    let config = RacerConfig::from_file(&args.config)?;
-    config.validate()?;
+    match args.command {
+        Some(Command::Init(init_args)) => return init(init_args),
+        Some(Command::Check(check_args)) => return check(check_args),
+        Some(Command::GenCluster(gen)) => return gen_cluster(gen),
+        Some(Command::ExportSchema(export)) => return export_schema(export),
+        None => {}
+    }
+
+    let path = args.config.expect("clap enforces --config without a subcommand");
+    let config = load(&path)?;
 
     if args.dump {
         println!("{}", serde_json::to_string_pretty(&config)?);
     } else if args.dump_toml {
         println!("{}", toml::to_string_pretty(&config)?);
     } else {
-        println!("✓ Configuration valid: {}", args.config.display());
+        println!("✓ Configuration valid: {}", path.display());
+        if let Some(profile) = config.profile {
+            println!("  Profile: {:?}", profile);
+        }
         println!();
         println!("Node:");
         println!("  ID: {}", config.node.id.as_deref().unwrap_or("<auto>"));
         println!("  Router: {}", config.node.router_bind);
         println!("  Publisher: {}", config.node.publisher_bind);
         println!("  Selection: {:?}", config.node.selection_type);
+        println!("  Role: {:?}", config.node.role);
+        println!("  Encryption: {:?}", config.node.encryption);
         println!();
         println!("Consensus:");
         println!("  Echo sample: {}", config.consensus.echo_sample_size);
         println!("  Ready sample: {}", config.consensus.ready_sample_size);
-        println!("  Ready threshold: {}", config.consensus.ready_threshold);
-        println!("  Delivery threshold: {}", config.consensus.delivery_threshold);
+        match (config.consensus.ready_threshold_pct, config.consensus.delivery_threshold_pct) {
+            (Some(ready), Some(delivery)) => {
+                println!("  Ready threshold: {:.0}% of peers", ready * 100.0);
+                println!("  Delivery threshold: {:.0}% of peers", delivery * 100.0);
+            }
+            _ => {
+                println!("  Ready threshold: {}", config.consensus.ready_threshold);
+                println!("  Delivery threshold: {}", config.consensus.delivery_threshold);
+            }
+        }
+        for (name, channel) in &config.consensus.channels {
+            println!("  Channel {}: {}", name, describe_thresholds(channel));
+        }
+        for (priority, thresholds) in &config.consensus.priorities {
+            println!("  Priority {}: {}", priority.as_str(), describe_thresholds(thresholds));
+        }
         println!();
         println!("PLATO:");
         println!("  Target latency: {}s", config.plato.target_latency_secs);
@@ -50,3 +163,145 @@ pub fn execute(args: Args) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn describe_thresholds(config: &ChannelConfig) -> String {
+    match (config.ready_threshold_pct, config.delivery_threshold_pct) {
+        (Some(ready), Some(delivery)) => format!(
+            "ready {:.0}%, delivery {:.0}% of peers",
+            ready * 100.0,
+            delivery * 100.0
+        ),
+        _ => format!("ready {}, delivery {}", config.ready_threshold, config.delivery_threshold),
+    }
+}
+
+/// Loads `path`, listing every violation before failing on an invalid config.
+fn load(path: &Path) -> anyhow::Result<RacerConfig> {
+    match RacerConfig::from_file(path) {
+        Err(ConfigError::Validation(violations)) => {
+            println!("✗ Configuration invalid: {}", path.display());
+            for violation in violations.iter() {
+                println!("  {}", violation);
+            }
+            anyhow::bail!("{} invalid setting(s)", violations.len());
+        }
+        config => Ok(config?),
+    }
+}
+
+fn init(args: InitArgs) -> anyhow::Result<()> {
+    if args.output.exists() && !args.force {
+        anyhow::bail!(
+            "Output file already exists: {}. Use --force to overwrite.",
+            args.output.display()
+        );
+    }
+
+    let config = match args.profile {
+        Some(profile) => RacerConfig::profile(profile),
+        None => RacerConfig::minimal(),
+    };
+    fs::write(&args.output, config.to_toml_string()?)?;
+    println!("✓ Config written to {}", args.output.display());
+    println!("Check it with: racer config check -c {}", args.output.display());
+
+    Ok(())
+}
+
+fn check(args: CheckArgs) -> anyhow::Result<()> {
+    let config = load(&args.config)?;
+    let mut warnings: Vec<String> = config.warnings().iter().map(ToString::to_string).collect();
+    if let Some(key_file) = config.node.key_file.as_ref().filter(|path| !path.exists()) {
+        warnings.push(format!("node.key_file: {} does not exist", key_file.display()));
+    }
+
+    println!("✓ Configuration valid: {}", args.config.display());
+    if !warnings.is_empty() {
+        println!();
+        println!("Warnings:");
+        for warning in &warnings {
+            println!("  ! {}", warning);
+        }
+    }
+
+    Ok(())
+}
+
+fn export_schema(args: ExportSchemaArgs) -> anyhow::Result<()> {
+    let schema = DefaultMessage::schema();
+    let rendered = match args.format {
+        SchemaFormat::Json => serde_json::to_string_pretty(&schema)?,
+        SchemaFormat::Proto => racer_core::schema::to_proto(&schema),
+    };
+
+    match &args.output {
+        Some(path) => {
+            fs::write(path, rendered)?;
+            println!("✓ Schema written to {}", path.display());
+        }
+        None => println!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+fn gen_cluster(args: GenClusterArgs) -> anyhow::Result<()> {
+    if args.nodes < 2 {
+        anyhow::bail!("--nodes must be at least 2");
+    }
+    let last_port = args.base_port as usize + ADMIN_PORT_OFFSET as usize + args.nodes - 1;
+    if last_port > u16::MAX as usize {
+        anyhow::bail!("--base-port {} leaves no room for {} nodes", args.base_port, args.nodes);
+    }
+    if args.out_dir.exists() && !args.force {
+        anyhow::bail!(
+            "Output directory already exists: {}. Use --force to overwrite.",
+            args.out_dir.display()
+        );
+    }
+
+    let template = match &args.template {
+        Some(path) => RacerConfig::from_file(path)?,
+        None => RacerConfig::default(),
+    };
+
+    let keys: Vec<KeyPair> = (0..args.nodes).map(|_| KeyPair::generate()).collect();
+    let routers: Vec<String> = (0..args.nodes)
+        .map(|i| format!("tcp://{}:{}", args.host, args.base_port + i as u16))
+        .collect();
+
+    fs::create_dir_all(&args.out_dir)?;
+    for (i, key) in keys.iter().enumerate() {
+        let id = format!("node-{}", i);
+        let port = args.base_port + i as u16;
+        let key_file = format!("{}.key", id);
+
+        let mut config = template.clone();
+        config.node.id = Some(id.clone());
+        config.node.router_bind = routers[i].clone();
+        config.node.publisher_bind = format!("tcp://{}:{}", args.host, port + PUBLISHER_PORT_OFFSET);
+        // The admin API stays on loopback whatever host the cluster uses.
+        config.node.admin_bind = Some(format!("127.0.0.1:{}", port + ADMIN_PORT_OFFSET));
+        config.node.key_file = Some(key_file.clone().into());
+        let others: Vec<usize> = (0..args.nodes).filter(|&j| j != i).collect();
+        config.peers.routers = others.iter().map(|&j| routers[j].clone()).collect();
+        config.peers.pinned_keys = others
+            .iter()
+            .map(|&j| (routers[j].clone(), keys[j].public_key()))
+            .collect();
+        config.validate()?;
+
+        fs::write(args.out_dir.join(&key_file), hex::encode(key.signing_key().to_bytes()))?;
+        fs::write(args.out_dir.join(format!("{}.toml", id)), config.to_toml_string()?)?;
+        println!("✓ {} → {} ({})", id, config.node.router_bind, key.public_key().to_hex());
+    }
+
+    println!();
+    println!("Wrote {} node configs to {}", args.nodes, args.out_dir.display());
+    println!(
+        "Start a node with: racer run -c {}",
+        args.out_dir.join("node-0.toml").display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use crate::conformance::{self, Fixture};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Write the canonical signed fixtures, one JSON file each.
+    Emit(EmitArgs),
+    /// Check fixtures, e.g. ones written by another implementation.
+    Verify(VerifyArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct EmitArgs {
+    #[arg(short, long, default_value = "conformance")]
+    pub out: PathBuf,
+
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct VerifyArgs {
+    /// Fixture files, or directories whose `.json` files are all checked.
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+pub fn execute(args: Args) -> anyhow::Result<()> {
+    match args.command {
+        Command::Emit(args) => emit(args),
+        Command::Verify(args) => verify(args),
+    }
+}
+
+fn emit(args: EmitArgs) -> anyhow::Result<()> {
+    fs::create_dir_all(&args.out)?;
+    for fixture in conformance::fixtures() {
+        let path = args.out.join(fixture.file_name());
+        if path.exists() && !args.force {
+            anyhow::bail!(
+                "Fixture already exists: {}. Use --force to overwrite.",
+                path.display()
+            );
+        }
+        fs::write(&path, serde_json::to_string_pretty(&fixture)? + "\n")?;
+        println!("✓ {}", path.display());
+    }
+    Ok(())
+}
+
+fn fixture_files(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn check(path: &Path) -> anyhow::Result<String> {
+    let fixture: Fixture = serde_json::from_str(&fs::read_to_string(path)?)?;
+    conformance::verify(&fixture)?;
+    Ok(fixture.name)
+}
+
+fn verify(args: VerifyArgs) -> anyhow::Result<()> {
+    let files = fixture_files(&args.paths)?;
+    if files.is_empty() {
+        anyhow::bail!("No fixtures found");
+    }
+
+    let mut failed = 0;
+    for path in &files {
+        match check(path) {
+            Ok(name) => println!("✓ {} ({})", name, path.display()),
+            Err(e) => {
+                failed += 1;
+                println!("✗ {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} fixtures failed", failed, files.len());
+    }
+    println!();
+    println!("All {} fixtures verified", files.len());
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! `racer export` subcommand implementation.
+//!
+//! Dumps delivered batches from a running node's store to a `.rcr` file for
+//! carrying to a node with no link to this one; `racer import` loads it
+//! there. The file is JSON lines: an [`ArchiveHeader`], then one stored
+//! delivery (`{hash, delivered_at, batch}`) per line, oldest first. Batches
+//! keep their creator signatures, so the importing node can check them.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use super::admin::{self, AdminArgs};
+use crate::node::admin::{AdminRequest, AdminResponse};
+
+pub const ARCHIVE_FORMAT: &str = "racer-batches";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// First line of a `.rcr` file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveHeader {
+    pub format: String,
+    pub version: u32,
+    /// Epoch millis the export started from.
+    pub since: u64,
+    pub batches: usize,
+}
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    /// Export batches delivered at or after this time (epoch millis).
+    #[arg(long, default_value_t = 0)]
+    pub since: u64,
+
+    #[arg(long, default_value = "batches.rcr")]
+    pub out: PathBuf,
+
+    /// How long the node may take to read its store.
+    #[arg(long, default_value_t = 60.0)]
+    pub timeout_secs: f64,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    let request = AdminRequest::Export { since: args.since };
+    let timeout = admin::timeout(args.timeout_secs)?;
+    let AdminResponse::Exported { batches } = args.admin.request_with_timeout(request, timeout).await? else {
+        anyhow::bail!("unexpected response to export request");
+    };
+
+    let header = ArchiveHeader {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        since: args.since,
+        batches: batches.len(),
+    };
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&args.out)?);
+    writeln!(out, "{}", serde_json::to_string(&header)?)?;
+    for batch in &batches {
+        writeln!(out, "{}", serde_json::to_string(batch)?)?;
+    }
+    out.flush()?;
+
+    println!("✓ Exported {} batches to {}", batches.len(), args.out.display());
+    Ok(())
+}
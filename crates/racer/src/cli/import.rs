@@ -0,0 +1,99 @@
+//! `racer import` subcommand implementation.
+//!
+//! Loads a `.rcr` file written by `racer export` into a running node, which
+//! checks each batch's creator signature and merkle root and delivers the
+//! ones it has not delivered already.
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use super::admin::{self, AdminArgs};
+use super::export::{ArchiveHeader, ARCHIVE_FORMAT, ARCHIVE_VERSION};
+use crate::node::admin::{AdminRequest, AdminResponse};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    /// File written by `racer export`.
+    pub file: PathBuf,
+
+    /// How long the node may take to deliver the batches.
+    #[arg(long, default_value_t = 60.0)]
+    pub timeout_secs: f64,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    let batches = read_archive(&args.file)?;
+    let count = batches.len();
+
+    let request = AdminRequest::Import { batches };
+    let timeout = admin::timeout(args.timeout_secs)?;
+    let AdminResponse::Imported(report) = args.admin.request_with_timeout(request, timeout).await? else {
+        anyhow::bail!("unexpected response to import request");
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("Read {} batches from {}", count, args.file.display());
+        println!("  Imported: {}", report.imported);
+        println!("  Already delivered: {}", report.duplicates);
+        println!("  Rejected: {}", report.rejected);
+    }
+
+    if report.rejected > 0 {
+        anyhow::bail!("{} batches failed verification", report.rejected);
+    }
+    Ok(())
+}
+
+/// The signed batches in a `.rcr` file, in file order.
+pub fn read_archive(path: &Path) -> anyhow::Result<Vec<serde_json::Value>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut lines = file.lines();
+
+    let header: ArchiveHeader = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => anyhow::bail!("{} is empty", path.display()),
+    };
+    if header.format != ARCHIVE_FORMAT || header.version != ARCHIVE_VERSION {
+        anyhow::bail!(
+            "{} is not a {} v{} file (found {} v{})",
+            path.display(),
+            ARCHIVE_FORMAT,
+            ARCHIVE_VERSION,
+            header.format,
+            header.version
+        );
+    }
+
+    let mut batches = Vec::new();
+    for (idx, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record: serde_json::Value = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("{} line {}: {}", path.display(), idx + 2, e))?;
+        match record.get_mut("batch").map(serde_json::Value::take) {
+            Some(batch) => batches.push(batch),
+            None => anyhow::bail!("{} line {}: missing batch", path.display(), idx + 2),
+        }
+    }
+    if batches.len() != header.batches {
+        anyhow::bail!(
+            "{} holds {} batches but its header says {}",
+            path.display(),
+            batches.len(),
+            header.batches
+        );
+    }
+    Ok(batches)
+}
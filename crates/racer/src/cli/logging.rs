@@ -4,35 +4,69 @@ use std::path::PathBuf;
 use file_rotate::{
     compression::Compression,
     suffix::AppendCount,
-    ContentLimit, FileRotate,
+    ContentLimit, FileRotate, TimeFrequency,
 };
 use tracing_subscriber::{
     fmt::{self, MakeWriter},
     layer::SubscriberExt,
     util::SubscriberInitExt,
-    EnvFilter, Layer,
+    EnvFilter, Layer, Registry,
 };
 
+use crate::util::logging::{LogConfig, LogFormat, LogRotation};
+
 #[derive(Debug, Clone)]
 pub struct LoggingConfig {
     pub log_dir: PathBuf,
     pub max_size_mb: u64,
     pub max_files: usize,
+    pub rotation: LogRotation,
+}
+
+impl LoggingConfig {
+    fn rotating_writer(&self, file: &str) -> RotatingWriter {
+        let limit = match self.rotation {
+            LogRotation::Size => ContentLimit::Bytes((self.max_size_mb * 1024 * 1024) as usize),
+            LogRotation::Hourly => ContentLimit::Time(TimeFrequency::Hourly),
+            LogRotation::Daily => ContentLimit::Time(TimeFrequency::Daily),
+        };
+        RotatingWriter::new(FileRotate::new(
+            self.log_dir.join(file),
+            AppendCount::new(self.max_files),
+            limit,
+            Compression::None,
+            #[cfg(unix)]
+            None,
+        ))
+    }
 }
 
-fn make_rotating_writer(
-    path: PathBuf,
-    max_size_mb: u64,
-    max_files: usize,
-) -> FileRotate<AppendCount> {
-    FileRotate::new(
-        path,
-        AppendCount::new(max_files),
-        ContentLimit::Bytes((max_size_mb * 1024 * 1024) as usize),
-        Compression::None,
-        #[cfg(unix)]
-        None,
-    )
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// `level`/`levels` from the config, then `RUST_LOG`, which wins on overlap.
+fn level_filter(config: &LogConfig) -> anyhow::Result<EnvFilter> {
+    let mut filter = EnvFilter::try_new(config.directives())?;
+    if let Ok(env) = std::env::var(EnvFilter::DEFAULT_ENV) {
+        for directive in env.split(',').filter(|d| !d.trim().is_empty()) {
+            filter = filter.add_directive(directive.trim().parse()?);
+        }
+    }
+    Ok(filter)
+}
+
+fn format_layer<W>(format: LogFormat, writer: W, ansi: bool, filter: EnvFilter) -> BoxedLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_target(true)
+        .with_level(true);
+    match format {
+        LogFormat::Pretty => layer.with_filter(filter).boxed(),
+        LogFormat::Json => layer.json().with_filter(filter).boxed(),
+    }
 }
 
 struct RotatingWriter {
@@ -71,31 +105,29 @@ impl<'a> std::io::Write for RotatingWriterGuard<'a> {
     }
 }
 
-pub fn init_logging(config: LoggingConfig) -> anyhow::Result<()> {
+/// `settings` supplies the console format, levels and `file` sink, and the
+/// OTLP settings when the `telemetry` feature is on.
+pub fn init_logging(config: LoggingConfig, settings: &LogConfig) -> anyhow::Result<()> {
     fs::create_dir_all(&config.log_dir)?;
 
-    let messages_writer = RotatingWriter::new(make_rotating_writer(
-        config.log_dir.join("messages.jsonl"),
-        config.max_size_mb,
-        config.max_files,
-    ));
-
-    let events_writer = RotatingWriter::new(make_rotating_writer(
-        config.log_dir.join("events.jsonl"),
-        config.max_size_mb,
-        config.max_files,
-    ));
-
-    let protocol_writer = RotatingWriter::new(make_rotating_writer(
-        config.log_dir.join("protocol.jsonl"),
-        config.max_size_mb,
-        config.max_files,
-    ));
-
-    let console_layer = fmt::layer()
-        .with_target(true)
-        .with_level(true)
-        .with_filter(EnvFilter::from_default_env().add_directive("racer=info".parse()?));
+    let messages_writer = config.rotating_writer("messages.jsonl");
+    let events_writer = config.rotating_writer("events.jsonl");
+    let protocol_writer = config.rotating_writer("protocol.jsonl");
+
+    let mut sinks = vec![format_layer(
+        settings.format,
+        std::io::stdout,
+        settings.format == LogFormat::Pretty,
+        level_filter(settings)?,
+    )];
+    if let Some(file) = &settings.file {
+        sinks.push(format_layer(
+            settings.format,
+            config.rotating_writer(file),
+            false,
+            level_filter(settings)?,
+        ));
+    }
 
     let messages_layer = fmt::layer()
         .json()
@@ -112,17 +144,21 @@ pub fn init_logging(config: LoggingConfig) -> anyhow::Result<()> {
         .with_writer(protocol_writer)
         .with_filter(EnvFilter::new("racer::protocol::gossip=trace,racer::plato=debug"));
 
-    tracing_subscriber::registry()
-        .with(console_layer)
+    let registry = tracing_subscriber::registry()
+        .with(sinks)
         .with(messages_layer)
         .with(events_layer)
-        .with(protocol_layer)
-        .init();
+        .with(protocol_layer);
+    #[cfg(feature = "telemetry")]
+    let registry = registry.with(crate::telemetry::layer(settings)?);
+    registry.init();
 
     tracing::info!(
         log_dir = %config.log_dir.display(),
         max_size_mb = config.max_size_mb,
         max_files = config.max_files,
+        rotation = ?config.rotation,
+        format = ?settings.format,
         "Logging initialized"
     );
 
@@ -1,4 +1,16 @@
+pub mod admin;
+pub mod bench;
 pub mod config;
+pub mod conformance;
+pub mod export;
+pub mod import;
 pub mod keygen;
 pub mod logging;
+pub mod peers;
+pub mod plato_sim;
+pub mod quarantine;
 pub mod run;
+pub mod soak;
+pub mod status;
+pub mod submit;
+pub mod topo;
@@ -0,0 +1,61 @@
+//! `racer peers` subcommand implementation.
+
+use clap::Parser;
+
+use super::admin::AdminArgs;
+use crate::node::admin::{AdminRequest, AdminResponse};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    let AdminResponse::Peers { peers } = args.admin.request(AdminRequest::Peers).await? else {
+        anyhow::bail!("unexpected response to peers request");
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&peers)?);
+        return Ok(());
+    }
+
+    if peers.is_empty() {
+        println!("No known peers");
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<28} {:>10} {:>10} {:>9} {:>6}",
+        "ID", "ROUTER", "LATENCY", "LAST SEEN", "SKEW", "PINNED"
+    );
+    for peer in &peers {
+        let last_seen = peer
+            .last_seen_secs
+            .map(|secs| format!("{:.0}s ago", secs))
+            .unwrap_or_else(|| "never".into());
+        let skew = peer
+            .clock_skew_ms
+            .map(|ms| format!("{:+.1}s", ms as f64 / 1000.0))
+            .unwrap_or_else(|| "-".into());
+        let router = match &peer.relay {
+            Some(relay) => format!("via {}", relay),
+            None => peer.connected_router.clone().unwrap_or_else(|| peer.router_address.clone()),
+        };
+        println!(
+            "{:<16} {:<28} {:>9.3}s {:>10} {:>9} {:>6}",
+            peer.id,
+            router,
+            peer.latency_secs,
+            last_seen,
+            skew,
+            if peer.pinned { "yes" } else { "no" }
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! `racer plato-sim` subcommand implementation.
+//!
+//! Replays a recorded latency trace through PLATO with the `[plato]` section
+//! of a config and writes the resulting latency and publish frequency
+//! trajectory as CSV, so RSI and Savitzky-Golay settings can be tuned
+//! offline before they are deployed:
+//! ```bash
+//! racer plato-sim --trace latencies.csv --config racer.toml > trajectory.csv
+//! ```
+//! See [`crate::plato::sim`] for the trace format.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::config::RacerConfig;
+use crate::plato::sim::{self, SimStep};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// CSV with `time_secs` and `our_latency` columns, and optionally
+    /// `peer_latency` and `queue_depth`.
+    #[arg(short, long)]
+    pub trace: PathBuf,
+
+    #[arg(short, long, default_value = "racer.toml")]
+    pub config: PathBuf,
+
+    /// Trace seconds between congestion checks; defaults to
+    /// `plato.update_interval_secs`.
+    #[arg(long)]
+    pub tick_secs: Option<f64>,
+
+    /// Write the trajectory here instead of to stdout.
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+}
+
+pub fn execute(args: Args) -> anyhow::Result<()> {
+    let config = if args.config.exists() {
+        RacerConfig::from_file(&args.config)?
+    } else {
+        RacerConfig::default()
+    };
+    config.validate()?;
+
+    let tick_secs = args.tick_secs.unwrap_or(config.plato.update_interval_secs);
+    if !(tick_secs.is_finite() && tick_secs > 0.0) {
+        anyhow::bail!("--tick-secs must be positive (plato.update_interval_secs is {})", tick_secs);
+    }
+
+    let trace = std::fs::read_to_string(&args.trace)
+        .map_err(|e| anyhow::anyhow!("{}: {}", args.trace.display(), e))?;
+    let trace = sim::parse_trace(&trace).map_err(|e| anyhow::anyhow!("{}: {}", args.trace.display(), e))?;
+    let steps = sim::simulate(&config.plato, &trace, tick_secs);
+
+    let mut out: Box<dyn Write> = match &args.out {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    writeln!(out, "{}", SimStep::CSV_HEADER)?;
+    for step in &steps {
+        writeln!(out, "{}", step.to_csv_row())?;
+    }
+    out.flush()?;
+
+    if let (Some(path), Some(last)) = (&args.out, steps.last()) {
+        println!(
+            "✓ {} checks over {} samples written to {} (final latency {:.3}s, publish interval {:.3}s)",
+            steps.len(),
+            trace.len(),
+            path.display(),
+            last.current_latency,
+            last.publish_frequency
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! `racer quarantine` subcommand implementation.
+//!
+//! Shows the inbound frames a running node rejected, and writes them out as
+//! received for replaying against another implementation:
+//! ```bash
+//! racer quarantine list
+//! racer quarantine dump --out frames/ --clear
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use super::admin::AdminArgs;
+use crate::node::admin::{AdminRequest, AdminResponse};
+use crate::node::quarantine::QuarantinedFrame;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// One line per rejected frame: when, from where, and why.
+    List(ListArgs),
+    /// Write each rejected frame to its own file, byte for byte.
+    Dump(DumpArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct ListArgs {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct DumpArgs {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    #[arg(short, long, default_value = "quarantine")]
+    pub out: PathBuf,
+
+    /// Empty the node's quarantine once the frames are read.
+    #[arg(long)]
+    pub clear: bool,
+}
+
+async fn fetch(admin: &AdminArgs, clear: bool) -> anyhow::Result<Vec<QuarantinedFrame>> {
+    let AdminResponse::Quarantined { frames } = admin.request(AdminRequest::Quarantine { clear }).await? else {
+        anyhow::bail!("unexpected response to quarantine request");
+    };
+    Ok(frames)
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    match args.command {
+        Command::List(args) => list(args).await,
+        Command::Dump(args) => dump(args).await,
+    }
+}
+
+async fn list(args: ListArgs) -> anyhow::Result<()> {
+    let frames = fetch(&args.admin, false).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&frames)?);
+        return Ok(());
+    }
+
+    if frames.is_empty() {
+        println!("No quarantined frames");
+        return Ok(());
+    }
+
+    println!(
+        "{:>4} {:<15} {:<12} {:<20} {:>7}  REASON",
+        "#", "RECEIVED", "PEER", "SOURCE", "BYTES"
+    );
+    for (index, frame) in frames.iter().enumerate() {
+        println!(
+            "{:>4} {:<15} {:<12} {:<20} {:>7}  {}",
+            index,
+            frame.received_at,
+            frame.peer.as_deref().unwrap_or("-"),
+            frame.source,
+            frame.frame_bytes().len(),
+            frame.reason
+        );
+    }
+    Ok(())
+}
+
+async fn dump(args: DumpArgs) -> anyhow::Result<()> {
+    let frames = fetch(&args.admin, args.clear).await?;
+    fs::create_dir_all(&args.out)?;
+
+    for (index, frame) in frames.iter().enumerate() {
+        let path = args.out.join(format!("{:04}-{}.frame", index, frame.received_at));
+        fs::write(&path, frame.frame_bytes())?;
+        println!("✓ {} ({})", path.display(), frame.reason);
+    }
+    println!("{} frame(s) written to {}", frames.len(), args.out.display());
+    Ok(())
+}
@@ -34,14 +34,22 @@ pub struct Args {
     #[arg(long, env = "RACER_PEERS", value_delimiter = ',')]
     pub peers: Option<Vec<String>>,
 
+    #[arg(long, env = "RACER_ADMIN_BIND")]
+    pub admin_bind: Option<String>,
+
+    #[arg(long, env = "RACER_KEY_FILE")]
+    pub key_file: Option<PathBuf>,
+
     #[arg(long, default_value = "./logs")]
     pub log_dir: PathBuf,
 
-    #[arg(long, default_value = "100")]
-    pub log_max_size_mb: u64,
+    /// Overrides `logging.max_file_size_mb`.
+    #[arg(long)]
+    pub log_max_size_mb: Option<u64>,
 
-    #[arg(long, default_value = "10")]
-    pub log_max_files: usize,
+    /// Overrides `logging.max_files`.
+    #[arg(long)]
+    pub log_max_files: Option<usize>,
 }
 
 pub async fn execute(args: Args) -> anyhow::Result<()> {
@@ -67,15 +75,22 @@ pub async fn execute(args: Args) -> anyhow::Result<()> {
     if let Some(peers) = args.peers {
         config.peers.routers = peers;
     }
+    if let Some(admin) = args.admin_bind {
+        config.node.admin_bind = Some(admin);
+    }
+    if let Some(key_file) = args.key_file {
+        config.node.key_file = Some(key_file);
+    }
 
     config.validate()?;
 
     let log_config = logging::LoggingConfig {
         log_dir: args.log_dir,
-        max_size_mb: args.log_max_size_mb,
-        max_files: args.log_max_files,
+        max_size_mb: args.log_max_size_mb.unwrap_or(config.logging.max_file_size_mb),
+        max_files: args.log_max_files.unwrap_or(config.logging.max_files),
+        rotation: config.logging.rotation,
     };
-    logging::init_logging(log_config)?;
+    logging::init_logging(log_config, &config.logging)?;
 
     tracing::info!(
         node_id = ?config.node.id,
@@ -94,6 +109,8 @@ pub async fn execute(args: Args) -> anyhow::Result<()> {
 
     tracing::info!("Shutdown signal received");
     node.stop().await;
+    #[cfg(feature = "telemetry")]
+    crate::telemetry::shutdown();
 
     Ok(())
 }
@@ -0,0 +1,312 @@
+//! `racer soak` subcommand implementation.
+//!
+//! Runs an in-process [`Cluster`] for hours, submitting messages at a fixed
+//! rate, and every report interval prints a health report and checks the
+//! invariants a long-lived node must keep:
+//!
+//! - batches resolved in the interval were delivered on every node at least
+//!   `--min-delivery-ratio` of the time;
+//! - the gossip stores hold at most `--max-stored-mb` across the cluster, and
+//!   no node keeps more delivered batches than `consensus.retained_delivered`
+//!   allows;
+//! - no node has more open rounds or pending submissions than the cluster
+//!   can submit in `--stuck-after-secs`, which only happens when rounds get
+//!   stuck instead of delivering or timing out.
+//!
+//! The run stops with an error at the first report that breaks one, so
+//! leaks show up as a failure rather than a slow climb in a log. Ctrl-C ends
+//! the run early with a last report.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use serde::Serialize;
+use tokio::signal;
+
+use crate::config::RacerConfig;
+use crate::testing::Cluster;
+use racer_core::message::DefaultMessage;
+
+/// How often submitted batches are checked for delivery.
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[arg(short, long, default_value = "racer.toml")]
+    pub config: PathBuf,
+
+    #[arg(short, long, default_value_t = 8)]
+    pub nodes: usize,
+
+    #[arg(long, default_value_t = 24.0)]
+    pub hours: f64,
+
+    /// Messages per second across the whole cluster.
+    #[arg(long, default_value_t = 10.0)]
+    pub rate: f64,
+
+    #[arg(long, default_value_t = 60.0)]
+    pub report_interval_secs: f64,
+
+    /// A batch not delivered on every node this long after submission
+    /// counts as undelivered. Should exceed the gossip round timeout.
+    #[arg(long, default_value_t = 120.0)]
+    pub stuck_after_secs: f64,
+
+    #[arg(long, default_value_t = 0.99)]
+    pub min_delivery_ratio: f64,
+
+    /// Bound on the batches held by all gossip stores together.
+    #[arg(long, default_value_t = 256.0)]
+    pub max_stored_mb: f64,
+
+    /// Print each report as a line of JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The cluster's health at one report.
+#[derive(Debug, Serialize)]
+pub struct SoakReport {
+    pub elapsed_secs: f64,
+    pub submitted: usize,
+    pub delivered: usize,
+    pub undelivered: usize,
+    pub errors: usize,
+    /// Submitted batches neither delivered everywhere nor given up on yet.
+    pub pending: usize,
+    /// Share of the batches resolved since the last report that were
+    /// delivered everywhere; `None` if none were.
+    pub delivery_ratio: Option<f64>,
+    pub stored_batches: usize,
+    pub stored_bytes: usize,
+    pub evicted_batches: u64,
+    /// Highest count over the nodes.
+    pub max_active_rounds: usize,
+    pub max_pending_submissions: usize,
+    pub violations: Vec<String>,
+}
+
+/// Submission counts, for the whole run or since the last report.
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    submitted: usize,
+    delivered: usize,
+    undelivered: usize,
+    errors: usize,
+}
+
+/// What the harness allows, derived from the arguments and config.
+struct Bounds {
+    min_delivery_ratio: f64,
+    stored_bytes: usize,
+    delivered_batches: usize,
+    open_rounds: usize,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    if args.nodes < 2 {
+        anyhow::bail!("--nodes must be at least 2");
+    }
+    if args.hours <= 0.0 || args.rate <= 0.0 || args.report_interval_secs <= 0.0 || args.stuck_after_secs <= 0.0 {
+        anyhow::bail!("--hours, --rate, --report-interval-secs and --stuck-after-secs must be positive");
+    }
+    if !(0.0..=1.0).contains(&args.min_delivery_ratio) {
+        anyhow::bail!("--min-delivery-ratio must be between 0 and 1");
+    }
+
+    let base = if args.config.exists() {
+        RacerConfig::from_file(&args.config)?
+    } else {
+        RacerConfig::default()
+    };
+    base.validate()?;
+
+    let bounds = Bounds {
+        min_delivery_ratio: args.min_delivery_ratio,
+        stored_bytes: (args.max_stored_mb * 1024.0 * 1024.0) as usize,
+        delivered_batches: base.consensus.retained_delivered + base.node.gossip_shards,
+        open_rounds: (args.rate * args.stuck_after_secs).ceil() as usize,
+    };
+    let cluster = Cluster::<DefaultMessage>::new(args.nodes, base).await?;
+    let result = run(&cluster, &args, &bounds).await;
+    cluster.shutdown().await;
+    result
+}
+
+async fn run(cluster: &Cluster<DefaultMessage>, args: &Args, bounds: &Bounds) -> anyhow::Result<()> {
+    let duration = Duration::from_secs_f64(args.hours * 3600.0);
+    let stuck_after = Duration::from_secs_f64(args.stuck_after_secs);
+    let started = Instant::now();
+
+    let mut submit = tokio::time::interval(Duration::from_secs_f64(1.0 / args.rate));
+    let mut resolve = tokio::time::interval(RESOLVE_INTERVAL);
+    let mut report = tokio::time::interval(Duration::from_secs_f64(args.report_interval_secs));
+    report.tick().await;
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    let mut pending: VecDeque<(String, Instant)> = VecDeque::new();
+    let (mut total, mut window) = (Tally::default(), Tally::default());
+
+    loop {
+        tokio::select! {
+            _ = submit.tick() => {
+                let index = total.submitted % cluster.len();
+                let message = DefaultMessage::with_padding(total.submitted as u64);
+                total.submitted += 1;
+                window.submitted += 1;
+                match cluster.submit(index, message).await {
+                    Ok(batch_id) => pending.push_back((batch_id, Instant::now())),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "soak submission failed");
+                        total.errors += 1;
+                        window.errors += 1;
+                    }
+                }
+            }
+            _ = resolve.tick() => {
+                pending.retain(|(batch_id, submitted_at)| {
+                    let delivered = (0..cluster.len()).all(|i| cluster.delivered(i, batch_id));
+                    if !delivered && submitted_at.elapsed() < stuck_after {
+                        return true;
+                    }
+                    for tally in [&mut total, &mut window] {
+                        if delivered {
+                            tally.delivered += 1;
+                        } else {
+                            tally.undelivered += 1;
+                        }
+                    }
+                    cluster.forget(batch_id);
+                    false
+                });
+            }
+            _ = report.tick() => {
+                let health = snapshot(cluster, started, total, window, pending.len(), bounds).await;
+                window = Tally::default();
+                print_report(&health, args.json)?;
+                if !health.violations.is_empty() {
+                    anyhow::bail!("soak invariants broken: {}", health.violations.join("; "));
+                }
+                if started.elapsed() >= duration {
+                    return Ok(());
+                }
+            }
+            result = &mut ctrl_c => {
+                result?;
+                let health = snapshot(cluster, started, total, window, pending.len(), bounds).await;
+                return print_report(&health, args.json);
+            }
+        }
+    }
+}
+
+async fn snapshot(
+    cluster: &Cluster<DefaultMessage>,
+    started: Instant,
+    total: Tally,
+    window: Tally,
+    pending: usize,
+    bounds: &Bounds,
+) -> SoakReport {
+    let resolved = window.delivered + window.undelivered;
+    let mut report = SoakReport {
+        elapsed_secs: started.elapsed().as_secs_f64(),
+        submitted: total.submitted,
+        delivered: total.delivered,
+        undelivered: total.undelivered,
+        errors: total.errors,
+        pending,
+        delivery_ratio: (resolved > 0).then(|| window.delivered as f64 / resolved as f64),
+        stored_batches: 0,
+        stored_bytes: 0,
+        evicted_batches: 0,
+        max_active_rounds: 0,
+        max_pending_submissions: 0,
+        violations: Vec::new(),
+    };
+
+    for node in cluster.nodes() {
+        let stats = node.gossip_stats().await;
+        let pending_submissions = node.pending_submissions().len();
+        report.stored_batches += stats.store.batches;
+        report.stored_bytes += stats.store.bytes;
+        report.evicted_batches += stats.store.evicted;
+        report.max_active_rounds = report.max_active_rounds.max(stats.active_rounds);
+        report.max_pending_submissions = report.max_pending_submissions.max(pending_submissions);
+
+        if stats.store.delivered > bounds.delivered_batches {
+            report.violations.push(format!(
+                "{} holds {} delivered batches, over {}",
+                node.id(),
+                stats.store.delivered,
+                bounds.delivered_batches
+            ));
+        }
+        if stats.active_rounds > bounds.open_rounds {
+            report.violations.push(format!(
+                "{} has {} open rounds, over {}",
+                node.id(),
+                stats.active_rounds,
+                bounds.open_rounds
+            ));
+        }
+        if pending_submissions > bounds.open_rounds {
+            report.violations.push(format!(
+                "{} has {} pending submissions, over {}",
+                node.id(),
+                pending_submissions,
+                bounds.open_rounds
+            ));
+        }
+    }
+
+    if report.stored_bytes > bounds.stored_bytes {
+        report.violations.push(format!(
+            "gossip stores hold {} bytes, over {}",
+            report.stored_bytes, bounds.stored_bytes
+        ));
+    }
+    if let Some(ratio) = report.delivery_ratio {
+        if ratio < bounds.min_delivery_ratio {
+            report.violations.push(format!(
+                "delivery ratio {:.4} under {}",
+                ratio, bounds.min_delivery_ratio
+            ));
+        }
+    }
+    report
+}
+
+fn print_report(report: &SoakReport, json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(report)?);
+        return Ok(());
+    }
+
+    println!(
+        "[{:.0}s] submitted {} delivered {} undelivered {} errors {} pending {}",
+        report.elapsed_secs, report.submitted, report.delivered, report.undelivered, report.errors, report.pending
+    );
+    match report.delivery_ratio {
+        Some(ratio) => println!("  Delivery ratio: {:.4}", ratio),
+        None => println!("  Delivery ratio: -"),
+    }
+    println!(
+        "  Stored: {} batches, {:.1} KiB ({} evicted)",
+        report.stored_batches,
+        report.stored_bytes as f64 / 1024.0,
+        report.evicted_batches
+    );
+    println!(
+        "  Busiest node: {} open rounds, {} pending submissions",
+        report.max_active_rounds, report.max_pending_submissions
+    );
+    for violation in &report.violations {
+        println!("  VIOLATION: {}", violation);
+    }
+    Ok(())
+}
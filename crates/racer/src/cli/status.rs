@@ -0,0 +1,57 @@
+//! `racer status` subcommand implementation.
+
+use clap::Parser;
+
+use super::admin::AdminArgs;
+use crate::node::admin::{AdminRequest, AdminResponse};
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    let AdminResponse::Status(status) = args.admin.request(AdminRequest::Status).await? else {
+        anyhow::bail!("unexpected response to status request");
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    println!("Node: {}", status.id);
+    println!("  Public key: {}", status.public_key);
+    println!("  Running: {}", status.running);
+    println!("  Uptime: {:.0}s", status.uptime_secs);
+    println!("  Active rounds: {}", status.active_rounds);
+    println!("  Known peers: {}", status.known_peers);
+    println!("  PLATO latency: {:.3}s", status.plato_latency_secs);
+    println!();
+    println!("Pipeline:");
+    println!("  Received: {}", status.pipeline.received);
+    println!("  Rejected: {}", status.pipeline.rejected);
+    println!("  Duplicates: {}", status.pipeline.duplicates);
+    println!("  Delivered: {}", status.pipeline.delivered);
+    if status.pipeline.app_duplicates > 0 {
+        println!("  Withheld as app duplicates: {}", status.pipeline.app_duplicates);
+    }
+    println!();
+    println!("Signature cache:");
+    println!(
+        "  Signatures: {:.1}% hits ({} cached)",
+        status.verify_cache.signature_hit_rate() * 100.0,
+        status.verify_cache.cached_signatures
+    );
+    println!(
+        "  Public keys: {:.1}% hits ({} cached)",
+        status.verify_cache.key_hit_rate() * 100.0,
+        status.verify_cache.cached_keys
+    );
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! `racer submit` subcommand implementation.
+//!
+//! The payload is submitted to a running node through its admin API. With
+//! `--dry-run` the batch is built and measured locally instead, and nothing is
+//! sent.
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use super::admin::AdminArgs;
+use crate::config::RacerConfig;
+use crate::node::admin::{self, AdminRequest, AdminResponse};
+use crate::node::Node;
+use racer_core::message::DefaultMessage;
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    #[arg(long, conflicts_with = "stdin")]
+    pub file: Option<PathBuf>,
+
+    #[arg(long)]
+    pub stdin: bool,
+
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Wait for the round to finish and report whether it was delivered.
+    #[arg(long, conflicts_with = "dry_run")]
+    pub wait: bool,
+
+    /// How long `--wait` may take before giving up.
+    #[arg(long, default_value_t = 30.0)]
+    pub timeout_secs: f64,
+
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    let payload = match (&args.file, args.stdin) {
+        (Some(path), _) => std::fs::read_to_string(path)?,
+        (None, true) => {
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        }
+        (None, false) => anyhow::bail!("Provide a payload with --file or --stdin"),
+    };
+    let message: DefaultMessage = serde_json::from_str(&payload)?;
+
+    if args.dry_run {
+        dry_run(&args, message).await
+    } else {
+        submit(&args, message).await
+    }
+}
+
+async fn submit(args: &Args, message: DefaultMessage) -> anyhow::Result<()> {
+    let request = AdminRequest::Submit {
+        message: serde_json::to_value(&message)?,
+        wait: args.wait,
+    };
+    let timeout = if args.wait {
+        super::admin::timeout(args.timeout_secs)?
+    } else {
+        admin::ADMIN_TIMEOUT
+    };
+
+    let response = admin::request_with_timeout(&args.admin.address()?, &request, timeout).await?;
+    let (batch_id, delivered) = match response {
+        AdminResponse::Submitted { batch_id, delivered } => (batch_id, delivered),
+        AdminResponse::Error { message } => anyhow::bail!("node returned an error: {}", message),
+        _ => anyhow::bail!("unexpected response to submit request"),
+    };
+
+    if args.json {
+        let report = serde_json::json!({ "batch_id": batch_id, "delivered": delivered });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("Batch ID: {}", batch_id);
+        match delivered {
+            Some(true) => println!("✓ Delivered"),
+            Some(false) => println!("✗ Not delivered"),
+            None => {}
+        }
+    }
+
+    if delivered == Some(false) {
+        anyhow::bail!("batch {} was not delivered", batch_id);
+    }
+    Ok(())
+}
+
+async fn dry_run(args: &Args, message: DefaultMessage) -> anyhow::Result<()> {
+    let config = if args.admin.config.exists() {
+        RacerConfig::from_file(&args.admin.config)?
+    } else {
+        RacerConfig::default()
+    };
+    config.validate()?;
+
+    let node = Node::<DefaultMessage>::new(config).await?;
+    let report = node.dry_run(message).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    match &report.validation_error {
+        None => println!("✓ Message valid"),
+        Some(err) => println!("✗ Message invalid: {}", err),
+    }
+    println!();
+    println!("Batch:");
+    println!("  ID: {}", report.batch_id);
+    println!("  Hash: {}", report.batch_hash);
+    println!("  Merkle root: {}", report.merkle_root);
+    println!();
+    println!("Wire size (JSON):");
+    println!("  Message: {} bytes", report.message_size);
+    println!("  Envelope: {} bytes", report.envelope_size);
+    println!();
+    println!("Round estimate:");
+    println!("  Known peers: {}", report.known_peers);
+    println!("  Echo / ready peers: {} / {}", report.echo_peers, report.ready_peers);
+    println!("  Phase timeout: {:.2}s", report.phase_timeout_secs);
+    println!("  Round timeout: {:.2}s", report.estimated_round_timeout_secs);
+
+    if !report.valid {
+        anyhow::bail!("dry run failed validation");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! `racer topo` subcommand implementation.
+//!
+//! Prints the latency graph a running node knows of, as Graphviz DOT or JSON:
+//! ```bash
+//! racer topo | dot -Tsvg > cluster.svg
+//! ```
+
+use clap::{Parser, ValueEnum};
+
+use super::admin::AdminArgs;
+use crate::node::admin::{AdminRequest, AdminResponse};
+
+#[derive(Debug, Clone, Copy, ValueEnum, Default)]
+pub enum TopoFormat {
+    /// Graphviz DOT.
+    #[default]
+    Dot,
+    /// The report as the admin API returns it.
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct Args {
+    #[command(flatten)]
+    pub admin: AdminArgs,
+
+    #[arg(long, value_enum, default_value = "dot")]
+    pub format: TopoFormat,
+}
+
+pub async fn execute(args: Args) -> anyhow::Result<()> {
+    let AdminResponse::Topology(report) = args.admin.request(AdminRequest::Topology).await? else {
+        anyhow::bail!("unexpected response to topology request");
+    };
+
+    match args.format {
+        TopoFormat::Dot => print!("{}", report.to_dot()),
+        TopoFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
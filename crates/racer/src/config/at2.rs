@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-use super::ConfigError;
+use super::{ConfigError, Violations};
+use crate::protocol::Priority;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct At2Config {
@@ -16,8 +20,207 @@ pub struct At2Config {
     pub feedback_threshold: usize,
     #[serde(default = "default_delivery_threshold")]
     pub delivery_threshold: usize,
+    /// Fraction of known peers, replacing `ready_threshold` when set.
+    /// Resolved against the peer registry when each round starts, and
+    /// capped at `echo_sample_size`.
+    #[serde(default)]
+    pub ready_threshold_pct: Option<f64>,
+    /// Fraction of known peers, replacing `feedback_threshold` when set.
+    #[serde(default)]
+    pub feedback_threshold_pct: Option<f64>,
+    /// Fraction of known peers, replacing `delivery_threshold` when set.
+    #[serde(default)]
+    pub delivery_threshold_pct: Option<f64>,
+    #[serde(default = "default_health_interval")]
+    pub health_interval_secs: u64,
+    #[serde(default = "default_health_window")]
+    pub health_window_secs: u64,
+    /// Most entries kept in the vector clock carried by every batch; peers
+    /// with the lowest counters are dropped first. 0 disables the bound.
+    #[serde(default = "default_vector_clock_max_entries")]
+    pub vector_clock_max_entries: usize,
+    /// Drops vector clock entries that have not advanced for this long.
+    #[serde(default)]
+    pub vector_clock_max_age_secs: Option<u64>,
+    /// How long delivered batches wait before `subscribe_ordered` releases
+    /// them; must exceed delivery latency plus clock skew between creators.
+    #[serde(default = "default_sequencing_stability")]
+    pub sequencing_stability_secs: f64,
+    /// Length of a membership epoch; join/leave updates take effect at the
+    /// first epoch boundary after their batch was created.
+    #[serde(default = "default_membership_epoch")]
+    pub membership_epoch_secs: u64,
+    /// Let any key join through a membership update. Otherwise only keys in
+    /// `allowed_creators` may join, and none if it is empty.
+    #[serde(default)]
+    pub open_membership: bool,
+    /// Thresholds for named channels (`Node::submit_on`), replacing the ones
+    /// above for batches on that channel. Unlisted channels use the above.
+    #[serde(default)]
+    pub channels: BTreeMap<String, ChannelConfig>,
+    /// Thresholds for batches of a priority, as `[consensus.priorities.high]`.
+    /// A batch's channel thresholds take precedence over these.
+    #[serde(default)]
+    pub priorities: BTreeMap<Priority, ChannelConfig>,
+    /// Batch hashes remembered per generation of the duplicate-suppression
+    /// filter; late copies of these batches are not gossiped again.
+    #[serde(default = "default_seen_filter_capacity")]
+    pub seen_filter_capacity: usize,
+    /// Target false positive rate of that filter. A false positive drops a
+    /// new batch as a duplicate.
+    #[serde(default = "default_seen_filter_fp_rate")]
+    pub seen_filter_fp_rate: f64,
+    /// Delivered rounds remembered for duplicate checks, with their batches;
+    /// the oldest are dropped beyond this.
+    #[serde(default = "default_retained_delivered")]
+    pub retained_delivered: usize,
+    /// How long a delivered batch is held for late requests and re-gossip
+    /// after delivery. 0 drops it at the next maintenance pass.
+    #[serde(default = "default_delivered_retention")]
+    pub delivered_retention_secs: u64,
+    /// How long the batch of a round that timed out undelivered is held.
+    /// 0 drops it with the round.
+    #[serde(default)]
+    pub timed_out_retention_secs: u64,
+    /// Age of a relayed batch at which its re-gossip echo/ready samples are
+    /// halved, as older batches have likely reached most peers. 0 disables.
+    #[serde(default = "default_regossip_half_life")]
+    pub regossip_half_life_secs: f64,
+    /// Smallest re-gossip sample the decay shrinks to.
+    #[serde(default = "default_regossip_min_fanout")]
+    pub regossip_min_fanout: usize,
+    /// Relays a batch created here may pass through before nodes stop
+    /// re-gossiping it. 0 disables the limit.
+    #[serde(default = "default_max_hops")]
+    pub max_hops: u32,
+    /// How long after creation a batch created here is still processed;
+    /// older copies are dropped on arrival. Receivers measure the age on
+    /// the creator's clock, corrected by its estimated skew when it is a
+    /// known peer. 0 disables the limit.
+    #[serde(default = "default_batch_ttl_ms")]
+    pub batch_ttl_ms: u64,
+    /// Known peers needed before local submissions are accepted; with
+    /// fewer, they fail at once with `NodeError::InsufficientPeers` rather
+    /// than time out. 0 disables the check.
+    #[serde(default)]
+    pub min_peers_for_submit: usize,
+    /// How long a delivered message's application key (by default its
+    /// `Message::id`) is remembered; later messages with the same key are
+    /// not delivered again. 0 disables the check.
+    #[serde(default)]
+    pub dedup_window_secs: u64,
+    /// How long the validator set with `Node::set_validator` may take to
+    /// judge a received batch before it is rejected. 0 waits indefinitely.
+    #[serde(default = "default_validation_timeout_ms")]
+    pub validation_timeout_ms: u64,
+    /// Largest batch accepted, in bytes of its messages serialized: larger
+    /// ones are rejected on arrival, and submitting one fails. 0 disables
+    /// the limit.
+    #[serde(default = "default_max_batch_bytes")]
+    pub max_batch_bytes: usize,
+    /// Most messages a received batch may carry. 0 disables the limit.
+    #[serde(default = "default_max_messages_per_batch")]
+    pub max_messages_per_batch: usize,
+    /// Keys whose batches this node echoes: hex public keys, or hex
+    /// prefixes ending in `*`. Empty lets any key create batches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_creators: Vec<String>,
+    /// Tell the creator of each batch delivered here how long it took, for
+    /// its `Node::delivery_reports`.
+    #[serde(default)]
+    pub delivery_reports: bool,
+}
+
+/// Thresholds of one consensus channel, as `[consensus.channels.<name>]`,
+/// or of one priority, as `[consensus.priorities.<priority>]`. Like the
+/// `[consensus]` ones, they are counts or, with all three `_pct` keys set,
+/// fractions of known peers.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    #[serde(default)]
+    pub ready_threshold: usize,
+    #[serde(default)]
+    pub feedback_threshold: usize,
+    #[serde(default)]
+    pub delivery_threshold: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_threshold_pct: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback_threshold_pct: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery_threshold_pct: Option<f64>,
+}
+
+impl ChannelConfig {
+    /// Absolute thresholds.
+    pub fn counts(ready: usize, feedback: usize, delivery: usize) -> Self {
+        Self {
+            ready_threshold: ready,
+            feedback_threshold: feedback,
+            delivery_threshold: delivery,
+            ..Default::default()
+        }
+    }
+
+    /// Thresholds as fractions of known peers.
+    pub fn fractions(ready: f64, feedback: f64, delivery: f64) -> Self {
+        Self {
+            ready_threshold_pct: Some(ready),
+            feedback_threshold_pct: Some(feedback),
+            delivery_threshold_pct: Some(delivery),
+            ..Default::default()
+        }
+    }
+
+    fn pcts(&self) -> [Option<f64>; 3] {
+        [self.ready_threshold_pct, self.feedback_threshold_pct, self.delivery_threshold_pct]
+    }
+
+    /// The absolute thresholds, ignoring any percentages.
+    pub fn absolute_thresholds(&self) -> Thresholds {
+        Thresholds {
+            ready: self.ready_threshold,
+            feedback: self.feedback_threshold,
+            delivery: self.delivery_threshold,
+        }
+    }
+
+    /// Thresholds among `peer_count` known peers, before
+    /// [`At2Config`] caps them at its sample sizes.
+    pub fn thresholds(&self, peer_count: usize) -> Thresholds {
+        resolve(self.pcts(), self.absolute_thresholds(), peer_count)
+    }
 }
 
+/// Response counts a gossip round needs to advance, fixed when it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thresholds {
+    pub ready: usize,
+    pub feedback: usize,
+    pub delivery: usize,
+}
+
+/// Each percentage in `pcts` becomes `ceil(pct * peer_count)`, at least 1;
+/// the rest stay as in `absolute`.
+fn resolve(pcts: [Option<f64>; 3], absolute: Thresholds, peer_count: usize) -> Thresholds {
+    let resolve = |pct: Option<f64>, absolute: usize| match pct {
+        Some(pct) => ((pct * peer_count as f64).ceil() as usize).max(1),
+        None => absolute,
+    };
+    Thresholds {
+        ready: resolve(pcts[0], absolute.ready),
+        feedback: resolve(pcts[1], absolute.feedback),
+        delivery: resolve(pcts[2], absolute.delivery),
+    }
+}
+
+/// Absolute threshold keys and the percentage keys that replace them.
+const THRESHOLD_FORMS: [(&str, &str); 3] = [
+    ("ready_threshold", "ready_threshold_pct"),
+    ("feedback_threshold", "feedback_threshold_pct"),
+    ("delivery_threshold", "delivery_threshold_pct"),
+];
+
 fn default_sample_size() -> usize {
     6
 }
@@ -34,55 +237,347 @@ fn default_delivery_threshold() -> usize {
     6
 }
 
+fn default_health_interval() -> u64 {
+    30
+}
+
+fn default_health_window() -> u64 {
+    300
+}
+
+fn default_vector_clock_max_entries() -> usize {
+    256
+}
+
+fn default_sequencing_stability() -> f64 {
+    10.0
+}
+
+fn default_membership_epoch() -> u64 {
+    60
+}
+
+fn default_seen_filter_capacity() -> usize {
+    crate::protocol::seen::DEFAULT_SEEN_CAPACITY
+}
+
+fn default_seen_filter_fp_rate() -> f64 {
+    crate::protocol::seen::DEFAULT_SEEN_FP_RATE
+}
+
+fn default_retained_delivered() -> usize {
+    crate::protocol::gossip::DEFAULT_MAX_DELIVERED
+}
+
+fn default_delivered_retention() -> u64 {
+    crate::protocol::gossip::DEFAULT_DELIVERED_RETENTION.as_secs()
+}
+
+fn default_regossip_half_life() -> f64 {
+    5.0
+}
+
+fn default_regossip_min_fanout() -> usize {
+    2
+}
+
+fn default_max_hops() -> u32 {
+    16
+}
+
+fn default_batch_ttl_ms() -> u64 {
+    300_000
+}
+
+fn default_validation_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_max_batch_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_max_messages_per_batch() -> usize {
+    1024
+}
+
 impl At2Config {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        if !(self.ready_threshold < self.feedback_threshold
-            && self.feedback_threshold < self.delivery_threshold)
-        {
-            return Err(ConfigError::Validation(
-                "thresholds must satisfy: ready < feedback < delivery".into(),
-            ));
+        self.violations().into_result()
+    }
+
+    /// Every invalid setting, with paths relative to `[consensus]`.
+    pub(super) fn violations(&self) -> Violations {
+        let mut v = Violations::new();
+
+        v.check(
+            self.sequencing_stability_secs.is_finite() && self.sequencing_stability_secs >= 0.0,
+            "sequencing_stability_secs",
+            || "must be a non-negative number".into(),
+        );
+        v.check(self.membership_epoch_secs > 0, "membership_epoch_secs", || "must be > 0".into());
+        v.check(self.seen_filter_capacity > 0, "seen_filter_capacity", || "must be > 0".into());
+        v.check(
+            self.seen_filter_fp_rate > 0.0 && self.seen_filter_fp_rate < 1.0,
+            "seen_filter_fp_rate",
+            || "must be in (0, 1)".into(),
+        );
+        v.check(self.retained_delivered > 0, "retained_delivered", || "must be > 0".into());
+        v.check(
+            self.regossip_half_life_secs.is_finite() && self.regossip_half_life_secs >= 0.0,
+            "regossip_half_life_secs",
+            || "must be a non-negative number".into(),
+        );
+
+        for (name, sample) in [
+            ("echo_sample_size", self.echo_sample_size),
+            ("ready_sample_size", self.ready_sample_size),
+            ("delivery_sample_size", self.delivery_sample_size),
+        ] {
+            v.check(sample > 0, name, || "must be > 0".into());
+        }
+
+        for entry in &self.allowed_creators {
+            let valid = match entry.strip_suffix('*') {
+                Some(prefix) => !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_hexdigit()),
+                None => crate::crypto::PublicKey::from_hex(entry).is_ok(),
+            };
+            v.check(valid, "allowed_creators", || {
+                format!("{:?} is neither a hex public key nor a hex prefix ending in *", entry)
+            });
         }
 
+        for (name, channel) in &self.channels {
+            if name.is_empty() {
+                v.push("channels", "channel names must not be empty");
+                continue;
+            }
+            self.check_override(&mut v, &format!("channels.{}.", name), channel);
+        }
+        for (priority, thresholds) in &self.priorities {
+            self.check_override(&mut v, &format!("priorities.{}.", priority.as_str()), thresholds);
+        }
+
+        let pcts = [self.ready_threshold_pct, self.feedback_threshold_pct, self.delivery_threshold_pct];
+        if Self::check_pcts(&mut v, "", pcts) {
+            self.check_absolute_thresholds(&mut v);
+        }
+        v
+    }
+
+    /// Checks percentages set for all three thresholds or none, in range and
+    /// ordered. Returns whether none are set, leaving the counts to check.
+    fn check_pcts(v: &mut Violations, prefix: &str, pcts: [Option<f64>; 3]) -> bool {
+        for ((_, key), pct) in THRESHOLD_FORMS.iter().zip(pcts) {
+            if let Some(pct) = pct {
+                v.check(pct > 0.0 && pct <= 1.0, &format!("{}{}", prefix, key), || {
+                    format!("{} is not in (0, 1]", pct)
+                });
+            }
+        }
+        match pcts {
+            [None, None, None] => return true,
+            [Some(ready), Some(feedback), Some(delivery)] => {
+                v.check(ready < feedback, &format!("{}feedback_threshold_pct", prefix), || {
+                    format!("{} must be > ready_threshold_pct ({})", feedback, ready)
+                });
+                v.check(feedback < delivery, &format!("{}delivery_threshold_pct", prefix), || {
+                    format!("{} must be > feedback_threshold_pct ({})", delivery, feedback)
+                });
+            }
+            _ => {
+                for ((_, key), pct) in THRESHOLD_FORMS.iter().zip(pcts) {
+                    if pct.is_none() {
+                        v.push(
+                            format!("{}{}", prefix, key),
+                            "set all of ready/feedback/delivery_threshold_pct or none of them",
+                        );
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// A channel or priority override: fractions, or counts that are
+    /// ordered and reachable within the samples.
+    fn check_override(&self, v: &mut Violations, prefix: &str, config: &ChannelConfig) {
+        if !Self::check_pcts(v, prefix, config.pcts()) {
+            return;
+        }
+        let thresholds = config.absolute_thresholds();
+        let ready = format!("{}ready_threshold", prefix);
+        v.check(thresholds.ready > 0, &ready, || "must be > 0".into());
+        self.check_ordering(v, prefix, thresholds);
+        self.check_within_samples(v, prefix, thresholds);
+    }
+
+    /// A `[consensus]` table that sets a threshold both as a count and as a
+    /// percentage, which `validate` cannot see once defaults are filled in.
+    pub(super) fn check_threshold_forms(table: &toml::Table) -> Violations {
+        let mut v = Violations::new();
+        for (absolute, pct) in THRESHOLD_FORMS {
+            v.check(!(table.contains_key(absolute) && table.contains_key(pct)), pct, || {
+                format!("{} is also set; use one", absolute)
+            });
+        }
+        v
+    }
+
+    /// Thresholds for a round among `peer_count` known peers: each percentage
+    /// becomes `ceil(pct * peer_count)`, at least 1 and at most the sample
+    /// its responses come from.
+    pub fn thresholds(&self, peer_count: usize) -> Thresholds {
+        let pcts = [self.ready_threshold_pct, self.feedback_threshold_pct, self.delivery_threshold_pct];
+        self.within_samples(resolve(pcts, self.absolute_thresholds(), peer_count))
+    }
+
+    /// `thresholds` capped at their samples. A fraction of many peers would
+    /// otherwise ask for more responses than a sample can give.
+    fn within_samples(&self, thresholds: Thresholds) -> Thresholds {
+        Thresholds {
+            ready: thresholds.ready.min(self.echo_sample_size),
+            feedback: thresholds.feedback.min(self.ready_sample_size),
+            delivery: thresholds.delivery.min(self.delivery_sample_size),
+        }
+    }
+
+    /// Thresholds for a round on `channel` among `peer_count` known peers:
+    /// the channel's own if configured, otherwise [`thresholds`](Self::thresholds).
+    pub fn channel_thresholds(&self, channel: Option<&str>, peer_count: usize) -> Thresholds {
+        match channel.and_then(|name| self.channels.get(name)) {
+            Some(channel) => self.within_samples(channel.thresholds(peer_count)),
+            None => self.thresholds(peer_count),
+        }
+    }
+
+    /// Thresholds for a round of a `priority` batch on `channel` among
+    /// `peer_count` known peers: the channel's, else the priority's, else
+    /// [`thresholds`](Self::thresholds).
+    pub fn batch_thresholds(&self, channel: Option<&str>, priority: Priority, peer_count: usize) -> Thresholds {
+        channel
+            .and_then(|name| self.channels.get(name))
+            .or_else(|| self.priorities.get(&priority))
+            .map_or_else(
+                || self.thresholds(peer_count),
+                |config| self.within_samples(config.thresholds(peer_count)),
+            )
+    }
+
+    /// Peers to sample when relaying a batch that is `age_secs` old, out of
+    /// a full sample of `sample`: halved every `regossip_half_life_secs`, but
+    /// never below `regossip_min_fanout`.
+    pub fn regossip_fanout(&self, sample: usize, age_secs: f64) -> usize {
+        if self.regossip_half_life_secs <= 0.0 {
+            return sample;
+        }
+        let decay = 0.5f64.powf(age_secs.max(0.0) / self.regossip_half_life_secs);
+        let floor = self.regossip_min_fanout.min(sample);
+        ((sample as f64 * decay).round() as usize).max(floor)
+    }
+
+    /// How long the gossip state holds the batches of finished rounds.
+    pub fn retention(&self) -> crate::protocol::RetentionPolicy {
+        crate::protocol::RetentionPolicy {
+            max_delivered: self.retained_delivered,
+            delivered: std::time::Duration::from_secs(self.delivered_retention_secs),
+            timed_out: std::time::Duration::from_secs(self.timed_out_retention_secs),
+        }
+    }
+
+    /// `max_hops` as stamped on new batches.
+    pub fn hop_limit(&self) -> Option<u32> {
+        (self.max_hops > 0).then_some(self.max_hops)
+    }
+
+    /// `batch_ttl_ms` as stamped on new batches.
+    pub fn batch_ttl(&self) -> Option<u64> {
+        (self.batch_ttl_ms > 0).then_some(self.batch_ttl_ms)
+    }
+
+    /// `validation_timeout_ms`, or `None` if unlimited.
+    pub fn validation_timeout(&self) -> Option<Duration> {
+        (self.validation_timeout_ms > 0).then(|| Duration::from_millis(self.validation_timeout_ms))
+    }
+
+    /// The absolute thresholds, ignoring any percentages.
+    pub fn absolute_thresholds(&self) -> Thresholds {
+        Thresholds {
+            ready: self.ready_threshold,
+            feedback: self.feedback_threshold,
+            delivery: self.delivery_threshold,
+        }
+    }
+
+    fn check_absolute_thresholds(&self, v: &mut Violations) {
+        let thresholds = self.absolute_thresholds();
+        v.check(thresholds.ready > 0, "ready_threshold", || "must be > 0".into());
+        self.check_ordering(v, "", thresholds);
+        self.check_within_samples(v, "", thresholds);
+
         let min_ready = (self.echo_sample_size / 2) + 1;
-        if self.ready_threshold < min_ready {
-            return Err(ConfigError::Validation(format!(
-                "ready_threshold ({}) must be >= {} (majority of echo_sample_size)",
+        v.check(self.ready_threshold >= min_ready, "ready_threshold", || {
+            format!(
+                "{} must be >= {} (majority of echo_sample_size)",
                 self.ready_threshold, min_ready
-            )));
-        }
+            )
+        });
 
         let min_feedback = (self.ready_sample_size * 3 + 3) / 4; // ceil(n * 0.75)
-        if self.feedback_threshold < min_feedback {
-            return Err(ConfigError::Validation(format!(
-                "feedback_threshold ({}) must be >= {} (75% of ready_sample_size)",
+        v.check(self.feedback_threshold >= min_feedback, "feedback_threshold", || {
+            format!(
+                "{} must be >= {} (75% of ready_sample_size)",
                 self.feedback_threshold, min_feedback
-            )));
-        }
+            )
+        });
 
         let min_delivery = (self.delivery_sample_size * 85 + 99) / 100; // ceil(n * 0.85)
-        if self.delivery_threshold < min_delivery {
-            return Err(ConfigError::Validation(format!(
-                "delivery_threshold ({}) must be >= {} (85% of delivery_sample_size)",
+        v.check(self.delivery_threshold >= min_delivery, "delivery_threshold", || {
+            format!(
+                "{} must be >= {} (85% of delivery_sample_size)",
                 self.delivery_threshold, min_delivery
-            )));
-        }
+            )
+        });
+    }
 
-        Ok(())
+    /// ready < feedback < delivery, each pair reported on its own.
+    fn check_ordering(&self, v: &mut Violations, prefix: &str, t: Thresholds) {
+        v.check(t.ready < t.feedback, &format!("{}feedback_threshold", prefix), || {
+            format!("{} must be > ready_threshold ({})", t.feedback, t.ready)
+        });
+        v.check(t.feedback < t.delivery, &format!("{}delivery_threshold", prefix), || {
+            format!("{} must be > feedback_threshold ({})", t.delivery, t.feedback)
+        });
+    }
+
+    /// Each threshold counts responses from one sample, so it cannot exceed it.
+    fn check_within_samples(&self, v: &mut Violations, prefix: &str, t: Thresholds) {
+        for (key, threshold, sample_key, sample) in [
+            ("ready_threshold", t.ready, "echo_sample_size", self.echo_sample_size),
+            ("feedback_threshold", t.feedback, "ready_sample_size", self.ready_sample_size),
+            ("delivery_threshold", t.delivery, "delivery_sample_size", self.delivery_sample_size),
+        ] {
+            v.check(threshold <= sample, &format!("{}{}", prefix, key), || {
+                format!("{} exceeds {} ({}) and can never be reached", threshold, sample_key, sample)
+            });
+        }
     }
 
+    /// Thresholds for samples of `size`. Below 4 peers the strict ordering
+    /// pushes thresholds past `size`, so those samples are widened to match.
     pub fn with_sample_size(size: usize) -> Self {
         let ready = (size / 2) + 1;
-        let feedback = (size * 3 + 3) / 4;
-        let delivery = (size * 85 + 99) / 100;
+        let feedback = ((size * 3 + 3) / 4).max(ready + 1);
+        let delivery = ((size * 85 + 99) / 100).max(feedback + 1);
 
         Self {
-            echo_sample_size: size,
-            ready_sample_size: size,
-            delivery_sample_size: size,
+            echo_sample_size: size.max(ready),
+            ready_sample_size: size.max(feedback),
+            delivery_sample_size: size.max(delivery),
             ready_threshold: ready,
-            feedback_threshold: feedback.max(ready + 1),
-            delivery_threshold: delivery.max(feedback + 1).max(ready + 2),
+            feedback_threshold: feedback,
+            delivery_threshold: delivery,
+            ..Default::default()
         }
     }
 }
@@ -96,6 +591,34 @@ impl Default for At2Config {
             ready_threshold: default_ready_threshold(),
             feedback_threshold: default_feedback_threshold(),
             delivery_threshold: default_delivery_threshold(),
+            ready_threshold_pct: None,
+            feedback_threshold_pct: None,
+            delivery_threshold_pct: None,
+            health_interval_secs: default_health_interval(),
+            health_window_secs: default_health_window(),
+            vector_clock_max_entries: default_vector_clock_max_entries(),
+            vector_clock_max_age_secs: None,
+            sequencing_stability_secs: default_sequencing_stability(),
+            membership_epoch_secs: default_membership_epoch(),
+            open_membership: false,
+            channels: BTreeMap::new(),
+            priorities: BTreeMap::new(),
+            seen_filter_capacity: default_seen_filter_capacity(),
+            seen_filter_fp_rate: default_seen_filter_fp_rate(),
+            retained_delivered: default_retained_delivered(),
+            delivered_retention_secs: default_delivered_retention(),
+            timed_out_retention_secs: 0,
+            regossip_half_life_secs: default_regossip_half_life(),
+            regossip_min_fanout: default_regossip_min_fanout(),
+            max_hops: default_max_hops(),
+            batch_ttl_ms: default_batch_ttl_ms(),
+            min_peers_for_submit: 0,
+            dedup_window_secs: 0,
+            validation_timeout_ms: default_validation_timeout_ms(),
+            max_batch_bytes: default_max_batch_bytes(),
+            max_messages_per_batch: default_max_messages_per_batch(),
+            allowed_creators: Vec::new(),
+            delivery_reports: false,
         }
     }
 }
@@ -123,6 +646,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_invalid_sequencing_stability() {
+        let config = At2Config {
+            sequencing_stability_secs: -1.0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_invalid_membership_epoch() {
+        let config = At2Config {
+            membership_epoch_secs: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_percentage_thresholds() {
+        let config = At2Config {
+            echo_sample_size: 10,
+            ready_sample_size: 10,
+            delivery_sample_size: 10,
+            ready_threshold_pct: Some(0.5),
+            feedback_threshold_pct: Some(0.66),
+            delivery_threshold_pct: Some(0.8),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.thresholds(10),
+            Thresholds { ready: 5, feedback: 7, delivery: 8 }
+        );
+        assert_eq!(config.thresholds(0).ready, 1);
+        assert_eq!(config.absolute_thresholds().ready, config.ready_threshold);
+    }
+
+    #[test]
+    fn test_percentage_thresholds_stay_within_samples() {
+        let config = At2Config {
+            ready_threshold_pct: Some(0.5),
+            feedback_threshold_pct: Some(0.66),
+            delivery_threshold_pct: Some(0.8),
+            ..Default::default()
+        };
+        // Samples of 6 among 10 peers: 0.66 of them would be 7 responses.
+        assert_eq!(
+            config.thresholds(10),
+            Thresholds { ready: 5, feedback: 6, delivery: 6 }
+        );
+        assert_eq!(
+            config.thresholds(100),
+            Thresholds { ready: 6, feedback: 6, delivery: 6 }
+        );
+        let mut violations = Violations::new();
+        config.check_within_samples(&mut violations, "", config.thresholds(100));
+        assert!(violations.is_empty(), "{}", violations);
+    }
+
+    #[test]
+    fn test_invalid_percentage_thresholds() {
+        let partial = At2Config {
+            ready_threshold_pct: Some(0.66),
+            ..Default::default()
+        };
+        assert!(partial.validate().is_err());
+
+        let out_of_range = At2Config {
+            ready_threshold_pct: Some(0.5),
+            feedback_threshold_pct: Some(0.75),
+            delivery_threshold_pct: Some(1.5),
+            ..Default::default()
+        };
+        assert!(out_of_range.validate().is_err());
+
+        let unordered = At2Config {
+            ready_threshold_pct: Some(0.8),
+            feedback_threshold_pct: Some(0.75),
+            delivery_threshold_pct: Some(0.9),
+            ..Default::default()
+        };
+        assert!(unordered.validate().is_err());
+    }
+
+    #[test]
+    fn test_both_threshold_forms_rejected() {
+        let table: toml::Table = toml::from_str("ready_threshold = 4\nready_threshold_pct = 0.66").unwrap();
+        assert!(At2Config::check_threshold_forms(&table).contains("ready_threshold_pct"));
+
+        let table: toml::Table = toml::from_str("ready_threshold_pct = 0.66").unwrap();
+        assert!(At2Config::check_threshold_forms(&table).is_empty());
+    }
+
     #[test]
     fn test_invalid_ordering() {
         let config = At2Config {
@@ -133,4 +750,92 @@ mod tests {
         };
         assert!(config.validate().is_err());
     }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let config = At2Config {
+            echo_sample_size: 3,
+            ready_threshold: 5,
+            feedback_threshold: 5,
+            delivery_threshold: 9,
+            membership_epoch_secs: 0,
+            ..Default::default()
+        };
+        let violations = config.violations();
+        for field in [
+            "membership_epoch_secs",
+            "feedback_threshold",
+            "ready_threshold",
+            "delivery_threshold",
+        ] {
+            assert!(violations.contains(field), "{} missing from {}", field, violations);
+        }
+        assert!(violations.to_string().contains("ready_threshold: 5 exceeds echo_sample_size (3)"));
+    }
+
+    #[test]
+    fn test_channel_thresholds() {
+        let mut config = At2Config::default();
+        config.channels.insert("alerts".into(), ChannelConfig::counts(1, 2, 3));
+        assert!(config.validate().is_ok());
+        assert_eq!(config.channel_thresholds(Some("alerts"), 10).delivery, 3);
+        assert_eq!(config.channel_thresholds(Some("other"), 10), config.thresholds(10));
+        assert_eq!(config.channel_thresholds(None, 10), config.thresholds(10));
+
+        config.channels.get_mut("alerts").unwrap().feedback_threshold = 3;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_priority_thresholds() {
+        let mut config = At2Config::default();
+        config.priorities.insert(Priority::High, ChannelConfig::fractions(0.5, 0.75, 1.0));
+        config.priorities.insert(Priority::Low, ChannelConfig::counts(1, 2, 3));
+        config.channels.insert("alerts".into(), ChannelConfig::counts(2, 3, 4));
+        assert!(config.validate().is_ok());
+
+        let high = config.batch_thresholds(None, Priority::High, 10);
+        assert_eq!(high, Thresholds { ready: 5, feedback: 6, delivery: 6 });
+        assert_eq!(config.batch_thresholds(None, Priority::Low, 10).delivery, 3);
+        assert_eq!(config.batch_thresholds(None, Priority::Normal, 10), config.thresholds(10));
+        assert_eq!(config.batch_thresholds(Some("alerts"), Priority::High, 10).delivery, 4);
+
+        config.priorities.get_mut(&Priority::High).unwrap().delivery_threshold_pct = None;
+        let violations = config.violations();
+        assert!(violations.contains("priorities.high.delivery_threshold_pct"), "{}", violations);
+
+        let parsed: At2Config = toml::from_str(
+            "[priorities.high]\ndelivery_threshold_pct = 1.0\nready_threshold_pct = 0.5\nfeedback_threshold_pct = 0.6",
+        )
+        .unwrap();
+        assert_eq!(parsed.batch_thresholds(None, Priority::High, 4).delivery, 4);
+    }
+
+    #[test]
+    fn test_allowed_creators_entries() {
+        let key = crate::crypto::KeyPair::generate().public_key().to_hex();
+        let mut config = At2Config {
+            allowed_creators: vec![key, "03AB*".into()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.allowed_creators = vec!["03ab".into(), "*".into(), "xyz*".into()];
+        assert_eq!(config.violations().to_string().matches("allowed_creators").count(), 3);
+    }
+
+    #[test]
+    fn test_regossip_fanout_decays_with_age() {
+        let config = At2Config::default();
+        assert_eq!(config.regossip_fanout(8, 0.0), 8);
+        assert_eq!(config.regossip_fanout(8, 5.0), 4);
+        assert_eq!(config.regossip_fanout(8, 60.0), 2);
+        assert_eq!(config.regossip_fanout(1, 60.0), 1);
+
+        let config = At2Config {
+            regossip_half_life_secs: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(config.regossip_fanout(8, 60.0), 8);
+    }
 }
@@ -1,17 +1,32 @@
 mod at2;
+mod network;
 mod plato;
+mod profile;
+mod violations;
+mod writer;
 
-use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-pub use at2::At2Config;
-pub use plato::PlatoConfig;
-pub use crate::util::logging::LogConfig;
+use crate::crypto::PublicKey;
+
+pub use at2::{At2Config, ChannelConfig, Thresholds};
+pub use network::NetworkConfig;
+pub use plato::{EstimatorKind, PlatoConfig};
+pub use profile::NetworkProfile;
+pub use violations::{Violation, Violations};
+pub use crate::util::logging::{DeliveredField, DeliveredFormat, LogConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RacerConfig {
+    /// Preset the file was laid over; see [`RacerConfig::profile`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<NetworkProfile>,
     pub node: NodeConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
     pub consensus: At2Config,
     pub plato: PlatoConfig,
     pub peers: PeerConfig,
@@ -26,8 +41,124 @@ pub struct NodeConfig {
     pub router_bind: String,
     #[serde(default = "default_publisher_bind")]
     pub publisher_bind: String,
+    /// Further router endpoints bound alongside `router_bind`, such as
+    /// `tcp://[::]:20001` for IPv6 or an `ipc://` path.
+    #[serde(default)]
+    pub extra_router_binds: Vec<String>,
+    /// Further publisher endpoints bound alongside `publisher_bind`.
+    #[serde(default)]
+    pub extra_publisher_binds: Vec<String>,
     #[serde(default)]
     pub selection_type: SelectionType,
+    /// The part this node takes in rounds, announced to peers in its
+    /// PeerDiscovery.
+    #[serde(default)]
+    pub role: NodeRole,
+    #[serde(default)]
+    pub encryption: EncryptionMode,
+    /// `host:port` for WebSocket observers; needs the `websocket` feature.
+    /// Its frames are never sealed, so it cannot be set with
+    /// `encryption = "required"`.
+    #[serde(default)]
+    pub websocket_bind: Option<String>,
+    /// `host:port` for the local admin API used by `racer status`/`racer peers`.
+    #[serde(default)]
+    pub admin_bind: Option<String>,
+    /// Lets `admin_bind` be an address other than loopback. The admin API
+    /// is unauthenticated and can submit and import batches.
+    #[serde(default)]
+    pub admin_allow_remote: bool,
+    /// Secret key written by `racer keygen`. Relative paths are resolved against
+    /// the config file's directory. A fresh key is generated when unset.
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+    /// Directory for the delivered batch store; needs the `store` feature.
+    /// Relative paths are resolved like `key_file`.
+    #[serde(default)]
+    pub store_path: Option<PathBuf>,
+    /// Directory where local submissions wait until delivered, so they are
+    /// retried after a restart; needs the `store` feature. Without it they
+    /// are kept in memory. Relative paths are resolved like `key_file`.
+    #[serde(default)]
+    pub outbox_path: Option<PathBuf>,
+    /// File PLATO's estimate is saved to while running and on stop, and
+    /// resumed from at start if younger than `plato.state_max_age_secs`.
+    /// Relative paths are resolved like `key_file`.
+    #[serde(default)]
+    pub plato_state_path: Option<PathBuf>,
+    /// File holding the sequence number of the last batch created here, so
+    /// numbering carries on across restarts. Batches are numbered only with
+    /// it set, since a number reused after a restart would look like
+    /// equivocation. Relative paths are resolved like `key_file`.
+    #[serde(default)]
+    pub sequence_path: Option<PathBuf>,
+    /// Frames queued per peer before `send_overflow` applies.
+    #[serde(default = "default_send_queue_capacity")]
+    pub send_queue_capacity: usize,
+    #[serde(default)]
+    pub send_overflow: OverflowPolicy,
+    /// Delay before retrying a lost or unreachable peer; doubles per failed
+    /// attempt up to `reconnect_max_ms`.
+    #[serde(default = "default_reconnect_initial_ms")]
+    pub reconnect_initial_ms: u64,
+    #[serde(default = "default_reconnect_max_ms")]
+    pub reconnect_max_ms: u64,
+    /// Rounds for local submissions gossiped at once; further submissions
+    /// wait, most urgent `Priority` first. 0 disables the bound.
+    #[serde(default = "default_max_inflight_submissions")]
+    pub max_inflight_submissions: usize,
+    /// Local rounds started back to back before the rest are paced one
+    /// PLATO publish interval apart. 0 starts each as soon as a
+    /// `max_inflight_submissions` slot frees.
+    #[serde(default = "default_publish_burst")]
+    pub publish_burst: usize,
+    /// Delay before gossiping an undelivered local submission again;
+    /// doubles per failed round up to `submit_retry_max_ms`.
+    #[serde(default = "default_submit_retry_initial_ms")]
+    pub submit_retry_initial_ms: u64,
+    #[serde(default = "default_submit_retry_max_ms")]
+    pub submit_retry_max_ms: u64,
+    /// Age at which an undelivered local submission is dropped. 0 keeps
+    /// retrying until the batch TTL passes.
+    #[serde(default = "default_submit_expiry_secs")]
+    pub submit_expiry_secs: u64,
+    /// Rounds after which an undelivered local submission is dropped; 1
+    /// disables retries. 0 disables the limit.
+    #[serde(default)]
+    pub submit_max_attempts: u32,
+    /// Independently locked shards of round state; more let rounds for
+    /// different batches progress without waiting on one another.
+    #[serde(default = "default_gossip_shards")]
+    pub gossip_shards: usize,
+    /// Longest a frame to a peer waits to share one `Bundle` frame with
+    /// others bound there. 0 sends every frame at once.
+    #[serde(default)]
+    pub coalesce_window_ms: u64,
+    /// Frames per bundle; a full bundle is sent without waiting.
+    #[serde(default = "default_coalesce_max_messages")]
+    pub coalesce_max_messages: usize,
+    /// Forward frames for nodes behind NAT that register with this node, and
+    /// publish their Echo/Ready responses. Needs a publicly reachable router.
+    #[serde(default)]
+    pub relay: bool,
+    /// Router of a relay node to register with, for a node behind NAT whose
+    /// own router cannot be dialed. Announcements then name the relay.
+    #[serde(default)]
+    pub relay_via: Option<String>,
+    /// Rejected inbound frames kept for `racer quarantine`; 0 keeps none.
+    #[serde(default = "default_quarantine_capacity")]
+    pub quarantine_capacity: usize,
+    /// For single-core gateways: one task reads every socket, background
+    /// ticks run less often, PLATO's `rsi` estimator gives way to `ewma`,
+    /// and relayed batches are re-gossiped from one task rather than a task
+    /// each.
+    #[serde(default)]
+    pub low_power: bool,
+    /// Seeds peer selection and PLATO's adjustment steps, so simulations
+    /// and reproduced failures pick the same peers run after run. Unset,
+    /// each start draws a seed from the OS.
+    #[serde(default)]
+    pub rng_seed: Option<u64>,
 }
 
 fn default_router_bind() -> String {
@@ -38,52 +169,482 @@ fn default_publisher_bind() -> String {
     "tcp://0.0.0.0:21001".into()
 }
 
+fn default_send_queue_capacity() -> usize {
+    crate::network::DEFAULT_SEND_QUEUE_CAPACITY
+}
+
+fn default_reconnect_initial_ms() -> u64 {
+    crate::network::DEFAULT_RECONNECT_INITIAL.as_millis() as u64
+}
+
+fn default_reconnect_max_ms() -> u64 {
+    crate::network::DEFAULT_RECONNECT_MAX.as_millis() as u64
+}
+
+fn default_max_inflight_submissions() -> usize {
+    64
+}
+
+fn default_publish_burst() -> usize {
+    16
+}
+
+fn default_submit_retry_initial_ms() -> u64 {
+    1_000
+}
+
+fn default_submit_retry_max_ms() -> u64 {
+    30_000
+}
+
+fn default_submit_expiry_secs() -> u64 {
+    300
+}
+
+fn default_gossip_shards() -> usize {
+    crate::protocol::DEFAULT_GOSSIP_SHARDS
+}
+
+fn default_coalesce_max_messages() -> usize {
+    32
+}
+
+fn default_quarantine_capacity() -> usize {
+    64
+}
+
+impl NodeConfig {
+    fn violations(&self) -> Violations {
+        let mut v = Violations::new();
+        for (name, address) in [("router_bind", &self.router_bind), ("publisher_bind", &self.publisher_bind)] {
+            v.check(violations::is_zmq_endpoint(address), name, || {
+                format!("{:?} is not a tcp://host:port, ipc://path or local://name endpoint", address)
+            });
+        }
+        v.check(self.router_bind != self.publisher_bind, "publisher_bind", || {
+            "must differ from router_bind".into()
+        });
+        let mut binds = HashSet::from([self.router_bind.as_str(), self.publisher_bind.as_str()]);
+        for (name, addresses) in [
+            ("extra_router_binds", &self.extra_router_binds),
+            ("extra_publisher_binds", &self.extra_publisher_binds),
+        ] {
+            for (i, address) in addresses.iter().enumerate() {
+                let field = format!("{}[{}]", name, i);
+                v.check(violations::is_zmq_endpoint(address), &field, || {
+                    format!("{:?} is not a tcp://host:port, ipc://path or local://name endpoint", address)
+                });
+                v.check(binds.insert(address.as_str()), &field, || format!("{} is bound more than once", address));
+            }
+        }
+        for (name, address) in [("websocket_bind", &self.websocket_bind), ("admin_bind", &self.admin_bind)] {
+            if let Some(address) = address {
+                v.check(violations::is_host_port(address), name, || {
+                    format!("{:?} is not a host:port address", address)
+                });
+            }
+        }
+        v.check(self.websocket_bind.is_none() || self.encryption != EncryptionMode::Required, "websocket_bind", || {
+            "WebSocket frames are never sealed, so the listener cannot run with encryption = \"required\"".into()
+        });
+        if let Some(address) = &self.admin_bind {
+            v.check(self.admin_allow_remote || violations::is_loopback(address), "admin_bind", || {
+                format!("{:?} is not a loopback address; set admin_allow_remote to serve it anyway", address)
+            });
+        }
+
+        if let Some(relay) = &self.relay_via {
+            v.check(violations::is_zmq_endpoint(relay), "relay_via", || {
+                format!("{:?} is not a tcp://host:port, ipc://path or local://name endpoint", relay)
+            });
+            v.check(!self.relay, "relay_via", || "a relay node must be reachable itself".into());
+        }
+
+        v.check(self.send_queue_capacity > 0, "send_queue_capacity", || "must be > 0".into());
+        v.check(self.gossip_shards > 0, "gossip_shards", || "must be > 0".into());
+        v.check(self.coalesce_max_messages > 0, "coalesce_max_messages", || "must be > 0".into());
+        v.check(self.reconnect_initial_ms > 0, "reconnect_initial_ms", || "must be > 0".into());
+        v.check(self.reconnect_max_ms >= self.reconnect_initial_ms, "reconnect_max_ms", || {
+            format!(
+                "{} must be >= reconnect_initial_ms ({})",
+                self.reconnect_max_ms, self.reconnect_initial_ms
+            )
+        });
+        v.check(self.submit_retry_initial_ms > 0, "submit_retry_initial_ms", || "must be > 0".into());
+        v.check(self.submit_retry_max_ms >= self.submit_retry_initial_ms, "submit_retry_max_ms", || {
+            format!(
+                "{} must be >= submit_retry_initial_ms ({})",
+                self.submit_retry_max_ms, self.submit_retry_initial_ms
+            )
+        });
+        v
+    }
+
+    pub fn coalesce_policy(&self) -> crate::network::CoalescePolicy {
+        crate::network::CoalescePolicy {
+            window: std::time::Duration::from_millis(self.coalesce_window_ms),
+            max_messages: self.coalesce_max_messages,
+        }
+    }
+
+    pub fn submit_retry_policy(&self) -> crate::node::outbox::RetryPolicy {
+        crate::node::outbox::RetryPolicy {
+            initial: std::time::Duration::from_millis(self.submit_retry_initial_ms),
+            max: std::time::Duration::from_millis(self.submit_retry_max_ms),
+            expiry: (self.submit_expiry_secs > 0).then(|| std::time::Duration::from_secs(self.submit_expiry_secs)),
+            max_attempts: (self.submit_max_attempts > 0).then_some(self.submit_max_attempts),
+        }
+    }
+
+    pub fn reconnect_policy(&self) -> crate::network::ReconnectPolicy {
+        crate::network::ReconnectPolicy::new(
+            std::time::Duration::from_millis(self.reconnect_initial_ms),
+            std::time::Duration::from_millis(self.reconnect_max_ms),
+        )
+    }
+}
+
+/// How each round picks the peers it gossips to.
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum SelectionType {
+    /// Fan-out peers uniformly at random.
     #[default]
     Normal,
+    /// The same as `Normal`.
     Random,
+    /// Uniformly at random, with the sample size drawn from a Poisson
+    /// distribution around the fan-out.
     Poisson,
+    /// The next fan-out peers in id order, so all are sampled evenly.
+    #[serde(rename = "round_robin")]
+    RoundRobin,
+}
+
+/// The part a node takes in rounds.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    /// Echoes, readies, delivers and submits.
+    #[default]
+    Full,
+    /// Follows rounds and delivers their batches once enough peers vouch
+    /// for them, but never sends Echo or Ready responses. Peers leave it
+    /// out of their samples and thresholds, and send it batches alone.
+    Observer,
+    /// Echoes, readies and re-gossips batches like a full node, and keeps
+    /// their total order and membership, but neither hands them to its
+    /// application nor submits its own. Not to be confused with `node.relay`,
+    /// which forwards frames for NATed nodes.
+    Forwarder,
+}
+
+impl NodeRole {
+    /// Whether the node sends Echo and Ready responses, and so counts
+    /// toward round thresholds.
+    pub fn answers_rounds(self) -> bool {
+        self != Self::Observer
+    }
+
+    /// Whether delivered batches reach the node's application.
+    pub fn delivers(self) -> bool {
+        self != Self::Forwarder
+    }
+
+    pub fn is_full(&self) -> bool {
+        *self == Self::Full
+    }
+}
+
+/// Transport encryption policy for router/dealer traffic.
+///
+/// Pub/sub traffic stays plaintext: it only carries signed Echo/Ready responses.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionMode {
+    /// Send plaintext; still accept sealed frames.
+    #[default]
+    Off,
+    /// Seal frames to peers with a known key; accept plaintext.
+    Opportunistic,
+    /// Seal every frame, and drop plaintext ones and ones sealed by a key
+    /// no pinned or known peer has.
+    Required,
+}
+
+/// What a full per-peer send queue does with another frame.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued frame to make room.
+    #[default]
+    DropOldest,
+    /// Drop the new frame; the send fails with `NetworkError::QueueFull`.
+    DropNew,
+    /// Wait until the peer's worker frees a slot.
+    Block,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerConfig {
     #[serde(default)]
     pub routers: Vec<String>,
+    /// Public keys pinned per router address.
+    #[serde(default)]
+    pub pinned_keys: BTreeMap<String, PublicKey>,
+    /// Bootstrap nodes as DNS names: `host:port`, dialed at every address
+    /// it resolves to, or an SRV name like `_racer._tcp.example.com` (needs
+    /// the `srv` feature). Each is asked for the peers it knows.
+    #[serde(default)]
+    pub seeds: Vec<String>,
+    /// How often seeds are resolved and asked for peers again. 0 contacts
+    /// them once at start.
+    #[serde(default = "default_seed_interval_secs")]
+    pub seed_interval_secs: u64,
+    /// How often a sample of verified peers is pushed to every peer as a
+    /// `PeerListGossip`. 0 disables it.
+    #[serde(default = "default_gossip_interval_secs")]
+    pub gossip_interval_secs: u64,
+    /// Peers per `PeerListGossip`, from 1 to 32.
+    #[serde(default = "default_gossip_sample")]
+    pub gossip_sample: usize,
+    /// Peers learnt from seeds and peer gossip are no longer dialed once
+    /// the registry holds this many. 0 is unlimited. Configured routers and
+    /// peers that dial in are always accepted.
+    #[serde(default)]
+    pub max_peers: usize,
+}
+
+fn default_seed_interval_secs() -> u64 {
+    300
+}
+
+fn default_gossip_interval_secs() -> u64 {
+    60
+}
+
+fn default_gossip_sample() -> usize {
+    8
+}
+
+impl Default for PeerConfig {
+    fn default() -> Self {
+        Self {
+            routers: Vec::new(),
+            pinned_keys: BTreeMap::new(),
+            seeds: Vec::new(),
+            seed_interval_secs: default_seed_interval_secs(),
+            gossip_interval_secs: default_gossip_interval_secs(),
+            gossip_sample: default_gossip_sample(),
+            max_peers: 0,
+        }
+    }
 }
 
 impl RacerConfig {
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
         let content = std::fs::read_to_string(path.as_ref())
             .map_err(|e| ConfigError::Io(e.to_string()))?;
-        Self::from_toml(&content)
+        let mut config = Self::from_toml(&content)?;
+
+        if let Some(dir) = path.as_ref().parent() {
+            let node = &mut config.node;
+            for file in [
+                &mut node.key_file,
+                &mut node.store_path,
+                &mut node.outbox_path,
+                &mut node.plato_state_path,
+                &mut node.sequence_path,
+                &mut config.plato.trace_file,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                if file.is_relative() {
+                    *file = dir.join(&*file);
+                }
+            }
+        }
+        Ok(config)
     }
 
     pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
-        let config: Self = toml::from_str(content)
+        let table: toml::Table = toml::from_str(content)
             .map_err(|e| ConfigError::Parse(e.to_string()))?;
+        if let Some(consensus) = table.get("consensus").and_then(toml::Value::as_table) {
+            let mut v = Violations::new();
+            v.extend_section("consensus", At2Config::check_threshold_forms(consensus));
+            v.into_result()?;
+        }
+        let table = Self::apply_profile(table)?;
+        let config: Self = table
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse(e.to_string()))?;
         config.validate()?;
         Ok(config)
     }
 
+    /// Checks every section and cross-field constraint, reporting all
+    /// violations at once rather than stopping at the first.
     pub fn validate(&self) -> Result<(), ConfigError> {
-        self.consensus.validate()?;
-        self.plato.validate()?;
-        Ok(())
+        self.violations().into_result()
+    }
+
+    pub fn violations(&self) -> Violations {
+        let mut v = Violations::new();
+        v.extend_section("node", self.node.violations());
+        v.extend_section("network", self.network.violations());
+        v.extend_section("consensus", self.consensus.violations());
+        v.extend_section("plato", self.plato.violations());
+
+        if self.network.max_frame_bytes > 0 {
+            v.check(
+                self.consensus.max_batch_bytes > 0 && self.consensus.max_batch_bytes < self.network.max_frame_bytes,
+                "consensus.max_batch_bytes",
+                || {
+                    format!(
+                        "{} must be > 0 and < network.max_frame_bytes ({}), or batches that pass it cannot be received",
+                        self.consensus.max_batch_bytes, self.network.max_frame_bytes
+                    )
+                },
+            );
+        }
+
+        v.check(
+            (0.0..=1.0).contains(&self.logging.trace_sample_ratio),
+            "logging.trace_sample_ratio",
+            || "must be between 0.0 and 1.0".into(),
+        );
+        v.check(
+            !(self.logging.rotation_enabled && self.logging.max_file_size_mb == 0),
+            "logging.max_file_size_mb",
+            || "must be > 0 when rotation_enabled is set".into(),
+        );
+        match self.logging.delivered_format {
+            DeliveredFormat::Csv => v.check(!self.logging.delivered_fields.is_empty(), "logging.delivered_fields", || {
+                "must name at least one column for delivered_format = \"csv\"".into()
+            }),
+            DeliveredFormat::Parquet => v.check(cfg!(feature = "parquet"), "logging.delivered_format", || {
+                "\"parquet\" needs the `parquet` feature".into()
+            }),
+            DeliveredFormat::Jsonl => {}
+        }
+        let is_level = |level: &str| level.parse::<tracing_subscriber::filter::LevelFilter>().is_ok();
+        v.check(is_level(&self.logging.level), "logging.level", || {
+            format!("{:?} is not one of off, error, warn, info, debug, trace", self.logging.level)
+        });
+        for (target, level) in &self.logging.levels {
+            let field = format!("logging.levels.{}", target);
+            v.check(!target.is_empty() && !target.contains([',', '=']), &field, || {
+                "module path must be non-empty without ',' or '='".into()
+            });
+            v.check(is_level(level), &field, || {
+                format!("{:?} is not one of off, error, warn, info, debug, trace", level)
+            });
+        }
+
+        for (i, router) in self.peers.routers.iter().enumerate() {
+            v.check(violations::is_zmq_endpoint(router), &format!("peers.routers[{}]", i), || {
+                format!("{:?} is not a tcp://host:port, ipc://path or local://name endpoint", router)
+            });
+            if self.node.encryption == EncryptionMode::Required {
+                v.check(self.peers.pinned_keys.contains_key(router), &format!("peers.routers[{}]", i), || {
+                    format!("encryption = \"required\" but no pinned key for router {}", router)
+                });
+            }
+        }
+        for (i, seed) in self.peers.seeds.iter().enumerate() {
+            let field = format!("peers.seeds[{}]", i);
+            v.check(crate::network::seeds::is_seed(seed), &field, || {
+                format!("{:?} is not a host:port or _service._proto.domain SRV name", seed)
+            });
+            v.check(cfg!(feature = "srv") || !crate::network::seeds::is_srv(seed), &field, || {
+                "SRV seeds need the `srv` feature".into()
+            });
+        }
+        v.check(
+            (1..=crate::node::MAX_PEER_LIST).contains(&self.peers.gossip_sample),
+            "peers.gossip_sample",
+            || format!("must be between 1 and {}", crate::node::MAX_PEER_LIST),
+        );
+        v
+    }
+
+    /// Settings that are valid but likely to surprise: the node will run,
+    /// though perhaps not as intended.
+    pub fn warnings(&self) -> Violations {
+        let mut w = Violations::new();
+        let peers = self.peers.routers.len();
+
+        w.check(peers > 0 || !self.peers.seeds.is_empty(), "peers.routers", || {
+            "no peers configured; this node only gossips with peers that dial it".into()
+        });
+        w.check(self.node.key_file.is_some(), "node.key_file", || {
+            "unset; the node takes a fresh identity on every start".into()
+        });
+        w.check(self.node.encryption != EncryptionMode::Off || peers == 0, "node.encryption", || {
+            "off; batches to peers travel in plaintext".into()
+        });
+        w.check(self.node.role.delivers() || self.node.outbox_path.is_none(), "node.outbox_path", || {
+            "unused; a forwarder node submits no batches".into()
+        });
+        if peers > 0 && self.consensus.ready_threshold_pct.is_none() {
+            for (name, sample) in [
+                ("consensus.echo_sample_size", self.consensus.echo_sample_size),
+                ("consensus.ready_sample_size", self.consensus.ready_sample_size),
+                ("consensus.delivery_sample_size", self.consensus.delivery_sample_size),
+            ] {
+                w.check(sample <= peers, name, || {
+                    format!("{} exceeds the {} configured peers; rounds stall until more are discovered", sample, peers)
+                });
+            }
+        }
+        w
     }
 
     pub fn minimal() -> Self {
         Self {
+            profile: None,
             node: NodeConfig {
                 id: None,
                 router_bind: default_router_bind(),
                 publisher_bind: default_publisher_bind(),
+                extra_router_binds: Vec::new(),
+                extra_publisher_binds: Vec::new(),
                 selection_type: SelectionType::Normal,
+                role: NodeRole::Full,
+                encryption: EncryptionMode::Off,
+                websocket_bind: None,
+                admin_bind: None,
+                admin_allow_remote: false,
+                key_file: None,
+                store_path: None,
+                outbox_path: None,
+                plato_state_path: None,
+                sequence_path: None,
+                send_queue_capacity: default_send_queue_capacity(),
+                send_overflow: OverflowPolicy::default(),
+                reconnect_initial_ms: default_reconnect_initial_ms(),
+                reconnect_max_ms: default_reconnect_max_ms(),
+                max_inflight_submissions: default_max_inflight_submissions(),
+                publish_burst: default_publish_burst(),
+                submit_retry_initial_ms: default_submit_retry_initial_ms(),
+                submit_retry_max_ms: default_submit_retry_max_ms(),
+                submit_expiry_secs: default_submit_expiry_secs(),
+                submit_max_attempts: 0,
+                gossip_shards: default_gossip_shards(),
+                coalesce_window_ms: 0,
+                coalesce_max_messages: default_coalesce_max_messages(),
+                relay: false,
+                relay_via: None,
+                quarantine_capacity: default_quarantine_capacity(),
+                low_power: false,
+                rng_seed: None,
             },
+            network: NetworkConfig::default(),
             consensus: At2Config::default(),
             plato: PlatoConfig::default(),
-            peers: PeerConfig { routers: vec![] },
+            peers: PeerConfig::default(),
             logging: LogConfig::default(),
         }
     }
@@ -96,7 +657,7 @@ pub enum ConfigError {
     #[error("parse error: {0}")]
     Parse(String),
     #[error("validation error: {0}")]
-    Validation(String),
+    Validation(Violations),
 }
 
 impl Default for RacerConfig {
@@ -141,5 +702,244 @@ mod tests {
         let config = RacerConfig::from_toml(toml).unwrap();
         assert_eq!(config.consensus.echo_sample_size, 6);
         assert_eq!(config.peers.routers.len(), 1);
+        assert_eq!(config.node.encryption, EncryptionMode::Off);
+    }
+
+    #[test]
+    fn test_parse_percentage_thresholds() {
+        let base = r#"
+            [node]
+            [plato]
+            [peers]
+
+            [consensus]
+            ready_threshold_pct = 0.5
+            feedback_threshold_pct = 0.66
+            delivery_threshold_pct = 0.8
+        "#;
+        let config = RacerConfig::from_toml(base).unwrap();
+        assert_eq!(config.consensus.thresholds(5).feedback, 4);
+
+        let ambiguous = format!("{}\n            delivery_threshold = 6\n", base);
+        assert!(matches!(
+            RacerConfig::from_toml(&ambiguous),
+            Err(ConfigError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_trace_sample_ratio_range() {
+        let mut config = RacerConfig::minimal();
+        config.logging.trace_sample_ratio = 1.5;
+        assert!(config.validate().is_err());
+
+        config.logging.trace_sample_ratio = 0.0;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_reconnect_backoff_range() {
+        let mut config = RacerConfig::minimal();
+        config.node.reconnect_max_ms = config.node.reconnect_initial_ms - 1;
+        assert!(config.validate().is_err());
+
+        config.node.reconnect_initial_ms = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_required_encryption_needs_pinned_keys() {
+        let key = crate::crypto::KeyPair::generate().public_key();
+        let mut config = RacerConfig::minimal();
+        config.node.encryption = EncryptionMode::Required;
+        config.peers.routers = vec!["tcp://10.0.0.2:20001".into()];
+        assert!(config.validate().is_err());
+
+        config.peers.pinned_keys.insert("tcp://10.0.0.2:20001".into(), key);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_extra_binds() {
+        let mut config = RacerConfig::minimal();
+        config.node.extra_router_binds = vec!["tcp://[::]:20001".into(), "ipc:///tmp/racer-router".into()];
+        config.node.extra_publisher_binds = vec!["tcp://[::]:21001".into()];
+        assert!(config.validate().is_ok());
+
+        config.node.extra_router_binds = vec!["tcp://::1:20001".into()];
+        config.node.extra_publisher_binds = vec![config.node.router_bind.clone()];
+        let Err(ConfigError::Validation(violations)) = config.validate() else {
+            panic!("config should be invalid");
+        };
+        assert!(violations.contains("node.extra_router_binds[0]"));
+        assert!(violations.contains("node.extra_publisher_binds[0]"));
+    }
+
+    #[test]
+    fn test_websocket_bind_needs_unrequired_encryption() {
+        let mut config = RacerConfig::minimal();
+        config.node.websocket_bind = Some("127.0.0.1:23001".into());
+        config.node.encryption = EncryptionMode::Opportunistic;
+        assert!(config.validate().is_ok());
+        config.node.encryption = EncryptionMode::Required;
+        let Err(ConfigError::Validation(violations)) = config.validate() else {
+            panic!("config should be invalid");
+        };
+        assert!(violations.contains("node.websocket_bind"));
+    }
+
+    #[test]
+    fn test_admin_bind_stays_on_loopback() {
+        let mut config = RacerConfig::minimal();
+        for address in ["127.0.0.1:22001", "[::1]:22001", "localhost:22001"] {
+            config.node.admin_bind = Some(address.into());
+            assert!(config.validate().is_ok(), "{}", address);
+        }
+        config.node.admin_bind = Some("0.0.0.0:22001".into());
+        let Err(ConfigError::Validation(violations)) = config.validate() else {
+            panic!("config should be invalid");
+        };
+        assert!(violations.contains("node.admin_bind"));
+        config.node.admin_allow_remote = true;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_violations_name_their_fields() {
+        let mut config = RacerConfig::minimal();
+        config.node.router_bind = "tcp://0.0.0.0".into();
+        config.node.admin_bind = Some("localhost:admin".into());
+        config.node.relay_via = Some("relay.example:20001".into());
+        config.consensus.ready_threshold = 7;
+        config.plato.minimum_latency_secs = 3.0;
+        config.peers.routers = vec!["10.0.0.2:20001".into()];
+
+        let Err(ConfigError::Validation(violations)) = config.validate() else {
+            panic!("config should be invalid");
+        };
+        for field in [
+            "node.router_bind",
+            "node.admin_bind",
+            "node.relay_via",
+            "consensus.ready_threshold",
+            "consensus.feedback_threshold",
+            "plato.target_latency_secs",
+            "peers.routers[0]",
+        ] {
+            assert!(violations.contains(field), "{} missing from {}", field, violations);
+        }
+
+        config = RacerConfig::minimal();
+        config.node.websocket_bind = Some("127.0.0.1:9000".into());
+        config.node.router_bind = "ipc:///tmp/racer-router".into();
+        assert!(config.validate().is_ok());
+        config.node.publisher_bind = "local://node-0/pub".into();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_log_levels_validated() {
+        let mut config = RacerConfig::minimal();
+        config.logging.levels.insert("racer::plato".into(), "debug".into());
+        assert!(config.validate().is_ok());
+
+        config.logging.level = "loud".into();
+        config.logging.levels.insert("racer::node".into(), "verbose".into());
+        let Err(ConfigError::Validation(violations)) = config.validate() else {
+            panic!("config should be invalid");
+        };
+        assert!(violations.contains("logging.level"));
+        assert!(violations.contains("logging.levels.racer::node"));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut config = RacerConfig::minimal();
+        config.peers.routers = vec!["tcp://10.0.0.2:20001".into()];
+        let warnings = config.warnings();
+        for field in ["node.key_file", "node.encryption", "consensus.echo_sample_size"] {
+            assert!(warnings.contains(field), "{} missing from {}", field, warnings);
+        }
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_parse_node_role() {
+        let toml = r#"
+            [node]
+            role = "forwarder"
+            outbox_path = "outbox"
+
+            [consensus]
+
+            [plato]
+
+            [peers]
+        "#;
+        let config = RacerConfig::from_toml(toml).unwrap();
+        assert_eq!(config.node.role, NodeRole::Forwarder);
+        assert!(config.node.role.answers_rounds() && !config.node.role.delivers());
+        assert!(config.warnings().contains("node.outbox_path"));
+        assert_eq!(RacerConfig::minimal().node.role, NodeRole::Full);
+        assert!(!NodeRole::Observer.answers_rounds());
+        assert!(RacerConfig::from_toml(&toml.replace("forwarder", "witness")).is_err());
+    }
+
+    #[test]
+    fn test_seeds_are_validated() {
+        let mut config = RacerConfig::minimal();
+        config.peers.seeds = vec!["seed.racer.example:20001".into(), "seed.racer.example".into()];
+        let violations = config.violations();
+        assert!(!violations.contains("peers.seeds[0]"));
+        assert!(violations.contains("peers.seeds[1]"));
+
+        config.peers.seeds.truncate(1);
+        assert!(!config.warnings().contains("peers.routers"));
+    }
+
+    #[test]
+    fn test_gossip_sample_is_bounded() {
+        let mut config = RacerConfig::minimal();
+        assert!(!config.violations().contains("peers.gossip_sample"));
+        for sample in [0, 33] {
+            config.peers.gossip_sample = sample;
+            assert!(config.violations().contains("peers.gossip_sample"), "{}", sample);
+        }
+    }
+
+    #[test]
+    fn test_batch_limit_fits_in_a_frame() {
+        let mut config = RacerConfig::minimal();
+        assert!(!config.violations().contains("consensus.max_batch_bytes"));
+        config.consensus.max_batch_bytes = config.network.max_frame_bytes;
+        assert!(config.violations().contains("consensus.max_batch_bytes"));
+        config.network.max_frame_bytes = 0;
+        assert!(!config.violations().contains("consensus.max_batch_bytes"));
+    }
+
+    #[test]
+    fn test_parse_encryption_toml() {
+        let key = crate::crypto::KeyPair::generate().public_key();
+        let toml = format!(
+            r#"
+            [node]
+            encryption = "opportunistic"
+
+            [consensus]
+
+            [plato]
+
+            [peers]
+            routers = ["tcp://10.0.0.2:20001"]
+
+            [peers.pinned_keys]
+            "tcp://10.0.0.2:20001" = "{}"
+        "#,
+            key
+        );
+
+        let config = RacerConfig::from_toml(&toml).unwrap();
+        assert_eq!(config.node.encryption, EncryptionMode::Opportunistic);
+        assert_eq!(config.peers.pinned_keys.get("tcp://10.0.0.2:20001"), Some(&key));
     }
 }
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::Violations;
+use crate::network::{HeartbeatPolicy, SocketOptions};
+
+/// Socket options applied when the node creates its [`RacerNetwork`](crate::network::RacerNetwork).
+///
+/// Reconnect backoff and per-peer send queues are set in `[node]`
+/// (`reconnect_*_ms`, `send_queue_capacity`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Quiet time after which a dealer link sends its peer a heartbeat.
+    /// 0 disables heartbeats, leaving half-open connections undetected.
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub heartbeat_interval_ms: u64,
+    /// Silence after which a link is taken for dead and reconnected.
+    #[serde(default = "default_heartbeat_timeout_ms")]
+    pub heartbeat_timeout_ms: u64,
+    /// Inbound frames buffered per socket before it stops reading.
+    #[serde(default = "default_receive_hwm")]
+    pub receive_hwm: usize,
+    /// Longest inbound frame decoded, in bytes; longer ones are dropped
+    /// before any of their JSON is parsed, and WebSocket clients sending one
+    /// are cut off before it is buffered whole. The ZeroMQ transport still
+    /// reads such a frame into memory first. 0 disables the limit.
+    #[serde(default = "default_max_frame_bytes")]
+    pub max_frame_bytes: usize,
+}
+
+fn default_heartbeat_interval_ms() -> u64 {
+    crate::network::DEFAULT_HEARTBEAT_INTERVAL.as_millis() as u64
+}
+
+fn default_heartbeat_timeout_ms() -> u64 {
+    crate::network::DEFAULT_HEARTBEAT_TIMEOUT.as_millis() as u64
+}
+
+fn default_receive_hwm() -> usize {
+    crate::network::DEFAULT_RECEIVE_HWM
+}
+
+fn default_max_frame_bytes() -> usize {
+    crate::network::DEFAULT_MAX_FRAME_BYTES
+}
+
+impl NetworkConfig {
+    pub(super) fn violations(&self) -> Violations {
+        let mut v = Violations::new();
+        if self.heartbeat_interval_ms > 0 {
+            v.check(self.heartbeat_timeout_ms > self.heartbeat_interval_ms, "heartbeat_timeout_ms", || {
+                format!(
+                    "{} must be > heartbeat_interval_ms ({})",
+                    self.heartbeat_timeout_ms, self.heartbeat_interval_ms
+                )
+            });
+        }
+        v.check(self.receive_hwm > 0, "receive_hwm", || "must be > 0".into());
+        v
+    }
+
+    pub fn socket_options(&self) -> SocketOptions {
+        SocketOptions {
+            heartbeat: (self.heartbeat_interval_ms > 0).then(|| {
+                HeartbeatPolicy::new(
+                    Duration::from_millis(self.heartbeat_interval_ms),
+                    Duration::from_millis(self.heartbeat_timeout_ms),
+                )
+            }),
+            receive_hwm: self.receive_hwm,
+            max_frame_bytes: self.max_frame_bytes,
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_ms: default_heartbeat_interval_ms(),
+            heartbeat_timeout_ms: default_heartbeat_timeout_ms(),
+            receive_hwm: default_receive_hwm(),
+            max_frame_bytes: default_max_frame_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_timeout_must_exceed_interval() {
+        let mut config = NetworkConfig::default();
+        assert!(config.violations().is_empty());
+        assert!(config.socket_options().heartbeat.is_some());
+
+        config.heartbeat_timeout_ms = config.heartbeat_interval_ms;
+        assert!(!config.violations().is_empty());
+
+        config.heartbeat_interval_ms = 0;
+        assert!(config.violations().is_empty());
+        assert!(config.socket_options().heartbeat.is_none());
+    }
+}
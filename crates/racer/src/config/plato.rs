@@ -1,6 +1,8 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
-use super::ConfigError;
+use super::{ConfigError, Violations};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlatoConfig {
@@ -14,6 +16,9 @@ pub struct PlatoConfig {
     pub minimum_latency_secs: f64,
     #[serde(default = "default_max_gossip_timeout")]
     pub max_gossip_timeout_secs: f64,
+    /// Floor for each gossip phase timeout, however low the latency estimate.
+    #[serde(default = "default_min_phase_timeout")]
+    pub min_phase_timeout_secs: f64,
     #[serde(default = "default_rsi_increase_period")]
     pub rsi_increase_period: usize,
     #[serde(default = "default_rsi_decrease_period")]
@@ -28,6 +33,42 @@ pub struct PlatoConfig {
     pub savgol_increase_window: usize,
     #[serde(default = "default_savgol_decrease_window")]
     pub savgol_decrease_window: usize,
+    /// Order of the polynomials the Savitzky-Golay filters fit; must be at
+    /// least two below both windows, which are rounded up to odd sizes.
+    #[serde(default = "default_savgol_polynomial_order")]
+    pub savgol_polynomial_order: usize,
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+    #[serde(default = "default_update_interval")]
+    pub update_interval_secs: f64,
+    #[serde(default)]
+    pub estimator: EstimatorKind,
+    #[serde(default = "default_kalman_process_noise")]
+    pub kalman_process_noise: f64,
+    #[serde(default = "default_kalman_measurement_noise")]
+    pub kalman_measurement_noise: f64,
+    /// Fill of the fullest send queue, from 0.0 to 1.0, at or above which
+    /// PLATO throttles without waiting for latency to rise. 0 disables it.
+    #[serde(default = "default_queue_depth_threshold")]
+    pub queue_depth_threshold: f64,
+    /// Age past which a saved estimate (`node.plato_state_path`) is
+    /// discarded at start in favour of the targets above.
+    #[serde(default = "default_state_max_age_secs")]
+    pub state_max_age_secs: u64,
+    /// CSV file every latency sample and congestion decision is appended
+    /// to, in the format `racer plato-sim --trace` reads. Relative paths are
+    /// resolved like `node.key_file`.
+    #[serde(default)]
+    pub trace_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EstimatorKind {
+    #[default]
+    Rsi,
+    Ewma,
+    Kalman,
 }
 
 fn default_target_latency() -> f64 {
@@ -50,6 +91,10 @@ fn default_max_gossip_timeout() -> f64 {
     60.0
 }
 
+fn default_min_phase_timeout() -> f64 {
+    5.0
+}
+
 fn default_rsi_increase_period() -> usize {
     14
 }
@@ -78,39 +123,143 @@ fn default_savgol_decrease_window() -> usize {
     21
 }
 
+fn default_savgol_polynomial_order() -> usize {
+    crate::plato::DEFAULT_POLYNOMIAL_ORDER
+}
+
+fn default_history_size() -> usize {
+    256
+}
+
+fn default_update_interval() -> f64 {
+    5.0
+}
+
+fn default_kalman_process_noise() -> f64 {
+    0.01
+}
+
+fn default_kalman_measurement_noise() -> f64 {
+    0.25
+}
+
+fn default_queue_depth_threshold() -> f64 {
+    0.75
+}
+
+fn default_state_max_age_secs() -> u64 {
+    600
+}
+
 impl PlatoConfig {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.minimum_latency_secs <= 0.0 {
-            return Err(ConfigError::Validation(
-                "minimum_latency_secs must be positive".into(),
-            ));
-        }
+        self.violations().into_result()
+    }
 
-        if self.target_latency_secs < self.minimum_latency_secs {
-            return Err(ConfigError::Validation(
-                "target_latency_secs must be >= minimum_latency_secs".into(),
-            ));
-        }
+    pub fn state_max_age(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.state_max_age_secs)
+    }
 
-        if self.max_gossip_timeout_secs <= self.target_latency_secs {
-            return Err(ConfigError::Validation(
-                "max_gossip_timeout_secs must be > target_latency_secs".into(),
-            ));
-        }
+    /// Every invalid setting, with paths relative to `[plato]`.
+    pub(super) fn violations(&self) -> Violations {
+        let mut v = Violations::new();
 
-        if !(0.0..=1.0).contains(&self.own_latency_weight) {
-            return Err(ConfigError::Validation(
-                "own_latency_weight must be between 0.0 and 1.0".into(),
-            ));
+        for (name, secs) in [
+            ("target_latency_secs", self.target_latency_secs),
+            ("minimum_latency_secs", self.minimum_latency_secs),
+            ("max_gossip_timeout_secs", self.max_gossip_timeout_secs),
+            ("min_phase_timeout_secs", self.min_phase_timeout_secs),
+            ("target_publishing_frequency_secs", self.target_publishing_frequency_secs),
+            ("max_publishing_frequency_secs", self.max_publishing_frequency_secs),
+            ("update_interval_secs", self.update_interval_secs),
+        ] {
+            v.check(secs.is_finite(), name, || "must be a finite number".into());
         }
 
-        if self.rsi_overbought <= self.rsi_oversold {
-            return Err(ConfigError::Validation(
-                "rsi_overbought must be > rsi_oversold".into(),
-            ));
+        v.check(self.minimum_latency_secs > 0.0, "minimum_latency_secs", || "must be positive".into());
+        v.check(self.target_latency_secs >= self.minimum_latency_secs, "target_latency_secs", || {
+            format!(
+                "{} must be >= minimum_latency_secs ({})",
+                self.target_latency_secs, self.minimum_latency_secs
+            )
+        });
+        v.check(
+            self.max_gossip_timeout_secs > self.target_latency_secs,
+            "max_gossip_timeout_secs",
+            || {
+                format!(
+                    "{} must be > target_latency_secs ({})",
+                    self.max_gossip_timeout_secs, self.target_latency_secs
+                )
+            },
+        );
+        v.check(self.min_phase_timeout_secs > 0.0, "min_phase_timeout_secs", || "must be positive".into());
+        v.check(
+            self.min_phase_timeout_secs <= self.max_gossip_timeout_secs,
+            "min_phase_timeout_secs",
+            || {
+                format!(
+                    "{} must be <= max_gossip_timeout_secs ({})",
+                    self.min_phase_timeout_secs, self.max_gossip_timeout_secs
+                )
+            },
+        );
+
+        v.check(
+            self.target_publishing_frequency_secs > 0.0,
+            "target_publishing_frequency_secs",
+            || "must be positive".into(),
+        );
+        v.check(
+            self.max_publishing_frequency_secs >= self.target_publishing_frequency_secs,
+            "max_publishing_frequency_secs",
+            || {
+                format!(
+                    "{} must be >= target_publishing_frequency_secs ({})",
+                    self.max_publishing_frequency_secs, self.target_publishing_frequency_secs
+                )
+            },
+        );
+
+        v.check((0.0..=1.0).contains(&self.own_latency_weight), "own_latency_weight", || {
+            "must be between 0.0 and 1.0".into()
+        });
+        v.check(self.rsi_overbought > self.rsi_oversold, "rsi_overbought", || {
+            format!("{} must be > rsi_oversold ({})", self.rsi_overbought, self.rsi_oversold)
+        });
+        for (name, period) in [
+            ("rsi_increase_period", self.rsi_increase_period),
+            ("rsi_decrease_period", self.rsi_decrease_period),
+            ("savgol_increase_window", self.savgol_increase_window),
+            ("savgol_decrease_window", self.savgol_decrease_window),
+        ] {
+            v.check(period > 0, name, || "must be > 0".into());
+        }
+        for (name, window) in [
+            ("savgol_increase_window", self.savgol_increase_window),
+            ("savgol_decrease_window", self.savgol_decrease_window),
+        ] {
+            let odd = window | 1;
+            v.check(self.savgol_polynomial_order + 2 <= odd, "savgol_polynomial_order", || {
+                format!(
+                    "{} must be at most {} ({} rounded up to odd, minus 2)",
+                    self.savgol_polynomial_order,
+                    odd.saturating_sub(2),
+                    name
+                )
+            });
         }
 
-        Ok(())
+        v.check(self.update_interval_secs >= 0.0, "update_interval_secs", || "must not be negative".into());
+        v.check(self.kalman_process_noise > 0.0, "kalman_process_noise", || "must be positive".into());
+        v.check(self.kalman_measurement_noise > 0.0, "kalman_measurement_noise", || {
+            "must be positive".into()
+        });
+        v.check((0.0..=1.0).contains(&self.queue_depth_threshold), "queue_depth_threshold", || {
+            "must be between 0.0 and 1.0".into()
+        });
+        v.check(self.state_max_age_secs > 0, "state_max_age_secs", || "must be > 0".into());
+        v
     }
 }
 
@@ -122,6 +271,7 @@ impl Default for PlatoConfig {
             max_publishing_frequency_secs: default_max_publishing_frequency(),
             minimum_latency_secs: default_minimum_latency(),
             max_gossip_timeout_secs: default_max_gossip_timeout(),
+            min_phase_timeout_secs: default_min_phase_timeout(),
             rsi_increase_period: default_rsi_increase_period(),
             rsi_decrease_period: default_rsi_decrease_period(),
             rsi_overbought: default_rsi_overbought(),
@@ -129,6 +279,15 @@ impl Default for PlatoConfig {
             own_latency_weight: default_own_latency_weight(),
             savgol_increase_window: default_savgol_increase_window(),
             savgol_decrease_window: default_savgol_decrease_window(),
+            savgol_polynomial_order: default_savgol_polynomial_order(),
+            history_size: default_history_size(),
+            update_interval_secs: default_update_interval(),
+            estimator: EstimatorKind::default(),
+            kalman_process_noise: default_kalman_process_noise(),
+            kalman_measurement_noise: default_kalman_measurement_noise(),
+            queue_depth_threshold: default_queue_depth_threshold(),
+            state_max_age_secs: default_state_max_age_secs(),
+            trace_file: None,
         }
     }
 }
@@ -151,4 +310,26 @@ mod tests {
         };
         assert!(config.validate().is_err());
     }
+
+    #[test]
+    fn test_savgol_order_fits_windows() {
+        let mut config = PlatoConfig {
+            savgol_increase_window: 6,
+            savgol_polynomial_order: 5,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.savgol_polynomial_order = 6;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_estimator_from_toml() {
+        let config: PlatoConfig = toml::from_str("estimator = \"kalman\"").unwrap();
+        assert_eq!(config.estimator, EstimatorKind::Kalman);
+
+        let config: PlatoConfig = toml::from_str("").unwrap();
+        assert_eq!(config.estimator, EstimatorKind::Rsi);
+    }
 }
@@ -0,0 +1,176 @@
+//! Presets for classes of network.
+//!
+//! A profile seeds every section of [`RacerConfig`] with values suited to a
+//! link type; a TOML file selects one with a top-level `profile = "..."` key
+//! and any key it sets explicitly still wins over the preset.
+
+use serde::{Deserialize, Serialize};
+
+use super::{ConfigError, EstimatorKind, RacerConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProfile {
+    /// Wired or Wi-Fi LAN: sub-second latency, rare loss.
+    Lan,
+    /// LTE/NB-IoT backhaul: latency of seconds, jitter, metered traffic.
+    Cellular,
+    /// LoRa and similar radios: tens of seconds per hop and frequent loss.
+    Lossy,
+}
+
+impl RacerConfig {
+    /// The minimal config tuned for `profile`.
+    pub fn profile(profile: NetworkProfile) -> Self {
+        let mut config = Self::minimal();
+        config.profile = Some(profile);
+        let (node, network, consensus, plato) =
+            (&mut config.node, &mut config.network, &mut config.consensus, &mut config.plato);
+
+        match profile {
+            NetworkProfile::Lan => {
+                plato.target_latency_secs = 0.5;
+                plato.minimum_latency_secs = 0.1;
+                plato.min_phase_timeout_secs = 1.0;
+                plato.max_gossip_timeout_secs = 10.0;
+                plato.target_publishing_frequency_secs = 0.5;
+                plato.max_publishing_frequency_secs = 5.0;
+                plato.update_interval_secs = 1.0;
+                consensus.health_interval_secs = 10;
+                consensus.health_window_secs = 120;
+                consensus.sequencing_stability_secs = 2.0;
+                consensus.regossip_half_life_secs = 1.0;
+                consensus.batch_ttl_ms = 60_000;
+                node.reconnect_max_ms = 5_000;
+                network.heartbeat_interval_ms = 5_000;
+                network.heartbeat_timeout_ms = 15_000;
+            }
+            NetworkProfile::Cellular => {
+                plato.target_latency_secs = 3.0;
+                plato.minimum_latency_secs = 1.0;
+                plato.min_phase_timeout_secs = 6.0;
+                plato.max_gossip_timeout_secs = 90.0;
+                plato.target_publishing_frequency_secs = 5.0;
+                plato.max_publishing_frequency_secs = 30.0;
+                plato.update_interval_secs = 10.0;
+                plato.estimator = EstimatorKind::Ewma;
+                consensus.echo_sample_size = 5;
+                consensus.ready_sample_size = 5;
+                consensus.delivery_sample_size = 5;
+                consensus.ready_threshold = 3;
+                consensus.feedback_threshold = 4;
+                consensus.delivery_threshold = 5;
+                consensus.health_interval_secs = 60;
+                consensus.health_window_secs = 600;
+                consensus.sequencing_stability_secs = 20.0;
+                consensus.regossip_half_life_secs = 10.0;
+                consensus.batch_ttl_ms = 600_000;
+                node.reconnect_initial_ms = 500;
+                node.reconnect_max_ms = 60_000;
+                // Under the ~30 s idle timeout of many carrier NATs.
+                network.heartbeat_interval_ms = 15_000;
+                network.heartbeat_timeout_ms = 60_000;
+            }
+            NetworkProfile::Lossy => {
+                plato.target_latency_secs = 10.0;
+                plato.minimum_latency_secs = 5.0;
+                plato.min_phase_timeout_secs = 20.0;
+                plato.max_gossip_timeout_secs = 300.0;
+                plato.target_publishing_frequency_secs = 30.0;
+                plato.max_publishing_frequency_secs = 120.0;
+                plato.update_interval_secs = 30.0;
+                plato.estimator = EstimatorKind::Kalman;
+                plato.kalman_measurement_noise = 1.0;
+                // Samples wider than the delivery threshold, so a lost response still clears it.
+                consensus.echo_sample_size = 8;
+                consensus.ready_sample_size = 8;
+                consensus.delivery_sample_size = 8;
+                consensus.ready_threshold = 5;
+                consensus.feedback_threshold = 6;
+                consensus.delivery_threshold = 7;
+                consensus.health_interval_secs = 120;
+                consensus.health_window_secs = 1_200;
+                consensus.sequencing_stability_secs = 60.0;
+                consensus.membership_epoch_secs = 300;
+                consensus.regossip_half_life_secs = 30.0;
+                consensus.max_hops = 8;
+                consensus.batch_ttl_ms = 1_800_000;
+                node.send_queue_capacity = 64;
+                node.coalesce_window_ms = 250;
+                node.max_inflight_submissions = 8;
+                node.reconnect_initial_ms = 1_000;
+                node.reconnect_max_ms = 120_000;
+                network.heartbeat_interval_ms = 30_000;
+                network.heartbeat_timeout_ms = 180_000;
+            }
+        }
+        config
+    }
+
+    /// The parsed TOML `table` laid over the preset its `profile` key names.
+    pub(super) fn apply_profile(mut table: toml::Table) -> Result<toml::Table, ConfigError> {
+        let Some(profile) = table.remove("profile") else {
+            return Ok(table);
+        };
+        let profile: NetworkProfile = profile
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse(format!("profile: {}", e)))?;
+        let mut preset = toml::Table::try_from(Self::profile(profile))
+            .map_err(|e| ConfigError::Parse(format!("profile: {}", e)))?;
+        merge(&mut preset, table);
+        Ok(preset)
+    }
+}
+
+/// Recursively overwrites `base` with `overlay`, keeping keys only in `base`.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_are_valid() {
+        for profile in [NetworkProfile::Lan, NetworkProfile::Cellular, NetworkProfile::Lossy] {
+            let config = RacerConfig::profile(profile);
+            assert!(config.validate().is_ok(), "{:?}: {:?}", profile, config.validate());
+        }
+    }
+
+    #[test]
+    fn test_explicit_keys_override_profile() {
+        let toml = r#"
+            profile = "lossy"
+
+            [plato]
+            target_latency_secs = 12.0
+
+            [consensus]
+            ready_threshold_pct = 0.5
+            feedback_threshold_pct = 0.6
+            delivery_threshold_pct = 0.7
+        "#;
+
+        let config = RacerConfig::from_toml(toml).unwrap();
+        assert_eq!(config.profile, Some(NetworkProfile::Lossy));
+        assert_eq!(config.plato.target_latency_secs, 12.0);
+        assert_eq!(config.plato.min_phase_timeout_secs, 20.0);
+        assert_eq!(config.consensus.echo_sample_size, 8);
+        assert_eq!(config.consensus.ready_threshold_pct, Some(0.5));
+
+        assert!(matches!(
+            RacerConfig::from_toml("profile = \"satellite\""),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+//! Every problem `validate` finds in a config, each under its field path.
+
+use std::fmt;
+
+/// One invalid setting, e.g. `consensus.ready_threshold`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// The violations collected over a whole config, in the order checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Violations(Vec<Violation>);
+
+impl Violations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(Violation { field: field.into(), message: message.into() });
+    }
+
+    /// Records `message` against `field` unless `ok` holds.
+    pub fn check(&mut self, ok: bool, field: &str, message: impl FnOnce() -> String) {
+        if !ok {
+            self.push(field, message());
+        }
+    }
+
+    /// Adds `other`'s violations with their paths under `section`.
+    pub fn extend_section(&mut self, section: &str, other: Violations) {
+        self.0.extend(other.0.into_iter().map(|v| Violation {
+            field: format!("{}.{}", section, v.field),
+            message: v.message,
+        }));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Violation> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether any violation is reported against `field`.
+    pub fn contains(&self, field: &str) -> bool {
+        self.0.iter().any(|v| v.field == field)
+    }
+
+    pub(super) fn into_result(self) -> Result<(), super::ConfigError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(super::ConfigError::Validation(self))
+        }
+    }
+}
+
+impl fmt::Display for Violations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, violation) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Violation> for Violations {
+    fn from(violation: Violation) -> Self {
+        Self(vec![violation])
+    }
+}
+
+impl IntoIterator for Violations {
+    type Item = Violation;
+    type IntoIter = std::vec::IntoIter<Violation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// `host:port` with a non-empty host and a numeric port. IPv6 hosts are
+/// bracketed, as in `[::1]:20001`.
+pub(super) fn is_host_port(address: &str) -> bool {
+    match address.rsplit_once(':') {
+        Some((host, port)) => {
+            let bracketed = host.len() > 2 && host.starts_with('[') && host.ends_with(']');
+            !host.is_empty() && (bracketed || !host.contains(':')) && port.parse::<u16>().is_ok()
+        }
+        None => false,
+    }
+}
+
+/// A `host:port` whose host is `localhost` or a loopback IP.
+pub(super) fn is_loopback(address: &str) -> bool {
+    let Some((host, _)) = address.rsplit_once(':') else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host == "localhost" || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// A ZeroMQ endpoint, `tcp://host:port` or `ipc://path`, or an in-process
+/// `local://name` one.
+pub(super) fn is_zmq_endpoint(address: &str) -> bool {
+    if let Some(rest) = address.strip_prefix("tcp://") {
+        is_host_port(rest)
+    } else if let Some(path) = address.strip_prefix("ipc://") {
+        !path.is_empty()
+    } else if let Some(name) = address.strip_prefix("local://") {
+        !name.is_empty()
+    } else {
+        false
+    }
+}
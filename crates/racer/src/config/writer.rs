@@ -0,0 +1,426 @@
+//! Documented TOML output for [`RacerConfig`].
+//!
+//! [`RacerConfig::to_toml_string`] writes every key under a comment saying
+//! what it does and, when the value was changed, the default it replaced.
+//! Unset optional keys are written as commented-out examples, so the output
+//! doubles as a starter config that parses back to the same config.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::{ConfigError, RacerConfig};
+
+/// Description and, for keys unset by default, a commented-out example.
+struct Doc {
+    path: &'static str,
+    text: &'static str,
+    example: Option<&'static str>,
+}
+
+const fn doc(path: &'static str, text: &'static str) -> Doc {
+    Doc { path, text, example: None }
+}
+
+const fn example(path: &'static str, text: &'static str, example: &'static str) -> Doc {
+    Doc { path, text, example: Some(example) }
+}
+
+const SECTIONS: [&str; 6] = ["node", "network", "consensus", "plato", "peers", "logging"];
+
+const DOCS: &[Doc] = &[
+    doc("profile", "Network preset the values below were laid over: lan, cellular or lossy."),
+    doc("node", "Identity, sockets and send queues of this node."),
+    example("node.id", "Name used in logs and the log directory.", "id = \"node-0\""),
+    doc("node.router_bind", "Endpoint peers send batches to: tcp://host:port, ipc://path, or local://name within one process."),
+    doc("node.publisher_bind", "ZeroMQ endpoint Echo/Ready responses are published on."),
+    doc("node.extra_router_binds", "Further router endpoints, e.g. tcp://[::]:20001 for IPv6 or an ipc:// path."),
+    doc("node.extra_publisher_binds", "Further publisher endpoints bound alongside publisher_bind."),
+    doc("node.selection_type", "How gossip samples are drawn: normal or random (uniform), poisson (uniform, sized around the fan-out) or round_robin (each peer in turn)."),
+    doc("node.role", "Part taken in rounds: full, observer (delivers but never echoes or readies, left out of peers' samples) or forwarder (echoes and re-gossips but hands nothing to its application or submits)."),
+    doc("node.encryption", "Router traffic sealing: off, opportunistic or required (needs peers.pinned_keys)."),
+    example(
+        "node.websocket_bind",
+        "host:port for WebSocket observers; needs the `websocket` feature and not encryption = \"required\".",
+        "websocket_bind = \"127.0.0.1:23001\"",
+    ),
+    example(
+        "node.admin_bind",
+        "host:port for the local admin API used by `racer status`/`racer peers`.",
+        "admin_bind = \"127.0.0.1:22001\"",
+    ),
+    doc(
+        "node.admin_allow_remote",
+        "Allow admin_bind on a non-loopback address; the admin API is unauthenticated.",
+    ),
+    example(
+        "node.key_file",
+        "Secret key written by `racer keygen`, relative to this file. A fresh key is generated when unset.",
+        "key_file = \"node.key\"",
+    ),
+    example(
+        "node.store_path",
+        "Directory for the delivered batch store; needs the `store` feature.",
+        "store_path = \"store\"",
+    ),
+    example(
+        "node.outbox_path",
+        "Directory where local submissions wait until delivered, surviving restarts; needs the `store` feature.",
+        "outbox_path = \"outbox\"",
+    ),
+    example(
+        "node.plato_state_path",
+        "File PLATO's estimate is saved to and resumed from across restarts.",
+        "plato_state_path = \"plato.json\"",
+    ),
+    example(
+        "node.sequence_path",
+        "File the number of the last batch created here is kept in; batches are numbered only with it set.",
+        "sequence_path = \"sequence\"",
+    ),
+    doc("node.send_queue_capacity", "Frames queued per peer before send_overflow applies."),
+    doc("node.send_overflow", "What a full send queue does: drop_oldest, drop_new or block."),
+    doc("node.reconnect_initial_ms", "Delay before retrying a lost peer; doubles per failed attempt."),
+    doc("node.reconnect_max_ms", "Longest delay between reconnect attempts."),
+    doc("node.max_inflight_submissions", "Local submissions gossiped at once; 0 disables the bound."),
+    doc("node.publish_burst", "Local rounds started back to back before pacing at the PLATO publish interval; 0 disables pacing."),
+    doc("node.submit_retry_initial_ms", "Delay before gossiping an undelivered submission again; doubles per round."),
+    doc("node.submit_retry_max_ms", "Longest delay between retries of an undelivered submission."),
+    doc("node.submit_expiry_secs", "Age at which an undelivered submission is dropped; 0 waits for the batch TTL."),
+    doc("node.submit_max_attempts", "Rounds before an undelivered submission is dropped; 1 disables retries, 0 the limit."),
+    doc("node.gossip_shards", "Independently locked shards of round state."),
+    doc("node.coalesce_window_ms", "Wait to bundle frames bound for one peer; 0 sends each at once."),
+    doc("node.coalesce_max_messages", "Frames per bundle; a full bundle is sent at once."),
+    doc("node.relay", "Forward frames for nodes behind NAT that register with this node."),
+    example(
+        "node.relay_via",
+        "Router of a relay to register with when this node's router cannot be dialed.",
+        "relay_via = \"tcp://relay.example:20001\"",
+    ),
+    doc("node.quarantine_capacity", "Rejected inbound frames kept for racer quarantine; 0 keeps none."),
+    doc(
+        "node.low_power",
+        "Fewer background tasks and cheaper congestion estimates, for single-core gateways.",
+    ),
+    example(
+        "node.rng_seed",
+        "Seed for peer selection and PLATO's steps, for runs that repeat exactly; unset draws one per start.",
+        "rng_seed = 42",
+    ),
+    doc("network", "Liveness checks and buffering of peer connections."),
+    doc(
+        "network.heartbeat_interval_ms",
+        "Quiet time after which a peer link sends a heartbeat; 0 disables heartbeats.",
+    ),
+    doc("network.heartbeat_timeout_ms", "Silence after which a peer link is reconnected; must exceed heartbeat_interval_ms."),
+    doc("network.receive_hwm", "Inbound frames buffered per socket before it stops reading."),
+    doc(
+        "network.max_frame_bytes",
+        "Longest inbound frame decoded; longer ones are dropped unparsed. 0 disables the limit.",
+    ),
+    doc("consensus", "AT2 sample sizes, thresholds and gossip limits."),
+    doc("consensus.echo_sample_size", "Peers asked to echo each batch."),
+    doc("consensus.ready_sample_size", "Peers whose Ready responses feed back into this node."),
+    doc("consensus.delivery_sample_size", "Peers whose Ready responses decide delivery."),
+    doc("consensus.ready_threshold", "Echoes needed to send Ready; at least a majority of echo_sample_size."),
+    doc("consensus.feedback_threshold", "Readies needed to send Ready without echoes; at least 75% of ready_sample_size."),
+    doc("consensus.delivery_threshold", "Readies needed to deliver; at least 85% of delivery_sample_size."),
+    example(
+        "consensus.ready_threshold_pct",
+        "Fraction of known peers replacing ready_threshold, capped at echo_sample_size; set all three _pct keys or none.",
+        "ready_threshold_pct = 0.5",
+    ),
+    example(
+        "consensus.feedback_threshold_pct",
+        "Fraction of known peers replacing feedback_threshold.",
+        "feedback_threshold_pct = 0.66",
+    ),
+    example(
+        "consensus.delivery_threshold_pct",
+        "Fraction of known peers replacing delivery_threshold.",
+        "delivery_threshold_pct = 0.8",
+    ),
+    doc("consensus.health_interval_secs", "Seconds between peer health reports."),
+    doc("consensus.health_window_secs", "Seconds of history each health report covers."),
+    doc("consensus.vector_clock_max_entries", "Most vector clock entries carried per batch; 0 disables the bound."),
+    example(
+        "consensus.vector_clock_max_age_secs",
+        "Drops vector clock entries that have not advanced for this long.",
+        "vector_clock_max_age_secs = 3600",
+    ),
+    doc("consensus.sequencing_stability_secs", "How long delivered batches wait before ordered delivery."),
+    doc("consensus.membership_epoch_secs", "Length of a membership epoch."),
+    doc("consensus.open_membership", "Let any key join through a membership update; otherwise only allowed_creators may."),
+    doc("consensus.seen_filter_capacity", "Batch hashes remembered per generation of the duplicate filter."),
+    doc("consensus.seen_filter_fp_rate", "Target false positive rate of the duplicate filter."),
+    doc("consensus.retained_delivered", "Delivered rounds remembered with their batches; the oldest are dropped beyond this."),
+    doc("consensus.delivered_retention_secs", "How long a delivered batch is held after delivery; 0 drops it at the next maintenance pass."),
+    doc("consensus.timed_out_retention_secs", "How long the batch of a timed-out round is held; 0 drops it with the round."),
+    doc("consensus.regossip_half_life_secs", "Batch age halving re-gossip samples; 0 disables the decay."),
+    doc("consensus.regossip_min_fanout", "Smallest re-gossip sample the decay shrinks to."),
+    doc("consensus.max_hops", "Relays a batch created here may pass through; 0 disables the limit."),
+    doc("consensus.batch_ttl_ms", "Age after which batches created here are dropped; 0 disables the limit."),
+    doc("consensus.min_peers_for_submit", "Known peers needed before submissions are accepted; 0 disables the check."),
+    doc("consensus.dedup_window_secs", "Window in which messages with an already delivered id are dropped; 0 disables it."),
+    doc("consensus.validation_timeout_ms", "Time the node's async validator may take per received batch; 0 waits indefinitely."),
+    doc("consensus.max_batch_bytes", "Largest batch accepted, in bytes of its serialized messages; 0 disables the limit."),
+    doc("consensus.max_messages_per_batch", "Most messages a received batch may carry; 0 disables the limit."),
+    doc("consensus.allowed_creators", "Keys whose batches are echoed: hex public keys or hex prefixes ending in *; empty allows any."),
+    doc("consensus.delivery_reports", "Report the delivery latency of each batch delivered here back to its creator."),
+    example(
+        "consensus.channels",
+        "Thresholds for named channels (`Node::submit_on`).",
+        "[consensus.channels.alerts]\nready_threshold = 2\nfeedback_threshold = 3\ndelivery_threshold = 4",
+    ),
+    example(
+        "consensus.priorities",
+        "Thresholds for batches of a priority, unless their channel sets its own.",
+        "[consensus.priorities.high]\nready_threshold_pct = 0.5\nfeedback_threshold_pct = 0.75\ndelivery_threshold_pct = 1.0",
+    ),
+    doc("plato", "PLATO latency targets and congestion estimator."),
+    doc("plato.target_latency_secs", "Gossip latency PLATO steers towards."),
+    doc("plato.target_publishing_frequency_secs", "Publish interval while latency is on target."),
+    doc("plato.max_publishing_frequency_secs", "Longest publish interval under congestion."),
+    doc("plato.minimum_latency_secs", "Lowest latency estimate PLATO accepts."),
+    doc("plato.max_gossip_timeout_secs", "Ceiling for each gossip phase timeout."),
+    doc("plato.min_phase_timeout_secs", "Floor for each gossip phase timeout."),
+    doc("plato.rsi_increase_period", "Samples in the RSI window that raises the latency estimate."),
+    doc("plato.rsi_decrease_period", "Samples in the RSI window that lowers the latency estimate."),
+    doc("plato.rsi_overbought", "RSI level above which latency is raised."),
+    doc("plato.rsi_oversold", "RSI level below which latency is lowered."),
+    doc("plato.own_latency_weight", "Weight of this node's latency against its peers', from 0.0 to 1.0."),
+    doc("plato.savgol_increase_window", "Savitzky-Golay smoothing window for increases."),
+    doc("plato.savgol_decrease_window", "Savitzky-Golay smoothing window for decreases."),
+    doc("plato.savgol_polynomial_order", "Polynomial order of the Savitzky-Golay fits; at most the odd windows minus 2."),
+    doc("plato.history_size", "PLATO samples kept for `racer status`; 0 disables the history."),
+    doc("plato.update_interval_secs", "Seconds between latency estimate updates."),
+    doc("plato.estimator", "Congestion estimator: rsi, ewma or kalman."),
+    doc("plato.kalman_process_noise", "Kalman estimator process noise."),
+    doc("plato.kalman_measurement_noise", "Kalman estimator measurement noise."),
+    doc(
+        "plato.queue_depth_threshold",
+        "Fill of the fullest send queue, 0.0 to 1.0, that throttles regardless of latency; 0 disables it.",
+    ),
+    doc("plato.state_max_age_secs", "Age past which a saved PLATO estimate is discarded at start."),
+    example(
+        "plato.trace_file",
+        "CSV of every latency sample and congestion decision, for racer plato-sim.",
+        "trace_file = \"plato-trace.csv\"",
+    ),
+    doc("peers", "Routers dialed at start."),
+    doc("peers.routers", "Router endpoints of known peers."),
+    example(
+        "peers.seeds",
+        "Bootstrap nodes as host:port or SRV names (SRV needs the `srv` feature), asked for peer lists.",
+        "seeds = [\"seed.racer.example:20001\", \"_racer._tcp.racer.example\"]",
+    ),
+    doc("peers.seed_interval_secs", "How often seeds are resolved and asked for peers again; 0 only at start."),
+    doc("peers.gossip_interval_secs", "How often a sample of known peers is pushed to every peer; 0 disables it."),
+    doc("peers.gossip_sample", "Peers per pushed sample, from 1 to 32."),
+    doc("peers.max_peers", "Stop dialing peers learnt from seeds and gossip at this many; 0 is unlimited."),
+    example(
+        "peers.pinned_keys",
+        "Public keys pinned per router endpoint.",
+        "[peers.pinned_keys]\n\"tcp://10.0.0.2:20001\" = \"<hex public key>\"",
+    ),
+    doc("logging", "Node logs, delivered batch log and telemetry export."),
+    doc("logging.enabled", "Write delivered batches to delivered_file."),
+    doc("logging.log_dir", "Log directory; {node_id} is replaced with node.id."),
+    doc("logging.delivered_file", "File in log_dir delivered batches are appended to."),
+    doc("logging.delivered_format", "Format of delivered_file: jsonl, csv, or parquet (needs the `parquet` feature)."),
+    doc("logging.delivered_fields", "Columns of a csv delivered_file; add \"payload\" for the messages as JSON."),
+    doc("logging.rotation_enabled", "Rotate delivered_file once it reaches max_file_size_mb."),
+    doc("logging.max_file_size_mb", "Size at which delivered_file is rotated."),
+    doc("logging.max_files", "Rotated files kept."),
+    example(
+        "logging.otlp_endpoint",
+        "OTLP/HTTP collector base URL; needs the `telemetry` feature.",
+        "otlp_endpoint = \"http://localhost:4318\"",
+    ),
+    doc("logging.service_name", "`service.name` reported with exported traces and metrics."),
+    doc("logging.trace_sample_ratio", "Fraction of traces to sample, from 0.0 to 1.0."),
+    doc("logging.format", "Console and file output: pretty, or json for log aggregation."),
+    doc("logging.level", "Level of racer events: off, error, warn, info, debug or trace."),
+    example(
+        "logging.levels",
+        "Levels per module; RUST_LOG still takes precedence.",
+        "[logging.levels]\n\"racer::plato\" = \"debug\"",
+    ),
+    example(
+        "logging.file",
+        "File in the CLI's --log-dir receiving console events in `format`.",
+        "file = \"node.log\"",
+    ),
+    doc("logging.rotation", "When log files rotate: size (at max_file_size_mb), hourly or daily."),
+];
+
+impl RacerConfig {
+    /// This config as documented TOML that [`from_toml`](Self::from_toml)
+    /// reads back unchanged.
+    pub fn to_toml_string(&self) -> Result<String, ConfigError> {
+        let table = to_table(self)?;
+        let defaults = to_table(&match self.profile {
+            Some(profile) => Self::profile(profile),
+            None => Self::minimal(),
+        })?;
+
+        let mut out = String::from("# RACER node configuration. Keys left out take the defaults noted below.\n");
+        if let Some(profile) = table.get("profile") {
+            out.push('\n');
+            write_doc(&mut out, lookup("profile").map(|d| d.text), None);
+            let _ = writeln!(out, "profile = {}", profile);
+        }
+        for section in SECTIONS {
+            if let Some(toml::Value::Table(values)) = table.get(section) {
+                let defaults = defaults.get(section).and_then(toml::Value::as_table);
+                write_section(&mut out, section, section, values, defaults);
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn to_table(config: &RacerConfig) -> Result<toml::Table, ConfigError> {
+    toml::Table::try_from(config).map_err(|e| ConfigError::Parse(e.to_string()))
+}
+
+fn lookup(path: &str) -> Option<&'static Doc> {
+    DOCS.iter().find(|d| d.path == path)
+}
+
+/// Writes `[header]` and its keys in [`DOCS`] order, then subtables.
+/// `path` is `header` without key quoting, as [`DOCS`] spells it.
+fn write_section(
+    out: &mut String,
+    path: &str,
+    header: &str,
+    values: &toml::Table,
+    defaults: Option<&toml::Table>,
+) {
+    out.push('\n');
+    write_doc(out, lookup(path).map(|d| d.text), None);
+    let _ = writeln!(out, "[{}]", header);
+
+    let prefix = format!("{}.", path);
+    let documented = DOCS
+        .iter()
+        .filter_map(|d| Some((d.path.strip_prefix(&prefix)?, d)))
+        .filter(|(key, _)| !key.contains('.'));
+    let mut seen = HashSet::new();
+    let mut subtables = Vec::new();
+    let mut examples = Vec::new();
+
+    for (key, d) in documented {
+        seen.insert(key);
+        match values.get(key) {
+            Some(toml::Value::Table(sub)) if sub.is_empty() => examples.push(d),
+            Some(toml::Value::Table(sub)) => subtables.push((key.to_string(), sub)),
+            Some(value) => {
+                let default = defaults.map(|t| t.get(key));
+                write_doc(out, Some(d.text), changed_from(value, default));
+                let _ = writeln!(out, "{} = {}", format_key(key), value);
+            }
+            None => match d.example {
+                Some(example) if !example.starts_with('[') => {
+                    write_doc(out, Some(d.text), None);
+                    write_commented(out, example);
+                }
+                Some(_) => examples.push(d),
+                None => {}
+            },
+        }
+    }
+    for (key, value) in values.iter().filter(|(key, _)| !seen.contains(key.as_str())) {
+        match value {
+            toml::Value::Table(sub) => subtables.push((key.clone(), sub)),
+            value => {
+                let _ = writeln!(out, "{} = {}", format_key(key), value);
+            }
+        }
+    }
+
+    for d in examples {
+        out.push('\n');
+        write_doc(out, Some(d.text), None);
+        write_commented(out, d.example.unwrap_or_default());
+    }
+    for (key, sub) in subtables {
+        let defaults = defaults.and_then(|t| t.get(&key)).and_then(toml::Value::as_table);
+        let sub_path = format!("{}.{}", path, key);
+        let sub_header = format!("{}.{}", header, format_key(&key));
+        write_section(out, &sub_path, &sub_header, sub, defaults);
+    }
+}
+
+/// `Some(default)` for a value that differs from it; `None` without defaults.
+fn changed_from(value: &toml::Value, default: Option<Option<&toml::Value>>) -> Option<String> {
+    match default? {
+        Some(default) if default == value => None,
+        Some(default) => Some(default.to_string()),
+        None => Some("unset".into()),
+    }
+}
+
+fn write_doc(out: &mut String, text: Option<&str>, default: Option<String>) {
+    if let Some(text) = text {
+        let _ = writeln!(out, "# {}", text);
+    }
+    if let Some(default) = default {
+        let _ = writeln!(out, "# (default: {})", default);
+    }
+}
+
+fn write_commented(out: &mut String, text: &str) {
+    for line in text.lines() {
+        let _ = writeln!(out, "# {}", line);
+    }
+}
+
+fn format_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ChannelConfig, NetworkProfile};
+    use crate::crypto::KeyPair;
+
+    #[test]
+    fn test_documented_toml_round_trips() {
+        let mut config = RacerConfig::profile(NetworkProfile::Cellular);
+        config.node.id = Some("node-0".into());
+        config.plato.target_latency_secs = 4.0;
+        config.consensus.channels.insert(
+            "alerts".into(),
+            ChannelConfig::counts(2, 3, 4),
+        );
+        config.peers.routers = vec!["tcp://10.0.0.2:20001".into()];
+        config
+            .peers
+            .pinned_keys
+            .insert("tcp://10.0.0.2:20001".into(), KeyPair::generate().public_key());
+
+        let written = config.to_toml_string().unwrap();
+        assert!(written.contains("# Gossip latency PLATO steers towards.\n# (default: 3.0)\ntarget_latency_secs = 4.0"));
+        assert!(written.contains("# admin_bind = \"127.0.0.1:22001\""));
+        assert!(written.contains("[consensus.channels.alerts]"));
+        assert!(written.contains("\"tcp://10.0.0.2:20001\" = "));
+
+        let parsed = RacerConfig::from_toml(&written).unwrap();
+        assert_eq!(to_table(&parsed).unwrap(), to_table(&config).unwrap());
+    }
+
+    #[test]
+    fn test_minimal_starter_parses() {
+        let written = RacerConfig::minimal().to_toml_string().unwrap();
+        assert!(!written.contains("(default:"));
+        assert!(written.contains("# [consensus.channels.alerts]"));
+
+        let parsed = RacerConfig::from_toml(&written).unwrap();
+        assert_eq!(to_table(&parsed).unwrap(), to_table(&RacerConfig::minimal()).unwrap());
+    }
+}
@@ -0,0 +1,288 @@
+//! Test vectors for implementations of the RACER wire protocol.
+//!
+//! [`fixtures`] builds canonical signed `Echo`, `Response` and
+//! `BatchedMessage` frames from fixed keys and timestamps. Signatures are
+//! RFC 6979 deterministic, so the output is identical on every run and
+//! platform; the copies checked in under `tests/conformance` must match
+//! them. Each [`Fixture`] carries the frame as sent on the wire, the exact
+//! bytes its signatures cover and, for batches, the round hash peers derive
+//! topics from.
+//!
+//! [`verify`] checks a fixture produced by another implementation: its frame
+//! must decode, reproduce the recorded signing bytes and hash, carry valid
+//! signatures and, for batches, messages matching the merkle root. `racer conformance emit` and `racer conformance verify`
+//! expose both from the command line.
+
+use std::collections::BTreeMap;
+
+use racer_core::message::DefaultMessage;
+use racer_core::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{KeyPair, Signer};
+use crate::protocol::{
+    merkle_root, BatchedMessages, Echo, EchoType, Priority, ProtocolMessage, ProtocolResponse, VectorClock,
+};
+
+/// Secret key of the node creating every fixture.
+pub const CREATOR_SECRET: [u8; 32] = [0x01; 32];
+
+/// Secret key of the node relaying the relayed batch fixture.
+pub const RELAY_SECRET: [u8; 32] = [0x02; 32];
+
+/// Epoch millis used for every timestamp in the fixtures.
+pub const FIXTURE_TIMESTAMP: u64 = 1_700_000_000_000;
+
+/// One test vector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub name: String,
+    /// The `ProtocolMessage` frame, as sent on the wire.
+    pub frame: serde_json::Value,
+    /// UTF-8 bytes each signature in the frame covers, by signature field.
+    #[serde(default)]
+    pub signing_bytes: BTreeMap<String, String>,
+    /// [`BatchedMessages::compute_hash`] of a batch frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl Fixture {
+    /// File name the fixture is written to by `racer conformance emit`.
+    pub fn file_name(&self) -> String {
+        format!("{}.json", self.name)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConformanceError {
+    #[error("frame does not decode: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("unsupported message type for a fixture: {0}")]
+    Unsupported(&'static str),
+    #[error("{field} mismatch: expected {expected}, computed {actual}")]
+    Mismatch {
+        field: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("{0} is missing or does not verify")]
+    BadSignature(&'static str),
+    #[error("messages do not match merkle_root: expected {expected}, computed {actual}")]
+    MerkleRoot { expected: String, actual: String },
+}
+
+fn keys(secret: &[u8; 32]) -> KeyPair {
+    KeyPair::from_bytes(secret).expect("fixture secret is a valid P-256 scalar")
+}
+
+fn frame<M: Serialize>(message: ProtocolMessage<M>) -> serde_json::Value {
+    serde_json::to_value(message).expect("protocol messages serialize to JSON")
+}
+
+fn utf8(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).expect("signing bytes are JSON text")
+}
+
+fn batch(relayed: bool) -> BatchedMessages<DefaultMessage> {
+    let creator = keys(&CREATOR_SECRET);
+    let message = DefaultMessage {
+        timestamp: FIXTURE_TIMESTAMP,
+        padding: 7,
+    };
+    let mut vector_clock = VectorClock::new();
+    vector_clock.set("conformance", 1);
+
+    let mut batch = BatchedMessages {
+        batch_id: format!("conformance-{}", message.id()),
+        creator_ecdsa: creator.public_key(),
+        sender_ecdsa: creator.public_key(),
+        merkle_root: merkle_root(std::slice::from_ref(&message)),
+        batch_size: 1,
+        messages: vec![message],
+        vector_clock,
+        creator_signature: None,
+        sender_signature: None,
+        created_at: FIXTURE_TIMESTAMP,
+        membership: Vec::new(),
+        channel: None,
+        hops: 0,
+        max_hops: None,
+        ttl_ms: None,
+        priority: Priority::Normal,
+        sequence: 0,
+        #[cfg(feature = "bls")]
+        creator_bls: None,
+        #[cfg(feature = "bls")]
+        aggregated_signature: None,
+    };
+    if relayed {
+        batch.channel = Some("alerts".to_string());
+        batch.max_hops = Some(16);
+        batch.ttl_ms = Some(300_000);
+        batch.priority = Priority::High;
+    }
+    batch.sign_as_creator(&creator);
+
+    if relayed {
+        let relay = keys(&RELAY_SECRET);
+        batch.sender_ecdsa = relay.public_key();
+        batch.hops = 1;
+        batch.sign_as_sender(&relay);
+    } else {
+        batch.sign_as_sender(&creator);
+    }
+    batch
+}
+
+fn batch_fixture(name: &str, batch: BatchedMessages<DefaultMessage>) -> Fixture {
+    let signing_bytes = BTreeMap::from([
+        ("creator_signature".to_string(), utf8(batch.creator_signing_bytes())),
+        ("sender_signature".to_string(), utf8(batch.sender_signing_bytes())),
+    ]);
+    Fixture {
+        name: name.to_string(),
+        hash: Some(batch.compute_hash()),
+        signing_bytes,
+        frame: frame(ProtocolMessage::BatchedMessages(batch)),
+    }
+}
+
+fn echo_fixture(name: &str, echo_type: EchoType, topic: &str) -> Fixture {
+    let keys = keys(&RELAY_SECRET);
+    let mut echo = Echo::new(echo_type, topic, keys.public_key());
+    echo.timestamp = FIXTURE_TIMESTAMP;
+    echo.sign(&keys);
+    Fixture {
+        name: name.to_string(),
+        signing_bytes: BTreeMap::from([("signature".to_string(), utf8(echo.signing_bytes()))]),
+        hash: None,
+        frame: frame::<DefaultMessage>(ProtocolMessage::Echo(echo)),
+    }
+}
+
+fn response_fixture(name: &str, mut response: ProtocolResponse) -> Fixture {
+    let keys = keys(&CREATOR_SECRET);
+    response.timestamp = FIXTURE_TIMESTAMP;
+    response.sign(&keys);
+    Fixture {
+        name: name.to_string(),
+        signing_bytes: BTreeMap::from([("signature".to_string(), utf8(response.signing_bytes()))]),
+        hash: None,
+        frame: frame::<DefaultMessage>(ProtocolMessage::Response(response)),
+    }
+}
+
+/// The canonical fixtures: a batch as created and as relayed, the Echo and
+/// Ready subscriptions a peer sends for it, and the creator's responses.
+pub fn fixtures() -> Vec<Fixture> {
+    let created = batch_fixture("batched_message", batch(false));
+    let relayed = batch_fixture("batched_message_relayed", batch(true));
+    let topic = created.hash.clone().unwrap_or_default();
+    let creator = keys(&CREATOR_SECRET).public_key();
+
+    vec![
+        echo_fixture("echo_subscribe", EchoType::EchoSubscribe, &topic),
+        echo_fixture("ready_subscribe", EchoType::ReadySubscribe, &topic),
+        response_fixture("echo_response", ProtocolResponse::echo_response(topic.as_str(), creator.clone())),
+        response_fixture("ready_response", ProtocolResponse::ready_response(topic.as_str(), creator)),
+        created,
+        relayed,
+    ]
+}
+
+fn expect(fixture: &Fixture, field: &str, actual: String) -> Result<(), ConformanceError> {
+    let expected = if field == "hash" {
+        fixture.hash.as_ref()
+    } else {
+        fixture.signing_bytes.get(field)
+    };
+    match expected {
+        Some(expected) if *expected != actual => Err(ConformanceError::Mismatch {
+            field: field.to_string(),
+            expected: expected.clone(),
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn signed(ok: bool, field: &'static str) -> Result<(), ConformanceError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(ConformanceError::BadSignature(field))
+    }
+}
+
+/// Checks a fixture, possibly from another implementation. Recorded signing
+/// bytes and hash are compared when present; signatures always are. A
+/// batch's merkle root is recomputed from its messages, read as the
+/// fixtures' [`DefaultMessage`]s, with the leaves the node uses.
+pub fn verify(fixture: &Fixture) -> Result<(), ConformanceError> {
+    let message: ProtocolMessage<serde_json::Value> = serde_json::from_value(fixture.frame.clone())?;
+    match message {
+        ProtocolMessage::Echo(echo) => {
+            expect(fixture, "signature", utf8(echo.signing_bytes()))?;
+            signed(echo.verify(), "signature")
+        }
+        ProtocolMessage::Response(response) => {
+            expect(fixture, "signature", utf8(response.signing_bytes()))?;
+            signed(response.verify(), "signature")
+        }
+        ProtocolMessage::BatchedMessages(batch) => {
+            expect(fixture, "creator_signature", utf8(batch.creator_signing_bytes()))?;
+            expect(fixture, "sender_signature", utf8(batch.sender_signing_bytes()))?;
+            expect(fixture, "hash", batch.compute_hash())?;
+            signed(batch.verify_creator_signature(), "creator_signature")?;
+            signed(batch.verify_sender_signature(), "sender_signature")?;
+            let messages = batch
+                .messages
+                .into_iter()
+                .map(serde_json::from_value::<DefaultMessage>)
+                .collect::<Result<Vec<_>, _>>()?;
+            let actual = merkle_root(&messages);
+            if actual != batch.merkle_root {
+                return Err(ConformanceError::MerkleRoot { expected: batch.merkle_root, actual });
+            }
+            Ok(())
+        }
+        ProtocolMessage::PeerDiscovery(_) => Err(ConformanceError::Unsupported("PeerDiscovery")),
+        ProtocolMessage::PeerChallenge(_) => Err(ConformanceError::Unsupported("PeerChallenge")),
+        ProtocolMessage::HealthSummary(_) => Err(ConformanceError::Unsupported("HealthSummary")),
+        ProtocolMessage::PeerListRequest(_) => Err(ConformanceError::Unsupported("PeerListRequest")),
+        ProtocolMessage::PeerListGossip(_) => Err(ConformanceError::Unsupported("PeerListGossip")),
+        ProtocolMessage::Relay(_) => Err(ConformanceError::Unsupported("Relay")),
+        ProtocolMessage::DeliveryReport(_) => Err(ConformanceError::Unsupported("DeliveryReport")),
+        ProtocolMessage::Bundle { .. } => Err(ConformanceError::Unsupported("Bundle")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_are_deterministic() {
+        assert_eq!(fixtures(), fixtures());
+    }
+
+    #[test]
+    fn test_fixtures_verify() {
+        let fixtures = fixtures();
+        assert_eq!(fixtures.len(), 6);
+        for fixture in &fixtures {
+            verify(fixture).unwrap_or_else(|e| panic!("{}: {}", fixture.name, e));
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering() {
+        let mut fixture = fixtures().into_iter().find(|f| f.name == "batched_message_relayed").unwrap();
+        fixture.frame["payload"]["hops"] = serde_json::json!(2);
+        assert!(matches!(verify(&fixture), Err(ConformanceError::Mismatch { .. })));
+
+        fixture.signing_bytes.clear();
+        assert!(matches!(verify(&fixture), Err(ConformanceError::BadSignature("sender_signature"))));
+    }
+}
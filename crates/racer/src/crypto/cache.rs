@@ -0,0 +1,259 @@
+//! Process-wide memo of ECDSA verification work.
+//!
+//! A node checks the same sender's key over and over: every echo, ready
+//! response and relayed batch from a peer carries its signature, and the
+//! same batch usually arrives from several relays. Two LRU caches cut that
+//! cost: one maps a SEC1-encoded [`PublicKey`] to its decoded `VerifyingKey`,
+//! the other remembers the outcome of verifying a (public key, message hash,
+//! signature) triple. Both outcomes are cached, since a triple always
+//! verifies the same way.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use p256::ecdsa::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+use super::ecdsa::{EcdsaSignature, EcdsaVerifier};
+use super::keys::PublicKey;
+
+/// Verification outcomes remembered.
+pub const SIGNATURE_CACHE_CAPACITY: usize = 8192;
+/// Decoded public keys remembered.
+pub const KEY_CACHE_CAPACITY: usize = 1024;
+
+/// Least-recently-used map; recency is a counter stamped on each access.
+#[derive(Debug)]
+pub(crate) struct Lru<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    recency: BTreeMap<u64, K>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Lru<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &K) -> Option<V> {
+        let (value, stamp) = self.entries.get_mut(key)?;
+        self.recency.remove(stamp);
+        self.tick += 1;
+        *stamp = self.tick;
+        self.recency.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, stamp)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.recency.remove(&stamp);
+        }
+        self.recency.insert(self.tick, key);
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[derive(Debug, Default)]
+struct HitCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HitCounter {
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Key of a cached outcome: the digest of the public key, the message's
+/// SHA-256 and the DER signature, so entries stay small whatever the
+/// message size.
+type SignatureKey = [u8; 32];
+
+struct VerifyCache {
+    signatures: Mutex<Lru<SignatureKey, bool>>,
+    keys: Mutex<Lru<Vec<u8>, VerifyingKey>>,
+    signature_hits: HitCounter,
+    key_hits: HitCounter,
+}
+
+impl VerifyCache {
+    fn new(signature_capacity: usize, key_capacity: usize) -> Self {
+        Self {
+            signatures: Mutex::new(Lru::new(signature_capacity)),
+            keys: Mutex::new(Lru::new(key_capacity)),
+            signature_hits: HitCounter::default(),
+            key_hits: HitCounter::default(),
+        }
+    }
+
+    fn verifying_key(&self, public_key: &PublicKey) -> Option<VerifyingKey> {
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let cached = keys.get(&public_key.as_bytes().to_vec());
+        self.key_hits.record(cached.is_some());
+        if cached.is_some() {
+            return cached;
+        }
+        let key = public_key.to_verifying_key().ok()?;
+        keys.insert(public_key.as_bytes().to_vec(), key);
+        Some(key)
+    }
+
+    fn verify(&self, public_key: &PublicKey, message: &[u8], signature: &EcdsaSignature) -> bool {
+        let key = signature_key(public_key, message, signature);
+        let cached = self
+            .signatures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key);
+        self.signature_hits.record(cached.is_some());
+        if let Some(valid) = cached {
+            return valid;
+        }
+
+        // Verified outside the lock; a racing thread at worst repeats the work.
+        let valid = self
+            .verifying_key(public_key)
+            .is_some_and(|k| EcdsaVerifier::new(k).verify(message, signature).is_ok());
+        self.signatures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, valid);
+        valid
+    }
+
+    fn stats(&self) -> VerifyCacheStats {
+        let load = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        VerifyCacheStats {
+            signature_hits: load(&self.signature_hits.hits),
+            signature_misses: load(&self.signature_hits.misses),
+            key_hits: load(&self.key_hits.hits),
+            key_misses: load(&self.key_hits.misses),
+            cached_signatures: self.signatures.lock().unwrap_or_else(|e| e.into_inner()).len(),
+            cached_keys: self.keys.lock().unwrap_or_else(|e| e.into_inner()).len(),
+        }
+    }
+}
+
+fn signature_key(public_key: &PublicKey, message: &[u8], signature: &EcdsaSignature) -> SignatureKey {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update((public_key.as_bytes().len() as u32).to_be_bytes());
+    hasher.update(public_key.as_bytes());
+    hasher.update(super::sha256(message));
+    hasher.update(signature.to_der());
+    hasher.finalize().into()
+}
+
+fn cache() -> &'static VerifyCache {
+    static CACHE: OnceLock<VerifyCache> = OnceLock::new();
+    CACHE.get_or_init(|| VerifyCache::new(SIGNATURE_CACHE_CAPACITY, KEY_CACHE_CAPACITY))
+}
+
+/// Whether `signature` over `message` verifies under `public_key`, answered
+/// from the cache when this triple was checked before.
+pub fn verify_cached(public_key: &PublicKey, message: &[u8], signature: &EcdsaSignature) -> bool {
+    cache().verify(public_key, message, signature)
+}
+
+/// Decodes `public_key`, reusing an earlier decoding when cached.
+pub fn cached_verifying_key(public_key: &PublicKey) -> Option<VerifyingKey> {
+    cache().verifying_key(public_key)
+}
+
+/// Counters since process start for [`verify_cached`] and
+/// [`cached_verifying_key`].
+pub fn verify_cache_stats() -> VerifyCacheStats {
+    cache().stats()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerifyCacheStats {
+    pub signature_hits: u64,
+    pub signature_misses: u64,
+    pub key_hits: u64,
+    pub key_misses: u64,
+    pub cached_signatures: usize,
+    pub cached_keys: usize,
+}
+
+impl VerifyCacheStats {
+    /// Fraction of signature checks answered from the cache, or 0 before any.
+    pub fn signature_hit_rate(&self) -> f64 {
+        hit_rate(self.signature_hits, self.signature_misses)
+    }
+
+    /// Fraction of public key decodings answered from the cache, or 0 before any.
+    pub fn key_hit_rate(&self) -> f64 {
+        hit_rate(self.key_hits, self.key_misses)
+    }
+}
+
+fn hit_rate(hits: u64, misses: u64) -> f64 {
+    let total = hits + misses;
+    if total == 0 {
+        0.0
+    } else {
+        hits as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{EcdsaSigner, KeyPair};
+
+    #[test]
+    fn test_repeat_verification_hits_cache() {
+        let cache = VerifyCache::new(16, 4);
+        let kp = KeyPair::generate();
+        let signature = EcdsaSigner::new(kp.signing_key().clone()).sign(b"echo");
+
+        assert!(cache.verify(&kp.public_key(), b"echo", &signature));
+        assert!(cache.verify(&kp.public_key(), b"echo", &signature));
+        assert!(!cache.verify(&kp.public_key(), b"forged", &signature));
+        assert!(!cache.verify(&kp.public_key(), b"forged", &signature));
+
+        let stats = cache.stats();
+        assert_eq!((stats.signature_hits, stats.signature_misses), (2, 2));
+        assert_eq!((stats.key_hits, stats.key_misses), (1, 1));
+        assert_eq!(stats.signature_hit_rate(), 0.5);
+        assert_eq!(stats.cached_signatures, 2);
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get(&"a"), Some(1));
+        lru.insert("c", 3);
+
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"a"), Some(1));
+        assert_eq!(lru.get(&"c"), Some(3));
+        assert_eq!(lru.len(), 2);
+    }
+}
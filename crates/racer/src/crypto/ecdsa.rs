@@ -41,7 +41,9 @@ impl EcdsaSignature {
 
 impl std::fmt::Debug for EcdsaSignature {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "EcdsaSignature({}...)", &self.to_base64()[..16])
+        // A DER signature with small scalars encodes to fewer than 16 chars.
+        let b64 = self.to_base64();
+        write!(f, "EcdsaSignature({}...)", b64.get(..16).unwrap_or(&b64))
     }
 }
 
@@ -79,6 +81,10 @@ impl EcdsaSigner {
             bytes: signature.to_der().as_bytes().to_vec(),
         }
     }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_verifying_key(self.signing_key.verifying_key())
+    }
 }
 
 pub struct EcdsaVerifier {
@@ -91,9 +97,7 @@ impl EcdsaVerifier {
     }
 
     pub fn from_public_key(public_key: &PublicKey) -> Result<Self, SignatureError> {
-        let verifying_key = public_key
-            .to_verifying_key()
-            .map_err(|_| SignatureError::InvalidPublicKey)?;
+        let verifying_key = super::cached_verifying_key(public_key).ok_or(SignatureError::InvalidPublicKey)?;
         Ok(Self { verifying_key })
     }
 
@@ -153,4 +157,11 @@ mod tests {
 
         assert_eq!(signature, signature2);
     }
+
+    #[test]
+    fn test_debug_short_signature() {
+        // r = s = 1: valid DER, but only 12 base64 chars.
+        let signature = EcdsaSignature::from_der(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01]).unwrap();
+        assert_eq!(format!("{:?}", signature), "EcdsaSignature(MAYCAQECAQE=...)");
+    }
 }
@@ -47,6 +47,15 @@ impl KeyPair {
         }
     }
 
+    /// Parses a secret key as written by `racer keygen` (hex or base64).
+    pub fn from_encoded(encoded: &str) -> Result<Self, KeyError> {
+        let encoded = encoded.trim();
+        let bytes = hex::decode(encoded)
+            .or_else(|_| base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded))
+            .map_err(|_| KeyError::InvalidSecretKey)?;
+        Self::from_bytes(&bytes)
+    }
+
     pub fn signing_key(&self) -> &SigningKey {
         &self.signing_key
     }
@@ -178,6 +187,21 @@ mod tests {
         assert_eq!(kp1.public_key(), kp2.public_key());
     }
 
+    #[test]
+    fn test_keypair_from_encoded() {
+        let kp = KeyPair::generate();
+        let bytes = kp.to_bytes();
+
+        let from_hex = KeyPair::from_encoded(&format!("{}\n", hex::encode(&bytes))).unwrap();
+        assert_eq!(from_hex.public_key(), kp.public_key());
+
+        let b64 = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &bytes);
+        let from_b64 = KeyPair::from_encoded(&b64).unwrap();
+        assert_eq!(from_b64.public_key(), kp.public_key());
+
+        assert!(KeyPair::from_encoded("not a key").is_err());
+    }
+
     #[test]
     fn test_public_key_hex() {
         let kp = KeyPair::generate();
@@ -1,11 +1,18 @@
+mod cache;
 mod ecdsa;
 mod keys;
+mod signer;
+mod transport;
 
 #[cfg(feature = "bls")]
 mod bls;
 
+pub(crate) use cache::Lru;
+pub use cache::{cached_verifying_key, verify_cache_stats, verify_cached, VerifyCacheStats};
 pub use self::ecdsa::{EcdsaSignature, EcdsaSigner, EcdsaVerifier};
 pub use keys::{KeyPair, PublicKey};
+pub use signer::{AsyncSigner, SignFuture, Signer};
+pub use transport::{TransportError, TransportKeys};
 
 #[cfg(feature = "bls")]
 pub use self::bls::{BlsPublicKey, BlsSecretKey, BlsSignature};
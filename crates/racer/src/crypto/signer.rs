@@ -0,0 +1,164 @@
+//! Signing abstraction for node identities.
+//!
+//! Everything a node signs goes through a [`Signer`] or an [`AsyncSigner`],
+//! so the identity key can live in a secure element (ATECC608), a TPM or a
+//! PKCS#11 token that never exposes it. [`EcdsaSigner`] and [`KeyPair`] are
+//! the in-memory implementations. Every [`Signer`] is also an
+//! [`AsyncSigner`]; devices slow enough to stall the runtime should
+//! implement [`AsyncSigner`] directly, for example by moving the device
+//! call onto `tokio::task::spawn_blocking`.
+//!
+//! With the `bls` feature, a signer may also hold a BLS key for aggregate
+//! batch signatures; [`KeyPair`] does, external devices by default do not,
+//! and their batches then carry none.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use super::ecdsa::{EcdsaSignature, EcdsaSigner};
+use super::keys::{KeyPair, PublicKey};
+#[cfg(feature = "bls")]
+use super::bls::{BlsPublicKey, BlsSignature};
+
+/// Produces P-256 ECDSA signatures for one identity.
+pub trait Signer: Send + Sync {
+    fn sign(&self, msg: &[u8]) -> EcdsaSignature;
+
+    /// The key [`sign`](Self::sign)'s signatures verify under.
+    fn public_key(&self) -> PublicKey;
+
+    /// The BLS key [`bls_sign`](Self::bls_sign)'s signatures verify under,
+    /// if this identity has one.
+    #[cfg(feature = "bls")]
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        None
+    }
+
+    #[cfg(feature = "bls")]
+    fn bls_sign(&self, _msg: &[u8]) -> Option<BlsSignature> {
+        None
+    }
+}
+
+pub type SignFuture<'a> = Pin<Box<dyn Future<Output = EcdsaSignature> + Send + 'a>>;
+
+/// A [`Signer`] whose device round-trip is awaited rather than blocked on.
+pub trait AsyncSigner: Send + Sync {
+    fn sign_async<'a>(&'a self, msg: &'a [u8]) -> SignFuture<'a>;
+
+    fn public_key(&self) -> PublicKey;
+
+    /// As [`Signer::bls_public_key`].
+    #[cfg(feature = "bls")]
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        None
+    }
+
+    /// As [`Signer::bls_sign`].
+    #[cfg(feature = "bls")]
+    fn bls_sign(&self, _msg: &[u8]) -> Option<BlsSignature> {
+        None
+    }
+}
+
+impl<S: Signer + ?Sized> AsyncSigner for S {
+    fn sign_async<'a>(&'a self, msg: &'a [u8]) -> SignFuture<'a> {
+        Box::pin(std::future::ready(self.sign(msg)))
+    }
+
+    fn public_key(&self) -> PublicKey {
+        Signer::public_key(self)
+    }
+
+    #[cfg(feature = "bls")]
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        Signer::bls_public_key(self)
+    }
+
+    #[cfg(feature = "bls")]
+    fn bls_sign(&self, msg: &[u8]) -> Option<BlsSignature> {
+        Signer::bls_sign(self, msg)
+    }
+}
+
+impl Signer for EcdsaSigner {
+    fn sign(&self, msg: &[u8]) -> EcdsaSignature {
+        EcdsaSigner::sign(self, msg)
+    }
+
+    fn public_key(&self) -> PublicKey {
+        EcdsaSigner::public_key(self)
+    }
+}
+
+impl Signer for KeyPair {
+    fn sign(&self, msg: &[u8]) -> EcdsaSignature {
+        EcdsaSigner::new(self.signing_key().clone()).sign(msg)
+    }
+
+    fn public_key(&self) -> PublicKey {
+        KeyPair::public_key(self)
+    }
+
+    #[cfg(feature = "bls")]
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        Some(KeyPair::bls_public_key(self))
+    }
+
+    #[cfg(feature = "bls")]
+    fn bls_sign(&self, msg: &[u8]) -> Option<BlsSignature> {
+        Some(self.bls_secret().sign(msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::EcdsaVerifier;
+
+    /// Stands in for a device that only hands out signatures.
+    struct Device(KeyPair);
+
+    impl AsyncSigner for Device {
+        fn sign_async<'a>(&'a self, msg: &'a [u8]) -> SignFuture<'a> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                Signer::sign(&self.0, msg)
+            })
+        }
+
+        fn public_key(&self) -> PublicKey {
+            self.0.public_key()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signers_verify_under_their_public_key() {
+        let kp = KeyPair::generate();
+        let signers: Vec<Box<dyn AsyncSigner>> = vec![
+            Box::new(kp.clone()),
+            Box::new(EcdsaSigner::new(kp.signing_key().clone())),
+            Box::new(Device(kp.clone())),
+        ];
+
+        for signer in signers {
+            assert_eq!(signer.public_key(), kp.public_key());
+            let signature = signer.sign_async(b"ready").await;
+            let verifier = EcdsaVerifier::from_public_key(&signer.public_key()).unwrap();
+            assert!(verifier.verify(b"ready", &signature).is_ok());
+        }
+    }
+
+    #[cfg(feature = "bls")]
+    #[test]
+    fn test_only_key_pairs_sign_bls() {
+        let kp = KeyPair::generate();
+        let signature = AsyncSigner::bls_sign(&kp, b"batch").unwrap();
+        let public_key = AsyncSigner::bls_public_key(&kp).unwrap();
+        assert!(public_key.verify(&signature, b"batch").is_ok());
+
+        let device = Device(kp);
+        assert!(device.bls_public_key().is_none());
+        assert!(device.bls_sign(b"batch").is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! Frame encryption for point-to-point transport.
+//!
+//! The node's long-term ECDSA key doubles as its static P-256 key agreement
+//! key, so peers that already know each other's `PublicKey` can derive a
+//! shared ChaCha20-Poly1305 key without a handshake:
+//!
+//! ```text
+//! key = HKDF-SHA256(ECDH(our_secret, their_public), salt, sorted(pub_a, pub_b))
+//! ```
+//!
+//! Only the holders of either secret can derive the key, so a frame that opens
+//! correctly both hides the payload and authenticates its sender.
+//!
+//! Sealed frame layout: `MAGIC (4) | sender public key (33) | nonce (12) | ciphertext`.
+
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use p256::ecdh;
+use sha2::Sha256;
+
+use super::cache::Lru;
+use super::keys::{KeyPair, PublicKey};
+
+const MAGIC: &[u8; 4] = b"RCE1";
+const PUBLIC_KEY_LEN: usize = 33;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + PUBLIC_KEY_LEN + NONCE_LEN;
+const KDF_SALT: &[u8] = b"racer-transport-v1";
+
+/// Session keys remembered. Any sender can make us derive one, so the
+/// least recently used are dropped and derived again when needed.
+pub const SESSION_KEY_CAPACITY: usize = 1024;
+
+/// Static key agreement state for one node.
+pub struct TransportKeys {
+    keys: KeyPair,
+    public: PublicKey,
+    session_keys: Mutex<Lru<PublicKey, Key>>,
+}
+
+impl TransportKeys {
+    pub fn new(keys: KeyPair) -> Self {
+        let public = keys.public_key();
+        Self {
+            keys,
+            public,
+            session_keys: Mutex::new(Lru::new(SESSION_KEY_CAPACITY)),
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public
+    }
+
+    /// Returns `true` if `frame` carries the sealed-frame header.
+    pub fn is_sealed(frame: &[u8]) -> bool {
+        frame.len() >= HEADER_LEN && frame.starts_with(MAGIC)
+    }
+
+    /// Encrypts `plaintext` so that only `remote` (and we) can open it.
+    pub fn seal(&self, remote: &PublicKey, plaintext: &[u8]) -> Result<Vec<u8>, TransportError> {
+        let cipher = ChaCha20Poly1305::new(&self.session_key(remote)?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| TransportError::Seal)?;
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        frame.extend_from_slice(MAGIC);
+        frame.extend_from_slice(self.public.as_bytes());
+        frame.extend_from_slice(&nonce);
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+
+    /// Decrypts a sealed frame, returning the sender's public key and the payload.
+    pub fn open(&self, frame: &[u8]) -> Result<(PublicKey, Vec<u8>), TransportError> {
+        if !Self::is_sealed(frame) {
+            return Err(TransportError::NotSealed);
+        }
+
+        let (key_bytes, rest) = frame[MAGIC.len()..].split_at(PUBLIC_KEY_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| TransportError::Open)?;
+        let sender = PublicKey::from_bytes(key_bytes).map_err(|_| TransportError::InvalidKey)?;
+
+        let cipher = ChaCha20Poly1305::new(&self.session_key(&sender)?);
+        let plaintext = cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| TransportError::Open)?;
+        Ok((sender, plaintext))
+    }
+
+    fn session_key(&self, remote: &PublicKey) -> Result<Key, TransportError> {
+        let mut cache = self.session_keys.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(key) = cache.get(remote) {
+            return Ok(key);
+        }
+
+        let remote_key = p256::PublicKey::from_sec1_bytes(remote.as_bytes())
+            .map_err(|_| TransportError::InvalidKey)?;
+        let shared = ecdh::diffie_hellman(
+            self.keys.signing_key().as_nonzero_scalar(),
+            remote_key.as_affine(),
+        );
+
+        let (first, second) = if self.public.as_bytes() <= remote.as_bytes() {
+            (self.public.as_bytes(), remote.as_bytes())
+        } else {
+            (remote.as_bytes(), self.public.as_bytes())
+        };
+        let info = [first, second].concat();
+
+        let mut key = Key::default();
+        shared
+            .extract::<Sha256>(Some(KDF_SALT))
+            .expand(&info, &mut key)
+            .map_err(|_| TransportError::InvalidKey)?;
+
+        cache.insert(remote.clone(), key);
+        Ok(key)
+    }
+}
+
+impl std::fmt::Debug for TransportKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransportKeys")
+            .field("public_key", &self.public)
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error("frame is not sealed")]
+    NotSealed,
+    #[error("invalid transport public key")]
+    InvalidKey,
+    #[error("failed to seal frame")]
+    Seal,
+    #[error("failed to open frame")]
+    Open,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_roundtrip() {
+        let alice = TransportKeys::new(KeyPair::generate());
+        let bob = TransportKeys::new(KeyPair::generate());
+
+        let frame = alice.seal(bob.public_key(), b"hello").unwrap();
+        assert!(TransportKeys::is_sealed(&frame));
+
+        let (sender, plaintext) = bob.open(&frame).unwrap();
+        assert_eq!(&sender, alice.public_key());
+        assert_eq!(plaintext, b"hello");
+    }
+
+    #[test]
+    fn test_third_party_cannot_open() {
+        let alice = TransportKeys::new(KeyPair::generate());
+        let bob = TransportKeys::new(KeyPair::generate());
+        let eve = TransportKeys::new(KeyPair::generate());
+
+        let frame = alice.seal(bob.public_key(), b"hello").unwrap();
+        assert!(eve.open(&frame).is_err());
+    }
+
+    #[test]
+    fn test_tampered_frame_rejected() {
+        let alice = TransportKeys::new(KeyPair::generate());
+        let bob = TransportKeys::new(KeyPair::generate());
+
+        let mut frame = alice.seal(bob.public_key(), b"hello").unwrap();
+        let last = frame.len() - 1;
+        frame[last] ^= 0x01;
+        assert!(bob.open(&frame).is_err());
+    }
+
+    #[test]
+    fn test_plaintext_is_not_sealed() {
+        assert!(!TransportKeys::is_sealed(br#"{"type":"Echo"}"#));
+    }
+}
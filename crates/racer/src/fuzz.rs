@@ -0,0 +1,262 @@
+//! Fuzzing entry points and [`Arbitrary`] impls for wire types.
+//!
+//! Every frame a node parses comes from the network, so no input may panic
+//! it. The targets under `crates/racer/fuzz` call the functions here, which
+//! run raw bytes through the same decode, unbundle and verify stages a
+//! router or subscriber frame takes, then touch everything the node derives
+//! from a decoded message: hashes, signing bytes, ids and `Debug` output.
+//! [`roundtrip`] takes structured input instead, generated by the
+//! [`Arbitrary`] impls below, and checks that encoding is lossless.
+//!
+//! ```bash
+//! cargo install cargo-fuzz
+//! cd crates/racer/fuzz && cargo +nightly fuzz run protocol_message
+//! ```
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use bytes::Bytes;
+use racer_core::message::DefaultMessage;
+
+use crate::crypto::{EcdsaSignature, KeyPair, PublicKey, Signer};
+use crate::node::pipeline::{self, Frame, Inbound, Verdict};
+use crate::protocol::{
+    BatchedMessages, Echo, EchoType, Priority, ProtocolMessage, ProtocolResponse, VectorClock,
+};
+
+/// Secret used when the fuzzer's 32 bytes are not a valid P-256 scalar.
+const FALLBACK_SECRET: [u8; 32] = [0x01; 32];
+
+fn keys(u: &mut Unstructured<'_>) -> Result<KeyPair> {
+    let secret: [u8; 32] = u.arbitrary()?;
+    Ok(KeyPair::from_bytes(&secret)
+        .or_else(|_| KeyPair::from_bytes(&FALLBACK_SECRET))
+        .expect("fallback secret is a valid P-256 scalar"))
+}
+
+/// A signature that is valid, one that is well-formed but signs other
+/// bytes, or none.
+fn signature(u: &mut Unstructured<'_>, keys: &KeyPair, signing_bytes: &[u8]) -> Result<Option<EcdsaSignature>> {
+    Ok(match u.int_in_range(0..=2)? {
+        0 => Some(keys.sign(signing_bytes)),
+        1 => Some(u.arbitrary()?),
+        _ => None,
+    })
+}
+
+impl<'a> Arbitrary<'a> for PublicKey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(keys(u)?.public_key())
+    }
+}
+
+impl<'a> Arbitrary<'a> for EcdsaSignature {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let keys = keys(u)?;
+        let message: &[u8] = u.arbitrary()?;
+        Ok(keys.sign(message))
+    }
+}
+
+impl<'a> Arbitrary<'a> for VectorClock {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut clock = VectorClock::new();
+        for entry in u.arbitrary_iter::<(String, u64)>()? {
+            let (node_id, time) = entry?;
+            clock.set(&node_id, time);
+        }
+        Ok(clock)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Priority {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&Priority::ALL)?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for EchoType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[EchoType::EchoSubscribe, EchoType::ReadySubscribe])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Echo {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let keys = keys(u)?;
+        let mut echo = Echo::new(u.arbitrary()?, String::arbitrary(u)?, keys.public_key());
+        echo.timestamp = u.arbitrary()?;
+        echo.signature = signature(u, &keys, &echo.signing_bytes())?;
+        Ok(echo)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ProtocolResponse {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let keys = keys(u)?;
+        let topic = String::arbitrary(u)?;
+        let mut response = if u.arbitrary()? {
+            ProtocolResponse::echo_response(topic, keys.public_key())
+        } else {
+            ProtocolResponse::ready_response(topic, keys.public_key())
+        };
+        response.timestamp = u.arbitrary()?;
+        response.signature = signature(u, &keys, &response.signing_bytes())?;
+        Ok(response)
+    }
+}
+
+impl<'a, M> Arbitrary<'a> for BatchedMessages<M>
+where
+    M: Arbitrary<'a> + serde::Serialize + Clone,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let creator = keys(u)?;
+        let sender = if u.arbitrary()? { keys(u)? } else { creator.clone() };
+        let messages: Vec<M> = u.arbitrary()?;
+
+        let mut batch = BatchedMessages {
+            batch_id: u.arbitrary()?,
+            creator_ecdsa: creator.public_key(),
+            sender_ecdsa: sender.public_key(),
+            merkle_root: u.arbitrary()?,
+            batch_size: messages.len(),
+            messages,
+            vector_clock: u.arbitrary()?,
+            creator_signature: None,
+            sender_signature: None,
+            created_at: u.arbitrary()?,
+            membership: Vec::new(),
+            channel: u.arbitrary()?,
+            hops: u.arbitrary()?,
+            max_hops: u.arbitrary()?,
+            ttl_ms: u.arbitrary()?,
+            priority: u.arbitrary()?,
+            sequence: u.arbitrary()?,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        };
+        batch.creator_signature = signature(u, &creator, &batch.creator_signing_bytes())?;
+        batch.sender_signature = signature(u, &sender, &batch.sender_signing_bytes())?;
+        Ok(batch)
+    }
+}
+
+impl<'a, M> Arbitrary<'a> for ProtocolMessage<M>
+where
+    M: Arbitrary<'a> + serde::Serialize + Clone,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=3)? {
+            0 => ProtocolMessage::BatchedMessages(u.arbitrary()?),
+            1 => ProtocolMessage::Echo(u.arbitrary()?),
+            2 => ProtocolMessage::Response(u.arbitrary()?),
+            _ => ProtocolMessage::Bundle {
+                messages: u.arbitrary_iter()?.collect::<Result<_>>()?,
+            },
+        })
+    }
+}
+
+/// Touches everything the node derives from a decoded batch.
+fn inspect_batch<M: serde::Serialize + Clone + std::fmt::Debug>(batch: &BatchedMessages<M>) {
+    let _ = format!("{:?}", batch);
+    let _ = batch.compute_hash();
+    let _ = batch.verify_creator_signature();
+    let _ = batch.verify_sender_signature();
+    let _ = batch.is_expired(u64::MAX);
+    let _ = batch.is_expired(0);
+    let _ = batch.hop_limit_reached();
+    let _ = batch.relay_copy(batch.sender_ecdsa.clone()).sender_signing_bytes();
+    #[cfg(feature = "bls")]
+    let _ = batch.verify_aggregated_signature();
+    let _ = batch.vector_clock.sum();
+}
+
+/// Runs `data` through the pipeline's decode, unbundle and verify stages as
+/// both a router request and a subscriber response.
+pub fn protocol_message(data: &[u8]) {
+    let content = Bytes::copy_from_slice(data);
+    let frames = [
+        Frame::Router { identity: b"fuzz".to_vec(), content: content.clone() },
+        Frame::Subscriber { topic: "fuzz-echo".to_string(), content },
+    ];
+
+    for frame in frames {
+        let Ok(inbound) = pipeline::decode::<DefaultMessage>(frame) else {
+            continue;
+        };
+        for inbound in pipeline::unbundle(inbound) {
+            match &inbound {
+                Inbound::Request { message: ProtocolMessage::BatchedMessages(batch), .. } => inspect_batch(batch),
+                Inbound::Request { message: ProtocolMessage::Echo(echo), .. } => {
+                    let _ = echo.sender_id();
+                }
+                Inbound::Request { message: ProtocolMessage::HealthSummary(summary), .. } => {
+                    let _ = summary.sender_id();
+                    let _ = summary.delivery_rate();
+                }
+                Inbound::Request { message: ProtocolMessage::Relay(frame), .. } => {
+                    let _ = frame.sender_id();
+                    let _ = frame.signing_bytes();
+                }
+                Inbound::Response(response) => {
+                    let _ = response.sender_id();
+                }
+                _ => {}
+            }
+            if let Verdict::Accept(accepted) = pipeline::verify(inbound) {
+                let _ = format!("{:?}", accepted);
+            }
+        }
+    }
+}
+
+/// Parses `data` as a bare batch carrying arbitrary JSON payloads.
+pub fn batched_messages(data: &[u8]) {
+    if let Ok(batch) = serde_json::from_slice::<BatchedMessages<serde_json::Value>>(data) {
+        inspect_batch(&batch);
+    }
+}
+
+/// Parses two clocks from `data` and exercises every comparison and prune.
+pub fn vector_clock(data: &[u8]) {
+    let Ok((mut a, b)) = serde_json::from_slice::<(VectorClock, VectorClock)>(data) else {
+        return;
+    };
+    let _ = a.happens_before(&b);
+    let _ = b.happens_before(&a);
+    let _ = a.concurrent(&b);
+    let _ = a.sum();
+    a.merge(&b);
+    for node_id in b.nodes() {
+        a.increment(node_id);
+    }
+    a.prune_keeping(1, "fuzz");
+    a.prune(0);
+    a.prune_older_than(u64::MAX);
+}
+
+/// Parses `data` as the encodings keys and signatures arrive in.
+pub fn crypto_encodings(data: &[u8]) {
+    if let Ok(key) = PublicKey::from_bytes(data) {
+        let _ = format!("{:?} {}", key, key);
+    }
+    if let Ok(signature) = EcdsaSignature::from_der(data) {
+        let _ = format!("{:?}", signature);
+    }
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = PublicKey::from_hex(text);
+        let _ = EcdsaSignature::from_base64(text);
+        let _ = KeyPair::from_encoded(text);
+    }
+}
+
+/// Encoding a message and decoding it again must give back the same frame.
+pub fn roundtrip(message: ProtocolMessage<String>) {
+    let encoded = serde_json::to_value(&message).expect("protocol messages serialize to JSON");
+    let decoded: ProtocolMessage<String> =
+        serde_json::from_value(encoded.clone()).expect("encoded frames decode");
+    assert_eq!(serde_json::to_value(&decoded).expect("protocol messages serialize to JSON"), encoded);
+}
@@ -35,23 +35,39 @@
 //!
 //! - `bls`: Enable BLS signature aggregation (requires `blst` C library)
 //! - `cli`: Enable CLI binary with logging and key generation
+//! - `websocket`: Enable the WebSocket observer listener (`node.websocket_bind`)
+//! - `store`: Persist delivered batches (`node.store_path`) and pending submissions (`node.outbox_path`) to disk; source of `racer export`
+//! - `telemetry`: Export traces and metrics over OTLP (`logging.otlp_endpoint`)
+//! - `srv`: Resolve SRV names in `peers.seeds`
+//! - `fuzz`: `Arbitrary` impls and entry points for the targets in `crates/racer/fuzz`
 
 pub mod config;
+pub mod conformance;
 pub mod crypto;
 pub mod network;
 pub mod plato;
 pub mod protocol;
 pub mod node;
+pub mod testing;
 pub mod util;
 
+#[cfg(feature = "store")]
+pub mod store;
+
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+
 #[cfg(feature = "cli")]
 pub mod cli;
 
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+
 pub use racer_core::{Message, RacerError, ValidationError};
 
 pub mod prelude {
     pub use racer_core::Message;
-    pub use racer_macros::racer_message;
+    pub use racer_macros::{racer_message, racer_messages, RacerMessage};
 
     pub use crate::config::RacerConfig;
     pub use crate::node::Node;
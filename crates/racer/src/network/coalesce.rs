@@ -0,0 +1,190 @@
+//! Coalescing of dealer frames into multi-message bundles.
+//!
+//! Every Echo request and batch relayed to a peer is otherwise its own ZMQ
+//! message, paying framing, sealing and a router reply each. A [`Coalescer`]
+//! holds the frames bound for one peer at one [`Priority`] for up to
+//! `window`, then sends them as a single `Bundle` frame:
+//!
+//! ```text
+//! {"v":3,"type":"Bundle","payload":{"messages":[<frame>,<frame>,...]}}
+//! ```
+//!
+//! Each `<frame>` is an encoded `ProtocolMessage`, copied verbatim, so the
+//! envelope decodes as `ProtocolMessage::Bundle` and its messages are
+//! screened and answered one by one; see [`crate::protocol::envelope`]. A buffer is flushed early once it holds
+//! `max_messages`; a lone frame is sent as is.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use super::{NetworkError, RacerNetwork};
+use crate::protocol::Priority;
+
+/// An encoded empty `Bundle` is `BUNDLE_HEAD` then `BUNDLE_TAIL`, at the
+/// current protocol version.
+const BUNDLE_HEAD: &[u8] = br#"{"v":3,"type":"Bundle","payload":{"messages":["#;
+const BUNDLE_TAIL: &[u8] = b"]}}";
+
+/// How long and how many frames a [`Coalescer`] holds before sending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoalescePolicy {
+    /// Longest a frame waits for company; zero sends every frame at once.
+    pub window: Duration,
+    pub max_messages: usize,
+}
+
+impl CoalescePolicy {
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero() && self.max_messages > 1
+    }
+}
+
+/// Wraps encoded `ProtocolMessage`s in one `Bundle` envelope.
+pub fn bundle(frames: &[Bytes]) -> Bytes {
+    let len = BUNDLE_HEAD.len() + BUNDLE_TAIL.len() + frames.iter().map(|f| f.len() + 1).sum::<usize>();
+    let mut out = BytesMut::with_capacity(len);
+    out.put_slice(BUNDLE_HEAD);
+    for (i, frame) in frames.iter().enumerate() {
+        if i > 0 {
+            out.put_u8(b',');
+        }
+        out.put_slice(frame);
+    }
+    out.put_slice(BUNDLE_TAIL);
+    out.freeze()
+}
+
+type Pending = HashMap<(String, Priority), Vec<Bytes>>;
+
+/// Sends frames to peers through `network`, bundling those that arrive
+/// within one window. Clones share the same buffers.
+#[derive(Clone)]
+pub struct Coalescer {
+    network: Arc<RacerNetwork>,
+    policy: CoalescePolicy,
+    pending: Arc<Mutex<Pending>>,
+}
+
+impl Coalescer {
+    pub fn new(network: Arc<RacerNetwork>, policy: CoalescePolicy) -> Self {
+        Self {
+            network,
+            policy,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Queues `frame` for `peer_id`. With coalescing disabled this is
+    /// [`RacerNetwork::send_to_peer_with_priority`]; otherwise send errors
+    /// surface only in the log, once the bundle is flushed.
+    pub async fn send(&self, peer_id: &str, frame: Bytes, priority: Priority) -> Result<(), NetworkError> {
+        if !self.policy.is_enabled() {
+            return self.network.send_to_peer_with_priority(peer_id, frame, priority).await;
+        }
+
+        let key = (peer_id.to_string(), priority);
+        let (first, full) = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            let frames = pending.entry(key.clone()).or_default();
+            frames.push(frame);
+            (frames.len() == 1, frames.len() >= self.policy.max_messages)
+        };
+
+        if full {
+            self.flush(&key).await;
+        } else if first {
+            let coalescer = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(coalescer.policy.window).await;
+                coalescer.flush(&key).await;
+            });
+        }
+        Ok(())
+    }
+
+    /// Sends whatever is buffered for every peer now.
+    pub async fn flush_all(&self) {
+        let keys: Vec<_> = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+        for key in keys {
+            self.flush(&key).await;
+        }
+    }
+
+    async fn flush(&self, key: &(String, Priority)) {
+        let frames = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key)
+            .unwrap_or_default();
+        let frame = match frames.len() {
+            0 => return,
+            1 => frames.into_iter().next().unwrap_or_default(),
+            _ => bundle(&frames),
+        };
+
+        let (peer_id, priority) = key;
+        if let Err(e) = self.network.send_to_peer_with_priority(peer_id, frame, *priority).await {
+            tracing::debug!(peer_id, error = %e, "send of coalesced frames failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ProtocolMessage;
+
+    #[test]
+    fn test_bundle_envelope() {
+        let frames = [
+            Bytes::from_static(br#"{"v":3,"type":"Echo","payload":{"topic":"a"}}"#),
+            Bytes::from_static(br#"{"v":3,"type":"Echo","payload":{"topic":"b"}}"#),
+        ];
+        let envelope = bundle(&frames);
+        let value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+        assert_eq!(value["type"], "Bundle");
+        assert_eq!(value["payload"]["messages"][1]["payload"]["topic"], "b");
+
+        let empty = ProtocolMessage::<String>::Bundle { messages: Vec::new() };
+        assert_eq!(serde_json::to_vec(&empty).unwrap(), [BUNDLE_HEAD, BUNDLE_TAIL].concat());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_policy_sends_directly() {
+        let network = Arc::new(RacerNetwork::new("tcp://127.0.0.1:27411", "tcp://127.0.0.1:27412"));
+        let coalescer = Coalescer::new(
+            network,
+            CoalescePolicy { window: Duration::ZERO, max_messages: 16 },
+        );
+        assert!(matches!(
+            coalescer.send("nobody", Bytes::from_static(b"{}"), Priority::Normal).await,
+            Err(NetworkError::PeerNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_frames_are_held_until_flushed() {
+        let network = Arc::new(RacerNetwork::new("tcp://127.0.0.1:27413", "tcp://127.0.0.1:27414"));
+        let coalescer = Coalescer::new(
+            network,
+            CoalescePolicy { window: Duration::from_secs(60), max_messages: 3 },
+        );
+        for _ in 0..2 {
+            coalescer.send("p", Bytes::from_static(b"{}"), Priority::Normal).await.unwrap();
+        }
+        assert_eq!(coalescer.pending.lock().unwrap()[&("p".to_string(), Priority::Normal)].len(), 2);
+
+        coalescer.send("p", Bytes::from_static(b"{}"), Priority::Normal).await.unwrap();
+        assert!(coalescer.pending.lock().unwrap().is_empty());
+    }
+}
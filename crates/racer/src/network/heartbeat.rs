@@ -0,0 +1,117 @@
+//! Liveness checks for dealer links.
+//!
+//! A TCP connection whose far end vanished without closing it, say behind a
+//! cellular modem that changed cells or a NAT that dropped its mapping, still
+//! accepts writes until the kernel gives up retransmitting, and an idle one
+//! never notices at all. The zeromq crate exposes neither ZMTP heartbeats nor
+//! TCP keepalive, so links check themselves: a dealer worker that has heard
+//! nothing from its peer for `interval` sends an empty frame, which the
+//! peer's router answers at once, and a link silent for `timeout` is dropped
+//! and reconnected like one that failed. The heartbeats also keep NAT
+//! mappings on the path from expiring.
+
+use std::time::{Duration, Instant};
+
+/// Default quiet time before a link sends a heartbeat.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// Default silence after which a link is taken for dead.
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl HeartbeatPolicy {
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        let interval = interval.max(Duration::from_millis(1));
+        Self {
+            interval,
+            timeout: timeout.max(interval),
+        }
+    }
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEARTBEAT_INTERVAL, DEFAULT_HEARTBEAT_TIMEOUT)
+    }
+}
+
+/// What a link should do at a heartbeat tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pulse {
+    /// Heard from the peer within the interval.
+    Alive,
+    /// Quiet for an interval: send a heartbeat.
+    Quiet,
+    /// Silent past the timeout: reconnect.
+    Dead,
+}
+
+/// When a link last heard from its peer.
+#[derive(Debug)]
+pub(crate) struct Liveness {
+    policy: HeartbeatPolicy,
+    last_heard: Instant,
+}
+
+impl Liveness {
+    pub(crate) fn new(policy: HeartbeatPolicy) -> Self {
+        Self {
+            policy,
+            last_heard: Instant::now(),
+        }
+    }
+
+    pub(crate) fn heard(&mut self) {
+        self.last_heard = Instant::now();
+    }
+
+    pub(crate) fn pulse(&self, now: Instant) -> Pulse {
+        let silent = now.saturating_duration_since(self.last_heard);
+        if silent >= self.policy.timeout {
+            Pulse::Dead
+        } else if silent >= self.policy.interval {
+            Pulse::Quiet
+        } else {
+            Pulse::Alive
+        }
+    }
+
+    /// Ticks every interval, the first one interval from now.
+    pub(crate) fn ticker(&self) -> tokio::time::Interval {
+        let mut ticker = tokio::time::interval_at(
+            tokio::time::Instant::now() + self.policy.interval,
+            self.policy.interval,
+        );
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pulse_follows_silence() {
+        let policy = HeartbeatPolicy::new(Duration::from_secs(10), Duration::from_secs(30));
+        let mut liveness = Liveness::new(policy);
+        let start = Instant::now();
+
+        assert_eq!(liveness.pulse(start), Pulse::Alive);
+        assert_eq!(liveness.pulse(start + Duration::from_secs(12)), Pulse::Quiet);
+        assert_eq!(liveness.pulse(start + Duration::from_secs(31)), Pulse::Dead);
+
+        liveness.heard();
+        assert_eq!(liveness.pulse(Instant::now()), Pulse::Alive);
+    }
+
+    #[test]
+    fn test_timeout_is_at_least_interval() {
+        let policy = HeartbeatPolicy::new(Duration::from_secs(10), Duration::from_secs(1));
+        assert_eq!(policy.timeout, Duration::from_secs(10));
+    }
+}
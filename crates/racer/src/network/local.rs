@@ -0,0 +1,267 @@
+//! In-process transport for nodes sharing one process.
+//!
+//! Tests, simulations and gateways hosting several logical nodes need not
+//! talk to one another through sockets. A node binding `local://<name>`
+//! (its id, say) registers its router or publisher on a [`LocalBus`], and
+//! peers dialing that endpoint hand frames straight to its inbox. Frames
+//! travel as [`Bytes`], so they are shared rather than copied, and skip
+//! ZeroMQ framing and the kernel altogether. Sealing and signatures apply
+//! as on any other link.
+//!
+//! Like PUB sockets, local publishers drop frames for subscribers that fall
+//! behind rather than wait for them.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+
+use super::sockets::CHANNEL_BUFFER;
+use super::NetworkError;
+
+pub const LOCAL_SCHEME: &str = "local://";
+
+/// Router identities handed to local dealers, unique within the process.
+static NEXT_IDENTITY: AtomicU64 = AtomicU64::new(0);
+
+/// Whether `address` is a `local://` endpoint.
+pub fn is_local(address: &str) -> bool {
+    address.starts_with(LOCAL_SCHEME)
+}
+
+/// Reply mailboxes of the dealers connected to a local router, by identity.
+pub(crate) type LocalClients = Arc<Mutex<HashMap<Vec<u8>, mpsc::Sender<Bytes>>>>;
+
+/// Subscribers attached to a local publisher.
+#[derive(Clone, Default)]
+pub(crate) struct LocalPublisher {
+    subscribers: Arc<Mutex<Vec<LocalSubscriber>>>,
+}
+
+/// A subscriber's inbox and the topic prefixes it accepts.
+#[derive(Clone)]
+pub(crate) struct LocalSubscriber {
+    pub(crate) topics: Arc<Mutex<HashSet<String>>>,
+    pub(crate) inbox: mpsc::Sender<(String, Bytes)>,
+}
+
+#[derive(Clone)]
+enum Endpoint {
+    Router {
+        inbox: mpsc::Sender<(Vec<u8>, Bytes)>,
+        clients: LocalClients,
+    },
+    Publisher(LocalPublisher),
+}
+
+/// Registry of the `local://` endpoints bound in a process.
+#[derive(Clone, Default)]
+pub struct LocalBus {
+    endpoints: Arc<Mutex<HashMap<String, Endpoint>>>,
+}
+
+impl LocalBus {
+    /// A bus of its own, isolated from [`global`](Self::global).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bus networks use unless given another.
+    pub fn global() -> Self {
+        static BUS: OnceLock<LocalBus> = OnceLock::new();
+        BUS.get_or_init(LocalBus::new).clone()
+    }
+
+    /// Endpoints currently bound, sorted.
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut endpoints: Vec<_> = self.lock().keys().cloned().collect();
+        endpoints.sort();
+        endpoints
+    }
+
+    pub fn is_bound(&self, address: &str) -> bool {
+        self.lock().contains_key(address)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Endpoint>> {
+        self.endpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn bind(&self, address: &str, endpoint: Endpoint) -> Result<(), NetworkError> {
+        let mut endpoints = self.lock();
+        if endpoints.contains_key(address) {
+            return Err(NetworkError::Bind(format!("{} is already bound", address)));
+        }
+        endpoints.insert(address.to_string(), endpoint);
+        Ok(())
+    }
+
+    pub(crate) fn bind_router(
+        &self,
+        address: &str,
+        inbox: mpsc::Sender<(Vec<u8>, Bytes)>,
+        clients: LocalClients,
+    ) -> Result<(), NetworkError> {
+        self.bind(address, Endpoint::Router { inbox, clients })
+    }
+
+    pub(crate) fn bind_publisher(&self, address: &str, publisher: LocalPublisher) -> Result<(), NetworkError> {
+        self.bind(address, Endpoint::Publisher(publisher))
+    }
+
+    pub(crate) fn unbind(&self, address: &str) {
+        self.lock().remove(address);
+    }
+
+    /// A link to the router bound at `address`, if one is.
+    pub(crate) fn connect(&self, address: &str) -> Option<LocalConn> {
+        let Some(Endpoint::Router { inbox, clients }) = self.lock().get(address).cloned() else {
+            return None;
+        };
+        if inbox.is_closed() {
+            return None;
+        }
+        let identity = format!("local-{}", NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed)).into_bytes();
+        let (tx, replies) = mpsc::channel(CHANNEL_BUFFER);
+        lock_clients(&clients).insert(identity.clone(), tx);
+        Some(LocalConn {
+            identity,
+            inbox,
+            replies,
+            clients,
+        })
+    }
+
+    /// Attaches `subscriber` to the publisher bound at `address`. Returns
+    /// false if none is.
+    pub(crate) fn subscribe(&self, address: &str, subscriber: &LocalSubscriber) -> bool {
+        let Some(Endpoint::Publisher(publisher)) = self.lock().get(address).cloned() else {
+            return false;
+        };
+        let mut subscribers = publisher.lock();
+        if !subscribers.iter().any(|s| s.inbox.same_channel(&subscriber.inbox)) {
+            subscribers.push(subscriber.clone());
+        }
+        true
+    }
+}
+
+fn lock_clients(clients: &LocalClients) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, mpsc::Sender<Bytes>>> {
+    clients.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Delivers a router reply to a local dealer. Returns false if `identity`
+/// is not one.
+pub(crate) fn reply(clients: &LocalClients, identity: &[u8], content: Bytes) -> bool {
+    let Some(client) = lock_clients(clients).get(identity).cloned() else {
+        return false;
+    };
+    if client.try_send(content).is_err() {
+        tracing::debug!("local dealer not keeping up, dropped reply");
+    }
+    true
+}
+
+impl LocalPublisher {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<LocalSubscriber>> {
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Hands `content` to every subscriber with a topic prefix matching
+    /// `topic`, forgetting subscribers that have gone away.
+    pub(crate) fn publish(&self, topic: &str, content: &Bytes) {
+        self.lock().retain(|subscriber| {
+            if subscriber.inbox.is_closed() {
+                return false;
+            }
+            let wanted = subscriber
+                .topics
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .any(|prefix| topic.starts_with(prefix.as_str()));
+            if wanted && subscriber.inbox.try_send((topic.to_string(), content.clone())).is_err() {
+                tracing::debug!(topic, "local subscriber not keeping up, dropped frame");
+            }
+            true
+        });
+    }
+}
+
+/// A dealer's link to a local router.
+pub(crate) struct LocalConn {
+    identity: Vec<u8>,
+    inbox: mpsc::Sender<(Vec<u8>, Bytes)>,
+    replies: mpsc::Receiver<Bytes>,
+    clients: LocalClients,
+}
+
+impl LocalConn {
+    pub(crate) async fn send(&self, content: Bytes) -> Result<(), String> {
+        self.inbox
+            .send((self.identity.clone(), content))
+            .await
+            .map_err(|_| "local router closed".to_string())
+    }
+
+    pub(crate) async fn recv(&mut self) -> Result<Bytes, String> {
+        self.replies.recv().await.ok_or_else(|| "local router closed".to_string())
+    }
+}
+
+impl Drop for LocalConn {
+    fn drop(&mut self) {
+        lock_clients(&self.clients).remove(&self.identity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_router_roundtrip() {
+        let bus = LocalBus::new();
+        let (inbox, mut requests) = mpsc::channel(4);
+        let clients = LocalClients::default();
+        bus.bind_router("local://a", inbox.clone(), Arc::clone(&clients)).unwrap();
+        assert!(bus.bind_router("local://a", inbox, Arc::clone(&clients)).is_err());
+        assert!(bus.connect("local://b").is_none());
+
+        let mut conn = bus.connect("local://a").unwrap();
+        conn.send(Bytes::from_static(b"ping")).await.unwrap();
+        let (identity, content) = requests.recv().await.unwrap();
+        assert_eq!(&content[..], b"ping");
+
+        assert!(reply(&clients, &identity, Bytes::from_static(b"pong")));
+        assert_eq!(&conn.recv().await.unwrap()[..], b"pong");
+
+        drop(conn);
+        assert!(!reply(&clients, &identity, Bytes::new()));
+        bus.unbind("local://a");
+        assert!(bus.endpoints().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publish_matches_topic_prefixes() {
+        let bus = LocalBus::new();
+        let publisher = LocalPublisher::default();
+        bus.bind_publisher("local://a/pub", publisher.clone()).unwrap();
+
+        let (inbox, mut frames) = mpsc::channel(4);
+        let subscriber = LocalSubscriber {
+            topics: Arc::new(Mutex::new(HashSet::from(["echo".to_string()]))),
+            inbox,
+        };
+        assert!(bus.subscribe("local://a/pub", &subscriber));
+        assert!(bus.subscribe("local://a/pub", &subscriber));
+
+        publisher.publish("ready-1", &Bytes::from_static(b"skipped"));
+        publisher.publish("echo-1", &Bytes::from_static(b"frame"));
+        let (topic, content) = frames.recv().await.unwrap();
+        assert_eq!((topic.as_str(), &content[..]), ("echo-1", &b"frame"[..]));
+        assert!(frames.try_recv().is_err());
+    }
+}
@@ -1,5 +1,25 @@
+mod coalesce;
+mod heartbeat;
+mod local;
 mod peer;
+mod queue;
+mod reconnect;
+pub mod seeds;
+mod skew;
 mod sockets;
+mod stats;
+#[cfg(feature = "websocket")]
+mod websocket;
 
-pub use peer::{PeerInfo, PeerRegistry};
-pub use sockets::{NetworkError, RacerNetwork};
+pub use coalesce::{bundle, CoalescePolicy, Coalescer};
+pub use heartbeat::{HeartbeatPolicy, DEFAULT_HEARTBEAT_INTERVAL, DEFAULT_HEARTBEAT_TIMEOUT};
+pub use local::{LocalBus, LOCAL_SCHEME};
+pub use peer::{PeerEvent, PeerInfo, PeerRegistry};
+pub use reconnect::{ReconnectPolicy, DEFAULT_RECONNECT_INITIAL, DEFAULT_RECONNECT_MAX};
+pub use skew::{ClockSkew, SKEW_WINDOW};
+pub use sockets::{
+    NetworkError, RacerNetwork, SocketOptions, DEFAULT_MAX_FRAME_BYTES, DEFAULT_RECEIVE_HWM, DEFAULT_SEND_QUEUE_CAPACITY,
+};
+pub use stats::{Backpressure, NetworkStats, PeerLinkStats};
+#[cfg(feature = "websocket")]
+pub use websocket::MAX_WEBSOCKET_CLIENTS;
@@ -1,9 +1,22 @@
 use std::collections::HashMap;
 use std::time::Instant;
 
+use rand::seq::SliceRandom;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+use super::ClockSkew;
+use crate::config::NodeRole;
 use crate::crypto::PublicKey;
+use crate::protocol::version::Capabilities;
+
+/// A dealer link coming up or going down, from
+/// [`RacerNetwork::subscribe_peer_events`](super::RacerNetwork::subscribe_peer_events).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerEvent {
+    Up(String),
+    Down(String),
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerInfo {
@@ -11,10 +24,40 @@ pub struct PeerInfo {
     pub ecdsa_public: PublicKey,
     pub router_address: String,
     pub publisher_address: String,
+    /// Further routers of the peer, tried in order after `router_address`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_router_addresses: Vec<String>,
+    /// Further publishers, tried in order after `publisher_address`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_publisher_addresses: Vec<String>,
+    /// When set, `ecdsa_public` is fixed and later discovery cannot replace it.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Router of the relay that forwards frames to this peer, when the peer
+    /// cannot accept connections; `router_address` is then unreachable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay: Option<String>,
+    /// Protocol version agreed with the peer in the PeerDiscovery
+    /// handshake; `None` for peers added without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u32>,
+    /// Capabilities both ends have, once a handshake agreed on a version.
+    #[serde(default, skip_serializing_if = "Capabilities::is_empty")]
+    pub capabilities: Capabilities,
+    /// The part the peer announced it takes in rounds.
+    #[serde(default, skip_serializing_if = "NodeRole::is_full")]
+    pub role: NodeRole,
     #[serde(skip)]
     pub reported_latency: f64,
     #[serde(skip)]
     pub last_seen: Option<Instant>,
+    /// The router endpoint the link to this peer connected to, once it has.
+    #[serde(skip)]
+    pub connected_router: Option<String>,
+    /// How far the peer's wall clock runs from ours, from the timestamps
+    /// of its Echoes and Responses.
+    #[serde(skip)]
+    pub clock_skew: ClockSkew,
 }
 
 impl PeerInfo {
@@ -29,14 +72,65 @@ impl PeerInfo {
             ecdsa_public,
             router_address: router_address.into(),
             publisher_address: publisher_address.into(),
+            alt_router_addresses: Vec::new(),
+            alt_publisher_addresses: Vec::new(),
+            pinned: false,
+            relay: None,
+            protocol_version: None,
+            capabilities: Capabilities::NONE,
+            role: NodeRole::Full,
             reported_latency: 0.0,
             last_seen: None,
+            connected_router: None,
+            clock_skew: ClockSkew::new(),
         }
     }
 
+    pub fn with_pinned_key(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
+
+    /// Whether messages needing `capability` may be sent to the peer. A peer
+    /// added without a handshake is assumed to match this node.
+    pub fn supports(&self, capability: Capabilities) -> bool {
+        self.protocol_version.is_none() || self.capabilities.contains(capability)
+    }
+
+    /// Every router of the peer, `router_address` first.
+    pub fn router_addresses(&self) -> Vec<String> {
+        std::iter::once(&self.router_address).chain(&self.alt_router_addresses).cloned().collect()
+    }
+
+    /// Reachable only through the relay at `router`.
+    pub fn with_relay(mut self, router: impl Into<String>) -> Self {
+        self.relay = Some(router.into());
+        self
+    }
+
+    pub fn with_role(mut self, role: NodeRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Whether frames to this peer go through a relay rather than its own router.
+    pub fn is_relayed(&self) -> bool {
+        self.relay.is_some()
+    }
+
+    /// Short id derived from the public key, as used in protocol `sender_id()`s.
+    pub fn key_id(&self) -> String {
+        self.ecdsa_public.to_hex()[..10].to_string()
+    }
+
     pub fn touch(&mut self) {
         self.last_seen = Some(Instant::now());
     }
+
+    /// Milliseconds the peer's clock runs ahead of ours, if estimated yet.
+    pub fn clock_skew_ms(&self) -> Option<i64> {
+        self.clock_skew.estimate_ms()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -58,11 +152,20 @@ impl PeerRegistry {
         self.self_id.as_deref()
     }
 
-    pub fn add_peer(&mut self, peer: PeerInfo) {
+    /// Adds or replaces a peer. Returns `false` if the peer is ourselves or
+    /// would replace a pinned key with a different one.
+    pub fn add_peer(&mut self, peer: PeerInfo) -> bool {
         if Some(peer.id.as_str()) == self.self_id.as_deref() {
-            return;
+            return false;
+        }
+        if let Some(existing) = self.peers.get(&peer.id) {
+            if existing.pinned && existing.ecdsa_public != peer.ecdsa_public {
+                tracing::warn!(peer = %peer.id, "rejected key change for pinned peer");
+                return false;
+            }
         }
         self.peers.insert(peer.id.clone(), peer);
+        true
     }
 
     pub fn get(&self, id: &str) -> Option<&PeerInfo> {
@@ -73,6 +176,10 @@ impl PeerRegistry {
         self.peers.get_mut(id)
     }
 
+    pub fn find_by_key_id(&self, key_id: &str) -> Option<&PeerInfo> {
+        self.peers.values().find(|p| p.key_id() == key_id)
+    }
+
     pub fn remove(&mut self, id: &str) -> Option<PeerInfo> {
         self.peers.remove(id)
     }
@@ -89,14 +196,26 @@ impl PeerRegistry {
         self.peers.values()
     }
 
+    /// Peers that echo and ready, and so count towards round thresholds.
+    pub fn participants(&self) -> impl Iterator<Item = &PeerInfo> {
+        self.peers.values().filter(|p| p.role.answers_rounds())
+    }
+
     pub fn peer_ids(&self) -> Vec<String> {
         self.peers.keys().cloned().collect()
     }
 
     pub fn select_random(&self, n: usize) -> Vec<&PeerInfo> {
-        use rand::seq::SliceRandom;
+        self.select_random_with(n, &mut rand::thread_rng())
+    }
+
+    /// [`select_random`](Self::select_random) drawing from `rng`: the same
+    /// peers and `rng` state pick the same peers.
+    pub fn select_random_with<R: Rng + ?Sized>(&self, n: usize, rng: &mut R) -> Vec<&PeerInfo> {
         let mut peers: Vec<_> = self.peers.values().collect();
-        peers.shuffle(&mut rand::thread_rng());
+        // Map order differs between processes; the shuffle must not.
+        peers.sort_by(|a, b| a.id.cmp(&b.id));
+        peers.shuffle(rng);
         peers.into_iter().take(n).collect()
     }
 
@@ -107,6 +226,27 @@ impl PeerRegistry {
         }
     }
 
+    /// Records a timestamp stamped at `remote_ms` by the peer whose key id
+    /// is `key_id`, received at `local_ms`. Returns `false` for an unknown peer.
+    pub fn record_clock(&mut self, key_id: &str, remote_ms: u64, local_ms: u64) -> bool {
+        match self.peers.values_mut().find(|p| p.key_id() == key_id) {
+            Some(peer) => {
+                peer.clock_skew.record(remote_ms, local_ms);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Our `local_ms` as the clock of the peer whose key id is `key_id`
+    /// reads it, unchanged for a peer whose skew is unknown.
+    pub fn peer_time(&self, key_id: &str, local_ms: u64) -> u64 {
+        match self.find_by_key_id(key_id) {
+            Some(peer) => peer.clock_skew.peer_time(local_ms),
+            None => local_ms,
+        }
+    }
+
     pub fn average_latency(&self) -> f64 {
         if self.peers.is_empty() {
             return 0.0;
@@ -141,6 +281,44 @@ mod tests {
         assert!(registry.get("1").is_some());
     }
 
+    #[test]
+    fn test_pinned_key_not_replaced() {
+        let mut registry = PeerRegistry::new();
+        let pinned = make_peer("1").with_pinned_key();
+        let key = pinned.ecdsa_public.clone();
+        assert!(registry.add_peer(pinned));
+
+        assert!(!registry.add_peer(make_peer("1")));
+        assert_eq!(registry.get("1").unwrap().ecdsa_public, key);
+    }
+
+    #[test]
+    fn test_find_by_key_id() {
+        let mut registry = PeerRegistry::new();
+        let peer = make_peer("1");
+        let key_id = peer.key_id();
+        registry.add_peer(peer);
+        registry.add_peer(make_peer("2"));
+
+        assert_eq!(registry.find_by_key_id(&key_id).map(|p| p.id.as_str()), Some("1"));
+        assert!(registry.find_by_key_id("0000000000").is_none());
+    }
+
+    #[test]
+    fn test_record_clock() {
+        let mut registry = PeerRegistry::new();
+        let peer = make_peer("1");
+        let key_id = peer.key_id();
+        registry.add_peer(peer);
+
+        assert_eq!(registry.peer_time(&key_id, 50_000), 50_000);
+        assert!(registry.record_clock(&key_id, 47_000, 50_000));
+        assert!(!registry.record_clock("0000000000", 47_000, 50_000));
+        assert_eq!(registry.get("1").unwrap().clock_skew_ms(), Some(-3_000));
+        assert_eq!(registry.peer_time(&key_id, 60_000), 57_000);
+        assert_eq!(registry.peer_time("0000000000", 60_000), 60_000);
+    }
+
     #[test]
     fn test_select_random() {
         let mut registry = PeerRegistry::new();
@@ -152,6 +330,38 @@ mod tests {
         assert_eq!(selected.len(), 3);
     }
 
+    #[test]
+    fn test_seeded_selection_repeats() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut registry = PeerRegistry::new();
+        for i in 0..10 {
+            registry.add_peer(make_peer(&i.to_string()));
+        }
+        let mut shuffled = PeerRegistry::new();
+        for i in (0..10).rev() {
+            shuffled.add_peer(make_peer(&i.to_string()));
+        }
+
+        let ids = |peers: Vec<&PeerInfo>| peers.into_iter().map(|p| p.id.clone()).collect::<Vec<_>>();
+        let first = ids(registry.select_random_with(4, &mut StdRng::seed_from_u64(7)));
+        let second = ids(shuffled.select_random_with(4, &mut StdRng::seed_from_u64(7)));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_participants_leave_out_observers() {
+        let mut registry = PeerRegistry::new();
+        registry.add_peer(make_peer("1"));
+        registry.add_peer(make_peer("2").with_role(NodeRole::Observer));
+        registry.add_peer(make_peer("3").with_role(NodeRole::Forwarder));
+
+        let mut ids: Vec<_> = registry.participants().map(|p| p.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["1", "3"]);
+    }
+
     #[test]
     fn test_dont_add_self() {
         let mut registry = PeerRegistry::new();
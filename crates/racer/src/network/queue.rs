@@ -0,0 +1,296 @@
+//! Bounded per-peer send queues.
+//!
+//! Each dealer worker drains its own [`SendQueue`], so a slow or unreachable
+//! peer only backs up its own queue. What happens when a queue is full is set
+//! by the configured [`OverflowPolicy`].
+//!
+//! Frames are kept in one lane per [`Priority`]: the worker always sends the
+//! most urgent frame first, and `DropOldest` evicts the oldest frame of the
+//! least urgent lane, never one more urgent than the frame being queued.
+//!
+//! A queue that fills up reports [`Backpressure::QueueSaturated`] once, and
+//! again only after draining to half its capacity.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use tokio::sync::{broadcast, Notify};
+
+use super::stats::{Backpressure, PeerLinkStats};
+use crate::config::OverflowPolicy;
+use crate::protocol::Priority;
+
+#[derive(Debug, Default)]
+struct Lanes([VecDeque<Bytes>; Priority::ALL.len()]);
+
+impl Lanes {
+    fn len(&self) -> usize {
+        self.0.iter().map(VecDeque::len).sum()
+    }
+
+    fn push_back(&mut self, priority: Priority, frame: Bytes) {
+        self.0[priority.rank()].push_back(frame);
+    }
+
+    fn pop_front(&mut self) -> Option<Bytes> {
+        self.0.iter_mut().find_map(VecDeque::pop_front)
+    }
+
+    /// Drops the oldest frame no more urgent than `priority`, least urgent lane first.
+    fn evict_for(&mut self, priority: Priority) -> bool {
+        self.0[priority.rank()..]
+            .iter_mut()
+            .rev()
+            .find_map(VecDeque::pop_front)
+            .is_some()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SendQueue {
+    items: Mutex<Lanes>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    readable: Notify,
+    writable: Notify,
+    dropped: AtomicU64,
+    send_failures: AtomicU64,
+    reconnects: AtomicU64,
+    saturated: AtomicBool,
+    saturations: AtomicU64,
+    /// Peer id and channel saturation is reported on.
+    alerts: Option<(String, broadcast::Sender<Backpressure>)>,
+    /// Endpoint the peer's link last connected to.
+    endpoint: Mutex<Option<String>>,
+}
+
+/// Outcome of [`SendQueue::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pushed {
+    Queued,
+    /// Queued after dropping the oldest frame.
+    Displaced,
+    /// The frame itself was dropped.
+    Rejected,
+}
+
+impl SendQueue {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            items: Mutex::new(Lanes::default()),
+            capacity: capacity.max(1),
+            policy,
+            readable: Notify::new(),
+            writable: Notify::new(),
+            dropped: AtomicU64::new(0),
+            send_failures: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            saturated: AtomicBool::new(false),
+            saturations: AtomicU64::new(0),
+            alerts: None,
+            endpoint: Mutex::new(None),
+        }
+    }
+
+    /// Reports saturation of `peer_id`'s queue on `alerts`.
+    pub(crate) fn with_alerts(mut self, peer_id: &str, alerts: broadcast::Sender<Backpressure>) -> Self {
+        self.alerts = Some((peer_id.to_string(), alerts));
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lanes> {
+        self.items.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues `frame`, applying the overflow policy if the queue is full.
+    /// Only waits under [`OverflowPolicy::Block`].
+    pub(crate) async fn push(&self, frame: Bytes, priority: Priority) -> Pushed {
+        loop {
+            let (pushed, full) = {
+                let mut items = self.lock();
+                let pushed = if items.len() < self.capacity {
+                    items.push_back(priority, frame.clone());
+                    Some(Pushed::Queued)
+                } else {
+                    match self.policy {
+                        OverflowPolicy::DropOldest if items.evict_for(priority) => {
+                            items.push_back(priority, frame.clone());
+                            Some(Pushed::Displaced)
+                        }
+                        OverflowPolicy::DropOldest => Some(Pushed::Rejected),
+                        OverflowPolicy::DropNew => Some(Pushed::Rejected),
+                        OverflowPolicy::Block => None,
+                    }
+                };
+                (pushed, items.len() >= self.capacity)
+            };
+            if full {
+                self.record_saturated();
+            }
+
+            match pushed {
+                Some(pushed) => {
+                    if pushed != Pushed::Queued {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if pushed != Pushed::Rejected {
+                        self.readable.notify_one();
+                    }
+                    return pushed;
+                }
+                None => self.writable.notified().await,
+            }
+        }
+    }
+
+    /// Waits for the next frame, most urgent first.
+    pub(crate) async fn pop(&self) -> Bytes {
+        loop {
+            let popped = {
+                let mut items = self.lock();
+                items.pop_front().map(|frame| (frame, items.len()))
+            };
+            if let Some((frame, depth)) = popped {
+                if depth <= self.capacity / 2 {
+                    self.saturated.store(false, Ordering::Relaxed);
+                }
+                self.writable.notify_one();
+                return frame;
+            }
+            self.readable.notified().await;
+        }
+    }
+
+    fn record_saturated(&self) {
+        if self.saturated.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        self.saturations.fetch_add(1, Ordering::Relaxed);
+        if let Some((peer_id, alerts)) = &self.alerts {
+            tracing::debug!(peer_id, capacity = self.capacity, "send queue saturated");
+            let _ = alerts.send(Backpressure::QueueSaturated {
+                peer_id: peer_id.clone(),
+                capacity: self.capacity,
+            });
+        }
+    }
+
+    /// Queued frames as a fraction of capacity.
+    pub(crate) fn fill(&self) -> f64 {
+        self.lock().len() as f64 / self.capacity as f64
+    }
+
+    pub(crate) fn record_send_failure(&self) {
+        self.send_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_endpoint(&self, endpoint: &str) {
+        *self.endpoint.lock().unwrap_or_else(|e| e.into_inner()) = Some(endpoint.to_string());
+    }
+
+    pub(crate) fn endpoint(&self) -> Option<String> {
+        self.endpoint.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub(crate) fn stats(&self, peer_id: &str) -> PeerLinkStats {
+        PeerLinkStats {
+            peer_id: peer_id.to_string(),
+            queue_depth: self.lock().len(),
+            queue_capacity: self.capacity,
+            dropped: self.dropped.load(Ordering::Relaxed),
+            send_failures: self.send_failures.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            saturations: self.saturations.load(Ordering::Relaxed),
+            endpoint: self.endpoint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_drop_oldest_keeps_newest() {
+        let queue = SendQueue::new(2, OverflowPolicy::DropOldest);
+        for frame in ["a", "b", "c"] {
+            queue.push(Bytes::from(frame), Priority::Normal).await;
+        }
+
+        assert_eq!(queue.pop().await, "b");
+        assert_eq!(queue.pop().await, "c");
+        assert_eq!(queue.stats("p").dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_urgent_frames_jump_the_queue() {
+        let queue = SendQueue::new(2, OverflowPolicy::DropOldest);
+        queue.push(Bytes::from("routine"), Priority::Low).await;
+        queue.push(Bytes::from("normal"), Priority::Normal).await;
+        assert_eq!(queue.push(Bytes::from("alert"), Priority::High).await, Pushed::Displaced);
+        assert_eq!(queue.push(Bytes::from("late"), Priority::Low).await, Pushed::Rejected);
+
+        assert_eq!(queue.pop().await, "alert");
+        assert_eq!(queue.pop().await, "normal");
+    }
+
+    #[tokio::test]
+    async fn test_drop_new_rejects_overflow() {
+        let queue = SendQueue::new(1, OverflowPolicy::DropNew);
+        assert_eq!(queue.push(Bytes::from("a"), Priority::Normal).await, Pushed::Queued);
+        assert_eq!(queue.push(Bytes::from("b"), Priority::Normal).await, Pushed::Rejected);
+
+        assert_eq!(queue.pop().await, "a");
+        assert_eq!(queue.stats("p").queue_depth, 0);
+    }
+
+    #[tokio::test]
+    async fn test_saturation_reported_once_until_half_drained() {
+        let (alerts, mut rx) = broadcast::channel(8);
+        let queue = SendQueue::new(4, OverflowPolicy::DropNew).with_alerts("p", alerts);
+        for frame in ["a", "b", "c", "d", "e"] {
+            queue.push(Bytes::from(frame), Priority::Normal).await;
+        }
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            Backpressure::QueueSaturated { peer_id: "p".into(), capacity: 4 }
+        );
+        assert!(rx.try_recv().is_err());
+
+        queue.pop().await;
+        queue.push(Bytes::from("f"), Priority::Normal).await;
+        assert!(rx.try_recv().is_err(), "still saturated");
+
+        queue.pop().await;
+        queue.pop().await;
+        queue.push(Bytes::from("g"), Priority::Normal).await;
+        queue.push(Bytes::from("h"), Priority::Normal).await;
+        assert!(rx.try_recv().is_ok());
+        assert_eq!(queue.stats("p").saturations, 2);
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_space() {
+        let queue = Arc::new(SendQueue::new(1, OverflowPolicy::Block));
+        queue.push(Bytes::from("a"), Priority::Normal).await;
+
+        let pusher = tokio::spawn({
+            let queue = Arc::clone(&queue);
+            async move { queue.push(Bytes::from("b"), Priority::Normal).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!pusher.is_finished());
+
+        assert_eq!(queue.pop().await, "a");
+        assert_eq!(pusher.await.unwrap(), Pushed::Queued);
+        assert_eq!(queue.pop().await, "b");
+    }
+}
@@ -0,0 +1,91 @@
+//! Reconnection policy for dealer links.
+//!
+//! A dealer worker that loses its peer, or cannot reach it in the first place,
+//! retries with exponential backoff: each failed attempt doubles the delay up
+//! to `max`, and the delay actually slept is drawn from its upper half so that
+//! nodes restarted together do not retry in lockstep.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Default delay before the first retry.
+pub const DEFAULT_RECONNECT_INITIAL: Duration = Duration::from_millis(100);
+/// Default ceiling on the retry delay.
+pub const DEFAULT_RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// How long a single connect attempt may take before it counts as failed.
+pub(crate) const CONNECT_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl ReconnectPolicy {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial: initial.max(Duration::from_millis(1)),
+            max: max.max(initial),
+        }
+    }
+
+    pub(crate) fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: *self,
+            current: self.initial,
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECONNECT_INITIAL, DEFAULT_RECONNECT_MAX)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    policy: ReconnectPolicy,
+    current: Duration,
+}
+
+impl Backoff {
+    /// The delay to sleep before the next attempt.
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let ceiling = self.current;
+        self.current = (self.current * 2).min(self.policy.max);
+        rand::thread_rng().gen_range(ceiling / 2..=ceiling)
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.current = self.policy.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = ReconnectPolicy::new(Duration::from_millis(100), Duration::from_millis(500));
+        let mut backoff = policy.backoff();
+
+        for ceiling in [100, 200, 400, 500, 500] {
+            let delay = backoff.next_delay();
+            assert!(delay >= Duration::from_millis(ceiling / 2), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(ceiling), "{:?}", delay);
+        }
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_max_never_below_initial() {
+        let policy = ReconnectPolicy::new(Duration::from_secs(5), Duration::from_secs(1));
+        assert_eq!(policy.max, Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,104 @@
+//! Bootstrap seeds: DNS names resolved to router endpoints.
+//!
+//! A seed is `host:port`, resolved to every A and AAAA address of `host`, or
+//! an SRV name such as `_racer._tcp.example.com` whose records name the
+//! hosts and ports; SRV lookups need the `srv` feature. Each address becomes
+//! a `tcp://` router endpoint. Seeds are resolved again on every refresh, so
+//! DNS can move bootstrap nodes without reconfiguring devices.
+
+use std::net::SocketAddr;
+
+use super::NetworkError;
+
+/// Whether `seed` is an SRV name rather than `host:port`.
+pub fn is_srv(seed: &str) -> bool {
+    seed.starts_with('_')
+}
+
+/// Whether `seed` is `host:port` with a non-zero port, or an SRV name of
+/// the form `_service._proto.domain`.
+pub fn is_seed(seed: &str) -> bool {
+    if is_srv(seed) {
+        return seed.split('.').count() >= 3 && !seed.contains([':', '/']);
+    }
+    match seed.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && !host.contains('/') && port.parse::<u16>().is_ok_and(|p| p > 0),
+        None => false,
+    }
+}
+
+/// Router endpoints `seed` resolves to, in the order DNS returned them,
+/// without duplicates.
+pub async fn resolve(seed: &str) -> Result<Vec<String>, NetworkError> {
+    let targets = if is_srv(seed) {
+        srv_targets(seed).await?
+    } else {
+        vec![seed.to_string()]
+    };
+
+    let mut endpoints = Vec::new();
+    for target in targets {
+        let addrs = tokio::net::lookup_host(target.as_str())
+            .await
+            .map_err(|e| NetworkError::Connect(format!("resolving {}: {}", target, e)))?;
+        for addr in addrs {
+            let endpoint = endpoint(addr);
+            if !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        }
+    }
+    Ok(endpoints)
+}
+
+fn endpoint(addr: SocketAddr) -> String {
+    format!("tcp://{}", addr)
+}
+
+/// `host:port` of each SRV record, lowest priority first and, within a
+/// priority, heaviest weight first.
+#[cfg(feature = "srv")]
+async fn srv_targets(name: &str) -> Result<Vec<String>, NetworkError> {
+    let resolver = hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()
+        .map_err(|e| NetworkError::Connect(format!("DNS resolver: {}", e)))?;
+    let lookup = resolver
+        .srv_lookup(name)
+        .await
+        .map_err(|e| NetworkError::Connect(format!("resolving {}: {}", name, e)))?;
+
+    let mut records: Vec<_> = lookup.iter().collect();
+    records.sort_by_key(|r| (r.priority(), std::cmp::Reverse(r.weight())));
+    Ok(records
+        .iter()
+        .map(|r| format!("{}:{}", r.target().to_utf8().trim_end_matches('.'), r.port()))
+        .collect())
+}
+
+#[cfg(not(feature = "srv"))]
+async fn srv_targets(name: &str) -> Result<Vec<String>, NetworkError> {
+    Err(NetworkError::Connect(format!(
+        "{} is an SRV name but the `srv` feature is disabled",
+        name
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_forms() {
+        for seed in ["seed.racer.example:20001", "10.0.0.2:20001", "[::1]:20001", "_racer._tcp.example.com"] {
+            assert!(is_seed(seed), "{}", seed);
+        }
+        for seed in ["seed.racer.example", "host:0", ":20001", "tcp://host:20001", "_racer.example"] {
+            assert!(!is_seed(seed), "{}", seed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_literal_addresses() {
+        assert_eq!(resolve("127.0.0.1:20001").await.unwrap(), vec!["tcp://127.0.0.1:20001"]);
+        assert_eq!(resolve("[::1]:20001").await.unwrap(), vec!["tcp://[::1]:20001"]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Estimation of how far a peer's wall clock runs from ours.
+//!
+//! A batch's `created_at` and the `timestamp` of Echoes and Responses are
+//! epoch millis read off the sender's clock, and the RTCs of small devices
+//! drift by seconds a day. Each Echo or Response from a known peer is a
+//! sample of its offset: the timestamp less our clock when it arrived, which
+//! is the skew minus the time the message spent in transit. Transit only
+//! ever pulls a sample down, so the largest of the last [`SKEW_WINDOW`]
+//! samples is the estimate, and dropping old ones lets it follow a clock
+//! that keeps drifting.
+
+use std::collections::VecDeque;
+
+/// Samples the estimate is taken over.
+pub const SKEW_WINDOW: usize = 16;
+
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkew {
+    samples: VecDeque<i64>,
+}
+
+impl ClockSkew {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a timestamp the peer stamped at `remote_ms` that reached us
+    /// at `local_ms`, both epoch millis.
+    pub fn record(&mut self, remote_ms: u64, local_ms: u64) {
+        let offset = (remote_ms as i128 - local_ms as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        if self.samples.len() == SKEW_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(offset);
+    }
+
+    /// Milliseconds the peer's clock runs ahead of ours, negative when it
+    /// runs behind; `None` before the first sample.
+    pub fn estimate_ms(&self) -> Option<i64> {
+        self.samples.iter().max().copied()
+    }
+
+    /// Our `local_ms` as the peer's clock reads it, unchanged while the
+    /// skew is unknown.
+    pub fn peer_time(&self, local_ms: u64) -> u64 {
+        local_ms.saturating_add_signed(self.estimate_ms().unwrap_or(0))
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_discounts_transit() {
+        let mut skew = ClockSkew::new();
+        assert_eq!(skew.estimate_ms(), None);
+        assert_eq!(skew.peer_time(10_000), 10_000);
+
+        // The peer runs 5s ahead; messages take 20 to 300 ms to arrive.
+        for (sent, transit) in [(0, 300), (1_000, 20), (2_000, 150)] {
+            skew.record(105_000 + sent, 100_000 + sent + transit);
+        }
+        assert_eq!(skew.estimate_ms(), Some(4_980));
+        assert_eq!(skew.peer_time(10_000), 14_980);
+
+        let mut behind = ClockSkew::new();
+        behind.record(1_000, 3_000);
+        assert_eq!(behind.estimate_ms(), Some(-2_000));
+        assert_eq!(behind.peer_time(1_000), 0);
+    }
+
+    #[test]
+    fn test_estimate_follows_drift() {
+        let mut skew = ClockSkew::new();
+        skew.record(10_000, 0);
+        for i in 1..=SKEW_WINDOW as u64 {
+            skew.record(i * 1_000 + 2_000, i * 1_000);
+        }
+        assert_eq!(skew.sample_count(), SKEW_WINDOW);
+        assert_eq!(skew.estimate_ms(), Some(2_000));
+    }
+}
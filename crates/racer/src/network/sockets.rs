@@ -3,25 +3,97 @@
 //! To prevent deadlocks caused by sharing sockets with RwLocks, we isolate each socket
 //! in its own background task (Actor). The `RacerNetwork` struct acts as a controller
 //! that communicates with these actors via MPSC channels.
+//!
+//! Router/dealer frames are sealed and opened here, outside the actors,
+//! according to the configured `EncryptionMode` (see `crypto::TransportKeys`).
+//! Before sealing, frames to a peer set to [`Framing::Bare`] are rewritten
+//! in the form nodes from before the protocol envelope read.
+//!
+//! Each peer has its own dealer worker, which reconnects with backoff when
+//! its peer goes away (see [`ReconnectPolicy`]) and reports the link going up
+//! or down as a [`PeerEvent`]. A peer may be reachable at several endpoints,
+//! say over IPv4, IPv6 and IPC; the worker tries them in order on every
+//! attempt and records which one answered. Links also probe a quiet peer
+//! with heartbeats and reconnect once it stops answering (see
+//! [`HeartbeatPolicy`]), since an abandoned TCP connection can otherwise
+//! look healthy for many minutes.
+//!
+//! Frame contents travel as [`Bytes`]: received frames are handed on without
+//! copying, and an outbound buffer can be cloned cheaply when the same frame
+//! goes to several peers. The ZeroMQ transport reads each frame whole before
+//! handing it over, so frame length is not checked here: the node drops
+//! frames over `network.max_frame_bytes` before decoding them (see
+//! `FrameLimits`). WebSocket clients are cut off before a message over
+//! [`SocketOptions::max_frame_bytes`] is buffered whole.
+//!
+//! `local://` endpoints bypass the sockets and pass frames over a
+//! [`LocalBus`] instead, for nodes running in the same process.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, Mutex, RwLock};
+use bytes::Bytes;
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
 use zeromq::{DealerSocket, PubSocket, RouterSocket, Socket, SubSocket, SocketRecv, SocketSend};
 
-const CHANNEL_BUFFER: usize = 100;
+use crate::config::{EncryptionMode, OverflowPolicy};
+use crate::crypto::{Lru, PublicKey, TransportKeys};
+use crate::protocol::{envelope, Framing, Priority};
 
+use super::heartbeat::{HeartbeatPolicy, Liveness, Pulse};
+use super::local::{self, LocalBus, LocalClients, LocalConn, LocalPublisher, LocalSubscriber};
+use super::peer::PeerEvent;
+use super::queue::{Pushed, SendQueue};
+use super::reconnect::{ReconnectPolicy, CONNECT_ATTEMPT_TIMEOUT};
+use super::stats::{Backpressure, FrameKind, NetworkStats, TrafficCounters};
+
+pub(crate) const CHANNEL_BUFFER: usize = 100;
+/// Default inbound frames buffered per socket.
+pub const DEFAULT_RECEIVE_HWM: usize = CHANNEL_BUFFER;
+/// Default frames queued per peer before the overflow policy applies.
+pub const DEFAULT_SEND_QUEUE_CAPACITY: usize = 256;
+/// Default longest frame received.
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 4 * 1024 * 1024;
+/// Peer up/down events buffered per subscriber.
+const PEER_EVENT_BUFFER: usize = 256;
+/// Router identities whose sealing key is remembered for replies. Each
+/// dealer that connects brings a new identity, so the least recently heard
+/// from are forgotten.
+const IDENTITY_KEY_CAPACITY: usize = 4096;
+
+/// Socket settings fixed when a [`RacerNetwork`] is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Liveness checks on dealer links; `None` trusts the connection.
+    pub heartbeat: Option<HeartbeatPolicy>,
+    /// Inbound frames buffered per socket before it stops reading, leaving
+    /// further frames to queue in the transport.
+    pub receive_hwm: usize,
+    /// Longest message a WebSocket client may send, in bytes; the client is
+    /// disconnected before a longer one is buffered whole. 0 admits any
+    /// length.
+    pub max_frame_bytes: usize,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            heartbeat: Some(HeartbeatPolicy::default()),
+            receive_hwm: DEFAULT_RECEIVE_HWM,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+        }
+    }
+}
 
 #[derive(Debug)]
 enum RouterCommand {
     Bind(String),
-    SendReply(Vec<u8>, Vec<u8>), // identity, content
+    SendReply(Vec<u8>, Bytes), // identity, content
 }
 
 #[derive(Debug)]
 enum SubscriberCommand {
-    Connect(String),
+    Connect(Vec<String>), // endpoints of one publisher, in order of preference
     Subscribe(String),
     Unsubscribe(String),
 }
@@ -29,19 +101,16 @@ enum SubscriberCommand {
 #[derive(Debug)]
 enum PublisherCommand {
     Bind(String),
-    Publish(String, Vec<u8>), // topic, content
+    Publish(String, Bytes), // topic, content
 }
 
-#[derive(Debug)]
 enum DealerCommand {
-    Connect(String, String), // peer_id, address
-    Send(String, Vec<u8>),   // peer_id, content
+    Connect(DealerLink, Vec<String>), // addresses
 }
 
 #[derive(Debug)]
 enum DealerWorkerCommand {
-    Connect(String), // address
-    Send(Vec<u8>),   // content
+    Connect(Vec<String>), // addresses
 }
 
 
@@ -52,34 +121,89 @@ pub struct RacerNetwork {
     subscriber_tx: mpsc::Sender<SubscriberCommand>,
     dealer_tx: mpsc::Sender<DealerCommand>,
 
-    router_rx: Arc<Mutex<mpsc::Receiver<(Vec<u8>, Vec<u8>)>>>,
-    subscriber_rx: Arc<Mutex<mpsc::Receiver<(String, Vec<u8>)>>>,
-    dealer_rx: Arc<Mutex<mpsc::Receiver<(String, Vec<u8>)>>>, // (peer_id, content)
+    router_rx: Arc<Mutex<mpsc::Receiver<(Vec<u8>, Bytes)>>>,
+    subscriber_rx: Arc<Mutex<mpsc::Receiver<(String, Bytes)>>>,
+    dealer_rx: Arc<Mutex<mpsc::Receiver<(String, Bytes)>>>, // (peer_id, content)
+
+    router_binds: Vec<String>,
+    publisher_binds: Vec<String>,
 
-    router_bind: String,
-    publisher_bind: String,
-    
     subscribed_topics: Arc<RwLock<HashSet<String>>>,
+
+    local: LocalBus,
+    local_bound: std::sync::Mutex<Vec<String>>,
+    local_clients: LocalClients,
+    local_publisher: LocalPublisher,
+    local_subscriber: LocalSubscriber,
+    router_inbox: mpsc::Sender<(Vec<u8>, Bytes)>,
+
+    send_queues: Arc<RwLock<HashMap<String, Arc<SendQueue>>>>, // peer_id -> queue
+    send_queue_capacity: usize,
+    overflow: OverflowPolicy,
+    reconnect: ReconnectPolicy,
+    heartbeat: Option<HeartbeatPolicy>,
+    peer_events: broadcast::Sender<PeerEvent>,
+    backpressure: broadcast::Sender<Backpressure>,
+    traffic: Arc<TrafficCounters>,
+
+    transport: Option<TransportKeys>,
+    encryption: EncryptionMode,
+    peer_keys: Arc<RwLock<HashMap<String, PublicKey>>>,      // peer_id -> key
+    identity_keys: Arc<Mutex<Lru<Vec<u8>, PublicKey>>>,      // router identity -> key
+    bare_peers: Arc<RwLock<HashSet<String>>>,                // peers sent Framing::Bare
+
+    #[cfg(feature = "websocket")]
+    websocket_bind: Option<String>,
+    #[cfg(feature = "websocket")]
+    websocket_clients: super::websocket::Clients,
+    #[cfg(feature = "websocket")]
+    websocket_cap: super::websocket::FrameCap,
 }
 
 impl RacerNetwork {
     pub fn new(router_bind: impl Into<String>, publisher_bind: impl Into<String>) -> Self {
+        Self::with_options(router_bind, publisher_bind, SocketOptions::default())
+    }
+
+    pub fn with_options(
+        router_bind: impl Into<String>,
+        publisher_bind: impl Into<String>,
+        options: SocketOptions,
+    ) -> Self {
         let router_bind = router_bind.into();
         let publisher_bind = publisher_bind.into();
+        let receive_hwm = options.receive_hwm.max(1);
 
         let (router_cmd_tx, router_cmd_rx) = mpsc::channel(CHANNEL_BUFFER);
-        let (router_msg_tx, router_msg_rx) = mpsc::channel(CHANNEL_BUFFER);
+        let (router_msg_tx, router_msg_rx) = mpsc::channel(receive_hwm);
 
         let (pub_cmd_tx, pub_cmd_rx) = mpsc::channel(CHANNEL_BUFFER);
 
         let (sub_cmd_tx, sub_cmd_rx) = mpsc::channel(CHANNEL_BUFFER);
-        let (sub_msg_tx, sub_msg_rx) = mpsc::channel(CHANNEL_BUFFER);
+        let (sub_msg_tx, sub_msg_rx) = mpsc::channel(receive_hwm);
 
         let (dealer_cmd_tx, dealer_cmd_rx) = mpsc::channel(CHANNEL_BUFFER);
-        let (dealer_msg_tx, dealer_msg_rx) = mpsc::channel(CHANNEL_BUFFER);
+        let (dealer_msg_tx, dealer_msg_rx) = mpsc::channel(receive_hwm);
+        let (peer_events, _) = broadcast::channel(PEER_EVENT_BUFFER);
+        let (backpressure, _) = broadcast::channel(PEER_EVENT_BUFFER);
+        let traffic = Arc::new(TrafficCounters::default());
+        #[cfg(feature = "websocket")]
+        let websocket_cap = super::websocket::FrameCap::new(options.max_frame_bytes, Arc::clone(&traffic));
 
+        let router_inbox = router_msg_tx.clone();
+        let local_clients = LocalClients::default();
+        let local_publisher = LocalPublisher::default();
+        let local_subscriber = LocalSubscriber {
+            topics: Default::default(),
+            inbox: sub_msg_tx.clone(),
+        };
         tokio::spawn(router_actor(router_cmd_rx, router_msg_tx));
-        tokio::spawn(publisher_actor(pub_cmd_rx));
+        tokio::spawn(publisher_actor(
+            pub_cmd_rx,
+            local_publisher.clone(),
+            Arc::clone(&traffic),
+            backpressure.clone(),
+        ));
         tokio::spawn(subscriber_actor(sub_cmd_rx, sub_msg_tx));
         tokio::spawn(dealer_actor(dealer_cmd_rx, dealer_msg_tx));
 
@@ -91,43 +215,289 @@ impl RacerNetwork {
             router_rx: Arc::new(Mutex::new(router_msg_rx)),
             subscriber_rx: Arc::new(Mutex::new(sub_msg_rx)),
             dealer_rx: Arc::new(Mutex::new(dealer_msg_rx)),
-            router_bind,
-            publisher_bind,
+            router_binds: vec![router_bind],
+            publisher_binds: vec![publisher_bind],
             subscribed_topics: Arc::new(RwLock::new(HashSet::new())),
+            local: LocalBus::global(),
+            local_bound: std::sync::Mutex::new(Vec::new()),
+            local_clients,
+            local_publisher,
+            local_subscriber,
+            router_inbox,
+            send_queues: Arc::new(RwLock::new(HashMap::new())),
+            send_queue_capacity: DEFAULT_SEND_QUEUE_CAPACITY,
+            overflow: OverflowPolicy::default(),
+            reconnect: ReconnectPolicy::default(),
+            heartbeat: options.heartbeat,
+            peer_events,
+            backpressure,
+            traffic,
+            transport: None,
+            encryption: EncryptionMode::Off,
+            peer_keys: Arc::new(RwLock::new(HashMap::new())),
+            identity_keys: Arc::new(Mutex::new(Lru::new(IDENTITY_KEY_CAPACITY))),
+            bare_peers: Arc::new(RwLock::new(HashSet::new())),
+            #[cfg(feature = "websocket")]
+            websocket_bind: None,
+            #[cfg(feature = "websocket")]
+            websocket_clients: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "websocket")]
+            websocket_cap,
+        }
+    }
+
+    /// Also binds the router and publisher to these endpoints, such as an
+    /// IPv6 or `ipc://` one alongside the IPv4 endpoints given to `new`.
+    pub fn with_extra_binds(
+        mut self,
+        router: impl IntoIterator<Item = String>,
+        publisher: impl IntoIterator<Item = String>,
+    ) -> Self {
+        self.router_binds.extend(router);
+        self.publisher_binds.extend(publisher);
+        self
+    }
+
+    /// Binds and dials `local://` endpoints on `bus` rather than on
+    /// [`LocalBus::global`].
+    pub fn with_local_bus(mut self, bus: LocalBus) -> Self {
+        self.local = bus;
+        self
+    }
+
+    /// Also accept WebSocket clients on `address` (`host:port`) once bound.
+    #[cfg(feature = "websocket")]
+    pub fn with_websocket(mut self, address: impl Into<String>) -> Self {
+        self.websocket_bind = Some(address.into());
+        self
+    }
+
+    pub fn with_encryption(mut self, transport: TransportKeys, encryption: EncryptionMode) -> Self {
+        self.transport = Some(transport);
+        self.encryption = encryption;
+        self
+    }
+
+    /// Bounds each peer's send queue to `capacity` frames, applying `overflow`
+    /// when a peer falls behind. Affects peers connected afterwards.
+    pub fn with_send_queue(mut self, capacity: usize, overflow: OverflowPolicy) -> Self {
+        self.send_queue_capacity = capacity;
+        self.overflow = overflow;
+        self
+    }
+
+    /// Backoff between attempts to reach a peer. Affects peers connected afterwards.
+    pub fn with_reconnect(mut self, reconnect: ReconnectPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub fn encryption(&self) -> EncryptionMode {
+        self.encryption
+    }
+
+    /// Pins the key used to seal frames to `peer_id` and to authenticate its replies.
+    pub async fn set_peer_key(&self, peer_id: &str, key: PublicKey) {
+        self.peer_keys.write().await.insert(peer_id.to_string(), key);
+    }
+
+    pub async fn peer_key(&self, peer_id: &str) -> Option<PublicKey> {
+        self.peer_keys.read().await.get(peer_id).cloned()
+    }
+
+    async fn is_peer_key(&self, key: &PublicKey) -> bool {
+        self.peer_keys.read().await.values().any(|known| known == key)
+    }
+
+    /// Sets how protocol messages to `peer_id` are framed, once the
+    /// handshake tells which form it reads.
+    pub async fn set_peer_framing(&self, peer_id: &str, framing: Framing) {
+        let mut bare_peers = self.bare_peers.write().await;
+        match framing {
+            Framing::Bare => bare_peers.insert(peer_id.to_string()),
+            Framing::Envelope => bare_peers.remove(peer_id),
+        };
+    }
+
+    pub async fn peer_framing(&self, peer_id: &str) -> Framing {
+        if self.bare_peers.read().await.contains(peer_id) {
+            Framing::Bare
+        } else {
+            Framing::Envelope
+        }
+    }
+
+    fn seal(&self, key: Option<&PublicKey>, message: Bytes) -> Result<Bytes, NetworkError> {
+        let sealing = match self.encryption {
+            EncryptionMode::Off => None,
+            EncryptionMode::Opportunistic => self.transport.as_ref().zip(key),
+            EncryptionMode::Required => Some(
+                self.transport
+                    .as_ref()
+                    .zip(key)
+                    .ok_or_else(|| NetworkError::Encryption("no transport key for peer".into()))?,
+            ),
+        };
+
+        match sealing {
+            Some((transport, key)) => transport
+                .seal(key, &message)
+                .map(Bytes::from)
+                .map_err(|e| NetworkError::Encryption(e.to_string())),
+            None => Ok(message),
+        }
+    }
+
+    /// Opens a sealed frame. Returns `None` for frames that must be dropped.
+    fn open(&self, content: Bytes) -> Option<(Option<PublicKey>, Bytes)> {
+        if !TransportKeys::is_sealed(&content) {
+            if self.encryption == EncryptionMode::Required {
+                tracing::warn!("dropped plaintext frame, encryption is required");
+                return None;
+            }
+            return Some((None, content));
+        }
+
+        let Some(transport) = &self.transport else {
+            tracing::warn!("dropped sealed frame, no transport keys configured");
+            return None;
+        };
+        match transport.open(&content) {
+            Ok((sender, plaintext)) => Some((Some(sender), Bytes::from(plaintext))),
+            Err(e) => {
+                tracing::warn!(error = %e, "dropped sealed frame");
+                None
+            }
         }
     }
 
     pub async fn bind(&self) -> Result<(), NetworkError> {
-        self.router_tx
-            .send(RouterCommand::Bind(self.router_bind.clone()))
-            .await
-            .map_err(|_| NetworkError::Send("Router actor closed".into()))?;
+        for address in &self.router_binds {
+            if local::is_local(address) {
+                self.local
+                    .bind_router(address, self.router_inbox.clone(), Arc::clone(&self.local_clients))?;
+                self.record_local_bind(address);
+                continue;
+            }
+            self.router_tx
+                .send(RouterCommand::Bind(address.clone()))
+                .await
+                .map_err(|_| NetworkError::Send("Router actor closed".into()))?;
+        }
 
-        self.publisher_tx
-            .send(PublisherCommand::Bind(self.publisher_bind.clone()))
-            .await
-            .map_err(|_| NetworkError::Send("Publisher actor closed".into()))?;
+        for address in &self.publisher_binds {
+            if local::is_local(address) {
+                self.local.bind_publisher(address, self.local_publisher.clone())?;
+                self.record_local_bind(address);
+                continue;
+            }
+            self.publisher_tx
+                .send(PublisherCommand::Bind(address.clone()))
+                .await
+                .map_err(|_| NetworkError::Send("Publisher actor closed".into()))?;
+        }
+
+        #[cfg(feature = "websocket")]
+        if let Some(address) = &self.websocket_bind {
+            let listener = tokio::net::TcpListener::bind(address)
+                .await
+                .map_err(|e| NetworkError::Bind(e.to_string()))?;
+            tokio::spawn(super::websocket::websocket_actor(
+                listener,
+                self.router_inbox.clone(),
+                Arc::clone(&self.websocket_clients),
+                self.websocket_cap.clone(),
+            ));
+            tracing::info!(websocket = %address, "websocket listener bound");
+        }
 
         tracing::info!(
-            router = %self.router_bind,
-            publisher = %self.publisher_bind,
+            router = %self.router_binds.join(", "),
+            publisher = %self.publisher_binds.join(", "),
             "network sockets bound"
         );
 
         Ok(())
     }
 
+    fn record_local_bind(&self, address: &str) {
+        self.local_bound
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(address.to_string());
+    }
+
     pub async fn connect_to_peer(&self, peer_id: &str, address: &str) -> Result<(), NetworkError> {
+        self.connect_to_peer_endpoints(peer_id, &[address.to_string()]).await
+    }
+
+    /// Links `peer_id` over the first of `addresses` that accepts a
+    /// connection, trying them in order on every attempt. See
+    /// [`connected_endpoint`](Self::connected_endpoint) for the one in use.
+    pub async fn connect_to_peer_endpoints(&self, peer_id: &str, addresses: &[String]) -> Result<(), NetworkError> {
+        if addresses.is_empty() {
+            return Err(NetworkError::Connect(format!("no endpoints for peer {}", peer_id)));
+        }
+        let queue = {
+            let mut queues = self.send_queues.write().await;
+            if queues.contains_key(peer_id) {
+                return Ok(());
+            }
+            let queue = SendQueue::new(self.send_queue_capacity, self.overflow)
+                .with_alerts(peer_id, self.backpressure.clone());
+            let queue = Arc::new(queue);
+            queues.insert(peer_id.to_string(), Arc::clone(&queue));
+            queue
+        };
+        let link = DealerLink {
+            peer_id: peer_id.to_string(),
+            queue,
+            reconnect: self.reconnect,
+            heartbeat: self.heartbeat,
+            events: self.peer_events.clone(),
+            local: self.local.clone(),
+        };
         self.dealer_tx
-            .send(DealerCommand::Connect(peer_id.to_string(), address.to_string()))
+            .send(DealerCommand::Connect(link, addresses.to_vec()))
             .await
             .map_err(|_| NetworkError::Send("Dealer actor closed".into()))?;
         Ok(())
     }
 
+    /// The endpoint `peer_id`'s dealer link last connected to, if it has.
+    pub async fn connected_endpoint(&self, peer_id: &str) -> Option<String> {
+        self.send_queues.read().await.get(peer_id)?.endpoint()
+    }
+
+    /// Dealer links coming up or going down from now on. A link that drops is
+    /// retried until it comes back, so every `Down` is followed by an `Up`
+    /// once the peer is reachable again.
+    pub fn subscribe_peer_events(&self) -> broadcast::Receiver<PeerEvent> {
+        self.peer_events.subscribe()
+    }
+
+    /// Reports send queues filling up and publishes failing, as they happen.
+    pub fn subscribe_backpressure(&self) -> broadcast::Receiver<Backpressure> {
+        self.backpressure.subscribe()
+    }
+
     pub async fn subscribe_to_peer(&self, address: &str) -> Result<(), NetworkError> {
+        self.subscribe_to_peer_endpoints(&[address.to_string()]).await
+    }
+
+    /// Subscribes to one peer's publisher at the first of `addresses` that
+    /// accepts a connection. `local://` endpoints are preferred, and must
+    /// already be bound.
+    pub async fn subscribe_to_peer_endpoints(&self, addresses: &[String]) -> Result<(), NetworkError> {
+        let (local, remote): (Vec<_>, Vec<_>) = addresses.iter().cloned().partition(|a| local::is_local(a));
+        if local.iter().any(|address| self.local.subscribe(address, &self.local_subscriber)) {
+            return Ok(());
+        }
+        if remote.is_empty() {
+            return Err(NetworkError::Subscribe(format!("no publisher bound at {:?}", local)));
+        }
         self.subscriber_tx
-            .send(SubscriberCommand::Connect(address.to_string()))
+            .send(SubscriberCommand::Connect(remote))
             .await
             .map_err(|_| NetworkError::Send("Subscriber actor closed".into()))?;
         Ok(())
@@ -138,7 +508,8 @@ impl RacerNetwork {
             .send(SubscriberCommand::Subscribe(topic.to_string()))
             .await
             .map_err(|_| NetworkError::Send("Subscriber actor closed".into()))?;
-        
+        self.local_topics().insert(topic.to_string());
+
         self.subscribed_topics.write().await.insert(topic.to_string());
         Ok(())
     }
@@ -148,24 +519,96 @@ impl RacerNetwork {
             .send(SubscriberCommand::Unsubscribe(topic.to_string()))
             .await
             .map_err(|_| NetworkError::Send("Subscriber actor closed".into()))?;
+        self.local_topics().remove(topic);
 
         self.subscribed_topics.write().await.remove(topic);
         Ok(())
     }
 
+    fn local_topics(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.local_subscriber.topics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     pub async fn is_subscribed(&self, topic: &str) -> bool {
         self.subscribed_topics.read().await.contains(topic)
     }
 
-    pub async fn send_to_peer(&self, peer_id: &str, message: Vec<u8>) -> Result<(), NetworkError> {
-        self.dealer_tx
-            .send(DealerCommand::Send(peer_id.to_string(), message))
+    /// Queues `message` for `peer_id`. Under [`OverflowPolicy::Block`] this
+    /// waits while the peer's queue is full; otherwise it returns at once.
+    pub async fn send_to_peer(&self, peer_id: &str, message: impl Into<Bytes>) -> Result<(), NetworkError> {
+        self.send_to_peer_with_priority(peer_id, message, Priority::Normal).await
+    }
+
+    /// Like [`send_to_peer`](Self::send_to_peer), but the frame is sent
+    /// ahead of less urgent ones still queued for the peer.
+    pub async fn send_to_peer_with_priority(
+        &self,
+        peer_id: &str,
+        message: impl Into<Bytes>,
+        priority: Priority,
+    ) -> Result<(), NetworkError> {
+        let plaintext = match self.peer_framing(peer_id).await {
+            Framing::Envelope => message.into(),
+            Framing::Bare => envelope::to_bare(&message.into()),
+        };
+        let message = self.seal(self.peer_key(peer_id).await.as_ref(), plaintext.clone())?;
+        let queue = self
+            .send_queues
+            .read()
             .await
-            .map_err(|_| NetworkError::Send("Dealer actor closed".into()))?;
+            .get(peer_id)
+            .cloned()
+            .ok_or_else(|| NetworkError::PeerNotFound(peer_id.to_string()))?;
+
+        let wire_len = message.len();
+        match queue.push(message, priority).await {
+            Pushed::Queued => {}
+            Pushed::Displaced => tracing::debug!(peer_id, "send queue full, dropped oldest frame"),
+            Pushed::Rejected => {
+                queue.record_send_failure();
+                return Err(NetworkError::QueueFull(peer_id.to_string()));
+            }
+        }
+        self.traffic.record_sent(FrameKind::Request, &plaintext, wire_len);
         Ok(())
     }
 
-    pub async fn publish(&self, topic: &str, message: Vec<u8>) -> Result<(), NetworkError> {
+    /// Depth of the fullest send queue as a fraction of its capacity; 0.0
+    /// without peers.
+    pub async fn send_queue_fill(&self) -> f64 {
+        self.send_queues
+            .read()
+            .await
+            .values()
+            .map(|queue| queue.fill())
+            .fold(0.0, f64::max)
+    }
+
+    /// Traffic totals since the network was created, per-peer link health
+    /// and the topics currently subscribed to.
+    pub async fn stats(&self) -> NetworkStats {
+        let mut peers: Vec<_> = self
+            .send_queues
+            .read()
+            .await
+            .iter()
+            .map(|(peer_id, queue)| queue.stats(peer_id))
+            .collect();
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+
+        let mut subscriptions: Vec<_> = self.subscribed_topics.read().await.iter().cloned().collect();
+        subscriptions.sort();
+
+        NetworkStats {
+            peers,
+            subscriptions,
+            ..self.traffic.snapshot()
+        }
+    }
+
+    pub async fn publish(&self, topic: &str, message: impl Into<Bytes>) -> Result<(), NetworkError> {
+        let message = message.into();
+        self.traffic.record_sent(FrameKind::ProtocolResponse, &message, message.len());
         self.publisher_tx
             .send(PublisherCommand::Publish(topic.to_string(), message))
             .await
@@ -173,43 +616,130 @@ impl RacerNetwork {
         Ok(())
     }
 
-    pub async fn recv_router(&self) -> Result<(Vec<u8>, Vec<u8>), NetworkError> {
+    pub async fn recv_router(&self) -> Result<(Vec<u8>, Bytes), NetworkError> {
         let mut rx = self.router_rx.lock().await;
-        rx.recv()
-            .await
-            .ok_or_else(|| NetworkError::Recv("Router actor closed".into()))
+        loop {
+            let (identity, content) = rx
+                .recv()
+                .await
+                .ok_or_else(|| NetworkError::Recv("Router actor closed".into()))?;
+
+            let wire_len = content.len();
+            let Some((sender, content)) = self.open(content) else {
+                continue;
+            };
+            if let Some(sender) = sender {
+                // Anyone can seal to our key; with encryption required, only
+                // the keys of pinned or registered peers may get in.
+                if self.encryption == EncryptionMode::Required && !self.is_peer_key(&sender).await {
+                    tracing::warn!("dropped frame sealed by a key of no known peer");
+                    continue;
+                }
+                self.identity_keys.lock().await.insert(identity.clone(), sender);
+            }
+            self.traffic.record_received(FrameKind::Request, &content, wire_len);
+            return Ok((identity, content));
+        }
     }
 
-    pub async fn recv_subscriber(&self) -> Result<(String, Vec<u8>), NetworkError> {
+    pub async fn recv_subscriber(&self) -> Result<(String, Bytes), NetworkError> {
         let mut rx = self.subscriber_rx.lock().await;
-        rx.recv()
+        let (topic, content) = rx
+            .recv()
             .await
-            .ok_or_else(|| NetworkError::Recv("Subscriber actor closed".into()))
+            .ok_or_else(|| NetworkError::Recv("Subscriber actor closed".into()))?;
+        self.traffic.record_received(FrameKind::ProtocolResponse, &content, content.len());
+        Ok((topic, content))
     }
 
-    pub async fn recv_dealer(&self) -> Result<(String, Vec<u8>), NetworkError> {
+    pub async fn recv_dealer(&self) -> Result<(String, Bytes), NetworkError> {
         let mut rx = self.dealer_rx.lock().await;
-        rx.recv()
-            .await
-            .ok_or_else(|| NetworkError::Recv("Dealer actor closed".into()))
+        loop {
+            let (peer_id, content) = rx
+                .recv()
+                .await
+                .ok_or_else(|| NetworkError::Recv("Dealer actor closed".into()))?;
+
+            let wire_len = content.len();
+            let Some((sender, content)) = self.open(content) else {
+                continue;
+            };
+            if let (Some(sender), Some(expected)) = (&sender, self.peer_key(&peer_id).await) {
+                if *sender != expected {
+                    tracing::warn!(peer_id, "dropped reply sealed by unexpected key");
+                    continue;
+                }
+            }
+            self.traffic.record_received(FrameKind::CongestionUpdate, &content, wire_len);
+            return Ok((peer_id, content));
+        }
     }
 
     pub async fn send_router_reply(
         &self,
         identity: Vec<u8>,
-        message: Vec<u8>,
+        message: impl Into<Bytes>,
     ) -> Result<(), NetworkError> {
+        let message = message.into();
+        #[cfg(feature = "websocket")]
+        if super::websocket::is_websocket_identity(&identity) {
+            let client = self.websocket_clients.read().await.get(&identity).cloned();
+            self.traffic.record_sent(FrameKind::CongestionUpdate, &message, message.len());
+            return match client {
+                Some(client) => client
+                    .send(message.to_vec())
+                    .await
+                    .map_err(|_| NetworkError::Send("WebSocket client closed".into())),
+                None => Err(NetworkError::PeerNotFound("WebSocket client disconnected".into())),
+            };
+        }
+
+        let key = self.identity_keys.lock().await.get(&identity);
+        let sealed = self.seal(key.as_ref(), message.clone())?;
+        self.traffic.record_sent(FrameKind::CongestionUpdate, &message, sealed.len());
+        if local::reply(&self.local_clients, &identity, sealed.clone()) {
+            return Ok(());
+        }
         self.router_tx
-            .send(RouterCommand::SendReply(identity, message))
+            .send(RouterCommand::SendReply(identity, sealed))
             .await
             .map_err(|_| NetworkError::Send("Router actor closed".into()))?;
         Ok(())
     }
 }
 
+impl RacerNetwork {
+    /// Pushes `message` to every connected WebSocket client. Slow clients miss it.
+    pub async fn broadcast_observers(&self, message: &[u8]) {
+        #[cfg(feature = "websocket")]
+        for client in self.websocket_clients.read().await.values() {
+            let _ = client.try_send(message.to_vec());
+        }
+        #[cfg(not(feature = "websocket"))]
+        let _ = message;
+    }
+
+    /// Number of connected WebSocket clients; always 0 without the `websocket` feature.
+    pub async fn observer_count(&self) -> usize {
+        #[cfg(feature = "websocket")]
+        return self.websocket_clients.read().await.len();
+        #[cfg(not(feature = "websocket"))]
+        0
+    }
+}
+
+impl Drop for RacerNetwork {
+    fn drop(&mut self) {
+        let bound = std::mem::take(self.local_bound.get_mut().unwrap_or_else(|e| e.into_inner()));
+        for address in bound {
+            self.local.unbind(&address);
+        }
+    }
+}
+
 async fn router_actor(
     mut commands: mpsc::Receiver<RouterCommand>,
-    msg_sender: mpsc::Sender<(Vec<u8>, Vec<u8>)>,
+    msg_sender: mpsc::Sender<(Vec<u8>, Bytes)>,
 ) {
     let mut socket = RouterSocket::new();
 
@@ -225,7 +755,7 @@ async fn router_actor(
                     }
                     Some(RouterCommand::SendReply(identity, content)) => {
                         let mut msg = zeromq::ZmqMessage::from(identity);
-                        msg.push_back(content.into());
+                        msg.push_back(content);
                         if let Err(e) = socket.send(msg).await {
                             tracing::error!(error = %e, "Router send failed");
                         }
@@ -240,7 +770,16 @@ async fn router_actor(
                         let frames: Vec<_> = msg.into_vec();
                         if frames.len() >= 2 {
                             let identity = frames[0].to_vec();
-                            let content = frames.get(1).map(|f| f.to_vec()).unwrap_or_default();
+                            let content = frames[1].clone();
+                            // An empty frame is a dealer's heartbeat: echo it.
+                            if content.is_empty() {
+                                let mut msg = zeromq::ZmqMessage::from(identity);
+                                msg.push_back(Bytes::new());
+                                if let Err(e) = socket.send(msg).await {
+                                    tracing::debug!(error = %e, "Router heartbeat reply failed");
+                                }
+                                continue;
+                            }
                             // If receiver is full or dropped, we just log and continue
                             if let Err(_) = msg_sender.send((identity, content)).await {
                                 tracing::debug!("Router msg receiver closed");
@@ -259,7 +798,7 @@ async fn router_actor(
 
 async fn subscriber_actor(
     mut commands: mpsc::Receiver<SubscriberCommand>,
-    msg_sender: mpsc::Sender<(String, Vec<u8>)>,
+    msg_sender: mpsc::Sender<(String, Bytes)>,
 ) {
     let mut socket = SubSocket::new();
 
@@ -267,11 +806,19 @@ async fn subscriber_actor(
         tokio::select! {
             cmd = commands.recv() => {
                 match cmd {
-                    Some(SubscriberCommand::Connect(addr)) => {
-                        if let Err(e) = socket.connect(&addr).await {
-                            tracing::error!(error = %e, "Subscriber connect failed");
-                        } else {
-                            tracing::debug!(addr, "Subscriber connected");
+                    Some(SubscriberCommand::Connect(addresses)) => {
+                        let mut errors = Vec::new();
+                        for addr in &addresses {
+                            match socket.connect(addr).await {
+                                Ok(()) => {
+                                    tracing::debug!(addr, "Subscriber connected");
+                                    break;
+                                }
+                                Err(e) => errors.push(format!("{}: {}", addr, e)),
+                            }
+                        }
+                        if errors.len() == addresses.len() {
+                            tracing::error!(errors = %errors.join("; "), "Subscriber connect failed");
                         }
                     }
                     Some(SubscriberCommand::Subscribe(topic)) => {
@@ -294,7 +841,7 @@ async fn subscriber_actor(
                         let frames: Vec<_> = msg.into_vec();
                         if frames.len() >= 2 {
                             let topic = String::from_utf8_lossy(&frames[0]).to_string();
-                            let content = frames[1].to_vec();
+                            let content = frames[1].clone();
                             if let Err(_) = msg_sender.send((topic, content)).await {
                                 break;
                             }
@@ -309,7 +856,12 @@ async fn subscriber_actor(
     }
 }
 
-async fn publisher_actor(mut commands: mpsc::Receiver<PublisherCommand>) {
+async fn publisher_actor(
+    mut commands: mpsc::Receiver<PublisherCommand>,
+    local: LocalPublisher,
+    traffic: Arc<TrafficCounters>,
+    backpressure: broadcast::Sender<Backpressure>,
+) {
     let mut socket = PubSocket::new();
 
     while let Some(cmd) = commands.recv().await {
@@ -320,94 +872,213 @@ async fn publisher_actor(mut commands: mpsc::Receiver<PublisherCommand>) {
                 }
             }
             PublisherCommand::Publish(topic, content) => {
+                local.publish(&topic, &content);
                 let mut msg = zeromq::ZmqMessage::from(topic.as_bytes().to_vec());
-                msg.push_back(content.into());
+                msg.push_back(content);
                 if let Err(e) = socket.send(msg).await {
                     tracing::error!(error = %e, "Publisher send failed");
+                    traffic.record_publish_failure();
+                    let _ = backpressure.send(Backpressure::PublishFailed { topic });
                 }
             }
         }
     }
 }
 
-async fn dealer_actor(
-    mut commands: mpsc::Receiver<DealerCommand>,
-    msg_sender: mpsc::Sender<(String, Vec<u8>)>,
-) {
+async fn dealer_actor(mut commands: mpsc::Receiver<DealerCommand>, msg_sender: mpsc::Sender<(String, Bytes)>) {
     let mut workers: std::collections::HashMap<String, mpsc::Sender<DealerWorkerCommand>> = std::collections::HashMap::new();
 
     while let Some(cmd) = commands.recv().await {
         match cmd {
-            DealerCommand::Connect(peer_id, addr) => {
-                if !workers.contains_key(&peer_id) {
+            DealerCommand::Connect(link, addresses) => {
+                if !workers.contains_key(&link.peer_id) {
                     let (tx, rx) = mpsc::channel(CHANNEL_BUFFER);
-                    tokio::spawn(dealer_worker(peer_id.clone(), addr.clone(), rx, msg_sender.clone()));
+                    let peer_id = link.peer_id.clone();
+                    tokio::spawn(dealer_worker(link, addresses, rx, msg_sender.clone()));
                     workers.insert(peer_id, tx);
                 }
             }
-            DealerCommand::Send(peer_id, content) => {
-                if let Some(tx) = workers.get(&peer_id) {
-                    if let Err(e) = tx.send(DealerWorkerCommand::Send(content)).await {
-                        tracing::error!(peer_id, error = %e, "Failed to send command to dealer worker");
-                        workers.remove(&peer_id); // Remove dead worker
-                    }
-                } else {
-                    tracing::warn!(peer_id, "Dealer worker not found for peer");
-                }
+        }
+    }
+}
+
+/// What a dealer worker needs besides its socket.
+struct DealerLink {
+    peer_id: String,
+    queue: Arc<SendQueue>,
+    reconnect: ReconnectPolicy,
+    heartbeat: Option<HeartbeatPolicy>,
+    events: broadcast::Sender<PeerEvent>,
+    local: LocalBus,
+}
+
+/// A dealer worker's connection: a socket, or a mailbox on the [`LocalBus`].
+enum DealerConn {
+    Zmq(DealerSocket),
+    Local(LocalConn),
+}
+
+impl DealerConn {
+    async fn send(&mut self, content: Bytes) -> Result<(), String> {
+        match self {
+            Self::Zmq(socket) => socket.send(zeromq::ZmqMessage::from(content)).await.map_err(|e| e.to_string()),
+            Self::Local(conn) => conn.send(content).await,
+        }
+    }
+
+    /// The content frame of the next reply, if it has one.
+    async fn recv(&mut self) -> Result<Option<Bytes>, String> {
+        match self {
+            Self::Zmq(socket) => match socket.recv().await {
+                Ok(msg) => Ok(msg.into_vec().last().cloned()),
+                Err(e) => Err(e.to_string()),
+            },
+            Self::Local(conn) => conn.recv().await.map(Some),
+        }
+    }
+}
+
+/// Waits for the next heartbeat tick; forever without heartbeats.
+async fn next_tick(ticker: &mut Option<tokio::time::Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Why a dealer worker left its serve loop.
+enum LinkEnd {
+    Lost,
+    Moved(Vec<String>),
+    Closed,
+}
+
+/// A connection to the first of `addresses` that answers, and that address.
+async fn connect_first(peer_id: &str, addresses: &[String], bus: &LocalBus) -> Option<(DealerConn, String)> {
+    for address in addresses {
+        if local::is_local(address) {
+            match bus.connect(address) {
+                Some(conn) => return Some((DealerConn::Local(conn), address.clone())),
+                None => tracing::debug!(peer_id, address, "Dealer worker found nothing bound"),
             }
+            continue;
+        }
+        let mut socket = DealerSocket::new();
+        match tokio::time::timeout(CONNECT_ATTEMPT_TIMEOUT, socket.connect(address)).await {
+            Ok(Ok(())) => return Some((DealerConn::Zmq(socket), address.clone())),
+            Ok(Err(e)) => tracing::debug!(peer_id, address, error = %e, "Dealer worker connect failed"),
+            Err(_) => tracing::debug!(peer_id, address, "Dealer worker connect timed out"),
         }
     }
+    None
 }
 
 async fn dealer_worker(
-    peer_id: String,
-    address: String,
+    link: DealerLink,
+    mut addresses: Vec<String>,
     mut commands: mpsc::Receiver<DealerWorkerCommand>,
-    msg_sender: mpsc::Sender<(String, Vec<u8>)>,
+    msg_sender: mpsc::Sender<(String, Bytes)>,
 ) {
-    let mut socket = DealerSocket::new();
-    
-    if let Err(e) = socket.connect(&address).await {
+    let peer_id = link.peer_id.as_str();
+    let mut backoff = link.reconnect.backoff();
+    let mut connected_before = false;
+
+    loop {
+        // Connect, backing off between rounds in which no address answered.
+        let (mut conn, address) = loop {
+            if let Some(connected) = connect_first(peer_id, &addresses, &link.local).await {
+                break connected;
+            }
+
+            let delay = backoff.next_delay();
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                cmd = commands.recv() => match cmd {
+                    Some(DealerWorkerCommand::Connect(new_addresses)) => addresses = new_addresses,
+                    None => return,
+                },
+            }
+        };
 
-    } else {
+        if connected_before {
+            link.queue.record_reconnect();
+        }
+        connected_before = true;
+        link.queue.record_endpoint(&address);
+        backoff.reset();
         tracing::debug!(peer_id, address, "Dealer worker connected");
-    }
+        let _ = link.events.send(PeerEvent::Up(peer_id.to_string()));
 
-    loop {
-        tokio::select! {
-            cmd = commands.recv() => {
-                match cmd {
-                    Some(DealerWorkerCommand::Connect(new_addr)) => {
-                         if let Err(e) = socket.connect(&new_addr).await {
-                            tracing::error!(peer_id, error = %e, "Dealer worker reconnect failed");
-                        }
-                    }
-                    Some(DealerWorkerCommand::Send(content)) => {
-                        let msg = zeromq::ZmqMessage::from(content);
-                        if let Err(e) = socket.send(msg).await {
-                            tracing::error!(peer_id, error = %e, "Dealer worker send failed");
-                        }
+        // Links on the local bus cannot be left half-open.
+        let mut liveness = match (&conn, link.heartbeat) {
+            (DealerConn::Zmq(_), Some(policy)) => Some(Liveness::new(policy)),
+            _ => None,
+        };
+        let mut ticker = liveness.as_ref().map(Liveness::ticker);
+
+        let end = loop {
+            tokio::select! {
+                cmd = commands.recv() => {
+                    match cmd {
+                        Some(DealerWorkerCommand::Connect(new_addresses)) => break LinkEnd::Moved(new_addresses),
+                        None => break LinkEnd::Closed,
                     }
-                    None => break, // Channel closed
                 }
-            }
 
-            res = socket.recv() => {
-                match res {
-                    Ok(msg) => {
+                content = link.queue.pop() => {
+                    if let Err(e) = conn.send(content).await {
+                        link.queue.record_send_failure();
+                        tracing::warn!(peer_id, error = %e, "Dealer worker send failed, reconnecting");
+                        break LinkEnd::Lost;
+                    }
+                }
 
-                        let frames: Vec<_> = msg.into_vec();
-                        if let Some(content_frame) = frames.last() {
-                             if let Err(_) = msg_sender.send((peer_id.clone(), content_frame.to_vec())).await {
-                                break;
+                _ = next_tick(&mut ticker) => {
+                    let pulse = liveness.as_ref().map_or(Pulse::Alive, |l| l.pulse(std::time::Instant::now()));
+                    match pulse {
+                        Pulse::Alive => {}
+                        Pulse::Quiet => {
+                            if let Err(e) = conn.send(Bytes::new()).await {
+                                tracing::warn!(peer_id, error = %e, "Dealer worker heartbeat failed, reconnecting");
+                                break LinkEnd::Lost;
                             }
                         }
+                        Pulse::Dead => {
+                            tracing::warn!(peer_id, address, "Dealer worker heard nothing within the heartbeat timeout, reconnecting");
+                            break LinkEnd::Lost;
+                        }
                     }
-                    Err(e) => {
-                        tracing::error!(peer_id, error = %e, "Dealer worker recv failed");
+                }
+
+                res = conn.recv() => {
+                    if let (Ok(_), Some(liveness)) = (&res, liveness.as_mut()) {
+                        liveness.heard();
+                    }
+                    match res {
+                        // The peer's answer to a heartbeat.
+                        Ok(Some(content)) if content.is_empty() => {}
+                        Ok(Some(content)) => {
+                            if msg_sender.send((peer_id.to_string(), content)).await.is_err() {
+                                break LinkEnd::Closed;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            tracing::warn!(peer_id, error = %e, "Dealer worker recv failed, reconnecting");
+                            break LinkEnd::Lost;
+                        }
                     }
                 }
             }
+        };
+
+        let _ = link.events.send(PeerEvent::Down(peer_id.to_string()));
+        match end {
+            LinkEnd::Lost => {}
+            LinkEnd::Moved(new_addresses) => addresses = new_addresses,
+            LinkEnd::Closed => return,
         }
     }
 }
@@ -428,4 +1099,8 @@ pub enum NetworkError {
     PeerNotFound(String),
     #[error("invalid message: {0}")]
     InvalidMessage(String),
+    #[error("encryption error: {0}")]
+    Encryption(String),
+    #[error("send queue full for peer: {0}")]
+    QueueFull(String),
 }
@@ -0,0 +1,182 @@
+//! Traffic counters behind [`RacerNetwork::stats`](super::RacerNetwork::stats).
+//!
+//! Frames are counted by their wire size, before opening or after sealing.
+//! The protocol type of a frame is read from the socket it travels on:
+//! router requests carry a `ProtocolMessage`, whose envelope leads with `v`
+//! and `type` (or, in the bare form, with the `message_type` tag), router
+//! replies a `CongestionUpdate` and pub/sub frames a `ProtocolResponse`.
+//!
+//! Frames this node cannot send fast enough are counted too, and reported as
+//! they happen as [`Backpressure`]: a send queue filling up, or the publisher
+//! socket refusing a frame. Left alone, both lose frames without a trace.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+const ENVELOPE_PREFIX: &[u8] = br#"{"v":"#;
+const TYPE_KEY: &[u8] = br#","type":""#;
+/// Frames in the bare form, from nodes that predate the envelope.
+const TAG_PREFIX: &[u8] = br#"{"message_type":""#;
+
+/// How a frame's protocol type is determined.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FrameKind {
+    /// A tagged `ProtocolMessage` on the router/dealer pair.
+    Request,
+    CongestionUpdate,
+    ProtocolResponse,
+}
+
+impl FrameKind {
+    fn message_type(self, plaintext: &[u8]) -> &str {
+        match self {
+            Self::Request => Self::type_field(plaintext)
+                .and_then(|rest| rest.iter().position(|&b| b == b'"').map(|end| &rest[..end]))
+                .and_then(|tag| std::str::from_utf8(tag).ok())
+                .unwrap_or("unknown"),
+            Self::CongestionUpdate => "CongestionUpdate",
+            Self::ProtocolResponse => "ProtocolResponse",
+        }
+    }
+
+    /// What follows the opening quote of a request's type.
+    fn type_field(plaintext: &[u8]) -> Option<&[u8]> {
+        match plaintext.strip_prefix(ENVELOPE_PREFIX) {
+            Some(rest) => {
+                let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                rest[digits..].strip_prefix(TYPE_KEY)
+            }
+            None => plaintext.strip_prefix(TAG_PREFIX),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TrafficCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    publish_failures: AtomicU64,
+    oversized_frames: AtomicU64,
+    sent: Mutex<BTreeMap<String, u64>>,
+    received: Mutex<BTreeMap<String, u64>>,
+}
+
+impl TrafficCounters {
+    pub(crate) fn record_sent(&self, kind: FrameKind, plaintext: &[u8], wire_len: usize) {
+        self.bytes_sent.fetch_add(wire_len as u64, Ordering::Relaxed);
+        Self::bump(&self.sent, kind.message_type(plaintext));
+    }
+
+    pub(crate) fn record_received(&self, kind: FrameKind, plaintext: &[u8], wire_len: usize) {
+        self.bytes_received.fetch_add(wire_len as u64, Ordering::Relaxed);
+        Self::bump(&self.received, kind.message_type(plaintext));
+    }
+
+    pub(crate) fn record_publish_failure(&self) {
+        self.publish_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "websocket")]
+    pub(crate) fn record_oversized(&self) {
+        self.oversized_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn bump(counts: &Mutex<BTreeMap<String, u64>>, message_type: &str) {
+        let mut counts = counts.lock().unwrap_or_else(|e| e.into_inner());
+        match counts.get_mut(message_type) {
+            Some(count) => *count += 1,
+            None => {
+                counts.insert(message_type.to_string(), 1);
+            }
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> NetworkStats {
+        let counts = |m: &Mutex<BTreeMap<String, u64>>| m.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        NetworkStats {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            publish_failures: self.publish_failures.load(Ordering::Relaxed),
+            oversized_frames: self.oversized_frames.load(Ordering::Relaxed),
+            messages_sent: counts(&self.sent),
+            messages_received: counts(&self.received),
+            ..Default::default()
+        }
+    }
+}
+
+/// Snapshot returned by [`RacerNetwork::stats`](super::RacerNetwork::stats).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Frames the publisher socket failed to send.
+    #[serde(default)]
+    pub publish_failures: u64,
+    /// WebSocket clients cut off for sending a message over `max_frame_bytes`.
+    #[serde(default)]
+    pub oversized_frames: u64,
+    /// Frames sent, by protocol type (`BatchedMessage`, `Echo`, ...).
+    pub messages_sent: BTreeMap<String, u64>,
+    pub messages_received: BTreeMap<String, u64>,
+    /// One entry per connected peer, ordered by peer id.
+    pub peers: Vec<PeerLinkStats>,
+    /// Pub/sub topics currently subscribed to, sorted.
+    pub subscriptions: Vec<String>,
+}
+
+/// Outbound link to one peer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerLinkStats {
+    pub peer_id: String,
+    /// Frames waiting in the peer's send queue.
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    /// Frames lost to the overflow policy.
+    pub dropped: u64,
+    /// Frames that could not be queued or written to the socket.
+    pub send_failures: u64,
+    pub reconnects: u64,
+    /// Times the send queue filled up, counting from empty or half full.
+    #[serde(default)]
+    pub saturations: u64,
+    /// Endpoint the link is connected to, or was last, if it ever connected.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+/// Frames produced faster than they could be sent, from
+/// [`RacerNetwork::subscribe_backpressure`](super::RacerNetwork::subscribe_backpressure).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Backpressure {
+    /// `peer_id`'s send queue filled up; frames beyond it go to the
+    /// overflow policy. Not reported again until the queue drains to half.
+    QueueSaturated { peer_id: String, capacity: usize },
+    /// The publisher socket failed to send a frame on `topic`.
+    PublishFailed { topic: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_by_message_type() {
+        let counters = TrafficCounters::default();
+        counters.record_sent(FrameKind::Request, br#"{"v":3,"type":"Echo","payload":{}}"#, 40);
+        counters.record_sent(FrameKind::Request, br#"{"message_type":"Echo","topic":"u"}"#, 40);
+        counters.record_sent(FrameKind::Request, b"garbage", 7);
+        counters.record_received(FrameKind::ProtocolResponse, b"{}", 2);
+
+        let stats = counters.snapshot();
+        assert_eq!(stats.bytes_sent, 87);
+        assert_eq!(stats.bytes_received, 2);
+        assert_eq!(stats.messages_sent["Echo"], 2);
+        assert_eq!(stats.messages_sent["unknown"], 1);
+        assert_eq!(stats.messages_received["ProtocolResponse"], 1);
+    }
+}
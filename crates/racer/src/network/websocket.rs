@@ -0,0 +1,162 @@
+//! WebSocket listener for lightweight observers (dashboards, WASM clients).
+//!
+//! Clients speak the same JSON `ProtocolMessage` envelope as dealers (see
+//! [`crate::protocol::envelope`]). Each inbound text or binary frame is
+//! handed to the router inbox under a synthetic identity, so replies to that
+//! identity go back over the socket.
+//! Delivered batches are pushed to every connected client. A client whose
+//! message or frame would exceed `max_frame_bytes` is disconnected before
+//! the message is buffered whole, and connections past
+//! [`MAX_WEBSOCKET_CLIENTS`] are closed on accept. Frames are never sealed,
+//! so the listener cannot be combined with `encryption = "required"`.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::{Error, Message};
+
+use super::sockets::CHANNEL_BUFFER;
+use super::stats::TrafficCounters;
+
+const IDENTITY_PREFIX: &[u8] = b"ws:";
+
+/// Clients connected, or completing their handshake, at once.
+pub const MAX_WEBSOCKET_CLIENTS: usize = 256;
+
+/// Connected clients keyed by their synthetic router identity.
+pub(crate) type Clients = Arc<RwLock<HashMap<Vec<u8>, mpsc::Sender<Vec<u8>>>>>;
+
+/// Cuts off clients whose message would exceed
+/// [`SocketOptions::max_frame_bytes`](super::SocketOptions::max_frame_bytes).
+#[derive(Debug, Clone)]
+pub(crate) struct FrameCap {
+    max_bytes: usize,
+    traffic: Arc<TrafficCounters>,
+}
+
+impl FrameCap {
+    pub(crate) fn new(max_bytes: usize, traffic: Arc<TrafficCounters>) -> Self {
+        Self { max_bytes, traffic }
+    }
+
+    fn record_oversized(&self) {
+        self.traffic.record_oversized();
+    }
+
+    /// The limit, or `None` without one.
+    fn max_bytes(&self) -> Option<usize> {
+        (self.max_bytes > 0).then_some(self.max_bytes)
+    }
+}
+
+pub(crate) fn is_websocket_identity(identity: &[u8]) -> bool {
+    identity.starts_with(IDENTITY_PREFIX)
+}
+
+pub(crate) async fn websocket_actor(
+    listener: TcpListener,
+    inbound: mpsc::Sender<(Vec<u8>, Bytes)>,
+    clients: Clients,
+    cap: FrameCap,
+) {
+    let next_id = AtomicU64::new(0);
+    let slots = Arc::new(Semaphore::new(MAX_WEBSOCKET_CLIENTS));
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                let Ok(slot) = Arc::clone(&slots).try_acquire_owned() else {
+                    tracing::warn!(%addr, max = MAX_WEBSOCKET_CLIENTS, "refused WebSocket client, too many connected");
+                    continue;
+                };
+                let mut identity = IDENTITY_PREFIX.to_vec();
+                identity.extend_from_slice(&next_id.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+                tokio::spawn(client_task(
+                    stream,
+                    addr,
+                    identity,
+                    inbound.clone(),
+                    Arc::clone(&clients),
+                    cap.clone(),
+                    slot,
+                ));
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "WebSocket accept failed");
+            }
+        }
+    }
+}
+
+async fn client_task(
+    stream: TcpStream,
+    addr: SocketAddr,
+    identity: Vec<u8>,
+    inbound: mpsc::Sender<(Vec<u8>, Bytes)>,
+    clients: Clients,
+    cap: FrameCap,
+    _slot: OwnedSemaphorePermit,
+) {
+    let mut config = WebSocketConfig::default();
+    config.max_message_size = cap.max_bytes();
+    config.max_frame_size = cap.max_bytes();
+    let socket = match tokio_tungstenite::accept_async_with_config(stream, Some(config)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::warn!(%addr, error = %e, "WebSocket handshake failed");
+            return;
+        }
+    };
+    tracing::debug!(%addr, "WebSocket client connected");
+
+    let (mut sink, mut frames) = socket.split();
+    let (tx, mut outbound) = mpsc::channel(CHANNEL_BUFFER);
+    clients.write().await.insert(identity.clone(), tx);
+
+    loop {
+        tokio::select! {
+            out = outbound.recv() => {
+                let Some(content) = out else { break };
+                let message = match String::from_utf8(content) {
+                    Ok(text) => Message::Text(text),
+                    Err(e) => Message::Binary(e.into_bytes()),
+                };
+                if let Err(e) = sink.send(message).await {
+                    tracing::debug!(%addr, error = %e, "WebSocket send failed");
+                    break;
+                }
+            }
+
+            frame = frames.next() => {
+                let content = match frame {
+                    Some(Ok(Message::Text(text))) => Bytes::from(text),
+                    Some(Ok(Message::Binary(bytes))) => Bytes::from(bytes),
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(Error::Capacity(e))) => {
+                        cap.record_oversized();
+                        tracing::warn!(%addr, error = %e, "disconnected WebSocket client sending an oversized message");
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        tracing::debug!(%addr, error = %e, "WebSocket recv failed");
+                        break;
+                    }
+                };
+                if inbound.send((identity.clone(), content)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    clients.write().await.remove(&identity);
+    tracing::debug!(%addr, "WebSocket client disconnected");
+}
@@ -0,0 +1,560 @@
+//! Local admin API.
+//!
+//! A running node answers newline-delimited JSON requests on `node.admin_bind`,
+//! which must be a loopback address unless `node.admin_allow_remote` is set.
+//! A connection may carry any number of requests of up to
+//! [`MAX_REQUEST_BYTES`]; each gets exactly one response line. [`request`] is
+//! the matching client used by the `racer` CLI.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::pipeline::{Delivery, PipelineStats};
+use super::quarantine::QuarantinedFrame;
+use super::topology::TopologyReport;
+use super::{ImportReport, Node, NodeError, NodeInner};
+use crate::crypto::{PublicKey, VerifyCacheStats};
+use crate::protocol::{BatchedMessages, Priority};
+#[cfg(feature = "store")]
+use crate::store::{DeliveryStore, StoredBatch};
+use crate::Message;
+
+/// Client-side limit for connecting and waiting for a response.
+pub const ADMIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request line the server reads, newline included. Large enough
+/// for an import of a sizeable export; a longer line closes the connection.
+pub const MAX_REQUEST_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum AdminRequest {
+    Status,
+    Peers,
+    Topology,
+    /// Submits a message (JSON for the node's message type). With `wait`, the
+    /// response is sent once the round has finished.
+    Submit {
+        message: serde_json::Value,
+        #[serde(default)]
+        wait: bool,
+    },
+    /// Delivered batches from the node's store, delivered at or after
+    /// `since` (epoch millis). Needs the `store` feature and
+    /// `node.store_path`.
+    Export {
+        #[serde(default)]
+        since: u64,
+    },
+    /// Feeds signed batches (JSON for the node's message type) to the
+    /// node's deliver stage; see [`Node::import_batches`].
+    Import { batches: Vec<serde_json::Value> },
+    /// Frames the pipeline rejected; see [`Node::quarantined`]. With
+    /// `clear`, the quarantine is emptied once they are read.
+    Quarantine {
+        #[serde(default)]
+        clear: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum AdminResponse {
+    Status(NodeStatus),
+    Peers { peers: Vec<PeerStatus> },
+    Topology(TopologyReport),
+    /// `delivered` is only set when the request asked to wait.
+    Submitted { batch_id: String, delivered: Option<bool> },
+    /// Stored deliveries, oldest first, each `{hash, delivered_at, batch}`.
+    Exported { batches: Vec<serde_json::Value> },
+    Imported(ImportReport),
+    Quarantined { frames: Vec<QuarantinedFrame> },
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub id: String,
+    pub public_key: String,
+    pub running: bool,
+    pub uptime_secs: f64,
+    pub active_rounds: usize,
+    pub known_peers: usize,
+    pub plato_latency_secs: f64,
+    pub pipeline: PipelineStats,
+    /// Process-wide signature verification cache counters.
+    #[serde(default)]
+    pub verify_cache: VerifyCacheStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerStatus {
+    pub id: String,
+    pub router_address: String,
+    pub publisher_address: String,
+    pub latency_secs: f64,
+    /// Seconds since the peer was last heard from, if ever.
+    pub last_seen_secs: Option<f64>,
+    pub pinned: bool,
+    /// Relay router the peer is reached through, if it is not reachable itself.
+    #[serde(default)]
+    pub relay: Option<String>,
+    /// Router endpoint the dealer link came up on, when the peer
+    /// advertises several.
+    #[serde(default)]
+    pub connected_router: Option<String>,
+    /// Milliseconds the peer's clock runs ahead of ours, once estimated.
+    #[serde(default)]
+    pub clock_skew_ms: Option<i64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminError {
+    #[error("failed to connect to admin API at {0}")]
+    Connect(String),
+    #[error("admin I/O error: {0}")]
+    Io(String),
+    #[error("invalid admin response: {0}")]
+    Protocol(String),
+    #[error("admin request timed out")]
+    Timeout,
+}
+
+/// Sends one request to the admin API at `address` and waits for its response.
+pub async fn request(address: &str, request: &AdminRequest) -> Result<AdminResponse, AdminError> {
+    request_with_timeout(address, request, ADMIN_TIMEOUT).await
+}
+
+/// Like [`request`], for requests that may legitimately take longer than
+/// [`ADMIN_TIMEOUT`] (e.g. a waiting submit).
+pub async fn request_with_timeout(
+    address: &str,
+    request: &AdminRequest,
+    timeout: Duration,
+) -> Result<AdminResponse, AdminError> {
+    tokio::time::timeout(timeout, async {
+        let stream = TcpStream::connect(address)
+            .await
+            .map_err(|_| AdminError::Connect(address.to_string()))?;
+        let (reader, mut writer) = stream.into_split();
+
+        let mut line = serde_json::to_string(request).map_err(|e| AdminError::Protocol(e.to_string()))?;
+        line.push('\n');
+        writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| AdminError::Io(e.to_string()))?;
+
+        let response = BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await
+            .map_err(|e| AdminError::Io(e.to_string()))?
+            .ok_or_else(|| AdminError::Protocol("connection closed".into()))?;
+        serde_json::from_str(&response).map_err(|e| AdminError::Protocol(e.to_string()))
+    })
+    .await
+    .map_err(|_| AdminError::Timeout)?
+}
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    pub async fn status(&self) -> NodeStatus {
+        Self::status_of(&self.inner).await
+    }
+
+    pub async fn peer_table(&self) -> Vec<PeerStatus> {
+        Self::peer_table_of(&self.inner).await
+    }
+
+    /// Batches delivered at or after `since_millis` (epoch millis), oldest first.
+    #[cfg(feature = "store")]
+    pub fn delivered_since(&self, since_millis: u64) -> Result<Vec<StoredBatch<M>>, NodeError> {
+        self.store()?
+            .delivered_since(since_millis)
+            .collect::<Result<_, _>>()
+            .map_err(|e| NodeError::Store(e.to_string()))
+    }
+
+    /// The delivered batch with this id, if any.
+    #[cfg(feature = "store")]
+    pub fn get_batch(&self, batch_id: &str) -> Result<Option<StoredBatch<M>>, NodeError> {
+        self.store()?
+            .get_batch(batch_id)
+            .map_err(|e| NodeError::Store(e.to_string()))
+    }
+
+    /// Delivered batches created by `creator`, oldest first.
+    #[cfg(feature = "store")]
+    pub fn delivered_by(&self, creator: &PublicKey) -> Result<Vec<StoredBatch<M>>, NodeError> {
+        self.store()?
+            .by_creator(creator)
+            .collect::<Result<_, _>>()
+            .map_err(|e| NodeError::Store(e.to_string()))
+    }
+
+    /// `creator`'s delivered batches in sequence order from `from_sequence`,
+    /// up to the first number not delivered here.
+    #[cfg(feature = "store")]
+    pub fn creator_log(&self, creator: &PublicKey, from_sequence: u64) -> Result<Vec<StoredBatch<M>>, NodeError> {
+        self.store()?
+            .creator_log(creator, from_sequence)
+            .collect::<Result<_, _>>()
+            .map_err(|e| NodeError::Store(e.to_string()))
+    }
+
+    /// Creators caught signing conflicting batches, whose batches this node
+    /// no longer echoes.
+    pub fn equivocators(&self) -> Vec<PublicKey> {
+        self.inner.equivocation.penalized()
+    }
+
+    /// Echoes `creator`'s batches again after it was caught equivocating.
+    /// Returns whether it had been.
+    pub fn pardon(&self, creator: &PublicKey) -> bool {
+        self.inner.equivocation.pardon(creator)
+    }
+
+    /// Feeds batches delivered elsewhere, such as those written by `racer
+    /// export`, to this node's deliver stage as if its own rounds had
+    /// delivered them: they reach sinks, subscribers and the store. Batches
+    /// whose creator signature does not verify, whose messages do not match
+    /// their signed merkle root and size, whose creator the authorization
+    /// policy refuses or whose messages fail validation, including the
+    /// [`set_validator`](Self::set_validator) one, are rejected; those
+    /// already delivered here are skipped.
+    pub async fn import_batches(&self, batches: Vec<BatchedMessages<M>>) -> Result<ImportReport, NodeError> {
+        Self::import_into(&self.inner, batches).await
+    }
+
+    async fn import_into(
+        inner: &NodeInner<M>,
+        batches: Vec<BatchedMessages<M>>,
+    ) -> Result<ImportReport, NodeError> {
+        if !inner.config.node.role.delivers() {
+            return Err(NodeError::Protocol("a forwarder node does not deliver imported batches".into()));
+        }
+        let not_running = || NodeError::Protocol("node is not running".into());
+        let deliveries = inner.deliveries.read().await.clone().ok_or_else(not_running)?;
+
+        let mut report = ImportReport::default();
+        for batch in batches {
+            // The creator signs the merkle root and batch size, not the
+            // messages, so they must be checked against both.
+            let contents_match = if batch.membership.is_empty() {
+                batch.batch_size == batch.messages.len()
+                    && crate::protocol::merkle_root(&batch.messages) == batch.merkle_root
+            } else {
+                batch.messages.is_empty()
+            };
+            if !contents_match
+                || !batch.verify_creator_signature()
+                || !inner.authorization.permits(&batch)
+                || batch.messages.iter().any(|m| m.validate().is_err())
+                || inner.validator.check(&batch).await.is_err()
+            {
+                tracing::warn!(id = %inner.id, batch_id = %batch.batch_id, "rejected imported batch");
+                report.rejected += 1;
+                continue;
+            }
+            let hash = batch.compute_hash();
+            if Self::already_delivered(inner, &hash).await {
+                report.duplicates += 1;
+                continue;
+            }
+            inner.gossip_state.mark_delivered(&hash).await;
+            deliveries.send(Delivery { hash, batch }).await.map_err(|_| not_running())?;
+            report.imported += 1;
+        }
+        Ok(report)
+    }
+
+    /// Whether `hash` was delivered recently or, with a store, ever.
+    async fn already_delivered(inner: &NodeInner<M>, hash: &str) -> bool {
+        if inner.gossip_state.was_recently_delivered(hash).await {
+            return true;
+        }
+        #[cfg(feature = "store")]
+        if let Some(store) = &inner.store {
+            return matches!(store.get(hash), Ok(Some(_)));
+        }
+        false
+    }
+
+    /// The delivered batch store, opened from `node.store_path`.
+    #[cfg(feature = "store")]
+    pub fn store(&self) -> Result<&DeliveryStore<M>, NodeError> {
+        self.inner
+            .store
+            .as_deref()
+            .ok_or_else(|| NodeError::Config("node.store_path is not set".into()))
+    }
+
+    /// Inbound frames that failed to decode or verify, oldest first, up to
+    /// `node.quarantine_capacity`. See [`quarantine`](super::quarantine).
+    pub fn quarantined(&self) -> Vec<QuarantinedFrame> {
+        self.inner.quarantine.frames()
+    }
+
+    /// Empties the quarantine, returning how many frames it held.
+    pub fn clear_quarantine(&self) -> usize {
+        self.inner.quarantine.clear()
+    }
+
+    pub(super) async fn spawn_admin_server(&self, address: &str) -> Result<JoinHandle<()>, NodeError> {
+        let listener = TcpListener::bind(address)
+            .await
+            .map_err(|e| NodeError::Network(format!("admin bind {}: {}", address, e)))?;
+        let local = listener
+            .local_addr()
+            .map_err(|e| NodeError::Network(format!("admin bind {}: {}", address, e)))?;
+        if !local.ip().is_loopback() && !self.inner.config.node.admin_allow_remote {
+            return Err(NodeError::Config(format!(
+                "admin_bind {} is not a loopback address; set node.admin_allow_remote to serve it",
+                local
+            )));
+        }
+        let inner = Arc::clone(&self.inner);
+        tracing::info!(id = %inner.id, admin = %address, "admin API listening");
+
+        Ok(tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(Self::serve_admin_connection(Arc::clone(&inner), stream));
+                    }
+                    Err(e) => tracing::warn!(id = %inner.id, error = %e, "admin accept failed"),
+                }
+            }
+        }))
+    }
+
+    async fn serve_admin_connection(inner: Arc<NodeInner<M>>, stream: TcpStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            // One byte past the cap is enough to tell an oversized line.
+            match (&mut reader).take(MAX_REQUEST_BYTES + 1).read_line(&mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let oversized = line.len() as u64 > MAX_REQUEST_BYTES;
+            let response = if oversized {
+                AdminResponse::Error {
+                    message: format!("request exceeds {} bytes", MAX_REQUEST_BYTES),
+                }
+            } else {
+                match serde_json::from_str::<AdminRequest>(&line) {
+                    Ok(request) => Self::handle_admin_request(&inner, request).await,
+                    Err(e) => AdminResponse::Error {
+                        message: format!("invalid request: {}", e),
+                    },
+                }
+            };
+
+            let Ok(mut out) = serde_json::to_string(&response) else {
+                break;
+            };
+            out.push('\n');
+            // The rest of an oversized line is not read, so nothing after
+            // it can be framed.
+            if writer.write_all(out.as_bytes()).await.is_err() || oversized {
+                break;
+            }
+        }
+    }
+
+    async fn handle_admin_request(inner: &Arc<NodeInner<M>>, request: AdminRequest) -> AdminResponse {
+        match request {
+            AdminRequest::Status => AdminResponse::Status(Self::status_of(inner).await),
+            AdminRequest::Peers => AdminResponse::Peers {
+                peers: Self::peer_table_of(inner).await,
+            },
+            AdminRequest::Topology => AdminResponse::Topology(Self::topology_of(inner).await),
+            AdminRequest::Submit { message, wait } => Self::admin_submit(inner, message, wait)
+                .await
+                .unwrap_or_else(|e| AdminResponse::Error { message: e.to_string() }),
+            AdminRequest::Export { since } => {
+                Self::admin_export(inner, since).unwrap_or_else(|e| AdminResponse::Error { message: e.to_string() })
+            }
+            AdminRequest::Import { batches } => Self::admin_import(inner, batches)
+                .await
+                .unwrap_or_else(|e| AdminResponse::Error { message: e.to_string() }),
+            AdminRequest::Quarantine { clear } => AdminResponse::Quarantined {
+                frames: if clear { inner.quarantine.take() } else { inner.quarantine.frames() },
+            },
+        }
+    }
+
+    #[cfg(feature = "store")]
+    fn admin_export(inner: &NodeInner<M>, since: u64) -> Result<AdminResponse, NodeError> {
+        let store = inner
+            .store
+            .as_deref()
+            .ok_or_else(|| NodeError::Config("node.store_path is not set".into()))?;
+        let batches = store
+            .delivered_since(since)
+            .map(|stored| {
+                let stored = stored.map_err(|e| NodeError::Store(e.to_string()))?;
+                serde_json::to_value(stored).map_err(|e| NodeError::Serialization(e.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(AdminResponse::Exported { batches })
+    }
+
+    #[cfg(not(feature = "store"))]
+    fn admin_export(_inner: &NodeInner<M>, _since: u64) -> Result<AdminResponse, NodeError> {
+        Err(NodeError::Config("exporting needs the `store` feature".into()))
+    }
+
+    async fn admin_import(
+        inner: &NodeInner<M>,
+        batches: Vec<serde_json::Value>,
+    ) -> Result<AdminResponse, NodeError> {
+        let batches = batches
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>()
+            .map_err(|e| NodeError::Serialization(e.to_string()))?;
+        Self::import_into(inner, batches).await.map(AdminResponse::Imported)
+    }
+
+    async fn admin_submit(
+        inner: &Arc<NodeInner<M>>,
+        message: serde_json::Value,
+        wait: bool,
+    ) -> Result<AdminResponse, NodeError> {
+        if !inner.running.load(Ordering::SeqCst) {
+            return Err(NodeError::Protocol("node is not running".into()));
+        }
+
+        let message: M = serde_json::from_value(message).map_err(|e| NodeError::Serialization(e.to_string()))?;
+        message.validate().map_err(|e| NodeError::Protocol(e.to_string()))?;
+        Self::check_peers(inner).await?;
+
+        let bm = Self::prepare_batch(inner, None, Priority::Normal, message).await?;
+        let batch_id = bm.batch_id.clone();
+        let hash = bm.compute_hash();
+        inner.outbox.insert(&hash, &bm, racer_core::message::now_millis());
+        let done = inner.scheduler.enqueue(bm, hash);
+
+        if !wait {
+            return Ok(AdminResponse::Submitted {
+                batch_id,
+                delivered: None,
+            });
+        }
+
+        let delivered = Self::round_outcome(done).await?;
+        Ok(AdminResponse::Submitted {
+            batch_id,
+            delivered: Some(delivered),
+        })
+    }
+
+    async fn status_of(inner: &NodeInner<M>) -> NodeStatus {
+        let uptime_secs = inner
+            .started_at
+            .read()
+            .await
+            .map(|at| at.elapsed().as_secs_f64())
+            .unwrap_or(0.0);
+
+        NodeStatus {
+            id: inner.id.clone(),
+            public_key: inner.signer.public_key().to_hex(),
+            running: inner.running.load(Ordering::SeqCst),
+            uptime_secs,
+            active_rounds: inner.gossip_state.active_rounds().await,
+            known_peers: inner.peers.read().await.len(),
+            plato_latency_secs: inner.plato.read().await.current_latency(),
+            pipeline: inner.pipeline.snapshot(),
+            verify_cache: crate::crypto::verify_cache_stats(),
+        }
+    }
+
+    async fn peer_table_of(inner: &NodeInner<M>) -> Vec<PeerStatus> {
+        let now = Instant::now();
+        let mut peers: Vec<_> = inner
+            .peers
+            .read()
+            .await
+            .iter()
+            .map(|peer| PeerStatus {
+                id: peer.id.clone(),
+                router_address: peer.router_address.clone(),
+                publisher_address: peer.publisher_address.clone(),
+                latency_secs: peer.reported_latency,
+                last_seen_secs: peer.last_seen.map(|at| now.duration_since(at).as_secs_f64()),
+                pinned: peer.pinned,
+                relay: peer.relay.clone(),
+                connected_router: peer.connected_router.clone(),
+                clock_skew_ms: peer.clock_skew_ms(),
+            })
+            .collect();
+        peers.sort_by(|a, b| a.id.cmp(&b.id));
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_wire_format() {
+        assert_eq!(serde_json::to_string(&AdminRequest::Status).unwrap(), r#"{"cmd":"status"}"#);
+        let parsed: AdminRequest = serde_json::from_str(r#"{"cmd":"peers"}"#).unwrap();
+        assert!(matches!(parsed, AdminRequest::Peers));
+    }
+
+    #[test]
+    fn test_submit_wait_defaults_to_false() {
+        let parsed: AdminRequest = serde_json::from_str(r#"{"cmd":"submit","message":{"id":1}}"#).unwrap();
+        assert!(matches!(parsed, AdminRequest::Submit { wait: false, .. }));
+    }
+
+    #[test]
+    fn test_export_since_defaults_to_zero() {
+        let parsed: AdminRequest = serde_json::from_str(r#"{"cmd":"export"}"#).unwrap();
+        assert!(matches!(parsed, AdminRequest::Export { since: 0 }));
+
+        let response = AdminResponse::Imported(ImportReport {
+            imported: 2,
+            duplicates: 1,
+            rejected: 0,
+        });
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(json, r#"{"result":"imported","imported":2,"duplicates":1,"rejected":0}"#);
+    }
+
+    #[test]
+    fn test_quarantine_clear_defaults_to_false() {
+        let parsed: AdminRequest = serde_json::from_str(r#"{"cmd":"quarantine"}"#).unwrap();
+        assert!(matches!(parsed, AdminRequest::Quarantine { clear: false }));
+    }
+
+    #[test]
+    fn test_error_response_roundtrip() {
+        let response = AdminResponse::Error {
+            message: "nope".into(),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains(r#""result":"error""#));
+        assert!(matches!(
+            serde_json::from_str::<AdminResponse>(&json).unwrap(),
+            AdminResponse::Error { .. }
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+//! Which keys may create batches.
+//!
+//! Signatures prove who created a batch, not that they were allowed to. An
+//! [`AuthorizationPolicy`] decides the latter per message: a node echoes a
+//! received batch only if the policy lets its creator create every message
+//! in it, so batches from unknown keys never gather a quorum. Set
+//! `consensus.allowed_creators` for an [`Allowlist`] or [`KeyPrefixes`], or
+//! call [`Node::set_authorization_policy`](super::Node::set_authorization_policy)
+//! for anything else. With neither, any key may create batches.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use crate::crypto::PublicKey;
+use crate::protocol::BatchedMessages;
+
+/// Decides whether `creator` may create `msg`.
+pub trait AuthorizationPolicy<M>: Send + Sync {
+    fn may_create(&self, creator: &PublicKey, msg: &M) -> bool;
+}
+
+impl<M, F> AuthorizationPolicy<M> for F
+where
+    F: Fn(&PublicKey, &M) -> bool + Send + Sync,
+{
+    fn may_create(&self, creator: &PublicKey, msg: &M) -> bool {
+        self(creator, msg)
+    }
+}
+
+/// Lets exactly the listed keys create messages.
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    keys: HashSet<PublicKey>,
+}
+
+impl Allowlist {
+    pub fn new(keys: impl IntoIterator<Item = PublicKey>) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+        }
+    }
+}
+
+impl<M> AuthorizationPolicy<M> for Allowlist {
+    fn may_create(&self, creator: &PublicKey, _msg: &M) -> bool {
+        self.keys.contains(creator)
+    }
+}
+
+/// Lets keys whose hex encoding starts with one of the prefixes create
+/// messages, such as every key id a provisioning batch was issued with.
+#[derive(Debug, Clone, Default)]
+pub struct KeyPrefixes {
+    prefixes: Vec<String>,
+}
+
+impl KeyPrefixes {
+    pub fn new(prefixes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            prefixes: prefixes.into_iter().map(|p| p.into().to_ascii_lowercase()).collect(),
+        }
+    }
+}
+
+impl<M> AuthorizationPolicy<M> for KeyPrefixes {
+    fn may_create(&self, creator: &PublicKey, _msg: &M) -> bool {
+        let hex = creator.to_hex();
+        self.prefixes.iter().any(|prefix| hex.starts_with(prefix.as_str()))
+    }
+}
+
+/// The policy `consensus.allowed_creators` describes: hex public keys, and
+/// hex prefixes ending in `*`. `None` if the list is empty.
+pub(crate) fn from_allowed_creators<M>(entries: &[String]) -> Option<Arc<dyn AuthorizationPolicy<M>>> {
+    if entries.is_empty() {
+        return None;
+    }
+    if entries.iter().any(|entry| entry.ends_with('*')) {
+        // A full key is a prefix matching only itself.
+        let prefixes = entries.iter().map(|entry| entry.trim_end_matches('*'));
+        return Some(Arc::new(KeyPrefixes::new(prefixes)));
+    }
+    // Entries were checked when the config was validated.
+    let keys = entries.iter().filter_map(|entry| PublicKey::from_hex(entry).ok());
+    Some(Arc::new(Allowlist::new(keys)))
+}
+
+/// The policy a node enforces, if any.
+pub(crate) struct AuthorizationSlot<M> {
+    policy: RwLock<Option<Arc<dyn AuthorizationPolicy<M>>>>,
+}
+
+impl<M> AuthorizationSlot<M> {
+    pub(crate) fn new(policy: Option<Arc<dyn AuthorizationPolicy<M>>>) -> Self {
+        Self {
+            policy: RwLock::new(policy),
+        }
+    }
+
+    pub(crate) fn set(&self, policy: Arc<dyn AuthorizationPolicy<M>>) {
+        *self.policy.write().unwrap_or_else(|e| e.into_inner()) = Some(policy);
+    }
+
+    /// Whether the batch's creator may create every message in it. Batches
+    /// without messages carry membership updates, which are signed by the
+    /// node they concern and always pass.
+    pub(crate) fn permits(&self, batch: &BatchedMessages<M>) -> bool {
+        match &*self.policy.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(policy) => batch.messages.iter().all(|msg| policy.may_create(&batch.creator_ecdsa, msg)),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use racer_core::message::DefaultMessage;
+
+    fn allowed(policy: &Arc<dyn AuthorizationPolicy<DefaultMessage>>, key: &PublicKey) -> bool {
+        policy.may_create(key, &DefaultMessage::new())
+    }
+
+    #[test]
+    fn test_allowlist_from_config() {
+        let (a, b) = (KeyPair::generate().public_key(), KeyPair::generate().public_key());
+        let policy = from_allowed_creators(&[a.to_hex()]).unwrap();
+        assert!(allowed(&policy, &a));
+        assert!(!allowed(&policy, &b));
+        assert!(from_allowed_creators::<DefaultMessage>(&[]).is_none());
+    }
+
+    #[test]
+    fn test_prefixes_from_config() {
+        let (a, b) = (KeyPair::generate().public_key(), KeyPair::generate().public_key());
+        let prefix = format!("{}*", &a.to_hex()[..10].to_ascii_uppercase());
+        let policy = from_allowed_creators(&[prefix, b.to_hex()]).unwrap();
+        assert!(allowed(&policy, &a));
+        assert!(allowed(&policy, &b));
+        assert!(!allowed(&policy, &KeyPair::generate().public_key()));
+    }
+}
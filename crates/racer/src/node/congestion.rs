@@ -0,0 +1,256 @@
+//! Congestion control.
+//!
+//! Each node broadcasts a signed health summary every
+//! `consensus.health_interval_secs`, and on every PLATO update sends its
+//! latency estimate to the dealers that contacted its router. The saved
+//! PLATO state lets a restarted node resume its estimate.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+
+use super::events::NodeEvent;
+use super::topology;
+use super::{Node, NodeError, NodeInner};
+use crate::plato::{PlatoController, PlatoState, RoundTimings};
+use crate::protocol::{ClusterHealthStats, CongestionUpdate, HealthSummary, Priority, ProtocolMessage};
+use crate::Message;
+
+/// How long a dealer that stopped sending to our router still gets
+/// congestion updates.
+pub(super) const DEALER_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+/// Dealers sent congestion updates at once; past this, the one idle longest
+/// is dropped for each new one.
+pub(super) const MAX_DEALER_IDENTITIES: usize = 1024;
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    pub(super) async fn touch_dealer(inner: &NodeInner<M>, identity: &[u8]) {
+        let mut dealers = inner.dealer_identities.write().await;
+        if dealers.len() >= MAX_DEALER_IDENTITIES && !dealers.contains_key(identity) {
+            let idlest = dealers
+                .iter()
+                .min_by_key(|(_, seen)| **seen)
+                .map(|(identity, _)| identity.clone());
+            if let Some(idlest) = idlest {
+                dealers.remove(&idlest);
+            }
+        }
+        dealers.insert(identity.to_vec(), Instant::now());
+    }
+
+    pub(super) fn spawn_health_exchange(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        let interval = Duration::from_secs(inner.config.consensus.health_interval_secs);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+
+            while inner.running.load(Ordering::SeqCst) {
+                ticker.tick().await;
+                if let Err(e) = Self::broadcast_health_summary(&inner).await {
+                    tracing::warn!(id = %inner.id, error = %e, "health summary broadcast failed");
+                }
+            }
+        })
+    }
+
+    async fn broadcast_health_summary(inner: &NodeInner<M>) -> Result<(), NodeError> {
+        let mut summary = inner
+            .health_tracker
+            .write()
+            .await
+            .summary(inner.signer.public_key());
+        summary.neighbors = topology::neighbors(&*inner.peers.read().await);
+        summary.signature = Some(Self::sign(inner, &summary.signing_bytes()).await);
+
+        let msg = Self::encode(&ProtocolMessage::<M>::HealthSummary(summary))?;
+
+        let peer_ids = inner.peers.read().await.peer_ids();
+        for peer_id in peer_ids {
+            Self::send_best_effort(inner, &peer_id, msg.clone(), Priority::Normal).await;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn spawn_congestion_fanout(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        let interval = Duration::from_secs_f64(inner.config.plato.update_interval_secs);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+
+            while inner.running.load(Ordering::SeqCst) {
+                ticker.tick().await;
+                Self::plato_tick(&inner).await;
+                Self::save_plato(&inner).await;
+                if let Err(e) = Self::broadcast_congestion_update(&inner).await {
+                    tracing::warn!(id = %inner.id, error = %e, "congestion update fan-out failed");
+                }
+            }
+        })
+    }
+
+    /// Sends our current PLATO latency to every dealer that has contacted our router.
+    pub(super) async fn broadcast_congestion_update(inner: &NodeInner<M>) -> Result<(), NodeError> {
+        let update = {
+            let plato = inner.plato.read().await;
+            CongestionUpdate::new(plato.current_latency(), plato.recently_missed_delivery())
+        };
+        let msg = Self::encode(&update)?;
+
+        let identities: Vec<Vec<u8>> = {
+            let mut dealers = inner.dealer_identities.write().await;
+            dealers.retain(|_, seen| seen.elapsed() < DEALER_IDLE_TIMEOUT);
+            dealers.keys().cloned().collect()
+        };
+        for identity in identities {
+            if let Err(e) = inner.network.send_router_reply(identity.clone(), msg.clone()).await {
+                tracing::debug!(id = %inner.id, error = %e, "dropping unreachable dealer");
+                inner.dealer_identities.write().await.remove(&identity);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) async fn inbox_health_summary(inner: &NodeInner<M>, summary: HealthSummary) -> CongestionUpdate {
+        let sender = summary.sender_id();
+        tracing::debug!(
+            id = %inner.id,
+            from = %sender,
+            delivery_rate = summary.delivery_rate(),
+            "received HealthSummary"
+        );
+
+        // Only peers we verified count towards the fanout; anyone else could
+        // mint keys to skew it.
+        if inner.peers.read().await.find_by_key_id(&sender).is_none() {
+            tracing::debug!(id = %inner.id, from = %sender, "ignored HealthSummary from an unregistered peer");
+            return CongestionUpdate::ok();
+        }
+        let sender_now = Self::peer_now(inner, &sender).await;
+        if !inner.cluster_health.write().await.record(summary, sender_now) {
+            tracing::debug!(id = %inner.id, from = %sender, "ignored stale HealthSummary");
+        }
+        CongestionUpdate::ok()
+    }
+
+    pub async fn cluster_health(&self) -> ClusterHealthStats {
+        self.inner.cluster_health.read().await.stats()
+    }
+
+    pub async fn run_plato_check(&self) {
+        Self::plato_tick(&self.inner).await;
+    }
+
+    async fn plato_tick(inner: &NodeInner<M>) {
+        let queue_depth = inner.network.send_queue_fill().await;
+        let mut plato = inner.plato.write().await;
+        plato.record_queue_depth(queue_depth);
+        plato.check_increasing_congestion();
+        plato.check_decreasing_congestion();
+        plato.record_sample();
+
+        if plato.timing_changed {
+            plato.clear_timing_changed();
+            let timings = plato.round_timings();
+            let _ = inner.events.send(NodeEvent::CongestionChanged {
+                current_latency: plato.current_latency(),
+                publish_frequency: plato.publish_frequency(),
+            });
+            drop(plato);
+            inner.gossip_state.set_timeout(timings.round_timeout()).await;
+            tracing::debug!(
+                id = %inner.id,
+                echo_timeout = timings.echo_timeout.as_secs_f64(),
+                ready_timeout = timings.ready_timeout.as_secs_f64(),
+                "PLATO: round timings updated"
+            );
+        }
+    }
+
+    /// Round timeouts and pacing from the current PLATO estimate.
+    pub async fn round_timings(&self) -> RoundTimings {
+        self.inner.plato.read().await.round_timings()
+    }
+
+    pub async fn plato_stats(&self) -> crate::plato::PlatoStats {
+        self.inner.plato.read().await.stats()
+    }
+
+    pub async fn plato_history(&self, window: Duration) -> Vec<crate::plato::PlatoSample> {
+        self.inner.plato.read().await.history(window)
+    }
+
+    /// PLATO's estimate and the samples behind it, as saved to
+    /// `node.plato_state_path`.
+    pub async fn plato_state(&self) -> PlatoState {
+        self.inner.plato.read().await.save_state()
+    }
+
+    /// Resumes PLATO from `state` unless it is older than
+    /// `plato.state_max_age_secs`. Returns whether it was used.
+    pub async fn restore_plato_state(&self, state: PlatoState) -> bool {
+        let restored = self
+            .inner
+            .plato
+            .write()
+            .await
+            .load_state(state, self.inner.config.plato.state_max_age());
+        if restored {
+            Self::plato_tick(&self.inner).await;
+        }
+        restored
+    }
+
+    /// Resumes `plato` from the estimate saved at `path`, if there is a
+    /// fresh one.
+    pub(super) fn restore_plato(plato: &mut PlatoController, path: &std::path::Path, max_age: Duration) {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "failed to read saved PLATO state");
+                return;
+            }
+        };
+        match serde_json::from_slice::<PlatoState>(&bytes) {
+            Ok(state) if plato.load_state(state, max_age) => {
+                tracing::info!(
+                    path = %path.display(),
+                    current_latency = plato.current_latency(),
+                    publish_frequency = plato.publish_frequency(),
+                    "resumed saved PLATO state"
+                );
+            }
+            Ok(_) => tracing::info!(path = %path.display(), "discarded stale PLATO state"),
+            Err(e) => tracing::warn!(path = %path.display(), error = %e, "ignoring unreadable PLATO state"),
+        }
+    }
+
+    /// Writes PLATO's estimate to `node.plato_state_path`, if set, through
+    /// a temporary file so a crash never leaves half of one.
+    pub(super) async fn save_plato(inner: &NodeInner<M>) {
+        let Some(path) = &inner.config.node.plato_state_path else {
+            return;
+        };
+        let state = inner.plato.read().await.save_state();
+        let result = serde_json::to_vec(&state).map_err(std::io::Error::other).and_then(|bytes| {
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, bytes)?;
+            std::fs::rename(&tmp, path)
+        });
+        if let Err(e) = result {
+            tracing::warn!(id = %inner.id, path = %path.display(), error = %e, "failed to save PLATO state");
+        }
+    }
+}
@@ -0,0 +1,448 @@
+//! The SPDE round logic as a sans-io state machine.
+//!
+//! [`ConsensusCore`] turns what happened to a round — a batch or response
+//! arriving, a phase timing out, the maintenance tick — into [`Command`]s:
+//! what to publish, whom to send what, what to deliver or forget. It touches
+//! no socket, lock or clock beyond the round timestamps, so the rules can be
+//! tested event by event and ported without the node around them.
+//!
+//! The core keeps no rounds of its own. Each call borrows the [`RoundTable`]
+//! the round lives in, so `Node` can keep rounds in its sharded locks while
+//! a test drives a plain table. `Node` is the I/O driver: it takes the
+//! shard's lock, hands the event to the core, releases the lock and then
+//! carries out the commands in order.
+
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use super::events::RoundPhase;
+use crate::config::Thresholds;
+use crate::network::PeerInfo;
+use crate::protocol::{BatchedMessages, GossipRound, Priority, ProtocolResponseType, RoundStep, RoundTable};
+
+/// Something that happened to round `hash`.
+#[derive(Debug, Clone)]
+pub enum Event<M> {
+    /// This node starts gossiping `batch`, its own or one it relays, to the
+    /// sampled peers.
+    GossipStarted {
+        hash: String,
+        batch: BatchedMessages<M>,
+        thresholds: Thresholds,
+        echo_peers: Vec<PeerInfo>,
+        ready_peers: Vec<PeerInfo>,
+        /// Observers sampled apart: they are sent the batch alone, since
+        /// they never answer a subscription.
+        observers: Vec<PeerInfo>,
+    },
+    /// A peer sent `batch`, already verified and deduplicated.
+    BatchReceived {
+        hash: String,
+        batch: BatchedMessages<M>,
+        thresholds: Thresholds,
+        now_ms: u64,
+    },
+    EchoSubscribeReceived { hash: String },
+    ReadySubscribeReceived { hash: String },
+    /// `from` is the responder's key id.
+    EchoResponseReceived { hash: String, from: String },
+    ReadyResponseReceived { hash: String, from: String },
+    /// The driver stopped waiting for `phase` of a round it gossiped.
+    PhaseTimedOut { hash: String, phase: RoundPhase },
+    /// Periodic maintenance: drop rounds past the gossip timeout.
+    Tick,
+}
+
+/// A request sent to one peer.
+#[derive(Debug, Clone)]
+pub enum Request<M> {
+    EchoSubscribe,
+    ReadySubscribe,
+    Batch(BatchedMessages<M>),
+}
+
+/// Something the driver must do, in the order returned.
+#[derive(Debug, Clone)]
+pub enum Command<M> {
+    /// Publish this node's signed response for round `hash`.
+    Publish { hash: String, response: ProtocolResponseType },
+    SendToPeer {
+        peer_id: String,
+        hash: String,
+        request: Request<M>,
+        priority: Priority,
+    },
+    /// Re-gossip `batch`, received from a peer, as its next relay.
+    Relay { batch: BatchedMessages<M> },
+    /// Round `hash` reached its delivery threshold.
+    Deliver { hash: String },
+    /// Round `hash` is gone: release its topics and drop its batch, or,
+    /// when `timed_out` because it was dropped undelivered, retire the batch
+    /// for its retention.
+    Forget { hash: String, timed_out: bool },
+    /// Announce that round `hash` gave up in `phase`.
+    TimedOut { hash: String, phase: RoundPhase },
+    /// The first EchoResponse from `from` took `rtt` after our Echo.
+    EchoRtt { from: String, rtt: Duration },
+    /// A received batch outlived its TTL and was ignored.
+    Expired { hash: String },
+    /// A received batch used up its hops and is not relayed.
+    HopLimited { hash: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsensusCore<M> {
+    /// Thresholds of rounds started without their own.
+    default_thresholds: Thresholds,
+    _batch: PhantomData<fn() -> M>,
+}
+
+impl<M: Clone> ConsensusCore<M> {
+    pub fn new(default_thresholds: Thresholds) -> Self {
+        Self {
+            default_thresholds,
+            _batch: PhantomData,
+        }
+    }
+
+    /// The thresholds `round` started with, or the default ones.
+    pub fn thresholds_of(&self, round: &GossipRound) -> Thresholds {
+        round.thresholds.unwrap_or(self.default_thresholds)
+    }
+
+    pub fn handle(&self, rounds: &mut RoundTable, event: Event<M>) -> Vec<Command<M>> {
+        match event {
+            Event::GossipStarted {
+                hash,
+                batch,
+                thresholds,
+                echo_peers,
+                ready_peers,
+                observers,
+            } => self.start_gossip(rounds, hash, batch, thresholds, &echo_peers, &ready_peers, &observers),
+            Event::BatchReceived {
+                hash,
+                batch,
+                thresholds,
+                now_ms,
+            } => {
+                if batch.is_expired(now_ms) {
+                    return vec![Command::Expired { hash }];
+                }
+                rounds.start_round_with(hash.as_str(), thresholds);
+                let relay = if batch.hop_limit_reached() {
+                    Command::HopLimited { hash: hash.clone() }
+                } else {
+                    Command::Relay { batch }
+                };
+                vec![
+                    Command::Publish {
+                        hash,
+                        response: ProtocolResponseType::EchoResponse,
+                    },
+                    relay,
+                ]
+            }
+            Event::EchoSubscribeReceived { hash } => {
+                if rounds.get_round(&hash).is_none() {
+                    return Vec::new();
+                }
+                vec![Command::Publish {
+                    hash,
+                    response: ProtocolResponseType::EchoResponse,
+                }]
+            }
+            Event::ReadySubscribeReceived { hash } => {
+                let ready = rounds
+                    .get_round(&hash)
+                    .is_some_and(|round| round.is_ready(&self.thresholds_of(round)));
+                if !ready {
+                    return Vec::new();
+                }
+                vec![Command::Publish {
+                    hash,
+                    response: ProtocolResponseType::ReadyResponse,
+                }]
+            }
+            Event::EchoResponseReceived { hash, from } => {
+                let Some(round) = rounds.get_round_mut(&hash) else {
+                    return Vec::new();
+                };
+                let rtt = round.record_echo(&from);
+                let step = round.advance(&self.thresholds_of(round));
+
+                let mut commands: Vec<_> = rtt.map(|rtt| Command::EchoRtt { from, rtt }).into_iter().collect();
+                commands.extend(Self::apply(rounds, hash, step));
+                commands
+            }
+            Event::ReadyResponseReceived { hash, from } => {
+                let Some(round) = rounds.get_round_mut(&hash) else {
+                    tracing::warn!(topic = %hash, "ReadyResponse for UNKNOWN round");
+                    return Vec::new();
+                };
+                round.record_ready(&from);
+                let step = round.advance(&self.thresholds_of(round));
+                Self::apply(rounds, hash, step)
+            }
+            Event::PhaseTimedOut { hash, phase } => {
+                if let Some(round) = rounds.get_round_mut(&hash) {
+                    round.timeout_reported = true;
+                }
+                vec![Command::TimedOut { hash, phase }]
+            }
+            Event::Tick => {
+                let mut commands = Vec::new();
+                for round in rounds.remove_timed_out() {
+                    let announce = !round.timeout_reported;
+                    let phase = if round.echo_complete { RoundPhase::Ready } else { RoundPhase::Echo };
+                    commands.push(Command::Forget {
+                        hash: round.hash.clone(),
+                        timed_out: true,
+                    });
+                    if announce {
+                        commands.push(Command::TimedOut { hash: round.hash, phase });
+                    }
+                }
+                commands
+            }
+        }
+    }
+
+    fn start_gossip(
+        &self,
+        rounds: &mut RoundTable,
+        hash: String,
+        batch: BatchedMessages<M>,
+        thresholds: Thresholds,
+        echo_peers: &[PeerInfo],
+        ready_peers: &[PeerInfo],
+        observers: &[PeerInfo],
+    ) -> Vec<Command<M>> {
+        let round = rounds.start_round_with(hash.as_str(), thresholds);
+        let thresholds = self.thresholds_of(round);
+        round.echo_waiting.extend(echo_peers.iter().map(|peer| peer.id.clone()));
+        round.ready_waiting.extend(ready_peers.iter().map(|peer| peer.id.clone()));
+
+        let priority = batch.priority;
+        let send = |peer: &PeerInfo, request: Request<M>| Command::SendToPeer {
+            peer_id: peer.id.clone(),
+            hash: hash.clone(),
+            request,
+            priority,
+        };
+
+        let mut commands = Vec::with_capacity(2 * echo_peers.len() + ready_peers.len() + observers.len());
+        for peer in echo_peers {
+            round.record_echo_sent(peer.key_id());
+            commands.push(send(peer, Request::EchoSubscribe));
+        }
+        for peer in ready_peers {
+            commands.push(send(peer, Request::ReadySubscribe));
+        }
+        // Peers already vouching Ready have the batch; no need to send it.
+        if round.ready_received.len() < thresholds.feedback {
+            for peer in echo_peers {
+                commands.push(send(peer, Request::Batch(batch.clone())));
+            }
+        }
+        for peer in observers {
+            commands.push(send(peer, Request::Batch(batch.clone())));
+        }
+        commands
+    }
+
+    /// The commands a round's step calls for.
+    fn apply(rounds: &mut RoundTable, hash: String, step: RoundStep) -> Vec<Command<M>> {
+        let mut commands = Vec::new();
+        if step.publish_ready {
+            commands.push(Command::Publish {
+                hash: hash.clone(),
+                response: ProtocolResponseType::ReadyResponse,
+            });
+        }
+        if step.deliver {
+            for evicted in rounds.mark_delivered(&hash) {
+                commands.push(Command::Forget {
+                    hash: evicted,
+                    timed_out: false,
+                });
+            }
+            commands.push(Command::Deliver { hash });
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::VectorClock;
+    use racer_core::message::DefaultMessage;
+
+    const THRESHOLDS: Thresholds = Thresholds {
+        ready: 1,
+        feedback: 2,
+        delivery: 2,
+    };
+
+    fn batch() -> BatchedMessages<DefaultMessage> {
+        let key = KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: "b1".into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: Some(1),
+            ttl_ms: Some(500),
+            priority: Priority::Normal,
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    fn peer(n: u8) -> PeerInfo {
+        PeerInfo::new(format!("p{}", n), KeyPair::generate().public_key(), "tcp://r", "tcp://p")
+    }
+
+    #[test]
+    fn test_received_batch_is_echoed_and_relayed() {
+        let core = ConsensusCore::new(THRESHOLDS);
+        let mut rounds = RoundTable::new();
+
+        let expired = core.handle(&mut rounds, Event::BatchReceived {
+            hash: "h".into(),
+            batch: batch(),
+            thresholds: THRESHOLDS,
+            now_ms: 2_000,
+        });
+        assert!(matches!(expired[..], [Command::Expired { .. }]));
+        assert!(rounds.get_round("h").is_none());
+
+        let commands = core.handle(&mut rounds, Event::BatchReceived {
+            hash: "h".into(),
+            batch: batch(),
+            thresholds: THRESHOLDS,
+            now_ms: 1_200,
+        });
+        assert!(matches!(
+            commands[..],
+            [Command::Publish { response: ProtocolResponseType::EchoResponse, .. }, Command::Relay { .. }]
+        ));
+
+        let mut relayed = batch();
+        relayed.hops = 1;
+        let commands = core.handle(&mut rounds, Event::BatchReceived {
+            hash: "h2".into(),
+            batch: relayed,
+            thresholds: THRESHOLDS,
+            now_ms: 1_200,
+        });
+        assert!(matches!(commands[1], Command::HopLimited { .. }));
+    }
+
+    #[test]
+    fn test_gossip_round_runs_to_delivery() {
+        let core = ConsensusCore::new(THRESHOLDS);
+        let mut rounds = RoundTable::new();
+        let peers = vec![peer(1), peer(2)];
+
+        let commands = core.handle(&mut rounds, Event::GossipStarted {
+            hash: "h".into(),
+            batch: batch(),
+            thresholds: THRESHOLDS,
+            echo_peers: peers.clone(),
+            ready_peers: peers.clone(),
+            observers: Vec::new(),
+        });
+        let batches = commands
+            .iter()
+            .filter(|c| matches!(c, Command::SendToPeer { request: Request::Batch(_), .. }))
+            .count();
+        assert_eq!((commands.len(), batches), (6, 2));
+
+        let commands = core.handle(&mut rounds, Event::EchoResponseReceived {
+            hash: "h".into(),
+            from: peers[0].key_id(),
+        });
+        assert!(matches!(
+            commands[..],
+            [Command::EchoRtt { .. }, Command::Publish { response: ProtocolResponseType::ReadyResponse, .. }]
+        ));
+
+        let ready = |from: &PeerInfo| Event::ReadyResponseReceived {
+            hash: "h".into(),
+            from: from.key_id(),
+        };
+        assert!(core.handle(&mut rounds, ready(&peers[0])).is_empty());
+        assert!(matches!(core.handle(&mut rounds, ready(&peers[1]))[..], [Command::Deliver { .. }]));
+        assert!(core.handle(&mut rounds, ready(&peers[1])).is_empty());
+        assert!(rounds.is_delivered("h"));
+    }
+
+    #[test]
+    fn test_observers_are_sent_the_batch_alone() {
+        let core = ConsensusCore::new(THRESHOLDS);
+        let mut rounds = RoundTable::new();
+        let observer = peer(3);
+
+        let commands = core.handle(&mut rounds, Event::GossipStarted {
+            hash: "h".into(),
+            batch: batch(),
+            thresholds: THRESHOLDS,
+            echo_peers: vec![peer(1)],
+            ready_peers: vec![peer(1)],
+            observers: vec![observer.clone()],
+        });
+        let to_observer: Vec<_> = commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::SendToPeer { peer_id, request, .. } if *peer_id == observer.id => Some(request),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(to_observer[..], [Request::Batch(_)]));
+
+        let round = rounds.get_round("h").unwrap();
+        assert!(!round.echo_waiting.contains(&observer.id));
+        assert!(!round.ready_waiting.contains(&observer.id));
+    }
+
+    #[test]
+    fn test_tick_announces_unreported_timeouts() {
+        let core = ConsensusCore::<DefaultMessage>::new(THRESHOLDS);
+        let mut rounds = RoundTable::new();
+        rounds.set_timeout(Duration::ZERO);
+        rounds.start_round("quiet");
+        rounds.start_round("reported");
+        core.handle(&mut rounds, Event::PhaseTimedOut {
+            hash: "reported".into(),
+            phase: RoundPhase::Echo,
+        });
+        std::thread::sleep(Duration::from_millis(5));
+
+        let commands = core.handle(&mut rounds, Event::Tick);
+        let forgotten = commands.iter().filter(|c| matches!(c, Command::Forget { timed_out: true, .. })).count();
+        let announced: Vec<_> = commands
+            .iter()
+            .filter_map(|c| match c {
+                Command::TimedOut { hash, phase } => Some((hash.as_str(), *phase)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(forgotten, 2);
+        assert_eq!(announced, vec![("quiet", RoundPhase::Echo)]);
+    }
+}
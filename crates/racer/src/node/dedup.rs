@@ -0,0 +1,219 @@
+//! Application-level deduplication at the delivery boundary.
+//!
+//! Gossip dedups by batch hash, so an application that retries a submission
+//! (with a fresh timestamp, or through another node) gets a second batch and
+//! a second delivery of the same logical message. With
+//! `consensus.dedup_window_secs` set, the deliver stage drops messages whose
+//! [`DedupPolicy`] key it already delivered within the window. A batch keeps
+//! its other messages; one left with none is not delivered at all.
+//!
+//! The window runs on each node's own clock, so nodes may drop different
+//! messages near its edges. Only what the application is handed is filtered:
+//! sinks, `Node::subscribe` receivers and WebSocket observers. The delivered
+//! batch store and the total order of `Node::subscribe_ordered` keep every
+//! batch as signed and agreed on.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::protocol::BatchedMessages;
+use crate::Message;
+
+/// Names the logical message a delivered message carries.
+pub trait DedupPolicy<M>: Send + Sync {
+    /// Messages with equal keys are duplicates. `None` never is.
+    fn key(&self, batch: &BatchedMessages<M>, message: &M) -> Option<String>;
+}
+
+/// Keys messages by [`Message::id`] alone, so a retry is caught whichever
+/// node it was submitted through. The default policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByMessageId;
+
+impl<M: Message> DedupPolicy<M> for ByMessageId {
+    fn key(&self, _batch: &BatchedMessages<M>, message: &M) -> Option<String> {
+        Some(message.id().to_string())
+    }
+}
+
+/// Keys messages by creator and [`Message::id`], for applications whose ids
+/// are only unique per submitting node.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByCreatorAndId;
+
+impl<M: Message> DedupPolicy<M> for ByCreatorAndId {
+    fn key(&self, batch: &BatchedMessages<M>, message: &M) -> Option<String> {
+        Some(format!("{}:{}", batch.creator_ecdsa.to_hex(), message.id()))
+    }
+}
+
+impl<M, F> DedupPolicy<M> for F
+where
+    F: Fn(&BatchedMessages<M>, &M) -> Option<String> + Send + Sync,
+{
+    fn key(&self, batch: &BatchedMessages<M>, message: &M) -> Option<String> {
+        self(batch, message)
+    }
+}
+
+struct Window {
+    seen: HashMap<String, Instant>,
+    /// Keys in the order they were first delivered, for expiry.
+    order: VecDeque<(Instant, String)>,
+}
+
+/// Keys delivered within the window, shared by the node and its deliver stage.
+pub(crate) struct MessageDedup<M> {
+    window: Duration,
+    policy: RwLock<Arc<dyn DedupPolicy<M>>>,
+    state: Mutex<Window>,
+}
+
+impl<M: Message> MessageDedup<M> {
+    /// Dedup over `window`; a zero window lets everything through.
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            policy: RwLock::new(Arc::new(ByMessageId)),
+            state: Mutex::new(Window {
+                seen: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn set_policy(&self, policy: Arc<dyn DedupPolicy<M>>) {
+        *self.policy.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    /// `batch` without the messages delivered within the window, or `None`
+    /// if it had messages and all were. Records the keys of the rest.
+    pub(crate) fn filter(&self, mut batch: BatchedMessages<M>) -> Option<BatchedMessages<M>> {
+        if self.window.is_zero() || batch.messages.is_empty() {
+            return Some(batch);
+        }
+        let policy = Arc::clone(&self.policy.read().unwrap_or_else(|e| e.into_inner()));
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.expire(now, self.window);
+
+        let messages = std::mem::take(&mut batch.messages);
+        let mut kept = Vec::with_capacity(messages.len());
+        for message in messages {
+            match policy.key(&batch, &message) {
+                Some(key) if state.seen.contains_key(&key) => {
+                    tracing::debug!(batch_id = %batch.batch_id, key = %key, "dropped duplicate message");
+                }
+                Some(key) => {
+                    state.seen.insert(key.clone(), now);
+                    state.order.push_back((now, key));
+                    kept.push(message);
+                }
+                None => kept.push(message),
+            }
+        }
+        if kept.is_empty() {
+            return None;
+        }
+        batch.messages = kept;
+        Some(batch)
+    }
+}
+
+impl Window {
+    fn expire(&mut self, now: Instant, window: Duration) {
+        while let Some((at, _)) = self.order.front() {
+            if now.duration_since(*at) < window {
+                break;
+            }
+            if let Some((_, key)) = self.order.pop_front() {
+                self.seen.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::{Priority, VectorClock};
+    use racer_core::message::DefaultMessage;
+
+    fn batch(keys: &KeyPair, timestamps: &[u64]) -> BatchedMessages<DefaultMessage> {
+        BatchedMessages {
+            batch_id: "b".into(),
+            creator_ecdsa: keys.public_key(),
+            sender_ecdsa: keys.public_key(),
+            merkle_root: "root".into(),
+            batch_size: timestamps.len(),
+            messages: timestamps
+                .iter()
+                .map(|&timestamp| DefaultMessage { timestamp, padding: 0 })
+                .collect(),
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Priority::Normal,
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    fn ids(batch: &BatchedMessages<DefaultMessage>) -> Vec<u64> {
+        batch.messages.iter().map(|m| m.timestamp).collect()
+    }
+
+    #[test]
+    fn test_duplicates_are_dropped_within_window() {
+        let dedup = MessageDedup::new(Duration::from_secs(60));
+        let (a, b) = (KeyPair::generate(), KeyPair::generate());
+
+        assert_eq!(ids(&dedup.filter(batch(&a, &[1, 2])).unwrap()), vec![1, 2]);
+        assert_eq!(ids(&dedup.filter(batch(&b, &[2, 3])).unwrap()), vec![3]);
+        assert!(dedup.filter(batch(&a, &[1])).is_none());
+        assert!(dedup.filter(batch(&a, &[])).is_some(), "empty batches carry membership updates");
+
+        dedup.set_policy(Arc::new(ByCreatorAndId));
+        assert_eq!(ids(&dedup.filter(batch(&b, &[1])).unwrap()), vec![1]);
+    }
+
+    #[test]
+    fn test_keys_expire_and_zero_window_disables() {
+        let dedup = MessageDedup::new(Duration::from_millis(10));
+        let keys = KeyPair::generate();
+        assert!(dedup.filter(batch(&keys, &[1])).is_some());
+        assert!(dedup.filter(batch(&keys, &[1])).is_none());
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(dedup.filter(batch(&keys, &[1])).is_some());
+
+        let off = MessageDedup::new(Duration::ZERO);
+        assert!(off.filter(batch(&keys, &[1])).is_some());
+        assert!(off.filter(batch(&keys, &[1])).is_some());
+    }
+
+    #[test]
+    fn test_closure_policy_can_opt_out() {
+        let dedup = MessageDedup::new(Duration::from_secs(60));
+        let policy = |_: &BatchedMessages<DefaultMessage>, m: &DefaultMessage| {
+            (m.padding == 0).then(|| m.timestamp.to_string())
+        };
+        dedup.set_policy(Arc::new(policy));
+
+        let keys = KeyPair::generate();
+        let mut unkeyed = batch(&keys, &[7]);
+        unkeyed.messages[0].padding = 1;
+        assert!(dedup.filter(unkeyed.clone()).is_some());
+        assert!(dedup.filter(unkeyed).is_some());
+    }
+}
@@ -0,0 +1,427 @@
+//! Peer discovery.
+//!
+//! A node announces itself with a signed PeerDiscovery and is added only
+//! once it signs back the nonce it was challenged with. Known peers are
+//! learned from `peers.seeds` and from the PeerListResponse and
+//! PeerListGossip of registered peers.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+
+use super::relay::relay_link;
+use super::{Node, NodeError, NodeInner};
+use crate::network::{ClockSkew, PeerInfo};
+use crate::protocol::envelope::{self, Framing};
+use crate::protocol::version;
+use crate::protocol::{
+    CongestionUpdate, PeerChallenge, PeerDiscovery, PeerListEntry, PeerListGossip, PeerListRequest, PeerListResponse,
+    Priority, ProtocolMessage,
+};
+use crate::Message;
+
+/// How long a PeerDiscovery nonce stays valid.
+const CHALLENGE_TTL: Duration = Duration::from_secs(30);
+/// Outstanding PeerDiscovery nonces kept at once; past this, the oldest is
+/// dropped for each new one.
+pub(super) const MAX_PENDING_CHALLENGES: usize = 256;
+/// Outstanding nonces per announced key; its further announcements are
+/// ignored until one is answered or expires.
+pub(super) const MAX_CHALLENGES_PER_KEY: usize = 2;
+/// How far a PeerDiscovery's timestamp may be from now by the announcer's
+/// clock, as far as its skew is known, before it is taken for a replay.
+pub(super) const DISCOVERY_MAX_AGE: Duration = Duration::from_secs(300);
+/// Most peers sent in, or dialed from, one PeerListResponse or PeerListGossip.
+pub(crate) const MAX_PEER_LIST: usize = 32;
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Announces this node to the connected peer `peer_id` with a signed
+    /// PeerDiscovery. The peer replies with a nonce, which is signed and sent
+    /// back automatically; only then does the peer add this node.
+    ///
+    /// With `node.relay_via` set, the announcement names the relay, and the
+    /// peer reaches this node through it; `publisher_address` should then be
+    /// the relay's publisher, where this node's responses appear.
+    pub async fn announce(
+        &self,
+        peer_id: &str,
+        router_address: impl Into<String>,
+        publisher_address: impl Into<String>,
+    ) -> Result<(), NodeError> {
+        self.announce_endpoints(peer_id, &[router_address.into()], &[publisher_address.into()])
+            .await
+    }
+
+    /// Like [`announce`](Self::announce), for a node reachable at several
+    /// endpoints, such as over IPv4, IPv6 and IPC. The peer dials them in
+    /// the order given and uses the first that answers.
+    pub async fn announce_endpoints(
+        &self,
+        peer_id: &str,
+        routers: &[String],
+        publishers: &[String],
+    ) -> Result<(), NodeError> {
+        let (Some((router, alt_routers)), Some((publisher, alt_publishers))) =
+            (routers.split_first(), publishers.split_first())
+        else {
+            return Err(NodeError::Config("announce needs a router and a publisher address".into()));
+        };
+        let mut pd = PeerDiscovery::new(self.inner.signer.public_key(), router, publisher)
+            .with_alternates(alt_routers.to_vec(), alt_publishers.to_vec())
+            .with_role(self.inner.config.node.role);
+        if let Some(relay) = &self.inner.config.node.relay_via {
+            pd = pd.with_relay(relay);
+        }
+        pd.signature = Some(Self::sign(&self.inner, &pd.signing_bytes()).await);
+
+        // Bare, since the peer's version is not known until it answers.
+        let msg = envelope::encode_bare(&ProtocolMessage::<M>::PeerDiscovery(pd))
+            .map_err(|e| NodeError::Serialization(e.to_string()))?;
+        self.inner
+            .network
+            .send_to_peer(peer_id, msg)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))
+    }
+
+    /// Contacts `peers.seeds` at start and every `seed_interval_secs`.
+    pub(super) fn spawn_seed_discovery(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        let interval = inner.config.peers.seed_interval_secs;
+
+        tokio::spawn(async move {
+            while inner.running.load(Ordering::SeqCst) {
+                Self::contact_seeds(&inner).await;
+                if interval == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        })
+    }
+
+    /// Pushes a sample of verified peers to every peer each
+    /// `peers.gossip_interval_secs`.
+    pub(super) fn spawn_peer_gossip(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        let interval = Duration::from_secs(inner.config.peers.gossip_interval_secs);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+
+            while inner.running.load(Ordering::SeqCst) {
+                ticker.tick().await;
+                if let Err(e) = Self::broadcast_peer_list(&inner).await {
+                    tracing::warn!(id = %inner.id, error = %e, "peer list gossip failed");
+                }
+            }
+        })
+    }
+
+    async fn broadcast_peer_list(inner: &NodeInner<M>) -> Result<(), NodeError> {
+        let peers = Self::sample_peers(inner, inner.config.peers.gossip_sample).await;
+        if peers.is_empty() {
+            return Ok(());
+        }
+        let mut gossip = PeerListGossip::new(inner.signer.public_key(), peers);
+        gossip.signature = Some(Self::sign(inner, &gossip.signing_bytes()).await);
+        let msg = Self::encode(&ProtocolMessage::<M>::PeerListGossip(gossip))?;
+
+        let peer_ids = inner.peers.read().await.peer_ids();
+        for peer_id in peer_ids {
+            Self::send_best_effort(inner, &peer_id, msg.clone(), Priority::Low).await;
+        }
+        Ok(())
+    }
+
+    /// Resolves every seed, dials the routers it names and asks each for
+    /// its peers. Returns how many routers were asked.
+    async fn contact_seeds(inner: &NodeInner<M>) -> usize {
+        let mut asked = 0;
+        for seed in &inner.config.peers.seeds {
+            let endpoints = match crate::network::seeds::resolve(seed).await {
+                Ok(endpoints) => endpoints,
+                Err(e) => {
+                    tracing::warn!(id = %inner.id, seed = %seed, error = %e, "failed to resolve seed");
+                    continue;
+                }
+            };
+            tracing::debug!(id = %inner.id, seed = %seed, routers = endpoints.len(), "resolved seed");
+            for endpoint in endpoints {
+                let peer_id = format!("seed-{}", endpoint.trim_start_matches("tcp://"));
+                match Self::request_peer_list(inner, &peer_id, &endpoint).await {
+                    Ok(()) => asked += 1,
+                    Err(e) => tracing::warn!(id = %inner.id, router = %endpoint, error = %e, "failed to contact seed"),
+                }
+            }
+        }
+        asked
+    }
+
+    /// Dials `router` as `peer_id`, if not linked yet, and sends it a PeerListRequest.
+    async fn request_peer_list(inner: &NodeInner<M>, peer_id: &str, router: &str) -> Result<(), NodeError> {
+        inner
+            .network
+            .connect_to_peer(peer_id, router)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))?;
+        let request = PeerListRequest { limit: MAX_PEER_LIST };
+        let msg = Self::encode(&ProtocolMessage::<M>::PeerListRequest(request))?;
+        inner
+            .network
+            .send_to_peer(peer_id, msg)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))
+    }
+
+    /// Checks a signed announcement against pinned keys and its age, and
+    /// replies with a nonce; the peer is only added once it signs the nonce
+    /// back.
+    pub(super) async fn inbox_peer_discovery(
+        inner: &NodeInner<M>,
+        pd: PeerDiscovery,
+    ) -> Result<CongestionUpdate, NodeError> {
+        let peer_id = pd.peer_id();
+
+        tracing::info!(
+            id = %inner.id,
+            peer = %peer_id,
+            router = %pd.router_address,
+            "received PeerDiscovery"
+        );
+
+        if let Some(key) = inner.config.peers.pinned_keys.get(&pd.router_address) {
+            if *key != pd.ecdsa_public_key {
+                tracing::warn!(
+                    id = %inner.id,
+                    peer = %peer_id,
+                    router = %pd.router_address,
+                    "PeerDiscovery key does not match pinned key"
+                );
+                return Ok(CongestionUpdate::ok());
+            }
+        }
+        if let Err(e) = version::negotiate(pd.protocol_version, pd.capabilities) {
+            tracing::warn!(id = %inner.id, peer = %peer_id, error = %e, "refusing PeerDiscovery");
+            return Ok(CongestionUpdate::ok());
+        }
+        let age_ms = Self::peer_now(inner, &peer_id).await.abs_diff(pd.timestamp);
+        if age_ms > DISCOVERY_MAX_AGE.as_millis() as u64 {
+            tracing::warn!(id = %inner.id, peer = %peer_id, age_ms, "refusing stale PeerDiscovery");
+            return Ok(CongestionUpdate::ok());
+        }
+
+        let mut challenges = inner.challenges.write().await;
+        challenges.retain(|_, (_, issued)| issued.elapsed() < CHALLENGE_TTL);
+        let pending = challenges
+            .values()
+            .filter(|(announced, _)| announced.ecdsa_public_key == pd.ecdsa_public_key)
+            .count();
+        if pending >= MAX_CHALLENGES_PER_KEY {
+            tracing::warn!(id = %inner.id, peer = %peer_id, "PeerDiscovery challenges for this key already pending");
+            return Ok(CongestionUpdate::ok());
+        }
+        if challenges.len() >= MAX_PENDING_CHALLENGES {
+            let oldest = challenges
+                .iter()
+                .min_by_key(|(_, (_, issued))| *issued)
+                .map(|(nonce, _)| nonce.clone());
+            if let Some(oldest) = oldest {
+                challenges.remove(&oldest);
+            }
+        }
+
+        let mut nonce = [0u8; 16];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut nonce);
+        let nonce = hex::encode(nonce);
+        challenges.insert(nonce.clone(), (pd, Instant::now()));
+
+        Ok(CongestionUpdate::challenge(nonce))
+    }
+
+    /// Adds the announced peer if `challenge` signs a nonce we issued to it.
+    pub(super) async fn inbox_peer_challenge(
+        inner: &NodeInner<M>,
+        challenge: PeerChallenge,
+    ) -> Result<CongestionUpdate, NodeError> {
+        let pending = inner.challenges.write().await.remove(&challenge.nonce);
+        let pd = match pending {
+            Some((pd, issued))
+                if issued.elapsed() < CHALLENGE_TTL && pd.ecdsa_public_key == challenge.ecdsa_public_key =>
+            {
+                pd
+            }
+            _ => {
+                tracing::warn!(
+                    id = %inner.id,
+                    key = %challenge.ecdsa_public_key.to_hex(),
+                    "PeerChallenge does not answer a pending nonce"
+                );
+                return Ok(CongestionUpdate::ok());
+            }
+        };
+
+        let peer_id = pd.peer_id();
+        let Ok(agreed) = version::negotiate(pd.protocol_version, pd.capabilities) else {
+            return Ok(CongestionUpdate::ok());
+        };
+        let peer = PeerInfo {
+            id: peer_id.clone(),
+            ecdsa_public: pd.ecdsa_public_key.clone(),
+            router_address: pd.router_address.clone(),
+            publisher_address: pd.publisher_address.clone(),
+            alt_router_addresses: pd.alt_router_addresses.clone(),
+            alt_publisher_addresses: pd.alt_publisher_addresses.clone(),
+            pinned: inner.config.peers.pinned_keys.contains_key(&pd.router_address),
+            relay: pd.relay.clone(),
+            protocol_version: Some(agreed.version),
+            capabilities: agreed.capabilities,
+            role: pd.role,
+            reported_latency: 0.0,
+            last_seen: None,
+            connected_router: None,
+            clock_skew: ClockSkew::new(),
+        };
+
+        if !inner.peers.write().await.add_peer(peer) {
+            return Ok(CongestionUpdate::ok());
+        }
+        inner
+            .network
+            .set_peer_framing(&peer_id, Framing::for_version(agreed.version))
+            .await;
+        tracing::info!(
+            id = %inner.id,
+            peer = %peer_id,
+            router = %pd.router_address,
+            relay = ?pd.relay,
+            protocol_version = agreed.version,
+            "peer verified"
+        );
+
+        // A relayed peer's router cannot be dialed; frames to it go to the relay.
+        let link = match &pd.relay {
+            Some(relay) => inner.network.connect_to_peer(&relay_link(relay), relay).await,
+            None => {
+                inner.network.set_peer_key(&peer_id, pd.ecdsa_public_key).await;
+                inner.network.connect_to_peer_endpoints(&peer_id, &pd.router_addresses()).await
+            }
+        };
+        if let Err(e) = link {
+            tracing::warn!(peer = %peer_id, error = %e, "failed to connect to peer router");
+        }
+        if let Err(e) = inner.network.subscribe_to_peer_endpoints(&pd.publisher_addresses()).await {
+            tracing::warn!(peer = %peer_id, error = %e, "failed to subscribe to peer publisher");
+        }
+
+        Ok(CongestionUpdate::ok())
+    }
+
+    /// Signs the nonce `peer_id` returned for our PeerDiscovery and sends it back.
+    pub(super) async fn answer_challenge(inner: &NodeInner<M>, peer_id: &str, nonce: String) -> Result<(), NodeError> {
+        let mut challenge = PeerChallenge::new(inner.signer.public_key(), nonce);
+        challenge.signature = Some(Self::sign(inner, &challenge.signing_bytes()).await);
+
+        let msg = serde_json::to_vec(&ProtocolMessage::<M>::PeerChallenge(challenge))
+            .map_err(|e| NodeError::Serialization(e.to_string()))?;
+        inner
+            .network
+            .send_to_peer(peer_id, msg)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))
+    }
+
+    /// Answers a PeerListRequest with a random sample of verified peers.
+    pub(super) async fn inbox_peer_list_request(inner: &NodeInner<M>, request: PeerListRequest) -> CongestionUpdate {
+        let limit = match request.limit {
+            0 => MAX_PEER_LIST,
+            limit => limit.min(MAX_PEER_LIST),
+        };
+        let peers = Self::sample_peers(inner, limit).await;
+        CongestionUpdate::peer_list(PeerListResponse { peers })
+    }
+
+    /// Up to `limit` verified peers, chosen at random, with the latency to
+    /// each once measured. Relayed peers are left out, as their routers
+    /// cannot be dialed.
+    async fn sample_peers(inner: &NodeInner<M>, limit: usize) -> Vec<PeerListEntry> {
+        let peers = inner.peers.read().await;
+        Self::selector(inner)
+            .random(&peers, limit.min(MAX_PEER_LIST))
+            .into_iter()
+            .filter(|peer| !peer.is_relayed())
+            .map(|peer| PeerListEntry {
+                ecdsa_public_key: peer.ecdsa_public.clone(),
+                router_address: peer.router_address.clone(),
+                publisher_address: peer.publisher_address.clone(),
+                latency_secs: peer.last_seen.map(|_| peer.reported_latency),
+            })
+            .collect()
+    }
+
+    /// Dials the peers a registered peer gossiped, as for a PeerListResponse.
+    /// Gossip from nodes not in the registry is ignored, so only peers that
+    /// passed the PeerDiscovery handshake can steer who this node dials.
+    pub(super) async fn inbox_peer_list_gossip(inner: &NodeInner<M>, gossip: PeerListGossip) -> CongestionUpdate {
+        let from = gossip.sender_id();
+        if inner.peers.read().await.find_by_key_id(&from).is_none() {
+            tracing::debug!(id = %inner.id, from = %from, "ignored PeerListGossip from unknown node");
+            return CongestionUpdate::ok();
+        }
+        Self::dial_peer_list(inner, &from, PeerListResponse { peers: gossip.peers }).await;
+        CongestionUpdate::ok()
+    }
+
+    /// Dials the routers in a PeerListResponse from `from` that this node
+    /// has no link to, keyed by the listed key, lowest reported latency
+    /// first and stopping at `peers.max_peers`. They join the registry, as
+    /// configured routers do, once a PeerDiscovery handshake completes.
+    /// Returns how many were dialed.
+    pub(super) async fn dial_peer_list(inner: &NodeInner<M>, from: &str, list: PeerListResponse) -> usize {
+        let own_key = inner.signer.public_key();
+        let max_peers = inner.config.peers.max_peers;
+        let mut entries: Vec<_> = list.peers.into_iter().take(MAX_PEER_LIST).collect();
+        entries.sort_by(|a, b| {
+            let latency = |e: &PeerListEntry| e.latency_secs.unwrap_or(f64::INFINITY);
+            latency(a).total_cmp(&latency(b))
+        });
+
+        let mut dialed = 0;
+        for entry in entries {
+            let peer_id = entry.peer_id();
+            let known = inner.peers.read().await.len();
+            if max_peers > 0 && known + dialed >= max_peers {
+                tracing::debug!(id = %inner.id, from = %from, max_peers, "peer cap reached, not dialing further peers");
+                break;
+            }
+            if entry.ecdsa_public_key == own_key || inner.peers.read().await.get(&peer_id).is_some() {
+                continue;
+            }
+            if let Some(pinned) = inner.config.peers.pinned_keys.get(&entry.router_address) {
+                if *pinned != entry.ecdsa_public_key {
+                    tracing::warn!(
+                        id = %inner.id,
+                        from = %from,
+                        router = %entry.router_address,
+                        "listed key does not match pinned key"
+                    );
+                    continue;
+                }
+            }
+            inner.network.set_peer_key(&peer_id, entry.ecdsa_public_key).await;
+            match inner.network.connect_to_peer(&peer_id, &entry.router_address).await {
+                Ok(()) => dialed += 1,
+                Err(e) => tracing::warn!(peer = %peer_id, error = %e, "failed to dial listed peer"),
+            }
+        }
+        tracing::debug!(id = %inner.id, from = %from, dialed, "received peer list");
+        dialed
+    }
+}
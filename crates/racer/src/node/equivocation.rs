@@ -0,0 +1,248 @@
+//! Detection of creators that sign conflicting batches.
+//!
+//! A creator's signature binds a batch's id, sequence number and merkle
+//! root, so two validly signed batches from one creator that share a
+//! sequence number but not a hash, or a batch id but not a merkle root, can
+//! only come from the creator equivocating: telling different nodes
+//! different things under one name. Resubmitting a message gives a new
+//! sequence number and the same merkle root, so it is not flagged.
+//!
+//! The [`EquivocationDetector`] remembers the last [`EQUIVOCATION_WINDOW`]
+//! batches received and checks each new one against them. On a conflict it
+//! returns both batches as [`Equivocation`] evidence, which anyone can check
+//! with [`Equivocation::verify`], and penalizes the creator: the node stops
+//! echoing its batches until [`Node::pardon`](super::Node::pardon) is called.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::crypto::PublicKey;
+use crate::protocol::BatchedMessages;
+
+/// Received batches remembered for comparison.
+pub const EQUIVOCATION_WINDOW: usize = 1024;
+
+/// What two conflicting batches both claimed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Slot {
+    Sequence(u64),
+    BatchId(String),
+}
+
+/// Two batches signed by one creator that cannot both be honest.
+#[derive(Debug, Clone)]
+pub struct Equivocation<M> {
+    pub creator: PublicKey,
+    /// The batch received first.
+    pub first: BatchedMessages<M>,
+    pub second: BatchedMessages<M>,
+}
+
+impl<M: Serialize + Clone> Equivocation<M> {
+    /// Whether the batches prove the equivocation on their own: both carry
+    /// a valid signature of `creator` and claim the same sequence number or
+    /// batch id with different contents.
+    pub fn verify(&self) -> bool {
+        let (first, second) = (&self.first, &self.second);
+        if first.creator_ecdsa != self.creator || second.creator_ecdsa != self.creator {
+            return false;
+        }
+        if !first.verify_creator_signature() || !second.verify_creator_signature() {
+            return false;
+        }
+        let same_sequence = first.sequence > 0 && first.sequence == second.sequence;
+        let same_id = first.batch_id == second.batch_id;
+        (same_sequence && first.compute_hash() != second.compute_hash())
+            || (same_id && first.merkle_root != second.merkle_root)
+    }
+}
+
+struct Seen<M> {
+    hash: String,
+    batch: Arc<BatchedMessages<M>>,
+}
+
+struct State<M> {
+    slots: HashMap<(String, Slot), Seen<M>>,
+    /// Keys of `slots`, oldest first.
+    order: VecDeque<(String, Slot)>,
+    penalized: HashSet<PublicKey>,
+}
+
+pub(crate) struct EquivocationDetector<M> {
+    state: Mutex<State<M>>,
+    capacity: usize,
+}
+
+impl<M: Clone> EquivocationDetector<M> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                slots: HashMap::new(),
+                order: VecDeque::new(),
+                penalized: HashSet::new(),
+            }),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<M>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Checks a received batch, whose creator signature has been verified,
+    /// against those remembered, and remembers it. On a conflict the
+    /// creator is penalized and the evidence returned.
+    pub(crate) fn check(&self, hash: &str, batch: &BatchedMessages<M>) -> Option<Equivocation<M>> {
+        let creator = batch.creator_ecdsa.to_hex();
+        let mut slots = vec![Slot::BatchId(batch.batch_id.clone())];
+        if batch.sequence > 0 {
+            slots.push(Slot::Sequence(batch.sequence));
+        }
+
+        let mut state = self.lock();
+        for slot in &slots {
+            let Some(seen) = state.slots.get(&(creator.clone(), slot.clone())) else {
+                continue;
+            };
+            let conflict = match slot {
+                Slot::Sequence(_) => seen.hash != hash,
+                Slot::BatchId(_) => seen.batch.merkle_root != batch.merkle_root,
+            };
+            if conflict {
+                let first = (*seen.batch).clone();
+                state.penalized.insert(batch.creator_ecdsa.clone());
+                return Some(Equivocation {
+                    creator: batch.creator_ecdsa.clone(),
+                    first,
+                    second: batch.clone(),
+                });
+            }
+        }
+
+        let shared = Arc::new(batch.clone());
+        for slot in slots {
+            let key = (creator.clone(), slot);
+            if state.slots.contains_key(&key) {
+                continue;
+            }
+            state.slots.insert(
+                key.clone(),
+                Seen {
+                    hash: hash.to_string(),
+                    batch: Arc::clone(&shared),
+                },
+            );
+            state.order.push_back(key);
+        }
+        while state.order.len() > self.capacity {
+            if let Some(key) = state.order.pop_front() {
+                state.slots.remove(&key);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn is_penalized(&self, creator: &PublicKey) -> bool {
+        self.lock().penalized.contains(creator)
+    }
+
+    pub(crate) fn penalized(&self) -> Vec<PublicKey> {
+        self.lock().penalized.iter().cloned().collect()
+    }
+
+    /// Lifts the penalty on `creator`. Returns whether it had one.
+    pub(crate) fn pardon(&self, creator: &PublicKey) -> bool {
+        self.lock().penalized.remove(creator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{EcdsaSigner, KeyPair};
+    use crate::protocol::VectorClock;
+    use racer_core::message::DefaultMessage;
+
+    fn batch(keys: &KeyPair, batch_id: &str, sequence: u64, root: &str) -> BatchedMessages<DefaultMessage> {
+        let mut bm = BatchedMessages {
+            batch_id: batch_id.into(),
+            creator_ecdsa: keys.public_key(),
+            sender_ecdsa: keys.public_key(),
+            merkle_root: root.into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        };
+        bm.sign_as_creator(&EcdsaSigner::new(keys.signing_key().clone()));
+        bm
+    }
+
+    fn check(detector: &EquivocationDetector<DefaultMessage>, bm: &BatchedMessages<DefaultMessage>) -> bool {
+        detector.check(&bm.compute_hash(), bm).is_some()
+    }
+
+    #[test]
+    fn test_conflicting_sequence_is_caught() {
+        let keys = KeyPair::generate();
+        let detector = EquivocationDetector::new(EQUIVOCATION_WINDOW);
+        let first = batch(&keys, "b-1", 7, "root-a");
+        assert!(!check(&detector, &first));
+        // A relayed copy is the same batch.
+        assert!(!check(&detector, &first.relay_copy(KeyPair::generate().public_key())));
+        // A resubmission carries the same content under a new number.
+        assert!(!check(&detector, &batch(&keys, "b-1", 8, "root-a")));
+        assert!(!detector.is_penalized(&keys.public_key()));
+
+        let second = batch(&keys, "b-2", 7, "root-b");
+        let evidence = detector.check(&second.compute_hash(), &second).unwrap();
+        assert!(evidence.verify());
+        assert_eq!(evidence.first.batch_id, "b-1");
+        assert!(detector.is_penalized(&keys.public_key()));
+
+        assert!(detector.pardon(&keys.public_key()));
+        assert!(detector.penalized().is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_batch_id_is_caught() {
+        let keys = KeyPair::generate();
+        let detector = EquivocationDetector::new(EQUIVOCATION_WINDOW);
+        assert!(!check(&detector, &batch(&keys, "b-1", 0, "root-a")));
+        assert!(!check(&detector, &batch(&KeyPair::generate(), "b-1", 0, "root-b")));
+
+        let evidence = detector.check("h", &batch(&keys, "b-1", 0, "root-b")).unwrap();
+        assert!(evidence.verify());
+
+        let mut forged = evidence.clone();
+        forged.second.merkle_root = "root-a".into();
+        assert!(!forged.verify());
+    }
+
+    #[test]
+    fn test_window_forgets_oldest() {
+        let keys = KeyPair::generate();
+        let detector = EquivocationDetector::new(2);
+        assert!(!check(&detector, &batch(&keys, "b-1", 0, "root-a")));
+        assert!(!check(&detector, &batch(&keys, "b-2", 0, "root-a")));
+        assert!(!check(&detector, &batch(&keys, "b-3", 0, "root-a")));
+        assert!(!check(&detector, &batch(&keys, "b-1", 0, "root-b")));
+        assert!(check(&detector, &batch(&keys, "b-3", 0, "root-b")));
+    }
+}
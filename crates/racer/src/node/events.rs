@@ -0,0 +1,63 @@
+//! Lifecycle events published by a running node; see
+//! [`Node::subscribe_events`](super::Node::subscribe_events).
+
+use serde::Serialize;
+
+use crate::network::{Backpressure, PeerEvent};
+
+/// Events buffered per `subscribe_events` receiver.
+pub(crate) const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NodeEvent {
+    /// The dealer link to `peer_id` is up, initially or after a reconnect.
+    PeerConnected { peer_id: String },
+    /// The dealer link to `peer_id` dropped; it is being retried with backoff.
+    PeerLost { peer_id: String },
+    /// This node started gossiping a batch, as its creator or re-gossiping it.
+    RoundStarted { hash: String, batch_id: String },
+    /// A round reached its delivery threshold on this node.
+    RoundDelivered { hash: String },
+    /// A round gave up waiting in `phase`: one this node gossiped as its
+    /// phase timed out, or a relayed one once dropped after the gossip timeout.
+    RoundTimedOut { hash: String, phase: RoundPhase },
+    /// A local submission was dropped undelivered after `attempts` rounds,
+    /// once the `node.submit_retry_*` policy or the batch TTL gave up on it.
+    SubmissionExpired { hash: String, batch_id: String, attempts: u32 },
+    /// A batch from `creator` (public key hex) was delivered with a sequence
+    /// number past `from..=to`, none of which has been delivered here yet.
+    /// Rounds finish out of order, so the gap may still fill in; if it does
+    /// not, the missing batches can be fetched from a peer's store.
+    SequenceGap { creator: String, from: u64, to: u64 },
+    /// `creator` (public key hex) signed two conflicting batches, given as
+    /// received with their signatures, which prove it to anyone; see
+    /// [`Equivocation::verify`](super::equivocation::Equivocation::verify).
+    /// The node no longer echoes the creator's batches.
+    Equivocation {
+        creator: String,
+        first: serde_json::Value,
+        second: serde_json::Value,
+    },
+    /// PLATO moved its latency estimate, and with it the round timeouts.
+    CongestionChanged { current_latency: f64, publish_frequency: f64 },
+    /// Frames were produced faster than this node could send them. PLATO
+    /// takes it as congestion at its next check.
+    Backpressure(Backpressure),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundPhase {
+    Echo,
+    Ready,
+}
+
+impl From<PeerEvent> for NodeEvent {
+    fn from(event: PeerEvent) -> Self {
+        match event {
+            PeerEvent::Up(peer_id) => Self::PeerConnected { peer_id },
+            PeerEvent::Down(peer_id) => Self::PeerLost { peer_id },
+        }
+    }
+}
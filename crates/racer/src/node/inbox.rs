@@ -0,0 +1,349 @@
+//! Inbound frames.
+//!
+//! Listener tasks read the router, subscriber and dealer sockets and feed
+//! router and subscriber frames into the [`pipeline`]; its consensus stage
+//! dispatches what the earlier stages accept to the handler for its
+//! message type. Dealer sockets carry the replies to our
+//! own requests: congestion updates, challenges, peer lists and relayed
+//! frames.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use super::pipeline::{self, Delivery, Frame, FrameLimits, Inbound, ScreenContext, Verdict};
+use super::{Node, NodeError, NodeInner};
+use crate::protocol::envelope::Framing;
+use crate::protocol::version;
+use crate::protocol::{CongestionUpdate, ProtocolMessage, ProtocolOffer};
+use crate::Message;
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Spawns the pipeline up to consensus: the decode, verify and dedup
+    /// stages, then consensus, which hands what it delivers to
+    /// `deliveries`. Returns the channel listeners send frames into and
+    /// the stages' tasks.
+    pub(super) fn spawn_pipeline(
+        &self,
+        deliveries: mpsc::Sender<Delivery<M>>,
+    ) -> (mpsc::Sender<Frame>, Vec<JoinHandle<()>>) {
+        let (frames, frames_rx) = mpsc::channel(pipeline::STAGE_CAPACITY);
+        let (verdicts, verdicts_rx) = mpsc::channel(pipeline::STAGE_CAPACITY);
+        let context = ScreenContext {
+            node_id: self.inner.id.clone(),
+            limits: FrameLimits::from_config(&self.inner.config),
+            state: Arc::clone(&self.inner.gossip_state),
+            counters: Arc::clone(&self.inner.pipeline),
+            quarantine: Arc::clone(&self.inner.quarantine),
+        };
+        let mut handles = pipeline::spawn_screen(frames_rx, verdicts, context);
+        handles.push(self.spawn_consensus(verdicts_rx, deliveries));
+        (frames, handles)
+    }
+
+    /// The consensus stage: handles each screened message in turn.
+    fn spawn_consensus(
+        &self,
+        mut verdicts: mpsc::Receiver<Verdict<M>>,
+        deliveries: mpsc::Sender<Delivery<M>>,
+    ) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+        let span = tracing::debug_span!("consensus", id = %inner.id);
+
+        tokio::spawn(
+            async move {
+                while let Some(verdict) = verdicts.recv().await {
+                    Self::process_verdict(&inner, verdict, &deliveries).await;
+                }
+            }
+            .instrument(span),
+        )
+    }
+
+    /// Runs the consensus stage for one screened message.
+    async fn process_verdict(
+        inner: &NodeInner<M>,
+        verdict: Verdict<M>,
+        deliveries: &mpsc::Sender<Delivery<M>>,
+    ) {
+        let result = match verdict {
+            Verdict::Accept(Inbound::Request { identity, message: ProtocolMessage::Relay(frame) }) => {
+                match Self::inbox_relay(inner, identity.clone(), frame).await {
+                    Ok(update) => Self::reply(inner, identity, update).await,
+                    Err(e) => Err(e),
+                }
+            }
+            Verdict::Accept(Inbound::Request { identity, message }) => {
+                match Self::handle_request(inner, message).await {
+                    Ok(update) => Self::reply(inner, identity, update).await,
+                    Err(e) => Err(e),
+                }
+            }
+            Verdict::Accept(Inbound::Response(response)) => {
+                match Self::handle_response(inner, response).await {
+                    Ok(Some(delivery)) => {
+                        let _ = deliveries.send(delivery).await;
+                        Ok(())
+                    }
+                    Ok(None) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+            Verdict::Reply { identity, update } => Self::reply(inner, identity, update).await,
+            Verdict::Drop => Ok(()),
+        };
+
+        inner.pipeline.record_processed();
+        if let Err(e) = result {
+            tracing::warn!(id = %inner.id, error = %e, "failed to handle inbound message");
+        }
+    }
+
+    async fn reply(
+        inner: &NodeInner<M>,
+        identity: Vec<u8>,
+        update: CongestionUpdate,
+    ) -> Result<(), NodeError> {
+        Self::touch_dealer(inner, &identity).await;
+
+        let reply = serde_json::to_vec(&update)
+            .map_err(|e| NodeError::Serialization(e.to_string()))?;
+
+        inner.network
+            .send_router_reply(identity, reply)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))
+    }
+
+    pub(super) fn spawn_router_listener(&self, frames: mpsc::Sender<Frame>) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            tracing::debug!(id = %inner.id, "router listener started");
+
+            while inner.running.load(Ordering::SeqCst) {
+                match inner.network.recv_router().await {
+                    Ok((identity, content)) => {
+                        let frame = Frame::Router { identity, content };
+                        let _ = frames.send(frame).await;
+                    }
+                    Err(e) => {
+                        if inner.running.load(Ordering::SeqCst) {
+                            tracing::warn!(id = %inner.id, error = %e, "router recv error");
+                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!(id = %inner.id, "router listener stopped");
+        })
+    }
+
+    pub(super) fn spawn_subscriber_listener(&self, frames: mpsc::Sender<Frame>) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            tracing::debug!(id = %inner.id, "subscriber listener started");
+
+            while inner.running.load(Ordering::SeqCst) {
+                match inner.network.recv_subscriber().await {
+                    Ok((topic, content)) => {
+                        let frame = Frame::Subscriber { topic, content };
+                        let _ = frames.send(frame).await;
+                    }
+                    Err(e) => {
+                        if inner.running.load(Ordering::SeqCst) {
+                            tracing::warn!(id = %inner.id, error = %e, "subscriber recv error");
+                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!(id = %inner.id, "subscriber listener stopped");
+        })
+    }
+
+    pub(super) fn spawn_dealer_listener(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            tracing::debug!(id = %inner.id, "dealer listener started");
+
+            while inner.running.load(Ordering::SeqCst) {
+                match inner.network.recv_dealer().await {
+                    Ok((peer_id, content)) => {
+                        if let Err(e) = Self::handle_dealer_message(&inner, &peer_id, &content).await {
+                            tracing::warn!(id = %inner.id, error = %e, "failed to handle dealer message");
+                        }
+                    }
+                    Err(e) => {
+                        if inner.running.load(Ordering::SeqCst) {
+                            tracing::warn!(id = %inner.id, error = %e, "dealer recv error");
+                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!(id = %inner.id, "dealer listener stopped");
+        })
+    }
+
+    /// With `node.low_power`, reads the router, subscriber and dealer
+    /// sockets from this one task rather than a task each. A receive that
+    /// loses the race is kept for the next turn, not dropped, so no frame
+    /// is lost between them.
+    pub(super) fn spawn_listener(&self, frames: mpsc::Sender<Frame>) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            tracing::debug!(id = %inner.id, "listener started");
+            let network = &inner.network;
+            let router = network.recv_router();
+            let subscriber = network.recv_subscriber();
+            let dealer = network.recv_dealer();
+            tokio::pin!(router, subscriber, dealer);
+
+            while inner.running.load(Ordering::SeqCst) {
+                tokio::select! {
+                    received = &mut router => {
+                        router.set(network.recv_router());
+                        match received {
+                            Ok((identity, content)) => {
+                                let frame = Frame::Router { identity, content };
+                                let _ = frames.send(frame).await;
+                            }
+                            Err(e) => Self::recv_failed(&inner, "router", &e).await,
+                        }
+                    }
+                    received = &mut subscriber => {
+                        subscriber.set(network.recv_subscriber());
+                        match received {
+                            Ok((topic, content)) => {
+                                let frame = Frame::Subscriber { topic, content };
+                                let _ = frames.send(frame).await;
+                            }
+                            Err(e) => Self::recv_failed(&inner, "subscriber", &e).await,
+                        }
+                    }
+                    received = &mut dealer => {
+                        dealer.set(network.recv_dealer());
+                        match received {
+                            Ok((peer_id, content)) => {
+                                if let Err(e) = Self::handle_dealer_message(&inner, &peer_id, &content).await {
+                                    tracing::warn!(id = %inner.id, error = %e, "failed to handle dealer message");
+                                }
+                            }
+                            Err(e) => Self::recv_failed(&inner, "dealer", &e).await,
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!(id = %inner.id, "listener stopped");
+        })
+    }
+
+    async fn recv_failed(inner: &NodeInner<M>, socket: &str, error: &impl std::fmt::Display) {
+        if inner.running.load(Ordering::SeqCst) {
+            tracing::warn!(id = %inner.id, socket, error = %error, "recv error");
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    pub(super) async fn handle_dealer_message(
+        inner: &NodeInner<M>,
+        peer_id: &str,
+        content: &[u8],
+    ) -> Result<(), NodeError> {
+        if let Err(reason) = FrameLimits::from_config(&inner.config).check_frame(content.len()) {
+            inner.pipeline.record_oversized();
+            tracing::warn!(id = %inner.id, peer = %peer_id, reason = %reason, "dropped oversized dealer reply");
+            return Ok(());
+        }
+        let update: CongestionUpdate = serde_json::from_slice(content)
+            .map_err(|e| NodeError::Serialization(e.to_string()))?;
+
+        if let Some(nonce) = update.challenge {
+            let offer = update.protocol.unwrap_or_else(ProtocolOffer::legacy);
+            let agreed = match version::negotiate(offer.version, offer.capabilities) {
+                Ok(agreed) => agreed,
+                Err(e) => {
+                    tracing::warn!(id = %inner.id, peer = %peer_id, error = %e, "not answering PeerDiscovery challenge");
+                    return Ok(());
+                }
+            };
+            if let Some(peer) = inner.peers.write().await.get_mut(peer_id) {
+                peer.protocol_version = Some(agreed.version);
+                peer.capabilities = agreed.capabilities;
+            }
+            inner
+                .network
+                .set_peer_framing(peer_id, Framing::for_version(agreed.version))
+                .await;
+            return Self::answer_challenge(inner, peer_id, nonce).await;
+        }
+        if let Some(list) = update.peer_list {
+            Self::dial_peer_list(inner, peer_id, list).await;
+            return Ok(());
+        }
+        if let Some(frame) = update.relayed {
+            Self::handle_relayed(inner, peer_id, frame).await;
+            return Ok(());
+        }
+
+        tracing::debug!(
+            id = %inner.id,
+            from = %peer_id,
+            latency = %update.current_latency,
+            "received CongestionUpdate"
+        );
+
+        let mut plato = inner.plato.write().await;
+        plato.record_peer_latency(update.current_latency);
+        if update.recently_missed {
+            plato.set_missed_delivery(true);
+        }
+
+        Ok(())
+    }
+
+    /// Handles a verified, deduplicated router request.
+    pub(super) async fn handle_request(
+        inner: &NodeInner<M>,
+        msg: ProtocolMessage<M>,
+    ) -> Result<CongestionUpdate, NodeError> {
+        match msg {
+            ProtocolMessage::BatchedMessages(bm) => Self::inbox_batched(inner, bm).await,
+            ProtocolMessage::Echo(echo) => Self::inbox_echo(inner, echo).await,
+            ProtocolMessage::PeerDiscovery(pd) => Self::inbox_peer_discovery(inner, pd).await,
+            ProtocolMessage::PeerChallenge(challenge) => {
+                Self::inbox_peer_challenge(inner, challenge).await
+            }
+            ProtocolMessage::Response(_) => Ok(CongestionUpdate::ok()),
+            ProtocolMessage::HealthSummary(summary) => {
+                Ok(Self::inbox_health_summary(inner, summary).await)
+            }
+            ProtocolMessage::PeerListRequest(request) => {
+                Ok(Self::inbox_peer_list_request(inner, request).await)
+            }
+            ProtocolMessage::PeerListGossip(gossip) => Ok(Self::inbox_peer_list_gossip(inner, gossip).await),
+            ProtocolMessage::DeliveryReport(report) => Ok(Self::inbox_delivery_report(inner, report).await),
+            // Handled in `process_verdict`, which has the sender's router
+            // identity; only one forwarded through a relay reaches here.
+            ProtocolMessage::Relay(_) => Ok(CongestionUpdate::ok()),
+            // Split by the pipeline before reaching here; only nested ones could.
+            ProtocolMessage::Bundle { .. } => Ok(CongestionUpdate::ok()),
+        }
+    }
+}
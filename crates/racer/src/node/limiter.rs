@@ -0,0 +1,141 @@
+//! Bound on gossip rounds this node runs for its own submissions.
+//!
+//! Each local submission holds a [`SubmitPermit`] for the length of its
+//! round. Once `node.max_inflight_submissions` rounds are running, further
+//! submissions wait, and a freed slot goes to the most urgent waiter first,
+//! oldest first within a [`Priority`].
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::protocol::Priority;
+
+#[derive(Debug)]
+struct LimiterState {
+    /// 0 is unlimited.
+    limit: usize,
+    in_flight: usize,
+    next_seq: u64,
+    waiting: BTreeMap<(Priority, u64), oneshot::Sender<SubmitPermit>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SubmitLimiter {
+    state: Arc<Mutex<LimiterState>>,
+}
+
+/// A running round's slot; released on drop.
+#[derive(Debug)]
+pub(crate) struct SubmitPermit {
+    state: Arc<Mutex<LimiterState>>,
+}
+
+impl SubmitLimiter {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LimiterState {
+                limit,
+                in_flight: 0,
+                next_seq: 0,
+                waiting: BTreeMap::new(),
+            })),
+        }
+    }
+
+    pub(crate) async fn acquire(&self, priority: Priority) -> SubmitPermit {
+        loop {
+            let rx = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                if state.limit == 0 || state.in_flight < state.limit {
+                    state.in_flight += 1;
+                    return SubmitPermit {
+                        state: Arc::clone(&self.state),
+                    };
+                }
+                let (tx, rx) = oneshot::channel();
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.waiting.insert((priority, seq), tx);
+                rx
+            };
+            if let Ok(permit) = rx.await {
+                return permit;
+            }
+        }
+    }
+
+    /// Rounds currently holding a permit.
+    #[cfg(test)]
+    pub(crate) fn in_flight(&self) -> usize {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).in_flight
+    }
+}
+
+impl Drop for SubmitPermit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some((_, tx)) = state.waiting.pop_first() {
+            let permit = SubmitPermit {
+                state: Arc::clone(&self.state),
+            };
+            match tx.send(permit) {
+                // The slot passes to the waiter; `in_flight` is unchanged.
+                Ok(()) => return,
+                // The waiter gave up; its permit must not release again.
+                Err(permit) => std::mem::forget(permit),
+            }
+        }
+        state.in_flight -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_freed_slot_goes_to_most_urgent_waiter() {
+        let limiter = SubmitLimiter::new(1);
+        let held = limiter.acquire(Priority::Normal).await;
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            let limiter = limiter.clone();
+            let order_tx = order_tx.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire(priority).await;
+                order_tx.send(priority).unwrap();
+            });
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(held);
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(order_rx.recv().await.unwrap());
+        }
+        assert_eq!(order, Priority::ALL);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_does_not_leak_slot() {
+        let limiter = SubmitLimiter::new(1);
+        let held = limiter.acquire(Priority::Normal).await;
+
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(Priority::High).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        waiter.abort();
+        let _ = waiter.await;
+
+        drop(held);
+        assert_eq!(limiter.in_flight(), 0);
+        let _permit = limiter.acquire(Priority::Low).await;
+        assert_eq!(limiter.in_flight(), 1);
+    }
+}
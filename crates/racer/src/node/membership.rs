@@ -0,0 +1,138 @@
+//! Membership changes gossiped as batches.
+//!
+//! A node proposes a join or leave in a batch of its own; every node stages
+//! the update once the batch is sequenced and applies the epoch's changes
+//! to its peer registry when the stability watermark crosses the epoch
+//! boundary (see [`Membership`](crate::protocol::Membership)).
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Node, NodeError, NodeInner};
+use crate::network::PeerInfo;
+use crate::protocol::{BatchedMessages, EpochChange, Member, MembershipChange, MembershipUpdate};
+use crate::Message;
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Applies an epoch's joins and leaves to the peer registry under one
+    /// write lock, so a round resolves percentage thresholds against either
+    /// the old or the new membership, never a mix.
+    pub(super) async fn apply_epoch_change(inner: &NodeInner<M>, change: EpochChange) {
+        let own_key = inner.signer.public_key();
+        let mut connect = Vec::new();
+        {
+            let mut peers = inner.peers.write().await;
+            for key in &change.left {
+                if let Some(id) = peers.find_by_key_id(&key.to_hex()[..10]).map(|p| p.id.clone()) {
+                    peers.remove(&id);
+                }
+            }
+            for member in &change.joined {
+                if member.key == own_key {
+                    continue;
+                }
+                let peer_id = member.key.to_hex()[..10].to_string();
+                let peer = PeerInfo::new(
+                    &peer_id,
+                    member.key.clone(),
+                    &member.router_address,
+                    &member.publisher_address,
+                );
+                if peers.add_peer(peer) {
+                    connect.push((peer_id, member));
+                }
+            }
+        }
+
+        for (peer_id, member) in connect {
+            inner.network.set_peer_key(&peer_id, member.key.clone()).await;
+            if let Err(e) = inner.network.connect_to_peer(&peer_id, &member.router_address).await {
+                tracing::warn!(peer = %peer_id, error = %e, "failed to connect to peer router");
+            }
+            if let Err(e) = inner.network.subscribe_to_peer(&member.publisher_address).await {
+                tracing::warn!(peer = %peer_id, error = %e, "failed to subscribe to peer publisher");
+            }
+        }
+
+        tracing::info!(
+            id = %inner.id,
+            epoch = change.epoch,
+            joined = change.joined.len(),
+            left = change.left.len(),
+            "membership epoch applied"
+        );
+    }
+
+    /// Signs `change` on behalf of this node and gossips it in a batch of its
+    /// own. Every node applies it to its peer registry at the first
+    /// `consensus.membership_epoch_secs` boundary after the batch was created,
+    /// once the batch has been sequenced (see [`subscribe_ordered`](Self::subscribe_ordered)).
+    /// Peers ignore a `Join` unless `consensus.open_membership` or their
+    /// `consensus.allowed_creators` admit this node's key.
+    pub async fn propose_membership(&self, change: MembershipChange) -> Result<String, NodeError> {
+        let bm = Self::prepare_membership_batch(&self.inner, change).await?;
+        let batch_id = bm.batch_id.clone();
+
+        Self::gossip_inner(&self.inner, bm).await?;
+
+        Ok(batch_id)
+    }
+
+    /// The last membership epoch whose boundary this node applied.
+    pub async fn membership_epoch(&self) -> u64 {
+        self.inner.membership.read().await.epoch()
+    }
+
+    /// Nodes that joined through [`propose_membership`](Self::propose_membership)
+    /// and have not left.
+    pub async fn members(&self) -> Vec<Member> {
+        self.inner.membership.read().await.members().cloned().collect()
+    }
+
+    /// Builds a signed batch carrying only a membership update for this node.
+    pub(super) async fn prepare_membership_batch(
+        inner: &NodeInner<M>,
+        change: MembershipChange,
+    ) -> Result<BatchedMessages<M>, NodeError> {
+        let mut update = MembershipUpdate::new(inner.signer.public_key(), change);
+        update.signature = Some(Self::sign(inner, &update.signing_bytes()).await);
+        let merkle_root = crate::crypto::sha256_hex(
+            &serde_json::to_vec(&update).map_err(|e| NodeError::Serialization(e.to_string()))?,
+        );
+        let sequence = Self::next_sequence(inner)?;
+
+        let mut vc = inner.vector_clock.write().await;
+        Self::tick_clock(inner, &mut vc);
+        let vector_clock = vc.clone();
+        drop(vc);
+
+        let mut bm = BatchedMessages {
+            batch_id: format!("{}-membership-{}", inner.id, update.timestamp),
+            creator_ecdsa: inner.signer.public_key(),
+            sender_ecdsa: inner.signer.public_key(),
+            merkle_root,
+            batch_size: 0,
+            messages: Vec::new(),
+            vector_clock,
+            creator_signature: None,
+            sender_signature: None,
+            created_at: racer_core::message::now_millis(),
+            membership: vec![update],
+            channel: None,
+            hops: 0,
+            max_hops: inner.config.consensus.hop_limit(),
+            ttl_ms: inner.config.consensus.batch_ttl(),
+            priority: Default::default(),
+            sequence,
+            #[cfg(feature = "bls")]
+            creator_bls: inner.signer.bls_public_key(),
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        };
+        Self::sign_batch(inner, &mut bm).await;
+
+        Ok(bm)
+    }
+}
@@ -0,0 +1,2183 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::config::{EncryptionMode, EstimatorKind, RacerConfig};
+use crate::crypto::{AsyncSigner, EcdsaSignature, KeyPair, PublicKey, TransportKeys};
+use crate::network::{Coalescer, NetworkStats, PeerEvent, PeerInfo, PeerRegistry, RacerNetwork};
+use crate::plato::trace::TraceRecorder;
+use crate::plato::PlatoController;
+use crate::protocol::{
+    Admission, BatchedMessages, ClusterHealth, CreatorLog, HealthTracker, Membership, OrderedBatch, PeerDiscovery,
+    Priority, RelayPayload, RoundSummary, Sequencer, ShardedGossipState, StoreStats, VectorClock,
+};
+use crate::protocol::envelope::{self, Framing};
+use crate::protocol::version;
+#[cfg(feature = "store")]
+use crate::store::DeliveryStore;
+use crate::util::logging::DeliveredMessageLogger;
+use crate::Message;
+
+pub mod admin;
+pub mod authorization;
+mod congestion;
+pub mod events;
+pub mod consensus;
+pub mod dedup;
+mod discovery;
+pub mod equivocation;
+mod inbox;
+mod limiter;
+mod membership;
+pub mod outbox;
+pub mod pipeline;
+pub mod quarantine;
+mod relay;
+pub mod reports;
+mod rounds;
+mod scheduler;
+mod selection;
+mod sequence;
+pub mod sink;
+mod submit;
+mod topics;
+pub mod topology;
+pub mod validator;
+
+pub(crate) use discovery::MAX_PEER_LIST;
+
+use consensus::ConsensusCore;
+use authorization::{AuthorizationPolicy, AuthorizationSlot};
+use dedup::{DedupPolicy, MessageDedup};
+use equivocation::EquivocationDetector;
+use quarantine::Quarantine;
+use reports::DeliveryReports;
+use scheduler::{Dispatch, PublishScheduler};
+use selection::PeerSelector;
+use sequence::SequenceCounter;
+use validator::{AsyncValidator, ValidatorSlot};
+use events::{NodeEvent, RoundPhase};
+use limiter::SubmitLimiter;
+use outbox::{Due, Outbox, PendingSubmission};
+use pipeline::{DeliverContext, Delivery, Inbound, PipelineCounters, PipelineStats};
+use sink::{DeliverySink, DeliverySinks};
+use topics::TopicManager;
+
+const SUBSCRIBE_SETTLE: Duration = Duration::from_millis(200);
+/// How often the sequencer checks for batches past the stability watermark.
+const SEQUENCER_TICK: Duration = Duration::from_millis(100);
+/// How often rounds past the gossip timeout are dropped.
+const MAINTENANCE_TICK: Duration = Duration::from_secs(1);
+/// `SEQUENCER_TICK` and `MAINTENANCE_TICK` with `node.low_power`.
+const LOW_POWER_SEQUENCER_TICK: Duration = Duration::from_millis(500);
+const LOW_POWER_MAINTENANCE_TICK: Duration = Duration::from_secs(5);
+/// Relayed batches waiting for one of those rounds; further ones are not
+/// re-gossiped.
+const LOW_POWER_RELAY_QUEUE: usize = 64;
+/// Ordered batches buffered per `subscribe_ordered` receiver.
+const ORDERED_CAPACITY: usize = 1024;
+/// Delivered batches buffered per `subscribe_channel` receiver.
+const CHANNEL_CAPACITY: usize = 1024;
+
+pub struct Node<M: Message> {
+    inner: Arc<NodeInner<M>>,
+    router_handle: RwLock<Option<JoinHandle<()>>>,
+    subscriber_handle: RwLock<Option<JoinHandle<()>>>,
+    dealer_handle: RwLock<Option<JoinHandle<()>>>,
+    health_handle: RwLock<Option<JoinHandle<()>>>,
+    congestion_handle: RwLock<Option<JoinHandle<()>>>,
+    deliver_handle: RwLock<Option<JoinHandle<()>>>,
+    /// The pipeline stages before deliver, one task each.
+    stage_handles: RwLock<Vec<JoinHandle<()>>>,
+    sequencer_handle: RwLock<Option<JoinHandle<()>>>,
+    maintenance_handle: RwLock<Option<JoinHandle<()>>>,
+    admin_handle: RwLock<Option<JoinHandle<()>>>,
+    events_handle: RwLock<Option<JoinHandle<()>>>,
+    seeds_handle: RwLock<Option<JoinHandle<()>>>,
+    peer_gossip_handle: RwLock<Option<JoinHandle<()>>>,
+    relay_handle: RwLock<Option<JoinHandle<()>>>,
+    publish_handle: RwLock<Option<JoinHandle<()>>>,
+    relayer_handle: RwLock<Option<JoinHandle<()>>>,
+}
+
+struct NodeInner<M: Message> {
+    /// The node's own state, for tasks spawned by handlers that only borrow it.
+    this: Weak<NodeInner<M>>,
+    config: RacerConfig,
+    id: String,
+    /// Signs everything this node sends; its public key is the node's identity.
+    signer: Arc<dyn AsyncSigner>,
+    network: Arc<RacerNetwork>,
+    /// Sends round fan-out frames, bundling those for the same peer.
+    coalescer: Coalescer,
+    topics: TopicManager,
+    peers: Arc<RwLock<PeerRegistry>>,
+    gossip_state: Arc<ShardedGossipState<M>>,
+    /// Decides what each round event calls for; the node carries it out.
+    consensus: ConsensusCore<M>,
+    plato: Arc<RwLock<PlatoController>>,
+    vector_clock: Arc<RwLock<VectorClock>>,
+    running: Arc<AtomicBool>,
+    sinks: DeliverySinks<M>,
+    health_tracker: Arc<RwLock<HealthTracker>>,
+    cluster_health: Arc<RwLock<ClusterHealth>>,
+    /// Router identities of the dealers that contacted us, with when they
+    /// last did.
+    dealer_identities: Arc<RwLock<HashMap<Vec<u8>, Instant>>>,
+    pipeline: Arc<PipelineCounters>,
+    /// Inbound frames the pipeline rejected, as received.
+    quarantine: Arc<Quarantine>,
+    started_at: Arc<RwLock<Option<Instant>>>,
+    sequencer: Arc<RwLock<Sequencer<M>>>,
+    ordered: broadcast::Sender<OrderedBatch<M>>,
+    delivered: broadcast::Sender<Delivery<M>>,
+    events: broadcast::Sender<NodeEvent>,
+    submissions: SubmitLimiter,
+    /// Local submissions waiting for their rounds, paced by PLATO.
+    scheduler: Arc<PublishScheduler<M>>,
+    /// Local submissions not yet delivered, retried until they are.
+    outbox: Arc<Outbox<M>>,
+    /// Numbers the batches created here.
+    sequence: Arc<SequenceCounter>,
+    /// Sequence numbers delivered from each creator, for gaps and reordering.
+    creator_log: Arc<RwLock<CreatorLog>>,
+    /// Application message keys delivered within `consensus.dedup_window_secs`.
+    dedup: Arc<MessageDedup<M>>,
+    /// Keys whose batches this node echoes.
+    authorization: Arc<AuthorizationSlot<M>>,
+    /// Recent batches by creator, for catching conflicting ones.
+    equivocation: Arc<EquivocationDetector<M>>,
+    /// Delivery latency of batches created here, as peers report it.
+    reports: Arc<DeliveryReports>,
+    /// Judges received batches before this node echoes them.
+    validator: Arc<ValidatorSlot<M>>,
+    membership: Arc<RwLock<Membership>>,
+    /// Picks the peers of each round and of peer-list replies.
+    selector: Arc<std::sync::Mutex<PeerSelector>>,
+    /// PeerDiscovery announcements awaiting a signed nonce, keyed by nonce.
+    challenges: Arc<RwLock<HashMap<String, (PeerDiscovery, Instant)>>>,
+    /// With `node.relay`, router identities of the nodes registered with
+    /// this relay and the timestamp of their latest Register, keyed by key id.
+    relay_clients: Arc<RwLock<HashMap<String, (Vec<u8>, u64)>>>,
+    /// Feeds the deliver stage while the node runs; imports go through it.
+    deliveries: Arc<RwLock<Option<mpsc::Sender<Delivery<M>>>>>,
+    /// With `node.low_power`, feeds the relayer while the node runs.
+    relays: Arc<RwLock<Option<mpsc::Sender<BatchedMessages<M>>>>>,
+    #[cfg(feature = "store")]
+    store: Option<Arc<DeliveryStore<M>>>,
+}
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    pub async fn new(config: RacerConfig) -> Result<Self, NodeError> {
+        Self::build(config, None, None).await
+    }
+
+    /// Creates a node with the identity `keys`, ignoring `node.key_file`.
+    pub async fn with_keys(config: RacerConfig, keys: KeyPair) -> Result<Self, NodeError> {
+        Self::build(config, Some(keys), None).await
+    }
+
+    /// Creates a node whose protocol messages are signed by `signer`, such
+    /// as a secure element, TPM or PKCS#11 token that keeps its key to
+    /// itself. The node's identity is `signer`'s public key. Transport
+    /// encryption derives session keys from the identity's private key, so
+    /// `node.encryption` must be `off`. No key pair is created; with `bls`,
+    /// batches carry BLS signatures only if `signer` has a BLS key.
+    pub async fn with_signer(config: RacerConfig, signer: Arc<dyn AsyncSigner>) -> Result<Self, NodeError> {
+        if config.node.encryption != EncryptionMode::Off {
+            return Err(NodeError::Config(
+                "node.encryption must be off when signing with an external signer".into(),
+            ));
+        }
+        Self::build(config, None, Some(signer)).await
+    }
+
+    async fn build(
+        config: RacerConfig,
+        keys: Option<KeyPair>,
+        signer: Option<Arc<dyn AsyncSigner>>,
+    ) -> Result<Self, NodeError> {
+        // A key pair is only loaded or generated when no external signer
+        // holds the identity; transport encryption needs it.
+        let (signer, transport): (Arc<dyn AsyncSigner>, _) = match signer {
+            Some(signer) => (signer, None),
+            None => {
+                let keys = match (keys, &config.node.key_file) {
+                    (Some(keys), _) => keys,
+                    (None, Some(path)) => {
+                        let encoded = std::fs::read_to_string(path)
+                            .map_err(|e| NodeError::Config(format!("key file {}: {}", path.display(), e)))?;
+                        KeyPair::from_encoded(&encoded)
+                            .map_err(|e| NodeError::Crypto(format!("key file {}: {}", path.display(), e)))?
+                    }
+                    (None, None) => KeyPair::generate(),
+                };
+                (Arc::new(keys.clone()), Some(TransportKeys::new(keys)))
+            }
+        };
+        let id = config
+            .node
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("node-{}", &signer.public_key().to_hex()[..8]));
+
+        let network = RacerNetwork::with_options(
+            &config.node.router_bind,
+            &config.node.publisher_bind,
+            config.network.socket_options(),
+        )
+        .with_extra_binds(config.node.extra_router_binds.clone(), config.node.extra_publisher_binds.clone())
+        .with_send_queue(config.node.send_queue_capacity, config.node.send_overflow)
+        .with_reconnect(config.node.reconnect_policy());
+        let network = match transport {
+            Some(transport) => network.with_encryption(transport, config.node.encryption),
+            None => network,
+        };
+        #[cfg(feature = "websocket")]
+        let network = match &config.node.websocket_bind {
+            Some(address) => network.with_websocket(address),
+            None => network,
+        };
+        #[cfg(not(feature = "websocket"))]
+        if config.node.websocket_bind.is_some() {
+            tracing::warn!("websocket_bind is set but the `websocket` feature is disabled");
+        }
+        let network = Arc::new(network);
+
+        #[cfg(feature = "store")]
+        let store = match &config.node.store_path {
+            Some(path) => Some(Arc::new(
+                DeliveryStore::open(path)
+                    .map_err(|e| NodeError::Store(format!("{}: {}", path.display(), e)))?,
+            )),
+            None => None,
+        };
+        #[cfg(not(feature = "store"))]
+        if config.node.store_path.is_some() {
+            tracing::warn!("store_path is set but the `store` feature is disabled");
+        }
+
+        let retry_policy = config.node.submit_retry_policy();
+        #[cfg(feature = "store")]
+        let outbox = match &config.node.outbox_path {
+            Some(path) => Outbox::open(path, retry_policy)
+                .map_err(|e| NodeError::Store(format!("{}: {}", path.display(), e)))?,
+            None => Outbox::new(retry_policy),
+        };
+        #[cfg(not(feature = "store"))]
+        let outbox = {
+            if config.node.outbox_path.is_some() {
+                tracing::warn!("outbox_path is set but the `store` feature is disabled");
+            }
+            Outbox::new(retry_policy)
+        };
+
+        #[cfg(not(feature = "telemetry"))]
+        if config.logging.otlp_endpoint.is_some() {
+            tracing::warn!("otlp_endpoint is set but the `telemetry` feature is disabled");
+        }
+
+        let mut peers = PeerRegistry::new();
+        peers.set_self_id(&id);
+
+        let mut plato_config = config.plato.clone();
+        if config.node.low_power && plato_config.estimator == EstimatorKind::Rsi {
+            // Savitzky-Golay smoothing and RSI cost several times more per sample.
+            plato_config.estimator = EstimatorKind::Ewma;
+        }
+        let mut plato = PlatoController::new(plato_config);
+        if let Some(seed) = config.node.rng_seed {
+            plato.seed_rng(seed);
+        }
+        let selector = PeerSelector::new(config.node.selection_type, config.node.rng_seed);
+        if let Some(path) = &config.node.plato_state_path {
+            Self::restore_plato(&mut plato, path, config.plato.state_max_age());
+        }
+        if let Some(path) = &config.plato.trace_file {
+            let recorder = TraceRecorder::open(path)
+                .map_err(|e| NodeError::Config(format!("plato.trace_file {}: {}", path.display(), e)))?;
+            plato.set_trace_recorder(recorder);
+        }
+        let mut gossip_state = ShardedGossipState::new(config.node.gossip_shards);
+        gossip_state.set_timeout(plato.round_timings().round_timeout()).await;
+        gossip_state.set_seen_filter(
+            config.consensus.seen_filter_capacity,
+            config.consensus.seen_filter_fp_rate,
+        );
+        gossip_state.set_retention(config.consensus.retention());
+        let health_window = Duration::from_secs(config.consensus.health_window_secs);
+        let sequencer = Sequencer::new(Duration::from_secs_f64(config.consensus.sequencing_stability_secs));
+        let (ordered, _) = broadcast::channel(ORDERED_CAPACITY);
+        let (delivered, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (events, _) = broadcast::channel(events::EVENT_CAPACITY);
+        let membership = Membership::new(Duration::from_secs(config.consensus.membership_epoch_secs))
+            .with_admission(Admission::from_config(&config.consensus));
+
+        let sinks = DeliverySinks::new();
+        if let Some(logger) = DeliveredMessageLogger::new(&config.logging, &id) {
+            sinks.add(Arc::new(logger));
+            tracing::debug!(id = %id, "delivered message logging enabled");
+        }
+
+        let submissions = SubmitLimiter::new(config.node.max_inflight_submissions);
+        let scheduler = PublishScheduler::new(config.node.publish_burst);
+        let sequence = SequenceCounter::open(config.node.sequence_path.clone())
+            .map_err(|e| NodeError::Store(format!("node.sequence_path: {}", e)))?;
+
+        let coalescer = Coalescer::new(Arc::clone(&network), config.node.coalesce_policy());
+        let consensus = ConsensusCore::new(config.consensus.absolute_thresholds());
+        let dedup = MessageDedup::new(Duration::from_secs(config.consensus.dedup_window_secs));
+        let quarantine = Quarantine::new(config.node.quarantine_capacity);
+        let validator = ValidatorSlot::new(config.consensus.validation_timeout());
+        let authorization =
+            AuthorizationSlot::new(authorization::from_allowed_creators(&config.consensus.allowed_creators));
+
+        let inner = Arc::new_cyclic(|this| NodeInner {
+            this: this.clone(),
+            config,
+            id,
+            signer,
+            topics: TopicManager::new(Arc::clone(&network)),
+            coalescer,
+            network,
+            peers: Arc::new(RwLock::new(peers)),
+            gossip_state: Arc::new(gossip_state),
+            consensus,
+            plato: Arc::new(RwLock::new(plato)),
+            vector_clock: Arc::new(RwLock::new(VectorClock::new())),
+            running: Arc::new(AtomicBool::new(false)),
+            sinks,
+            health_tracker: Arc::new(RwLock::new(HealthTracker::new(health_window))),
+            cluster_health: Arc::new(RwLock::new(ClusterHealth::new(health_window))),
+            dealer_identities: Arc::new(RwLock::new(HashMap::new())),
+            pipeline: Arc::new(PipelineCounters::default()),
+            quarantine: Arc::new(quarantine),
+            started_at: Arc::new(RwLock::new(None)),
+            sequencer: Arc::new(RwLock::new(sequencer)),
+            ordered,
+            delivered,
+            events,
+            submissions,
+            scheduler: Arc::new(scheduler),
+            outbox: Arc::new(outbox),
+            sequence: Arc::new(sequence),
+            creator_log: Arc::new(RwLock::new(CreatorLog::new())),
+            dedup: Arc::new(dedup),
+            authorization: Arc::new(authorization),
+            equivocation: Arc::new(EquivocationDetector::new(equivocation::EQUIVOCATION_WINDOW)),
+            reports: Arc::new(DeliveryReports::new(reports::DELIVERY_REPORT_BATCHES)),
+            validator: Arc::new(validator),
+            membership: Arc::new(RwLock::new(membership)),
+            challenges: Arc::new(RwLock::new(HashMap::new())),
+            relay_clients: Arc::new(RwLock::new(HashMap::new())),
+            deliveries: Arc::new(RwLock::new(None)),
+            relays: Arc::new(RwLock::new(None)),
+            selector: Arc::new(std::sync::Mutex::new(selector)),
+            #[cfg(feature = "store")]
+            store,
+        });
+
+        #[cfg(feature = "telemetry")]
+        {
+            let gossip_state = Arc::downgrade(&inner.gossip_state);
+            crate::telemetry::register_node_metrics(
+                &inner.id,
+                Arc::downgrade(&inner.plato),
+                Arc::downgrade(&inner.pipeline),
+                move || gossip_state.upgrade()?.try_active_rounds(),
+            );
+        }
+
+        Ok(Self {
+            inner,
+            router_handle: RwLock::new(None),
+            subscriber_handle: RwLock::new(None),
+            dealer_handle: RwLock::new(None),
+            health_handle: RwLock::new(None),
+            congestion_handle: RwLock::new(None),
+            deliver_handle: RwLock::new(None),
+            stage_handles: RwLock::new(Vec::new()),
+            sequencer_handle: RwLock::new(None),
+            maintenance_handle: RwLock::new(None),
+            admin_handle: RwLock::new(None),
+            events_handle: RwLock::new(None),
+            seeds_handle: RwLock::new(None),
+            peer_gossip_handle: RwLock::new(None),
+            relay_handle: RwLock::new(None),
+            publish_handle: RwLock::new(None),
+            relayer_handle: RwLock::new(None),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.inner.signer.public_key()
+    }
+
+    pub fn config(&self) -> &RacerConfig {
+        &self.inner.config
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.inner.running.load(Ordering::SeqCst)
+    }
+
+    pub async fn start(&self) -> Result<(), NodeError> {
+        if let Some(address) = &self.inner.config.node.admin_bind {
+            *self.admin_handle.write().await = Some(self.spawn_admin_server(address).await?);
+        }
+
+        self.inner
+            .network
+            .bind()
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))?;
+
+        self.inner.running.store(true, Ordering::SeqCst);
+        *self.inner.started_at.write().await = Some(Instant::now());
+        *self.events_handle.write().await = Some(self.spawn_peer_events());
+
+        for (idx, router_addr) in self.inner.config.peers.routers.iter().enumerate() {
+            let peer_id = format!("peer-{}", idx);
+            if let Some(key) = self.inner.config.peers.pinned_keys.get(router_addr) {
+                self.inner.network.set_peer_key(&peer_id, key.clone()).await;
+            }
+            if let Err(e) = self.inner.network.connect_to_peer(&peer_id, router_addr).await {
+                tracing::warn!(addr = %router_addr, error = %e, "failed to connect to peer");
+            }
+        }
+
+        let (deliveries, deliver_handle) = self.spawn_deliver();
+        *self.inner.deliveries.write().await = Some(deliveries.clone());
+        let (frames, stage_handles) = self.spawn_pipeline(deliveries);
+        let (router_handle, subscriber_handle, dealer_handle) = if self.inner.config.node.low_power {
+            let (relays, batches) = mpsc::channel(LOW_POWER_RELAY_QUEUE);
+            *self.inner.relays.write().await = Some(relays);
+            *self.relayer_handle.write().await = Some(self.spawn_relayer(batches));
+            (self.spawn_listener(frames), None, None)
+        } else {
+            (
+                self.spawn_router_listener(frames.clone()),
+                Some(self.spawn_subscriber_listener(frames)),
+                Some(self.spawn_dealer_listener()),
+            )
+        };
+
+        *self.deliver_handle.write().await = Some(deliver_handle);
+        *self.stage_handles.write().await = stage_handles;
+        *self.sequencer_handle.write().await = Some(self.spawn_sequencer());
+        *self.maintenance_handle.write().await = Some(self.spawn_maintenance());
+        *self.publish_handle.write().await = Some(self.spawn_publisher());
+        *self.router_handle.write().await = Some(router_handle);
+        *self.subscriber_handle.write().await = subscriber_handle;
+        *self.dealer_handle.write().await = dealer_handle;
+
+        if self.inner.config.consensus.health_interval_secs > 0 {
+            *self.health_handle.write().await = Some(self.spawn_health_exchange());
+        }
+        if self.inner.config.plato.update_interval_secs > 0.0 {
+            *self.congestion_handle.write().await = Some(self.spawn_congestion_fanout());
+        }
+        if !self.inner.config.peers.seeds.is_empty() {
+            *self.seeds_handle.write().await = Some(self.spawn_seed_discovery());
+        }
+        if self.inner.config.peers.gossip_interval_secs > 0 {
+            *self.peer_gossip_handle.write().await = Some(self.spawn_peer_gossip());
+        }
+        if let Some(relay) = &self.inner.config.node.relay_via {
+            *self.relay_handle.write().await = Some(self.spawn_relay_registration(relay.clone()));
+        }
+
+        tracing::info!(
+            id = %self.inner.id,
+            router = %self.inner.config.node.router_bind,
+            publisher = %self.inner.config.node.publisher_bind,
+            "node started with background listeners"
+        );
+
+        Ok(())
+    }
+
+    pub async fn stop(&self) {
+        self.inner.running.store(false, Ordering::SeqCst);
+        self.inner.coalescer.flush_all().await;
+        Self::save_plato(&self.inner).await;
+
+        if let Some(handle) = self.router_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.subscriber_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.dealer_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.health_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.congestion_handle.write().await.take() {
+            handle.abort();
+        }
+        for handle in self.stage_handles.write().await.drain(..) {
+            handle.abort();
+        }
+        *self.inner.deliveries.write().await = None;
+        if let Some(handle) = self.deliver_handle.write().await.take() {
+            handle.abort();
+        }
+        *self.inner.relays.write().await = None;
+        if let Some(handle) = self.relayer_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.sequencer_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.maintenance_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.admin_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.events_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.seeds_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.peer_gossip_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.relay_handle.write().await.take() {
+            handle.abort();
+        }
+        if let Some(handle) = self.publish_handle.write().await.take() {
+            handle.abort();
+        }
+        self.inner.scheduler.abandon_waiters();
+        *self.inner.started_at.write().await = None;
+
+        tracing::info!(id = %self.inner.id, "node stopped");
+    }
+
+    /// Republishes dealer link changes and backpressure as [`NodeEvent`]s,
+    /// records the endpoint each link came up on, and feeds backpressure to
+    /// PLATO. Subscribes before any peer is connected so the first
+    /// `PeerConnected` is not missed.
+    fn spawn_peer_events(&self) -> JoinHandle<()> {
+        let mut peer_events = self.inner.network.subscribe_peer_events();
+        let mut backpressure = self.inner.network.subscribe_backpressure();
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    event = peer_events.recv() => match event {
+                        Ok(event) => {
+                            if let PeerEvent::Up(peer_id) = &event {
+                                let endpoint = inner.network.connected_endpoint(peer_id).await;
+                                if let Some(peer) = inner.peers.write().await.get_mut(peer_id) {
+                                    peer.connected_router = endpoint;
+                                }
+                            }
+                            let _ = inner.events.send(event.into());
+                        }
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            tracing::warn!(missed, "peer event stream lagged");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    alert = backpressure.recv() => match alert {
+                        Ok(alert) => {
+                            tracing::warn!(id = %inner.id, ?alert, "backpressure");
+                            inner.plato.write().await.record_backpressure();
+                            let _ = inner.events.send(NodeEvent::Backpressure(alert));
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => {
+                            inner.plato.write().await.record_backpressure();
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+            }
+        })
+    }
+
+    fn spawn_deliver(&self) -> (mpsc::Sender<Delivery<M>>, JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel(pipeline::DELIVERY_CAPACITY);
+        let context = DeliverContext {
+            node_id: self.inner.id.clone(),
+            sinks: self.inner.sinks.clone(),
+            network: Arc::clone(&self.inner.network),
+            counters: Arc::clone(&self.inner.pipeline),
+            sequencer: Arc::clone(&self.inner.sequencer),
+            peers: Arc::clone(&self.inner.peers),
+            creator_log: Arc::clone(&self.inner.creator_log),
+            subscribers: self.inner.delivered.clone(),
+            events: self.inner.events.clone(),
+            dedup: Arc::clone(&self.inner.dedup),
+            to_application: self.inner.config.node.role.delivers(),
+            #[cfg(feature = "store")]
+            store: self.inner.store.clone(),
+        };
+        let handle = pipeline::spawn_deliver(rx, context);
+        (tx, handle)
+    }
+
+    /// Periodically releases batches past the stability watermark to
+    /// `subscribe_ordered` receivers, staging the membership updates they
+    /// carry and applying them once the watermark crosses an epoch boundary.
+    fn spawn_sequencer(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        let tick = if inner.config.node.low_power { LOW_POWER_SEQUENCER_TICK } else { SEQUENCER_TICK };
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            while inner.running.load(Ordering::SeqCst) {
+                interval.tick().await;
+                let now = racer_core::message::now_millis();
+                let (released, watermark) = {
+                    let mut sequencer = inner.sequencer.write().await;
+                    (sequencer.release(now), sequencer.watermark(now))
+                };
+
+                let mut membership = inner.membership.write().await;
+                for ordered in released {
+                    let batch = &ordered.batch;
+                    if !batch.membership.is_empty() {
+                        if let Err(e) = membership.stage(&batch.membership, &batch.creator_ecdsa, batch.created_at) {
+                            tracing::warn!(
+                                id = %inner.id,
+                                creator = %batch.creator_ecdsa.to_hex(),
+                                error = %e,
+                                "dropped membership update"
+                            );
+                        }
+                    }
+                    tracing::debug!(id = %inner.id, sequence = ordered.sequence, hash = %ordered.hash, "batch SEQUENCED");
+                    if inner.config.node.role.delivers() {
+                        let _ = inner.ordered.send(ordered);
+                    }
+                }
+                let change = membership.advance(watermark);
+                drop(membership);
+
+                if let Some(change) = change {
+                    Self::apply_epoch_change(&inner, change).await;
+                }
+            }
+        })
+    }
+
+    /// Periodically drops rounds that outlived the gossip timeout, evicts
+    /// the batches of finished rounds past their retention and retries
+    /// undelivered local submissions.
+    fn spawn_maintenance(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        let tick = if inner.config.node.low_power { LOW_POWER_MAINTENANCE_TICK } else { MAINTENANCE_TICK };
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            while inner.running.load(Ordering::SeqCst) {
+                interval.tick().await;
+                Self::expire_rounds(&inner).await;
+                let evicted = inner.gossip_state.evict().await;
+                if evicted > 0 {
+                    tracing::debug!(id = %inner.id, evicted, "evicted retired batches");
+                }
+                Self::retry_submissions(&inner);
+            }
+        })
+    }
+
+    /// Starts the rounds of queued local submissions, most urgent first and
+    /// paced by PLATO's publish interval, each in a task of its own holding
+    /// a `max_inflight_submissions` slot.
+    fn spawn_publisher(&self) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            loop {
+                let publish_interval = inner.plato.read().await.round_timings().publish_interval;
+                let Dispatch { batch, hash, done } = inner.scheduler.next(publish_interval).await;
+                let permit = inner.submissions.acquire(batch.priority).await;
+                let inner = Arc::clone(&inner);
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    let result = Self::gossip_submission(&inner, batch, hash).await;
+                    if let Err(e) = &result {
+                        tracing::warn!(id = %inner.id, error = %e, "submission round failed");
+                    }
+                    if let Some(done) = done {
+                        let _ = done.send(result);
+                    }
+                });
+            }
+        })
+    }
+
+    /// Queues the local submissions due for a retry for another round, and
+    /// reports those the retry policy gave up on.
+    fn retry_submissions(inner: &Arc<NodeInner<M>>) {
+        for due in inner.outbox.take_due(racer_core::message::now_millis()) {
+            match due {
+                Due::Retry(bm) => {
+                    let hash = bm.compute_hash();
+                    tracing::debug!(id = %inner.id, hash = %hash, "retrying undelivered submission");
+                    let _ = inner.scheduler.enqueue(bm, hash);
+                }
+                Due::Expired(pending) => {
+                    tracing::warn!(
+                        id = %inner.id,
+                        hash = %pending.hash,
+                        attempts = pending.attempts,
+                        "gave up on undelivered submission"
+                    );
+                    let _ = inner.events.send(NodeEvent::SubmissionExpired {
+                        hash: pending.hash,
+                        batch_id: pending.batch_id,
+                        attempts: pending.attempts,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Registers a sink that sees every batch this node delivers from now on,
+    /// alongside the delivered message log and any sinks added before.
+    pub fn add_sink(&self, sink: impl DeliverySink<M> + 'static) {
+        self.inner.sinks.add(Arc::new(sink));
+    }
+
+    /// Replaces how delivered messages are keyed for
+    /// `consensus.dedup_window_secs`; by default, by [`Message::id`] alone.
+    /// See [`dedup`].
+    pub fn set_dedup_policy(&self, policy: impl DedupPolicy<M> + 'static) {
+        self.inner.dedup.set_policy(Arc::new(policy));
+    }
+
+    /// Replaces the policy deciding which keys' batches this node echoes,
+    /// including one built from `consensus.allowed_creators`. See
+    /// [`authorization`].
+    pub fn set_authorization_policy(&self, policy: impl AuthorizationPolicy<M> + 'static) {
+        self.inner.authorization.set(Arc::new(policy));
+    }
+
+    /// Runs `validator` on every batch received from a peer, after its
+    /// signatures check out and before this node echoes it; batches it
+    /// rejects take no part in their round here. See [`validator`].
+    pub fn set_validator(&self, validator: impl AsyncValidator<M> + 'static) {
+        self.inner.validator.set(Arc::new(validator));
+    }
+
+    /// Delivered batches in a total order shared by every node: sorted by
+    /// `(created_at, creator public key, hash)` and released once older than
+    /// `consensus.sequencing_stability_secs`. See [`crate::protocol::sequencing`].
+    ///
+    /// Only batches delivered after subscribing are received. A receiver that
+    /// falls more than 1024 batches behind gets `RecvError::Lagged`.
+    pub fn subscribe_ordered(&self) -> broadcast::Receiver<OrderedBatch<M>> {
+        self.inner.ordered.subscribe()
+    }
+
+    /// Local submissions not yet delivered, oldest first. Each is gossiped
+    /// again with backoff until a round delivers it or the
+    /// `node.submit_retry_*` policy gives up, raising
+    /// [`NodeEvent::SubmissionExpired`].
+    pub fn pending_submissions(&self) -> Vec<PendingSubmission> {
+        self.inner.outbox.pending()
+    }
+
+    /// Node lifecycle events from now on. A receiver that falls more than
+    /// 1024 events behind gets `RecvError::Lagged`.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<NodeEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Batches delivered on `channel` from now on, in delivery order. Batches
+    /// on other channels, or on none, are skipped.
+    pub fn subscribe_channel(&self, channel: &str) -> ChannelSubscription<M> {
+        ChannelSubscription {
+            channel: channel.to_string(),
+            receiver: self.inner.delivered.subscribe(),
+        }
+    }
+
+    /// The highest sequence number up to which every batch from `creator`
+    /// was delivered here, counting from the first one this node saw.
+    pub async fn creator_sequence(&self, creator: &PublicKey) -> Option<u64> {
+        self.inner.creator_log.read().await.contiguous(&creator.to_hex())
+    }
+
+    /// Sequence numbers from `creator` not delivered here, though later
+    /// ones were.
+    pub async fn missing_sequences(&self, creator: &PublicKey) -> Vec<std::ops::RangeInclusive<u64>> {
+        self.inner.creator_log.read().await.missing(&creator.to_hex())
+    }
+
+    /// Takes a sample of the clock of the peer with key id `sender_id` from
+    /// a timestamp it stamped just before sending.
+    async fn record_clock(inner: &NodeInner<M>, sender_id: &str, timestamp: u64) {
+        let now = racer_core::message::now_millis();
+        inner.peers.write().await.record_clock(sender_id, timestamp, now);
+    }
+
+    /// Now as the clock of the peer with key id `key_id` reads it, to compare
+    /// against timestamps it stamped; our own clock for an unknown peer.
+    async fn peer_now(inner: &NodeInner<M>, key_id: &str) -> u64 {
+        inner.peers.read().await.peer_time(key_id, racer_core::message::now_millis())
+    }
+
+    pub async fn add_peer(&self, peer: PeerInfo) {
+        let router_addr = peer.router_address.clone();
+        let pub_addr = peer.publisher_address.clone();
+        let peer_id = peer.id.clone();
+        let key = peer.ecdsa_public.clone();
+        
+        if !self.inner.peers.write().await.add_peer(peer) {
+            return;
+        }
+        self.inner.network.set_peer_key(&peer_id, key).await;
+
+        if let Err(e) = self.inner.network.connect_to_peer(&peer_id, &router_addr).await {
+            tracing::warn!(peer_id, error = %e, "failed to connect to peer router");
+        }
+        if let Err(e) = self.inner.network.subscribe_to_peer(&pub_addr).await {
+            tracing::warn!(peer_id, error = %e, "failed to subscribe to peer");
+        }
+    }
+
+    async fn select_peers(inner: &NodeInner<M>, n: usize) -> Vec<PeerInfo> {
+        let peers = inner.peers.read().await;
+        Self::selector(inner).select(&peers, n).into_iter().cloned().collect()
+    }
+
+    async fn select_observers(inner: &NodeInner<M>, n: usize) -> Vec<PeerInfo> {
+        let peers = inner.peers.read().await;
+        Self::selector(inner).observers(&peers, n).into_iter().cloned().collect()
+    }
+
+    fn selector(inner: &NodeInner<M>) -> std::sync::MutexGuard<'_, PeerSelector> {
+        inner.selector.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Signs `bytes` with this node's identity.
+    async fn sign(inner: &NodeInner<M>, bytes: &[u8]) -> EcdsaSignature {
+        inner.signer.sign_async(bytes).await
+    }
+
+    /// Encodes `message` into a buffer that can be sent to many peers without copying.
+    fn encode<T: Serialize>(message: &T) -> Result<Bytes, NodeError> {
+        serde_json::to_vec(message)
+            .map(Bytes::from)
+            .map_err(|e| NodeError::Serialization(e.to_string()))
+    }
+
+    /// Sends to one peer of a fan-out, logging failures instead of returning
+    /// them so one unreachable or backed-up peer does not abort the rest.
+    async fn send_best_effort(inner: &NodeInner<M>, peer_id: &str, msg: Bytes, priority: Priority) {
+        let relayed = inner
+            .peers
+            .read()
+            .await
+            .get(peer_id)
+            .and_then(|peer| {
+                let framing = peer.protocol_version.map_or(Framing::Envelope, Framing::for_version);
+                peer.relay.clone().map(|relay| (relay, peer.key_id(), framing))
+            });
+        let result = match relayed {
+            Some((relay, to, framing)) => {
+                // The relay forwards the frame as is, so it must already be
+                // in the form the peer behind it reads.
+                let msg = match framing {
+                    Framing::Envelope => msg,
+                    Framing::Bare => envelope::to_bare(&msg),
+                };
+                match String::from_utf8(msg.to_vec()) {
+                    Ok(frame) => {
+                        Self::send_relay_frame(inner, &relay, RelayPayload::Forward { to, frame }, priority).await
+                    }
+                    Err(e) => Err(NodeError::Serialization(e.to_string())),
+                }
+            }
+            None => inner
+                .coalescer
+                .send(peer_id, msg, priority)
+                .await
+                .map_err(|e| NodeError::Network(e.to_string())),
+        };
+        if let Err(e) = result {
+            tracing::debug!(id = %inner.id, peer_id, error = %e, "send to peer failed");
+        }
+    }
+
+    pub async fn vector_clock(&self) -> VectorClock {
+        self.inner.vector_clock.read().await.clone()
+    }
+
+    /// Bytes and messages sent and received, per-peer link health and
+    /// active subscriptions.
+    pub async fn network_stats(&self) -> NetworkStats {
+        self.inner.network.stats().await
+    }
+
+    pub fn pipeline_stats(&self) -> PipelineStats {
+        self.inner.pipeline.snapshot()
+    }
+
+    pub async fn gossip_stats(&self) -> GossipStats {
+        let rounds = self.inner.gossip_state.round_stats().await;
+        GossipStats {
+            active_rounds: self.inner.gossip_state.active_rounds().await,
+            delivered: rounds.delivered,
+            timed_out: rounds.timed_out,
+            avg_echo_phase: rounds.avg_echo_phase(),
+            avg_ready_phase: rounds.avg_ready_phase(),
+            avg_echo_response_rate: rounds.avg_echo_response_rate(),
+            avg_ready_response_rate: rounds.avg_ready_response_rate(),
+            recent_rounds: rounds.recent().cloned().collect(),
+            store: self.inner.gossip_state.store_stats().await,
+        }
+    }
+}
+
+/// Gossip round counts since the node started. Averages are `None` until a
+/// round has finished with that phase.
+#[derive(Debug, Clone, Default)]
+pub struct GossipStats {
+    pub active_rounds: usize,
+    pub delivered: u64,
+    /// Rounds dropped undelivered after the gossip timeout.
+    pub timed_out: u64,
+    pub avg_echo_phase: Option<Duration>,
+    pub avg_ready_phase: Option<Duration>,
+    pub avg_echo_response_rate: Option<f64>,
+    pub avg_ready_response_rate: Option<f64>,
+    /// The last rounds to finish, oldest first.
+    pub recent_rounds: Vec<RoundSummary>,
+    /// Batches held for running and recently finished rounds, their size,
+    /// and how many the retention policy has evicted.
+    pub store: StoreStats,
+}
+
+/// Receiver returned by [`Node::subscribe_channel`].
+pub struct ChannelSubscription<M> {
+    channel: String,
+    receiver: broadcast::Receiver<Delivery<M>>,
+}
+
+impl<M: Clone> ChannelSubscription<M> {
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Waits for the next batch delivered on this channel. Fails with
+    /// `RecvError::Lagged` after falling more than 1024 deliveries behind.
+    pub async fn recv(&mut self) -> Result<Delivery<M>, broadcast::error::RecvError> {
+        loop {
+            let delivery = self.receiver.recv().await?;
+            if delivery.batch.channel.as_deref() == Some(self.channel.as_str()) {
+                return Ok(delivery);
+            }
+        }
+    }
+}
+
+/// Outcome of [`Node::dry_run`]. Sizes are in bytes of the JSON wire encoding.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub valid: bool,
+    pub validation_error: Option<String>,
+    pub batch_id: String,
+    pub batch_hash: String,
+    pub merkle_root: String,
+    pub message_size: usize,
+    pub envelope_size: usize,
+    pub known_peers: usize,
+    pub echo_peers: usize,
+    pub ready_peers: usize,
+    pub phase_timeout_secs: f64,
+    pub estimated_round_timeout_secs: f64,
+}
+
+/// Outcome of [`Node::import_batches`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Batches already delivered here, or earlier in the same import.
+    pub duplicates: usize,
+    /// Batches with a bad creator signature or invalid messages.
+    pub rejected: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NodeError {
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("network error: {0}")]
+    Network(String),
+    #[error("serialization error: {0}")]
+    Serialization(String),
+    #[error("crypto error: {0}")]
+    Crypto(String),
+    #[error("protocol error: {0}")]
+    Protocol(String),
+    #[error("store error: {0}")]
+    Store(String),
+    #[error("insufficient peers: have {have}, need {need}")]
+    InsufficientPeers { have: usize, need: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::discovery::{DISCOVERY_MAX_AGE, MAX_CHALLENGES_PER_KEY, MAX_PENDING_CHALLENGES};
+    use super::congestion::{DEALER_IDLE_TIMEOUT, MAX_DEALER_IDENTITIES};
+    use super::consensus::Event;
+    use super::pipeline::Frame;
+    use super::relay::RELAY_REGISTER_MAX_AGE;
+    use super::*;
+    use crate::config::OverflowPolicy;
+    use crate::crypto::EcdsaSigner;
+    use crate::network::Backpressure;
+    use crate::protocol::version::Capabilities;
+    use crate::protocol::{
+        CongestionUpdate, DeliveryReport, Echo, EchoType, EpochChange, HealthSummary, Member, MembershipChange,
+        PeerChallenge, PeerListEntry, PeerListGossip, PeerListRequest, PeerListResponse, ProtocolMessage,
+        ProtocolOffer, RelayFrame,
+    };
+    use racer_core::message::DefaultMessage;
+
+    /// [`RacerConfig::minimal`] logging deliveries under a temporary
+    /// directory instead of `logs/` in the working tree.
+    fn minimal_config() -> RacerConfig {
+        static LOG_DIR: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+        let dir = LOG_DIR.get_or_init(|| tempfile::tempdir().unwrap());
+        let mut config = RacerConfig::minimal();
+        config.logging.log_dir = dir.path().join("{node_id}").to_string_lossy().into_owned();
+        config
+    }
+
+    #[tokio::test]
+    async fn test_node_creation() {
+        let config = minimal_config();
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        assert!(!node.id().is_empty());
+        assert!(!node.is_running());
+    }
+
+    #[cfg(feature = "store")]
+    #[tokio::test]
+    async fn test_store_queries() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        assert!(matches!(node.get_batch("b-1"), Err(NodeError::Config(_))));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.node.store_path = Some(dir.path().join("delivered"));
+        config.node.sequence_path = Some(dir.path().join("sequence"));
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let batch = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        node.store().unwrap().insert("h1", &batch, 1_000).unwrap();
+
+        assert_eq!(node.get_batch(&batch.batch_id).unwrap().unwrap().hash, "h1");
+        assert_eq!(node.delivered_since(1_000).unwrap().len(), 1);
+        assert!(node.delivered_since(1_001).unwrap().is_empty());
+        assert_eq!(node.delivered_by(&node.public_key()).unwrap().len(), 1);
+        assert_eq!(node.creator_log(&node.public_key(), 1).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_plato_state_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.node.plato_state_path = Some(dir.path().join("plato.json"));
+
+        let node = Node::<DefaultMessage>::new(config.clone()).await.unwrap();
+        let mut state = node.plato_state().await;
+        state.current_latency = 7.5;
+        state.publish_frequency = 6.0;
+        assert!(node.restore_plato_state(state.clone()).await);
+        assert_eq!(node.round_timings().await.echo_timeout, Duration::from_secs_f64(7.5));
+        node.stop().await;
+
+        let restarted = Node::<DefaultMessage>::new(config.clone()).await.unwrap();
+        assert_eq!(restarted.plato_stats().await.current_latency, 7.5);
+        assert_eq!(restarted.round_timings().await.publish_interval, Duration::from_secs(6));
+
+        state.saved_at_ms -= config.plato.state_max_age().as_millis() as u64 + 1_000;
+        assert!(!restarted.restore_plato_state(state).await);
+    }
+
+    #[tokio::test]
+    async fn test_plato_trace_replays() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.plato.trace_file = Some(dir.path().join("trace.csv"));
+        let node = Node::<DefaultMessage>::new(config.clone()).await.unwrap();
+
+        node.inner.plato.write().await.record_our_latency(2.0);
+        node.inner.plato.write().await.record_backpressure();
+        node.run_plato_check().await;
+
+        let trace = std::fs::read_to_string(config.plato.trace_file.unwrap()).unwrap();
+        assert!(trace.lines().last().unwrap().contains(",throttle,"));
+        assert_eq!(crate::plato::sim::parse_trace(&trace).unwrap()[0].our_latency, Some(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_plato_timing_change_updates_gossip_timeout() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let expected = node.round_timings().await.round_timeout();
+        assert_eq!(node.inner.gossip_state.timeout().await, expected);
+
+        node.inner.gossip_state.set_timeout(Duration::from_secs(1)).await;
+        node.inner.plato.write().await.timing_changed = true;
+        node.run_plato_check().await;
+
+        assert_eq!(node.inner.gossip_state.timeout().await, expected);
+        assert!(!node.inner.plato.read().await.timing_changed);
+    }
+
+    #[tokio::test]
+    async fn test_membership_batch_is_signed() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let mut bm = Node::prepare_membership_batch(&node.inner, MembershipChange::Leave).await.unwrap();
+        assert!(bm.messages.is_empty());
+        assert!(bm.verify_creator_signature());
+        assert!(bm.membership[0].verify());
+
+        bm.membership.clear();
+        assert!(!bm.verify_creator_signature());
+    }
+
+    fn announcement(keys: &KeyPair) -> PeerDiscovery {
+        let mut pd = PeerDiscovery::new(keys.public_key(), "tcp://127.0.0.1:30101", "tcp://127.0.0.1:31101");
+        pd.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+        pd
+    }
+
+    fn answer(keys: &KeyPair, announced: &PublicKey, nonce: &str) -> PeerChallenge {
+        let mut challenge = PeerChallenge::new(announced.clone(), nonce);
+        challenge.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+        challenge
+    }
+
+    #[tokio::test]
+    async fn test_peer_discovery_requires_challenge() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+        let pd = announcement(&keys);
+        let peer_id = pd.peer_id();
+
+        let update = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap();
+        let nonce = update.challenge.expect("discovery is answered with a nonce");
+        assert!(node.inner.peers.read().await.get(&peer_id).is_none());
+
+        let unknown = answer(&keys, &keys.public_key(), "not-issued");
+        Node::inbox_peer_challenge(&node.inner, unknown).await.unwrap();
+        assert!(node.inner.peers.read().await.get(&peer_id).is_none());
+
+        Node::inbox_peer_challenge(&node.inner, answer(&keys, &keys.public_key(), &nonce)).await.unwrap();
+        assert!(node.inner.peers.read().await.get(&peer_id).is_some());
+        assert!(node.inner.challenges.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_peer_discovery_negotiates_version() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+        let mut pd = announcement(&keys);
+        pd.protocol_version = version::PROTOCOL_VERSION + 1;
+        pd.capabilities = Capabilities::DELIVERY_REPORTS | Capabilities::from_bits(1 << 30);
+        let peer_id = pd.peer_id();
+
+        let update = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap();
+        assert_eq!(update.protocol, Some(ProtocolOffer::local()));
+        let nonce = update.challenge.unwrap();
+        Node::inbox_peer_challenge(&node.inner, answer(&keys, &keys.public_key(), &nonce)).await.unwrap();
+        let peers = node.inner.peers.read().await;
+        let peer = peers.get(&peer_id).unwrap();
+        assert_eq!(peer.protocol_version, Some(version::PROTOCOL_VERSION));
+        assert_eq!(peer.capabilities, Capabilities::DELIVERY_REPORTS);
+        assert!(peer.supports(Capabilities::DELIVERY_REPORTS));
+        assert!(!peer.supports(Capabilities::RELAY));
+        drop(peers);
+
+        let mut ancient = announcement(&KeyPair::generate());
+        ancient.protocol_version = version::MIN_PROTOCOL_VERSION - 1;
+        let update = Node::inbox_peer_discovery(&node.inner, ancient).await.unwrap();
+        assert!(update.challenge.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_peer_discovery_carries_the_role() {
+        use crate::config::NodeRole;
+
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+        let mut pd = PeerDiscovery::new(keys.public_key(), "tcp://127.0.0.1:30101", "tcp://127.0.0.1:31101")
+            .with_role(NodeRole::Observer);
+        pd.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+        let peer_id = pd.peer_id();
+
+        let nonce = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap().challenge.unwrap();
+        Node::inbox_peer_challenge(&node.inner, answer(&keys, &keys.public_key(), &nonce)).await.unwrap();
+        let peers = node.inner.peers.read().await;
+        assert_eq!(peers.get(&peer_id).unwrap().role, NodeRole::Observer);
+        assert_eq!(peers.participants().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_forwarder_node_neither_submits_nor_imports() {
+        let mut config = minimal_config();
+        config.node.role = crate::config::NodeRole::Forwarder;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let result = node.submit(DefaultMessage::new()).await;
+        assert!(matches!(result, Err(NodeError::Protocol(_))));
+        assert!(node.pending_submissions().is_empty());
+        assert!(matches!(node.import_batches(Vec::new()).await, Err(NodeError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_challenge_downgrades_the_peer() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let legacy = PeerInfo::new("legacy", KeyPair::generate().public_key(), "tcp://10.0.0.9:20001", "tcp://10.0.0.9:21001");
+        node.inner.peers.write().await.add_peer(legacy);
+        assert!(node.inner.peers.read().await.get("legacy").unwrap().supports(Capabilities::DELIVERY_REPORTS));
+        assert_eq!(node.inner.network.peer_framing("legacy").await, Framing::Envelope);
+
+        // A reply from a node that predates versioning carries no offer.
+        let reply = br#"{"status":"CHALLENGE","current_latency":0.0,"recently_missed":false,"challenge":"abc"}"#;
+        let _ = Node::handle_dealer_message(&node.inner, "legacy", reply).await;
+
+        let peers = node.inner.peers.read().await;
+        let peer = peers.get("legacy").unwrap();
+        assert_eq!(peer.protocol_version, Some(version::LEGACY_PROTOCOL_VERSION));
+        assert!(!peer.supports(Capabilities::DELIVERY_REPORTS));
+        assert_eq!(node.inner.network.peer_framing("legacy").await, Framing::Bare);
+    }
+
+    #[tokio::test]
+    async fn test_peer_list_exchange() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let known = PeerInfo::new("known", KeyPair::generate().public_key(), "tcp://10.0.0.2:20001", "tcp://10.0.0.2:21001");
+        node.inner.peers.write().await.add_peer(known.clone());
+
+        let update = Node::inbox_peer_list_request(&node.inner, PeerListRequest::default()).await;
+        let list = update.peer_list.expect("request is answered with a peer list");
+        assert_eq!(list.peers.len(), 1);
+        assert_eq!(list.peers[0].router_address, known.router_address);
+
+        let listed = |key: PublicKey, port: u16| PeerListEntry {
+            ecdsa_public_key: key,
+            router_address: format!("tcp://10.0.0.3:{}", port),
+            publisher_address: format!("tcp://10.0.0.3:{}", port + 1000),
+            latency_secs: None,
+        };
+        let fresh = KeyPair::generate().public_key();
+        let list = PeerListResponse {
+            peers: vec![listed(node.public_key(), 20001), listed(fresh.clone(), 20002)],
+        };
+        assert_eq!(Node::dial_peer_list(&node.inner, "seed", list).await, 1);
+        let fresh_id = listed(fresh.clone(), 0).peer_id();
+        assert_eq!(node.inner.network.peer_key(&fresh_id).await, Some(fresh));
+    }
+
+    #[tokio::test]
+    async fn test_peer_list_gossip_from_registered_peers_only() {
+        let mut config = minimal_config();
+        config.peers.max_peers = 2;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let sender = KeyPair::generate();
+        let listed = |port: u16, latency_secs: f64| PeerListEntry {
+            ecdsa_public_key: KeyPair::generate().public_key(),
+            router_address: format!("tcp://10.0.0.4:{}", port),
+            publisher_address: format!("tcp://10.0.0.4:{}", port + 1000),
+            latency_secs: Some(latency_secs),
+        };
+        let (slow, fast) = (listed(20001, 0.5), listed(20002, 0.01));
+        let mut gossip = PeerListGossip::new(sender.public_key(), vec![slow.clone(), fast.clone()]);
+        gossip.sign(&EcdsaSigner::new(sender.signing_key().clone()));
+        assert!(gossip.verify());
+
+        Node::inbox_peer_list_gossip(&node.inner, gossip.clone()).await;
+        assert!(node.inner.network.peer_key(&fast.peer_id()).await.is_none());
+
+        let known = PeerInfo::new("sender", sender.public_key(), "tcp://10.0.0.4:20000", "tcp://10.0.0.4:21000");
+        node.inner.peers.write().await.add_peer(known);
+        Node::inbox_peer_list_gossip(&node.inner, gossip).await;
+
+        // One registered peer leaves room for one more under max_peers, taken
+        // by the lower-latency entry.
+        assert!(node.inner.network.peer_key(&fast.peer_id()).await.is_some());
+        assert!(node.inner.network.peer_key(&slow.peer_id()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_needs_min_peers() {
+        let mut config = minimal_config();
+        config.consensus.min_peers_for_submit = 1;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let result = node.submit(DefaultMessage::new()).await;
+        assert!(matches!(result, Err(NodeError::InsufficientPeers { have: 0, need: 1 })));
+        assert!(node.pending_submissions().is_empty());
+
+        let result = node.wait_for_peers(1, Duration::from_millis(20)).await;
+        assert!(matches!(result, Err(NodeError::InsufficientPeers { have: 0, need: 1 })));
+
+        let peers = Arc::clone(&node.inner.peers);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let peer = PeerInfo::new("late", KeyPair::generate().public_key(), "tcp://10.0.0.5:20001", "tcp://10.0.0.5:21001");
+            peers.write().await.add_peer(peer);
+        });
+        assert_eq!(node.wait_for_peers(1, Duration::from_secs(2)).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_relayed_peer_is_reached_through_relay() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let natted = KeyPair::generate();
+        let mut pd = PeerDiscovery::new(natted.public_key(), "tcp://192.168.0.7:20001", "tcp://203.0.113.1:21001")
+            .with_relay("tcp://203.0.113.1:20001");
+        pd.sign(&EcdsaSigner::new(natted.signing_key().clone()));
+        let peer_id = pd.peer_id();
+
+        let nonce = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap().challenge.unwrap();
+        Node::inbox_peer_challenge(&node.inner, answer(&natted, &natted.public_key(), &nonce)).await.unwrap();
+        assert!(node.inner.peers.read().await.get(&peer_id).unwrap().is_relayed());
+
+        let links: Vec<_> = node.inner.network.stats().await.peers.into_iter().map(|p| p.peer_id).collect();
+        assert_eq!(links, vec!["relay-203.0.113.1:20001"]);
+
+        // Its router cannot be dialed, so it is not handed out in peer lists.
+        let update = Node::inbox_peer_list_request(&node.inner, PeerListRequest::default()).await;
+        assert!(update.peer_list.unwrap().peers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_dealer_replies_are_dropped() {
+        let mut config = minimal_config();
+        config.network.max_frame_bytes = 16;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let reply = serde_json::to_vec(&CongestionUpdate::ok()).unwrap();
+        assert!(reply.len() > 16);
+        Node::handle_dealer_message(&node.inner, "peer", &reply).await.unwrap();
+        assert_eq!(node.pipeline_stats().oversized, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dealer_identities_are_bounded_and_expire() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        for i in 0..=MAX_DEALER_IDENTITIES {
+            Node::touch_dealer(&node.inner, &(i as u64).to_be_bytes()).await;
+        }
+        {
+            let dealers = node.inner.dealer_identities.read().await;
+            assert_eq!(dealers.len(), MAX_DEALER_IDENTITIES);
+            assert!(!dealers.contains_key(&0u64.to_be_bytes()[..]));
+        }
+
+        let idle = Instant::now().checked_sub(DEALER_IDLE_TIMEOUT).unwrap();
+        node.inner.dealer_identities.write().await.values_mut().for_each(|seen| *seen = idle);
+        Node::broadcast_congestion_update(&node.inner).await.unwrap();
+        assert!(node.inner.dealer_identities.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_relay_registers_clients() {
+        let client = KeyPair::generate();
+        let mut register = RelayFrame::new(client.public_key(), RelayPayload::Register);
+        register.sign(&EcdsaSigner::new(client.signing_key().clone()));
+        let client_id = register.sender_id();
+
+        let plain = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        Node::inbox_relay(&plain.inner, b"client".to_vec(), register.clone()).await.unwrap();
+        assert!(plain.inner.relay_clients.read().await.is_empty());
+
+        let mut config = minimal_config();
+        config.node.relay = true;
+        let relay = Node::<DefaultMessage>::new(config).await.unwrap();
+        Node::inbox_relay(&relay.inner, b"client".to_vec(), register.clone()).await.unwrap();
+        let registered = |identity: &[u8]| (identity.to_vec(), register.timestamp);
+        assert_eq!(relay.inner.relay_clients.read().await.get(&client_id), Some(&registered(b"client")));
+
+        // Replayed from another connection, it does not move the client.
+        Node::inbox_relay(&relay.inner, b"replayer".to_vec(), register.clone()).await.unwrap();
+        assert_eq!(relay.inner.relay_clients.read().await.get(&client_id), Some(&registered(b"client")));
+    }
+
+    #[tokio::test]
+    async fn test_relay_refuses_stale_registrations() {
+        let client = KeyPair::generate();
+        let signer = EcdsaSigner::new(client.signing_key().clone());
+        let mut config = minimal_config();
+        config.node.relay = true;
+        let relay = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let mut stale = RelayFrame::new(client.public_key(), RelayPayload::Register);
+        stale.timestamp -= RELAY_REGISTER_MAX_AGE.as_millis() as u64 + 1_000;
+        stale.sign(&signer);
+        Node::inbox_relay(&relay.inner, b"client".to_vec(), stale).await.unwrap();
+        assert!(relay.inner.relay_clients.read().await.is_empty());
+
+        let mut older = RelayFrame::new(client.public_key(), RelayPayload::Register);
+        older.timestamp -= 1_000;
+        older.sign(&signer);
+        let mut newer = RelayFrame::new(client.public_key(), RelayPayload::Register);
+        newer.sign(&signer);
+        let client_id = newer.sender_id();
+        Node::inbox_relay(&relay.inner, b"new".to_vec(), newer).await.unwrap();
+        Node::inbox_relay(&relay.inner, b"old".to_vec(), older).await.unwrap();
+        assert_eq!(relay.inner.relay_clients.read().await.get(&client_id).unwrap().0, b"new".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_replayed_peer_discovery_is_not_added() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let victim = KeyPair::generate();
+        let attacker = KeyPair::generate();
+        let pd = announcement(&victim);
+        let peer_id = pd.peer_id();
+
+        // The attacker replays a genuine announcement but cannot sign the nonce
+        // for the victim's key, and answering with its own key does not match.
+        let update = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap();
+        let nonce = update.challenge.unwrap();
+        let forged = answer(&attacker, &attacker.public_key(), &nonce);
+        Node::inbox_peer_challenge(&node.inner, forged).await.unwrap();
+        assert!(node.inner.peers.read().await.get(&peer_id).is_none());
+
+        // A used nonce cannot be answered again.
+        Node::inbox_peer_challenge(&node.inner, answer(&victim, &victim.public_key(), &nonce)).await.unwrap();
+        assert!(node.inner.peers.read().await.get(&peer_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_peer_discovery_rejects_pinned_key_mismatch() {
+        let mut config = minimal_config();
+        config
+            .peers
+            .pinned_keys
+            .insert("tcp://127.0.0.1:30101".into(), KeyPair::generate().public_key());
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let update = Node::inbox_peer_discovery(&node.inner, announcement(&KeyPair::generate())).await.unwrap();
+        assert!(update.challenge.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_peer_discovery_challenges_are_limited_per_key() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+        for _ in 0..MAX_CHALLENGES_PER_KEY {
+            let update = Node::inbox_peer_discovery(&node.inner, announcement(&keys)).await.unwrap();
+            assert!(update.challenge.is_some());
+        }
+        let update = Node::inbox_peer_discovery(&node.inner, announcement(&keys)).await.unwrap();
+        assert!(update.challenge.is_none());
+
+        // A full table makes room for newcomers by dropping its oldest nonce.
+        let first = node.inner.challenges.read().await.keys().cloned().collect::<Vec<_>>();
+        for _ in 0..MAX_PENDING_CHALLENGES {
+            let update = Node::inbox_peer_discovery(&node.inner, announcement(&KeyPair::generate())).await.unwrap();
+            assert!(update.challenge.is_some());
+        }
+        let challenges = node.inner.challenges.read().await;
+        assert_eq!(challenges.len(), MAX_PENDING_CHALLENGES);
+        assert!(first.iter().all(|nonce| !challenges.contains_key(nonce)));
+    }
+
+    #[tokio::test]
+    async fn test_stale_peer_discovery_is_refused() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+        let mut pd = announcement(&keys);
+        pd.timestamp -= DISCOVERY_MAX_AGE.as_millis() as u64 + 1_000;
+        pd.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+
+        let update = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap();
+        assert!(update.challenge.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_health_summaries_only_count_from_verified_peers() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+        let summary = HealthSummary::new(keys.public_key(), 60, 0, 10, 0.0);
+        Node::inbox_health_summary(&node.inner, summary.clone()).await;
+        assert_eq!(node.cluster_health().await.reporting_peers, 0);
+
+        let pd = announcement(&keys);
+        let nonce = Node::inbox_peer_discovery(&node.inner, pd).await.unwrap().challenge.unwrap();
+        Node::inbox_peer_challenge(&node.inner, answer(&keys, &keys.public_key(), &nonce)).await.unwrap();
+        Node::inbox_health_summary(&node.inner, summary).await;
+        assert_eq!(node.cluster_health().await.reporting_peers, 1);
+    }
+
+    #[tokio::test]
+    async fn test_epoch_change_updates_registry() {
+        let mut config = minimal_config();
+        config.consensus.ready_threshold_pct = Some(0.5);
+        config.consensus.feedback_threshold_pct = Some(0.75);
+        config.consensus.delivery_threshold_pct = Some(1.0);
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let members: Vec<Member> = (0..4)
+            .map(|i| Member {
+                key: KeyPair::generate().public_key(),
+                router_address: format!("tcp://127.0.0.1:{}", 30001 + i),
+                publisher_address: format!("tcp://127.0.0.1:{}", 31001 + i),
+            })
+            .collect();
+
+        let change = EpochChange {
+            epoch: 1,
+            joined: members.clone(),
+            left: Vec::new(),
+        };
+        Node::apply_epoch_change(&node.inner, change).await;
+        assert_eq!(Node::round_thresholds(&node.inner, None, Priority::Normal).await.delivery, 4);
+
+        let change = EpochChange {
+            epoch: 2,
+            joined: Vec::new(),
+            left: vec![members[0].key.clone()],
+        };
+        Node::apply_epoch_change(&node.inner, change).await;
+        assert_eq!(node.inner.peers.read().await.len(), 3);
+        assert_eq!(Node::round_thresholds(&node.inner, None, Priority::Normal).await.delivery, 3);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_batch_prunes_vector_clock() {
+        let mut config = minimal_config();
+        config.consensus.vector_clock_max_entries = 2;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        {
+            let mut vc = node.inner.vector_clock.write().await;
+            for (peer, time) in [("peer-a", 5), ("peer-b", 7), ("peer-c", 9)] {
+                vc.set(peer, time);
+            }
+        }
+
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert_eq!(bm.vector_clock.len(), 2);
+        assert_eq!(bm.vector_clock.get(node.id()), 1);
+        assert_eq!(bm.vector_clock.get("peer-c"), 9);
+        assert_eq!(node.vector_clock().await, bm.vector_clock);
+    }
+
+    #[tokio::test]
+    async fn test_round_without_peers_reports_echo_timeout() {
+        let mut config = minimal_config();
+        config.plato.minimum_latency_secs = 0.1;
+        config.plato.target_latency_secs = 0.2;
+        config.plato.min_phase_timeout_secs = 0.1;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let mut events = node.subscribe_events();
+
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        let hash = bm.compute_hash();
+        Node::gossip_inner(&node.inner, bm.clone()).await.unwrap();
+
+        assert_eq!(
+            events.try_recv().unwrap(),
+            NodeEvent::RoundStarted { hash: hash.clone(), batch_id: bm.batch_id }
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            NodeEvent::RoundTimedOut { hash, phase: RoundPhase::Echo }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submit_queues_the_round() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+
+        let batch_id = node.submit(DefaultMessage::new()).await.unwrap();
+        let pending = node.pending_submissions();
+        assert_eq!((pending[0].batch_id.as_str(), pending[0].attempts), (batch_id.as_str(), 0));
+        assert_eq!(node.inner.scheduler.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_submission_stays_pending() {
+        let mut config = minimal_config();
+        config.plato.minimum_latency_secs = 0.1;
+        config.plato.target_latency_secs = 0.2;
+        config.plato.min_phase_timeout_secs = 0.1;
+        config.node.submit_max_attempts = 1;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let _publisher = node.spawn_publisher();
+
+        let (batch_id, delivered) = node.submit_and_wait(DefaultMessage::new()).await.unwrap();
+        assert!(!delivered);
+        let pending = node.pending_submissions();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].batch_id.as_str(), pending[0].attempts), (batch_id.as_str(), 1));
+
+        let mut events = node.subscribe_events();
+        Node::retry_submissions(&node.inner);
+        assert!(node.pending_submissions().is_empty());
+        assert_eq!(
+            events.try_recv().unwrap(),
+            NodeEvent::SubmissionExpired { hash: pending[0].hash.clone(), batch_id, attempts: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expire_rounds_reports_relayed_timeouts() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let mut events = node.subscribe_events();
+        node.inner.gossip_state.set_timeout(Duration::ZERO).await;
+        for hash in ["relayed", "own"] {
+            node.inner.gossip_state.rounds(hash).write().await.start_round(hash);
+        }
+        let timed_out = Event::PhaseTimedOut { hash: "own".into(), phase: RoundPhase::Echo };
+        node.inner.consensus.handle(&mut *node.inner.gossip_state.rounds("own").write().await, timed_out);
+        node.inner.topics.hold_round("relayed").await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert_eq!(Node::expire_rounds(&node.inner).await, 2);
+        assert!(!node.inner.topics.holds_round("relayed"));
+        assert_eq!(node.inner.gossip_state.active_rounds().await, 0);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            NodeEvent::RoundTimedOut { hash: "relayed".into(), phase: RoundPhase::Echo }
+        );
+        assert!(events.try_recv().is_err());
+        assert!(node.inner.plato.read().await.recently_missed_delivery());
+        assert_eq!(Node::expire_rounds(&node.inner).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retention_holds_timed_out_batches() {
+        let mut config = minimal_config();
+        config.consensus.timed_out_retention_secs = 60;
+        config.consensus.delivered_retention_secs = 0;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        node.inner.gossip_state.set_timeout(Duration::ZERO).await;
+        let mut hashes = Vec::new();
+        for _ in 0..2 {
+            let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+            let hash = bm.compute_hash();
+            node.inner.gossip_state.rounds(&hash).write().await.start_round(hash.as_str());
+            node.inner.gossip_state.store_message(hash.clone(), bm).await;
+            hashes.push(hash);
+        }
+        node.inner.gossip_state.mark_delivered(&hashes[0]).await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert_eq!(Node::expire_rounds(&node.inner).await, 1);
+        assert_eq!(node.inner.gossip_state.evict().await, 1);
+        let store = node.gossip_stats().await.store;
+        assert_eq!((store.batches, store.timed_out, store.evicted), (1, 1, 1));
+        assert!(store.bytes > 0);
+        assert!(node.inner.gossip_state.has_message(&hashes[1]).await);
+    }
+
+    #[tokio::test]
+    async fn test_external_signer_is_node_identity() {
+        let device = KeyPair::generate();
+        let node = Node::<DefaultMessage>::with_signer(minimal_config(), Arc::new(device.clone()))
+            .await
+            .unwrap();
+        assert_eq!(node.public_key(), device.public_key());
+
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert_eq!(bm.creator_ecdsa, device.public_key());
+        assert!(bm.verify_creator_signature());
+        assert!(bm.verify_sender_signature());
+
+        let mut config = minimal_config();
+        config.node.encryption = EncryptionMode::Opportunistic;
+        assert!(matches!(
+            Node::<DefaultMessage>::with_signer(config, Arc::new(device)).await,
+            Err(NodeError::Config(_))
+        ));
+    }
+
+    #[cfg(feature = "bls")]
+    #[tokio::test]
+    async fn test_bls_signatures_come_from_the_signer() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert!(bm.creator_bls.is_some() && bm.aggregated_signature.is_some());
+        assert!(bm.verify_aggregated_signature());
+
+        let device = EcdsaSigner::new(KeyPair::generate().signing_key().clone());
+        let node = Node::<DefaultMessage>::with_signer(minimal_config(), Arc::new(device)).await.unwrap();
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert!(bm.creator_bls.is_none() && bm.aggregated_signature.is_none());
+        assert!(bm.verify_creator_signature());
+    }
+
+    #[tokio::test]
+    async fn test_batch_carries_signed_priority() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+
+        let mut bm = Node::prepare_batch(&node.inner, None, Priority::High, DefaultMessage::new()).await.unwrap();
+        assert_eq!(bm.priority, Priority::High);
+        assert!(bm.verify_creator_signature());
+
+        bm.priority = Priority::Low;
+        assert!(!bm.verify_creator_signature());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_ordered_receives_released_batches() {
+        let mut config = minimal_config();
+        config.consensus.sequencing_stability_secs = 0.0;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let mut ordered = node.subscribe_ordered();
+
+        node.start().await.unwrap();
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        let now = racer_core::message::now_millis();
+        node.inner.sequencer.write().await.insert(bm.compute_hash(), bm.clone(), now);
+
+        let received = tokio::time::timeout(Duration::from_secs(2), ordered.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.sequence, 0);
+        assert_eq!(received.batch.batch_id, bm.batch_id);
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_channel_batches_are_signed_and_scoped() {
+        let mut config = minimal_config();
+        config.consensus.channels.insert(
+            "alerts".into(),
+            crate::config::ChannelConfig::counts(1, 2, 3),
+        );
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let mut bm = Node::prepare_batch(&node.inner, Some("alerts"), Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert_eq!(bm.channel.as_deref(), Some("alerts"));
+        assert!(bm.verify_creator_signature());
+        assert_eq!(Node::round_thresholds(&node.inner, bm.channel.as_deref(), bm.priority).await.delivery, 3);
+        assert_eq!(Node::round_thresholds(&node.inner, None, Priority::Normal).await.delivery, 6);
+
+        bm.channel = Some("telemetry".into());
+        assert!(!bm.verify_creator_signature());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_channel_filters_deliveries() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let mut alerts = node.subscribe_channel("alerts");
+        let (deliveries, _handle) = node.spawn_deliver();
+
+        for channel in [None, Some("telemetry"), Some("alerts")] {
+            let batch = Node::prepare_batch(&node.inner, channel, Priority::Normal, DefaultMessage::new()).await.unwrap();
+            let hash = batch.compute_hash();
+            deliveries.send(Delivery { hash, batch }).await.unwrap();
+        }
+
+        let received = tokio::time::timeout(Duration::from_secs(2), alerts.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.batch.channel.as_deref(), Some("alerts"));
+        assert!(alerts.receiver.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_numbered_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.node.sequence_path = Some(dir.path().join("sequence"));
+
+        let node = Node::<DefaultMessage>::new(config.clone()).await.unwrap();
+        for expected in 1..=2 {
+            let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+            assert_eq!(bm.sequence, expected);
+        }
+        let preview = node.dry_run(DefaultMessage::new()).await.unwrap();
+        assert!(preview.valid);
+        drop(node);
+
+        let restarted = Node::<DefaultMessage>::new(config).await.unwrap();
+        let bm = Node::prepare_batch(&restarted.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert_eq!(bm.sequence, 3);
+        assert!(bm.verify_creator_signature());
+    }
+
+    #[tokio::test]
+    async fn test_sequence_gap_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.node.sequence_path = Some(dir.path().join("sequence"));
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let creator = Node::<DefaultMessage>::new(config).await.unwrap();
+        let mut events = node.subscribe_events();
+        let (deliveries, handle) = node.spawn_deliver();
+
+        let mut batches = Vec::new();
+        for _ in 0..3 {
+            batches.push(Node::prepare_batch(&creator.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap());
+        }
+        for index in [0, 2, 1] {
+            let batch = batches[index].clone();
+            deliveries.send(Delivery { hash: batch.compute_hash(), batch }).await.unwrap();
+        }
+        drop(deliveries);
+        handle.await.unwrap();
+
+        assert_eq!(
+            events.try_recv().unwrap(),
+            NodeEvent::SequenceGap { creator: creator.public_key().to_hex(), from: 2, to: 2 }
+        );
+        let stats = node.pipeline_stats();
+        assert_eq!((stats.sequence_gaps, stats.reordered), (1, 1));
+        assert_eq!(node.creator_sequence(&creator.public_key()).await, Some(3));
+        assert!(node.missing_sequences(&creator.public_key()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retried_message_is_delivered_once() {
+        let mut config = minimal_config();
+        config.consensus.dedup_window_secs = 60;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let (sink, mut delivered) = sink::ChannelSink::new(8);
+        node.add_sink(sink);
+        let (deliveries, _handle) = node.spawn_deliver();
+
+        // A retry: the same message in a new batch, with a different hash.
+        let message = DefaultMessage { timestamp: 42, padding: 0 };
+        for channel in [None, Some("retry")] {
+            let batch = Node::prepare_batch(&node.inner, channel, Priority::Normal, message.clone()).await.unwrap();
+            let hash = batch.compute_hash();
+            deliveries.send(Delivery { hash, batch }).await.unwrap();
+        }
+        drop(deliveries);
+
+        let first = tokio::time::timeout(Duration::from_secs(2), delivered.recv()).await.unwrap().unwrap();
+        assert_eq!(first.channel, None);
+        assert!(tokio::time::timeout(Duration::from_millis(100), delivered.recv()).await.is_err());
+        assert_eq!(node.pipeline_stats().app_duplicates, 1);
+        assert_eq!(node.pipeline_stats().delivered, 1);
+        // The total order still holds both batches, as every node agreed on them.
+        assert_eq!(node.inner.sequencer.read().await.pending(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_forwarder_sequences_but_hands_nothing_to_the_app() {
+        let mut config = minimal_config();
+        config.node.role = crate::config::NodeRole::Forwarder;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let creator = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let (sink, mut delivered) = sink::ChannelSink::new(8);
+        node.add_sink(sink);
+        let (deliveries, handle) = node.spawn_deliver();
+
+        let batch = Node::prepare_batch(&creator.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        deliveries.send(Delivery { hash: batch.compute_hash(), batch }).await.unwrap();
+        drop(deliveries);
+        handle.await.unwrap();
+
+        assert!(delivered.try_recv().is_err());
+        assert_eq!(node.pipeline_stats().delivered, 0);
+        assert_eq!(node.inner.sequencer.read().await.pending(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validator_rejects_before_echo() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        node.set_validator(|batch: &BatchedMessages<DefaultMessage>| {
+            let registered = batch.messages.iter().all(|m| m.padding == 0);
+            async move {
+                if registered {
+                    Ok(())
+                } else {
+                    Err(racer_core::ValidationError::custom("padding", "unregistered device"))
+                }
+            }
+        });
+
+        for padding in [1, 0] {
+            let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::with_padding(padding))
+                .await
+                .unwrap();
+            let hash = bm.compute_hash();
+            let _ = Node::inbox_batched(&node.inner, bm).await;
+            let stored = node.inner.gossip_state.get_message(&hash).await.is_some();
+            assert_eq!(stored, padding == 0, "padding {}", padding);
+        }
+        assert_eq!(node.pipeline_stats().rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_uses_creator_clock() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let creator = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let peer = PeerInfo::new("creator", creator.public_key(), "tcp://127.0.0.1:1", "tcp://127.0.0.1:2");
+        let key_id = peer.key_id();
+        node.inner.peers.write().await.add_peer(peer);
+
+        // The creator's clock runs 10s behind ours; its batch is 1s old.
+        let now = racer_core::message::now_millis();
+        let mut bm = Node::prepare_batch(&creator.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        bm.created_at = now - 11_000;
+        bm.ttl_ms = Some(5_000);
+        let hash = bm.compute_hash();
+
+        let _ = Node::inbox_batched(&node.inner, bm.clone()).await;
+        assert_eq!(node.pipeline_stats().expired, 1);
+
+        let echo = Echo::new(EchoType::EchoSubscribe, "other", creator.public_key());
+        let echo = Echo { timestamp: now - 10_000, ..echo };
+        Node::inbox_echo(&node.inner, echo).await.unwrap();
+        let skew = node.inner.peers.read().await.find_by_key_id(&key_id).unwrap().clock_skew_ms().unwrap();
+        assert!((-10_100..=-9_900).contains(&skew), "{}", skew);
+
+        let _ = Node::inbox_batched(&node.inner, bm).await;
+        assert_eq!(node.pipeline_stats().expired, 1);
+        assert!(node.inner.gossip_state.get_message(&hash).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_equivocating_creator_is_caught() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.node.sequence_path = Some(dir.path().join("sequence"));
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let creator = Node::<DefaultMessage>::new(config).await.unwrap();
+        let mut events = node.subscribe_events();
+
+        let honest = Node::prepare_batch(&creator.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        let mut conflicting =
+            Node::prepare_batch(&creator.inner, None, Priority::Normal, DefaultMessage::with_padding(1)).await.unwrap();
+        conflicting.sequence = honest.sequence;
+        Node::sign_batch(&creator.inner, &mut conflicting).await;
+
+        let _ = Node::inbox_batched(&node.inner, honest).await;
+        let _ = Node::inbox_batched(&node.inner, conflicting.clone()).await;
+        assert!(node.inner.gossip_state.get_message(&conflicting.compute_hash()).await.is_none());
+        assert_eq!(node.pipeline_stats().equivocations, 1);
+        assert_eq!(node.equivocators(), vec![creator.public_key()]);
+
+        let event = events.try_recv().unwrap();
+        let NodeEvent::Equivocation { creator: key, first, second } = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(key, creator.public_key().to_hex());
+        let evidence = equivocation::Equivocation {
+            creator: creator.public_key(),
+            first: serde_json::from_value(first).unwrap(),
+            second: serde_json::from_value(second).unwrap(),
+        };
+        assert!(evidence.verify());
+
+        // Later batches from the creator are refused until it is pardoned.
+        let later = Node::prepare_batch(&creator.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        let _ = Node::inbox_batched(&node.inner, later.clone()).await;
+        assert!(node.inner.gossip_state.get_message(&later.compute_hash()).await.is_none());
+        assert!(node.pardon(&creator.public_key()));
+        let _ = Node::inbox_batched(&node.inner, later.clone()).await;
+        assert!(node.inner.gossip_state.get_message(&later.compute_hash()).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_creator_is_not_echoed() {
+        let mut config = minimal_config();
+        config.consensus.allowed_creators = vec![KeyPair::generate().public_key().to_hex()];
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let bm = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        let hash = bm.compute_hash();
+        let _ = Node::inbox_batched(&node.inner, bm.clone()).await;
+        assert!(node.inner.gossip_state.get_message(&hash).await.is_none());
+        assert_eq!(node.pipeline_stats().rejected, 1);
+
+        let own_key = node.inner.signer.public_key();
+        node.set_authorization_policy(move |creator: &PublicKey, _: &DefaultMessage| *creator == own_key);
+        let _ = Node::inbox_batched(&node.inner, bm).await;
+        assert!(node.inner.gossip_state.get_message(&hash).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_submission_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = minimal_config();
+        config.node.sequence_path = Some(dir.path().join("sequence"));
+        config.consensus.max_batch_bytes = 16;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let result = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await;
+        assert!(matches!(result, Err(NodeError::Protocol(reason)) if reason.contains("max_batch_bytes")));
+        // The refused batch took no sequence number.
+        assert_eq!(node.inner.sequence.peek(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_frames_are_quarantined() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let (deliveries, _handle) = node.spawn_deliver();
+        let (frames, _stages) = node.spawn_pipeline(deliveries);
+        let frame = Frame::Router {
+            identity: vec![1],
+            content: bytes::Bytes::from_static(b"{\"message_type\":\"Echo\"}"),
+        };
+        frames.send(frame).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while node.quarantined().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let quarantined = node.quarantined();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].source, "router");
+        assert!(quarantined[0].reason.starts_with("decode error"), "{}", quarantined[0].reason);
+        assert_eq!(node.clear_quarantine(), 1);
+        assert!(node.quarantined().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_saturated_send_queue_throttles_plato() {
+        let mut config = minimal_config();
+        config.node.send_queue_capacity = 1;
+        config.node.send_overflow = OverflowPolicy::DropNew;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        let mut events = node.subscribe_events();
+        let _handle = node.spawn_peer_events();
+
+        // Nothing listens there, so the queue never drains.
+        node.inner.network.connect_to_peer("gone", "tcp://127.0.0.1:27385").await.unwrap();
+        node.inner.network.send_to_peer("gone", b"one".to_vec()).await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap();
+        assert_eq!(
+            event,
+            NodeEvent::Backpressure(Backpressure::QueueSaturated { peer_id: "gone".into(), capacity: 1 })
+        );
+        assert_eq!(node.network_stats().await.peers[0].saturations, 1);
+
+        let before = node.inner.plato.read().await.current_latency();
+        node.run_plato_check().await;
+        assert!(node.inner.plato.read().await.current_latency() > before);
+    }
+
+    #[tokio::test]
+    async fn test_encoded_echo_is_signed() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+
+        let msg = Node::encode_echo(&node.inner, EchoType::ReadySubscribe, "h1").await.unwrap();
+        match serde_json::from_slice::<ProtocolMessage<DefaultMessage>>(&msg).unwrap() {
+            ProtocolMessage::Echo(echo) => {
+                assert_eq!(echo.echo_type, EchoType::ReadySubscribe);
+                assert_eq!(echo.topic, "h1");
+                assert!(echo.verify());
+            }
+            other => panic!("expected Echo, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seeded_nodes_pick_the_same_peers() {
+        let mut config = minimal_config();
+        config.node.rng_seed = Some(42);
+        let peers: Vec<PeerInfo> = (0..10)
+            .map(|i| {
+                let id = format!("peer-{}", i);
+                PeerInfo::new(&id, KeyPair::generate().public_key(), "tcp://127.0.0.1:1", "tcp://127.0.0.1:2")
+            })
+            .collect();
+
+        let mut picks = Vec::new();
+        for _ in 0..2 {
+            let node = Node::<DefaultMessage>::new(config.clone()).await.unwrap();
+            for peer in &peers {
+                node.inner.peers.write().await.add_peer(peer.clone());
+            }
+            for _ in 0..3 {
+                let picked: Vec<String> = Node::select_peers(&node.inner, 4).await.into_iter().map(|peer| peer.id).collect();
+                picks.push(picked);
+            }
+        }
+        assert_eq!(picks[..3], picks[3..]);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_reports_are_tallied() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let peer = KeyPair::generate();
+        let stranger = KeyPair::generate();
+        node.inner.peers.write().await.add_peer(PeerInfo::new(
+            "peer",
+            peer.public_key(),
+            "tcp://127.0.0.1:1",
+            "tcp://127.0.0.1:2",
+        ));
+        let batch = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        assert_eq!(node.delivery_reports(&batch.batch_id).unwrap().reports, 0);
+
+        for (keys, elapsed_ms) in [(&peer, 180), (&stranger, 90), (&peer, 5)] {
+            let mut report = DeliveryReport::new(keys.public_key(), &batch.batch_id, "h", elapsed_ms, 4);
+            report.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+            Node::inbox_delivery_report(&node.inner, report).await;
+        }
+        let latency = node.delivery_reports(&batch.batch_id).unwrap();
+        assert_eq!(latency.reports, 1);
+        assert_eq!((latency.min_ms, latency.min_ready_count), (180, 4));
+        assert!(node.delivery_reports("someone-elses").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_low_power_node() {
+        let mut config = minimal_config();
+        config.node.low_power = true;
+        config.node.router_bind = "local://low-power/router".into();
+        config.node.publisher_bind = "local://low-power/pub".into();
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        assert_eq!(node.inner.plato.read().await.estimator_name(), "ewma");
+
+        node.start().await.unwrap();
+        assert!(node.router_handle.read().await.is_some());
+        assert!(node.subscriber_handle.read().await.is_none());
+        assert!(node.dealer_handle.read().await.is_none());
+        assert!(node.inner.relays.read().await.is_some());
+
+        let batch = Node::prepare_batch(&node.inner, None, Priority::Normal, DefaultMessage::new()).await.unwrap();
+        Node::relay(&node.inner, batch).await;
+
+        node.stop().await;
+        assert!(node.inner.relays.read().await.is_none());
+        assert!(node.relayer_handle.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_node_start_stop() {
+        let config = minimal_config();
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        node.start().await.unwrap();
+        assert!(node.is_running());
+
+        node.stop().await;
+        assert!(!node.is_running());
+    }
+}
@@ -0,0 +1,312 @@
+//! Local submissions awaiting delivery.
+//!
+//! A batch submitted while the node is partitioned can time out before any
+//! peer echoes it. The outbox holds every local submission until a round for
+//! it delivers, gossiping it again with exponential backoff in between, and
+//! drops it once the [`RetryPolicy`] gives up on it or the batch's own TTL
+//! passes. With `node.outbox_path` set and the `store` feature, pending
+//! submissions are kept on disk and retried after a restart.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::BatchedMessages;
+use crate::Message;
+
+/// A local submission not yet delivered; see
+/// [`Node::pending_submissions`](super::Node::pending_submissions).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingSubmission {
+    pub hash: String,
+    pub batch_id: String,
+    /// Epoch millis at which it was submitted.
+    pub submitted_at: u64,
+    /// Rounds gossiped for it so far.
+    pub attempts: u32,
+    /// Epoch millis after which it is gossiped again, unless a round for it
+    /// is running.
+    pub next_attempt_at: u64,
+}
+
+/// When undelivered submissions are gossiped again, and when they are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Delay after the first failed round; doubles per round up to `max`.
+    pub initial: Duration,
+    pub max: Duration,
+    /// Age at which a submission is dropped.
+    pub expiry: Option<Duration>,
+    /// Rounds after which a submission is dropped.
+    pub max_attempts: Option<u32>,
+}
+
+impl RetryPolicy {
+    /// The delay after `attempts` failed rounds.
+    fn delay(&self, attempts: u32) -> Duration {
+        let doublings = attempts.saturating_sub(1).min(20);
+        self.initial.saturating_mul(1 << doublings).min(self.max)
+    }
+
+    fn gives_up(&self, pending: &PendingSubmission, now: u64) -> bool {
+        let age = Duration::from_millis(now.saturating_sub(pending.submitted_at));
+        self.expiry.is_some_and(|expiry| age >= expiry)
+            || self.max_attempts.is_some_and(|max| pending.attempts >= max)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<M> {
+    pending: PendingSubmission,
+    batch: BatchedMessages<M>,
+    /// A round for it is running.
+    #[serde(skip)]
+    running: bool,
+}
+
+/// What [`Outbox::take_due`] found for one submission.
+pub(crate) enum Due<M> {
+    Retry(BatchedMessages<M>),
+    Expired(PendingSubmission),
+}
+
+pub(crate) struct Outbox<M> {
+    policy: RetryPolicy,
+    entries: Mutex<BTreeMap<String, Entry<M>>>,
+    #[cfg(feature = "store")]
+    tree: Option<sled::Tree>,
+}
+
+impl<M: Message> Outbox<M> {
+    /// An outbox kept in memory only.
+    pub(crate) fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            entries: Mutex::new(BTreeMap::new()),
+            #[cfg(feature = "store")]
+            tree: None,
+        }
+    }
+
+    /// An outbox persisted at `path`, with the submissions still pending
+    /// there due at once.
+    #[cfg(feature = "store")]
+    pub(crate) fn open(path: impl AsRef<std::path::Path>, policy: RetryPolicy) -> Result<Self, crate::store::StoreError> {
+        use crate::store::StoreError;
+
+        let tree = sled::open(path)?.open_tree("pending")?;
+        let mut entries = BTreeMap::new();
+        for item in tree.iter() {
+            let (key, value) = item?;
+            let mut entry: Entry<M> =
+                serde_json::from_slice(&value).map_err(|e| StoreError::Corrupt(e.to_string()))?;
+            entry.pending.next_attempt_at = 0;
+            entries.insert(String::from_utf8_lossy(&key).into_owned(), entry);
+        }
+        Ok(Self {
+            policy,
+            entries: Mutex::new(entries),
+            tree: Some(tree),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Entry<M>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes `entry` through to disk, if persisted.
+    fn persist(&self, entry: &Entry<M>) {
+        #[cfg(feature = "store")]
+        if let Some(tree) = &self.tree {
+            let result = serde_json::to_vec(entry)
+                .map_err(|e| e.to_string())
+                .and_then(|value| {
+                    tree.insert(entry.pending.hash.as_bytes(), value).map_err(|e| e.to_string())?;
+                    tree.flush().map(|_| ()).map_err(|e| e.to_string())
+                });
+            if let Err(e) = result {
+                tracing::warn!(hash = %entry.pending.hash, error = %e, "failed to persist pending submission");
+            }
+        }
+        #[cfg(not(feature = "store"))]
+        let _ = entry;
+    }
+
+    fn unpersist(&self, hash: &str) {
+        #[cfg(feature = "store")]
+        if let Some(tree) = &self.tree {
+            if let Err(e) = tree.remove(hash.as_bytes()) {
+                tracing::warn!(hash, error = %e, "failed to remove pending submission");
+            }
+        }
+        #[cfg(not(feature = "store"))]
+        let _ = hash;
+    }
+
+    /// Records a submission whose first round is about to start.
+    pub(crate) fn insert(&self, hash: &str, batch: &BatchedMessages<M>, now: u64) {
+        let entry = Entry {
+            pending: PendingSubmission {
+                hash: hash.to_string(),
+                batch_id: batch.batch_id.clone(),
+                submitted_at: now,
+                attempts: 0,
+                next_attempt_at: now,
+            },
+            batch: batch.clone(),
+            running: true,
+        };
+        self.persist(&entry);
+        self.lock().insert(hash.to_string(), entry);
+    }
+
+    /// Schedules the next retry of `hash` after a round that did not deliver.
+    pub(crate) fn finish_attempt(&self, hash: &str, now: u64) {
+        let mut entries = self.lock();
+        let Some(entry) = entries.get_mut(hash) else {
+            return;
+        };
+        entry.running = false;
+        entry.pending.attempts += 1;
+        entry.pending.next_attempt_at = now + self.policy.delay(entry.pending.attempts).as_millis() as u64;
+        self.persist(entry);
+    }
+
+    /// Forgets `hash` once delivered. Returns whether it was pending.
+    pub(crate) fn remove(&self, hash: &str) -> bool {
+        let removed = self.lock().remove(hash).is_some();
+        if removed {
+            self.unpersist(hash);
+        }
+        removed
+    }
+
+    /// Submissions due for another round, now marked running, and those
+    /// the policy or their TTL gave up on, now dropped.
+    pub(crate) fn take_due(&self, now: u64) -> Vec<Due<M>> {
+        let mut due = Vec::new();
+        let mut expired = Vec::new();
+        {
+            let mut entries = self.lock();
+            for (hash, entry) in entries.iter_mut() {
+                if entry.running {
+                    continue;
+                }
+                if self.policy.gives_up(&entry.pending, now) || entry.batch.is_expired(now) {
+                    expired.push(hash.clone());
+                } else if entry.pending.next_attempt_at <= now {
+                    entry.running = true;
+                    due.push(Due::Retry(entry.batch.clone()));
+                }
+            }
+            for hash in &expired {
+                if let Some(entry) = entries.remove(hash) {
+                    due.push(Due::Expired(entry.pending));
+                }
+            }
+        }
+        for hash in &expired {
+            self.unpersist(hash);
+        }
+        due
+    }
+
+    /// Pending submissions, oldest first.
+    pub(crate) fn pending(&self) -> Vec<PendingSubmission> {
+        let mut pending: Vec<_> = self.lock().values().map(|entry| entry.pending.clone()).collect();
+        pending.sort_by(|a, b| (a.submitted_at, &a.hash).cmp(&(b.submitted_at, &b.hash)));
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::{Priority, VectorClock};
+    use racer_core::message::DefaultMessage;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        initial: Duration::from_millis(100),
+        max: Duration::from_millis(250),
+        expiry: Some(Duration::from_secs(10)),
+        max_attempts: Some(4),
+    };
+
+    fn batch() -> BatchedMessages<DefaultMessage> {
+        let key = KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: "b1".into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Priority::Normal,
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    fn retried(due: &[Due<DefaultMessage>]) -> usize {
+        due.iter().filter(|d| matches!(d, Due::Retry(_))).count()
+    }
+
+    #[test]
+    fn test_retries_back_off_until_attempts_run_out() {
+        let outbox = Outbox::new(POLICY);
+        outbox.insert("h", &batch(), 1_000);
+        assert!(outbox.take_due(1_000).is_empty(), "first round is running");
+
+        let mut now = 1_000;
+        for delay in [100, 200, 250] {
+            outbox.finish_attempt("h", now);
+            assert_eq!(retried(&outbox.take_due(now + delay - 1)), 0);
+            now += delay;
+            assert_eq!(retried(&outbox.take_due(now)), 1);
+        }
+        outbox.finish_attempt("h", now);
+        assert_eq!(outbox.pending()[0].attempts, 4);
+
+        let due = outbox.take_due(now + 250);
+        assert!(matches!(&due[..], [Due::Expired(pending)] if pending.hash == "h"));
+        assert!(outbox.pending().is_empty());
+    }
+
+    #[test]
+    fn test_delivered_submissions_are_forgotten() {
+        let outbox = Outbox::new(POLICY);
+        outbox.insert("h", &batch(), 1_000);
+        assert!(outbox.remove("h"));
+        assert!(!outbox.remove("h"));
+        assert!(outbox.take_due(60_000).is_empty());
+    }
+
+    #[cfg(feature = "store")]
+    #[test]
+    fn test_pending_submissions_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let outbox = Outbox::<DefaultMessage>::open(dir.path(), POLICY).unwrap();
+            outbox.insert("h", &batch(), 1_000);
+            outbox.finish_attempt("h", 1_000);
+        }
+        let outbox = Outbox::<DefaultMessage>::open(dir.path(), POLICY).unwrap();
+        assert_eq!(outbox.pending()[0].attempts, 1);
+        assert_eq!(retried(&outbox.take_due(1_001)), 1);
+    }
+}
@@ -0,0 +1,1033 @@
+//! Inbound message pipeline.
+//!
+//! Each frame read off the router or subscriber socket goes through:
+//!
+//! 1. limit: frames over `network.max_frame_bytes` are dropped; see [`FrameLimits`].
+//! 2. decode: the envelope is parsed in place; a message type this build
+//!    does not know is answered, counted and skipped
+//!    ([`crate::protocol::envelope`]).
+//! 3. unbundle (router only): a `Bundle` becomes one message each, each
+//!    answered on its own.
+//! 4. verify: signatures and batch size limits; failures are kept in
+//!    [`super::quarantine`].
+//! 5. dedup (router only): messages already in the gossip state's shards.
+//! 6. consensus: handled on `Node`, which holds the full node state.
+//! 7. deliver: checks the creator's sequence number, drops messages the
+//!    application already has ([`super::dedup`]), then hands the batch to
+//!    the [`DeliverySink`](super::sink::DeliverySink)s, WebSocket observers,
+//!    the [`Sequencer`] and, with `store`, the delivered batch store.
+//!
+//! Stages 1 to 3 run on one task, and verify and dedup on one each, joined
+//! by bounded channels of what each hands on: [`Frame`]s, then [`Decoded`]
+//! messages, then [`Verdict`]s. Consensus takes the verdicts on a task of the node's and
+//! passes each batch that reaches delivery on to deliver as a
+//! [`Delivery`]. One task per stage keeps messages in the order they were
+//! read, and each stage is also a plain function ([`decode_stage`],
+//! [`verify_stage`], [`dedup_stage`]) that can be tested without sockets.
+//! A frame a relay forwards to a node behind NAT runs through the same
+//! functions in place ([`screen`]), as a router frame with no identity to
+//! answer.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use crate::protocol::{
+    envelope, BatchedMessages, CongestionUpdate, CreatorLog, EnvelopeError, ProtocolMessage, ProtocolResponse,
+    SequenceCheck, Sequencer, ShardedGossipState,
+};
+use crate::network::{PeerRegistry, RacerNetwork};
+#[cfg(feature = "store")]
+use crate::store::DeliveryStore;
+use super::dedup::MessageDedup;
+use super::events::NodeEvent;
+use super::quarantine::Quarantine;
+use super::sink::DeliverySinks;
+use crate::config::RacerConfig;
+use crate::Message;
+
+/// Capacity of the channel feeding the deliver stage.
+pub const DELIVERY_CAPACITY: usize = 1024;
+
+/// Capacity of each channel between the stages before deliver.
+pub const STAGE_CAPACITY: usize = 1024;
+
+/// Raw bytes as received from a socket, still sharing the socket's buffer.
+#[derive(Debug, Clone)]
+pub enum Frame {
+    Router { identity: Vec<u8>, content: Bytes },
+    Subscriber { topic: String, content: Bytes },
+}
+
+/// A decoded inbound message.
+#[derive(Debug, Clone)]
+pub enum Inbound<M> {
+    /// Request on the router socket; always answered with a `CongestionUpdate`.
+    Request {
+        identity: Vec<u8>,
+        message: ProtocolMessage<M>,
+    },
+    /// Echo/Ready response published on a round topic.
+    Response(ProtocolResponse),
+}
+
+/// Outcome of a filtering stage.
+// Each verdict is moved a few times and dropped; boxing `Accept` would cost an
+// allocation per frame to save stack space on the rare `Reply`.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum Verdict<M> {
+    /// Pass the message on to the next stage.
+    Accept(Inbound<M>),
+    /// Short-circuit: answer the router request without further processing.
+    Reply {
+        identity: Vec<u8>,
+        update: CongestionUpdate,
+    },
+    /// Discard silently.
+    Drop,
+}
+
+/// A batch that reached its delivery threshold.
+#[derive(Debug, Clone)]
+pub struct Delivery<M> {
+    pub hash: String,
+    pub batch: BatchedMessages<M>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("decode error: {0}")]
+    Decode(String),
+    /// A router frame of a message type this build does not know.
+    #[error("unknown message type {message_type:?}")]
+    UnknownType { message_type: String, version: Option<u32> },
+}
+
+/// Stage 2: deserialize a frame.
+pub fn decode<M: DeserializeOwned>(frame: Frame) -> Result<Inbound<M>, PipelineError> {
+    match frame {
+        Frame::Router { identity, content } => {
+            let message = envelope::decode(&content).map_err(|e| match e {
+                EnvelopeError::UnknownType { message_type, version } => {
+                    PipelineError::UnknownType { message_type, version }
+                }
+                EnvelopeError::Malformed(e) => PipelineError::Decode(e.to_string()),
+            })?;
+            Ok(Inbound::Request { identity, message })
+        }
+        Frame::Subscriber { content, .. } => {
+            let response = serde_json::from_slice(&content)
+                .map_err(|e| PipelineError::Decode(e.to_string()))?;
+            Ok(Inbound::Response(response))
+        }
+    }
+}
+
+/// Size limits on inbound frames and the batches they carry; 0 disables
+/// each. The ZeroMQ transport has read a frame whole before it is checked,
+/// so an oversized one still costs its buffer once; dealer replies get the
+/// same frame check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameLimits {
+    /// Longest frame decoded, in bytes.
+    pub max_frame_bytes: usize,
+    /// Largest batch, in bytes of its messages serialized.
+    pub max_batch_bytes: usize,
+    pub max_messages_per_batch: usize,
+}
+
+impl FrameLimits {
+    pub fn from_config(config: &RacerConfig) -> Self {
+        Self {
+            max_frame_bytes: config.network.max_frame_bytes,
+            max_batch_bytes: config.consensus.max_batch_bytes,
+            max_messages_per_batch: config.consensus.max_messages_per_batch,
+        }
+    }
+
+    /// Fails if a frame of `len` bytes is too long to decode.
+    pub fn check_frame(&self, len: usize) -> Result<(), String> {
+        if self.max_frame_bytes > 0 && len > self.max_frame_bytes {
+            return Err(format!("frame of {} bytes exceeds max_frame_bytes ({})", len, self.max_frame_bytes));
+        }
+        Ok(())
+    }
+
+    /// Fails if a batch of `messages` is over the batch limits.
+    pub fn check_messages<M: Serialize>(&self, messages: &[M]) -> Result<(), String> {
+        if self.max_messages_per_batch > 0 && messages.len() > self.max_messages_per_batch {
+            return Err(format!(
+                "batch of {} messages exceeds max_messages_per_batch ({})",
+                messages.len(),
+                self.max_messages_per_batch
+            ));
+        }
+        if self.max_batch_bytes > 0 {
+            let bytes = crate::util::serialized_len(messages).map_err(|e| e.to_string())?;
+            if bytes > self.max_batch_bytes {
+                return Err(format!(
+                    "batch of {} bytes exceeds max_batch_bytes ({})",
+                    bytes, self.max_batch_bytes
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a `Bundle` request into its messages, each keeping the frame's
+/// router identity. Bundles nested in a bundle are dropped.
+pub fn unbundle<M>(inbound: Inbound<M>) -> Vec<Inbound<M>> {
+    match inbound {
+        Inbound::Request {
+            identity,
+            message: ProtocolMessage::Bundle { messages },
+        } => messages
+            .into_iter()
+            .filter(|message| {
+                let nested = matches!(message, ProtocolMessage::Bundle { .. });
+                if nested {
+                    tracing::warn!("dropped Bundle nested in a Bundle");
+                }
+                !nested
+            })
+            .map(|message| Inbound::Request {
+                identity: identity.clone(),
+                message,
+            })
+            .collect(),
+        other => vec![other],
+    }
+}
+
+/// Stage 4: check signatures.
+pub fn verify<M: Serialize + Clone>(inbound: Inbound<M>) -> Verdict<M> {
+    let reason = rejection(&inbound);
+    judge(inbound, reason)
+}
+
+/// Why `inbound` fails verification, if it does.
+fn rejection<M: Serialize + Clone>(inbound: &Inbound<M>) -> Option<&'static str> {
+    match inbound {
+        Inbound::Request { message, .. } => match message {
+            ProtocolMessage::BatchedMessages(bm) if !bm.verify_creator_signature() => {
+                Some("creator signature on BatchedMessages")
+            }
+            ProtocolMessage::BatchedMessages(bm) if !bm.verify_sender_signature() => {
+                Some("sender signature on BatchedMessages")
+            }
+            ProtocolMessage::BatchedMessages(bm) if !bm.membership.iter().all(|u| u.verify()) => {
+                Some("signature on MembershipUpdate")
+            }
+            ProtocolMessage::Echo(echo) if !echo.verify() => Some("signature on Echo"),
+            ProtocolMessage::PeerDiscovery(pd) if !pd.verify() => Some("signature on PeerDiscovery"),
+            ProtocolMessage::PeerChallenge(challenge) if !challenge.verify() => {
+                Some("signature on PeerChallenge")
+            }
+            ProtocolMessage::HealthSummary(summary) if !summary.verify() => {
+                Some("signature on HealthSummary")
+            }
+            ProtocolMessage::PeerListGossip(gossip) if !gossip.verify() => Some("signature on PeerListGossip"),
+            ProtocolMessage::Relay(frame) if !frame.verify() => Some("signature on RelayFrame"),
+            ProtocolMessage::DeliveryReport(report) if !report.verify() => Some("signature on DeliveryReport"),
+            _ => None,
+        },
+        Inbound::Response(response) if !response.verify() => Some("signature on ProtocolResponse"),
+        Inbound::Response(_) => None,
+    }
+}
+
+/// The verdict on `inbound`, given its [`rejection`].
+fn judge<M>(inbound: Inbound<M>, reason: Option<&'static str>) -> Verdict<M> {
+    let Some(reason) = reason else {
+        return Verdict::Accept(inbound);
+    };
+    tracing::warn!(reason, "received invalid message");
+    match inbound {
+        Inbound::Request { identity, .. } => Verdict::Reply {
+            identity,
+            update: CongestionUpdate::ok(),
+        },
+        Inbound::Response(_) => Verdict::Drop,
+    }
+}
+
+/// Key id of the peer `inbound` names as its sender, if it names one.
+fn claimed_sender<M>(inbound: &Inbound<M>) -> Option<String> {
+    let key_id = |key: &crate::crypto::PublicKey| key.to_hex()[..10].to_string();
+    match inbound {
+        Inbound::Request { message, .. } => match message {
+            ProtocolMessage::BatchedMessages(bm) => Some(key_id(&bm.sender_ecdsa)),
+            ProtocolMessage::Echo(echo) => Some(echo.sender_id()),
+            ProtocolMessage::PeerDiscovery(pd) => Some(key_id(&pd.ecdsa_public_key)),
+            ProtocolMessage::PeerChallenge(challenge) => Some(key_id(&challenge.ecdsa_public_key)),
+            ProtocolMessage::HealthSummary(summary) => Some(summary.sender_id()),
+            ProtocolMessage::PeerListGossip(gossip) => Some(gossip.sender_id()),
+            ProtocolMessage::Relay(frame) => Some(frame.sender_id()),
+            ProtocolMessage::DeliveryReport(report) => Some(report.sender_id()),
+            _ => None,
+        },
+        Inbound::Response(response) => Some(response.sender_id()),
+    }
+}
+
+/// Stage 5: drop batches we hold. A batch only the seen-set remembers is
+/// let through, since the set can be wrong; consensus answers it but does
+/// not re-gossip it.
+pub async fn dedup<M: Message>(verdict: Verdict<M>, state: &ShardedGossipState<M>) -> Verdict<M> {
+    match verdict {
+        Verdict::Accept(Inbound::Request {
+            identity,
+            message: ProtocolMessage::BatchedMessages(bm),
+        }) => {
+            let hash = bm.compute_hash();
+            if state.has_message(&hash).await {
+                Verdict::Reply {
+                    identity,
+                    update: CongestionUpdate::already_received(),
+                }
+            } else {
+                Verdict::Accept(Inbound::Request {
+                    identity,
+                    message: ProtocolMessage::BatchedMessages(bm),
+                })
+            }
+        }
+        other => other,
+    }
+}
+
+/// Per-stage counters.
+#[derive(Debug, Default)]
+pub struct PipelineCounters {
+    received: AtomicU64,
+    decode_errors: AtomicU64,
+    unknown_types: AtomicU64,
+    rejected: AtomicU64,
+    duplicates: AtomicU64,
+    processed: AtomicU64,
+    delivered: AtomicU64,
+    expired: AtomicU64,
+    hop_limited: AtomicU64,
+    bundled: AtomicU64,
+    app_duplicates: AtomicU64,
+    oversized: AtomicU64,
+    sequence_gaps: AtomicU64,
+    reordered: AtomicU64,
+    equivocations: AtomicU64,
+}
+
+impl PipelineCounters {
+    pub fn snapshot(&self) -> PipelineStats {
+        PipelineStats {
+            received: self.received.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            unknown_types: self.unknown_types.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            processed: self.processed.load(Ordering::Relaxed),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            hop_limited: self.hop_limited.load(Ordering::Relaxed),
+            bundled: self.bundled.load(Ordering::Relaxed),
+            app_duplicates: self.app_duplicates.load(Ordering::Relaxed),
+            oversized: self.oversized.load(Ordering::Relaxed),
+            sequence_gaps: self.sequence_gaps.load(Ordering::Relaxed),
+            reordered: self.reordered.load(Ordering::Relaxed),
+            equivocations: self.equivocations.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_processed(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_expired(&self) {
+        self.expired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_hop_limited(&self) {
+        self.hop_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_equivocation(&self) {
+        self.equivocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_oversized(&self) {
+        self.oversized.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineStats {
+    pub received: u64,
+    pub decode_errors: u64,
+    pub rejected: u64,
+    pub duplicates: u64,
+    pub processed: u64,
+    pub delivered: u64,
+    /// Batches dropped on arrival for outliving their `ttl_ms`.
+    pub expired: u64,
+    /// Batches processed but not re-gossiped, having reached `max_hops`.
+    pub hop_limited: u64,
+    /// Messages that arrived inside a `Bundle` frame; `received` counts
+    /// each bundle once.
+    #[serde(default)]
+    pub bundled: u64,
+    /// Delivered batches withheld because every message in them was
+    /// delivered within `consensus.dedup_window_secs`.
+    #[serde(default)]
+    pub app_duplicates: u64,
+    /// Frames over `network.max_frame_bytes`, dropped undecoded, and
+    /// batches over the consensus size limits, which also count as
+    /// `rejected`.
+    #[serde(default)]
+    pub oversized: u64,
+    /// Delivered batches whose sequence number skipped past ones not yet
+    /// delivered from the same creator.
+    #[serde(default)]
+    pub sequence_gaps: u64,
+    /// Delivered batches that filled in such a gap.
+    #[serde(default)]
+    pub reordered: u64,
+    /// Received batches that conflicted with one their creator signed
+    /// before; see [`super::equivocation`].
+    #[serde(default)]
+    pub equivocations: u64,
+    /// Router frames skipped for carrying a message type this build does
+    /// not know, likely from a newer node.
+    #[serde(default)]
+    pub unknown_types: u64,
+}
+
+/// What the decode stage passes on to verify.
+#[derive(Debug, Clone)]
+pub(crate) enum Decoded<M> {
+    /// A message still to verify, with the frame it came in, kept for
+    /// quarantine when it is enabled.
+    Message {
+        inbound: Inbound<M>,
+        raw: Option<(String, Bytes)>,
+    },
+    /// Already settled, as for a message type this build does not know.
+    Settled(Verdict<M>),
+}
+
+/// Stages 1 to 3 with counting: drops frames too long to decode, decodes
+/// the rest and splits bundles. Frames that fail to decode are kept in
+/// `quarantine`.
+///
+/// Returns `None` if the frame was too long or could not be decoded. A
+/// request of an unknown message type comes back settled with a lone reply.
+pub(crate) fn decode_stage<M: DeserializeOwned>(
+    frame: Frame,
+    limits: &FrameLimits,
+    counters: &PipelineCounters,
+    quarantine: &Quarantine,
+) -> Option<Vec<Decoded<M>>> {
+    counters.received.fetch_add(1, Ordering::Relaxed);
+    let len = match &frame {
+        Frame::Router { content, .. } | Frame::Subscriber { content, .. } => content.len(),
+    };
+    if let Err(reason) = limits.check_frame(len) {
+        // Too big to keep in quarantine either.
+        counters.record_oversized();
+        tracing::warn!(reason = %reason, "dropped oversized inbound frame");
+        return None;
+    }
+    let raw = quarantine.is_enabled().then(|| match &frame {
+        Frame::Router { content, .. } => ("router".to_string(), content.clone()),
+        Frame::Subscriber { topic, content } => (topic.clone(), content.clone()),
+    });
+    let identity = match &frame {
+        Frame::Router { identity, .. } => identity.clone(),
+        Frame::Subscriber { .. } => Vec::new(),
+    };
+    let inbound = match decode(frame) {
+        Ok(inbound) => inbound,
+        Err(PipelineError::UnknownType { message_type, version }) => {
+            counters.unknown_types.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(message_type, ?version, "skipped message of unknown type");
+            return Some(vec![Decoded::Settled(Verdict::Reply {
+                identity,
+                update: CongestionUpdate::ok(),
+            })]);
+        }
+        Err(e) => {
+            counters.decode_errors.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(error = %e, "failed to decode inbound frame");
+            if let Some((source, content)) = &raw {
+                quarantine.push(source, content, e.to_string(), None);
+            }
+            return None;
+        }
+    };
+
+    let bundle = matches!(
+        &inbound,
+        Inbound::Request { message: ProtocolMessage::Bundle { .. }, .. }
+    );
+    let messages = unbundle(inbound);
+    if bundle {
+        counters.bundled.fetch_add(messages.len() as u64, Ordering::Relaxed);
+    }
+    Some(
+        messages
+            .into_iter()
+            .map(|inbound| Decoded::Message { inbound, raw: raw.clone() })
+            .collect(),
+    )
+}
+
+/// Stage 4 with counting: checks signatures and, on batches, `limits`.
+/// Messages that fail are kept in `quarantine`.
+pub(crate) fn verify_stage<M: Serialize + Clone>(
+    decoded: Decoded<M>,
+    limits: &FrameLimits,
+    counters: &PipelineCounters,
+    quarantine: &Quarantine,
+) -> Verdict<M> {
+    let (inbound, raw) = match decoded {
+        Decoded::Message { inbound, raw } => (inbound, raw),
+        Decoded::Settled(verdict) => return verdict,
+    };
+    let mut reason = rejection(&inbound);
+    if let (Some(reason), Some((source, content))) = (reason, &raw) {
+        quarantine.push(source, content, format!("invalid {}", reason), claimed_sender(&inbound));
+    }
+    if let (None, Inbound::Request { message: ProtocolMessage::BatchedMessages(bm), .. }) = (reason, &inbound) {
+        if let Err(over) = limits.check_messages(&bm.messages) {
+            counters.record_oversized();
+            if let Some((source, content)) = &raw {
+                quarantine.push(source, content, over, claimed_sender(&inbound));
+            }
+            reason = Some("size of BatchedMessages");
+        }
+    }
+    let verdict = judge(inbound, reason);
+    if !matches!(verdict, Verdict::Accept(_)) {
+        counters.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+    verdict
+}
+
+/// Stage 5 with counting; verdicts other than `Accept` pass through.
+pub(crate) async fn dedup_stage<M: Message>(
+    verdict: Verdict<M>,
+    state: &ShardedGossipState<M>,
+    counters: &PipelineCounters,
+) -> Verdict<M> {
+    if !matches!(verdict, Verdict::Accept(_)) {
+        return verdict;
+    }
+    let verdict = dedup(verdict, state).await;
+    if !matches!(verdict, Verdict::Accept(_)) {
+        counters.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+    verdict
+}
+
+/// Runs stages 1 to 5 on a frame in place, with one verdict per message
+/// it carries; for frames that arrive other than on a socket, such as
+/// those a relay forwards. `None` as for [`decode_stage`].
+pub(crate) async fn screen<M: Message>(
+    frame: Frame,
+    limits: &FrameLimits,
+    state: &ShardedGossipState<M>,
+    counters: &PipelineCounters,
+    quarantine: &Quarantine,
+) -> Option<Vec<Verdict<M>>> {
+    let decoded = decode_stage(frame, limits, counters, quarantine)?;
+    let mut verdicts = Vec::with_capacity(decoded.len());
+    for decoded in decoded {
+        let verdict = verify_stage(decoded, limits, counters, quarantine);
+        verdicts.push(dedup_stage(verdict, state, counters).await);
+    }
+    Some(verdicts)
+}
+
+/// The node state stages 1 to 5 work on, shared with the node.
+pub(crate) struct ScreenContext<M: Message> {
+    pub node_id: String,
+    pub limits: FrameLimits,
+    pub state: Arc<ShardedGossipState<M>>,
+    pub counters: Arc<PipelineCounters>,
+    pub quarantine: Arc<Quarantine>,
+}
+
+/// Spawns the decode, verify and dedup stages as a task each, joined by
+/// channels of [`STAGE_CAPACITY`]: frames go in on `frames`, and verdicts
+/// come out on `verdicts` in the order their frames went in. Each task
+/// ends when the channel feeding it closes.
+pub(crate) fn spawn_screen<M: Message>(
+    mut frames: mpsc::Receiver<Frame>,
+    verdicts: mpsc::Sender<Verdict<M>>,
+    context: ScreenContext<M>,
+) -> Vec<JoinHandle<()>> {
+    let ScreenContext { node_id, limits, state, counters, quarantine } = context;
+    let (decoded_tx, mut decoded_rx) = mpsc::channel::<Decoded<M>>(STAGE_CAPACITY);
+    let (verified_tx, mut verified_rx) = mpsc::channel::<Verdict<M>>(STAGE_CAPACITY);
+
+    let decode_task = {
+        let (counters, quarantine) = (Arc::clone(&counters), Arc::clone(&quarantine));
+        tokio::spawn(
+            async move {
+                while let Some(frame) = frames.recv().await {
+                    for decoded in decode_stage(frame, &limits, &counters, &quarantine).unwrap_or_default() {
+                        if decoded_tx.send(decoded).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            .instrument(tracing::debug_span!("decode", id = %node_id)),
+        )
+    };
+    let verify_task = {
+        let counters = Arc::clone(&counters);
+        tokio::spawn(
+            async move {
+                while let Some(decoded) = decoded_rx.recv().await {
+                    let verdict = verify_stage(decoded, &limits, &counters, &quarantine);
+                    if verified_tx.send(verdict).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            .instrument(tracing::debug_span!("verify", id = %node_id)),
+        )
+    };
+    let dedup_task = tokio::spawn(
+        async move {
+            while let Some(verdict) = verified_rx.recv().await {
+                if verdicts.send(dedup_stage(verdict, &state, &counters).await).is_err() {
+                    return;
+                }
+            }
+        }
+        .instrument(tracing::debug_span!("dedup", id = %node_id)),
+    );
+    vec![decode_task, verify_task, dedup_task]
+}
+
+/// The node state the deliver stage works on, shared with the node.
+pub(crate) struct DeliverContext<M: Message> {
+    pub node_id: String,
+    pub sinks: DeliverySinks<M>,
+    /// Forwards deliveries to WebSocket observers.
+    pub network: Arc<RacerNetwork>,
+    pub counters: Arc<PipelineCounters>,
+    pub sequencer: Arc<RwLock<Sequencer<M>>>,
+    /// Skew of each creator's clock, which dates its batches.
+    pub peers: Arc<RwLock<PeerRegistry>>,
+    pub creator_log: Arc<RwLock<CreatorLog>>,
+    /// `subscribe_channel` receivers.
+    pub subscribers: broadcast::Sender<Delivery<M>>,
+    pub events: broadcast::Sender<NodeEvent>,
+    pub dedup: Arc<MessageDedup<M>>,
+    /// Off on a forwarder, which orders batches and applies their membership
+    /// updates but hands them to no application.
+    pub to_application: bool,
+    #[cfg(feature = "store")]
+    pub store: Option<Arc<DeliveryStore<M>>>,
+}
+
+pub(crate) fn spawn_deliver<M: Message>(
+    mut rx: mpsc::Receiver<Delivery<M>>,
+    context: DeliverContext<M>,
+) -> JoinHandle<()> {
+    let DeliverContext {
+        node_id,
+        sinks,
+        network,
+        counters,
+        sequencer,
+        peers,
+        creator_log,
+        subscribers,
+        events,
+        dedup,
+        to_application,
+        #[cfg(feature = "store")]
+        store,
+    } = context;
+    tokio::spawn(async move {
+        while let Some(Delivery { hash, batch }) = rx.recv().await {
+            let creator = batch.creator_ecdsa.to_hex();
+            let creator_now = peers.read().await.peer_time(&creator[..10], racer_core::message::now_millis());
+            match creator_log.write().await.record(&creator, batch.sequence) {
+                SequenceCheck::Gap { missing } => {
+                    counters.sequence_gaps.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!(id = %node_id, hash = %hash, creator = %&creator[..10], ?missing, "sequence gap");
+                    let _ = events.send(NodeEvent::SequenceGap {
+                        creator,
+                        from: *missing.start(),
+                        to: *missing.end(),
+                    });
+                }
+                SequenceCheck::Reordered => {
+                    counters.reordered.fetch_add(1, Ordering::Relaxed);
+                }
+                SequenceCheck::InOrder | SequenceCheck::Duplicate | SequenceCheck::Unsequenced => {}
+            }
+            // The store and the total order get the batch as signed and
+            // agreed; only what the application sees is deduplicated.
+            #[cfg(feature = "store")]
+            if let Some(ref store) = store {
+                if let Err(e) = store.insert(&hash, &batch, racer_core::message::now_millis()) {
+                    tracing::warn!(id = %node_id, hash = %hash, error = %e, "failed to persist delivered batch");
+                }
+            }
+            if !sequencer.write().await.insert(hash.clone(), batch.clone(), creator_now) {
+                tracing::warn!(
+                    id = %node_id,
+                    hash = %hash,
+                    "delivered batch left out of the total order (late, duplicate or dated ahead of its creator)"
+                );
+            }
+            if !to_application {
+                continue;
+            }
+            let Some(batch) = dedup.filter(batch) else {
+                counters.app_duplicates.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(id = %node_id, hash = %hash, "withheld batch of duplicate messages");
+                continue;
+            };
+            counters.delivered.fetch_add(1, Ordering::Relaxed);
+            if subscribers.receiver_count() > 0 {
+                let _ = subscribers.send(Delivery {
+                    hash: hash.clone(),
+                    batch: batch.clone(),
+                });
+            }
+            sinks.deliver(&batch);
+            if network.observer_count().await > 0 {
+                match serde_json::to_vec(&ProtocolMessage::BatchedMessages(batch)) {
+                    Ok(frame) => network.broadcast_observers(&frame).await,
+                    Err(e) => tracing::warn!(error = %e, "failed to encode delivery for observers"),
+                }
+            }
+            tracing::info!(id = %node_id, hash = %hash, "message DELIVERED");
+        }
+        tracing::trace!("deliver stage stopped");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{EcdsaSigner, KeyPair};
+    use crate::protocol::{Echo, EchoType, PeerDiscovery, VectorClock};
+    use racer_core::message::DefaultMessage;
+
+    fn signed_batch(keys: &KeyPair) -> BatchedMessages<DefaultMessage> {
+        let signer = EcdsaSigner::new(keys.signing_key().clone());
+        let mut bm = BatchedMessages {
+            batch_id: "b-1".into(),
+            creator_ecdsa: keys.public_key(),
+            sender_ecdsa: keys.public_key(),
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::new()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 0,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        };
+        bm.sign_as_creator(&signer);
+        bm.sign_as_sender(&signer);
+        bm
+    }
+
+    fn request(message: ProtocolMessage<DefaultMessage>) -> Inbound<DefaultMessage> {
+        Inbound::Request {
+            identity: vec![1],
+            message,
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        let frame = Frame::Router {
+            identity: vec![1],
+            content: Bytes::from_static(b"not json"),
+        };
+        assert!(decode::<DefaultMessage>(frame).is_err());
+    }
+
+    #[test]
+    fn test_verify_replies_to_unsigned_echo() {
+        let echo = Echo::new(EchoType::EchoSubscribe, "t", KeyPair::generate().public_key());
+        match verify(request(ProtocolMessage::Echo(echo))) {
+            Verdict::Reply { update, .. } => assert_eq!(update.status, "OK"),
+            other => panic!("expected reply, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_spoofed_peer_discovery() {
+        let announced = KeyPair::generate();
+        let mut pd = PeerDiscovery::new(announced.public_key(), "tcp://10.6.6.6:20001", "tcp://10.6.6.6:21001");
+        pd.sign(&EcdsaSigner::new(KeyPair::generate().signing_key().clone()));
+        assert!(matches!(verify(request(ProtocolMessage::PeerDiscovery(pd))), Verdict::Reply { .. }));
+    }
+
+    #[test]
+    fn test_verify_accepts_signed_batch() {
+        let bm = signed_batch(&KeyPair::generate());
+        assert!(matches!(
+            verify(request(ProtocolMessage::BatchedMessages(bm))),
+            Verdict::Accept(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dedup_known_batch() {
+        let bm = signed_batch(&KeyPair::generate());
+        let state = ShardedGossipState::<DefaultMessage>::default();
+
+        let first = dedup(
+            Verdict::Accept(request(ProtocolMessage::BatchedMessages(bm.clone()))),
+            &state,
+        )
+        .await;
+        assert!(matches!(first, Verdict::Accept(_)));
+
+        state.store_message(bm.compute_hash(), bm.clone()).await;
+        let second = dedup(
+            Verdict::Accept(request(ProtocolMessage::BatchedMessages(bm.clone()))),
+            &state,
+        )
+        .await;
+        match second {
+            Verdict::Reply { update, .. } => assert_eq!(update.status, "ALREADY_RECEIVED"),
+            other => panic!("expected reply, got {:?}", other),
+        }
+
+        // Only the seen-set remembers it now, which is not enough to refuse it.
+        state.forget(&[bm.compute_hash()]).await;
+        assert!(state.has_seen(&bm.compute_hash()).await);
+        let third = dedup(
+            Verdict::Accept(request(ProtocolMessage::BatchedMessages(bm))),
+            &state,
+        )
+        .await;
+        assert!(matches!(third, Verdict::Accept(_)));
+    }
+
+    #[tokio::test]
+    async fn test_screen_counts_decode_errors() {
+        let state = ShardedGossipState::<DefaultMessage>::default();
+        let counters = PipelineCounters::default();
+        let frame = Frame::Subscriber {
+            topic: "t".into(),
+            content: Bytes::from_static(b"{"),
+        };
+
+        let quarantine = Quarantine::new(8);
+        assert!(screen(frame, &FrameLimits::default(), &state, &counters, &quarantine).await.is_none());
+        let stats = counters.snapshot();
+        assert_eq!(stats.received, 1);
+        assert_eq!(stats.decode_errors, 1);
+        let quarantined = &quarantine.frames()[0];
+        assert_eq!((quarantined.source.as_str(), quarantined.frame_bytes()), ("t", b"{".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_screen_skips_unknown_message_types() {
+        let state = ShardedGossipState::<DefaultMessage>::default();
+        let counters = PipelineCounters::default();
+        let quarantine = Quarantine::new(8);
+        let frame = Frame::Router {
+            identity: vec![9],
+            content: Bytes::from_static(br#"{"v":99,"type":"FutureThing","payload":{}}"#),
+        };
+
+        let verdicts = screen(frame, &FrameLimits::default(), &state, &counters, &quarantine).await.unwrap();
+        match verdicts.as_slice() {
+            [Verdict::Reply { identity, update }] => assert_eq!((identity.as_slice(), update.status.as_str()), (&[9u8][..], "OK")),
+            other => panic!("expected a lone reply, got {:?}", other),
+        }
+        let stats = counters.snapshot();
+        assert_eq!((stats.unknown_types, stats.decode_errors), (1, 0));
+        assert!(quarantine.frames().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_screen_splits_bundles() {
+        let state = ShardedGossipState::<DefaultMessage>::default();
+        let counters = PipelineCounters::default();
+        let echo = Echo::new(EchoType::EchoSubscribe, "t", KeyPair::generate().public_key());
+        let echo_sender = echo.sender_id();
+        let messages = [
+            ProtocolMessage::BatchedMessages(signed_batch(&KeyPair::generate())),
+            ProtocolMessage::Echo(echo),
+        ];
+        let frames: Vec<_> = messages.iter().map(|m| Bytes::from(serde_json::to_vec(m).unwrap())).collect();
+        let frame = Frame::Router {
+            identity: vec![7],
+            content: crate::network::bundle(&frames),
+        };
+
+        let quarantine = Quarantine::new(8);
+        let verdicts = screen(frame, &FrameLimits::default(), &state, &counters, &quarantine).await.unwrap();
+        assert!(matches!(verdicts[0], Verdict::Accept(Inbound::Request { ref identity, .. }) if identity == &[7]));
+        assert!(matches!(verdicts[1], Verdict::Reply { .. }));
+        let stats = counters.snapshot();
+        assert_eq!((stats.received, stats.bundled, stats.rejected), (1, 2, 1));
+
+        let quarantined = quarantine.frames();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].reason, "invalid signature on Echo");
+        assert_eq!(quarantined[0].peer.as_deref(), Some(echo_sender.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_screen_enforces_size_limits() {
+        let state = ShardedGossipState::<DefaultMessage>::default();
+        let counters = PipelineCounters::default();
+        let quarantine = Quarantine::new(8);
+        let limits = FrameLimits {
+            max_frame_bytes: 4096,
+            // One DefaultMessage serializes to about 40 bytes.
+            max_batch_bytes: 64,
+            max_messages_per_batch: 2,
+        };
+        let keys = KeyPair::generate();
+        let signer = EcdsaSigner::new(keys.signing_key().clone());
+        let batch = |messages: Vec<DefaultMessage>| {
+            let mut bm = signed_batch(&keys);
+            bm.batch_size = messages.len();
+            bm.messages = messages;
+            bm.sign_as_creator(&signer);
+            bm.sign_as_sender(&signer);
+            let message = ProtocolMessage::BatchedMessages(bm);
+            Frame::Router {
+                identity: vec![1],
+                content: Bytes::from(serde_json::to_vec(&message).unwrap()),
+            }
+        };
+
+        // Dropped unread: not even valid JSON.
+        let frame = Frame::Router {
+            identity: vec![1],
+            content: Bytes::from(vec![b'['; 4097]),
+        };
+        assert!(screen(frame, &limits, &state, &counters, &quarantine).await.is_none());
+        assert_eq!(counters.snapshot().decode_errors, 0);
+
+        let verdicts = screen(batch(vec![DefaultMessage::new(); 3]), &limits, &state, &counters, &quarantine).await.unwrap();
+        assert!(matches!(verdicts[0], Verdict::Reply { .. }));
+        let verdicts = screen(batch(vec![DefaultMessage::new(); 2]), &limits, &state, &counters, &quarantine).await.unwrap();
+        assert!(matches!(verdicts[0], Verdict::Reply { .. }));
+        let verdicts = screen(batch(vec![DefaultMessage::new()]), &limits, &state, &counters, &quarantine).await.unwrap();
+        assert!(matches!(verdicts[0], Verdict::Accept(_)));
+
+        let stats = counters.snapshot();
+        assert_eq!((stats.received, stats.oversized, stats.rejected), (4, 3, 2));
+        let reasons: Vec<_> = quarantine.frames().into_iter().map(|f| f.reason).collect();
+        assert_eq!(reasons.len(), 2);
+        assert!(reasons[0].contains("max_messages_per_batch"));
+        assert!(reasons[1].contains("max_batch_bytes"));
+        assert!(FrameLimits::default().check_messages(&vec![DefaultMessage::new(); 100_000]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_screen_survives_malformed_frames() {
+        let state = ShardedGossipState::<DefaultMessage>::default();
+        let counters = PipelineCounters::default();
+        let sender = KeyPair::generate().public_key().to_hex();
+        let nested = r#"{"message_type":"Bundle","messages":["#.repeat(512);
+        let inputs = [
+            String::new(),
+            "[".repeat(100_000),
+            nested,
+            r#"{"message_type":"Echo"}"#.to_string(),
+            r#"{"message_type":"Echo","echo_type":"echo_subscribe","topic":"t","sender":"02","signature":null,"timestamp":0}"#.to_string(),
+            // Smallest valid DER signature, shorter than its Debug prefix.
+            format!(
+                r#"{{"message_type":"Echo","echo_type":"echo_subscribe","topic":"t","sender":"{}","signature":"MAYCAQECAQE=","timestamp":18446744073709551615}}"#,
+                sender
+            ),
+            format!(
+                r#"{{"message_type":"HealthSummary","sender":"{}","window_secs":0,"delivered":18446744073709551615,"failed":18446744073709551615,"avg_round_latency":1e308,"signature":null,"timestamp":0}}"#,
+                sender
+            ),
+        ];
+
+        for input in &inputs {
+            let frame = Frame::Router {
+                identity: vec![1],
+                content: Bytes::from(input.clone()),
+            };
+            if let Some(verdicts) = screen(frame, &FrameLimits::default(), &state, &counters, &Quarantine::new(4)).await {
+                let _ = format!("{:?}", verdicts);
+            }
+        }
+        let stats = counters.snapshot();
+        assert_eq!(stats.received, inputs.len() as u64);
+        assert!(stats.decode_errors >= 5);
+    }
+
+    #[tokio::test]
+    async fn test_spawned_stages_keep_frame_order() {
+        let counters = Arc::new(PipelineCounters::default());
+        let context = ScreenContext {
+            node_id: "n".into(),
+            limits: FrameLimits::default(),
+            state: Arc::new(ShardedGossipState::<DefaultMessage>::default()),
+            counters: Arc::clone(&counters),
+            quarantine: Arc::new(Quarantine::new(8)),
+        };
+        let (frames, frames_rx) = mpsc::channel(STAGE_CAPACITY);
+        let (verdicts_tx, mut verdicts) = mpsc::channel(STAGE_CAPACITY);
+        let handles = spawn_screen(frames_rx, verdicts_tx, context);
+
+        let echo = Echo::new(EchoType::EchoSubscribe, "t", KeyPair::generate().public_key());
+        let echo = ProtocolMessage::<DefaultMessage>::Echo(echo);
+        let batch = ProtocolMessage::BatchedMessages(signed_batch(&KeyPair::generate()));
+        let router = |identity: u8, content: Vec<u8>| Frame::Router {
+            identity: vec![identity],
+            content: Bytes::from(content),
+        };
+        frames.send(router(1, b"not json".to_vec())).await.unwrap();
+        frames.send(router(2, serde_json::to_vec(&echo).unwrap())).await.unwrap();
+        frames.send(router(3, serde_json::to_vec(&batch).unwrap())).await.unwrap();
+        drop(frames);
+
+        assert!(matches!(verdicts.recv().await, Some(Verdict::Reply { identity, .. }) if identity == [2]));
+        assert!(matches!(
+            verdicts.recv().await,
+            Some(Verdict::Accept(Inbound::Request { identity, .. })) if identity == [3]
+        ));
+        // Closing the frame channel ends every stage in turn.
+        assert!(verdicts.recv().await.is_none());
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let stats = counters.snapshot();
+        assert_eq!((stats.received, stats.decode_errors, stats.rejected), (3, 1, 1));
+    }
+}
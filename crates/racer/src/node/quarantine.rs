@@ -0,0 +1,123 @@
+//! Frames the inbound pipeline rejected, kept for inspection.
+//!
+//! A frame that does not decode, or whose signatures do not verify, is
+//! answered and dropped; on its own that leaves only a log line, which is
+//! rarely enough to tell a peer running another implementation from one with
+//! a broken key or clock. The quarantine keeps the last
+//! `node.quarantine_capacity` such frames as they arrived, with the reason
+//! and the peer they claim to come from, for
+//! [`Node::quarantined`](super::Node::quarantined) and `racer quarantine`.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use base64::Engine;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// A rejected frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedFrame {
+    /// Epoch millis at which it arrived.
+    pub received_at: u64,
+    pub reason: String,
+    /// Key id of the peer the message names as its sender. Unverified:
+    /// the signature that would prove it is what failed, or the frame did
+    /// not decode far enough to name one.
+    pub peer: Option<String>,
+    /// `router`, or the topic the frame was published on.
+    pub source: String,
+    /// The frame as received, base64-encoded.
+    pub frame: String,
+}
+
+impl QuarantinedFrame {
+    /// The frame as received.
+    pub fn frame_bytes(&self) -> Vec<u8> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.frame)
+            .unwrap_or_default()
+    }
+}
+
+/// The last rejected frames, oldest first.
+pub(crate) struct Quarantine {
+    capacity: usize,
+    frames: Mutex<VecDeque<QuarantinedFrame>>,
+}
+
+impl Quarantine {
+    /// Keeps up to `capacity` frames; 0 keeps none.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            frames: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<QuarantinedFrame>> {
+        self.frames.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Keeps `content`, evicting the oldest frame if full.
+    pub(crate) fn push(&self, source: &str, content: &Bytes, reason: impl Into<String>, peer: Option<String>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let frame = QuarantinedFrame {
+            received_at: racer_core::message::now_millis(),
+            reason: reason.into(),
+            peer,
+            source: source.to_string(),
+            frame: base64::engine::general_purpose::STANDARD.encode(content),
+        };
+        let mut frames = self.lock();
+        if frames.len() == self.capacity {
+            frames.pop_front();
+        }
+        frames.push_back(frame);
+    }
+
+    pub(crate) fn frames(&self) -> Vec<QuarantinedFrame> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Empties the quarantine, returning the frames it held.
+    pub(crate) fn take(&self) -> Vec<QuarantinedFrame> {
+        std::mem::take(&mut *self.lock()).into()
+    }
+
+    /// Empties the quarantine. Returns how many frames it held.
+    pub(crate) fn clear(&self) -> usize {
+        let mut frames = self.lock();
+        let count = frames.len();
+        frames.clear();
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_the_latest_frames() {
+        let quarantine = Quarantine::new(2);
+        for content in [&b"one"[..], b"two", b"three"] {
+            quarantine.push("router", &Bytes::copy_from_slice(content), "bad", Some("0123456789".into()));
+        }
+        let frames = quarantine.frames();
+        let contents: Vec<_> = frames.iter().map(QuarantinedFrame::frame_bytes).collect();
+        assert_eq!(contents, vec![b"two".to_vec(), b"three".to_vec()]);
+        assert_eq!(quarantine.take(), frames);
+        assert_eq!(quarantine.clear(), 0);
+
+        let off = Quarantine::new(0);
+        off.push("router", &Bytes::from_static(b"x"), "bad", None);
+        assert!(off.frames().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! Relaying for nodes behind NAT.
+//!
+//! With `node.relay`, a node forwards frames to the nodes registered with
+//! it and publishes their responses. A node with `node.relay_via` registers
+//! with its relay and receives every frame through it.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+
+use super::pipeline::{self, Frame, FrameLimits, Inbound, Verdict};
+use super::{Node, NodeError, NodeInner};
+use crate::protocol::{CongestionUpdate, Priority, ProtocolMessage, ProtocolResponse, RelayFrame, RelayPayload};
+use crate::Message;
+
+/// How often a node behind NAT registers with its relay again, in case its
+/// connection, and so its router identity there, changed.
+const RELAY_REGISTER_INTERVAL: Duration = Duration::from_secs(30);
+/// How far a relay Register's timestamp may be from now by the client's
+/// clock, as far as its skew is known, before it is taken for a replay.
+pub(super) const RELAY_REGISTER_MAX_AGE: Duration = Duration::from_secs(60);
+/// Nodes a relay forwards for at once; further registrations are ignored.
+const MAX_RELAY_CLIENTS: usize = 1024;
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Registers with the relay at `router` at start and every
+    /// `RELAY_REGISTER_INTERVAL`.
+    pub(super) fn spawn_relay_registration(&self, router: String) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            while inner.running.load(Ordering::SeqCst) {
+                if let Err(e) = Self::send_relay_frame(&inner, &router, RelayPayload::Register, Priority::High).await {
+                    tracing::warn!(id = %inner.id, relay = %router, error = %e, "failed to register with relay");
+                }
+                tokio::time::sleep(RELAY_REGISTER_INTERVAL).await;
+            }
+        })
+    }
+
+    /// Carries out a RelayFrame from the dealer at `identity`: registers
+    /// the sender, forwards a frame down a registered node's connection, or
+    /// publishes a registered node's response. Ignored unless `node.relay`.
+    pub(super) async fn inbox_relay(
+        inner: &NodeInner<M>,
+        identity: Vec<u8>,
+        frame: RelayFrame,
+    ) -> Result<CongestionUpdate, NodeError> {
+        if !inner.config.node.relay {
+            tracing::debug!(id = %inner.id, from = %frame.sender_id(), "ignored RelayFrame, node.relay is off");
+            return Ok(CongestionUpdate::ok());
+        }
+        let sender = frame.sender_id();
+
+        match frame.payload {
+            RelayPayload::Register => {
+                // A replayed Register would point the client's traffic at
+                // whoever replays it.
+                let age_ms = Self::peer_now(inner, &sender).await.abs_diff(frame.timestamp);
+                if age_ms > RELAY_REGISTER_MAX_AGE.as_millis() as u64 {
+                    tracing::warn!(id = %inner.id, client = %sender, age_ms, "refusing stale relay registration");
+                    return Ok(CongestionUpdate::ok());
+                }
+                let mut clients = inner.relay_clients.write().await;
+                match clients.get(&sender) {
+                    Some((_, registered_at)) if frame.timestamp <= *registered_at => {
+                        tracing::warn!(
+                            id = %inner.id,
+                            client = %sender,
+                            "refusing relay registration older than the current one"
+                        );
+                        return Ok(CongestionUpdate::ok());
+                    }
+                    Some(_) => {}
+                    None if clients.len() >= MAX_RELAY_CLIENTS => {
+                        tracing::warn!(id = %inner.id, client = %sender, "too many relay clients");
+                        return Ok(CongestionUpdate::ok());
+                    }
+                    None => {}
+                }
+                if clients.insert(sender.clone(), (identity, frame.timestamp)).is_none() {
+                    tracing::info!(id = %inner.id, client = %sender, "relay client registered");
+                }
+            }
+            RelayPayload::Forward { to, frame } => {
+                let Some((client, _)) = inner.relay_clients.read().await.get(&to).cloned() else {
+                    tracing::debug!(id = %inner.id, from = %sender, to = %to, "no relay client to forward to");
+                    return Ok(CongestionUpdate::ok());
+                };
+                let msg = Self::encode(&CongestionUpdate::relayed(frame))?;
+                if let Err(e) = inner.network.send_router_reply(client, msg).await {
+                    tracing::debug!(id = %inner.id, to = %to, error = %e, "dropping unreachable relay client");
+                    inner.relay_clients.write().await.remove(&to);
+                }
+            }
+            RelayPayload::Publish { topic, frame } => {
+                if !inner.relay_clients.read().await.contains_key(&sender) {
+                    tracing::debug!(id = %inner.id, from = %sender, "ignored publish from unregistered relay client");
+                    return Ok(CongestionUpdate::ok());
+                }
+                // Subscribers verify the signature; only the signer's own
+                // responses are published, so the relay speaks for no one else.
+                match serde_json::from_str::<ProtocolResponse>(&frame) {
+                    Ok(response) if response.sender_id() == sender => {
+                        inner
+                            .network
+                            .publish(&topic, frame.into_bytes())
+                            .await
+                            .map_err(|e| NodeError::Network(e.to_string()))?;
+                    }
+                    _ => tracing::warn!(
+                        id = %inner.id,
+                        from = %sender,
+                        "relay client published a response it did not sign"
+                    ),
+                }
+            }
+        }
+
+        Ok(CongestionUpdate::ok())
+    }
+
+    /// Handles a frame the relay behind `from` forwarded as if it had arrived
+    /// on our router. Its reply has no way back to the requester and is dropped.
+    pub(super) async fn handle_relayed(inner: &NodeInner<M>, from: &str, frame: String) {
+        if inner.config.node.relay_via.as_deref().map(relay_link).as_deref() != Some(from) {
+            tracing::warn!(id = %inner.id, from = %from, "dropped relayed frame from a peer that is not our relay");
+            return;
+        }
+
+        let frame = Frame::Router { identity: Vec::new(), content: Bytes::from(frame) };
+        let limits = FrameLimits::from_config(&inner.config);
+        let Some(verdicts) =
+            pipeline::screen(frame, &limits, &inner.gossip_state, &inner.pipeline, &inner.quarantine).await
+        else {
+            return;
+        };
+        for verdict in verdicts {
+            if let Verdict::Accept(Inbound::Request { message, .. }) = verdict {
+                if let Err(e) = Self::handle_request(inner, message).await {
+                    tracing::warn!(id = %inner.id, error = %e, "failed to handle relayed message");
+                }
+            }
+            inner.pipeline.record_processed();
+        }
+    }
+
+    /// Signs `payload` into a RelayFrame and sends it to the relay at
+    /// `router`, dialing it first if needed.
+    pub(super) async fn send_relay_frame(
+        inner: &NodeInner<M>,
+        router: &str,
+        payload: RelayPayload,
+        priority: Priority,
+    ) -> Result<(), NodeError> {
+        let mut frame = RelayFrame::new(inner.signer.public_key(), payload);
+        frame.signature = Some(Self::sign(inner, &frame.signing_bytes()).await);
+        let msg = Self::encode(&ProtocolMessage::<M>::Relay(frame))?;
+
+        let link = relay_link(router);
+        inner
+            .network
+            .connect_to_peer(&link, router)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))?;
+        inner
+            .coalescer
+            .send(&link, msg, priority)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))
+    }
+}
+
+/// Dealer link id for the relay at `router`.
+pub(super) fn relay_link(router: &str) -> String {
+    format!("relay-{}", router.trim_start_matches("tcp://"))
+}
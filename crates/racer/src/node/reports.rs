@@ -0,0 +1,241 @@
+//! Delivery latency of this node's batches, as other nodes report it.
+//!
+//! A node with `consensus.delivery_reports` on sends the creator of each
+//! batch it delivers a [`DeliveryReport`](crate::protocol::DeliveryReport).
+//! The creator tallies the reports for the last
+//! [`DELIVERY_REPORT_BATCHES`] batches it created into a
+//! [`DeliveryLatency`] each, read through
+//! [`Node::delivery_reports`](super::Node::delivery_reports). A reporter
+//! counts once per batch, and reports for batches not created here, or
+//! created too long ago, are ignored.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::pipeline::Delivery;
+use super::{Node, NodeInner};
+use crate::protocol::version::Capabilities;
+use crate::protocol::{CongestionUpdate, DeliveryReport, Priority, ProtocolMessage};
+use crate::Message;
+
+/// Batches created here whose reports are kept.
+pub const DELIVERY_REPORT_BATCHES: usize = 1024;
+
+/// Upper bounds, in milliseconds, of the buckets of
+/// [`DeliveryLatency::buckets`]; a last bucket holds anything slower.
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// How long one batch took to be delivered across the nodes that reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryLatency {
+    pub batch_id: String,
+    pub reports: usize,
+    /// Reports per bucket of [`LATENCY_BUCKETS_MS`], then those slower than
+    /// the last bound.
+    pub buckets: Vec<usize>,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: f64,
+    /// Fewest Ready responses any reporter delivered on.
+    pub min_ready_count: usize,
+}
+
+impl DeliveryLatency {
+    fn new(batch_id: &str) -> Self {
+        Self {
+            batch_id: batch_id.to_string(),
+            reports: 0,
+            buckets: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            min_ms: 0,
+            max_ms: 0,
+            mean_ms: 0.0,
+            min_ready_count: 0,
+        }
+    }
+
+    fn record(&mut self, elapsed_ms: u64, ready_count: usize) {
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| elapsed_ms <= bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        if self.reports == 0 {
+            self.min_ms = elapsed_ms;
+            self.min_ready_count = ready_count;
+        }
+        self.min_ms = self.min_ms.min(elapsed_ms);
+        self.max_ms = self.max_ms.max(elapsed_ms);
+        self.min_ready_count = self.min_ready_count.min(ready_count);
+        self.mean_ms += (elapsed_ms as f64 - self.mean_ms) / (self.reports + 1) as f64;
+        self.reports += 1;
+    }
+}
+
+struct Tally {
+    reporters: HashSet<String>,
+    latency: DeliveryLatency,
+}
+
+#[derive(Default)]
+struct State {
+    batches: HashMap<String, Tally>,
+    /// Keys of `batches`, oldest first.
+    order: VecDeque<String>,
+}
+
+pub(crate) struct DeliveryReports {
+    state: Mutex<State>,
+    capacity: usize,
+}
+
+impl DeliveryReports {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts taking reports for `batch_id`, created here.
+    pub(crate) fn track(&self, batch_id: &str) {
+        let mut state = self.lock();
+        if state.batches.contains_key(batch_id) {
+            return;
+        }
+        state.batches.insert(
+            batch_id.to_string(),
+            Tally {
+                reporters: HashSet::new(),
+                latency: DeliveryLatency::new(batch_id),
+            },
+        );
+        state.order.push_back(batch_id.to_string());
+        while state.order.len() > self.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.batches.remove(&oldest);
+            }
+        }
+    }
+
+    /// Counts a report from `reporter` (key id). Returns whether it counted.
+    pub(crate) fn record(&self, batch_id: &str, reporter: &str, elapsed_ms: u64, ready_count: usize) -> bool {
+        let mut state = self.lock();
+        let Some(tally) = state.batches.get_mut(batch_id) else {
+            return false;
+        };
+        if !tally.reporters.insert(reporter.to_string()) {
+            return false;
+        }
+        tally.latency.record(elapsed_ms, ready_count);
+        true
+    }
+
+    pub(crate) fn get(&self, batch_id: &str) -> Option<DeliveryLatency> {
+        self.lock().batches.get(batch_id).map(|tally| tally.latency.clone())
+    }
+}
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// How long `batch_id`, created here, took to be delivered at the peers
+    /// that reported it; see the [module docs](self). `None` for batches not created
+    /// here or created too long ago.
+    pub fn delivery_reports(&self, batch_id: &str) -> Option<DeliveryLatency> {
+        self.inner.reports.get(batch_id)
+    }
+
+    /// Counts a peer's report on the delivery of a batch created here.
+    pub(super) async fn inbox_delivery_report(inner: &NodeInner<M>, report: DeliveryReport) -> CongestionUpdate {
+        let from = report.sender_id();
+        if inner.peers.read().await.find_by_key_id(&from).is_none() {
+            tracing::debug!(id = %inner.id, from = %from, "ignored DeliveryReport from unknown node");
+            return CongestionUpdate::ok();
+        }
+        if !inner.reports.record(&report.batch_id, &from, report.elapsed_ms, report.ready_count) {
+            tracing::debug!(id = %inner.id, from = %from, batch_id = %report.batch_id, "ignored DeliveryReport");
+        }
+        CongestionUpdate::ok()
+    }
+
+    /// With `consensus.delivery_reports`, tells the creator of a batch just
+    /// delivered here how long it took. Creators that are not peers of this
+    /// node go without.
+    pub(super) async fn report_delivery(inner: &NodeInner<M>, delivery: &Delivery<M>) {
+        let batch = &delivery.batch;
+        if !inner.config.consensus.delivery_reports || batch.creator_ecdsa == inner.signer.public_key() {
+            return;
+        }
+        let creator_id = batch.creator_ecdsa.to_hex()[..10].to_string();
+        let Some(peer_id) = inner
+            .peers
+            .read()
+            .await
+            .find_by_key_id(&creator_id)
+            .filter(|peer| peer.supports(Capabilities::DELIVERY_REPORTS))
+            .map(|peer| peer.id.clone())
+        else {
+            return;
+        };
+        // `created_at` is on the creator's clock.
+        let elapsed_ms = Self::peer_now(inner, &creator_id).await.saturating_sub(batch.created_at);
+        let ready_count = inner
+            .gossip_state
+            .rounds(&delivery.hash)
+            .read()
+            .await
+            .get_round(&delivery.hash)
+            .map_or(0, |round| round.ready_received.len());
+
+        let mut report =
+            DeliveryReport::new(inner.signer.public_key(), &batch.batch_id, &delivery.hash, elapsed_ms, ready_count);
+        report.signature = Some(Self::sign(inner, &report.signing_bytes()).await);
+        match Self::encode(&ProtocolMessage::<M>::DeliveryReport(report)) {
+            Ok(msg) => Self::send_best_effort(inner, &peer_id, msg, Priority::Low).await,
+            Err(e) => tracing::warn!(id = %inner.id, error = %e, "failed to encode DeliveryReport"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_fill_the_histogram() {
+        let reports = DeliveryReports::new(DELIVERY_REPORT_BATCHES);
+        assert!(!reports.record("b-1", "a", 40, 3));
+        reports.track("b-1");
+        assert_eq!(reports.get("b-1").unwrap().reports, 0);
+
+        assert!(reports.record("b-1", "a", 40, 3));
+        assert!(!reports.record("b-1", "a", 900, 3));
+        assert!(reports.record("b-1", "b", 120, 4));
+        assert!(reports.record("b-1", "c", 60_000, 2));
+
+        let latency = reports.get("b-1").unwrap();
+        assert_eq!(latency.reports, 3);
+        assert_eq!(latency.buckets, vec![0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1]);
+        assert_eq!((latency.min_ms, latency.max_ms), (40, 60_000));
+        assert!((latency.mean_ms - 20_053.333).abs() < 0.01);
+        assert_eq!(latency.min_ready_count, 2);
+    }
+
+    #[test]
+    fn test_oldest_batches_are_forgotten() {
+        let reports = DeliveryReports::new(2);
+        for batch_id in ["b-1", "b-2", "b-3"] {
+            reports.track(batch_id);
+        }
+        assert!(reports.get("b-1").is_none());
+        assert!(!reports.record("b-1", "a", 10, 1));
+        assert!(reports.record("b-3", "a", 10, 1));
+    }
+}
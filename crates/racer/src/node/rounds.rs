@@ -0,0 +1,511 @@
+//! Gossip rounds.
+//!
+//! The [`ConsensusCore`](super::consensus::ConsensusCore) decides what each
+//! round event calls for; the handlers here feed it events from received
+//! batches, echoes and responses, and from rounds this node starts, and
+//! carry out the commands it returns.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use super::consensus::{Command, Event, Request};
+use super::events::{NodeEvent, RoundPhase};
+use super::pipeline::Delivery;
+use super::topics::RoundTopic;
+use super::{Node, NodeError, NodeInner, SUBSCRIBE_SETTLE};
+use crate::config::Thresholds;
+use crate::protocol::{
+    BatchedMessages, CongestionUpdate, Echo, EchoType, GossipRound, Priority, ProtocolMessage, ProtocolResponse,
+    ProtocolResponseType, RelayPayload, RoundOutcome,
+};
+use crate::Message;
+
+/// Re-gossip rounds of relayed batches run at once with `node.low_power`.
+const LOW_POWER_RELAYS: usize = 16;
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Drops undelivered rounds past the gossip timeout, retiring their
+    /// batches, unsubscribes their response topics and reports the missed
+    /// delivery to PLATO. Returns how many rounds were dropped.
+    pub(super) async fn expire_rounds(inner: &NodeInner<M>) -> usize {
+        let mut expired = 0;
+        for table in inner.gossip_state.tables() {
+            let commands = inner.consensus.handle(&mut *table.write().await, Event::Tick);
+            expired += commands
+                .iter()
+                .filter(|c| matches!(c, Command::Forget { timed_out: true, .. }))
+                .count();
+            if let Err(e) = Self::execute(inner, commands).await {
+                tracing::warn!(id = %inner.id, error = %e, "round maintenance failed");
+            }
+        }
+        expired
+    }
+
+    /// With `node.low_power`, runs the re-gossip rounds of relayed batches
+    /// on this one task, up to [`LOW_POWER_RELAYS`] at once.
+    pub(super) fn spawn_relayer(&self, mut batches: mpsc::Receiver<BatchedMessages<M>>) -> JoinHandle<()> {
+        let inner = Arc::clone(&self.inner);
+
+        tokio::spawn(async move {
+            let inner = &*inner;
+            let mut rounds: Vec<Pin<Box<dyn Future<Output = ()> + Send + '_>>> = Vec::new();
+            loop {
+                let batch = tokio::select! {
+                    batch = batches.recv(), if rounds.len() < LOW_POWER_RELAYS => batch,
+                    // Wakes when a round ends, freeing a slot.
+                    () = std::future::poll_fn(|cx| {
+                        let before = rounds.len();
+                        rounds.retain_mut(|round| round.as_mut().poll(cx).is_pending());
+                        if rounds.len() < before { Poll::Ready(()) } else { Poll::Pending }
+                    }) => continue,
+                };
+                let Some(batch) = batch else { break };
+                rounds.push(Box::pin(Self::regossip(inner, batch)));
+            }
+        })
+    }
+
+    /// Handles a verified Echo/Ready response, returning the batch if it just reached delivery.
+    pub(super) async fn handle_response(
+        inner: &NodeInner<M>,
+        response: ProtocolResponse,
+    ) -> Result<Option<Delivery<M>>, NodeError> {
+        let from = response.sender_id();
+        Self::record_clock(inner, &from, response.timestamp).await;
+        let hash = response.topic;
+        let event = match response.response_type {
+            ProtocolResponseType::EchoResponse => {
+                tracing::debug!(id = %inner.id, topic = %hash, from = %from, "received EchoResponse");
+                Event::EchoResponseReceived { hash: hash.clone(), from }
+            }
+            ProtocolResponseType::ReadyResponse => {
+                tracing::debug!(id = %inner.id, topic = %hash, from = %from, "received ReadyResponse");
+                Event::ReadyResponseReceived { hash: hash.clone(), from }
+            }
+        };
+
+        let commands = inner.consensus.handle(&mut *inner.gossip_state.rounds(&hash).write().await, event);
+        Self::execute(inner, commands).await
+    }
+
+    pub(super) async fn inbox_batched(
+        inner: &NodeInner<M>,
+        bm: BatchedMessages<M>,
+    ) -> Result<CongestionUpdate, NodeError> {
+        let bm_hash = bm.compute_hash();
+
+        let creator_id = bm.creator_ecdsa.to_hex()[..10].to_string();
+        tracing::info!(
+            id = %inner.id,
+            hash = %bm_hash,
+            creator = %creator_id,
+            "received BatchedMessages"
+        );
+
+        if !inner.authorization.permits(&bm) {
+            inner.pipeline.record_rejected();
+            tracing::warn!(id = %inner.id, hash = %bm_hash, creator = %creator_id, "creator not authorized, batch rejected");
+            return Ok(CongestionUpdate::ok());
+        }
+        if inner.equivocation.is_penalized(&bm.creator_ecdsa) {
+            inner.pipeline.record_rejected();
+            tracing::debug!(id = %inner.id, hash = %bm_hash, creator = %creator_id, "creator equivocated, batch rejected");
+            return Ok(CongestionUpdate::ok());
+        }
+        if let Some(evidence) = inner.equivocation.check(&bm_hash, &bm) {
+            inner.pipeline.record_equivocation();
+            tracing::warn!(
+                id = %inner.id,
+                creator = %creator_id,
+                first = %evidence.first.batch_id,
+                second = %evidence.second.batch_id,
+                sequence = bm.sequence,
+                "creator signed conflicting batches; no longer echoing its batches"
+            );
+            let _ = inner.events.send(NodeEvent::Equivocation {
+                creator: evidence.creator.to_hex(),
+                first: serde_json::to_value(&evidence.first).unwrap_or_default(),
+                second: serde_json::to_value(&evidence.second).unwrap_or_default(),
+            });
+            return Ok(CongestionUpdate::ok());
+        }
+        if let Err(e) = inner.validator.check(&bm).await {
+            inner.pipeline.record_rejected();
+            tracing::warn!(id = %inner.id, hash = %bm_hash, error = %e, "validator rejected batch");
+            return Ok(CongestionUpdate::ok());
+        }
+
+        let thresholds = Self::round_thresholds(inner, bm.channel.as_deref(), bm.priority).await;
+        let event = Event::BatchReceived {
+            hash: bm_hash.clone(),
+            batch: bm.clone(),
+            thresholds,
+            // `created_at` is on the creator's clock.
+            now_ms: Self::peer_now(inner, &creator_id).await,
+        };
+        let mut commands = inner.consensus.handle(&mut *inner.gossip_state.rounds(&bm_hash).write().await, event);
+        // Most likely a late copy of a batch handled before, but the seen-set
+        // has false positives: take part in the round, leave re-gossip to others.
+        if inner.gossip_state.has_seen(&bm_hash).await {
+            commands.retain(|command| !matches!(command, Command::Relay { .. }));
+            tracing::debug!(id = %inner.id, hash = %bm_hash, "batch seen before, not re-gossiping");
+        }
+
+        if !matches!(commands[..], [Command::Expired { .. }]) {
+            inner.gossip_state.store_message(bm_hash.clone(), bm.clone()).await;
+            inner.vector_clock.write().await.merge(&bm.vector_clock);
+            inner.topics.hold_round(&bm_hash).await;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Self::execute(inner, commands).await?;
+
+        let latency = inner.plato.read().await.current_latency();
+        Ok(CongestionUpdate::new(latency, false))
+    }
+
+    pub(super) async fn inbox_echo(
+        inner: &NodeInner<M>,
+        echo: Echo,
+    ) -> Result<CongestionUpdate, NodeError> {
+        Self::record_clock(inner, &echo.sender_id(), echo.timestamp).await;
+        let hash = echo.topic;
+        let event = match echo.echo_type {
+            EchoType::EchoSubscribe => Event::EchoSubscribeReceived { hash: hash.clone() },
+            EchoType::ReadySubscribe => Event::ReadySubscribeReceived { hash: hash.clone() },
+        };
+        let commands = inner.consensus.handle(&mut *inner.gossip_state.rounds(&hash).write().await, event);
+        Self::execute(inner, commands).await?;
+
+        Ok(CongestionUpdate::ok())
+    }
+
+    /// Carries out the consensus core's commands in order. Returns the batch
+    /// of a round they delivered, if any.
+    async fn execute(inner: &NodeInner<M>, commands: Vec<Command<M>>) -> Result<Option<Delivery<M>>, NodeError> {
+        let mut delivery = None;
+        // A round's fan-out comes in one call; each frame is signed and
+        // encoded once and shared by every peer it goes to.
+        let (mut echo_msg, mut ready_msg, mut batch_msg) = (None, None, None);
+
+        for command in commands {
+            match command {
+                // Observers follow rounds without taking part in them.
+                Command::Publish { hash, response } if !inner.config.node.role.answers_rounds() => {
+                    tracing::trace!(id = %inner.id, hash = %hash, ?response, "observer, not publishing");
+                }
+                Command::Publish { hash, response: ProtocolResponseType::EchoResponse } => {
+                    Self::publish_echo_response(inner, &hash).await?;
+                }
+                Command::Publish { hash, response: ProtocolResponseType::ReadyResponse } => {
+                    Self::publish_ready_response(inner, &hash).await?;
+                    tracing::debug!(id = %inner.id, hash = %hash, "published ReadyResponse");
+                }
+                Command::SendToPeer { peer_id, hash, request, priority } => {
+                    let msg = match request {
+                        Request::EchoSubscribe => match &echo_msg {
+                            Some(msg) => msg.clone(),
+                            None => echo_msg
+                                .insert(Self::encode_echo(inner, EchoType::EchoSubscribe, &hash).await?)
+                                .clone(),
+                        },
+                        Request::ReadySubscribe => match &ready_msg {
+                            Some(msg) => msg.clone(),
+                            None => ready_msg
+                                .insert(Self::encode_echo(inner, EchoType::ReadySubscribe, &hash).await?)
+                                .clone(),
+                        },
+                        Request::Batch(bm) => match &batch_msg {
+                            Some(msg) => msg.clone(),
+                            None => batch_msg
+                                .insert(Self::encode(&ProtocolMessage::BatchedMessages(bm))?)
+                                .clone(),
+                        },
+                    };
+                    Self::send_best_effort(inner, &peer_id, msg, priority).await;
+                }
+                Command::Relay { batch } => Self::relay(inner, batch).await,
+                Command::Deliver { hash } => {
+                    inner.outbox.remove(&hash);
+                    inner.topics.finish_round(&hash);
+                    let _ = inner.events.send(NodeEvent::RoundDelivered { hash: hash.clone() });
+                    inner.gossip_state.retire(&hash, RoundOutcome::Delivered).await;
+                    delivery = inner
+                        .gossip_state
+                        .get_message(&hash)
+                        .await
+                        .map(|batch| Delivery { hash, batch });
+                    if let Some(delivery) = &delivery {
+                        Self::report_delivery(inner, delivery).await;
+                    }
+                }
+                Command::Forget { hash, timed_out } => {
+                    // A timed-out round's batch is held for its retention;
+                    // an evicted delivered round's batch goes with it.
+                    if timed_out {
+                        inner.gossip_state.retire(&hash, RoundOutcome::TimedOut).await;
+                    } else {
+                        inner.gossip_state.forget(std::slice::from_ref(&hash)).await;
+                    }
+                    inner.topics.finish_round(&hash);
+                    if timed_out {
+                        inner.plato.write().await.set_missed_delivery(true);
+                        tracing::debug!(id = %inner.id, hash = %hash, "dropped timed-out round");
+                    }
+                }
+                Command::TimedOut { hash, phase } => {
+                    let _ = inner.events.send(NodeEvent::RoundTimedOut { hash, phase });
+                }
+                Command::EchoRtt { from, rtt } => {
+                    Self::record_echo_rtt(inner, &from, rtt.as_secs_f64()).await;
+                }
+                Command::Expired { hash } => {
+                    inner.pipeline.record_expired();
+                    tracing::debug!(id = %inner.id, hash = %hash, "dropped expired BatchedMessages");
+                }
+                Command::HopLimited { hash } => {
+                    inner.pipeline.record_hop_limited();
+                    tracing::debug!(id = %inner.id, hash = %hash, "hop limit reached, not re-gossiping");
+                }
+            }
+        }
+        Ok(delivery)
+    }
+
+    /// Re-gossips a received batch as this node's relay, in the background:
+    /// on the relayer with `node.low_power`, else on a task of its own.
+    pub(super) async fn relay(inner: &NodeInner<M>, bm: BatchedMessages<M>) {
+        let mut bm_as_sender = bm.relay_copy(inner.signer.public_key());
+        bm_as_sender.sender_signature = Some(Self::sign(inner, &bm_as_sender.sender_signing_bytes()).await);
+        if let Some(relays) = inner.relays.read().await.as_ref() {
+            if relays.try_send(bm_as_sender).is_err() {
+                tracing::warn!(id = %inner.id, "relay queue full, not re-gossiping");
+            }
+            return;
+        }
+        // Holds while `inner` is borrowed, as it always lives in its Arc.
+        let Some(inner) = inner.this.upgrade() else {
+            return;
+        };
+        tokio::spawn(async move { Self::regossip(&inner, bm_as_sender).await });
+    }
+
+    async fn regossip(inner: &NodeInner<M>, bm: BatchedMessages<M>) {
+        if let Err(e) = Self::gossip_inner(inner, bm).await {
+            tracing::warn!(error = %e, "re-gossip failed");
+        }
+    }
+
+    /// Feeds an Echo round-trip time to PLATO and to the sender's registry entry.
+    async fn record_echo_rtt(inner: &NodeInner<M>, sender_id: &str, rtt: f64) {
+        inner.plato.write().await.record_our_latency(rtt);
+
+        let mut peers = inner.peers.write().await;
+        if let Some(id) = peers.find_by_key_id(sender_id).map(|p| p.id.clone()) {
+            peers.update_latency(&id, rtt);
+        }
+    }
+
+    async fn publish_echo_response(inner: &NodeInner<M>, topic: &str) -> Result<(), NodeError> {
+        let response = ProtocolResponse::echo_response(topic, inner.signer.public_key());
+        Self::publish_response(inner, &RoundTopic::Echo.name(topic), response).await
+    }
+
+    async fn publish_ready_response(inner: &NodeInner<M>, topic: &str) -> Result<(), NodeError> {
+        let response = ProtocolResponse::ready_response(topic, inner.signer.public_key());
+        Self::publish_response(inner, &RoundTopic::Ready.name(topic), response).await
+    }
+
+    /// Signs `response` and publishes it on `topic`, and through the relay
+    /// too when `node.relay_via` is set, as peers cannot subscribe to us.
+    async fn publish_response(
+        inner: &NodeInner<M>,
+        topic: &str,
+        mut response: ProtocolResponse,
+    ) -> Result<(), NodeError> {
+        response.signature = Some(Self::sign(inner, &response.signing_bytes()).await);
+
+        let msg = serde_json::to_vec(&response)
+            .map_err(|e| NodeError::Serialization(e.to_string()))?;
+
+        if let Some(relay) = &inner.config.node.relay_via {
+            let payload = RelayPayload::Publish {
+                topic: topic.to_string(),
+                frame: String::from_utf8(msg.clone()).map_err(|e| NodeError::Serialization(e.to_string()))?,
+            };
+            Self::send_relay_frame(inner, relay, payload, Priority::High).await?;
+        }
+
+        inner.network
+            .publish(topic, msg)
+            .await
+            .map_err(|e| NodeError::Network(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// A signed subscribe request for `hash`, encoded as a `ProtocolMessage`.
+    pub(super) async fn encode_echo(inner: &NodeInner<M>, echo_type: EchoType, hash: &str) -> Result<Bytes, NodeError> {
+        let mut echo = Echo::new(echo_type, hash, inner.signer.public_key());
+        echo.signature = Some(Self::sign(inner, &echo.signing_bytes()).await);
+        Self::encode(&ProtocolMessage::<M>::Echo(echo))
+    }
+
+    /// Runs a round for the local submission `hash`, which stays in the
+    /// outbox for another attempt unless it delivers. Returns whether it did.
+    pub(super) async fn gossip_submission(inner: &NodeInner<M>, bm: BatchedMessages<M>, hash: String) -> Result<bool, NodeError> {
+        let result = Self::gossip_inner(inner, bm).await;
+        let delivered = inner.gossip_state.was_recently_delivered(&hash).await;
+        if delivered {
+            inner.outbox.remove(&hash);
+        } else {
+            inner.outbox.finish_attempt(&hash, racer_core::message::now_millis());
+        }
+        result.map(|()| delivered)
+    }
+
+    /// Thresholds for a round of a `priority` batch on `channel` starting
+    /// now, with any percentages resolved against the current peer count.
+    pub(super) async fn round_thresholds(inner: &NodeInner<M>, channel: Option<&str>, priority: Priority) -> Thresholds {
+        let known_peers = inner.peers.read().await.participants().count();
+        inner.config.consensus.batch_thresholds(channel, priority, known_peers)
+    }
+
+    pub(super) async fn gossip_inner(inner: &NodeInner<M>, bm: BatchedMessages<M>) -> Result<(), NodeError> {
+        let hash = bm.compute_hash();
+        let span = tracing::info_span!(
+            "gossip_round",
+            id = %inner.id,
+            hash = %hash,
+            batch_id = %bm.batch_id,
+            size = bm.messages.len(),
+        );
+        Self::run_round(inner, bm, hash).instrument(span).await
+    }
+
+    async fn run_round(inner: &NodeInner<M>, bm: BatchedMessages<M>, hash: String) -> Result<(), NodeError> {
+        let config = &inner.config.consensus;
+        let i_am_creator = inner.signer.public_key().to_hex() == bm.creator_ecdsa.to_hex();
+
+        tracing::debug!(
+            id = %inner.id,
+            hash = %hash,
+            batch_id = %bm.batch_id,
+            creator = %i_am_creator,
+            "starting gossip"
+        );
+
+        let round_started = Instant::now();
+        let _ = inner.events.send(NodeEvent::RoundStarted {
+            hash: hash.clone(),
+            batch_id: bm.batch_id.clone(),
+        });
+        let fanout = inner.cluster_health.read().await.fanout_multiplier();
+        let mut echo_fanout = (config.echo_sample_size as f64 * fanout).round() as usize;
+        let mut ready_fanout = (config.ready_sample_size as f64 * fanout).round() as usize;
+        if !i_am_creator {
+            let creator_now = Self::peer_now(inner, &bm.creator_ecdsa.to_hex()[..10]).await;
+            let age_millis = creator_now.saturating_sub(bm.created_at);
+            let age_secs = age_millis as f64 / 1000.0;
+            echo_fanout = config.regossip_fanout(echo_fanout, age_secs);
+            ready_fanout = config.regossip_fanout(ready_fanout, age_secs);
+        }
+
+        let echo_peers = Self::select_peers(inner, echo_fanout).await;
+        let ready_peers = Self::select_peers(inner, ready_fanout).await;
+        let observers = Self::select_observers(inner, echo_fanout).await;
+        let thresholds = Self::round_thresholds(inner, bm.channel.as_deref(), bm.priority).await;
+        inner.gossip_state.store_message(hash.clone(), bm.clone()).await;
+
+        // Released when this call returns, on any path.
+        let _echo_lease = if !echo_peers.is_empty() {
+            Some(inner.topics.lease(&hash, RoundTopic::Echo).await)
+        } else {
+            None
+        };
+        let _ready_lease = if !ready_peers.is_empty() {
+            Some(inner.topics.lease(&hash, RoundTopic::Ready).await)
+        } else {
+            None
+        };
+
+        tokio::time::sleep(SUBSCRIBE_SETTLE).await;
+
+        let event = Event::GossipStarted {
+            hash: hash.clone(),
+            batch: bm,
+            thresholds,
+            echo_peers,
+            ready_peers,
+            observers,
+        };
+        let commands = inner.consensus.handle(&mut *inner.gossip_state.rounds(&hash).write().await, event);
+        Self::execute(inner, commands).await?;
+
+        // Responses advance the round as they arrive; this only waits for
+        // each phase to finish or time out.
+        let timings = inner.plato.read().await.round_timings();
+        let delivered = Self::await_phase(inner, &hash, RoundPhase::Echo, timings.echo_timeout).await?
+            && Self::await_phase(inner, &hash, RoundPhase::Ready, timings.ready_timeout).await?;
+
+        if delivered {
+            tracing::info!(id = %inner.id, hash = %hash, "message DELIVERED (gossip)");
+            inner.topics.finish_round(&hash);
+            inner
+                .health_tracker
+                .write()
+                .await
+                .record_delivered(round_started.elapsed().as_secs_f64());
+        } else {
+            tracing::warn!(id = %inner.id, hash = %hash, "message delivery FAILED");
+            inner.health_tracker.write().await.record_failed();
+        }
+
+        Ok(())
+    }
+
+    /// Polls round `hash` until `phase` completes, or reports the phase as
+    /// timed out after `timeout`. Returns whether it completed.
+    async fn await_phase(
+        inner: &NodeInner<M>,
+        hash: &str,
+        phase: RoundPhase,
+        timeout: Duration,
+    ) -> Result<bool, NodeError> {
+        let (poll, done): (_, fn(&GossipRound) -> bool) = match phase {
+            RoundPhase::Echo => (Duration::from_millis(100), |round| round.echo_complete),
+            RoundPhase::Ready => (Duration::from_millis(50), |round| round.delivered),
+        };
+        let start = Instant::now();
+        loop {
+            {
+                let state = inner.gossip_state.rounds(hash).read().await;
+                if state.get_round(hash).is_some_and(done) || state.was_recently_delivered(hash) {
+                    return Ok(true);
+                }
+            }
+
+            if start.elapsed() > timeout {
+                tracing::warn!(id = %inner.id, hash = %hash, ?phase, "phase timeout");
+                let event = Event::PhaseTimedOut { hash: hash.to_string(), phase };
+                let commands = inner.consensus.handle(&mut *inner.gossip_state.rounds(hash).write().await, event);
+                Self::execute(inner, commands).await?;
+                return Ok(false);
+            }
+
+            tokio::time::sleep(poll).await;
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Pacing of the gossip rounds this node starts for its own submissions.
+//!
+//! PLATO's publish frequency is the spacing it wants between this node's
+//! rounds: it widens as the network congests and narrows as it clears.
+//! Submissions, and retries of undelivered ones, are queued on the
+//! [`PublishScheduler`] instead of gossiped inline, and the node's publish
+//! task starts them most urgent first, oldest first within a [`Priority`].
+//! Up to `node.publish_burst` rounds start back to back; after that they are
+//! spaced one publish interval apart until the burst refills at the same rate.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{oneshot, Notify};
+
+use super::NodeError;
+use crate::protocol::{BatchedMessages, Priority};
+
+/// How a queued round ended: whether the batch was delivered.
+pub(crate) type DispatchResult = Result<bool, NodeError>;
+
+/// A local submission waiting for its round.
+pub(crate) struct Dispatch<M> {
+    pub(crate) batch: BatchedMessages<M>,
+    pub(crate) hash: String,
+    /// Told how the round ended, if anyone still waits.
+    pub(crate) done: Option<oneshot::Sender<DispatchResult>>,
+}
+
+/// Token bucket spacing rounds one interval apart once a burst is spent.
+#[derive(Debug)]
+struct Pacer {
+    /// 0 disables pacing.
+    burst: usize,
+    /// Negative while rounds are waiting on tokens not yet refilled.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Pacer {
+    fn new(burst: usize) -> Self {
+        Self {
+            burst,
+            tokens: burst as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a token for a round at `now`, refilling one per `interval`
+    /// since the last, and returns how long the round waits for it.
+    fn delay(&mut self, interval: Duration, now: Instant) -> Duration {
+        if self.burst == 0 {
+            return Duration::ZERO;
+        }
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.refilled_at = now;
+        let refill = if interval.is_zero() {
+            self.burst as f64
+        } else {
+            elapsed.as_secs_f64() / interval.as_secs_f64()
+        };
+        self.tokens = (self.tokens + refill).min(self.burst as f64) - 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            interval.mul_f64(-self.tokens)
+        }
+    }
+}
+
+struct Queue<M> {
+    next_seq: u64,
+    dispatches: BTreeMap<(Priority, u64), Dispatch<M>>,
+}
+
+/// Local submissions waiting for their rounds, most urgent first.
+pub(crate) struct PublishScheduler<M> {
+    queue: Mutex<Queue<M>>,
+    queued: Notify,
+    pacer: Mutex<Pacer>,
+}
+
+impl<M> PublishScheduler<M> {
+    /// Starts up to `burst` rounds back to back; 0 disables pacing.
+    pub(crate) fn new(burst: usize) -> Self {
+        Self {
+            queue: Mutex::new(Queue {
+                next_seq: 0,
+                dispatches: BTreeMap::new(),
+            }),
+            queued: Notify::new(),
+            pacer: Mutex::new(Pacer::new(burst)),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue<M>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues the round for the local submission `hash`. The receiver is
+    /// told how it ended; dropping it leaves the round queued.
+    pub(crate) fn enqueue(&self, batch: BatchedMessages<M>, hash: String) -> oneshot::Receiver<DispatchResult> {
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut queue = self.lock();
+            let seq = queue.next_seq;
+            queue.next_seq += 1;
+            queue.dispatches.insert(
+                (batch.priority, seq),
+                Dispatch {
+                    batch,
+                    hash,
+                    done: Some(tx),
+                },
+            );
+        }
+        self.queued.notify_one();
+        rx
+    }
+
+    /// Waits for the pacer, then takes the most urgent queued round.
+    /// Single consumer: the node's publish task.
+    pub(crate) async fn next(&self, publish_interval: Duration) -> Dispatch<M> {
+        loop {
+            if !self.lock().dispatches.is_empty() {
+                break;
+            }
+            self.queued.notified().await;
+        }
+        let delay = self
+            .pacer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .delay(publish_interval, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        // A more urgent round queued during the delay goes first.
+        let (_, dispatch) = self.lock().dispatches.pop_first().expect("only the publish task takes rounds");
+        dispatch
+    }
+
+    /// Rounds waiting to start.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.lock().dispatches.len()
+    }
+
+    /// Stops telling anyone how the queued rounds end, failing their
+    /// waiters, while keeping the rounds queued for a restart.
+    pub(crate) fn abandon_waiters(&self) {
+        for dispatch in self.lock().dispatches.values_mut() {
+            dispatch.done = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::VectorClock;
+    use racer_core::message::DefaultMessage;
+
+    fn batch(priority: Priority) -> BatchedMessages<DefaultMessage> {
+        let key = KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: "b1".into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority,
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_pacer_spaces_rounds_after_burst() {
+        let interval = Duration::from_secs(1);
+        let start = Instant::now();
+        let mut pacer = Pacer::new(2);
+        pacer.refilled_at = start;
+
+        assert_eq!(pacer.delay(interval, start), Duration::ZERO);
+        assert_eq!(pacer.delay(interval, start), Duration::ZERO);
+        assert_eq!(pacer.delay(interval, start), interval);
+        // The round that waited took the refilled token.
+        assert_eq!(pacer.delay(interval, start + interval), interval);
+        // Idle long enough, the burst is back, but no more than that.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(pacer.delay(interval, later), Duration::ZERO);
+        assert_eq!(pacer.delay(interval, later), Duration::ZERO);
+        assert_eq!(pacer.delay(interval, later), interval);
+
+        let mut unpaced = Pacer::new(0);
+        for _ in 0..10 {
+            assert_eq!(unpaced.delay(interval, start), Duration::ZERO);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_most_urgent_round_first() {
+        let scheduler = PublishScheduler::new(0);
+        let _low = scheduler.enqueue(batch(Priority::Low), "low".into());
+        let _first = scheduler.enqueue(batch(Priority::Normal), "first".into());
+        let _high = scheduler.enqueue(batch(Priority::High), "high".into());
+        let _second = scheduler.enqueue(batch(Priority::Normal), "second".into());
+        assert_eq!(scheduler.len(), 4);
+
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            order.push(scheduler.next(Duration::from_secs(1)).await.hash);
+        }
+        assert_eq!(order, ["high", "first", "second", "low"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiters_fail() {
+        let scheduler = PublishScheduler::new(0);
+        let done = scheduler.enqueue(batch(Priority::Normal), "a".into());
+        scheduler.abandon_waiters();
+        assert!(done.await.is_err());
+        assert_eq!(scheduler.len(), 1);
+    }
+}
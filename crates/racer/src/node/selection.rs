@@ -0,0 +1,201 @@
+//! How each round picks the peers it gossips to.
+//!
+//! A round asks for a sample of `n` peers, its fan-out, and
+//! `node.selection_type` decides which:
+//!
+//! - `normal` and `random`: `n` peers uniformly at random.
+//! - `poisson`: a uniformly random sample whose size is drawn from a
+//!   Poisson distribution with mean `n`, at least one peer, so fan-out varies
+//!   from round to round around the configured one.
+//! - `round_robin`: the next `n` peers in id order after those the last
+//!   round took, wrapping around, so every peer is sampled equally often
+//!   over time.
+//!
+//! Only peers that answer rounds are sampled: observers (`node.role`) never
+//! send Echo or Ready responses, so a round gains nothing from waiting on
+//! them. They get each batch from a uniform sample of their own instead; see
+//! [`PeerSelector::observers`].
+//!
+//! Draws come from one RNG, seeded by `node.rng_seed` when set, and peers are
+//! taken in id order before drawing, so a seeded node picks the same peers
+//! whatever order they were learned in.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_distr::{Distribution, Poisson};
+
+use crate::config::SelectionType;
+use crate::network::{PeerInfo, PeerRegistry};
+
+pub(crate) struct PeerSelector {
+    kind: SelectionType,
+    rng: StdRng,
+    /// Where the next `round_robin` sample starts, in id order.
+    cursor: usize,
+}
+
+impl PeerSelector {
+    pub(crate) fn new(kind: SelectionType, seed: Option<u64>) -> Self {
+        Self {
+            kind,
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            cursor: 0,
+        }
+    }
+
+    /// The peers of one round with fan-out `n`.
+    pub(crate) fn select<'a>(&mut self, peers: &'a PeerRegistry, n: usize) -> Vec<&'a PeerInfo> {
+        let candidates = Self::in_id_order(peers.participants());
+        match self.kind {
+            SelectionType::Normal | SelectionType::Random => self.shuffled(candidates, n),
+            SelectionType::Poisson => {
+                let n = self.poisson_size(n);
+                self.shuffled(candidates, n)
+            }
+            SelectionType::RoundRobin => self.round_robin(candidates, n),
+        }
+    }
+
+    /// Up to `n` observers uniformly at random, sent a round's batch alone.
+    pub(crate) fn observers<'a>(&mut self, peers: &'a PeerRegistry, n: usize) -> Vec<&'a PeerInfo> {
+        let observers = Self::in_id_order(peers.iter().filter(|peer| !peer.role.answers_rounds()));
+        self.shuffled(observers, n)
+    }
+
+    /// Up to `n` peers uniformly at random, whatever the selection type.
+    pub(crate) fn random<'a>(&mut self, peers: &'a PeerRegistry, n: usize) -> Vec<&'a PeerInfo> {
+        peers.select_random_with(n, &mut self.rng)
+    }
+
+    fn in_id_order<'a>(peers: impl Iterator<Item = &'a PeerInfo>) -> Vec<&'a PeerInfo> {
+        let mut peers: Vec<&PeerInfo> = peers.collect();
+        peers.sort_by(|a, b| a.id.cmp(&b.id));
+        peers
+    }
+
+    fn shuffled<'a>(&mut self, mut peers: Vec<&'a PeerInfo>, n: usize) -> Vec<&'a PeerInfo> {
+        peers.shuffle(&mut self.rng);
+        peers.truncate(n);
+        peers
+    }
+
+    fn poisson_size(&mut self, mean: usize) -> usize {
+        if mean == 0 {
+            return 0;
+        }
+        let poisson = Poisson::new(mean as f64).expect("a positive mean is a valid Poisson rate");
+        (poisson.sample(&mut self.rng) as usize).max(1)
+    }
+
+    fn round_robin<'a>(&mut self, mut ordered: Vec<&'a PeerInfo>, n: usize) -> Vec<&'a PeerInfo> {
+        if ordered.is_empty() {
+            return ordered;
+        }
+        let take = n.min(ordered.len());
+        let start = self.cursor % ordered.len();
+        self.cursor = (start + take) % ordered.len();
+        ordered.rotate_left(start);
+        ordered.truncate(take);
+        ordered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::*;
+    use crate::config::NodeRole;
+    use crate::crypto::KeyPair;
+
+    fn registry(n: usize) -> PeerRegistry {
+        let mut registry = PeerRegistry::new();
+        for i in 0..n {
+            registry.add_peer(PeerInfo::new(
+                format!("peer-{:02}", i),
+                KeyPair::generate().public_key(),
+                "tcp://127.0.0.1:1",
+                "tcp://127.0.0.1:2",
+            ));
+        }
+        registry
+    }
+
+    #[test]
+    fn test_poisson_sizes_center_on_the_fanout() {
+        let peers = registry(30);
+        let mut selector = PeerSelector::new(SelectionType::Poisson, Some(1));
+        let sizes: Vec<f64> = (0..4_000).map(|_| selector.select(&peers, 6).len() as f64).collect();
+
+        let mean = sizes.iter().sum::<f64>() / sizes.len() as f64;
+        let variance = sizes.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / sizes.len() as f64;
+        assert!((mean - 6.0).abs() < 0.3, "mean {}", mean);
+        // A Poisson's variance equals its mean.
+        assert!((variance - 6.0).abs() < 1.0, "variance {}", variance);
+        assert!(sizes.iter().all(|&s| s >= 1.0));
+        assert!(sizes.iter().any(|&s| s != 6.0));
+        assert!(selector.select(&peers, 0).is_empty());
+    }
+
+    #[test]
+    fn test_round_robin_samples_every_peer_evenly() {
+        let peers = registry(7);
+        let mut selector = PeerSelector::new(SelectionType::RoundRobin, None);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..7 {
+            let round = selector.select(&peers, 3);
+            assert_eq!(round.len(), 3);
+            assert_eq!(round.iter().map(|p| &p.id).collect::<HashSet<_>>().len(), 3);
+            for peer in round {
+                *counts.entry(peer.id.clone()).or_default() += 1;
+            }
+        }
+        assert_eq!(counts.len(), 7);
+        assert!(counts.values().all(|&count| count == 3), "{:?}", counts);
+
+        assert_eq!(selector.select(&peers, 10).len(), 7);
+        assert!(selector.select(&PeerRegistry::new(), 3).is_empty());
+    }
+
+    #[test]
+    fn test_observers_are_sampled_apart() {
+        let mut peers = registry(6);
+        for id in ["peer-01", "peer-04"] {
+            peers.get_mut(id).unwrap().role = NodeRole::Observer;
+        }
+        peers.get_mut("peer-02").unwrap().role = NodeRole::Forwarder;
+
+        for kind in [SelectionType::Random, SelectionType::Poisson, SelectionType::RoundRobin] {
+            let mut selector = PeerSelector::new(kind, Some(5));
+            for _ in 0..20 {
+                let round = selector.select(&peers, 10);
+                assert!(round.iter().all(|p| p.role != NodeRole::Observer), "{:?}", kind);
+            }
+        }
+        let mut selector = PeerSelector::new(SelectionType::Normal, Some(5));
+        assert_eq!(selector.select(&peers, 10).len(), 4);
+        let observers = selector.observers(&peers, 10);
+        assert_eq!(observers.iter().map(|p| p.id.as_str()).collect::<HashSet<_>>(), HashSet::from(["peer-01", "peer-04"]));
+        assert_eq!(selector.observers(&peers, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_random_samples_are_distinct_and_spread() {
+        let peers = registry(10);
+        let mut selector = PeerSelector::new(SelectionType::Random, Some(3));
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..2_000 {
+            let round = selector.select(&peers, 4);
+            assert_eq!(round.iter().map(|p| &p.id).collect::<HashSet<_>>().len(), 4);
+            for peer in round {
+                *counts.entry(peer.id.clone()).or_default() += 1;
+            }
+        }
+        // 800 expected per peer.
+        assert!(counts.values().all(|&count| (650..950).contains(&count)), "{:?}", counts);
+    }
+}
@@ -0,0 +1,99 @@
+//! Numbering of the batches this node creates.
+//!
+//! With `node.sequence_path` set, every batch created here carries the next
+//! number in
+//! [`BatchedMessages::sequence`](crate::protocol::BatchedMessages::sequence),
+//! from 1. Each number is written to that file before its batch is signed,
+//! and a restart carries on after the last one, so no number is ever reused:
+//! receivers take two batches under one number for the creator
+//! equivocating. Without the file, batches go unnumbered (0).
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub(crate) struct SequenceCounter {
+    /// The last number handed out; 0 before the first.
+    last: Mutex<u64>,
+    /// `None` leaves batches unnumbered.
+    path: Option<PathBuf>,
+}
+
+impl SequenceCounter {
+    /// Resumes after the number saved at `path`, if there is one.
+    pub(crate) fn open(path: Option<PathBuf>) -> io::Result<Self> {
+        let last = match &path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => text
+                    .trim()
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("not a sequence number: {}", e)))?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            },
+            None => 0,
+        };
+        Ok(Self {
+            last: Mutex::new(last),
+            path,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, u64> {
+        self.last.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Takes the next number, saving it first. If it cannot be saved it is
+    /// not taken, and the next call tries it again.
+    pub(crate) fn next(&self) -> io::Result<u64> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        let mut last = self.lock();
+        let next = *last + 1;
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        write!(file, "{}", next)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        *last = next;
+        Ok(next)
+    }
+
+    /// The number the next batch will carry.
+    pub(crate) fn peek(&self) -> u64 {
+        match self.path {
+            Some(_) => *self.lock() + 1,
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbering_resumes_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sequence");
+
+        let counter = SequenceCounter::open(Some(path.clone())).unwrap();
+        assert_eq!(counter.peek(), 1);
+        assert_eq!(counter.next().unwrap(), 1);
+        assert_eq!(counter.next().unwrap(), 2);
+        drop(counter);
+
+        let counter = SequenceCounter::open(Some(path.clone())).unwrap();
+        assert_eq!(counter.next().unwrap(), 3);
+
+        let unnumbered = SequenceCounter::open(None).unwrap();
+        assert_eq!(unnumbered.next().unwrap(), 0);
+        assert_eq!(unnumbered.peek(), 0);
+
+        std::fs::write(&path, "three").unwrap();
+        assert!(SequenceCounter::open(Some(path)).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Delivery sinks: where a node hands each batch it delivers.
+//!
+//! Every registered [`DeliverySink`] sees each delivered batch once, from the
+//! deliver stage (see [`super::pipeline`]) or, for batches this node created,
+//! from the gossip round itself. Sinks run inline on those tasks, so an
+//! implementation should hand slow work off rather than block.
+//!
+//! Shipped sinks: [`DeliveredMessageLogger`] (the `logging.delivered_file`
+//! log in `logging.delivered_format`), [`StdoutSink`] and [`ChannelSink`].
+
+use std::io::Write;
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::protocol::BatchedMessages;
+use crate::util::logging::DeliveredMessageLogger;
+
+pub trait DeliverySink<M>: Send + Sync {
+    fn on_delivered(&self, batch: &BatchedMessages<M>);
+}
+
+impl<M: Serialize> DeliverySink<M> for DeliveredMessageLogger {
+    fn on_delivered(&self, batch: &BatchedMessages<M>) {
+        self.log(
+            &batch.batch_id,
+            &batch.creator_ecdsa.to_hex(),
+            &batch.merkle_root,
+            batch.batch_size,
+            &batch.messages,
+        );
+    }
+}
+
+/// Writes each delivered batch to stdout as one JSON line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl<M: Serialize> DeliverySink<M> for StdoutSink {
+    fn on_delivered(&self, batch: &BatchedMessages<M>) {
+        match serde_json::to_string(batch) {
+            Ok(line) => {
+                let mut stdout = std::io::stdout().lock();
+                let _ = writeln!(stdout, "{}", line);
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to encode delivered batch for stdout"),
+        }
+    }
+}
+
+/// Forwards delivered batches to a bounded channel. Batches that arrive while
+/// the channel is full are dropped, so a slow consumer never stalls delivery.
+pub struct ChannelSink<M> {
+    sender: mpsc::Sender<BatchedMessages<M>>,
+}
+
+impl<M> ChannelSink<M> {
+    pub fn new(capacity: usize) -> (Self, mpsc::Receiver<BatchedMessages<M>>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (Self { sender }, receiver)
+    }
+}
+
+impl<M: Clone + Send> DeliverySink<M> for ChannelSink<M> {
+    fn on_delivered(&self, batch: &BatchedMessages<M>) {
+        if let Err(mpsc::error::TrySendError::Full(batch)) = self.sender.try_send(batch.clone()) {
+            tracing::warn!(batch_id = %batch.batch_id, "delivery channel full, batch dropped");
+        }
+    }
+}
+
+/// The sinks registered on a node, shared by every task that delivers.
+pub struct DeliverySinks<M> {
+    sinks: Arc<RwLock<Vec<Arc<dyn DeliverySink<M>>>>>,
+}
+
+impl<M> DeliverySinks<M> {
+    pub fn new() -> Self {
+        Self {
+            sinks: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn add(&self, sink: Arc<dyn DeliverySink<M>>) {
+        self.sinks.write().unwrap_or_else(|e| e.into_inner()).push(sink);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn deliver(&self, batch: &BatchedMessages<M>) {
+        for sink in self.sinks.read().unwrap_or_else(|e| e.into_inner()).iter() {
+            sink.on_delivered(batch);
+        }
+    }
+}
+
+impl<M> Clone for DeliverySinks<M> {
+    fn clone(&self) -> Self {
+        Self {
+            sinks: Arc::clone(&self.sinks),
+        }
+    }
+}
+
+impl<M> Default for DeliverySinks<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::VectorClock;
+    use racer_core::message::DefaultMessage;
+
+    fn batch(batch_id: &str) -> BatchedMessages<DefaultMessage> {
+        let key = KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: batch_id.into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 0,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    #[test]
+    fn test_every_sink_sees_each_batch() {
+        let sinks = DeliverySinks::<DefaultMessage>::new();
+        let (first, mut first_rx) = ChannelSink::<DefaultMessage>::new(4);
+        let (second, mut second_rx) = ChannelSink::<DefaultMessage>::new(4);
+        sinks.add(Arc::new(first));
+        sinks.clone().add(Arc::new(second));
+        assert_eq!(sinks.len(), 2);
+
+        sinks.deliver(&batch("b1"));
+        assert_eq!(first_rx.try_recv().unwrap().batch_id, "b1");
+        assert_eq!(second_rx.try_recv().unwrap().batch_id, "b1");
+    }
+
+    #[test]
+    fn test_channel_sink_drops_when_full() {
+        let (sink, mut rx) = ChannelSink::<DefaultMessage>::new(1);
+        sink.on_delivered(&batch("b1"));
+        sink.on_delivered(&batch("b2"));
+
+        assert_eq!(rx.try_recv().unwrap().batch_id, "b1");
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Local submissions.
+//!
+//! A submitted message is built into a signed batch, numbered and stamped
+//! with the vector clock, then queued on the publish scheduler and kept in
+//! the outbox until a round delivers it.
+
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::oneshot;
+
+use super::pipeline::FrameLimits;
+use super::scheduler;
+use super::{DryRunReport, Node, NodeError, NodeInner, SUBSCRIBE_SETTLE};
+use crate::protocol::{BatchedMessages, Priority, ProtocolMessage, VectorClock};
+use crate::Message;
+
+/// How often `wait_for_peers` looks at the registry.
+const PEER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Submits `message` on the named consensus channel. Its round uses the
+    /// channel's thresholds from `consensus.channels`, if configured, and it
+    /// is delivered to [`subscribe_channel`](Self::subscribe_channel) receivers
+    /// of that channel.
+    pub async fn submit_on(&self, channel: &str, message: M) -> Result<String, NodeError> {
+        self.submit_batch(Some(channel), Priority::Normal, message).await
+    }
+
+    pub async fn submit(&self, message: M) -> Result<String, NodeError> {
+        self.submit_batch(None, Priority::Normal, message).await
+    }
+
+    /// Submits `message` at `priority`, which travels with the batch. Under
+    /// load, higher priorities start their rounds first and jump ahead in
+    /// per-peer send queues. The round uses `consensus.priorities` thresholds for `priority`, if
+    /// configured and the batch has no channel of its own.
+    pub async fn submit_with_priority(&self, message: M, priority: Priority) -> Result<String, NodeError> {
+        self.submit_batch(None, priority, message).await
+    }
+
+    /// Queues the batch for its round, which the publish task starts once
+    /// PLATO's pacing allows; the returned id is known before then.
+    async fn submit_batch(&self, channel: Option<&str>, priority: Priority, message: M) -> Result<String, NodeError> {
+        Self::check_peers(&self.inner).await?;
+        let bm = Self::prepare_batch(&self.inner, channel, priority, message).await?;
+        let batch_id = bm.batch_id.clone();
+        let hash = bm.compute_hash();
+
+        self.inner.outbox.insert(&hash, &bm, racer_core::message::now_millis());
+        let _ = self.inner.scheduler.enqueue(bm, hash);
+
+        Ok(batch_id)
+    }
+
+    /// Fails with `InsufficientPeers` while fewer peers are known than
+    /// `consensus.min_peers_for_submit`.
+    pub(super) async fn check_peers(inner: &NodeInner<M>) -> Result<(), NodeError> {
+        let need = inner.config.consensus.min_peers_for_submit;
+        let have = inner.peers.read().await.len();
+        if have < need {
+            return Err(NodeError::InsufficientPeers { have, need });
+        }
+        Ok(())
+    }
+
+    /// Waits until at least `n` peers are known, returning how many are.
+    /// Fails with `InsufficientPeers` if `timeout` passes first.
+    pub async fn wait_for_peers(&self, n: usize, timeout: Duration) -> Result<usize, NodeError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let have = self.inner.peers.read().await.len();
+            if have >= n {
+                return Ok(have);
+            }
+            if Instant::now() >= deadline {
+                return Err(NodeError::InsufficientPeers { have, need: n });
+            }
+            tokio::time::sleep(PEER_POLL_INTERVAL.min(deadline - Instant::now())).await;
+        }
+    }
+
+    /// Validates `message` and estimates what submitting it would cost, without
+    /// touching the vector clock or sending anything.
+    pub async fn dry_run(&self, message: M) -> Result<DryRunReport, NodeError> {
+        let validation_error = message.validate().err().map(|e| e.to_string());
+        let message_size = serde_json::to_vec(&message)
+            .map_err(|e| NodeError::Serialization(e.to_string()))?
+            .len();
+
+        let mut vector_clock = self.inner.vector_clock.read().await.clone();
+        Self::tick_clock(&self.inner, &mut vector_clock);
+
+        let sequence = self.inner.sequence.peek();
+        let bm = Self::build_batch(&self.inner, None, Priority::Normal, message, vector_clock, sequence).await?;
+        let envelope_size = serde_json::to_vec(&ProtocolMessage::BatchedMessages(bm.clone()))
+            .map_err(|e| NodeError::Serialization(e.to_string()))?
+            .len();
+
+        let config = &self.inner.config.consensus;
+        let fanout = self.inner.cluster_health.read().await.fanout_multiplier();
+        let (known_peers, participants) = {
+            let peers = self.inner.peers.read().await;
+            (peers.len(), peers.participants().count())
+        };
+        let echo_peers = ((config.echo_sample_size as f64 * fanout).round() as usize).min(participants);
+        let ready_peers = ((config.ready_sample_size as f64 * fanout).round() as usize).min(participants);
+
+        let timings = self.inner.plato.read().await.round_timings();
+        let round_timeout = SUBSCRIBE_SETTLE + timings.round_timeout();
+
+        Ok(DryRunReport {
+            valid: validation_error.is_none(),
+            validation_error,
+            batch_id: bm.batch_id.clone(),
+            batch_hash: bm.compute_hash(),
+            merkle_root: bm.merkle_root.clone(),
+            message_size,
+            envelope_size,
+            known_peers,
+            echo_peers,
+            ready_peers,
+            phase_timeout_secs: timings.echo_timeout.as_secs_f64(),
+            estimated_round_timeout_secs: round_timeout.as_secs_f64(),
+        })
+    }
+
+    /// Like [`submit`](Self::submit), but waits for the round and reports
+    /// whether this node delivered the batch by the time it finished.
+    pub async fn submit_and_wait(&self, message: M) -> Result<(String, bool), NodeError> {
+        Self::check_peers(&self.inner).await?;
+        let bm = Self::prepare_batch(&self.inner, None, Priority::Normal, message).await?;
+        let batch_id = bm.batch_id.clone();
+        let hash = bm.compute_hash();
+
+        self.inner.outbox.insert(&hash, &bm, racer_core::message::now_millis());
+        let done = self.inner.scheduler.enqueue(bm, hash);
+        let delivered = Self::round_outcome(done).await?;
+        Ok((batch_id, delivered))
+    }
+
+    /// Waits for a queued round to end. Fails if the node stopped first;
+    /// the submission stays pending and runs once it restarts.
+    pub(super) async fn round_outcome(done: oneshot::Receiver<scheduler::DispatchResult>) -> Result<bool, NodeError> {
+        done.await.map_err(|_| NodeError::Protocol("node stopped before the submission's round".into()))?
+    }
+
+    /// Ticks the vector clock and builds the signed batch for a local
+    /// submission. Fails if the batch would be over `consensus.max_batch_bytes`,
+    /// as peers would reject it, or on a forwarder node.
+    pub(super) async fn prepare_batch(
+        inner: &NodeInner<M>,
+        channel: Option<&str>,
+        priority: Priority,
+        message: M,
+    ) -> Result<BatchedMessages<M>, NodeError> {
+        if !inner.config.node.role.delivers() {
+            return Err(NodeError::Protocol("a forwarder node does not submit batches".into()));
+        }
+        FrameLimits::from_config(&inner.config)
+            .check_messages(std::slice::from_ref(&message))
+            .map_err(NodeError::Protocol)?;
+        let sequence = Self::next_sequence(inner)?;
+        let mut vc = inner.vector_clock.write().await;
+        Self::tick_clock(inner, &mut vc);
+        let vector_clock = vc.clone();
+        drop(vc);
+
+        let bm = Self::build_batch(inner, channel, priority, message, vector_clock, sequence).await?;
+        inner.reports.track(&bm.batch_id);
+        Ok(bm)
+    }
+
+    /// Takes the sequence number for a batch created here.
+    pub(super) fn next_sequence(inner: &NodeInner<M>) -> Result<u64, NodeError> {
+        inner
+            .sequence
+            .next()
+            .map_err(|e| NodeError::Store(format!("failed to save batch sequence number: {}", e)))
+    }
+
+    /// Increments this node's entry, then prunes per the consensus config so
+    /// the clock embedded in each batch stays bounded as peers churn.
+    pub(super) fn tick_clock(inner: &NodeInner<M>, vc: &mut VectorClock) {
+        vc.increment(&inner.id);
+
+        let config = &inner.config.consensus;
+        let mut pruned = 0;
+        if let Some(max_age) = config.vector_clock_max_age_secs {
+            let cutoff = racer_core::message::now_millis().saturating_sub(max_age.saturating_mul(1000));
+            pruned += vc.prune_older_than(cutoff);
+        }
+        if config.vector_clock_max_entries > 0 {
+            pruned += vc.prune_keeping(config.vector_clock_max_entries, &inner.id);
+        }
+        if pruned > 0 {
+            tracing::debug!(id = %inner.id, pruned, remaining = vc.len(), "pruned vector clock");
+        }
+    }
+
+    async fn build_batch(
+        inner: &NodeInner<M>,
+        channel: Option<&str>,
+        priority: Priority,
+        message: M,
+        vector_clock: VectorClock,
+        sequence: u64,
+    ) -> Result<BatchedMessages<M>, NodeError> {
+        let batch_id = format!("{}-{}", inner.id, message.id());
+        let merkle_root = crate::protocol::merkle_root(std::slice::from_ref(&message));
+
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        let mut bm = BatchedMessages {
+            batch_id,
+            creator_ecdsa: inner.signer.public_key(),
+            sender_ecdsa: inner.signer.public_key(),
+            merkle_root,
+            batch_size: 1,
+            messages: vec![message],
+            vector_clock,
+            creator_signature: None,
+            sender_signature: None,
+            created_at,
+            membership: Vec::new(),
+            channel: channel.map(str::to_string),
+            hops: 0,
+            max_hops: inner.config.consensus.hop_limit(),
+            ttl_ms: inner.config.consensus.batch_ttl(),
+            priority,
+            sequence,
+            #[cfg(feature = "bls")]
+            creator_bls: inner.signer.bls_public_key(),
+            #[cfg(feature = "bls")]
+            aggregated_signature: None, // Will be set below
+        };
+
+        // A signer without a BLS key leaves both BLS fields empty.
+        #[cfg(feature = "bls")]
+        if bm.creator_bls.is_some() {
+            let mut signatures = Vec::with_capacity(bm.messages.len());
+
+            for msg in &bm.messages {
+                 let msg_bytes = serde_json::to_vec(msg)
+                    .map_err(|e| NodeError::Serialization(e.to_string()))?;
+                 signatures.extend(inner.signer.bls_sign(&msg_bytes));
+            }
+
+            if !signatures.is_empty() {
+                let agg = crate::crypto::BlsSignature::aggregate(&signatures)
+                    .map_err(|e| NodeError::Crypto(e.to_string()))?;
+                bm.aggregated_signature = Some(agg);
+            }
+        }
+
+        Self::sign_batch(inner, &mut bm).await;
+
+        Ok(bm)
+    }
+
+    /// Signs `bm` as both its creator and its sender.
+    pub(super) async fn sign_batch(inner: &NodeInner<M>, bm: &mut BatchedMessages<M>) {
+        bm.creator_signature = Some(Self::sign(inner, &bm.creator_signing_bytes()).await);
+        bm.sender_signature = Some(Self::sign(inner, &bm.sender_signing_bytes()).await);
+    }
+}
@@ -0,0 +1,171 @@
+//! Reference-counted subscriptions to the per-round response topics.
+//!
+//! Peers publish their Echo and Ready responses for a batch on
+//! `<hash>-echo` and `<hash>-ready`. A topic is wanted by the round itself,
+//! from its start until it is delivered or dropped, and by every
+//! `gossip_inner` call spreading the batch, which may overlap. Each holder
+//! takes a [`TopicLease`]; the topic is subscribed when the first lease is
+//! taken and unsubscribed once the last one is dropped, however the holder
+//! exits.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::network::RacerNetwork;
+
+/// A round's response topics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RoundTopic {
+    Echo,
+    Ready,
+}
+
+impl RoundTopic {
+    pub(crate) fn name(self, hash: &str) -> String {
+        match self {
+            Self::Echo => format!("{}-echo", hash),
+            Self::Ready => format!("{}-ready", hash),
+        }
+    }
+}
+
+struct Shared {
+    network: Arc<RacerNetwork>,
+    /// Leases per subscribed topic. Subscribe and unsubscribe commands are
+    /// sent under this lock, so they reach the network in refcount order.
+    refs: AsyncMutex<HashMap<String, usize>>,
+    /// Leases held by rounds until they finish, by round hash.
+    rounds: Mutex<HashMap<String, Vec<TopicLease>>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct TopicManager {
+    shared: Arc<Shared>,
+}
+
+/// One holder's claim on a topic; released on drop.
+pub(crate) struct TopicLease {
+    shared: Arc<Shared>,
+    topic: String,
+}
+
+impl TopicManager {
+    pub(crate) fn new(network: Arc<RacerNetwork>) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                network,
+                refs: AsyncMutex::new(HashMap::new()),
+                rounds: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Subscribes to `topic` of round `hash` unless already subscribed.
+    pub(crate) async fn lease(&self, hash: &str, topic: RoundTopic) -> TopicLease {
+        let topic = topic.name(hash);
+        let mut refs = self.shared.refs.lock().await;
+        let count = refs.entry(topic.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            if let Err(e) = self.shared.network.subscribe_topic(&topic).await {
+                tracing::warn!(topic = %topic, error = %e, "failed to subscribe to round topic");
+            }
+        }
+        TopicLease {
+            shared: Arc::clone(&self.shared),
+            topic,
+        }
+    }
+
+    /// Leases both response topics for round `hash` until
+    /// [`finish_round`](Self::finish_round). Holding them again is a no-op.
+    pub(crate) async fn hold_round(&self, hash: &str) {
+        if self.holds_round(hash) {
+            return;
+        }
+        let leases = vec![
+            self.lease(hash, RoundTopic::Echo).await,
+            self.lease(hash, RoundTopic::Ready).await,
+        ];
+        self.shared
+            .rounds
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(hash.to_string())
+            .or_insert(leases);
+    }
+
+    /// Releases the leases round `hash` holds, if any.
+    pub(crate) fn finish_round(&self, hash: &str) {
+        let leases = self.shared.rounds.lock().unwrap_or_else(|e| e.into_inner()).remove(hash);
+        drop(leases);
+    }
+
+    pub(crate) fn holds_round(&self, hash: &str) -> bool {
+        self.shared
+            .rounds
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(hash)
+    }
+
+    /// Topics currently subscribed through the manager.
+    pub(crate) async fn subscribed(&self) -> usize {
+        self.shared.refs.lock().await.len()
+    }
+}
+
+impl Drop for TopicLease {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let shared = Arc::clone(&self.shared);
+        let topic = std::mem::take(&mut self.topic);
+        runtime.spawn(async move {
+            let mut refs = shared.refs.lock().await;
+            let Some(count) = refs.get_mut(&topic) else {
+                return;
+            };
+            *count -= 1;
+            if *count == 0 {
+                refs.remove(&topic);
+                let _ = shared.network.unsubscribe_topic(&topic).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn test_topic_lives_until_last_lease() {
+        let network = Arc::new(RacerNetwork::new("tcp://127.0.0.1:27401", "tcp://127.0.0.1:27402"));
+        let topics = TopicManager::new(Arc::clone(&network));
+
+        topics.hold_round("h1").await;
+        topics.hold_round("h1").await;
+        let call = topics.lease("h1", RoundTopic::Echo).await;
+        assert!(network.is_subscribed("h1-echo").await);
+        assert!(network.is_subscribed("h1-ready").await);
+
+        topics.finish_round("h1");
+        settle().await;
+        assert!(network.is_subscribed("h1-echo").await);
+        assert!(!network.is_subscribed("h1-ready").await);
+
+        drop(call);
+        settle().await;
+        assert!(!network.is_subscribed("h1-echo").await);
+        assert_eq!(topics.subscribed().await, 0);
+    }
+}
@@ -0,0 +1,181 @@
+//! Latency-weighted graph of the cluster as seen from one node.
+//!
+//! [`Node::topology_report`] joins the node's own peer registry with the
+//! neighbour lists peers attach to their signed [`HealthSummary`]s, so the
+//! graph reaches one hop past the peers this node talks to. Vertices are key
+//! ids, as neighbour lists carry nothing else; edges point from the node that
+//! measured the latency. [`TopologyReport::to_dot`] renders it for Graphviz.
+//!
+//! [`HealthSummary`]: crate::protocol::HealthSummary
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Instant;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{Node, NodeInner};
+use crate::network::PeerRegistry;
+use crate::protocol::health::MAX_NEIGHBORS;
+use crate::protocol::Neighbor;
+use crate::Message;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TopologyReport {
+    /// Key id of the reporting node.
+    pub local: String,
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<TopologyEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyNode {
+    pub key_id: String,
+    /// Registry id, for the reporting node and the peers it knows.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyEdge {
+    pub from: String,
+    pub to: String,
+    pub latency_secs: f64,
+    /// Seconds since `from` last heard from `to`, if ever.
+    pub last_seen_secs: Option<f64>,
+}
+
+impl TopologyReport {
+    /// The graph in Graphviz DOT. Edges are labelled with their latency,
+    /// which is also their preferred length under `neato`, and dashed if
+    /// `from` has never heard from `to`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph racer {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let label = match &node.label {
+                Some(label) => format!("{}\\n{}", escape(label), node.key_id),
+                None => node.key_id.clone(),
+            };
+            let style = if node.key_id == self.local { ", style=bold" } else { "" };
+            let _ = writeln!(dot, "    \"{}\" [label=\"{}\"{}];", node.key_id, label, style);
+        }
+        for edge in &self.edges {
+            let style = if edge.last_seen_secs.is_none() { ", style=dashed" } else { "" };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{:.1}ms\", len={:.3}{}];",
+                edge.from,
+                edge.to,
+                edge.latency_secs * 1000.0,
+                edge.latency_secs,
+                style
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<M> Node<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Known peers and the peers they report, with measured latencies.
+    pub async fn topology_report(&self) -> TopologyReport {
+        Self::topology_of(&self.inner).await
+    }
+
+    pub(super) async fn topology_of(inner: &NodeInner<M>) -> TopologyReport {
+        let local = inner.signer.public_key().to_hex()[..10].to_string();
+        let mut nodes = BTreeMap::new();
+        let mut edges = Vec::new();
+        nodes.insert(local.clone(), Some(inner.id.clone()));
+
+        let peers = inner.peers.read().await;
+        for peer in peers.iter() {
+            nodes.insert(peer.key_id(), Some(peer.id.clone()));
+        }
+        for neighbor in neighbors(&peers) {
+            edges.push(edge(&local, neighbor));
+        }
+
+        for (reporter, neighbors) in inner.cluster_health.read().await.neighbors() {
+            nodes.entry(reporter.clone()).or_insert(None);
+            for neighbor in neighbors {
+                nodes.entry(neighbor.key_id.clone()).or_insert(None);
+                edges.push(edge(&reporter, neighbor.clone()));
+            }
+        }
+
+        edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+        TopologyReport {
+            local,
+            nodes: nodes
+                .into_iter()
+                .map(|(key_id, label)| TopologyNode { key_id, label })
+                .collect(),
+            edges,
+        }
+    }
+
+}
+
+/// The peers in `registry` as a node reports them in its health summaries.
+pub(super) fn neighbors(registry: &PeerRegistry) -> Vec<Neighbor> {
+    let now = Instant::now();
+    registry
+        .iter()
+        .take(MAX_NEIGHBORS)
+        .map(|peer| Neighbor {
+            key_id: peer.key_id(),
+            latency_secs: peer.reported_latency,
+            last_seen_secs: peer.last_seen.map(|at| now.duration_since(at).as_secs_f64()),
+        })
+        .collect()
+}
+
+fn edge(from: &str, neighbor: Neighbor) -> TopologyEdge {
+    TopologyEdge {
+        from: from.to_string(),
+        to: neighbor.key_id,
+        latency_secs: neighbor.latency_secs,
+        last_seen_secs: neighbor.last_seen_secs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_output() {
+        let report = TopologyReport {
+            local: "aaaa".into(),
+            nodes: vec![
+                TopologyNode {
+                    key_id: "aaaa".into(),
+                    label: Some("node \"a\"".into()),
+                },
+                TopologyNode {
+                    key_id: "bbbb".into(),
+                    label: None,
+                },
+            ],
+            edges: vec![TopologyEdge {
+                from: "aaaa".into(),
+                to: "bbbb".into(),
+                latency_secs: 0.02,
+                last_seen_secs: None,
+            }],
+        };
+
+        let dot = report.to_dot();
+        assert!(dot.starts_with("digraph racer {"));
+        assert!(dot.contains(r#""aaaa" [label="node \"a\"\naaaa", style=bold];"#));
+        assert!(dot.contains(r#""bbbb" [label="bbbb"];"#));
+        assert!(dot.contains(r#""aaaa" -> "bbbb" [label="20.0ms", len=0.020, style=dashed];"#));
+    }
+}
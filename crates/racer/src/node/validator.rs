@@ -0,0 +1,145 @@
+//! Asynchronous payload validation.
+//!
+//! [`Message::validate`](crate::Message::validate) is synchronous, so checks
+//! that need I/O, such as looking a device up in a registry, cannot run
+//! there. An [`AsyncValidator`] set with
+//! [`Node::set_validator`](super::Node::set_validator) sees every batch
+//! received from a peer after its signatures are verified and before the
+//! node echoes it; a batch it rejects, or that it takes longer than
+//! `consensus.validation_timeout_ms` to judge, is dropped without taking
+//! part in its round. Imported batches are judged the same way.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use racer_core::{ValidationError, ValidationResult};
+
+use crate::protocol::BatchedMessages;
+
+pub type ValidateFuture<'a> = Pin<Box<dyn Future<Output = ValidationResult> + Send + 'a>>;
+
+/// Accepts or rejects a received batch, awaiting whatever I/O that takes.
+pub trait AsyncValidator<M>: Send + Sync {
+    fn validate<'a>(&'a self, batch: &'a BatchedMessages<M>) -> ValidateFuture<'a>;
+}
+
+/// Closures returning an owned future, for checks that copy what they need
+/// out of the batch first.
+impl<M, F, Fut> AsyncValidator<M> for F
+where
+    F: Fn(&BatchedMessages<M>) -> Fut + Send + Sync,
+    Fut: Future<Output = ValidationResult> + Send + 'static,
+{
+    fn validate<'a>(&'a self, batch: &'a BatchedMessages<M>) -> ValidateFuture<'a> {
+        Box::pin(self(batch))
+    }
+}
+
+/// The validator a node runs, if one is set.
+pub(crate) struct ValidatorSlot<M> {
+    validator: RwLock<Option<Arc<dyn AsyncValidator<M>>>>,
+    /// `None` waits as long as the validator takes.
+    timeout: Option<Duration>,
+}
+
+impl<M> ValidatorSlot<M> {
+    pub(crate) fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            validator: RwLock::new(None),
+            timeout,
+        }
+    }
+
+    pub(crate) fn set(&self, validator: Arc<dyn AsyncValidator<M>>) {
+        *self.validator.write().unwrap_or_else(|e| e.into_inner()) = Some(validator);
+    }
+
+    /// Runs the validator on `batch`; `Ok` if none is set.
+    pub(crate) async fn check(&self, batch: &BatchedMessages<M>) -> ValidationResult {
+        let Some(validator) = self.validator.read().unwrap_or_else(|e| e.into_inner()).clone() else {
+            return Ok(());
+        };
+        let Some(timeout) = self.timeout else {
+            return validator.validate(batch).await;
+        };
+        tokio::time::timeout(timeout, validator.validate(batch))
+            .await
+            .unwrap_or_else(|_| {
+                Err(ValidationError::custom(
+                    "batch",
+                    format!("validator did not answer within {}ms", timeout.as_millis()),
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::{Priority, VectorClock};
+    use racer_core::message::DefaultMessage;
+
+    fn batch(padding: u64) -> BatchedMessages<DefaultMessage> {
+        let key = KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: "b".into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::with_padding(padding)],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Priority::Normal,
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    /// Stands in for a registry lookup: only even paddings are registered.
+    fn registry(batch: &BatchedMessages<DefaultMessage>) -> impl Future<Output = ValidationResult> + Send + 'static {
+        let padding = batch.messages[0].padding;
+        async move {
+            tokio::task::yield_now().await;
+            if padding % 2 == 0 {
+                Ok(())
+            } else {
+                Err(ValidationError::custom("padding", "unregistered device"))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validator_judges_batches() {
+        let slot = ValidatorSlot::new(None);
+        assert!(slot.check(&batch(1)).await.is_ok(), "no validator accepts everything");
+
+        slot.set(Arc::new(registry));
+        assert!(slot.check(&batch(2)).await.is_ok());
+        assert!(slot.check(&batch(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_validator_rejects() {
+        let slot = ValidatorSlot::new(Some(Duration::from_millis(10)));
+        slot.set(Arc::new(|_: &BatchedMessages<DefaultMessage>| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }));
+        let err = slot.check(&batch(2)).await.unwrap_err();
+        assert!(err.to_string().contains("10ms"));
+    }
+}
@@ -1,11 +1,15 @@
 use std::collections::VecDeque;
+use std::time::Duration;
 
-use rand::Rng;
+use racer_core::message::now_millis;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use crate::config::PlatoConfig;
 
-use super::rsi::RsiIndicator;
-use super::smoothing::SavitzkyGolayFilter;
+use super::estimator::{build_estimator, CongestionEstimator};
+use super::trace::TraceRecorder;
 
 #[derive(Debug)]
 pub struct PlatoController {
@@ -14,36 +18,63 @@ pub struct PlatoController {
     publish_frequency: f64,
     our_latency: VecDeque<f64>,
     peer_latency: VecDeque<f64>,
-    our_rsi_up: RsiIndicator,
-    peer_rsi_up: RsiIndicator,
-    our_rsi_down: RsiIndicator,
-    peer_rsi_down: RsiIndicator,
-    our_savgol_up: SavitzkyGolayFilter,
-    peer_savgol_up: SavitzkyGolayFilter,
-    our_savgol_down: SavitzkyGolayFilter,
-    peer_savgol_down: SavitzkyGolayFilter,
+    estimator: Box<dyn CongestionEstimator>,
     max_samples: usize,
     recently_missed_delivery: bool,
+    backpressure: bool,
+    /// Fill of the fullest outbound send queue, from 0.0 to 1.0.
+    queue_depth: f64,
+    history: VecDeque<PlatoSample>,
+    /// What the last congestion check did.
+    decision: PlatoDecision,
+    recorder: Option<TraceRecorder>,
+    /// Draws the size of each throttle and accelerate step.
+    rng: StdRng,
     pub timing_changed: bool,
 }
 
+/// What a congestion check did to the latency estimate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatoDecision {
+    /// Left the estimate alone.
+    #[default]
+    Hold,
+    /// Doubled the latency estimate to catch up with measured latency.
+    FastForward,
+    /// Raised latency and publish frequency.
+    Throttle,
+    /// Lowered latency and publish frequency.
+    Accelerate,
+}
+
+impl PlatoDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlatoDecision::Hold => "hold",
+            PlatoDecision::FastForward => "fast_forward",
+            PlatoDecision::Throttle => "throttle",
+            PlatoDecision::Accelerate => "accelerate",
+        }
+    }
+}
+
 impl PlatoController {
     pub fn new(config: PlatoConfig) -> Self {
         Self {
-            our_rsi_up: RsiIndicator::new(config.rsi_increase_period),
-            peer_rsi_up: RsiIndicator::new(config.rsi_increase_period),
-            our_rsi_down: RsiIndicator::new(config.rsi_decrease_period),
-            peer_rsi_down: RsiIndicator::new(config.rsi_decrease_period),
-            our_savgol_up: SavitzkyGolayFilter::new(config.savgol_increase_window),
-            peer_savgol_up: SavitzkyGolayFilter::new(config.savgol_increase_window),
-            our_savgol_down: SavitzkyGolayFilter::new(config.savgol_decrease_window),
-            peer_savgol_down: SavitzkyGolayFilter::new(config.savgol_decrease_window),
+            estimator: build_estimator(&config),
             current_latency: config.target_latency_secs,
             publish_frequency: config.target_publishing_frequency_secs,
             our_latency: VecDeque::with_capacity(100),
             peer_latency: VecDeque::with_capacity(100),
             max_samples: 100,
             recently_missed_delivery: false,
+            backpressure: false,
+            queue_depth: 0.0,
+            history: VecDeque::with_capacity(config.history_size),
+            decision: PlatoDecision::Hold,
+            recorder: None,
+            rng: StdRng::from_entropy(),
             timing_changed: false,
             config,
         }
@@ -55,10 +86,8 @@ impl PlatoController {
             self.our_latency.pop_front();
         }
 
-        self.our_rsi_up.next(latency);
-        self.our_rsi_down.next(latency);
-        self.our_savgol_up.next(latency);
-        self.our_savgol_down.next(latency);
+        self.estimator.record_our_latency(latency);
+        self.trace(|recorder| recorder.our_latency(latency));
     }
 
     pub fn record_peer_latency(&mut self, latency: f64) {
@@ -67,30 +96,97 @@ impl PlatoController {
             self.peer_latency.pop_front();
         }
 
-        self.peer_rsi_up.next(latency);
-        self.peer_rsi_down.next(latency);
-        self.peer_savgol_up.next(latency);
-        self.peer_savgol_down.next(latency);
+        self.estimator.record_peer_latency(latency);
+        self.trace(|recorder| recorder.peer_latency(latency));
+    }
+
+    /// Appends every latency sample and congestion check from now on to
+    /// `recorder` (`plato.trace_file`).
+    pub fn set_trace_recorder(&mut self, recorder: TraceRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Draws throttle and accelerate steps from `seed` (`node.rng_seed`)
+    /// from now on, so the same samples move the estimate the same way.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Writes to the trace, if recording; a failed write stops recording.
+    fn trace(&mut self, write: impl FnOnce(&mut TraceRecorder) -> std::io::Result<()>) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = write(recorder) {
+                tracing::warn!(error = %e, "PLATO trace write failed; recording stopped");
+                self.recorder = None;
+            }
+        }
     }
 
     pub fn set_missed_delivery(&mut self, missed: bool) {
         self.recently_missed_delivery = missed;
     }
 
+    /// Notes that this node's own send queues or sockets overflowed. The
+    /// next [`check_increasing_congestion`](Self::check_increasing_congestion)
+    /// throttles whatever the latency samples say, and the following
+    /// [`check_decreasing_congestion`](Self::check_decreasing_congestion)
+    /// leaves the rates alone.
+    pub fn record_backpressure(&mut self) {
+        self.backpressure = true;
+    }
+
+    /// Records the fill of the fullest outbound send queue, from 0.0 to
+    /// 1.0. At `queue_depth_threshold` or above, PLATO throttles whatever
+    /// the latency samples say and does not accelerate; below it, fuller
+    /// queues still make each throttling step larger.
+    pub fn record_queue_depth(&mut self, depth: f64) {
+        self.queue_depth = depth.clamp(0.0, 1.0);
+    }
+
+    pub fn queue_depth(&self) -> f64 {
+        self.queue_depth
+    }
+
+    fn queue_saturated(&self) -> bool {
+        self.config.queue_depth_threshold > 0.0 && self.queue_depth >= self.config.queue_depth_threshold
+    }
+
+    /// Raises latency and publish frequency by 1-10%, up to twice that
+    /// with the send queues full.
+    fn throttle(&mut self) {
+        let increase = 1.0 + self.rng.gen_range(0.01..0.10) * (1.0 + self.queue_depth);
+        self.current_latency = (self.current_latency * increase).min(self.config.max_gossip_timeout_secs);
+        self.publish_frequency = (self.publish_frequency * increase).min(self.config.max_publishing_frequency_secs);
+        self.timing_changed = true;
+        self.decision = PlatoDecision::Throttle;
+    }
+
     pub fn check_increasing_congestion(&mut self) {
-        if !self.our_savgol_up.is_ready() || !self.peer_savgol_up.is_ready() {
+        self.decision = PlatoDecision::Hold;
+        if self.backpressure || self.queue_saturated() {
+            self.throttle();
+            tracing::debug!(
+                current_latency = self.current_latency,
+                publish_frequency = self.publish_frequency,
+                queue_depth = self.queue_depth,
+                "PLATO: throttling due to local backpressure"
+            );
+            return;
+        }
+        if !self.estimator.ready_up() {
             return;
         }
 
         let weighted_latest = self.weighted_latency();
-        let our_rsi = self.our_rsi_up.value();
-        let peer_rsi = self.peer_rsi_up.value();
+        let scores = self.estimator.scores();
+        let (our_rsi, peer_rsi) = (scores.our_up, scores.peer_up);
 
         if self.current_latency <= 0.5 * weighted_latest {
             let proposed = self.current_latency * 2.0;
             if proposed < self.config.max_gossip_timeout_secs * 0.85 {
                 self.current_latency = proposed;
                 self.timing_changed = true;
+                self.decision = PlatoDecision::FastForward;
                 tracing::debug!(
                     current_latency = self.current_latency,
                     "PLATO: fast-forward"
@@ -98,17 +194,7 @@ impl PlatoController {
             }
         }
         else if our_rsi > self.config.rsi_overbought && peer_rsi > self.config.rsi_overbought {
-            let mut rng = rand::thread_rng();
-            let increase = rng.gen_range(1.01..1.10);
-
-            self.current_latency = (self.current_latency * increase).min(
-                self.config.max_gossip_timeout_secs,
-            );
-            self.publish_frequency = (self.publish_frequency * increase).min(
-                self.config.max_publishing_frequency_secs,
-            );
-
-            self.timing_changed = true;
+            self.throttle();
             tracing::debug!(
                 current_latency = self.current_latency,
                 publish_frequency = self.publish_frequency,
@@ -120,16 +206,16 @@ impl PlatoController {
     }
 
     pub fn check_decreasing_congestion(&mut self) {
-        if !self.our_savgol_down.is_ready() || !self.peer_savgol_down.is_ready() {
+        let backpressure = std::mem::take(&mut self.backpressure);
+        if backpressure || self.queue_saturated() || !self.estimator.ready_down() {
             return;
         }
 
-        let our_rsi = self.our_rsi_down.value();
-        let peer_rsi = self.peer_rsi_down.value();
+        let scores = self.estimator.scores();
+        let (our_rsi, peer_rsi) = (scores.our_down, scores.peer_down);
 
         if our_rsi < self.config.rsi_oversold && peer_rsi < self.config.rsi_oversold {
-            let mut rng = rand::thread_rng();
-            let decrease = rng.gen_range(0.90..0.99);
+            let decrease = self.rng.gen_range(0.90..0.99);
 
             self.current_latency = (self.current_latency * decrease)
                 .max(self.config.minimum_latency_secs);
@@ -137,6 +223,7 @@ impl PlatoController {
                 .max(self.config.minimum_latency_secs);
 
             self.timing_changed = true;
+            self.decision = PlatoDecision::Accelerate;
             tracing::debug!(
                 current_latency = self.current_latency,
                 publish_frequency = self.publish_frequency,
@@ -156,35 +243,174 @@ impl PlatoController {
     }
 
     pub fn weighted_latency(&self) -> f64 {
-        let our_smoothed = self.our_savgol_up.value();
-        let peer_smoothed = self.peer_savgol_up.value();
+        self.estimator.weighted_latency()
+    }
 
-        let w = self.config.own_latency_weight;
-        w * our_smoothed + (1.0 - w) * peer_smoothed
+    pub fn estimator_name(&self) -> &'static str {
+        self.estimator.name()
     }
 
     pub fn recently_missed_delivery(&self) -> bool {
         self.recently_missed_delivery
     }
 
+    /// What the last [`check_increasing_congestion`](Self::check_increasing_congestion)
+    /// and [`check_decreasing_congestion`](Self::check_decreasing_congestion) did.
+    pub fn last_decision(&self) -> PlatoDecision {
+        self.decision
+    }
+
     pub fn clear_timing_changed(&mut self) {
         self.timing_changed = false;
     }
 
+    /// Round timeouts and pacing for the current latency estimate. Phase
+    /// timeouts follow `current_latency`, clamped to
+    /// `[min_phase_timeout_secs, max_gossip_timeout_secs]`.
+    pub fn round_timings(&self) -> RoundTimings {
+        let phase = self
+            .current_latency
+            .clamp(self.config.min_phase_timeout_secs, self.config.max_gossip_timeout_secs);
+        RoundTimings {
+            echo_timeout: Duration::from_secs_f64(phase),
+            ready_timeout: Duration::from_secs_f64(phase),
+            publish_interval: Duration::from_secs_f64(self.publish_frequency),
+        }
+    }
+
+    /// Appends a timestamped sample to the history ring buffer, and the
+    /// last decision to the trace if recording.
+    pub fn record_sample(&mut self) {
+        let (decision, queue_depth) = (self.decision, self.queue_depth);
+        let (current_latency, publish_frequency) = (self.current_latency, self.publish_frequency);
+        self.trace(|recorder| recorder.decision(decision, queue_depth, current_latency, publish_frequency));
+
+        if self.config.history_size == 0 {
+            return;
+        }
+
+        let scores = self.estimator.scores();
+        let sample = PlatoSample {
+            timestamp_ms: now_millis(),
+            current_latency: self.current_latency,
+            weighted_latency: self.weighted_latency(),
+            publish_frequency: self.publish_frequency,
+            our_rsi_up: scores.our_up,
+            our_rsi_down: scores.our_down,
+            peer_rsi_up: scores.peer_up,
+            peer_rsi_down: scores.peer_down,
+            queue_depth: self.queue_depth,
+        };
+
+        self.history.push_back(sample);
+        while self.history.len() > self.config.history_size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Returns samples recorded within `window` of now, oldest first.
+    pub fn history(&self, window: Duration) -> Vec<PlatoSample> {
+        let cutoff = now_millis().saturating_sub(window.as_millis() as u64);
+        self.history
+            .iter()
+            .filter(|s| s.timestamp_ms >= cutoff)
+            .cloned()
+            .collect()
+    }
+
+    /// The estimate and the latency samples behind it, for
+    /// [`load_state`](Self::load_state) after a restart.
+    pub fn save_state(&self) -> PlatoState {
+        PlatoState {
+            saved_at_ms: now_millis(),
+            current_latency: self.current_latency,
+            publish_frequency: self.publish_frequency,
+            our_latency: self.our_latency.iter().copied().collect(),
+            peer_latency: self.peer_latency.iter().copied().collect(),
+        }
+    }
+
+    /// Resumes from `state` unless it is older than `max_age`, replaying
+    /// its samples so the estimator is warm again. Values outside this
+    /// controller's configured bounds are clamped. Returns whether it was
+    /// used.
+    pub fn load_state(&mut self, state: PlatoState, max_age: Duration) -> bool {
+        let age = now_millis().saturating_sub(state.saved_at_ms);
+        if age > max_age.as_millis() as u64 {
+            return false;
+        }
+
+        let config = &self.config;
+        if state.current_latency.is_finite() {
+            self.current_latency = state
+                .current_latency
+                .clamp(config.minimum_latency_secs, config.max_gossip_timeout_secs);
+        }
+        if state.publish_frequency.is_finite() {
+            self.publish_frequency = state
+                .publish_frequency
+                .clamp(config.minimum_latency_secs, config.max_publishing_frequency_secs);
+        }
+        let skip_our = state.our_latency.len().saturating_sub(self.max_samples);
+        for latency in state.our_latency.into_iter().skip(skip_our) {
+            self.record_our_latency(latency);
+        }
+        let skip_peer = state.peer_latency.len().saturating_sub(self.max_samples);
+        for latency in state.peer_latency.into_iter().skip(skip_peer) {
+            self.record_peer_latency(latency);
+        }
+        self.timing_changed = true;
+        true
+    }
+
     pub fn stats(&self) -> PlatoStats {
+        let scores = self.estimator.scores();
         PlatoStats {
             current_latency: self.current_latency,
             publish_frequency: self.publish_frequency,
-            our_rsi_up: self.our_rsi_up.value(),
-            our_rsi_down: self.our_rsi_down.value(),
-            peer_rsi_up: self.peer_rsi_up.value(),
-            peer_rsi_down: self.peer_rsi_down.value(),
+            our_rsi_up: scores.our_up,
+            our_rsi_down: scores.our_down,
+            peer_rsi_up: scores.peer_up,
+            peer_rsi_down: scores.peer_down,
             our_latency_samples: self.our_latency.len(),
             peer_latency_samples: self.peer_latency.len(),
+            queue_depth: self.queue_depth,
         }
     }
 }
 
+/// Timing a gossip round should use, derived from PLATO's latency estimate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundTimings {
+    /// How long the creator waits for the echo threshold.
+    pub echo_timeout: Duration,
+    /// How long the creator waits for the delivery threshold once echoed.
+    pub ready_timeout: Duration,
+    /// Gap the publish scheduler keeps between a node's own rounds once
+    /// its burst is spent.
+    pub publish_interval: Duration,
+}
+
+impl RoundTimings {
+    /// Longest a round can take before both phases time out.
+    pub fn round_timeout(&self) -> Duration {
+        self.echo_timeout + self.ready_timeout
+    }
+}
+
+/// What [`PlatoController::save_state`] keeps across a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlatoState {
+    /// Epoch millis at which it was saved.
+    pub saved_at_ms: u64,
+    pub current_latency: f64,
+    pub publish_frequency: f64,
+    /// This node's latency samples, oldest first.
+    pub our_latency: Vec<f64>,
+    /// Peers' reported latency samples, oldest first.
+    pub peer_latency: Vec<f64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct PlatoStats {
     pub current_latency: f64,
@@ -195,6 +421,21 @@ pub struct PlatoStats {
     pub peer_rsi_down: f64,
     pub our_latency_samples: usize,
     pub peer_latency_samples: usize,
+    /// Fill of the fullest send queue at the last check.
+    pub queue_depth: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatoSample {
+    pub timestamp_ms: u64,
+    pub current_latency: f64,
+    pub weighted_latency: f64,
+    pub publish_frequency: f64,
+    pub our_rsi_up: f64,
+    pub our_rsi_down: f64,
+    pub peer_rsi_up: f64,
+    pub peer_rsi_down: f64,
+    pub queue_depth: f64,
 }
 
 #[cfg(test)]
@@ -209,6 +450,20 @@ mod tests {
         assert!((controller.current_latency() - config.target_latency_secs).abs() < 0.001);
     }
 
+    #[test]
+    fn test_seeded_steps_repeat() {
+        let run = || {
+            let mut controller = PlatoController::new(PlatoConfig::default());
+            controller.seed_rng(7);
+            for _ in 0..3 {
+                controller.record_backpressure();
+                controller.check_increasing_congestion();
+            }
+            controller.current_latency()
+        };
+        assert_eq!(run(), run());
+    }
+
     #[test]
     fn test_record_latency() {
         let config = PlatoConfig::default();
@@ -223,4 +478,112 @@ mod tests {
         assert_eq!(stats.our_latency_samples, 20);
         assert_eq!(stats.peer_latency_samples, 20);
     }
+
+    #[test]
+    fn test_state_survives_restart_while_fresh() {
+        let config = PlatoConfig::default();
+        let mut controller = PlatoController::new(config.clone());
+        for _ in 0..3 {
+            controller.record_backpressure();
+            controller.check_increasing_congestion();
+        }
+        for i in 0..30 {
+            controller.record_our_latency(3.0 + i as f64 * 0.1);
+            controller.record_peer_latency(3.5);
+        }
+        let state = controller.save_state();
+        let json = serde_json::to_string(&state).unwrap();
+
+        let mut restarted = PlatoController::new(config.clone());
+        assert!(restarted.load_state(serde_json::from_str(&json).unwrap(), Duration::from_secs(60)));
+        assert_eq!(restarted.current_latency(), controller.current_latency());
+        assert_eq!(restarted.publish_frequency(), controller.publish_frequency());
+        assert_eq!(restarted.stats().our_latency_samples, 30);
+        assert_eq!(restarted.weighted_latency(), controller.weighted_latency());
+
+        let stale = PlatoState {
+            saved_at_ms: state.saved_at_ms - 120_000,
+            ..state
+        };
+        let mut fresh = PlatoController::new(config.clone());
+        assert!(!fresh.load_state(stale, Duration::from_secs(60)));
+        assert_eq!(fresh.current_latency(), config.target_latency_secs);
+    }
+
+    #[test]
+    fn test_backpressure_throttles_once() {
+        let config = PlatoConfig::default();
+        let mut controller = PlatoController::new(config.clone());
+
+        controller.record_backpressure();
+        controller.check_increasing_congestion();
+        controller.check_decreasing_congestion();
+        let throttled = controller.current_latency();
+        assert!(throttled > config.target_latency_secs);
+        assert!(controller.publish_frequency() > config.target_publishing_frequency_secs);
+        assert!(controller.timing_changed);
+        assert_eq!(controller.last_decision(), PlatoDecision::Throttle);
+
+        controller.check_increasing_congestion();
+        assert_eq!(controller.current_latency(), throttled);
+        assert_eq!(controller.last_decision(), PlatoDecision::Hold);
+    }
+
+    #[test]
+    fn test_full_queues_throttle_and_hold() {
+        let config = PlatoConfig::default();
+        let mut controller = PlatoController::new(config.clone());
+
+        controller.record_queue_depth(0.5);
+        controller.check_increasing_congestion();
+        assert_eq!(controller.current_latency(), config.target_latency_secs);
+
+        controller.record_queue_depth(1.5);
+        assert_eq!(controller.queue_depth(), 1.0);
+        for _ in 0..3 {
+            let before = controller.current_latency();
+            controller.check_increasing_congestion();
+            controller.check_decreasing_congestion();
+            assert!(controller.current_latency() > before);
+        }
+
+        let mut off = PlatoController::new(PlatoConfig {
+            queue_depth_threshold: 0.0,
+            ..config.clone()
+        });
+        off.record_queue_depth(1.0);
+        off.check_increasing_congestion();
+        assert_eq!(off.current_latency(), config.target_latency_secs);
+    }
+
+    #[test]
+    fn test_round_timings_follow_latency() {
+        let config = PlatoConfig {
+            target_latency_secs: 8.0,
+            min_phase_timeout_secs: 5.0,
+            ..Default::default()
+        };
+        let timings = PlatoController::new(config).round_timings();
+        assert_eq!(timings.echo_timeout, Duration::from_secs(8));
+        assert_eq!(timings.round_timeout(), Duration::from_secs(16));
+
+        let timings = PlatoController::new(PlatoConfig::default()).round_timings();
+        assert_eq!(timings.echo_timeout, Duration::from_secs(5));
+        assert_eq!(timings.publish_interval, Duration::from_secs_f64(2.5));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let config = PlatoConfig {
+            history_size: 5,
+            ..Default::default()
+        };
+        let mut controller = PlatoController::new(config);
+
+        for _ in 0..12 {
+            controller.record_sample();
+        }
+
+        assert_eq!(controller.history(Duration::from_secs(60)).len(), 5);
+    }
 }
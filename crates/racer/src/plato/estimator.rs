@@ -0,0 +1,198 @@
+use std::fmt;
+
+use crate::config::{EstimatorKind, PlatoConfig};
+
+use super::ewma::EwmaEstimator;
+use super::kalman::KalmanEstimator;
+use super::rsi::RsiIndicator;
+use super::smoothing::SavitzkyGolayFilter;
+
+/// Relative trend at which a score saturates towards 0 or 100.
+const TREND_GAIN: f64 = 10.0;
+
+/// Source of congestion signals for the PLATO controller.
+///
+/// Scores are on the RSI scale: 0–100, 50 neutral, compared against
+/// `rsi_overbought` / `rsi_oversold` by the controller.
+pub trait CongestionEstimator: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn record_our_latency(&mut self, latency: f64);
+
+    fn record_peer_latency(&mut self, latency: f64);
+
+    /// Whether enough samples have been seen to act on rising congestion.
+    fn ready_up(&self) -> bool;
+
+    /// Whether enough samples have been seen to act on falling congestion.
+    fn ready_down(&self) -> bool;
+
+    /// Smoothed latency blending our own and peer observations.
+    fn weighted_latency(&self) -> f64;
+
+    fn scores(&self) -> CongestionScores;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CongestionScores {
+    pub our_up: f64,
+    pub our_down: f64,
+    pub peer_up: f64,
+    pub peer_down: f64,
+}
+
+pub fn build_estimator(config: &PlatoConfig) -> Box<dyn CongestionEstimator> {
+    match config.estimator {
+        EstimatorKind::Rsi => Box::new(RsiEstimator::new(config)),
+        EstimatorKind::Ewma => Box::new(EwmaEstimator::new(config)),
+        EstimatorKind::Kalman => Box::new(KalmanEstimator::new(config)),
+    }
+}
+
+/// Maps a relative trend (slope / level) onto the 0–100 score scale.
+pub(crate) fn trend_score(trend: f64, level: f64) -> f64 {
+    if level.abs() < f64::EPSILON {
+        return 50.0;
+    }
+    50.0 * (1.0 + (TREND_GAIN * trend / level.abs()).tanh())
+}
+
+/// RSI momentum over Savitzky-Golay smoothed latency.
+#[derive(Debug)]
+pub struct RsiEstimator {
+    own_latency_weight: f64,
+    our_rsi_up: RsiIndicator,
+    peer_rsi_up: RsiIndicator,
+    our_rsi_down: RsiIndicator,
+    peer_rsi_down: RsiIndicator,
+    our_savgol_up: SavitzkyGolayFilter,
+    peer_savgol_up: SavitzkyGolayFilter,
+    our_savgol_down: SavitzkyGolayFilter,
+    peer_savgol_down: SavitzkyGolayFilter,
+}
+
+impl RsiEstimator {
+    pub fn new(config: &PlatoConfig) -> Self {
+        Self {
+            own_latency_weight: config.own_latency_weight,
+            our_rsi_up: RsiIndicator::new(config.rsi_increase_period),
+            peer_rsi_up: RsiIndicator::new(config.rsi_increase_period),
+            our_rsi_down: RsiIndicator::new(config.rsi_decrease_period),
+            peer_rsi_down: RsiIndicator::new(config.rsi_decrease_period),
+            our_savgol_up: SavitzkyGolayFilter::with_order(config.savgol_increase_window, config.savgol_polynomial_order),
+            peer_savgol_up: SavitzkyGolayFilter::with_order(config.savgol_increase_window, config.savgol_polynomial_order),
+            our_savgol_down: SavitzkyGolayFilter::with_order(config.savgol_decrease_window, config.savgol_polynomial_order),
+            peer_savgol_down: SavitzkyGolayFilter::with_order(config.savgol_decrease_window, config.savgol_polynomial_order),
+        }
+    }
+}
+
+impl CongestionEstimator for RsiEstimator {
+    fn name(&self) -> &'static str {
+        "rsi"
+    }
+
+    fn record_our_latency(&mut self, latency: f64) {
+        self.our_rsi_up.next(latency);
+        self.our_rsi_down.next(latency);
+        self.our_savgol_up.next(latency);
+        self.our_savgol_down.next(latency);
+    }
+
+    fn record_peer_latency(&mut self, latency: f64) {
+        self.peer_rsi_up.next(latency);
+        self.peer_rsi_down.next(latency);
+        self.peer_savgol_up.next(latency);
+        self.peer_savgol_down.next(latency);
+    }
+
+    fn ready_up(&self) -> bool {
+        self.our_savgol_up.is_ready() && self.peer_savgol_up.is_ready()
+    }
+
+    fn ready_down(&self) -> bool {
+        self.our_savgol_down.is_ready() && self.peer_savgol_down.is_ready()
+    }
+
+    fn weighted_latency(&self) -> f64 {
+        let w = self.own_latency_weight;
+        w * self.our_savgol_up.value() + (1.0 - w) * self.peer_savgol_up.value()
+    }
+
+    fn scores(&self) -> CongestionScores {
+        CongestionScores {
+            our_up: self.our_rsi_up.value(),
+            our_down: self.our_rsi_down.value(),
+            peer_up: self.peer_rsi_up.value(),
+            peer_down: self.peer_rsi_down.value(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trend_score_bounds() {
+        assert!((trend_score(0.0, 2.0) - 50.0).abs() < 1e-9);
+        assert!(trend_score(1.0, 2.0) > 95.0);
+        assert!(trend_score(-1.0, 2.0) < 5.0);
+        assert!((trend_score(1.0, 0.0) - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_build_selects_estimator() {
+        for (kind, name) in [
+            (EstimatorKind::Rsi, "rsi"),
+            (EstimatorKind::Ewma, "ewma"),
+            (EstimatorKind::Kalman, "kalman"),
+        ] {
+            let config = PlatoConfig {
+                estimator: kind,
+                ..Default::default()
+            };
+            assert_eq!(build_estimator(&config).name(), name);
+        }
+    }
+
+    #[test]
+    fn test_estimators_detect_uptrend() {
+        for kind in [EstimatorKind::Rsi, EstimatorKind::Ewma, EstimatorKind::Kalman] {
+            let config = PlatoConfig {
+                estimator: kind,
+                ..Default::default()
+            };
+            let mut estimator = build_estimator(&config);
+            for i in 0..40 {
+                let latency = 2.0 + i as f64 * 0.2;
+                estimator.record_our_latency(latency);
+                estimator.record_peer_latency(latency);
+            }
+
+            assert!(estimator.ready_up(), "{} not ready", estimator.name());
+            let scores = estimator.scores();
+            assert!(scores.our_up > 70.0, "{}: {:?}", estimator.name(), scores);
+            assert!(scores.peer_up > 70.0, "{}: {:?}", estimator.name(), scores);
+        }
+    }
+
+    #[test]
+    fn test_estimators_neutral_on_flat_input() {
+        for kind in [EstimatorKind::Ewma, EstimatorKind::Kalman] {
+            let config = PlatoConfig {
+                estimator: kind,
+                ..Default::default()
+            };
+            let mut estimator = build_estimator(&config);
+            for _ in 0..40 {
+                estimator.record_our_latency(2.0);
+                estimator.record_peer_latency(2.0);
+            }
+
+            let scores = estimator.scores();
+            assert!((scores.our_up - 50.0).abs() < 1.0, "{}: {:?}", estimator.name(), scores);
+            assert!((estimator.weighted_latency() - 2.0).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use crate::config::PlatoConfig;
+
+use super::estimator::{trend_score, CongestionEstimator, CongestionScores};
+
+/// Fast/slow exponential moving average pair; the gap between them is the trend.
+#[derive(Debug, Clone)]
+pub struct EwmaCrossover {
+    period: usize,
+    alpha_fast: f64,
+    alpha_slow: f64,
+    fast: f64,
+    slow: f64,
+    samples: usize,
+}
+
+impl EwmaCrossover {
+    pub fn new(period: usize) -> Self {
+        assert!(period > 0, "period must be positive");
+        let fast_span = (period / 3).max(1);
+        Self {
+            period,
+            alpha_fast: 2.0 / (fast_span as f64 + 1.0),
+            alpha_slow: 2.0 / (period as f64 + 1.0),
+            fast: 0.0,
+            slow: 0.0,
+            samples: 0,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) {
+        if self.samples == 0 {
+            self.fast = value;
+            self.slow = value;
+        } else {
+            self.fast += self.alpha_fast * (value - self.fast);
+            self.slow += self.alpha_slow * (value - self.slow);
+        }
+        self.samples += 1;
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.samples >= self.period
+    }
+
+    pub fn value(&self) -> f64 {
+        self.fast
+    }
+
+    pub fn score(&self) -> f64 {
+        if !self.is_ready() {
+            return 50.0; // Neutral during warmup
+        }
+        trend_score(self.fast - self.slow, self.slow)
+    }
+}
+
+/// Congestion estimator based on EWMA crossovers.
+#[derive(Debug)]
+pub struct EwmaEstimator {
+    own_latency_weight: f64,
+    our_up: EwmaCrossover,
+    peer_up: EwmaCrossover,
+    our_down: EwmaCrossover,
+    peer_down: EwmaCrossover,
+}
+
+impl EwmaEstimator {
+    pub fn new(config: &PlatoConfig) -> Self {
+        Self {
+            own_latency_weight: config.own_latency_weight,
+            our_up: EwmaCrossover::new(config.rsi_increase_period),
+            peer_up: EwmaCrossover::new(config.rsi_increase_period),
+            our_down: EwmaCrossover::new(config.rsi_decrease_period),
+            peer_down: EwmaCrossover::new(config.rsi_decrease_period),
+        }
+    }
+}
+
+impl CongestionEstimator for EwmaEstimator {
+    fn name(&self) -> &'static str {
+        "ewma"
+    }
+
+    fn record_our_latency(&mut self, latency: f64) {
+        self.our_up.next(latency);
+        self.our_down.next(latency);
+    }
+
+    fn record_peer_latency(&mut self, latency: f64) {
+        self.peer_up.next(latency);
+        self.peer_down.next(latency);
+    }
+
+    fn ready_up(&self) -> bool {
+        self.our_up.is_ready() && self.peer_up.is_ready()
+    }
+
+    fn ready_down(&self) -> bool {
+        self.our_down.is_ready() && self.peer_down.is_ready()
+    }
+
+    fn weighted_latency(&self) -> f64 {
+        let w = self.own_latency_weight;
+        w * self.our_up.value() + (1.0 - w) * self.peer_up.value()
+    }
+
+    fn scores(&self) -> CongestionScores {
+        CongestionScores {
+            our_up: self.our_up.score(),
+            our_down: self.our_down.score(),
+            peer_up: self.peer_up.score(),
+            peer_down: self.peer_down.score(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossover_downtrend() {
+        let mut ewma = EwmaCrossover::new(14);
+        for i in 0..30 {
+            ewma.next(10.0 - i as f64 * 0.2);
+        }
+
+        assert!(ewma.is_ready());
+        assert!(ewma.score() < 30.0);
+    }
+
+    #[test]
+    fn test_crossover_warmup_neutral() {
+        let mut ewma = EwmaCrossover::new(14);
+        for i in 0..5 {
+            ewma.next(i as f64);
+        }
+
+        assert!(!ewma.is_ready());
+        assert!((ewma.score() - 50.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::PlatoConfig;
+
+use super::estimator::{trend_score, CongestionEstimator, CongestionScores};
+
+/// Constant-velocity Kalman filter over a latency series.
+#[derive(Debug, Clone)]
+pub struct KalmanFilter {
+    process_noise: f64,
+    measurement_noise: f64,
+    level: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+    samples: usize,
+}
+
+impl KalmanFilter {
+    pub fn new(process_noise: f64, measurement_noise: f64) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+            level: 0.0,
+            velocity: 0.0,
+            covariance: [[1.0, 0.0], [0.0, 1.0]],
+            samples: 0,
+        }
+    }
+
+    pub fn next(&mut self, value: f64) {
+        self.samples += 1;
+        if self.samples == 1 {
+            self.level = value;
+            return;
+        }
+
+        // Predict: x = F x, P = F P F' + Q with F = [[1, 1], [0, 1]]
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let q = self.process_noise;
+        self.level += self.velocity;
+        let p00 = p00 + p01 + p10 + p11 + 0.25 * q;
+        let p01 = p01 + p11 + 0.5 * q;
+        let p10 = p10 + p11 + 0.5 * q;
+        let p11 = p11 + q;
+
+        // Update with H = [1, 0]
+        let residual = value - self.level;
+        let s = p00 + self.measurement_noise;
+        let k0 = p00 / s;
+        let k1 = p10 / s;
+        self.level += k0 * residual;
+        self.velocity += k1 * residual;
+        self.covariance = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p10 - k1 * p00, p11 - k1 * p01],
+        ];
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    pub fn velocity(&self) -> f64 {
+        self.velocity
+    }
+
+    /// Score of the change projected over `horizon` samples.
+    pub fn score(&self, horizon: usize) -> f64 {
+        trend_score(self.velocity * horizon as f64, self.level)
+    }
+}
+
+/// Congestion estimator tracking latency level and slope with a Kalman filter.
+#[derive(Debug)]
+pub struct KalmanEstimator {
+    own_latency_weight: f64,
+    increase_period: usize,
+    decrease_period: usize,
+    ours: KalmanFilter,
+    peers: KalmanFilter,
+}
+
+impl KalmanEstimator {
+    pub fn new(config: &PlatoConfig) -> Self {
+        let filter = KalmanFilter::new(
+            config.kalman_process_noise,
+            config.kalman_measurement_noise,
+        );
+        Self {
+            own_latency_weight: config.own_latency_weight,
+            increase_period: config.rsi_increase_period,
+            decrease_period: config.rsi_decrease_period,
+            ours: filter.clone(),
+            peers: filter,
+        }
+    }
+
+    fn score(&self, filter: &KalmanFilter, period: usize) -> f64 {
+        if filter.samples() < period {
+            return 50.0; // Neutral during warmup
+        }
+        filter.score(period / 2)
+    }
+}
+
+impl CongestionEstimator for KalmanEstimator {
+    fn name(&self) -> &'static str {
+        "kalman"
+    }
+
+    fn record_our_latency(&mut self, latency: f64) {
+        self.ours.next(latency);
+    }
+
+    fn record_peer_latency(&mut self, latency: f64) {
+        self.peers.next(latency);
+    }
+
+    fn ready_up(&self) -> bool {
+        self.ours.samples() >= self.increase_period && self.peers.samples() >= self.increase_period
+    }
+
+    fn ready_down(&self) -> bool {
+        self.ours.samples() >= self.decrease_period && self.peers.samples() >= self.decrease_period
+    }
+
+    fn weighted_latency(&self) -> f64 {
+        let w = self.own_latency_weight;
+        w * self.ours.level() + (1.0 - w) * self.peers.level()
+    }
+
+    fn scores(&self) -> CongestionScores {
+        CongestionScores {
+            our_up: self.score(&self.ours, self.increase_period),
+            our_down: self.score(&self.ours, self.decrease_period),
+            peer_up: self.score(&self.peers, self.increase_period),
+            peer_down: self.score(&self.peers, self.decrease_period),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_tracks_slope() {
+        let mut filter = KalmanFilter::new(0.01, 0.25);
+        for i in 0..60 {
+            filter.next(5.0 + i as f64 * 0.5);
+        }
+
+        assert!((filter.velocity() - 0.5).abs() < 0.1);
+        assert!((filter.level() - 34.5).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_filter_downtrend_score() {
+        let mut filter = KalmanFilter::new(0.01, 0.25);
+        for i in 0..40 {
+            filter.next(12.0 - i as f64 * 0.2);
+        }
+
+        assert!(filter.score(10) < 30.0);
+    }
+}
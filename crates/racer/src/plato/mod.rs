@@ -1,8 +1,15 @@
 mod rsi;
 mod smoothing;
+mod estimator;
+mod ewma;
+mod kalman;
 mod controller;
+pub mod sim;
+pub mod trace;
 
-pub use controller::{PlatoController, PlatoStats};
+pub use controller::{PlatoController, PlatoDecision, PlatoSample, PlatoState, PlatoStats, RoundTimings};
+pub use estimator::{build_estimator, CongestionEstimator, CongestionScores, RsiEstimator};
+pub use ewma::{EwmaCrossover, EwmaEstimator};
+pub use kalman::{KalmanEstimator, KalmanFilter};
 pub use rsi::RsiIndicator;
-pub use smoothing::SavitzkyGolayFilter;
-
+pub use smoothing::{SavitzkyGolayFilter, DEFAULT_POLYNOMIAL_ORDER};
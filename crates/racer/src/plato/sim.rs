@@ -0,0 +1,261 @@
+//! Offline replay of recorded latency traces through a [`PlatoController`].
+//!
+//! A trace is CSV with a header row naming its columns: `time_secs`, in
+//! seconds from any fixed origin, and `our_latency` are required. Without a
+//! `peer_latency` column, each row's peer sample equals its own; with one,
+//! a blank field means the row carries no sample of that kind, as in the
+//! files [`TraceRecorder`](super::trace::TraceRecorder) writes. A
+//! `queue_depth` holds until the next row that sets one, from 0. Samples are
+//! fed in order, and the controller runs its congestion checks every
+//! `tick_secs` of trace time, as a running node does every
+//! `plato.update_interval_secs`, recording a [`SimStep`] per check.
+
+use serde::Serialize;
+
+use super::{PlatoController, PlatoDecision};
+use crate::config::PlatoConfig;
+
+/// One row of a latency trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceSample {
+    /// Never decreases from one row to the next.
+    pub time_secs: f64,
+    pub our_latency: Option<f64>,
+    pub peer_latency: Option<f64>,
+    /// Fill of the fullest send queue, from 0.0 to 1.0.
+    pub queue_depth: Option<f64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TraceError {
+    #[error("trace has no samples")]
+    Empty,
+    #[error("trace header lacks a {0} column")]
+    MissingColumn(&'static str),
+    #[error("line {line}: {reason}")]
+    Invalid { line: usize, reason: String },
+}
+
+/// Parses a CSV latency trace. Blank lines and lines starting with `#` are
+/// skipped; unknown columns are ignored.
+pub fn parse_trace(csv: &str) -> Result<Vec<TraceSample>, TraceError> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let (_, header) = lines.next().ok_or(TraceError::Empty)?;
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|c| *c == name);
+    let time = column("time_secs").ok_or(TraceError::MissingColumn("time_secs"))?;
+    let ours = column("our_latency").ok_or(TraceError::MissingColumn("our_latency"))?;
+    let peers = column("peer_latency");
+    let depth = column("queue_depth");
+
+    let mut samples: Vec<TraceSample> = Vec::new();
+    for (line, row) in lines {
+        let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+        let field = |index: usize, name: &str| -> Result<Option<f64>, TraceError> {
+            let value = fields.get(index).copied().unwrap_or_default();
+            if value.is_empty() {
+                return Ok(None);
+            }
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .map(Some)
+                .ok_or_else(|| TraceError::Invalid {
+                    line,
+                    reason: format!("{} {:?} is not a non-negative number", name, value),
+                })
+        };
+
+        let time_secs = field(time, "time_secs")?.ok_or_else(|| TraceError::Invalid {
+            line,
+            reason: "missing time_secs".into(),
+        })?;
+        let our_latency = field(ours, "our_latency")?;
+        let sample = TraceSample {
+            time_secs,
+            our_latency,
+            peer_latency: match peers {
+                Some(index) => field(index, "peer_latency")?,
+                None => our_latency,
+            },
+            queue_depth: depth.map(|index| field(index, "queue_depth")).transpose()?.flatten(),
+        };
+        if let Some(last) = samples.last() {
+            if sample.time_secs < last.time_secs {
+                return Err(TraceError::Invalid {
+                    line,
+                    reason: format!("time_secs {} is before the previous row's {}", sample.time_secs, last.time_secs),
+                });
+            }
+        }
+        samples.push(sample);
+    }
+
+    if samples.is_empty() {
+        return Err(TraceError::Empty);
+    }
+    Ok(samples)
+}
+
+/// The controller's state after one congestion check.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimStep {
+    pub time_secs: f64,
+    /// The latest samples fed before the check; 0 before the first.
+    pub our_latency: f64,
+    pub peer_latency: f64,
+    pub queue_depth: f64,
+    pub decision: PlatoDecision,
+    pub current_latency: f64,
+    pub weighted_latency: f64,
+    pub publish_frequency: f64,
+    pub our_rsi_up: f64,
+    pub our_rsi_down: f64,
+    pub peer_rsi_up: f64,
+    pub peer_rsi_down: f64,
+}
+
+impl SimStep {
+    pub const CSV_HEADER: &'static str = "time_secs,our_latency,peer_latency,queue_depth,decision,\
+current_latency,weighted_latency,publish_frequency,our_rsi_up,our_rsi_down,peer_rsi_up,peer_rsi_down";
+
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{:.6},{:.6},{:.6},{:.3},{:.3},{:.3},{:.3}",
+            self.time_secs,
+            self.our_latency,
+            self.peer_latency,
+            self.queue_depth,
+            self.decision.as_str(),
+            self.current_latency,
+            self.weighted_latency,
+            self.publish_frequency,
+            self.our_rsi_up,
+            self.our_rsi_down,
+            self.peer_rsi_up,
+            self.peer_rsi_down
+        )
+    }
+}
+
+/// Replays `trace` through a controller built from `config`, checking for
+/// congestion every `tick_secs` of trace time from the first sample on.
+pub fn simulate(config: &PlatoConfig, trace: &[TraceSample], tick_secs: f64) -> Vec<SimStep> {
+    let mut controller = PlatoController::new(config.clone());
+    let mut steps = Vec::new();
+    let Some(first) = trace.first() else {
+        return steps;
+    };
+
+    let mut latest = Latest::default();
+    let mut next_check = first.time_secs + tick_secs;
+    for sample in trace {
+        while sample.time_secs >= next_check {
+            steps.push(check(&mut controller, next_check, &latest));
+            next_check += tick_secs;
+        }
+        if let Some(latency) = sample.our_latency {
+            controller.record_our_latency(latency);
+            latest.our_latency = latency;
+        }
+        if let Some(latency) = sample.peer_latency {
+            controller.record_peer_latency(latency);
+            latest.peer_latency = latency;
+        }
+        if let Some(depth) = sample.queue_depth {
+            latest.queue_depth = depth;
+        }
+    }
+    steps.push(check(&mut controller, next_check, &latest));
+    steps
+}
+
+#[derive(Debug, Default)]
+struct Latest {
+    our_latency: f64,
+    peer_latency: f64,
+    queue_depth: f64,
+}
+
+fn check(controller: &mut PlatoController, time_secs: f64, latest: &Latest) -> SimStep {
+    controller.record_queue_depth(latest.queue_depth);
+    controller.check_increasing_congestion();
+    controller.check_decreasing_congestion();
+    controller.clear_timing_changed();
+
+    let stats = controller.stats();
+    SimStep {
+        time_secs,
+        our_latency: latest.our_latency,
+        peer_latency: latest.peer_latency,
+        queue_depth: latest.queue_depth,
+        decision: controller.last_decision(),
+        current_latency: stats.current_latency,
+        weighted_latency: controller.weighted_latency(),
+        publish_frequency: stats.publish_frequency,
+        our_rsi_up: stats.our_rsi_up,
+        our_rsi_down: stats.our_rsi_down,
+        peer_rsi_up: stats.peer_rsi_up,
+        peer_rsi_down: stats.peer_rsi_down,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trace() {
+        let csv = "# recorded on the test bench\nour_latency,time_secs,peer_latency\n2.0,0,2.5\n\n2.1,0.5,\n";
+        let trace = parse_trace(csv).unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(
+            trace[1],
+            TraceSample { time_secs: 0.5, our_latency: Some(2.1), peer_latency: None, queue_depth: None }
+        );
+        let same = parse_trace("time_secs,our_latency\n0,2.0\n").unwrap();
+        assert_eq!(same[0].peer_latency, Some(2.0));
+
+        assert!(matches!(parse_trace("time_secs\n1\n"), Err(TraceError::MissingColumn("our_latency"))));
+        assert!(matches!(parse_trace("time_secs,our_latency\n"), Err(TraceError::Empty)));
+        assert!(matches!(
+            parse_trace("time_secs,our_latency\n1,2\n0,2\n"),
+            Err(TraceError::Invalid { line: 3, .. })
+        ));
+        assert!(matches!(
+            parse_trace("time_secs,our_latency\n1,slow\n"),
+            Err(TraceError::Invalid { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rising_latency_throttles() {
+        let config = PlatoConfig::default();
+        let trace: Vec<TraceSample> = (0..600)
+            .map(|i| {
+                let latency = 2.0 + i as f64 * 0.05;
+                TraceSample {
+                    time_secs: i as f64 * 0.5,
+                    our_latency: Some(latency),
+                    peer_latency: Some(latency),
+                    queue_depth: None,
+                }
+            })
+            .collect();
+
+        let steps = simulate(&config, &trace, config.update_interval_secs);
+        assert_eq!(steps.len(), 60);
+        assert_eq!(steps[0].time_secs, 5.0);
+        let last = steps.last().unwrap();
+        assert!(last.current_latency > config.target_latency_secs);
+        assert!(last.publish_frequency > config.target_publishing_frequency_secs);
+        assert!(steps.iter().any(|step| step.decision == PlatoDecision::Throttle));
+        assert_eq!(last.to_csv_row().split(',').count(), SimStep::CSV_HEADER.split(',').count());
+    }
+}
@@ -1,5 +1,10 @@
 use std::collections::VecDeque;
 
+/// Polynomial order used by [`SavitzkyGolayFilter::new`].
+pub const DEFAULT_POLYNOMIAL_ORDER: usize = 2;
+
+/// Least-squares polynomial smoothing over a sliding window, evaluated at
+/// the window's centre.
 #[derive(Debug, Clone)]
 pub struct SavitzkyGolayFilter {
     window_size: usize,
@@ -9,44 +14,31 @@ pub struct SavitzkyGolayFilter {
 
 impl SavitzkyGolayFilter {
     pub fn new(window_size: usize) -> Self {
+        Self::with_order(window_size, DEFAULT_POLYNOMIAL_ORDER)
+    }
+
+    /// Fits polynomials of `order` over `window_size` samples, rounded up to
+    /// an odd size. The order is capped at two below the window size, since
+    /// a polynomial through every sample would not smooth at all.
+    pub fn with_order(window_size: usize, order: usize) -> Self {
         let window_size = if window_size % 2 == 0 {
             window_size + 1 // Ensure odd
         } else {
             window_size
         };
-
-        let coefficients = match window_size {
-            3 => vec![1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0],
-            5 => vec![-3.0, 12.0, 17.0, 12.0, -3.0]
-                .into_iter()
-                .map(|c| c / 35.0)
-                .collect(),
-            7 => vec![-2.0, 3.0, 6.0, 7.0, 6.0, 3.0, -2.0]
-                .into_iter()
-                .map(|c| c / 21.0)
-                .collect(),
-            9 => vec![-21.0, 14.0, 39.0, 54.0, 59.0, 54.0, 39.0, 14.0, -21.0]
-                .into_iter()
-                .map(|c| c / 231.0)
-                .collect(),
-            11 => vec![
-                -36.0, 9.0, 44.0, 69.0, 84.0, 89.0, 84.0, 69.0, 44.0, 9.0, -36.0,
-            ]
-            .into_iter()
-            .map(|c| c / 429.0)
-            .collect(),
-            _ => {
-                vec![1.0 / window_size as f64; window_size]
-            }
-        };
+        let order = order.min(window_size.saturating_sub(2));
 
         Self {
             window_size,
-            coefficients,
+            coefficients: coefficients(window_size, order),
             buffer: VecDeque::with_capacity(window_size),
         }
     }
 
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefficients
+    }
+
     pub fn next(&mut self, value: f64) -> f64 {
         self.buffer.push_back(value);
         if self.buffer.len() > self.window_size {
@@ -89,6 +81,58 @@ impl SavitzkyGolayFilter {
     }
 }
 
+/// Weights that evaluate the least-squares polynomial of `order` through
+/// `window_size` evenly spaced samples at the centre one: the first row of
+/// `(JᵀJ)⁻¹Jᵀ` for the Vandermonde matrix `J` of the sample offsets.
+fn coefficients(window_size: usize, order: usize) -> Vec<f64> {
+    let half = (window_size / 2) as f64;
+    // Offsets scaled to [-1, 1] keep JᵀJ well conditioned for wide windows;
+    // the fitted polynomial, and so the weights, are the same.
+    let offsets: Vec<f64> = (0..window_size)
+        .map(|i| if half > 0.0 { (i as f64 - half) / half } else { 0.0 })
+        .collect();
+    let terms = order + 1;
+
+    // Normal equations JᵀJ a = e₀, whose solution a gives the weights.
+    let mut system = vec![vec![0.0; terms + 1]; terms];
+    for (row, equation) in system.iter_mut().enumerate() {
+        for (col, entry) in equation.iter_mut().take(terms).enumerate() {
+            *entry = offsets.iter().map(|z| z.powi((row + col) as i32)).sum();
+        }
+        equation[terms] = if row == 0 { 1.0 } else { 0.0 };
+    }
+    let a = solve(system);
+
+    offsets
+        .iter()
+        .map(|z| a.iter().enumerate().map(|(j, a)| a * z.powi(j as i32)).sum())
+        .collect()
+}
+
+/// Solves the augmented system by Gaussian elimination with partial pivoting.
+fn solve(mut system: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = system.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| system[a][col].abs().total_cmp(&system[b][col].abs()))
+            .unwrap_or(col);
+        system.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = system[row][col] / system[col][col];
+            for k in col..=n {
+                system[row][k] -= factor * system[col][k];
+            }
+        }
+    }
+
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known: f64 = (row + 1..n).map(|k| system[row][k] * solution[k]).sum();
+        solution[row] = (system[row][n] - known) / system[row][row];
+    }
+    solution
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,6 +155,52 @@ mod tests {
         assert!(smooth_variance <= raw_variance + 1.0); // Allow small tolerance
     }
 
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_matches_tabulated_coefficients() {
+        let tables: [(usize, f64, &[f64]); 4] = [
+            (5, 35.0, &[-3.0, 12.0, 17.0, 12.0, -3.0]),
+            (7, 21.0, &[-2.0, 3.0, 6.0, 7.0, 6.0, 3.0, -2.0]),
+            (9, 231.0, &[-21.0, 14.0, 39.0, 54.0, 59.0, 54.0, 39.0, 14.0, -21.0]),
+            (11, 429.0, &[-36.0, 9.0, 44.0, 69.0, 84.0, 89.0, 84.0, 69.0, 44.0, 9.0, -36.0]),
+        ];
+        for (window, norm, table) in tables {
+            let expected: Vec<f64> = table.iter().map(|c| c / norm).collect();
+            assert_close(SavitzkyGolayFilter::new(window).coefficients(), &expected);
+            // Cubic fits share the quadratic's centre weights.
+            assert_close(SavitzkyGolayFilter::with_order(window, 3).coefficients(), &expected);
+        }
+
+        let quartic: Vec<f64> = [15.0, -55.0, 30.0, 135.0, 179.0, 135.0, 30.0, -55.0, 15.0]
+            .iter()
+            .map(|c| c / 429.0)
+            .collect();
+        assert_close(SavitzkyGolayFilter::with_order(9, 4).coefficients(), &quartic);
+    }
+
+    #[test]
+    fn test_any_window_preserves_its_polynomial() {
+        for (window, order) in [(15, 2), (21, 2), (21, 4), (31, 3)] {
+            let filter = SavitzkyGolayFilter::with_order(window, order);
+            let weights = filter.coefficients();
+            assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            // A straight line passes through unchanged.
+            let half = (window / 2) as f64;
+            let line: f64 = weights.iter().enumerate().map(|(i, w)| w * (3.0 + 2.0 * (i as f64 - half))).sum();
+            assert!((line - 3.0).abs() < 1e-9, "window {} order {}: {}", window, order, line);
+        }
+
+        // Window 3 falls back to a line fit, the moving average.
+        assert_close(SavitzkyGolayFilter::new(3).coefficients(), &[1.0 / 3.0; 3]);
+        assert_eq!(SavitzkyGolayFilter::new(14).coefficients().len(), 15);
+    }
+
     #[test]
     fn test_steady_state() {
         let mut filter = SavitzkyGolayFilter::new(5);
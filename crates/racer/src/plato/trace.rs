@@ -0,0 +1,98 @@
+//! Recording of what PLATO saw and decided, for `racer plato-sim` and for
+//! reading back after an incident.
+//!
+//! With `plato.trace_file` set, the node's controller appends a row per
+//! latency sample, with the other latency column blank, and a row per
+//! congestion check carrying its decision and the resulting estimate. Times
+//! are epoch seconds, so a file appended to across restarts stays in order.
+//! The columns are those [`sim::parse_trace`](super::sim::parse_trace) reads;
+//! it skips the check rows' extra columns.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::PlatoDecision;
+
+pub const TRACE_HEADER: &str =
+    "time_secs,our_latency,peer_latency,queue_depth,decision,current_latency,publish_frequency";
+
+/// Appends rows to a trace file.
+#[derive(Debug)]
+pub struct TraceRecorder {
+    writer: BufWriter<File>,
+}
+
+impl TraceRecorder {
+    /// Opens `path` for appending, writing the header if it is new or empty.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if empty {
+            writeln!(writer, "{}", TRACE_HEADER)?;
+            writer.flush()?;
+        }
+        Ok(Self { writer })
+    }
+
+    pub fn our_latency(&mut self, latency: f64) -> io::Result<()> {
+        writeln!(self.writer, "{:.3},{},,,,,", now_secs(), latency)
+    }
+
+    pub fn peer_latency(&mut self, latency: f64) -> io::Result<()> {
+        writeln!(self.writer, "{:.3},,{},,,,", now_secs(), latency)
+    }
+
+    /// Records a congestion check and flushes, so a crash loses at most the
+    /// samples since the last one.
+    pub fn decision(
+        &mut self,
+        decision: PlatoDecision,
+        queue_depth: f64,
+        current_latency: f64,
+        publish_frequency: f64,
+    ) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{:.3},,,{},{},{:.6},{:.6}",
+            now_secs(),
+            queue_depth,
+            decision.as_str(),
+            current_latency,
+            publish_frequency
+        )?;
+        self.writer.flush()
+    }
+}
+
+fn now_secs() -> f64 {
+    racer_core::message::now_millis() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plato::sim;
+
+    #[test]
+    fn test_trace_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.csv");
+
+        let mut recorder = TraceRecorder::open(&path).unwrap();
+        recorder.our_latency(2.5).unwrap();
+        recorder.peer_latency(3.0).unwrap();
+        recorder.decision(PlatoDecision::Throttle, 0.5, 2.7, 2.6).unwrap();
+        drop(recorder);
+        // Reopening appends without a second header.
+        TraceRecorder::open(&path).unwrap().our_latency(2.4).unwrap();
+
+        let trace = sim::parse_trace(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(trace.len(), 4);
+        assert_eq!((trace[0].our_latency, trace[0].peer_latency), (Some(2.5), None));
+        assert_eq!((trace[1].our_latency, trace[1].peer_latency), (None, Some(3.0)));
+        assert_eq!(trace[2].queue_depth, Some(0.5));
+        assert_eq!(trace[3].our_latency, Some(2.4));
+    }
+}
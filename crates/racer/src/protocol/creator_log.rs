@@ -0,0 +1,203 @@
+//! Per-creator tracking of delivered batch sequence numbers.
+//!
+//! Creators number their batches from 1 in
+//! [`BatchedMessages::sequence`](super::BatchedMessages::sequence). The
+//! [`CreatorLog`] follows the numbers delivered from each creator: the
+//! highest one up to which none is missing, and those delivered past it. A
+//! number that skips ahead opens a gap, reported once, so the missing
+//! batches can be fetched from a peer's store; one that lands inside a gap
+//! arrived reordered. Tracking starts at the first number delivered from a
+//! creator, so a node that joins late does not take the creator's history
+//! for missing batches.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+
+/// Numbers held past a gap before the gap is given up on, per creator.
+pub const MAX_AHEAD: usize = 1024;
+
+/// What a delivered sequence number says about its creator's stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// The number after the highest one delivered, or the creator's first.
+    InOrder,
+    /// Skipped past `missing`, none of which has been delivered.
+    Gap { missing: RangeInclusive<u64> },
+    /// Filled in part of an earlier gap.
+    Reordered,
+    /// Already delivered, or from before tracking started.
+    Duplicate,
+    /// The creator does not number its batches.
+    Unsequenced,
+}
+
+#[derive(Debug, Default)]
+struct Stream {
+    /// Every number up to this one was delivered, or tracking started here.
+    contiguous: u64,
+    /// Delivered numbers past a gap.
+    ahead: BTreeSet<u64>,
+}
+
+impl Stream {
+    fn highest(&self) -> u64 {
+        self.ahead.last().copied().unwrap_or(self.contiguous)
+    }
+
+    /// Moves `contiguous` over the numbers that now follow it unbroken.
+    fn advance(&mut self) {
+        while let Some(next) = self.contiguous.checked_add(1) {
+            if self.ahead.first() != Some(&next) {
+                break;
+            }
+            self.contiguous = next;
+            self.ahead.pop_first();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CreatorLog {
+    streams: HashMap<String, Stream>,
+}
+
+impl CreatorLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `sequence` as delivered from `creator` (public key hex).
+    pub fn record(&mut self, creator: &str, sequence: u64) -> SequenceCheck {
+        if sequence == 0 {
+            return SequenceCheck::Unsequenced;
+        }
+        let Some(stream) = self.streams.get_mut(creator) else {
+            self.streams.insert(
+                creator.to_string(),
+                Stream {
+                    contiguous: sequence,
+                    ahead: BTreeSet::new(),
+                },
+            );
+            return SequenceCheck::InOrder;
+        };
+        if sequence <= stream.contiguous || stream.ahead.contains(&sequence) {
+            return SequenceCheck::Duplicate;
+        }
+
+        let highest = stream.highest();
+        let check = if sequence > highest.saturating_add(1) {
+            SequenceCheck::Gap {
+                missing: highest + 1..=sequence - 1,
+            }
+        } else if sequence < highest {
+            SequenceCheck::Reordered
+        } else {
+            SequenceCheck::InOrder
+        };
+        stream.ahead.insert(sequence);
+        stream.advance();
+        while stream.ahead.len() > MAX_AHEAD {
+            // Gives up on the oldest gap.
+            stream.contiguous = stream.ahead.pop_first().expect("ahead is not empty");
+            stream.advance();
+        }
+        check
+    }
+
+    /// The highest number up to which every batch from `creator` was
+    /// delivered, counting from the first one tracked.
+    pub fn contiguous(&self, creator: &str) -> Option<u64> {
+        self.streams.get(creator).map(|stream| stream.contiguous)
+    }
+
+    /// Numbers from `creator` still missing below the highest delivered.
+    pub fn missing(&self, creator: &str) -> Vec<RangeInclusive<u64>> {
+        let Some(stream) = self.streams.get(creator) else {
+            return Vec::new();
+        };
+        let mut missing = Vec::new();
+        let mut next = stream.contiguous.saturating_add(1);
+        for &sequence in &stream.ahead {
+            if sequence > next {
+                missing.push(next..=sequence - 1);
+            }
+            // Nothing sorts after `u64::MAX`.
+            next = sequence.saturating_add(1);
+        }
+        missing
+    }
+
+    /// Creators tracked.
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps_and_reordering() {
+        let mut log = CreatorLog::new();
+        assert_eq!(log.record("a", 0), SequenceCheck::Unsequenced);
+        assert!(log.is_empty());
+
+        // Tracking starts wherever the creator is first seen.
+        assert_eq!(log.record("a", 40), SequenceCheck::InOrder);
+        assert_eq!(log.record("a", 41), SequenceCheck::InOrder);
+        assert_eq!(log.record("a", 45), SequenceCheck::Gap { missing: 42..=44 });
+        assert_eq!(log.record("a", 46), SequenceCheck::InOrder);
+        assert_eq!(log.missing("a"), vec![42..=44]);
+
+        assert_eq!(log.record("a", 43), SequenceCheck::Reordered);
+        assert_eq!(log.missing("a"), vec![42..=42, 44..=44]);
+        assert_eq!(log.record("a", 42), SequenceCheck::Reordered);
+        assert_eq!(log.contiguous("a"), Some(43));
+        assert_eq!(log.record("a", 44), SequenceCheck::Reordered);
+        assert_eq!(log.contiguous("a"), Some(46));
+        assert!(log.missing("a").is_empty());
+
+        assert_eq!(log.record("a", 44), SequenceCheck::Duplicate);
+        assert_eq!(log.record("a", 39), SequenceCheck::Duplicate);
+        assert_eq!(log.contiguous("b"), None);
+    }
+
+    #[test]
+    fn test_sequence_numbers_up_to_u64_max() {
+        let mut log = CreatorLog::new();
+        log.record("a", 1);
+        assert_eq!(log.record("a", u64::MAX), SequenceCheck::Gap { missing: 2..=u64::MAX - 1 });
+        assert_eq!(log.record("a", u64::MAX - 1), SequenceCheck::Reordered);
+        assert_eq!(log.missing("a"), vec![2..=u64::MAX - 2]);
+        assert_eq!(log.record("a", u64::MAX), SequenceCheck::Duplicate);
+
+        assert_eq!(log.record("b", u64::MAX), SequenceCheck::InOrder);
+        assert_eq!(log.record("b", u64::MAX), SequenceCheck::Duplicate);
+        assert!(log.missing("b").is_empty());
+
+        // A gap given up on right below the top.
+        log.record("c", 1);
+        for sequence in (u64::MAX - MAX_AHEAD as u64)..=u64::MAX {
+            log.record("c", sequence);
+        }
+        assert_eq!(log.contiguous("c"), Some(u64::MAX));
+        assert!(log.missing("c").is_empty());
+    }
+
+    #[test]
+    fn test_abandons_oldest_gap() {
+        let mut log = CreatorLog::new();
+        log.record("a", 1);
+        for sequence in 3..MAX_AHEAD as u64 + 4 {
+            log.record("a", sequence);
+        }
+        assert_eq!(log.contiguous("a"), Some(MAX_AHEAD as u64 + 3));
+        assert!(log.missing("a").is_empty());
+    }
+}
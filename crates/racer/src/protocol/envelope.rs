@@ -0,0 +1,313 @@
+//! The wire form of a [`ProtocolMessage`].
+//!
+//! Every protocol message travels as an envelope that names its type apart
+//! from its fields:
+//!
+//! ```text
+//! {"v":3,"type":"Echo","payload":{"echo_type":"echo_subscribe",...}}
+//! ```
+//!
+//! `v` is the sender's [`PROTOCOL_VERSION`]. A message whose `type` this
+//! build does not know fails to decode with [`EnvelopeError::UnknownType`]
+//! rather than as malformed, so the pipeline skips it with a warning instead
+//! of dropping the frame, and inside a `Bundle` only that message is
+//! skipped. Newer nodes can then add message types without the older nodes
+//! of a fleet losing the frames that carry them.
+//!
+//! Nodes from before [`ENVELOPE_VERSION`] send and expect the bare form,
+//! the type in a `message_type` field beside the others. Decoding accepts
+//! both. The network rewrites frames to a peer that negotiated an older
+//! version with [`to_bare`], and a PeerDiscovery goes out bare through
+//! [`encode_bare`], since the peer's version is not known yet. Peers added
+//! without the handshake are sent envelopes.
+
+use bytes::Bytes;
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::messages::ProtocolMessage;
+use super::version::PROTOCOL_VERSION;
+
+/// First protocol version whose nodes read envelopes.
+pub const ENVELOPE_VERSION: u32 = 3;
+
+/// The type field of the bare form.
+const BARE_TAG: &str = "message_type";
+
+const BUNDLE: &str = "Bundle";
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnvelopeError {
+    /// A well-formed message of a type this build does not know, likely
+    /// from a newer node; `version` is the envelope's `v`.
+    #[error("unknown message type {message_type:?}")]
+    UnknownType { message_type: String, version: Option<u32> },
+    #[error(transparent)]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// How frames to a peer are encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    #[default]
+    Envelope,
+    /// The form nodes before [`ENVELOPE_VERSION`] read.
+    Bare,
+}
+
+impl Framing {
+    /// The framing a peer that negotiated `version` reads.
+    pub fn for_version(version: u32) -> Self {
+        if version >= ENVELOPE_VERSION {
+            Self::Envelope
+        } else {
+            Self::Bare
+        }
+    }
+}
+
+impl<M> ProtocolMessage<M> {
+    /// The `type` this message is sent under.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            Self::BatchedMessages(_) => "BatchedMessage",
+            Self::Echo(_) => "Echo",
+            Self::Response(_) => "Response",
+            Self::PeerDiscovery(_) => "PeerDiscovery",
+            Self::PeerChallenge(_) => "PeerChallenge",
+            Self::HealthSummary(_) => "HealthSummary",
+            Self::PeerListRequest(_) => "PeerListRequest",
+            Self::PeerListGossip(_) => "PeerListGossip",
+            Self::Relay(_) => "Relay",
+            Self::DeliveryReport(_) => "DeliveryReport",
+            Self::Bundle { .. } => BUNDLE,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BundleRef<'a, M> {
+    messages: &'a [ProtocolMessage<M>],
+}
+
+#[derive(Deserialize)]
+struct BundlePayload {
+    messages: Vec<Value>,
+}
+
+impl<M: Serialize> Serialize for ProtocolMessage<M> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut envelope = serializer.serialize_struct("Envelope", 3)?;
+        envelope.serialize_field("v", &PROTOCOL_VERSION)?;
+        envelope.serialize_field("type", self.message_type())?;
+        match self {
+            Self::BatchedMessages(batch) => envelope.serialize_field("payload", batch)?,
+            Self::Echo(echo) => envelope.serialize_field("payload", echo)?,
+            Self::Response(response) => envelope.serialize_field("payload", response)?,
+            Self::PeerDiscovery(pd) => envelope.serialize_field("payload", pd)?,
+            Self::PeerChallenge(challenge) => envelope.serialize_field("payload", challenge)?,
+            Self::HealthSummary(summary) => envelope.serialize_field("payload", summary)?,
+            Self::PeerListRequest(request) => envelope.serialize_field("payload", request)?,
+            Self::PeerListGossip(gossip) => envelope.serialize_field("payload", gossip)?,
+            Self::Relay(frame) => envelope.serialize_field("payload", frame)?,
+            Self::DeliveryReport(report) => envelope.serialize_field("payload", report)?,
+            Self::Bundle { messages } => envelope.serialize_field("payload", &BundleRef { messages })?,
+        }
+        envelope.end()
+    }
+}
+
+impl<'de, M: DeserializeOwned> Deserialize<'de> for ProtocolMessage<M> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        open(Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// Decodes a frame in either form.
+pub fn decode<M: DeserializeOwned>(frame: &[u8]) -> Result<ProtocolMessage<M>, EnvelopeError> {
+    open(serde_json::from_slice(frame)?)
+}
+
+/// Encodes `message` in the bare form.
+pub fn encode_bare<M: Serialize>(message: &ProtocolMessage<M>) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(&bare(serde_json::to_value(message)?))
+}
+
+/// `frame` in the bare form, bundled messages included. Frames that are
+/// not envelopes are returned as they are.
+pub fn to_bare(frame: &Bytes) -> Bytes {
+    if !frame.starts_with(br#"{"v":"#) {
+        return frame.clone();
+    }
+    match serde_json::from_slice(frame).and_then(|value| serde_json::to_vec(&bare(value))) {
+        Ok(bare) => Bytes::from(bare),
+        Err(_) => frame.clone(),
+    }
+}
+
+fn bare(value: Value) -> Value {
+    let Value::Object(mut envelope) = value else {
+        return value;
+    };
+    if envelope.contains_key(BARE_TAG) || !envelope.contains_key("payload") {
+        return Value::Object(envelope);
+    }
+    let (Some(message_type), Some(payload)) = (envelope.remove("type"), envelope.remove("payload")) else {
+        return Value::Object(envelope);
+    };
+    let mut fields = match payload {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    };
+    if message_type == BUNDLE {
+        if let Some(Value::Array(messages)) = fields.remove("messages") {
+            fields.insert("messages".into(), Value::Array(messages.into_iter().map(bare).collect()));
+        }
+    }
+    fields.insert(BARE_TAG.into(), message_type);
+    Value::Object(fields)
+}
+
+fn open<M: DeserializeOwned>(value: Value) -> Result<ProtocolMessage<M>, EnvelopeError> {
+    let Value::Object(mut fields) = value else {
+        return Err(malformed("a protocol message must be a JSON object"));
+    };
+    let (message_type, version, payload) = match fields.remove(BARE_TAG) {
+        Some(message_type) => (message_type, None, Value::Object(fields)),
+        None => {
+            let version = fields
+                .get("v")
+                .and_then(Value::as_u64)
+                .and_then(|v| u32::try_from(v).ok());
+            let message_type = fields
+                .remove("type")
+                .ok_or_else(|| <serde_json::Error as de::Error>::missing_field("type"))?;
+            let payload = fields
+                .remove("payload")
+                .ok_or_else(|| <serde_json::Error as de::Error>::missing_field("payload"))?;
+            (message_type, version, payload)
+        }
+    };
+    let Value::String(message_type) = message_type else {
+        return Err(malformed("message type must be a string"));
+    };
+
+    Ok(match message_type.as_str() {
+        "BatchedMessage" => ProtocolMessage::BatchedMessages(serde_json::from_value(payload)?),
+        "Echo" => ProtocolMessage::Echo(serde_json::from_value(payload)?),
+        "Response" => ProtocolMessage::Response(serde_json::from_value(payload)?),
+        "PeerDiscovery" => ProtocolMessage::PeerDiscovery(serde_json::from_value(payload)?),
+        "PeerChallenge" => ProtocolMessage::PeerChallenge(serde_json::from_value(payload)?),
+        "HealthSummary" => ProtocolMessage::HealthSummary(serde_json::from_value(payload)?),
+        "PeerListRequest" => ProtocolMessage::PeerListRequest(serde_json::from_value(payload)?),
+        "PeerListGossip" => ProtocolMessage::PeerListGossip(serde_json::from_value(payload)?),
+        "Relay" => ProtocolMessage::Relay(serde_json::from_value(payload)?),
+        "DeliveryReport" => ProtocolMessage::DeliveryReport(serde_json::from_value(payload)?),
+        BUNDLE => ProtocolMessage::Bundle {
+            messages: open_bundle(payload)?,
+        },
+        _ => return Err(EnvelopeError::UnknownType { message_type, version }),
+    })
+}
+
+fn malformed(reason: &str) -> EnvelopeError {
+    EnvelopeError::Malformed(de::Error::custom(reason))
+}
+
+/// The messages of a bundle, less those of unknown types.
+fn open_bundle<M: DeserializeOwned>(payload: Value) -> Result<Vec<ProtocolMessage<M>>, EnvelopeError> {
+    let BundlePayload { messages } = serde_json::from_value(payload)?;
+    let mut opened = Vec::with_capacity(messages.len());
+    for message in messages {
+        match open(message) {
+            Ok(message) => opened.push(message),
+            Err(EnvelopeError::UnknownType { message_type, version }) => {
+                tracing::warn!(message_type, ?version, "skipped bundled message of unknown type");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(opened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::{Echo, EchoType, PeerListRequest};
+
+    fn echo() -> ProtocolMessage<String> {
+        ProtocolMessage::Echo(Echo::new(EchoType::EchoSubscribe, "t", KeyPair::generate().public_key()))
+    }
+
+    #[test]
+    fn test_messages_travel_in_an_envelope() {
+        let value = serde_json::to_value(echo()).unwrap();
+        assert_eq!(value["v"], PROTOCOL_VERSION);
+        assert_eq!(value["type"], "Echo");
+        assert_eq!(value["payload"]["topic"], "t");
+        assert!(value.get(BARE_TAG).is_none());
+
+        let decoded: ProtocolMessage<String> = serde_json::from_value(value).unwrap();
+        assert!(matches!(decoded, ProtocolMessage::Echo(echo) if echo.topic == "t"));
+    }
+
+    #[test]
+    fn test_bare_frames_still_decode() {
+        let bundle = ProtocolMessage::Bundle {
+            messages: vec![echo(), ProtocolMessage::PeerListRequest(PeerListRequest { limit: 4 })],
+        };
+        let frame = Bytes::from(serde_json::to_vec(&bundle).unwrap());
+        let bare = to_bare(&frame);
+        let value: Value = serde_json::from_slice(&bare).unwrap();
+        assert_eq!(value[BARE_TAG], BUNDLE);
+        assert_eq!(value["messages"][0][BARE_TAG], "Echo");
+        assert_eq!(value["messages"][0]["topic"], "t");
+        assert_eq!(to_bare(&bare), bare);
+
+        match decode::<String>(&bare).unwrap() {
+            ProtocolMessage::Bundle { messages } => assert_eq!(messages.len(), 2),
+            other => panic!("expected a bundle, got {:?}", other),
+        }
+        let discovery = encode_bare(&echo()).unwrap();
+        assert!(decode::<String>(&discovery).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_types_are_told_apart_and_skipped_in_bundles() {
+        let unknown = br#"{"v":9,"type":"Gossip2","payload":{"anything":[1,2]}}"#;
+        match decode::<String>(unknown) {
+            Err(EnvelopeError::UnknownType { message_type, version }) => {
+                assert_eq!((message_type.as_str(), version), ("Gossip2", Some(9)));
+            }
+            other => panic!("expected an unknown type, got {:?}", other),
+        }
+        assert!(matches!(
+            decode::<String>(br#"{"v":3,"type":"Echo","payload":{}}"#),
+            Err(EnvelopeError::Malformed(_))
+        ));
+
+        let known = serde_json::to_string(&echo()).unwrap();
+        let frame = format!(
+            r#"{{"v":9,"type":"Bundle","payload":{{"messages":[{},{}]}}}}"#,
+            std::str::from_utf8(unknown).unwrap(),
+            known
+        );
+        match decode::<String>(frame.as_bytes()).unwrap() {
+            ProtocolMessage::Bundle { messages } => {
+                assert_eq!(messages.len(), 1);
+                assert!(matches!(messages[0], ProtocolMessage::Echo(_)));
+            }
+            other => panic!("expected a bundle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_framing_follows_the_negotiated_version() {
+        assert_eq!(Framing::for_version(ENVELOPE_VERSION), Framing::Envelope);
+        assert_eq!(Framing::for_version(ENVELOPE_VERSION - 1), Framing::Bare);
+        assert!(PROTOCOL_VERSION >= ENVELOPE_VERSION);
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 
+use crate::config::Thresholds;
+use crate::protocol::seen::SeenFilter;
 use crate::protocol::BatchedMessages;
 use crate::Message;
 
@@ -8,6 +10,7 @@ use crate::Message;
 pub struct GossipRound {
     pub hash: String,
     pub started_at: Instant,
+    pub echo_sent: HashMap<String, Instant>,
     pub echo_waiting: HashSet<String>,
     pub echo_received: HashSet<String>,
     pub ready_waiting: HashSet<String>,
@@ -15,6 +18,144 @@ pub struct GossipRound {
     pub echo_complete: bool,
     pub ready_complete: bool,
     pub delivered: bool,
+    /// Resolved when the round starts; `None` for rounds started with
+    /// [`GossipState::start_round`].
+    pub thresholds: Option<Thresholds>,
+    pub echo_completed_at: Option<Instant>,
+    pub ready_completed_at: Option<Instant>,
+    /// Set by whoever already announced the round's timeout, so the cleanup
+    /// that later drops it does not announce it again.
+    pub timeout_reported: bool,
+    /// Set once the round's outcome is counted in [`RoundStats`].
+    recorded: bool,
+}
+
+/// What a round's node must do after recording a response, as decided by
+/// [`GossipRound::advance`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RoundStep {
+    /// Publish this node's ReadyResponse; set at most once per round.
+    pub publish_ready: bool,
+    /// The round just reached its delivery threshold; set at most once.
+    pub deliver: bool,
+}
+
+/// How a finished round ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundOutcome {
+    Delivered,
+    TimedOut,
+}
+
+/// Timings and response rates of one finished round.
+#[derive(Debug, Clone)]
+pub struct RoundSummary {
+    pub hash: String,
+    pub outcome: RoundOutcome,
+    pub finished_at: Instant,
+    pub duration: Duration,
+    /// From the round's start until its echo phase completed.
+    pub echo_phase: Option<Duration>,
+    /// From the echo phase completing until the ready phase did.
+    pub ready_phase: Option<Duration>,
+    /// Share of the peers sampled for Echo that answered, from 0.0 to 1.0;
+    /// `None` when none were sampled.
+    pub echo_response_rate: Option<f64>,
+    pub ready_response_rate: Option<f64>,
+}
+
+/// Most recent round summaries kept by [`RoundStats`].
+const RECENT_ROUNDS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Mean {
+    sum: f64,
+    count: u64,
+}
+
+impl Mean {
+    fn add(&mut self, value: Option<f64>) {
+        if let Some(value) = value {
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+
+    fn get(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    fn merge(&mut self, other: Mean) {
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+}
+
+/// Outcomes of every round finished since the state was created.
+#[derive(Debug, Clone, Default)]
+pub struct RoundStats {
+    pub delivered: u64,
+    pub timed_out: u64,
+    echo_phase: Mean,
+    ready_phase: Mean,
+    echo_rate: Mean,
+    ready_rate: Mean,
+    recent: VecDeque<RoundSummary>,
+}
+
+impl RoundStats {
+    fn record(&mut self, summary: RoundSummary) {
+        match summary.outcome {
+            RoundOutcome::Delivered => self.delivered += 1,
+            RoundOutcome::TimedOut => self.timed_out += 1,
+        }
+        self.echo_phase.add(summary.echo_phase.map(|d| d.as_secs_f64()));
+        self.ready_phase.add(summary.ready_phase.map(|d| d.as_secs_f64()));
+        self.echo_rate.add(summary.echo_response_rate);
+        self.ready_rate.add(summary.ready_response_rate);
+
+        if self.recent.len() == RECENT_ROUNDS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(summary);
+    }
+
+    pub fn avg_echo_phase(&self) -> Option<Duration> {
+        self.echo_phase.get().map(Duration::from_secs_f64)
+    }
+
+    pub fn avg_ready_phase(&self) -> Option<Duration> {
+        self.ready_phase.get().map(Duration::from_secs_f64)
+    }
+
+    pub fn avg_echo_response_rate(&self) -> Option<f64> {
+        self.echo_rate.get()
+    }
+
+    pub fn avg_ready_response_rate(&self) -> Option<f64> {
+        self.ready_rate.get()
+    }
+
+    /// The last rounds to finish, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &RoundSummary> {
+        self.recent.iter()
+    }
+
+    /// Folds in the rounds counted by `other`, keeping the most recent
+    /// summaries of both.
+    pub(super) fn merge(&mut self, other: &RoundStats) {
+        self.delivered += other.delivered;
+        self.timed_out += other.timed_out;
+        self.echo_phase.merge(other.echo_phase);
+        self.ready_phase.merge(other.ready_phase);
+        self.echo_rate.merge(other.echo_rate);
+        self.ready_rate.merge(other.ready_rate);
+
+        let mut recent: Vec<_> = self.recent.drain(..).chain(other.recent.iter().cloned()).collect();
+        recent.sort_by_key(|summary| summary.finished_at);
+        let skip = recent.len().saturating_sub(RECENT_ROUNDS);
+        self.recent = recent.into_iter().skip(skip).collect();
+    }
 }
 
 impl GossipRound {
@@ -22,6 +163,7 @@ impl GossipRound {
         Self {
             hash: hash.into(),
             started_at: Instant::now(),
+            echo_sent: HashMap::new(),
             echo_waiting: HashSet::new(),
             echo_received: HashSet::new(),
             ready_waiting: HashSet::new(),
@@ -29,12 +171,62 @@ impl GossipRound {
             echo_complete: false,
             ready_complete: false,
             delivered: false,
+            thresholds: None,
+            echo_completed_at: None,
+            ready_completed_at: None,
+            timeout_reported: false,
+            recorded: false,
         }
     }
 
-    pub fn record_echo(&mut self, peer_id: &str) {
+    /// Marks the echo phase complete; true only the first time.
+    pub fn complete_echo(&mut self) -> bool {
+        if self.echo_complete {
+            return false;
+        }
+        self.echo_complete = true;
+        self.echo_completed_at = Some(Instant::now());
+        true
+    }
+
+    pub fn complete_ready(&mut self) {
+        if !self.ready_complete {
+            self.ready_complete = true;
+            self.ready_completed_at = Some(Instant::now());
+        }
+    }
+
+    pub fn summary(&self, outcome: RoundOutcome) -> RoundSummary {
+        let rate = |received: usize, waiting: usize| {
+            (received + waiting > 0).then(|| received as f64 / (received + waiting) as f64)
+        };
+        RoundSummary {
+            hash: self.hash.clone(),
+            outcome,
+            finished_at: Instant::now(),
+            duration: self.elapsed(),
+            echo_phase: self.echo_completed_at.map(|at| at - self.started_at),
+            ready_phase: self
+                .ready_completed_at
+                .zip(self.echo_completed_at)
+                .map(|(ready, echo)| ready.saturating_duration_since(echo)),
+            echo_response_rate: rate(self.echo_received.len(), self.echo_waiting.len()),
+            ready_response_rate: rate(self.ready_received.len(), self.ready_waiting.len()),
+        }
+    }
+
+    pub fn record_echo_sent(&mut self, peer_id: impl Into<String>) {
+        self.echo_sent.insert(peer_id.into(), Instant::now());
+    }
+
+    /// Records an EchoResponse and returns the round-trip time since the Echo
+    /// sent to `peer_id`, on the first response from that peer only.
+    pub fn record_echo(&mut self, peer_id: &str) -> Option<Duration> {
         self.echo_waiting.remove(peer_id);
-        self.echo_received.insert(peer_id.to_string());
+        if !self.echo_received.insert(peer_id.to_string()) {
+            return None;
+        }
+        self.echo_sent.get(peer_id).map(Instant::elapsed)
     }
 
     pub fn record_ready(&mut self, peer_id: &str) {
@@ -42,6 +234,25 @@ impl GossipRound {
         self.ready_received.insert(peer_id.to_string());
     }
 
+    /// Whether enough peers echoed the batch, or vouched Ready for it, for
+    /// this node to vouch Ready too.
+    pub fn is_ready(&self, thresholds: &Thresholds) -> bool {
+        self.echo_received.len() >= thresholds.ready || self.ready_received.len() >= thresholds.feedback
+    }
+
+    /// Applies `thresholds` to the responses recorded so far. Pure in the
+    /// round's state: the same responses always lead to the same steps, and
+    /// each step is returned once however often this is called.
+    pub fn advance(&mut self, thresholds: &Thresholds) -> RoundStep {
+        let publish_ready = self.is_ready(thresholds) && self.complete_echo();
+        let deliver = !self.delivered && self.ready_received.len() >= thresholds.delivery;
+        if deliver {
+            self.complete_ready();
+            self.delivered = true;
+        }
+        RoundStep { publish_ready, deliver }
+    }
+
     pub fn elapsed(&self) -> Duration {
         self.started_at.elapsed()
     }
@@ -51,22 +262,25 @@ impl GossipRound {
     }
 }
 
-pub struct GossipState<M: Message> {
+/// Bookkeeping for in-flight and recently delivered rounds, without the
+/// batches themselves.
+#[derive(Debug)]
+pub struct RoundTable {
     rounds: HashMap<String, GossipRound>,
-    received_messages: HashMap<String, BatchedMessages<M>>,
     delivered_hashes: VecDeque<String>,
     max_delivered: usize,
     default_timeout: Duration,
+    stats: RoundStats,
 }
 
-impl<M: Message> GossipState<M> {
+impl RoundTable {
     pub fn new() -> Self {
         Self {
             rounds: HashMap::new(),
-            received_messages: HashMap::new(),
             delivered_hashes: VecDeque::new(),
-            max_delivered: 1000,
+            max_delivered: DEFAULT_MAX_DELIVERED,
             default_timeout: Duration::from_secs(60),
+            stats: RoundStats::default(),
         }
     }
 
@@ -74,6 +288,11 @@ impl<M: Message> GossipState<M> {
         self.default_timeout = timeout;
     }
 
+    /// How long an undelivered round lives before `cleanup_timed_out` drops it.
+    pub fn timeout(&self) -> Duration {
+        self.default_timeout
+    }
+
     pub fn set_max_delivered(&mut self, max: usize) {
         self.max_delivered = max;
     }
@@ -83,6 +302,16 @@ impl<M: Message> GossipState<M> {
         self.rounds.entry(hash.clone()).or_insert_with(|| GossipRound::new(&hash))
     }
 
+    /// Like [`start_round`](Self::start_round), fixing `thresholds` if the
+    /// round is new. An existing round keeps the thresholds it started with.
+    pub fn start_round_with(&mut self, hash: impl Into<String>, thresholds: Thresholds) -> &mut GossipRound {
+        let hash = hash.into();
+        self.rounds.entry(hash.clone()).or_insert_with(|| GossipRound {
+            thresholds: Some(thresholds),
+            ..GossipRound::new(&hash)
+        })
+    }
+
     pub fn get_round(&self, hash: &str) -> Option<&GossipRound> {
         self.rounds.get(hash)
     }
@@ -91,30 +320,29 @@ impl<M: Message> GossipState<M> {
         self.rounds.get_mut(hash)
     }
 
-    pub fn store_message(&mut self, hash: String, message: BatchedMessages<M>) {
-        self.received_messages.insert(hash, message);
-    }
-
-    pub fn get_message(&self, hash: &str) -> Option<&BatchedMessages<M>> {
-        self.received_messages.get(hash)
-    }
-
-    pub fn has_message(&self, hash: &str) -> bool {
-        self.received_messages.contains_key(hash)
-    }
-
-    pub fn mark_delivered(&mut self, hash: &str) {
+    /// Marks the round delivered and returns the hashes of older delivered
+    /// rounds evicted to stay within `max_delivered`, whose batches the
+    /// caller should drop too.
+    #[must_use = "the batches of evicted rounds are still stored"]
+    pub fn mark_delivered(&mut self, hash: &str) -> Vec<String> {
         if let Some(round) = self.rounds.get_mut(hash) {
             round.delivered = true;
+            round.complete_ready();
+            if !round.recorded {
+                round.recorded = true;
+                self.stats.record(round.summary(RoundOutcome::Delivered));
+            }
         }
 
+        let mut evicted = Vec::new();
         self.delivered_hashes.push_back(hash.to_string());
         while self.delivered_hashes.len() > self.max_delivered {
             if let Some(old_hash) = self.delivered_hashes.pop_front() {
                 self.rounds.remove(&old_hash);
-                self.received_messages.remove(&old_hash);
+                evicted.push(old_hash);
             }
         }
+        evicted
     }
 
     pub fn is_delivered(&self, hash: &str) -> bool {
@@ -129,6 +357,12 @@ impl<M: Message> GossipState<M> {
     }
 
     pub fn cleanup_timed_out(&mut self) -> Vec<String> {
+        self.remove_timed_out().into_iter().map(|round| round.hash).collect()
+    }
+
+    /// Like [`cleanup_timed_out`](Self::cleanup_timed_out), returning the
+    /// dropped rounds themselves.
+    pub fn remove_timed_out(&mut self) -> Vec<GossipRound> {
         let timeout = self.default_timeout;
         let timed_out: Vec<_> = self
             .rounds
@@ -137,16 +371,350 @@ impl<M: Message> GossipState<M> {
             .map(|(hash, _)| hash.clone())
             .collect();
 
+        let mut removed = Vec::with_capacity(timed_out.len());
         for hash in &timed_out {
-            self.rounds.remove(hash);
-            self.received_messages.remove(hash);
+            if let Some(round) = self.rounds.remove(hash) {
+                self.stats.record(round.summary(RoundOutcome::TimedOut));
+                removed.push(round);
+            }
+        }
+        removed
+    }
+
+    pub fn active_rounds(&self) -> usize {
+        self.rounds.values().filter(|r| !r.delivered).count()
+    }
+
+    pub fn round_stats(&self) -> &RoundStats {
+        &self.stats
+    }
+}
+
+impl Default for RoundTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Delivered rounds a [`RoundTable`] remembers unless told otherwise.
+pub const DEFAULT_MAX_DELIVERED: usize = 1000;
+
+/// How long a delivered round's batch is held unless told otherwise.
+pub const DEFAULT_DELIVERED_RETENTION: Duration = Duration::from_secs(300);
+
+/// How long the batches of finished rounds stay in a [`MessageStore`] before
+/// [`MessageStore::evict`] drops them. A delivered batch also goes when its
+/// round falls out of the last `max_delivered`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_delivered: usize,
+    /// Age since delivery at which a delivered batch is dropped.
+    pub delivered: Duration,
+    /// Age since its round timed out at which a batch is dropped; zero drops
+    /// it on the first eviction after the timeout.
+    pub timed_out: Duration,
+}
+
+impl RetentionPolicy {
+    fn max_age(&self, outcome: RoundOutcome) -> Duration {
+        match outcome {
+            RoundOutcome::Delivered => self.delivered,
+            RoundOutcome::TimedOut => self.timed_out,
+        }
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_delivered: DEFAULT_MAX_DELIVERED,
+            delivered: DEFAULT_DELIVERED_RETENTION,
+            timed_out: Duration::ZERO,
         }
+    }
+}
+
+/// What a [`MessageStore`] holds, and what it has evicted so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub batches: usize,
+    /// Serialized size of the batches held, in bytes.
+    pub bytes: usize,
+    /// Held batches whose round was delivered.
+    pub delivered: usize,
+    /// Held batches whose round timed out.
+    pub timed_out: usize,
+    /// Batches dropped by [`MessageStore::evict`].
+    pub evicted: u64,
+}
+
+impl StoreStats {
+    pub fn merge(&mut self, other: StoreStats) {
+        self.batches += other.batches;
+        self.bytes += other.bytes;
+        self.delivered += other.delivered;
+        self.timed_out += other.timed_out;
+        self.evicted += other.evicted;
+    }
+}
+
+struct StoredBatch<M> {
+    batch: BatchedMessages<M>,
+    bytes: usize,
+    /// How the batch's round ended, and when; `None` while it runs.
+    retired: Option<(RoundOutcome, Instant)>,
+}
+
+/// Batches held for their rounds, and a trace of every hash ever stored.
+pub struct MessageStore<M: Message> {
+    messages: HashMap<String, StoredBatch<M>>,
+    seen: SeenFilter,
+    retention: RetentionPolicy,
+    bytes: usize,
+    evicted: u64,
+}
+
+impl<M: Message> MessageStore<M> {
+    pub fn new() -> Self {
+        Self {
+            messages: HashMap::new(),
+            seen: SeenFilter::default(),
+            retention: RetentionPolicy::default(),
+            bytes: 0,
+            evicted: 0,
+        }
+    }
+
+    /// Replaces the seen-set, forgetting every hash recorded so far.
+    pub fn set_seen_filter(&mut self, seen: SeenFilter) {
+        self.seen = seen;
+    }
+
+    pub fn set_retention(&mut self, retention: RetentionPolicy) {
+        self.retention = retention;
+    }
+
+    /// Stores the batch. Storing a batch again keeps the outcome of its
+    /// round, if one was recorded.
+    pub fn insert(&mut self, hash: String, message: BatchedMessages<M>) {
+        self.seen.insert(&hash);
+        let bytes = crate::util::serialized_len(&message).unwrap_or(0);
+        self.bytes += bytes;
+        let retired = self.messages.get(&hash).and_then(|stored| stored.retired);
+        let stored = StoredBatch {
+            batch: message,
+            bytes,
+            retired,
+        };
+        if let Some(old) = self.messages.insert(hash, stored) {
+            self.bytes -= old.bytes;
+        }
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&BatchedMessages<M>> {
+        self.messages.get(hash).map(|stored| &stored.batch)
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.messages.contains_key(hash)
+    }
+
+    /// Whether the batch is held now or was stored at some point before
+    /// being dropped. May rarely be true for a batch never seen.
+    pub fn has_seen(&self, hash: &str) -> bool {
+        self.contains(hash) || self.seen.contains(hash)
+    }
+
+    /// Drops the batch, keeping its hash in the seen-set.
+    pub fn remove(&mut self, hash: &str) -> Option<BatchedMessages<M>> {
+        let stored = self.messages.remove(hash)?;
+        self.bytes -= stored.bytes;
+        Some(stored.batch)
+    }
+
+    /// Records how the batch's round ended, starting the age the retention
+    /// policy holds it for. A batch is retired once; later calls are ignored.
+    pub fn retire(&mut self, hash: &str, outcome: RoundOutcome) {
+        if let Some(stored) = self.messages.get_mut(hash) {
+            stored.retired.get_or_insert((outcome, Instant::now()));
+        }
+    }
 
+    /// Drops the retired batches older than the retention policy allows and
+    /// returns their hashes. Batches of running rounds are kept.
+    pub fn evict(&mut self) -> Vec<String> {
+        let retention = self.retention;
+        let expired: Vec<String> = self
+            .messages
+            .iter()
+            .filter(|(_, stored)| {
+                stored
+                    .retired
+                    .is_some_and(|(outcome, at)| at.elapsed() >= retention.max_age(outcome))
+            })
+            .map(|(hash, _)| hash.clone())
+            .collect();
+        for hash in &expired {
+            self.remove(hash);
+        }
+        self.evicted += expired.len() as u64;
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Serialized size of the batches held, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn stats(&self) -> StoreStats {
+        let retired = |outcome| {
+            self.messages
+                .values()
+                .filter(|stored| matches!(stored.retired, Some((o, _)) if o == outcome))
+                .count()
+        };
+        StoreStats {
+            batches: self.messages.len(),
+            bytes: self.bytes,
+            delivered: retired(RoundOutcome::Delivered),
+            timed_out: retired(RoundOutcome::TimedOut),
+            evicted: self.evicted,
+        }
+    }
+}
+
+impl<M: Message> Default for MessageStore<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rounds and their batches under one owner. A node spreads the same over
+/// several locks with [`ShardedGossipState`](super::ShardedGossipState).
+pub struct GossipState<M: Message> {
+    rounds: RoundTable,
+    messages: MessageStore<M>,
+}
+
+impl<M: Message> GossipState<M> {
+    pub fn new() -> Self {
+        Self {
+            rounds: RoundTable::new(),
+            messages: MessageStore::new(),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.rounds.set_timeout(timeout);
+    }
+
+    /// How long an undelivered round lives before `cleanup_timed_out` drops it.
+    pub fn timeout(&self) -> Duration {
+        self.rounds.timeout()
+    }
+
+    pub fn set_max_delivered(&mut self, max: usize) {
+        self.rounds.set_max_delivered(max);
+    }
+
+    /// Sets how many delivered rounds are remembered and how long finished
+    /// rounds' batches are held.
+    pub fn set_retention(&mut self, retention: RetentionPolicy) {
+        self.rounds.set_max_delivered(retention.max_delivered);
+        self.messages.set_retention(retention);
+    }
+
+    /// Replaces the seen-set, forgetting every hash recorded so far.
+    pub fn set_seen_filter(&mut self, seen: SeenFilter) {
+        self.messages.set_seen_filter(seen);
+    }
+
+    pub fn start_round(&mut self, hash: impl Into<String>) -> &mut GossipRound {
+        self.rounds.start_round(hash)
+    }
+
+    /// Like [`start_round`](Self::start_round), fixing `thresholds` if the
+    /// round is new. An existing round keeps the thresholds it started with.
+    pub fn start_round_with(&mut self, hash: impl Into<String>, thresholds: Thresholds) -> &mut GossipRound {
+        self.rounds.start_round_with(hash, thresholds)
+    }
+
+    pub fn get_round(&self, hash: &str) -> Option<&GossipRound> {
+        self.rounds.get_round(hash)
+    }
+
+    pub fn get_round_mut(&mut self, hash: &str) -> Option<&mut GossipRound> {
+        self.rounds.get_round_mut(hash)
+    }
+
+    pub fn store_message(&mut self, hash: String, message: BatchedMessages<M>) {
+        self.messages.insert(hash, message);
+    }
+
+    pub fn get_message(&self, hash: &str) -> Option<&BatchedMessages<M>> {
+        self.messages.get(hash)
+    }
+
+    pub fn has_message(&self, hash: &str) -> bool {
+        self.messages.contains(hash)
+    }
+
+    /// Whether the batch is held now or was stored at some point before
+    /// being dropped. May rarely be true for a batch never seen.
+    pub fn has_seen(&self, hash: &str) -> bool {
+        self.messages.has_seen(hash)
+    }
+
+    pub fn mark_delivered(&mut self, hash: &str) {
+        for evicted in self.rounds.mark_delivered(hash) {
+            self.messages.remove(&evicted);
+        }
+        self.messages.retire(hash, RoundOutcome::Delivered);
+    }
+
+    pub fn is_delivered(&self, hash: &str) -> bool {
+        self.rounds.is_delivered(hash)
+    }
+
+    pub fn was_recently_delivered(&self, hash: &str) -> bool {
+        self.rounds.was_recently_delivered(hash)
+    }
+
+    /// Drops undelivered rounds past the timeout, retiring their batches,
+    /// then evicts the batches the retention policy no longer holds.
+    pub fn cleanup_timed_out(&mut self) -> Vec<String> {
+        let timed_out = self.rounds.cleanup_timed_out();
+        for hash in &timed_out {
+            self.messages.retire(hash, RoundOutcome::TimedOut);
+        }
+        self.messages.evict();
         timed_out
     }
 
+    /// Drops the batches of finished rounds older than the retention policy
+    /// allows, returning their hashes.
+    pub fn evict(&mut self) -> Vec<String> {
+        self.messages.evict()
+    }
+
     pub fn active_rounds(&self) -> usize {
-        self.rounds.values().filter(|r| !r.delivered).count()
+        self.rounds.active_rounds()
+    }
+
+    pub fn store_stats(&self) -> StoreStats {
+        self.messages.stats()
+    }
+
+    pub fn round_stats(&self) -> &RoundStats {
+        self.rounds.round_stats()
     }
 }
 
@@ -161,6 +729,33 @@ mod tests {
     use super::*;
     use racer_core::message::DefaultMessage;
 
+    fn batch(id: &str) -> BatchedMessages<DefaultMessage> {
+        let key = crate::crypto::KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: id.into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: crate::protocol::VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 0,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
     #[test]
     fn test_gossip_round() {
         let mut round = GossipRound::new("hash123");
@@ -172,6 +767,37 @@ mod tests {
         assert!(round.echo_received.contains("peer1"));
     }
 
+    #[test]
+    fn test_echo_rtt() {
+        let mut round = GossipRound::new("hash123");
+        round.record_echo_sent("peer1");
+
+        assert!(round.record_echo("peer1").is_some());
+        assert!(round.record_echo("peer1").is_none());
+        assert!(round.record_echo("peer2").is_none());
+    }
+
+    #[test]
+    fn test_advance_steps_once() {
+        let thresholds = Thresholds { ready: 2, feedback: 3, delivery: 4 };
+        let mut round = GossipRound::new("hash123");
+
+        round.record_echo("a");
+        assert_eq!(round.advance(&thresholds), RoundStep::default());
+        round.record_echo("b");
+        assert!(round.advance(&thresholds).publish_ready);
+        assert!(!round.advance(&thresholds).publish_ready);
+
+        for peer in ["a", "b", "c"] {
+            round.record_ready(peer);
+        }
+        assert_eq!(round.advance(&thresholds), RoundStep::default());
+        round.record_ready("d");
+        assert!(round.advance(&thresholds).deliver);
+        assert!(round.ready_complete);
+        assert_eq!(round.advance(&thresholds), RoundStep::default());
+    }
+
     #[test]
     fn test_gossip_state() {
         let mut state = GossipState::<DefaultMessage>::new();
@@ -185,4 +811,116 @@ mod tests {
         assert!(state.is_delivered("hash1"));
         assert!(!state.is_delivered("hash2"));
     }
+
+    #[test]
+    fn test_round_stats() {
+        let mut state = GossipState::<DefaultMessage>::new();
+        let round = state.start_round("hash1");
+        round.echo_waiting.extend(["a".to_string(), "b".to_string()]);
+        round.ready_waiting.extend(["a".to_string(), "b".to_string()]);
+        round.record_echo("a");
+        assert!(round.complete_echo());
+        assert!(!round.complete_echo());
+        round.record_ready("a");
+        round.record_ready("b");
+        state.mark_delivered("hash1");
+        state.mark_delivered("hash1");
+
+        state.set_timeout(Duration::ZERO);
+        state.start_round("hash2");
+        std::thread::sleep(Duration::from_millis(5));
+        state.cleanup_timed_out();
+
+        let stats = state.round_stats();
+        assert_eq!((stats.delivered, stats.timed_out), (1, 1));
+        assert!(stats.avg_echo_phase().is_some());
+        assert!(stats.avg_ready_phase().is_some());
+        assert_eq!(stats.avg_echo_response_rate(), Some(0.5));
+        assert_eq!(stats.avg_ready_response_rate(), Some(1.0));
+
+        let recent: Vec<_> = stats.recent().map(|r| (r.hash.as_str(), r.outcome)).collect();
+        assert_eq!(recent, vec![("hash1", RoundOutcome::Delivered), ("hash2", RoundOutcome::TimedOut)]);
+    }
+
+    #[test]
+    fn test_start_round_with_keeps_first_thresholds() {
+        let mut state = GossipState::<DefaultMessage>::new();
+        let first = Thresholds { ready: 2, feedback: 3, delivery: 4 };
+        let second = Thresholds { ready: 5, feedback: 6, delivery: 7 };
+
+        state.start_round_with("hash1", first);
+        state.start_round_with("hash1", second);
+        assert_eq!(state.get_round("hash1").unwrap().thresholds, Some(first));
+    }
+
+    #[test]
+    fn test_has_seen_outlives_cleanup() {
+        let mut state = GossipState::<DefaultMessage>::new();
+        state.set_timeout(Duration::ZERO);
+        state.start_round("hash1");
+        state.store_message("hash1".into(), batch("b1"));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(state.cleanup_timed_out(), vec!["hash1".to_string()]);
+        assert!(!state.has_message("hash1"));
+        assert!(state.has_seen("hash1"));
+        assert!(!state.has_seen("hash2"));
+    }
+
+    #[test]
+    fn test_store_counts_bytes() {
+        let mut store = MessageStore::<DefaultMessage>::new();
+        let bytes = crate::util::serialized_len(&batch("b1")).unwrap();
+        store.insert("hash1".into(), batch("b1"));
+        store.insert("hash2".into(), batch("b2"));
+        assert_eq!(store.bytes(), 2 * bytes);
+
+        store.insert("hash1".into(), batch("b1"));
+        assert_eq!(store.bytes(), 2 * bytes);
+        store.remove("hash1");
+        assert_eq!(store.stats(), StoreStats { batches: 1, bytes, ..Default::default() });
+    }
+
+    #[test]
+    fn test_retention_evicts_by_outcome_and_age() {
+        let mut store = MessageStore::<DefaultMessage>::new();
+        store.set_retention(RetentionPolicy {
+            max_delivered: DEFAULT_MAX_DELIVERED,
+            delivered: Duration::from_secs(60),
+            timed_out: Duration::ZERO,
+        });
+        for hash in ["running", "delivered", "timed-out"] {
+            store.insert(hash.into(), batch(hash));
+        }
+        store.retire("delivered", RoundOutcome::Delivered);
+        store.retire("timed-out", RoundOutcome::TimedOut);
+        store.retire("timed-out", RoundOutcome::Delivered);
+
+        let stats = store.stats();
+        assert_eq!((stats.delivered, stats.timed_out), (1, 1));
+        assert_eq!(store.evict(), vec!["timed-out".to_string()]);
+        assert!(store.contains("running") && store.contains("delivered"));
+        assert!(store.has_seen("timed-out"));
+
+        store.set_retention(RetentionPolicy { delivered: Duration::ZERO, ..RetentionPolicy::default() });
+        assert_eq!(store.evict(), vec!["delivered".to_string()]);
+        assert!(store.evict().is_empty());
+        assert_eq!(store.stats().evicted, 2);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_timed_out_batches_outlive_their_round() {
+        let mut state = GossipState::<DefaultMessage>::new();
+        state.set_timeout(Duration::ZERO);
+        state.set_retention(RetentionPolicy { timed_out: Duration::from_secs(60), ..RetentionPolicy::default() });
+        state.start_round("hash1");
+        state.store_message("hash1".into(), batch("b1"));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(state.cleanup_timed_out(), vec!["hash1".to_string()]);
+        assert!(state.get_round("hash1").is_none());
+        assert!(state.has_message("hash1"));
+        assert_eq!(state.store_stats().timed_out, 1);
+    }
 }
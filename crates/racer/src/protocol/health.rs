@@ -0,0 +1,401 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use racer_core::message::now_millis;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{EcdsaSignature, PublicKey, Signer};
+
+/// Neighbours reported per [`HealthSummary`], at most.
+pub const MAX_NEIGHBORS: usize = 64;
+
+/// Compact, signed summary of a node's recent consensus performance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSummary {
+    pub sender: PublicKey,
+    pub window_secs: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    pub avg_round_latency: f64,
+    pub timestamp: u64,
+    /// The sender's own peers and its latency to each, for topology views.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub neighbors: Vec<Neighbor>,
+    pub signature: Option<EcdsaSignature>,
+}
+
+/// A peer as seen by the node reporting it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Neighbor {
+    /// Leading hex of the peer's public key, as in [`HealthSummary::sender_id`].
+    pub key_id: String,
+    pub latency_secs: f64,
+    /// Seconds since the reporter last heard from the peer, if ever.
+    pub last_seen_secs: Option<f64>,
+}
+
+impl HealthSummary {
+    pub fn new(
+        sender: PublicKey,
+        window_secs: u64,
+        delivered: u64,
+        failed: u64,
+        avg_round_latency: f64,
+    ) -> Self {
+        Self {
+            sender,
+            window_secs,
+            delivered,
+            failed,
+            avg_round_latency,
+            neighbors: Vec::new(),
+            signature: None,
+            timestamp: now_millis(),
+        }
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut fields = serde_json::json!({
+            "sender": self.sender.to_hex(),
+            "window_secs": self.window_secs,
+            "delivered": self.delivered,
+            "failed": self.failed,
+            "avg_round_latency": self.avg_round_latency,
+            "timestamp": self.timestamp,
+        });
+        if !self.neighbors.is_empty() {
+            fields["neighbors"] = serde_json::json!(self.neighbors);
+        }
+        fields.to_string().into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn sender_id(&self) -> String {
+        self.sender.to_hex()[..10].to_string()
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.sender, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+
+    pub fn delivery_rate(&self) -> f64 {
+        let total = self.delivered.saturating_add(self.failed);
+        if total == 0 {
+            return 1.0;
+        }
+        self.delivered as f64 / total as f64
+    }
+}
+
+/// Sliding window of this node's own round outcomes.
+#[derive(Debug)]
+pub struct HealthTracker {
+    window: Duration,
+    outcomes: VecDeque<(Instant, bool, f64)>,
+}
+
+impl HealthTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            outcomes: VecDeque::new(),
+        }
+    }
+
+    pub fn record_delivered(&mut self, latency_secs: f64) {
+        self.outcomes.push_back((Instant::now(), true, latency_secs));
+        self.prune();
+    }
+
+    pub fn record_failed(&mut self) {
+        self.outcomes.push_back((Instant::now(), false, 0.0));
+        self.prune();
+    }
+
+    fn prune(&mut self) {
+        while let Some((at, _, _)) = self.outcomes.front() {
+            if at.elapsed() > self.window {
+                self.outcomes.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn summary(&mut self, sender: PublicKey) -> HealthSummary {
+        self.prune();
+        let delivered: Vec<f64> = self
+            .outcomes
+            .iter()
+            .filter(|(_, ok, _)| *ok)
+            .map(|(_, _, latency)| *latency)
+            .collect();
+        let failed = (self.outcomes.len() - delivered.len()) as u64;
+        let avg_latency = if delivered.is_empty() {
+            0.0
+        } else {
+            delivered.iter().sum::<f64>() / delivered.len() as f64
+        };
+
+        HealthSummary::new(
+            sender,
+            self.window.as_secs(),
+            delivered.len() as u64,
+            failed,
+            avg_latency,
+        )
+    }
+}
+
+/// Aggregates the latest summary received from each peer.
+#[derive(Debug)]
+pub struct ClusterHealth {
+    summaries: HashMap<String, (Instant, HealthSummary)>,
+    max_age: Duration,
+}
+
+impl ClusterHealth {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            summaries: HashMap::new(),
+            max_age,
+        }
+    }
+
+    /// Keeps `summary` as its sender's latest, unless it is older than the
+    /// one held or dated further than the window from `sender_now`, now
+    /// (epoch millis) by the sender's clock as far as its skew is known.
+    /// Summaries that have gone stale are dropped. Returns whether it was
+    /// kept.
+    pub fn record(&mut self, summary: HealthSummary, sender_now: u64) -> bool {
+        let max_age = self.max_age;
+        self.summaries.retain(|_, (at, _)| at.elapsed() <= max_age);
+
+        if sender_now.abs_diff(summary.timestamp) > max_age.as_millis() as u64 {
+            return false;
+        }
+        let sender = summary.sender_id();
+        if let Some((_, held)) = self.summaries.get(&sender) {
+            if summary.timestamp <= held.timestamp {
+                return false;
+            }
+        }
+        self.summaries.insert(sender, (Instant::now(), summary));
+        true
+    }
+
+    /// Peers with a summary held, fresh or not yet dropped.
+    pub fn len(&self) -> usize {
+        self.summaries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summaries.is_empty()
+    }
+
+    fn fresh(&self) -> impl Iterator<Item = &HealthSummary> {
+        self.summaries
+            .values()
+            .filter(|(at, _)| at.elapsed() <= self.max_age)
+            .map(|(_, s)| s)
+    }
+
+    /// Each fresh summary's sender id with the neighbours it reported.
+    pub fn neighbors(&self) -> impl Iterator<Item = (String, &[Neighbor])> {
+        self.fresh()
+            .map(|s| (s.sender_id(), &s.neighbors[..s.neighbors.len().min(MAX_NEIGHBORS)]))
+    }
+
+    pub fn stats(&self) -> ClusterHealthStats {
+        let fresh: Vec<_> = self.fresh().collect();
+        if fresh.is_empty() {
+            return ClusterHealthStats::default();
+        }
+
+        // Counts are peer-supplied, so a forged summary must not overflow them.
+        let delivered = fresh.iter().fold(0u64, |acc, s| acc.saturating_add(s.delivered));
+        let failed = fresh.iter().fold(0u64, |acc, s| acc.saturating_add(s.failed));
+        let total = delivered.saturating_add(failed);
+        let delivery_rate = if total == 0 {
+            1.0
+        } else {
+            delivered as f64 / total as f64
+        };
+
+        let with_latency: Vec<f64> = fresh
+            .iter()
+            .filter(|s| s.delivered > 0)
+            .map(|s| s.avg_round_latency)
+            .collect();
+        let avg_round_latency = if with_latency.is_empty() {
+            0.0
+        } else {
+            with_latency.iter().sum::<f64>() / with_latency.len() as f64
+        };
+
+        ClusterHealthStats {
+            reporting_peers: fresh.len(),
+            delivery_rate,
+            avg_round_latency,
+        }
+    }
+
+    /// Fanout multiplier in `[1.0, 1.5]`, growing as cluster delivery rate drops.
+    pub fn fanout_multiplier(&self) -> f64 {
+        let rate = self.stats().delivery_rate;
+        (1.0 + (1.0 - rate)).clamp(1.0, 1.5)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterHealthStats {
+    pub reporting_peers: usize,
+    pub delivery_rate: f64,
+    pub avg_round_latency: f64,
+}
+
+impl Default for ClusterHealthStats {
+    fn default() -> Self {
+        Self {
+            reporting_peers: 0,
+            delivery_rate: 1.0,
+            avg_round_latency: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{EcdsaSigner, KeyPair};
+
+    #[test]
+    fn test_summary_sign_verify() {
+        let keys = KeyPair::generate();
+        let signer = EcdsaSigner::new(keys.signing_key().clone());
+
+        let mut summary = HealthSummary::new(keys.public_key(), 60, 9, 1, 1.2);
+        assert!(!summary.verify());
+
+        summary.sign(&signer);
+        assert!(summary.verify());
+
+        summary.delivered = 100;
+        assert!(!summary.verify());
+    }
+
+    #[test]
+    fn test_neighbors_are_signed() {
+        let keys = KeyPair::generate();
+        let signer = EcdsaSigner::new(keys.signing_key().clone());
+
+        let mut summary = HealthSummary::new(keys.public_key(), 60, 1, 0, 0.5);
+        summary.neighbors.push(Neighbor {
+            key_id: "0123456789".into(),
+            latency_secs: 0.02,
+            last_seen_secs: Some(1.0),
+        });
+        summary.sign(&signer);
+        assert!(summary.verify());
+
+        summary.neighbors[0].latency_secs = 9.0;
+        assert!(!summary.verify());
+
+        let mut cluster = ClusterHealth::new(Duration::from_secs(60));
+        cluster.record(summary.clone(), summary.timestamp);
+        let reported: Vec<_> = cluster.neighbors().collect();
+        assert_eq!(reported, vec![(summary.sender_id(), &summary.neighbors[..])]);
+    }
+
+    #[test]
+    fn test_tracker_summary() {
+        let keys = KeyPair::generate();
+        let mut tracker = HealthTracker::new(Duration::from_secs(60));
+        tracker.record_delivered(1.0);
+        tracker.record_delivered(3.0);
+        tracker.record_failed();
+
+        let summary = tracker.summary(keys.public_key());
+        assert_eq!(summary.delivered, 2);
+        assert_eq!(summary.failed, 1);
+        assert!((summary.avg_round_latency - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cluster_aggregation() {
+        let mut cluster = ClusterHealth::new(Duration::from_secs(60));
+        assert_eq!(cluster.stats().reporting_peers, 0);
+        assert!((cluster.fanout_multiplier() - 1.0).abs() < 1e-9);
+
+        for summary in [
+            HealthSummary::new(KeyPair::generate().public_key(), 60, 3, 1, 2.0),
+            HealthSummary::new(KeyPair::generate().public_key(), 60, 1, 3, 4.0),
+        ] {
+            let now = summary.timestamp;
+            assert!(cluster.record(summary, now));
+        }
+
+        let stats = cluster.stats();
+        assert_eq!(stats.reporting_peers, 2);
+        assert!((stats.delivery_rate - 0.5).abs() < 1e-9);
+        assert!((stats.avg_round_latency - 3.0).abs() < 1e-9);
+        assert!((cluster.fanout_multiplier() - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cluster_saturates_forged_counts() {
+        let mut cluster = ClusterHealth::new(Duration::from_secs(60));
+        for summary in [
+            HealthSummary::new(KeyPair::generate().public_key(), 60, u64::MAX, u64::MAX, 1.0),
+            HealthSummary::new(KeyPair::generate().public_key(), 60, u64::MAX, 0, 1.0),
+        ] {
+            let now = summary.timestamp;
+            cluster.record(summary, now);
+        }
+
+        assert!((cluster.stats().delivery_rate - 1.0).abs() < 1e-9);
+        assert_eq!(HealthSummary::new(KeyPair::generate().public_key(), 60, u64::MAX, 1, 0.0).delivery_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_cluster_refuses_old_summaries() {
+        let mut cluster = ClusterHealth::new(Duration::from_secs(60));
+        let sender = KeyPair::generate().public_key();
+        let latest = HealthSummary::new(sender.clone(), 60, 5, 0, 1.0);
+        let now = latest.timestamp;
+
+        let mut stale = latest.clone();
+        stale.timestamp -= 61_000;
+        assert!(!cluster.record(stale, now));
+        assert!(cluster.is_empty());
+
+        assert!(cluster.record(latest.clone(), now));
+        let mut older = latest.clone();
+        older.timestamp -= 1;
+        older.failed = 100;
+        assert!(!cluster.record(older, now));
+        assert!(!cluster.record(latest, now));
+        assert!((cluster.stats().delivery_rate - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cluster_drops_stale_summaries() {
+        let mut cluster = ClusterHealth::new(Duration::ZERO);
+        let gone = HealthSummary::new(KeyPair::generate().public_key(), 60, 1, 0, 1.0);
+        let now = gone.timestamp;
+        cluster.record(gone, now);
+        std::thread::sleep(Duration::from_millis(5));
+
+        let summary = HealthSummary::new(KeyPair::generate().public_key(), 60, 1, 0, 1.0);
+        let now = summary.timestamp;
+        cluster.record(summary, now);
+        assert_eq!(cluster.len(), 1);
+    }
+}
@@ -0,0 +1,457 @@
+//! Membership changes agreed through consensus.
+//!
+//! A [`MembershipUpdate`] is signed by the node joining or leaving and rides
+//! in an otherwise empty batch, so it is delivered like any other batch. Once
+//! the sequencer releases it in total order, [`Membership`] holds it until the
+//! next epoch boundary, where every update of the epoch is applied at once.
+//! Epochs are fixed slices of wall-clock time, so every node assigns an update
+//! to the same boundary from its batch's `created_at` alone.
+//!
+//! An update is only staged from a batch its member created, stamped within
+//! an epoch of the batch and newer than the member's last update, so a
+//! captured update cannot be replayed in another batch. Joins also need the
+//! [`Admission`] of the receiving node.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::At2Config;
+use crate::crypto::{EcdsaSignature, PublicKey, Signer};
+
+/// Most updates one batch may carry. A batch only carries updates for its
+/// creator, and one is all it can mean.
+pub const MAX_UPDATES_PER_BATCH: usize = 1;
+
+/// Why the updates of a batch were not staged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum MembershipError {
+    #[error("update signature missing or invalid")]
+    BadSignature,
+    #[error("update is not for the batch creator")]
+    NotCreator,
+    #[error("batch carries more than {MAX_UPDATES_PER_BATCH} updates")]
+    TooMany,
+    #[error("update is outside its batch's epoch window")]
+    Stale,
+    #[error("update is not newer than the member's last one")]
+    Replayed,
+    #[error("member may not join")]
+    NotAdmitted,
+}
+
+/// Which keys may join through a membership update.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Admission {
+    /// No key may join; peers come from configuration and discovery only.
+    #[default]
+    Closed,
+    Open,
+    /// Keys whose hex encoding starts with one of these lowercase prefixes.
+    /// A full key is a prefix matching only itself.
+    Prefixes(Vec<String>),
+}
+
+impl Admission {
+    /// The admission `consensus.open_membership` and
+    /// `consensus.allowed_creators` describe.
+    pub fn from_config(config: &At2Config) -> Self {
+        if config.open_membership {
+            Self::Open
+        } else if config.allowed_creators.is_empty() {
+            Self::Closed
+        } else {
+            let prefixes = config.allowed_creators.iter();
+            Self::Prefixes(prefixes.map(|entry| entry.trim_end_matches('*').to_ascii_lowercase()).collect())
+        }
+    }
+
+    pub fn admits(&self, key: &PublicKey) -> bool {
+        match self {
+            Self::Closed => false,
+            Self::Open => true,
+            Self::Prefixes(prefixes) => {
+                let hex = key.to_hex();
+                prefixes.iter().any(|prefix| hex.starts_with(prefix.as_str()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MembershipChange {
+    Join {
+        router_address: String,
+        publisher_address: String,
+    },
+    Leave,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipUpdate {
+    /// The node joining or leaving; it signs the update.
+    pub member: PublicKey,
+    pub change: MembershipChange,
+    pub timestamp: u64,
+    pub signature: Option<EcdsaSignature>,
+}
+
+impl MembershipUpdate {
+    pub fn new(member: PublicKey, change: MembershipChange) -> Self {
+        Self {
+            member,
+            change,
+            timestamp: racer_core::message::now_millis(),
+            signature: None,
+        }
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::json!({
+            "member": self.member.to_hex(),
+            "change": self.change,
+            "timestamp": self.timestamp,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn member_id(&self) -> String {
+        self.member.to_hex()[..10].to_string()
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.member, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub key: PublicKey,
+    pub router_address: String,
+    pub publisher_address: String,
+}
+
+/// Net effect of the updates applied at one epoch boundary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpochChange {
+    pub epoch: u64,
+    pub joined: Vec<Member>,
+    pub left: Vec<PublicKey>,
+}
+
+pub struct Membership {
+    epoch_length_millis: u64,
+    epoch: u64,
+    admission: Admission,
+    members: BTreeMap<String, Member>,
+    pending: BTreeMap<u64, Vec<MembershipUpdate>>,
+    /// Timestamp of the last update staged per member key, kept while such
+    /// an update could still be staged.
+    latest: HashMap<String, u64>,
+}
+
+impl Membership {
+    pub fn new(epoch_length: Duration) -> Self {
+        Self {
+            epoch_length_millis: (epoch_length.as_millis() as u64).max(1),
+            epoch: 0,
+            admission: Admission::default(),
+            members: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            latest: HashMap::new(),
+        }
+    }
+
+    pub fn with_admission(mut self, admission: Admission) -> Self {
+        self.admission = admission;
+        self
+    }
+
+    /// The last epoch whose boundary was applied.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Epoch containing `millis` (epoch millis).
+    pub fn epoch_at(&self, millis: u64) -> u64 {
+        millis / self.epoch_length_millis
+    }
+
+    /// Nodes that joined through a membership update and have not left.
+    pub fn members(&self) -> impl Iterator<Item = &Member> {
+        self.members.values()
+    }
+
+    pub fn is_member(&self, key: &PublicKey) -> bool {
+        self.members.contains_key(&key.to_hex())
+    }
+
+    /// Updates waiting for their epoch boundary.
+    pub fn pending(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    /// Queues the updates of a batch `creator` created at `created_at` for
+    /// the boundary after it. Queues none of them if any is invalid.
+    pub fn stage(
+        &mut self,
+        updates: &[MembershipUpdate],
+        creator: &PublicKey,
+        created_at: u64,
+    ) -> Result<(), MembershipError> {
+        if updates.len() > MAX_UPDATES_PER_BATCH {
+            return Err(MembershipError::TooMany);
+        }
+        let boundary = self.epoch_at(created_at).saturating_add(1);
+        for update in updates {
+            self.check(update, creator, created_at, boundary)?;
+        }
+
+        let epoch = boundary.max(self.epoch + 1);
+        for update in updates {
+            self.latest.insert(update.member.to_hex(), update.timestamp);
+            self.pending.entry(epoch).or_default().push(update.clone());
+        }
+        Ok(())
+    }
+
+    fn check(
+        &self,
+        update: &MembershipUpdate,
+        creator: &PublicKey,
+        created_at: u64,
+        boundary: u64,
+    ) -> Result<(), MembershipError> {
+        if update.member != *creator {
+            return Err(MembershipError::NotCreator);
+        }
+        // A batch may arrive after its own boundary, not after the next.
+        if created_at.abs_diff(update.timestamp) > self.epoch_length_millis || boundary < self.epoch {
+            return Err(MembershipError::Stale);
+        }
+        if self.latest.get(&update.member.to_hex()).is_some_and(|&last| update.timestamp <= last) {
+            return Err(MembershipError::Replayed);
+        }
+        if matches!(update.change, MembershipChange::Join { .. }) && !self.admission.admits(&update.member) {
+            return Err(MembershipError::NotAdmitted);
+        }
+        if !update.verify() {
+            return Err(MembershipError::BadSignature);
+        }
+        Ok(())
+    }
+
+    /// Moves to the epoch containing `watermark_millis`, applying the updates
+    /// due by then in the order they were staged. Returns the change if any
+    /// update was applied.
+    pub fn advance(&mut self, watermark_millis: u64) -> Option<EpochChange> {
+        let epoch = self.epoch_at(watermark_millis);
+        if epoch <= self.epoch {
+            return None;
+        }
+        self.epoch = epoch;
+        // Older updates are stale by now, so need no replay check.
+        let epoch_length = self.epoch_length_millis;
+        self.latest.retain(|_, timestamp| (*timestamp / epoch_length).saturating_add(2) >= epoch);
+
+        let later = self.pending.split_off(&(epoch + 1));
+        let due = std::mem::replace(&mut self.pending, later);
+        if due.is_empty() {
+            return None;
+        }
+
+        let mut change = EpochChange {
+            epoch,
+            ..Default::default()
+        };
+        for update in due.into_values().flatten() {
+            let key = update.member;
+            change.joined.retain(|m| m.key != key);
+            change.left.retain(|k| *k != key);
+            match update.change {
+                MembershipChange::Join {
+                    router_address,
+                    publisher_address,
+                } => {
+                    let member = Member {
+                        key: key.clone(),
+                        router_address,
+                        publisher_address,
+                    };
+                    self.members.insert(key.to_hex(), member.clone());
+                    change.joined.push(member);
+                }
+                MembershipChange::Leave => {
+                    self.members.remove(&key.to_hex());
+                    change.left.push(key);
+                }
+            }
+        }
+        Some(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{EcdsaSigner, KeyPair};
+
+    const EPOCH: Duration = Duration::from_millis(1_000);
+
+    fn signed_at(keys: &KeyPair, change: MembershipChange, timestamp: u64) -> MembershipUpdate {
+        let mut update = MembershipUpdate::new(keys.public_key(), change);
+        update.timestamp = timestamp;
+        update.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+        update
+    }
+
+    fn join_at(keys: &KeyPair, timestamp: u64) -> MembershipUpdate {
+        signed_at(
+            keys,
+            MembershipChange::Join {
+                router_address: "tcp://10.0.0.2:20001".into(),
+                publisher_address: "tcp://10.0.0.2:21001".into(),
+            },
+            timestamp,
+        )
+    }
+
+    fn join(keys: &KeyPair) -> MembershipUpdate {
+        join_at(keys, 1_000)
+    }
+
+    fn open() -> Membership {
+        Membership::new(EPOCH).with_admission(Admission::Open)
+    }
+
+    /// Stages `update` from a batch its member created at `created_at`.
+    fn stage(membership: &mut Membership, update: MembershipUpdate, created_at: u64) -> Result<(), MembershipError> {
+        let creator = update.member.clone();
+        membership.stage(&[update], &creator, created_at)
+    }
+
+    #[test]
+    fn test_update_signature() {
+        let keys = KeyPair::generate();
+        let mut update = join(&keys);
+        assert!(update.verify());
+
+        update.change = MembershipChange::Leave;
+        assert!(!update.verify());
+        assert!(!MembershipUpdate::new(keys.public_key(), MembershipChange::Leave).verify());
+    }
+
+    #[test]
+    fn test_updates_apply_at_next_boundary() {
+        let keys = KeyPair::generate();
+        let mut membership = open();
+        assert_eq!(stage(&mut membership, join_at(&keys, 5_200), 5_200), Ok(()));
+
+        assert!(membership.advance(5_900).is_none());
+        assert_eq!(membership.pending(), 1);
+
+        let change = membership.advance(6_000).unwrap();
+        assert_eq!(change.epoch, 6);
+        assert_eq!(change.joined.len(), 1);
+        assert!(membership.is_member(&keys.public_key()));
+        assert_eq!(membership.pending(), 0);
+    }
+
+    #[test]
+    fn test_leave_in_same_epoch_cancels_join() {
+        let keys = KeyPair::generate();
+        let mut membership = open();
+        stage(&mut membership, join_at(&keys, 1_100), 1_100).unwrap();
+        stage(&mut membership, signed_at(&keys, MembershipChange::Leave, 1_300), 1_300).unwrap();
+
+        let change = membership.advance(2_000).unwrap();
+        assert!(change.joined.is_empty());
+        assert_eq!(change.left, vec![keys.public_key()]);
+        assert!(!membership.is_member(&keys.public_key()));
+    }
+
+    #[test]
+    fn test_stale_update_waits_for_following_boundary() {
+        let keys = KeyPair::generate();
+        let mut membership = open();
+        membership.advance(3_000);
+
+        stage(&mut membership, join_at(&keys, 2_500), 2_500).unwrap();
+        assert_eq!(membership.advance(4_000).unwrap().epoch, 4);
+    }
+
+    #[test]
+    fn test_old_and_replayed_updates_are_rejected() {
+        let keys = KeyPair::generate();
+        let mut membership = open();
+        membership.advance(3_000);
+
+        let leave = signed_at(&keys, MembershipChange::Leave, 1_000);
+        assert_eq!(stage(&mut membership, leave.clone(), 1_000), Err(MembershipError::Stale));
+        // An old update in a new batch is no fresher.
+        assert_eq!(stage(&mut membership, leave, 3_100), Err(MembershipError::Stale));
+
+        let leave = signed_at(&keys, MembershipChange::Leave, 3_100);
+        assert_eq!(stage(&mut membership, leave.clone(), 3_100), Ok(()));
+        assert_eq!(stage(&mut membership, leave, 3_200), Err(MembershipError::Replayed));
+        assert_eq!(membership.pending(), 1);
+    }
+
+    #[test]
+    fn test_update_must_come_from_its_member() {
+        let (member, creator) = (KeyPair::generate(), KeyPair::generate());
+        let mut membership = open();
+        let leave = signed_at(&member, MembershipChange::Leave, 1_000);
+        assert_eq!(
+            membership.stage(&[leave.clone()], &creator.public_key(), 1_000),
+            Err(MembershipError::NotCreator)
+        );
+
+        let second = signed_at(&member, MembershipChange::Leave, 1_001);
+        assert_eq!(
+            membership.stage(&[leave, second], &member.public_key(), 1_000),
+            Err(MembershipError::TooMany)
+        );
+        assert_eq!(membership.pending(), 0);
+    }
+
+    #[test]
+    fn test_joins_need_admission() {
+        let (admitted, other) = (KeyPair::generate(), KeyPair::generate());
+        let prefix = format!("{}*", &admitted.public_key().to_hex()[..12]);
+        let config = At2Config {
+            allowed_creators: vec![prefix],
+            ..Default::default()
+        };
+        let mut membership = Membership::new(EPOCH).with_admission(Admission::from_config(&config));
+
+        assert_eq!(stage(&mut membership, join_at(&other, 1_000), 1_000), Err(MembershipError::NotAdmitted));
+        assert_eq!(stage(&mut membership, join_at(&admitted, 1_000), 1_000), Ok(()));
+        // Anyone may leave.
+        assert_eq!(stage(&mut membership, signed_at(&other, MembershipChange::Leave, 1_000), 1_000), Ok(()));
+
+        let mut closed = Membership::new(EPOCH);
+        assert_eq!(stage(&mut closed, join_at(&admitted, 1_000), 1_000), Err(MembershipError::NotAdmitted));
+    }
+
+    #[test]
+    fn test_unsigned_update_is_rejected() {
+        let keys = KeyPair::generate();
+        let mut membership = open();
+        let unsigned = MembershipUpdate::new(keys.public_key(), MembershipChange::Leave);
+        let created_at = unsigned.timestamp;
+        assert_eq!(stage(&mut membership, unsigned, created_at), Err(MembershipError::BadSignature));
+        assert_eq!(membership.pending(), 0);
+    }
+}
@@ -1,8 +1,57 @@
+use rs_merkle::{algorithms::Sha256, MerkleTree};
 use serde::{Deserialize, Serialize};
 
-use crate::crypto::{EcdsaSignature, EcdsaSigner, PublicKey};
+use crate::config::NodeRole;
+use crate::crypto::{EcdsaSignature, PublicKey, Signer};
+use crate::Message;
 
-use super::VectorClock;
+use super::version::{self, Capabilities, ProtocolOffer};
+use super::{MembershipUpdate, VectorClock};
+
+/// How urgently a batch should be gossiped relative to others. Orders local
+/// submissions waiting for a round slot and frames waiting in per-peer send
+/// queues, and selects `[consensus.priorities]` thresholds if configured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// Every priority, most urgent first.
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    /// The name used on the wire and in config keys.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+
+    pub(crate) fn rank(self) -> usize {
+        self as usize
+    }
+}
+
+fn is_normal(priority: &Priority) -> bool {
+    *priority == Priority::Normal
+}
+
+fn is_unsequenced(sequence: &u64) -> bool {
+    *sequence == 0
+}
+
+/// Hex root of the merkle tree over each message's [`Message::content_hash`].
+/// One message's root is its content hash; no messages give all zeros.
+pub fn merkle_root<M: Message>(messages: &[M]) -> String {
+    let leaves: Vec<[u8; 32]> = messages.iter().map(Message::content_hash).collect();
+    hex::encode(MerkleTree::<Sha256>::from_leaves(&leaves).root().unwrap_or_default())
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatchedMessages<M> {
@@ -16,6 +65,30 @@ pub struct BatchedMessages<M> {
     pub creator_signature: Option<EcdsaSignature>,
     pub sender_signature: Option<EcdsaSignature>,
     pub created_at: u64,
+    /// Join/leave updates agreed through this batch; see [`super::membership`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub membership: Vec<MembershipUpdate>,
+    /// Consensus channel the batch belongs to; `None` is the default channel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Relays the batch has passed through; each re-gossip adds one.
+    #[serde(default)]
+    pub hops: u32,
+    /// Hops after which the batch is no longer re-gossiped; `None` is unlimited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hops: Option<u32>,
+    /// Milliseconds after `created_at` past which the batch is dropped
+    /// unprocessed; `None` never expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+    /// Set by the creator; relays send the batch at the same priority.
+    #[serde(default, skip_serializing_if = "is_normal")]
+    pub priority: Priority,
+    /// The creator's count of batches it created, from 1, so receivers can
+    /// tell missed and reordered batches apart; 0 from creators that do not
+    /// number their batches.
+    #[serde(default, skip_serializing_if = "is_unsequenced")]
+    pub sequence: u64,
     #[cfg(feature = "bls")]
     pub creator_bls: Option<crate::crypto::BlsPublicKey>,
     #[cfg(feature = "bls")]
@@ -39,31 +112,50 @@ where
     }
 
     pub fn creator_signing_bytes(&self) -> Vec<u8> {
-        serde_json::json!({
+        let mut fields = serde_json::json!({
             "batch_id": self.batch_id,
             "merkle_root": self.merkle_root,
             "batch_size": self.batch_size,
             "created_at": self.created_at,
-        })
-        .to_string()
-        .into_bytes()
+        });
+        if !self.membership.is_empty() {
+            fields["membership"] = serde_json::json!(self.membership);
+        }
+        if let Some(channel) = &self.channel {
+            fields["channel"] = serde_json::json!(channel);
+        }
+        if let Some(max_hops) = self.max_hops {
+            fields["max_hops"] = serde_json::json!(max_hops);
+        }
+        if let Some(ttl_ms) = self.ttl_ms {
+            fields["ttl_ms"] = serde_json::json!(ttl_ms);
+        }
+        if self.priority != Priority::Normal {
+            fields["priority"] = serde_json::json!(self.priority);
+        }
+        if self.sequence > 0 {
+            fields["sequence"] = serde_json::json!(self.sequence);
+        }
+        fields.to_string().into_bytes()
     }
 
     pub fn sender_signing_bytes(&self) -> Vec<u8> {
-        serde_json::json!({
+        let mut fields = serde_json::json!({
             "batch_id": self.batch_id,
             "merkle_root": self.merkle_root,
             "sender": self.sender_ecdsa.to_hex(),
-        })
-        .to_string()
-        .into_bytes()
+        });
+        if self.hops > 0 {
+            fields["hops"] = serde_json::json!(self.hops);
+        }
+        fields.to_string().into_bytes()
     }
 
-    pub fn sign_as_creator(&mut self, signer: &EcdsaSigner) {
+    pub fn sign_as_creator(&mut self, signer: &dyn Signer) {
         self.creator_signature = Some(signer.sign(&self.creator_signing_bytes()));
     }
 
-    pub fn sign_as_sender(&mut self, signer: &EcdsaSigner) {
+    pub fn sign_as_sender(&mut self, signer: &dyn Signer) {
         self.sender_signature = Some(signer.sign(&self.sender_signing_bytes()));
     }
 
@@ -71,11 +163,20 @@ where
         self.creator_signature.is_some() && self.sender_signature.is_some()
     }
 
-    pub fn become_sender(&self, keys: &crate::crypto::KeyPair) -> Self {
-        let mut new_bm = Self {
+    /// Re-signs the batch as sent by `signer`, counting one more hop.
+    pub fn become_sender(&self, signer: &dyn Signer) -> Self {
+        let mut new_bm = self.relay_copy(signer.public_key());
+        new_bm.sign_as_sender(signer);
+        new_bm
+    }
+
+    /// The batch as relayed by `sender_ecdsa`, one hop further and without
+    /// a sender signature yet.
+    pub fn relay_copy(&self, sender_ecdsa: PublicKey) -> Self {
+        Self {
             batch_id: self.batch_id.clone(),
             creator_ecdsa: self.creator_ecdsa.clone(),
-            sender_ecdsa: keys.public_key(),
+            sender_ecdsa,
             merkle_root: self.merkle_root.clone(),
             batch_size: self.batch_size,
             messages: self.messages.clone(),
@@ -83,23 +184,23 @@ where
             creator_signature: self.creator_signature.clone(),
             sender_signature: None,
             created_at: self.created_at,
+            membership: self.membership.clone(),
+            channel: self.channel.clone(),
+            hops: self.hops.saturating_add(1),
+            max_hops: self.max_hops,
+            ttl_ms: self.ttl_ms,
+            priority: self.priority,
+            sequence: self.sequence,
             #[cfg(feature = "bls")]
             creator_bls: self.creator_bls.clone(),
             #[cfg(feature = "bls")]
             aggregated_signature: self.aggregated_signature.clone(),
-        };
-        let signer = EcdsaSigner::new(keys.signing_key().clone());
-        new_bm.sign_as_sender(&signer);
-        new_bm
+        }
     }
 
     pub fn verify_creator_signature(&self) -> bool {
         if let Some(signature) = &self.creator_signature {
-            let verifier = match crate::crypto::EcdsaVerifier::from_public_key(&self.creator_ecdsa) {
-                Ok(v) => v,
-                Err(_) => return false,
-            };
-            verifier.verify(&self.creator_signing_bytes(), signature).is_ok()
+            crate::crypto::verify_cached(&self.creator_ecdsa, &self.creator_signing_bytes(), signature)
         } else {
             false
         }
@@ -107,11 +208,7 @@ where
 
     pub fn verify_sender_signature(&self) -> bool {
         if let Some(signature) = &self.sender_signature {
-            let verifier = match crate::crypto::EcdsaVerifier::from_public_key(&self.sender_ecdsa) {
-                Ok(v) => v,
-                Err(_) => return false,
-            };
-            verifier.verify(&self.sender_signing_bytes(), signature).is_ok()
+            crate::crypto::verify_cached(&self.sender_ecdsa, &self.sender_signing_bytes(), signature)
         } else {
             false
         }
@@ -140,9 +237,18 @@ where
     }
 }
 
- impl<M> BatchedMessages<M> {
+impl<M> BatchedMessages<M> {
+    /// Whether the batch outlived its `ttl_ms` at `now_ms` (epoch millis).
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.ttl_ms
+            .is_some_and(|ttl| now_ms > self.created_at.saturating_add(ttl))
+    }
 
- }
+    /// Whether the batch has used up its `max_hops` and must not be relayed further.
+    pub fn hop_limit_reached(&self) -> bool {
+        self.max_hops.is_some_and(|max| self.hops >= max)
+    }
+}
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -186,7 +292,7 @@ impl Echo {
         .into_bytes()
     }
 
-    pub fn sign(&mut self, signer: &EcdsaSigner) {
+    pub fn sign(&mut self, signer: &dyn Signer) {
         self.signature = Some(signer.sign(&self.signing_bytes()));
     }
 
@@ -200,11 +306,7 @@ impl Echo {
 
     pub fn verify(&self) -> bool {
         if let Some(signature) = &self.signature {
-            let verifier = match crate::crypto::EcdsaVerifier::from_public_key(&self.sender) {
-                Ok(v) => v,
-                Err(_) => return false,
-            };
-            verifier.verify(&self.signing_bytes(), signature).is_ok()
+            crate::crypto::verify_cached(&self.sender, &self.signing_bytes(), signature)
         } else {
             false
         }
@@ -265,7 +367,7 @@ impl ProtocolResponse {
         .into_bytes()
     }
 
-    pub fn sign(&mut self, signer: &EcdsaSigner) {
+    pub fn sign(&mut self, signer: &dyn Signer) {
         self.signature = Some(signer.sign(&self.signing_bytes()));
     }
 
@@ -275,35 +377,412 @@ impl ProtocolResponse {
 
     pub fn verify(&self) -> bool {
         if let Some(signature) = &self.signature {
-            let verifier = match crate::crypto::EcdsaVerifier::from_public_key(&self.sender) {
-                Ok(v) => v,
-                Err(_) => return false,
-            };
-            verifier.verify(&self.signing_bytes(), signature).is_ok()
+            crate::crypto::verify_cached(&self.sender, &self.signing_bytes(), signature)
         } else {
             false
         }
     }
 }
 
+/// Announces a node to a peer. It must be signed by `ecdsa_public_key`, and the
+/// receiver only adds the node once it answers a [`PeerChallenge`] nonce.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerDiscovery {
     pub ecdsa_public_key: PublicKey,
     pub router_address: String,
     pub publisher_address: String,
+    /// Further routers of the same node, such as its IPv6 or IPC endpoints,
+    /// tried in order after `router_address`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_router_addresses: Vec<String>,
+    /// Further publishers, tried in order after `publisher_address`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_publisher_addresses: Vec<String>,
+    /// Router of the relay that forwards frames to this node, for nodes that
+    /// cannot accept connections; see [`RelayFrame`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay: Option<String>,
+    /// The part the node takes in rounds. Signed when not `full`, since a
+    /// forged role would take the node out of its peers' thresholds.
+    #[serde(default, skip_serializing_if = "NodeRole::is_full")]
+    pub role: NodeRole,
+    /// Highest protocol version the node speaks. Not signed, so nodes from
+    /// before versioning still verify the announcement; a forged value can
+    /// at most move the link to another version both ends accept.
+    #[serde(default = "version::legacy_protocol_version")]
+    pub protocol_version: u32,
+    /// Optional messages the node understands; unsigned like the version.
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    #[serde(default)]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub signature: Option<EcdsaSignature>,
+}
+
+impl PeerDiscovery {
+    pub fn new(
+        ecdsa_public_key: PublicKey,
+        router_address: impl Into<String>,
+        publisher_address: impl Into<String>,
+    ) -> Self {
+        Self {
+            ecdsa_public_key,
+            router_address: router_address.into(),
+            publisher_address: publisher_address.into(),
+            alt_router_addresses: Vec::new(),
+            alt_publisher_addresses: Vec::new(),
+            relay: None,
+            role: NodeRole::Full,
+            protocol_version: version::PROTOCOL_VERSION,
+            capabilities: Capabilities::local(),
+            timestamp: racer_core::message::now_millis(),
+            signature: None,
+        }
+    }
+
+    /// Also announces these routers and publishers, in order of preference.
+    pub fn with_alternates(mut self, routers: Vec<String>, publishers: Vec<String>) -> Self {
+        self.alt_router_addresses = routers;
+        self.alt_publisher_addresses = publishers;
+        self
+    }
+
+    /// Every announced router, `router_address` first.
+    pub fn router_addresses(&self) -> Vec<String> {
+        std::iter::once(&self.router_address).chain(&self.alt_router_addresses).cloned().collect()
+    }
+
+    /// Every announced publisher, `publisher_address` first.
+    pub fn publisher_addresses(&self) -> Vec<String> {
+        std::iter::once(&self.publisher_address).chain(&self.alt_publisher_addresses).cloned().collect()
+    }
+
+    /// Announces the node as reachable only through the relay at `router`.
+    pub fn with_relay(mut self, router: impl Into<String>) -> Self {
+        self.relay = Some(router.into());
+        self
+    }
+
+    pub fn with_role(mut self, role: NodeRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut fields = serde_json::json!({
+            "ecdsa_public_key": self.ecdsa_public_key.to_hex(),
+            "router_address": self.router_address,
+            "publisher_address": self.publisher_address,
+            "timestamp": self.timestamp,
+        });
+        // Only signed when set, so plain announcements keep their bytes.
+        if !self.alt_router_addresses.is_empty() {
+            fields["alt_router_addresses"] = serde_json::json!(self.alt_router_addresses);
+        }
+        if !self.alt_publisher_addresses.is_empty() {
+            fields["alt_publisher_addresses"] = serde_json::json!(self.alt_publisher_addresses);
+        }
+        if let Some(relay) = &self.relay {
+            fields["relay"] = serde_json::json!(relay);
+        }
+        if !self.role.is_full() {
+            fields["role"] = serde_json::json!(self.role);
+        }
+        fields.to_string().into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn peer_id(&self) -> String {
+        self.ecdsa_public_key.to_hex()[..10].to_string()
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.ecdsa_public_key, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+}
+
+/// Answer to the nonce a peer sent back in reply to our [`PeerDiscovery`],
+/// signed by the announced key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerChallenge {
+    pub ecdsa_public_key: PublicKey,
+    pub nonce: String,
+    pub signature: Option<EcdsaSignature>,
+}
+
+impl PeerChallenge {
+    pub fn new(ecdsa_public_key: PublicKey, nonce: impl Into<String>) -> Self {
+        Self {
+            ecdsa_public_key,
+            nonce: nonce.into(),
+            signature: None,
+        }
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::json!({
+            "ecdsa_public_key": self.ecdsa_public_key.to_hex(),
+            "nonce": self.nonce,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.ecdsa_public_key, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+}
+
+/// Asks a peer for routers it knows. The reply carries a
+/// [`PeerListResponse`] in its `peer_list`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeerListRequest {
+    /// Most peers wanted; the responder may send fewer. 0 leaves it to the
+    /// responder.
+    #[serde(default)]
+    pub limit: usize,
+}
+
+/// One peer in a [`PeerListResponse`], as the responder verified it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerListEntry {
+    pub ecdsa_public_key: PublicKey,
+    pub router_address: String,
+    pub publisher_address: String,
+    /// The responder's latency to the peer, in seconds, if measured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_secs: Option<f64>,
+}
+
+impl PeerListEntry {
+    pub fn peer_id(&self) -> String {
+        self.ecdsa_public_key.to_hex()[..10].to_string()
+    }
+}
+
+/// Peers a node knows, in reply to a [`PeerListRequest`]. Entries are hints:
+/// the receiver dials them, but a peer only joins its registry once the
+/// peer's own [`PeerDiscovery`] answers a nonce challenge.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerListResponse {
+    pub peers: Vec<PeerListEntry>,
+}
+
+/// A sample of the sender's verified peers, pushed to its own peers every
+/// `peers.gossip_interval_secs` so meshes fill in from a few bootstrap
+/// links. Entries are hints, as in a [`PeerListResponse`]. Signed by
+/// `sender`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerListGossip {
+    pub sender: PublicKey,
+    pub peers: Vec<PeerListEntry>,
+    pub timestamp: u64,
+    pub signature: Option<EcdsaSignature>,
+}
+
+impl PeerListGossip {
+    pub fn new(sender: PublicKey, peers: Vec<PeerListEntry>) -> Self {
+        Self {
+            sender,
+            peers,
+            timestamp: racer_core::message::now_millis(),
+            signature: None,
+        }
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::json!({
+            "sender": self.sender.to_hex(),
+            "peers": self.peers,
+            "timestamp": self.timestamp,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn sender_id(&self) -> String {
+        self.sender.to_hex()[..10].to_string()
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.sender, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+}
+
+/// Sent by a node, with `consensus.delivery_reports` on, to the creator of
+/// a batch it delivered: how long the batch took to reach delivery there,
+/// from `created_at` by the creator's clock as the sender estimates it, and
+/// how many Ready responses got it there. Signed by `sender`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryReport {
+    pub sender: PublicKey,
+    pub batch_id: String,
+    pub hash: String,
+    pub elapsed_ms: u64,
+    pub ready_count: usize,
+    pub timestamp: u64,
+    pub signature: Option<EcdsaSignature>,
+}
+
+impl DeliveryReport {
+    pub fn new(
+        sender: PublicKey,
+        batch_id: impl Into<String>,
+        hash: impl Into<String>,
+        elapsed_ms: u64,
+        ready_count: usize,
+    ) -> Self {
+        Self {
+            sender,
+            batch_id: batch_id.into(),
+            hash: hash.into(),
+            elapsed_ms,
+            ready_count,
+            timestamp: racer_core::message::now_millis(),
+            signature: None,
+        }
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::json!({
+            "sender": self.sender.to_hex(),
+            "batch_id": self.batch_id,
+            "hash": self.hash,
+            "elapsed_ms": self.elapsed_ms,
+            "ready_count": self.ready_count,
+            "timestamp": self.timestamp,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn sender_id(&self) -> String {
+        self.sender.to_hex()[..10].to_string()
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.sender, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+}
+
+/// What a [`RelayFrame`] asks the relay to do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RelayPayload {
+    /// Forward frames addressed to the sender down this router connection.
+    /// Repeated periodically, as the connection may change on reconnect.
+    /// The relay refuses one whose timestamp is stale or not newer than the
+    /// registration it holds for the sender.
+    Register,
+    /// Hand `frame`, an encoded [`ProtocolMessage`], to the registered node
+    /// whose key id is `to`.
+    Forward { to: String, frame: String },
+    /// Publish `frame`, an encoded [`ProtocolResponse`] signed by the sender,
+    /// on `topic` for the sender's subscribers.
+    Publish { topic: String, frame: String },
 }
 
+/// A frame for a relay node (`node.relay`), which forwards traffic for nodes
+/// behind NAT: they dial the relay and register, peers reach them by sending
+/// [`RelayPayload::Forward`] to the relay, and their Echo/Ready responses are
+/// published on the relay's publisher. Signed by `sender`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "message_type")]
+pub struct RelayFrame {
+    pub sender: PublicKey,
+    pub payload: RelayPayload,
+    #[serde(default)]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub signature: Option<EcdsaSignature>,
+}
+
+impl RelayFrame {
+    pub fn new(sender: PublicKey, payload: RelayPayload) -> Self {
+        Self {
+            sender,
+            payload,
+            timestamp: racer_core::message::now_millis(),
+            signature: None,
+        }
+    }
+
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::json!({
+            "sender": self.sender.to_hex(),
+            "payload": self.payload,
+            "timestamp": self.timestamp,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn sign(&mut self, signer: &dyn Signer) {
+        self.signature = Some(signer.sign(&self.signing_bytes()));
+    }
+
+    pub fn sender_id(&self) -> String {
+        self.sender.to_hex()[..10].to_string()
+    }
+
+    pub fn verify(&self) -> bool {
+        if let Some(signature) = &self.signature {
+            crate::crypto::verify_cached(&self.sender, &self.signing_bytes(), signature)
+        } else {
+            false
+        }
+    }
+}
+
+/// Anything sent on the router/dealer pair. Encoded as an envelope naming
+/// its type; see [`super::envelope`].
+#[derive(Debug, Clone)]
 pub enum ProtocolMessage<M> {
-    #[serde(rename = "BatchedMessage")]
     BatchedMessages(BatchedMessages<M>),
-    #[serde(rename = "Echo")]
     Echo(Echo),
-    #[serde(rename = "Response")]
     Response(ProtocolResponse),
-    #[serde(rename = "PeerDiscovery")]
     PeerDiscovery(PeerDiscovery),
+    PeerChallenge(PeerChallenge),
+    HealthSummary(super::HealthSummary),
+    PeerListRequest(PeerListRequest),
+    PeerListGossip(PeerListGossip),
+    Relay(RelayFrame),
+    DeliveryReport(DeliveryReport),
+    /// Several messages for one peer sent as a single frame; see
+    /// [`Coalescer`](crate::network::Coalescer). Each is handled and
+    /// answered as if it had arrived alone.
+    Bundle { messages: Vec<ProtocolMessage<M>> },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -311,6 +790,20 @@ pub struct CongestionUpdate {
     pub status: String,
     pub current_latency: f64,
     pub recently_missed: bool,
+    /// Nonce to sign in a [`PeerChallenge`]; set in reply to a [`PeerDiscovery`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    /// Set in reply to a [`PeerListRequest`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_list: Option<PeerListResponse>,
+    /// An encoded [`ProtocolMessage`] a relay forwards to a node registered
+    /// with it, handled as if it had arrived on that node's router.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayed: Option<String>,
+    /// The responder's version and capabilities; set in reply to a
+    /// [`PeerDiscovery`], and missing from nodes that predate versioning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<ProtocolOffer>,
 }
 
 impl CongestionUpdate {
@@ -319,6 +812,10 @@ impl CongestionUpdate {
             status: "CongestionUpdate".to_string(),
             current_latency,
             recently_missed,
+            challenge: None,
+            peer_list: None,
+            relayed: None,
+            protocol: None,
         }
     }
 
@@ -327,6 +824,46 @@ impl CongestionUpdate {
             status: "OK".to_string(),
             current_latency: 0.0,
             recently_missed: false,
+            challenge: None,
+            peer_list: None,
+            relayed: None,
+            protocol: None,
+        }
+    }
+
+    pub fn challenge(nonce: impl Into<String>) -> Self {
+        Self {
+            status: "CHALLENGE".to_string(),
+            current_latency: 0.0,
+            recently_missed: false,
+            challenge: Some(nonce.into()),
+            peer_list: None,
+            relayed: None,
+            protocol: Some(ProtocolOffer::local()),
+        }
+    }
+
+    pub fn peer_list(response: PeerListResponse) -> Self {
+        Self {
+            status: "PEER_LIST".to_string(),
+            current_latency: 0.0,
+            recently_missed: false,
+            challenge: None,
+            peer_list: Some(response),
+            relayed: None,
+            protocol: None,
+        }
+    }
+
+    pub fn relayed(frame: impl Into<String>) -> Self {
+        Self {
+            status: "RELAYED".to_string(),
+            current_latency: 0.0,
+            recently_missed: false,
+            challenge: None,
+            peer_list: None,
+            relayed: Some(frame.into()),
+            protocol: None,
         }
     }
 
@@ -335,6 +872,10 @@ impl CongestionUpdate {
             status: "ALREADY_RECEIVED".to_string(),
             current_latency: 0.0,
             recently_missed: false,
+            challenge: None,
+            peer_list: None,
+            relayed: None,
+            protocol: None,
         }
     }
 }
@@ -342,6 +883,17 @@ impl CongestionUpdate {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::EcdsaSigner;
+
+    #[test]
+    fn test_merkle_root_over_content_hashes() {
+        use racer_core::message::DefaultMessage;
+
+        let (a, b) = (DefaultMessage::with_padding(1), DefaultMessage::with_padding(2));
+        assert_eq!(merkle_root(std::slice::from_ref(&a)), crate::crypto::sha256_hex(&a.merkle_bytes()));
+        assert_ne!(merkle_root(&[a.clone(), b.clone()]), merkle_root(&[b, a]));
+        assert_eq!(merkle_root::<DefaultMessage>(&[]), "0".repeat(64));
+    }
 
     #[test]
     fn test_echo_type_serialize() {
@@ -430,6 +982,13 @@ mod tests {
             creator_signature: None,
             sender_signature: None,
             created_at: 1000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
             #[cfg(feature = "bls")]
             creator_bls: None,
             #[cfg(feature = "bls")]
@@ -451,4 +1010,143 @@ mod tests {
         assert!(!bm.verify_creator_signature());
         assert!(!bm.verify_sender_signature()); // Sender sig also covers merkle root
     }
+
+    #[test]
+    fn test_relay_counts_hops_under_signed_limits() {
+        use crate::crypto::{KeyPair, EcdsaSigner};
+        use crate::protocol::VectorClock;
+
+        let creator_keys = KeyPair::generate();
+        let relay_keys = KeyPair::generate();
+        let mut bm = BatchedMessages {
+            batch_id: "batch-1".to_string(),
+            creator_ecdsa: creator_keys.public_key(),
+            sender_ecdsa: creator_keys.public_key(),
+            merkle_root: "root".to_string(),
+            batch_size: 1,
+            messages: vec!["msg1".to_string()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: Some(1),
+            ttl_ms: Some(500),
+            priority: Default::default(),
+            sequence: 7,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        };
+        bm.sign_as_creator(&EcdsaSigner::new(creator_keys.signing_key().clone()));
+        assert!(!bm.hop_limit_reached());
+        assert!(!bm.is_expired(1500));
+        assert!(bm.is_expired(1501));
+
+        let relayed = bm.become_sender(&relay_keys);
+        assert_eq!(relayed.hops, 1);
+        assert!(relayed.hop_limit_reached());
+        assert!(relayed.verify_creator_signature());
+        assert!(relayed.verify_sender_signature());
+        assert_eq!(relayed.sequence, 7);
+        let mut renumbered = relayed.clone();
+        renumbered.sequence = 6;
+        assert!(!renumbered.verify_creator_signature());
+
+        let mut tampered = relayed.clone();
+        tampered.hops = 0;
+        assert!(!tampered.verify_sender_signature());
+        tampered.max_hops = Some(10);
+        assert!(!tampered.verify_creator_signature());
+    }
+
+    #[test]
+    fn test_peer_discovery_verification() {
+        use crate::crypto::{KeyPair, EcdsaSigner};
+        let keys = KeyPair::generate();
+        let attacker = KeyPair::generate();
+
+        let mut pd = PeerDiscovery::new(keys.public_key(), "tcp://10.0.0.2:20001", "tcp://10.0.0.2:21001");
+        assert!(!pd.verify());
+
+        pd.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+        assert!(pd.verify());
+
+        pd.router_address = "tcp://10.6.6.6:20001".to_string();
+        assert!(!pd.verify());
+
+        let mut spoofed = PeerDiscovery::new(keys.public_key(), "tcp://10.6.6.6:20001", "tcp://10.6.6.6:21001");
+        spoofed.sign(&EcdsaSigner::new(attacker.signing_key().clone()));
+        assert!(!spoofed.verify());
+    }
+
+    #[test]
+    fn test_peer_discovery_role_is_signed() {
+        use crate::crypto::{KeyPair, EcdsaSigner};
+        let keys = KeyPair::generate();
+        let signer = EcdsaSigner::new(keys.signing_key().clone());
+
+        let plain = PeerDiscovery::new(keys.public_key(), "tcp://10.0.0.2:20001", "tcp://10.0.0.2:21001");
+        let mut pd = plain.clone().with_role(NodeRole::Full);
+        assert_eq!(pd.signing_bytes(), plain.signing_bytes());
+        assert!(!serde_json::to_string(&pd).unwrap().contains("role"));
+
+        pd = pd.with_role(NodeRole::Observer);
+        pd.sign(&signer);
+        assert!(pd.verify());
+        let json = serde_json::to_string(&pd).unwrap();
+        assert_eq!(serde_json::from_str::<PeerDiscovery>(&json).unwrap().role, NodeRole::Observer);
+
+        pd.role = NodeRole::Full;
+        assert!(!pd.verify());
+    }
+
+    #[test]
+    fn test_peer_challenge_verification() {
+        use crate::crypto::{KeyPair, EcdsaSigner};
+        let keys = KeyPair::generate();
+
+        let mut challenge = PeerChallenge::new(keys.public_key(), "n1");
+        challenge.sign(&EcdsaSigner::new(keys.signing_key().clone()));
+        assert!(challenge.verify());
+
+        challenge.nonce = "n2".to_string();
+        assert!(!challenge.verify());
+    }
+
+    #[test]
+    fn test_relay_frame_verification() {
+        use crate::crypto::{KeyPair, EcdsaSigner};
+        let keys = KeyPair::generate();
+        let signer = EcdsaSigner::new(keys.signing_key().clone());
+
+        let mut pd = PeerDiscovery::new(keys.public_key(), "tcp://10.0.0.2:20001", "tcp://10.0.0.1:21001")
+            .with_relay("tcp://10.0.0.1:20001");
+        pd.sign(&signer);
+        assert!(pd.verify());
+        pd.relay = Some("tcp://10.6.6.6:20001".to_string());
+        assert!(!pd.verify());
+
+        let mut pd = PeerDiscovery::new(keys.public_key(), "tcp://10.0.0.2:20001", "tcp://10.0.0.2:21001")
+            .with_alternates(vec!["tcp://[fd00::2]:20001".into()], Vec::new());
+        pd.sign(&signer);
+        assert!(pd.verify());
+        assert_eq!(pd.router_addresses(), vec!["tcp://10.0.0.2:20001", "tcp://[fd00::2]:20001"]);
+        pd.alt_router_addresses.clear();
+        assert!(!pd.verify());
+
+        let payload = RelayPayload::Forward { to: "abc".into(), frame: "{}".into() };
+        let mut frame = RelayFrame::new(keys.public_key(), payload);
+        frame.sign(&signer);
+        assert!(frame.verify());
+
+        let json = serde_json::to_string(&ProtocolMessage::<String>::Relay(frame.clone())).unwrap();
+        assert!(json.contains("\"action\":\"forward\""));
+
+        frame.payload = RelayPayload::Register;
+        assert!(!frame.verify());
+    }
 }
@@ -1,11 +1,33 @@
 mod messages;
 mod vector_clock;
+pub mod creator_log;
+pub mod envelope;
 pub mod gossip;
+pub mod health;
+pub mod membership;
+pub mod seen;
+pub mod shards;
+pub mod sequencing;
+pub mod version;
 
 pub use messages::{
-    BatchedMessages, Echo, EchoType, 
+    BatchedMessages, DeliveryReport, Echo, EchoType, 
     ProtocolMessage, ProtocolResponse, ProtocolResponseType,
-    PeerDiscovery, CongestionUpdate,
+    PeerChallenge, PeerDiscovery, PeerListEntry, PeerListGossip, PeerListRequest, PeerListResponse,
+    RelayFrame, RelayPayload,
+    CongestionUpdate, Priority,
+    merkle_root,
 };
 pub use vector_clock::VectorClock;
-pub use gossip::{GossipRound, GossipState};
+pub use creator_log::{CreatorLog, SequenceCheck};
+pub use envelope::{EnvelopeError, Framing};
+pub use gossip::{
+    GossipRound, GossipState, MessageStore, RetentionPolicy, RoundOutcome, RoundStats, RoundStep,
+    RoundSummary, RoundTable, StoreStats,
+};
+pub use health::{ClusterHealth, ClusterHealthStats, HealthSummary, HealthTracker, Neighbor};
+pub use membership::{Admission, EpochChange, Member, Membership, MembershipChange, MembershipError, MembershipUpdate};
+pub use seen::SeenFilter;
+pub use shards::{ShardedGossipState, DEFAULT_GOSSIP_SHARDS};
+pub use sequencing::{OrderedBatch, Sequencer};
+pub use version::{Capabilities, ProtocolOffer, PROTOCOL_VERSION};
@@ -0,0 +1,133 @@
+//! Probabilistic record of batch hashes this node has already handled.
+//!
+//! [`GossipState`](super::GossipState) forgets a batch once it is delivered
+//! and evicted or its round times out, so a late copy arriving afterwards would
+//! start a fresh round and be re-gossiped to a full echo sample. The
+//! [`SeenFilter`] keeps a compact trace of every hash for much longer: two
+//! Bloom filter generations, the older dropped once the newer holds
+//! `capacity` hashes. Lookups report false positives at roughly the configured
+//! rate and never miss any of the last `capacity` hashes inserted.
+//!
+//! Each filter hashes with keys of its own, so nodes that handled similar
+//! batches do not share their false positives.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+pub const DEFAULT_SEEN_CAPACITY: usize = 10_000;
+pub const DEFAULT_SEEN_FP_RATE: f64 = 0.001;
+
+#[derive(Debug, Clone)]
+pub struct SeenFilter {
+    current: Vec<u64>,
+    previous: Vec<u64>,
+    hashes: u64,
+    capacity: usize,
+    inserted: usize,
+    keys: RandomState,
+}
+
+impl SeenFilter {
+    /// A filter sized so each generation of `capacity` hashes has a false
+    /// positive rate close to `fp_rate`.
+    pub fn new(capacity: usize, fp_rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        let fp_rate = fp_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let bits = (-(capacity as f64) * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let words = bits.div_ceil(64);
+        let hashes = ((words * 64) as f64 / capacity as f64 * ln2).round().max(1.0) as u64;
+
+        Self {
+            current: vec![0; words],
+            previous: vec![0; words],
+            hashes,
+            capacity,
+            inserted: 0,
+            keys: RandomState::new(),
+        }
+    }
+
+    /// Records `hash`, rotating generations once the current one is full.
+    pub fn insert(&mut self, hash: &str) {
+        if self.contains_in(&self.current, hash) {
+            return;
+        }
+        for bit in self.bits(self.current.len(), hash) {
+            self.current[bit / 64] |= 1 << (bit % 64);
+        }
+
+        self.inserted += 1;
+        if self.inserted >= self.capacity {
+            self.previous = std::mem::replace(&mut self.current, vec![0; self.previous.len()]);
+            self.inserted = 0;
+        }
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.contains_in(&self.current, hash) || self.contains_in(&self.previous, hash)
+    }
+
+    fn contains_in(&self, words: &[u64], hash: &str) -> bool {
+        self.bits(words.len(), hash).all(|bit| words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Bit positions for `hash`, by double hashing two SipHash digests
+    /// under this filter's keys.
+    fn bits(&self, words: usize, hash: &str) -> impl Iterator<Item = usize> {
+        let h1 = self.keys.hash_one(hash);
+        let h2 = self.keys.hash_one((hash, "seen")) | 1;
+
+        let len = (words * 64) as u64;
+        (0..self.hashes).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+impl Default for SeenFilter {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEEN_CAPACITY, DEFAULT_SEEN_FP_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remembers_inserted_hashes() {
+        let mut filter = SeenFilter::new(100, 0.01);
+        for i in 0..100 {
+            filter.insert(&format!("hash-{}", i));
+        }
+        assert!((0..100).all(|i| filter.contains(&format!("hash-{}", i))));
+
+        let false_positives = (0..1000).filter(|i| filter.contains(&format!("other-{}", i))).count();
+        assert!(false_positives < 50, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_rotation_forgets_oldest_generation() {
+        let mut filter = SeenFilter::new(10, 0.001);
+        filter.insert("first");
+        for i in 0..10 {
+            filter.insert(&format!("hash-{}", i));
+        }
+        assert!(filter.contains("first"));
+
+        for i in 10..20 {
+            filter.insert(&format!("hash-{}", i));
+        }
+        assert!(!filter.contains("first"));
+        assert!(filter.contains("hash-19"));
+    }
+
+    #[test]
+    fn test_filters_hash_apart() {
+        let (mut a, mut b) = (SeenFilter::new(100, 0.01), SeenFilter::new(100, 0.01));
+        a.insert("hash");
+        b.insert("hash");
+        assert!(a.contains("hash") && b.contains("hash"));
+        assert_ne!(a.current, b.current);
+    }
+}
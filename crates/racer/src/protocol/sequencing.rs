@@ -0,0 +1,236 @@
+//! Total order over delivered batches.
+//!
+//! SPDE delivers each batch reliably but in whatever order rounds finish. The
+//! [`Sequencer`] holds delivered batches until they are older than a
+//! stability window, then releases them sorted by `(created_at, creator
+//! public key, hash)`, numbering them as it goes. Every node that sees the
+//! same batches before they leave the window releases them in the same
+//! order; a batch delivered after a later-ordered one was already released
+//! is counted as late and dropped from the ordered stream. So is a batch
+//! dated more than the window ahead of its creator's clock, which would
+//! otherwise sit in the queue until then and, once released, make every
+//! batch created before it late.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::protocol::BatchedMessages;
+
+/// A delivered batch with its position in the total order.
+#[derive(Debug, Clone)]
+pub struct OrderedBatch<M> {
+    /// Position in the order, counted from 0 since this sequencer started.
+    pub sequence: u64,
+    pub hash: String,
+    pub batch: BatchedMessages<M>,
+}
+
+/// Sort key; field order is the tiebreak order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct OrderKey {
+    created_at: u64,
+    creator: String,
+    hash: String,
+}
+
+pub struct Sequencer<M> {
+    stability: Duration,
+    pending: BTreeMap<OrderKey, BatchedMessages<M>>,
+    last_released: Option<OrderKey>,
+    next_sequence: u64,
+    late: u64,
+    ahead: u64,
+}
+
+impl<M> Sequencer<M> {
+    /// `stability` should exceed delivery latency plus clock skew between
+    /// creators; batches delivered later than that risk arriving late.
+    pub fn new(stability: Duration) -> Self {
+        Self {
+            stability,
+            pending: BTreeMap::new(),
+            last_released: None,
+            next_sequence: 0,
+            late: 0,
+            ahead: 0,
+        }
+    }
+
+    /// Queues a delivered batch. Returns `false` if it was already queued,
+    /// sorts before a batch that has already been released, or is dated
+    /// more than the stability window after `creator_now`, now (epoch
+    /// millis) by its creator's clock as far as its skew is known.
+    pub fn insert(&mut self, hash: impl Into<String>, batch: BatchedMessages<M>, creator_now: u64) -> bool {
+        if batch.created_at > creator_now.saturating_add(self.stability.as_millis() as u64) {
+            self.ahead += 1;
+            return false;
+        }
+        let key = OrderKey {
+            created_at: batch.created_at,
+            creator: batch.creator_ecdsa.to_hex(),
+            hash: hash.into(),
+        };
+
+        if self.last_released.as_ref().is_some_and(|last| key <= *last) {
+            self.late += 1;
+            return false;
+        }
+        if self.pending.contains_key(&key) {
+            return false;
+        }
+        self.pending.insert(key, batch);
+        true
+    }
+
+    /// Batches created at or before `now_millis - stability`, in order.
+    pub fn release(&mut self, now_millis: u64) -> Vec<OrderedBatch<M>> {
+        let watermark = self.watermark(now_millis);
+        let mut released = Vec::new();
+
+        while let Some(entry) = self.pending.first_entry() {
+            if entry.key().created_at > watermark {
+                break;
+            }
+            let (key, batch) = entry.remove_entry();
+            released.push(OrderedBatch {
+                sequence: self.next_sequence,
+                hash: key.hash.clone(),
+                batch,
+            });
+            self.next_sequence += 1;
+            self.last_released = Some(key);
+        }
+
+        released
+    }
+
+    /// Creation time up to which batches are considered stable.
+    pub fn watermark(&self, now_millis: u64) -> u64 {
+        now_millis.saturating_sub(self.stability.as_millis() as u64)
+    }
+
+    /// Batches waiting for the watermark to pass them.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Batches rejected because they arrived after their slot was released.
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+
+    /// Batches rejected because they were dated ahead of their creator's clock.
+    pub fn ahead(&self) -> u64 {
+        self.ahead
+    }
+
+    /// Sequence number the next released batch will get.
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{KeyPair, PublicKey};
+    use crate::protocol::VectorClock;
+    use racer_core::message::DefaultMessage;
+
+    const STABILITY: Duration = Duration::from_millis(1_000);
+
+    fn batch(creator: &PublicKey, created_at: u64) -> BatchedMessages<DefaultMessage> {
+        BatchedMessages {
+            batch_id: format!("b-{}", created_at),
+            creator_ecdsa: creator.clone(),
+            sender_ecdsa: creator.clone(),
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    fn hashes<M>(released: &[OrderedBatch<M>]) -> Vec<&str> {
+        released.iter().map(|b| b.hash.as_str()).collect()
+    }
+
+    #[test]
+    fn test_release_waits_for_watermark() {
+        let key = KeyPair::generate().public_key();
+        let mut seq = Sequencer::new(STABILITY);
+        assert!(seq.insert("h1", batch(&key, 5_000), 5_000));
+
+        assert!(seq.release(5_500).is_empty());
+        assert_eq!(seq.pending(), 1);
+
+        let released = seq.release(6_000);
+        assert_eq!(hashes(&released), vec!["h1"]);
+        assert_eq!(released[0].sequence, 0);
+        assert_eq!(seq.next_sequence(), 1);
+    }
+
+    #[test]
+    fn test_order_is_independent_of_delivery_order() {
+        let (a, b) = (KeyPair::generate().public_key(), KeyPair::generate().public_key());
+        let (first, second) = if a.to_hex() < b.to_hex() { (a, b) } else { (b, a) };
+        let batches = [
+            ("h-late", batch(&first, 300)),
+            ("h-tie-second", batch(&second, 200)),
+            ("h-tie-first", batch(&first, 200)),
+            ("h-early", batch(&second, 100)),
+        ];
+
+        let mut forward = Sequencer::new(STABILITY);
+        let mut backward = Sequencer::new(STABILITY);
+        for (hash, bm) in batches.iter().cloned() {
+            forward.insert(hash, bm, 300);
+        }
+        for (hash, bm) in batches.iter().rev().cloned() {
+            backward.insert(hash, bm, 300);
+        }
+
+        let expected = vec!["h-early", "h-tie-first", "h-tie-second", "h-late"];
+        assert_eq!(hashes(&forward.release(10_000)), expected);
+        assert_eq!(hashes(&backward.release(10_000)), expected);
+    }
+
+    #[test]
+    fn test_late_and_duplicate_batches_are_rejected() {
+        let key = KeyPair::generate().public_key();
+        let mut seq = Sequencer::new(STABILITY);
+        seq.insert("h2", batch(&key, 2_000), 2_000);
+        assert!(!seq.insert("h2", batch(&key, 2_000), 2_000));
+        assert_eq!(seq.release(3_000).len(), 1);
+
+        assert!(!seq.insert("h1", batch(&key, 1_500), 3_000));
+        assert_eq!(seq.late(), 1);
+
+        assert!(seq.insert("h3", batch(&key, 2_500), 3_000));
+        assert_eq!(seq.release(4_000)[0].sequence, 1);
+    }
+
+    #[test]
+    fn test_batches_dated_ahead_of_their_creator_are_rejected() {
+        let key = KeyPair::generate().public_key();
+        let mut seq = Sequencer::new(STABILITY);
+        assert!(seq.insert("h1", batch(&key, 6_000), 5_000));
+        assert!(!seq.insert("h2", batch(&key, 6_001), 5_000));
+        assert!(!seq.insert("h3", batch(&key, u64::MAX), 5_000));
+        assert_eq!((seq.ahead(), seq.pending()), (2, 1));
+    }
+}
@@ -0,0 +1,313 @@
+//! Gossip state spread over independently locked shards.
+//!
+//! Rounds never touch one another, yet behind a single lock every Echo and
+//! Ready response waits on the same writer, which caps throughput under high
+//! submit rates. [`ShardedGossipState`] picks a shard by the leading bytes
+//! of a round's hash, so responses for different batches mostly take
+//! different locks. Batches sit in a [`MessageStore`] per shard, separate
+//! from the [`RoundTable`], so dedup lookups and re-gossip reads do not queue
+//! behind round bookkeeping.
+//!
+//! A finished round's batch stays in its store until
+//! [`evict`](ShardedGossipState::evict) finds it older than the
+//! [`RetentionPolicy`] allows, or its round falls out of the last
+//! `max_delivered` delivered rounds, which are split evenly over the shards.
+
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use super::gossip::{
+    GossipRound, MessageStore, RetentionPolicy, RoundOutcome, RoundStats, RoundTable, StoreStats,
+    DEFAULT_MAX_DELIVERED,
+};
+use super::seen::SeenFilter;
+use super::BatchedMessages;
+use crate::Message;
+
+pub const DEFAULT_GOSSIP_SHARDS: usize = 16;
+
+/// Leading bytes of a hash that select its shard.
+const SHARD_PREFIX: usize = 8;
+
+pub struct ShardedGossipState<M: Message> {
+    rounds: Box<[RwLock<RoundTable>]>,
+    messages: Box<[RwLock<MessageStore<M>>]>,
+}
+
+impl<M: Message> ShardedGossipState<M> {
+    /// State split over `shards` locks, at least one.
+    pub fn new(shards: usize) -> Self {
+        let shards = shards.max(1);
+        let max_delivered = DEFAULT_MAX_DELIVERED.div_ceil(shards);
+        Self {
+            rounds: (0..shards)
+                .map(|_| {
+                    let mut table = RoundTable::new();
+                    table.set_max_delivered(max_delivered);
+                    RwLock::new(table)
+                })
+                .collect(),
+            messages: (0..shards).map(|_| RwLock::new(MessageStore::new())).collect(),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.rounds.len()
+    }
+
+    fn index(&self, hash: &str) -> usize {
+        let prefix = hash
+            .bytes()
+            .take(SHARD_PREFIX)
+            .fold(0usize, |acc, b| acc.wrapping_mul(31).wrapping_add(b as usize));
+        prefix % self.rounds.len()
+    }
+
+    /// The round table holding `hash`'s round.
+    pub fn rounds(&self, hash: &str) -> &RwLock<RoundTable> {
+        &self.rounds[self.index(hash)]
+    }
+
+    /// Every shard's round table, for work that visits all rounds.
+    pub fn tables(&self) -> impl Iterator<Item = &RwLock<RoundTable>> {
+        self.rounds.iter()
+    }
+
+    /// The store holding `hash`'s batch.
+    pub fn messages(&self, hash: &str) -> &RwLock<MessageStore<M>> {
+        &self.messages[self.index(hash)]
+    }
+
+    /// Gives every shard a seen-set sized for its share of `capacity`.
+    pub fn set_seen_filter(&mut self, capacity: usize, fp_rate: f64) {
+        let per_shard = capacity.div_ceil(self.messages.len());
+        for store in self.messages.iter_mut() {
+            store.get_mut().set_seen_filter(SeenFilter::new(per_shard, fp_rate));
+        }
+    }
+
+    /// Applies `retention` to every shard, each remembering its share of
+    /// `max_delivered` delivered rounds.
+    pub fn set_retention(&mut self, retention: RetentionPolicy) {
+        let max_delivered = retention.max_delivered.div_ceil(self.rounds.len());
+        for table in self.rounds.iter_mut() {
+            table.get_mut().set_max_delivered(max_delivered);
+        }
+        for store in self.messages.iter_mut() {
+            store.get_mut().set_retention(retention);
+        }
+    }
+
+    pub async fn set_timeout(&self, timeout: Duration) {
+        for table in self.rounds.iter() {
+            table.write().await.set_timeout(timeout);
+        }
+    }
+
+    /// How long an undelivered round lives before `cleanup_timed_out` drops it.
+    pub async fn timeout(&self) -> Duration {
+        self.rounds[0].read().await.timeout()
+    }
+
+    pub async fn store_message(&self, hash: String, message: BatchedMessages<M>) {
+        self.messages(&hash).write().await.insert(hash, message);
+    }
+
+    pub async fn get_message(&self, hash: &str) -> Option<BatchedMessages<M>> {
+        self.messages(hash).read().await.get(hash).cloned()
+    }
+
+    pub async fn has_message(&self, hash: &str) -> bool {
+        self.messages(hash).read().await.contains(hash)
+    }
+
+    /// Whether the batch is held now or was stored at some point before
+    /// being dropped. May rarely be true for a batch never seen.
+    pub async fn has_seen(&self, hash: &str) -> bool {
+        self.messages(hash).read().await.has_seen(hash)
+    }
+
+    /// Drops the batches of rounds a [`RoundTable`] evicted or timed out.
+    pub async fn forget(&self, hashes: &[String]) {
+        for hash in hashes {
+            self.messages(hash).write().await.remove(hash);
+        }
+    }
+
+    /// Records how `hash`'s round ended, so [`evict`](Self::evict) drops its
+    /// batch once the retention policy's age for that outcome passes.
+    pub async fn retire(&self, hash: &str, outcome: RoundOutcome) {
+        self.messages(hash).write().await.retire(hash, outcome);
+    }
+
+    /// Drops the batches of finished rounds older than the retention policy
+    /// allows in every shard, returning how many were dropped.
+    pub async fn evict(&self) -> usize {
+        let mut evicted = 0;
+        for store in self.messages.iter() {
+            evicted += store.write().await.evict().len();
+        }
+        evicted
+    }
+
+    pub async fn mark_delivered(&self, hash: &str) {
+        let evicted = self.rounds(hash).write().await.mark_delivered(hash);
+        self.forget(&evicted).await;
+        self.retire(hash, RoundOutcome::Delivered).await;
+    }
+
+    pub async fn was_recently_delivered(&self, hash: &str) -> bool {
+        self.rounds(hash).read().await.was_recently_delivered(hash)
+    }
+
+    /// Drops undelivered rounds past the timeout in every shard, retiring
+    /// their batches, then evicts the batches the retention policy no longer
+    /// holds. Returns the rounds dropped.
+    pub async fn cleanup_timed_out(&self) -> Vec<GossipRound> {
+        let mut timed_out = Vec::new();
+        for table in self.rounds.iter() {
+            let expired = table.write().await.remove_timed_out();
+            for round in &expired {
+                self.retire(&round.hash, RoundOutcome::TimedOut).await;
+            }
+            timed_out.extend(expired);
+        }
+        self.evict().await;
+        timed_out
+    }
+
+    pub async fn active_rounds(&self) -> usize {
+        let mut active = 0;
+        for table in self.rounds.iter() {
+            active += table.read().await.active_rounds();
+        }
+        active
+    }
+
+    /// Like [`active_rounds`](Self::active_rounds), or `None` rather than
+    /// waiting if a shard is being written.
+    pub fn try_active_rounds(&self) -> Option<usize> {
+        self.rounds
+            .iter()
+            .map(|table| table.try_read().ok().map(|t| t.active_rounds()))
+            .sum()
+    }
+
+    /// What the stores of all shards hold.
+    pub async fn store_stats(&self) -> StoreStats {
+        let mut stats = StoreStats::default();
+        for store in self.messages.iter() {
+            stats.merge(store.read().await.stats());
+        }
+        stats
+    }
+
+    /// Round outcomes over all shards.
+    pub async fn round_stats(&self) -> RoundStats {
+        let mut stats = RoundStats::default();
+        for table in self.rounds.iter() {
+            stats.merge(table.read().await.round_stats());
+        }
+        stats
+    }
+}
+
+impl<M: Message> Default for ShardedGossipState<M> {
+    fn default() -> Self {
+        Self::new(DEFAULT_GOSSIP_SHARDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use racer_core::message::DefaultMessage;
+
+    fn batch() -> BatchedMessages<DefaultMessage> {
+        let key = crate::crypto::KeyPair::generate().public_key();
+        BatchedMessages {
+            batch_id: "b1".into(),
+            creator_ecdsa: key.clone(),
+            sender_ecdsa: key,
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: crate::protocol::VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 0,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rounds_spread_over_shards() {
+        let state = ShardedGossipState::<DefaultMessage>::new(4);
+        let hashes: Vec<String> = (0..64).map(|i| crate::crypto::sha256_hex(&[i])).collect();
+
+        for hash in &hashes {
+            state.rounds(hash).write().await.start_round(hash.as_str());
+        }
+        assert_eq!(state.active_rounds().await, 64);
+        assert_eq!(state.try_active_rounds(), Some(64));
+        for table in state.rounds.iter() {
+            assert!(table.read().await.active_rounds() > 0);
+        }
+
+        state.mark_delivered(&hashes[0]).await;
+        assert!(state.was_recently_delivered(&hashes[0]).await);
+        assert_eq!(state.active_rounds().await, 63);
+        assert_eq!(state.round_stats().await.delivered, 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_applies_to_every_shard() {
+        let state = ShardedGossipState::<DefaultMessage>::new(3);
+        state.set_timeout(Duration::ZERO).await;
+        for hash in ["a", "b", "c", "d"] {
+            state.rounds(hash).write().await.start_round(hash);
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let mut timed_out: Vec<_> = state.cleanup_timed_out().await.into_iter().map(|r| r.hash).collect();
+        timed_out.sort();
+        assert_eq!(timed_out, vec!["a", "b", "c", "d"]);
+        assert_eq!(state.round_stats().await.timed_out, 4);
+        assert_eq!(state.timeout().await, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_retention_spans_every_shard() {
+        let mut state = ShardedGossipState::<DefaultMessage>::new(4);
+        state.set_retention(RetentionPolicy { delivered: Duration::from_secs(60), ..RetentionPolicy::default() });
+        let batch = batch();
+        let bytes = crate::util::serialized_len(&batch).unwrap();
+
+        let hashes: Vec<String> = (0..16).map(|i| crate::crypto::sha256_hex(&[i])).collect();
+        for hash in &hashes {
+            state.rounds(hash).write().await.start_round(hash.as_str());
+            state.store_message(hash.clone(), batch.clone()).await;
+        }
+        assert_eq!(state.store_stats().await.bytes, 16 * bytes);
+
+        state.mark_delivered(&hashes[0]).await;
+        state.retire(&hashes[1], RoundOutcome::TimedOut).await;
+        assert_eq!(state.evict().await, 1);
+
+        let stats = state.store_stats().await;
+        assert_eq!((stats.batches, stats.delivered, stats.evicted), (15, 1, 1));
+        assert!(state.has_message(&hashes[0]).await);
+        assert!(!state.has_message(&hashes[1]).await);
+    }
+}
@@ -1,42 +1,147 @@
 use std::collections::HashMap;
 
-use serde::{Deserialize, Serialize};
+use racer_core::message::now_millis;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VectorClock {
-    clock: HashMap<String, u64>,
+    #[serde(serialize_with = "serialize_clock", deserialize_with = "deserialize_clock")]
+    clock: HashMap<String, Entry>,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    time: u64,
+    /// Local epoch millis at which the entry last advanced, for
+    /// [`VectorClock::prune_older_than`]. Not sent on the wire, so `None`
+    /// for entries only ever seen in a deserialized clock or merged in from
+    /// one without advancing here.
+    advanced_at: Option<u64>,
+}
+
+impl Entry {
+    fn advanced(time: u64) -> Self {
+        Self { time, advanced_at: Some(now_millis()) }
+    }
+}
+
+fn serialize_clock<S: Serializer>(clock: &HashMap<String, Entry>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(clock.iter().map(|(node_id, entry)| (node_id, entry.time)))
+}
+
+fn deserialize_clock<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Entry>, D::Error> {
+    let times = HashMap::<String, u64>::deserialize(deserializer)?;
+    Ok(times
+        .into_iter()
+        .map(|(node_id, time)| (node_id, Entry { time, advanced_at: None }))
+        .collect())
+}
+
+impl PartialEq for VectorClock {
+    fn eq(&self, other: &Self) -> bool {
+        self.clock.len() == other.clock.len()
+            && self.clock.iter().all(|(node_id, entry)| other.get_entry(node_id) == Some(entry.time))
+    }
+}
+
+impl Eq for VectorClock {}
+
 impl VectorClock {
     pub fn new() -> Self {
         Self::default()
     }
 
     pub fn get(&self, node_id: &str) -> u64 {
-        self.clock.get(node_id).copied().unwrap_or(0)
+        self.get_entry(node_id).unwrap_or(0)
+    }
+
+    fn get_entry(&self, node_id: &str) -> Option<u64> {
+        self.clock.get(node_id).map(|entry| entry.time)
     }
 
     pub fn increment(&mut self, node_id: &str) {
-        let entry = self.clock.entry(node_id.to_string()).or_insert(0);
-        *entry = entry.saturating_add(1);
+        let time = self.get(node_id).saturating_add(1);
+        self.clock.insert(node_id.to_string(), Entry::advanced(time));
     }
 
     pub fn set(&mut self, node_id: &str, time: u64) {
-        self.clock.insert(node_id.to_string(), time);
+        self.clock.insert(node_id.to_string(), Entry::advanced(time));
     }
 
+    /// Takes the higher counter of each entry. An entry counts as advanced
+    /// only if it rose past one this clock already held; one merged in fresh
+    /// keeps `other`'s stamp, so peers gossiping a departed node's entry
+    /// back do not keep it alive against [`prune_older_than`](Self::prune_older_than).
     pub fn merge(&mut self, other: &VectorClock) {
-        for (node_id, &time) in &other.clock {
-            let entry = self.clock.entry(node_id.clone()).or_insert(0);
-            *entry = (*entry).max(time);
+        for (node_id, entry) in &other.clock {
+            match self.clock.get(node_id) {
+                Some(held) if entry.time > held.time => {
+                    self.clock.insert(node_id.clone(), Entry::advanced(entry.time));
+                }
+                Some(_) => {}
+                None => {
+                    self.clock.insert(node_id.clone(), *entry);
+                }
+            }
+        }
+    }
+
+    /// Drops entries until at most `max_entries` remain, evicting the lowest
+    /// counters first and breaking ties by node id, so equal clocks prune
+    /// identically everywhere. Returns how many entries were dropped.
+    ///
+    /// A dropped entry reads as 0 again, so comparisons involving that node
+    /// become less precise; long-lived networks trade that for bounded batches.
+    pub fn prune(&mut self, max_entries: usize) -> usize {
+        self.evict(max_entries, None)
+    }
+
+    /// Like [`prune`](Self::prune), but never drops `node_id`'s entry.
+    pub fn prune_keeping(&mut self, max_entries: usize, node_id: &str) -> usize {
+        self.evict(max_entries, Some(node_id))
+    }
+
+    /// Drops entries that last advanced before `cutoff_millis` (epoch millis).
+    /// Entries this clock never saw advance, e.g. in a deserialized clock or
+    /// merged in from one, are kept. Returns how many entries were dropped.
+    pub fn prune_older_than(&mut self, cutoff_millis: u64) -> usize {
+        let before = self.clock.len();
+        self.clock
+            .retain(|_, entry| entry.advanced_at.is_none_or(|at| at >= cutoff_millis));
+        before - self.clock.len()
+    }
+
+    fn evict(&mut self, max_entries: usize, keep: Option<&str>) -> usize {
+        if self.clock.len() <= max_entries {
+            return 0;
+        }
+
+        let mut candidates: Vec<(u64, &String)> = self
+            .clock
+            .iter()
+            .filter(|(node_id, _)| Some(node_id.as_str()) != keep)
+            .map(|(node_id, entry)| (entry.time, node_id))
+            .collect();
+        candidates.sort();
+
+        let excess = self.clock.len() - max_entries;
+        let evicted: Vec<String> = candidates
+            .into_iter()
+            .take(excess)
+            .map(|(_, node_id)| node_id.clone())
+            .collect();
+        for node_id in &evicted {
+            self.clock.remove(node_id);
         }
+        evicted.len()
     }
 
     pub fn happens_before(&self, other: &VectorClock) -> bool {
         let mut dominated = true;
         let mut strictly_less = false;
 
-        for (node_id, &time) in &self.clock {
+        for (node_id, entry) in &self.clock {
+            let time = entry.time;
             let other_time = other.get(node_id);
             if time > other_time {
                 dominated = false;
@@ -48,8 +153,8 @@ impl VectorClock {
         }
 
         if dominated {
-            for (node_id, &time) in &other.clock {
-                if !self.clock.contains_key(node_id) && time > 0 {
+            for (node_id, entry) in &other.clock {
+                if !self.clock.contains_key(node_id) && entry.time > 0 {
                     strictly_less = true;
                     break;
                 }
@@ -64,7 +169,7 @@ impl VectorClock {
     }
 
     pub fn sum(&self) -> u64 {
-        self.clock.values().sum()
+        self.clock.values().map(|entry| entry.time).sum()
     }
 
     pub fn nodes(&self) -> impl Iterator<Item = &str> {
@@ -85,7 +190,7 @@ impl std::fmt::Display for VectorClock {
         let entries: Vec<_> = self
             .clock
             .iter()
-            .map(|(k, v)| format!("{}:{}", k, v))
+            .map(|(k, v)| format!("{}:{}", k, v.time))
             .collect();
         write!(f, "{{{}}}", entries.join(", "))
     }
@@ -137,6 +242,90 @@ mod tests {
         assert!(!vc2.happens_before(&vc1));
     }
 
+    #[test]
+    fn test_prune_evicts_lowest_counters_deterministically() {
+        let mut vc = VectorClock::new();
+        vc.set("a", 5);
+        vc.set("b", 1);
+        vc.set("c", 1);
+        vc.set("d", 9);
+
+        assert_eq!(vc.prune(2), 2);
+        assert_eq!(vc.len(), 2);
+        assert_eq!(vc.get("a"), 5);
+        assert_eq!(vc.get("d"), 9);
+        assert_eq!(vc.prune(2), 0);
+
+        let mut vc = VectorClock::new();
+        vc.set("c", 1);
+        vc.set("b", 1);
+        vc.prune(1);
+        assert_eq!(vc.nodes().collect::<Vec<_>>(), vec!["c"]);
+    }
+
+    #[test]
+    fn test_prune_keeping_protects_node() {
+        let mut vc = VectorClock::new();
+        vc.set("self", 1);
+        vc.set("a", 4);
+        vc.set("b", 3);
+
+        assert_eq!(vc.prune_keeping(2, "self"), 1);
+        assert_eq!(vc.get("self"), 1);
+        assert_eq!(vc.get("a"), 4);
+        assert_eq!(vc.get("b"), 0);
+    }
+
+    #[test]
+    fn test_prune_older_than() {
+        let mut vc = VectorClock::new();
+        vc.set("a", 1);
+        assert_eq!(vc.prune_older_than(0), 0);
+        assert_eq!(vc.prune_older_than(now_millis() + 1_000), 1);
+        assert!(vc.is_empty());
+    }
+
+    #[test]
+    fn test_prune_older_than_keeps_unobserved_entries() {
+        let mut vc = VectorClock::new();
+        vc.set("a", 1);
+        let json = serde_json::to_string(&vc).unwrap();
+        let mut received: VectorClock = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(received, vc);
+        assert_eq!(received.prune_older_than(u64::MAX), 0);
+        assert_eq!(received.get("a"), 1);
+    }
+
+    #[test]
+    fn test_merge_only_refreshes_advanced_entries() {
+        let mut vc = VectorClock::new();
+        vc.clock.insert("a".into(), Entry { time: 3, advanced_at: Some(0) });
+
+        let mut other = VectorClock::new();
+        other.set("a", 2);
+        vc.merge(&other);
+        assert_eq!(vc.prune_older_than(1), 1);
+    }
+
+    #[test]
+    fn test_merge_does_not_stamp_entries_it_only_adds() {
+        let mut stale = VectorClock::new();
+        stale.clock.insert("gone".into(), Entry { time: 4, advanced_at: Some(0) });
+        let mut vc = VectorClock::new();
+        vc.merge(&stale);
+        assert_eq!(vc.prune_older_than(1), 1);
+
+        let received: VectorClock = serde_json::from_str(r#"{"clock":{"a":2}}"#).unwrap();
+        vc.merge(&received);
+        assert_eq!(vc.prune_older_than(u64::MAX), 0);
+        assert_eq!(vc.get("a"), 2);
+
+        let received: VectorClock = serde_json::from_str(r#"{"clock":{"a":3}}"#).unwrap();
+        vc.merge(&received);
+        assert_eq!(vc.prune_older_than(now_millis() + 1_000), 1);
+    }
+
     #[test]
     fn test_concurrent() {
         let mut vc1 = VectorClock::new();
@@ -0,0 +1,181 @@
+//! Protocol versions and optional capabilities, agreed per peer in the
+//! PeerDiscovery handshake.
+//!
+//! A [`PeerDiscovery`](super::PeerDiscovery) carries the announcer's
+//! highest version and its [`Capabilities`]; the challenge reply carries the
+//! responder's. Each side then talks the lower of the two versions with the
+//! capabilities both have, and refuses a peer whose version is below its own
+//! [`MIN_PROTOCOL_VERSION`]. Nodes from before versioning send neither and
+//! count as [`LEGACY_PROTOCOL_VERSION`] with no capabilities, so newer nodes
+//! stop sending them messages they cannot decode rather than lose them.
+
+use std::fmt;
+use std::ops::BitOr;
+
+use serde::{Deserialize, Serialize};
+
+/// Highest version this node speaks. Version 2 added the handshake; 3
+/// sends messages in an [envelope](super::envelope).
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Lowest version this node still accepts from a peer.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// What a node that predates versioning counts as.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional message kinds a node understands, as a bitmap on the wire.
+/// Negotiation drops the bits this build does not know.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    pub const NONE: Self = Self(0);
+    /// Several protocol messages coalesced into one `Bundle` frame.
+    pub const BUNDLE: Self = Self(1 << 0);
+    /// Frames forwarded for nodes behind a relay (`node.relay_via`).
+    pub const RELAY: Self = Self(1 << 1);
+    /// `DeliveryReport` messages (`consensus.delivery_reports`).
+    pub const DELIVERY_REPORTS: Self = Self(1 << 2);
+    /// BLS aggregate signatures on batches.
+    pub const BLS: Self = Self(1 << 3);
+
+    /// The capabilities of this build.
+    pub fn local() -> Self {
+        let local = Self::BUNDLE | Self::RELAY | Self::DELIVERY_REPORTS;
+        if cfg!(feature = "bls") {
+            local | Self::BLS
+        } else {
+            local
+        }
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Capabilities({:#06b})", self.0)
+    }
+}
+
+/// A node's highest version and its capabilities, as the responder offers
+/// them in reply to a PeerDiscovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolOffer {
+    pub version: u32,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+impl ProtocolOffer {
+    /// What this node offers.
+    pub fn local() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities: Capabilities::local(),
+        }
+    }
+
+    /// What a reply without an offer, from a node that predates versioning,
+    /// stands for.
+    pub fn legacy() -> Self {
+        Self {
+            version: LEGACY_PROTOCOL_VERSION,
+            capabilities: Capabilities::NONE,
+        }
+    }
+}
+
+/// A peer's version is below [`MIN_PROTOCOL_VERSION`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("peer speaks protocol version {theirs}, below the oldest supported {min}")]
+pub struct UnsupportedVersion {
+    pub theirs: u32,
+    pub min: u32,
+}
+
+/// What two nodes use with each other after the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Negotiated {
+    pub version: u32,
+    pub capabilities: Capabilities,
+}
+
+/// Agrees on a version and capabilities with a peer announcing `version`
+/// and `capabilities`, or fails if the peer is too old.
+pub fn negotiate(version: u32, capabilities: Capabilities) -> Result<Negotiated, UnsupportedVersion> {
+    let agreed = version.min(PROTOCOL_VERSION);
+    if agreed < MIN_PROTOCOL_VERSION {
+        return Err(UnsupportedVersion {
+            theirs: version,
+            min: MIN_PROTOCOL_VERSION,
+        });
+    }
+    Ok(Negotiated {
+        version: agreed,
+        capabilities: capabilities.intersection(Capabilities::local()),
+    })
+}
+
+pub(crate) fn legacy_protocol_version() -> u32 {
+    LEGACY_PROTOCOL_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_takes_the_lower_version_and_shared_capabilities() {
+        let newer = Capabilities::local() | Capabilities::from_bits(1 << 20);
+        let agreed = negotiate(PROTOCOL_VERSION + 3, newer).unwrap();
+        assert_eq!(agreed.version, PROTOCOL_VERSION);
+        assert_eq!(agreed.capabilities, Capabilities::local());
+
+        let agreed = negotiate(LEGACY_PROTOCOL_VERSION, Capabilities::NONE).unwrap();
+        assert_eq!(agreed.version, LEGACY_PROTOCOL_VERSION);
+        assert!(!agreed.capabilities.contains(Capabilities::DELIVERY_REPORTS));
+
+        assert_eq!(
+            negotiate(0, Capabilities::local()),
+            Err(UnsupportedVersion { theirs: 0, min: MIN_PROTOCOL_VERSION })
+        );
+    }
+
+    #[test]
+    fn test_capabilities_are_a_plain_bitmap_on_the_wire() {
+        let caps = Capabilities::BUNDLE | Capabilities::DELIVERY_REPORTS;
+        assert_eq!(serde_json::to_string(&caps).unwrap(), "5");
+        assert_eq!(serde_json::from_str::<Capabilities>("5").unwrap(), caps);
+        assert!(caps.contains(Capabilities::BUNDLE));
+        assert!(!caps.contains(Capabilities::RELAY));
+    }
+}
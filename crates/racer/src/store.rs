@@ -0,0 +1,350 @@
+//! Disk-backed store of delivered batches.
+//!
+//! With `node.store_path` set, every batch the deliver stage hands out is
+//! persisted, so it can be audited or served to peers catching up after the
+//! in-memory gossip state has forgotten it. Batches are keyed by their hash
+//! and indexed by batch id, local delivery time and creator, and numbered
+//! ones by creator and sequence number, the first delivered with a number
+//! holding it.
+
+use std::marker::PhantomData;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
+
+use crate::crypto::PublicKey;
+use crate::protocol::BatchedMessages;
+use crate::Message;
+
+/// A persisted delivery.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredBatch<M> {
+    pub hash: String,
+    /// Local epoch millis at which this node delivered the batch.
+    pub delivered_at: u64,
+    pub batch: BatchedMessages<M>,
+}
+
+pub struct DeliveryStore<M> {
+    db: sled::Db,
+    /// hash → `StoredBatch` as JSON
+    batches: sled::Tree,
+    /// batch id → hash of its latest delivery
+    ids: sled::Tree,
+    /// delivered_at (big-endian) ++ hash → ()
+    by_time: sled::Tree,
+    /// creator hex ++ `/` ++ delivered_at (big-endian) ++ hash → ()
+    by_creator: sled::Tree,
+    /// creator hex ++ `/` ++ sequence (big-endian) → hash
+    by_sequence: sled::Tree,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M: Message> DeliveryStore<M> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_db(sled::open(path)?)
+    }
+
+    /// A store deleted when dropped, for tests and ephemeral nodes.
+    pub fn temporary() -> Result<Self, StoreError> {
+        Self::from_db(sled::Config::new().temporary(true).open()?)
+    }
+
+    fn from_db(db: sled::Db) -> Result<Self, StoreError> {
+        Ok(Self {
+            batches: db.open_tree("batches")?,
+            ids: db.open_tree("ids")?,
+            by_time: db.open_tree("by_time")?,
+            by_creator: db.open_tree("by_creator")?,
+            by_sequence: db.open_tree("by_sequence")?,
+            db,
+            _message: PhantomData,
+        })
+    }
+
+    /// Persists a delivery. Returns `false` if `hash` was already stored.
+    pub fn insert(&self, hash: &str, batch: &BatchedMessages<M>, delivered_at: u64) -> Result<bool, StoreError> {
+        let stored = StoredBatch {
+            hash: hash.to_string(),
+            delivered_at,
+            batch: batch.clone(),
+        };
+        let value = serde_json::to_vec(&stored).map_err(|e| StoreError::Serialization(e.to_string()))?;
+        let time_key = time_key(delivered_at, hash);
+        let creator_key = creator_key(&batch.creator_ecdsa, delivered_at, hash);
+        let sequence_key = (batch.sequence > 0).then(|| sequence_key(&batch.creator_ecdsa, batch.sequence));
+
+        let inserted = (&self.batches, &self.ids, &self.by_time, &self.by_creator, &self.by_sequence).transaction(
+            |(batches, ids, by_time, by_creator, by_sequence)| {
+                if batches.get(hash)?.is_some() {
+                    return Ok(false);
+                }
+                batches.insert(hash.as_bytes(), value.as_slice())?;
+                ids.insert(batch.batch_id.as_bytes(), hash.as_bytes())?;
+                by_time.insert(time_key.as_slice(), &[])?;
+                by_creator.insert(creator_key.as_slice(), &[])?;
+                if let Some(key) = &sequence_key {
+                    if by_sequence.get(key.as_slice())?.is_none() {
+                        by_sequence.insert(key.as_slice(), hash.as_bytes())?;
+                    }
+                }
+                Ok::<_, ConflictableTransactionError<StoreError>>(true)
+            },
+        )?;
+        Ok(inserted)
+    }
+
+    pub fn get(&self, hash: &str) -> Result<Option<StoredBatch<M>>, StoreError> {
+        self.batches.get(hash)?.map(|value| decode(&value)).transpose()
+    }
+
+    /// Latest delivery of the batch with this id.
+    pub fn get_batch(&self, batch_id: &str) -> Result<Option<StoredBatch<M>>, StoreError> {
+        match self.ids.get(batch_id)? {
+            Some(hash) => self.get(&String::from_utf8_lossy(&hash)),
+            None => Ok(None),
+        }
+    }
+
+    /// Deliveries at or after `since_millis`, oldest first.
+    pub fn delivered_since(
+        &self,
+        since_millis: u64,
+    ) -> impl Iterator<Item = Result<StoredBatch<M>, StoreError>> + '_ {
+        self.by_time
+            .range(since_millis.to_be_bytes()..)
+            .map(move |entry| self.resolve(entry?.0, 8))
+    }
+
+    /// Deliveries of batches created by `creator`, oldest first.
+    pub fn by_creator(
+        &self,
+        creator: &PublicKey,
+    ) -> impl Iterator<Item = Result<StoredBatch<M>, StoreError>> + '_ {
+        let prefix = creator_prefix(creator);
+        let hash_offset = prefix.len() + 8;
+        self.by_creator
+            .scan_prefix(prefix)
+            .map(move |entry| self.resolve(entry?.0, hash_offset))
+    }
+
+    /// Deliveries of `creator`'s numbered batches in sequence order, from
+    /// `from_sequence` up to the first number not delivered here.
+    pub fn creator_log(
+        &self,
+        creator: &PublicKey,
+        from_sequence: u64,
+    ) -> impl Iterator<Item = Result<StoredBatch<M>, StoreError>> + '_ {
+        let mut expected = from_sequence.max(1);
+        self.by_sequence
+            .range(sequence_key(creator, expected)..sequence_key(creator, u64::MAX))
+            .take_while(move |entry| match entry {
+                Ok((key, _)) if sequence_of(key) == expected => {
+                    expected += 1;
+                    true
+                }
+                Ok(_) => false,
+                Err(_) => true,
+            })
+            .map(move |entry| {
+                let (_, hash) = entry?;
+                let hash = String::from_utf8_lossy(&hash).into_owned();
+                self.get(&hash)?
+                    .ok_or_else(|| StoreError::Corrupt(format!("sequence entry for missing batch {}", hash)))
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    pub fn flush(&self) -> Result<(), StoreError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Loads the batch whose hash ends an index key at `hash_offset`.
+    fn resolve(&self, key: sled::IVec, hash_offset: usize) -> Result<StoredBatch<M>, StoreError> {
+        let hash = String::from_utf8_lossy(&key[hash_offset..]).into_owned();
+        self.get(&hash)?
+            .ok_or_else(|| StoreError::Corrupt(format!("index entry for missing batch {}", hash)))
+    }
+}
+
+fn decode<M: Message>(value: &[u8]) -> Result<StoredBatch<M>, StoreError> {
+    serde_json::from_slice(value).map_err(|e| StoreError::Serialization(e.to_string()))
+}
+
+fn time_key(delivered_at: u64, hash: &str) -> Vec<u8> {
+    let mut key = delivered_at.to_be_bytes().to_vec();
+    key.extend_from_slice(hash.as_bytes());
+    key
+}
+
+fn creator_prefix(creator: &PublicKey) -> Vec<u8> {
+    let mut prefix = creator.to_hex().into_bytes();
+    prefix.push(b'/');
+    prefix
+}
+
+fn creator_key(creator: &PublicKey, delivered_at: u64, hash: &str) -> Vec<u8> {
+    let mut key = creator_prefix(creator);
+    key.extend_from_slice(&time_key(delivered_at, hash));
+    key
+}
+
+fn sequence_key(creator: &PublicKey, sequence: u64) -> Vec<u8> {
+    let mut key = creator_prefix(creator);
+    key.extend_from_slice(&sequence.to_be_bytes());
+    key
+}
+
+fn sequence_of(key: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&key[key.len() - 8..]);
+    u64::from_be_bytes(bytes)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("storage error: {0}")]
+    Storage(#[from] sled::Error),
+    #[error("serialization error: {0}")]
+    Serialization(String),
+    #[error("corrupt store: {0}")]
+    Corrupt(String),
+}
+
+impl From<TransactionError<StoreError>> for StoreError {
+    fn from(e: TransactionError<StoreError>) -> Self {
+        match e {
+            TransactionError::Abort(e) => e,
+            TransactionError::Storage(e) => StoreError::Storage(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::protocol::VectorClock;
+    use racer_core::message::DefaultMessage;
+
+    fn numbered(creator: &PublicKey, sequence: u64) -> BatchedMessages<DefaultMessage> {
+        BatchedMessages {
+            sequence,
+            ..batch(creator, &format!("n-{}", sequence))
+        }
+    }
+
+    fn batch(creator: &PublicKey, batch_id: &str) -> BatchedMessages<DefaultMessage> {
+        BatchedMessages {
+            batch_id: batch_id.into(),
+            creator_ecdsa: creator.clone(),
+            sender_ecdsa: creator.clone(),
+            merkle_root: "root".into(),
+            batch_size: 1,
+            messages: vec![DefaultMessage::default()],
+            vector_clock: VectorClock::new(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 0,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        }
+    }
+
+    fn hashes(items: impl Iterator<Item = Result<StoredBatch<DefaultMessage>, StoreError>>) -> Vec<String> {
+        items.map(|item| item.unwrap().hash).collect()
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let store = DeliveryStore::temporary().unwrap();
+        let creator = KeyPair::generate().public_key();
+
+        assert!(store.insert("h1", &batch(&creator, "b-1"), 100).unwrap());
+        assert!(!store.insert("h1", &batch(&creator, "b-1"), 200).unwrap());
+        assert_eq!(store.len(), 1);
+
+        let stored = store.get_batch("b-1").unwrap().unwrap();
+        assert_eq!(stored.hash, "h1");
+        assert_eq!(stored.delivered_at, 100);
+        assert!(store.get_batch("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delivered_since() {
+        let store = DeliveryStore::temporary().unwrap();
+        let creator = KeyPair::generate().public_key();
+        store.insert("h3", &batch(&creator, "b-3"), 300).unwrap();
+        store.insert("h1", &batch(&creator, "b-1"), 100).unwrap();
+        store.insert("h2", &batch(&creator, "b-2"), 200).unwrap();
+
+        assert_eq!(hashes(store.delivered_since(200)), vec!["h2", "h3"]);
+        assert_eq!(hashes(store.delivered_since(0)).len(), 3);
+        assert!(hashes(store.delivered_since(301)).is_empty());
+    }
+
+    #[test]
+    fn test_by_creator() {
+        let store = DeliveryStore::temporary().unwrap();
+        let (alice, bob) = (KeyPair::generate().public_key(), KeyPair::generate().public_key());
+        store.insert("a2", &batch(&alice, "a-2"), 20).unwrap();
+        store.insert("b1", &batch(&bob, "b-1"), 15).unwrap();
+        store.insert("a1", &batch(&alice, "a-1"), 10).unwrap();
+
+        assert_eq!(hashes(store.by_creator(&alice)), vec!["a1", "a2"]);
+        assert_eq!(hashes(store.by_creator(&bob)), vec!["b1"]);
+    }
+
+    #[test]
+    fn test_creator_log_stops_at_gap() {
+        let store = DeliveryStore::temporary().unwrap();
+        let (alice, bob) = (KeyPair::generate().public_key(), KeyPair::generate().public_key());
+        store.insert("a3", &numbered(&alice, 3), 10).unwrap();
+        store.insert("a1", &numbered(&alice, 1), 20).unwrap();
+        store.insert("a2", &numbered(&alice, 2), 30).unwrap();
+        store.insert("a5", &numbered(&alice, 5), 40).unwrap();
+        store.insert("b4", &numbered(&bob, 4), 50).unwrap();
+        // A second batch under a number already held does not replace it.
+        store.insert("a2x", &numbered(&alice, 2), 60).unwrap();
+
+        assert_eq!(hashes(store.creator_log(&alice, 0)), vec!["a1", "a2", "a3"]);
+        assert_eq!(hashes(store.creator_log(&alice, 2)), vec!["a2", "a3"]);
+        assert_eq!(hashes(store.creator_log(&alice, 5)), vec!["a5"]);
+        assert!(hashes(store.creator_log(&alice, 4)).is_empty());
+        assert!(hashes(store.creator_log(&bob, 1)).is_empty());
+        assert_eq!(hashes(store.creator_log(&bob, 4)), vec!["b4"]);
+    }
+
+    #[test]
+    fn test_reopen_keeps_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let creator = KeyPair::generate().public_key();
+        {
+            let store = DeliveryStore::open(dir.path()).unwrap();
+            store.insert("h1", &batch(&creator, "b-1"), 1).unwrap();
+            store.flush().unwrap();
+        }
+
+        let store = DeliveryStore::<DefaultMessage>::open(dir.path()).unwrap();
+        assert_eq!(store.get("h1").unwrap().unwrap().batch.batch_id, "b-1");
+    }
+}
@@ -0,0 +1,221 @@
+//! OpenTelemetry export of traces and metrics over OTLP/HTTP.
+//!
+//! Configured from [`LogConfig`]: when `otlp_endpoint` is set, [`init`] installs
+//! a console subscriber plus an OpenTelemetry layer, so `gossip_round` spans
+//! reach the collector, and each [`Node`](crate::node::Node) reports its PLATO
+//! gauges and pipeline counters through the global meter provider. Call
+//! [`shutdown`] before exiting to flush what is still buffered.
+
+use std::sync::{Mutex, Weak};
+
+use opentelemetry::metrics::Meter;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+use tokio::sync::RwLock;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::node::pipeline::PipelineCounters;
+use crate::plato::PlatoController;
+use crate::util::logging::LogConfig;
+
+const METER_NAME: &str = "racer";
+
+static PROVIDERS: Mutex<Option<(SdkTracerProvider, SdkMeterProvider)>> = Mutex::new(None);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryError {
+    #[error("exporter error: {0}")]
+    Exporter(#[from] opentelemetry_otlp::ExporterBuildError),
+    #[error("subscriber error: {0}")]
+    Subscriber(String),
+}
+
+/// Builds the OpenTelemetry layer for `config` and installs the meter provider
+/// globally. Returns `None` when no `otlp_endpoint` is configured.
+///
+/// Use this to add export to a subscriber you assemble yourself; [`init`] does
+/// it for you.
+pub fn layer<S>(config: &LogConfig) -> Result<Option<OpenTelemetryLayer<S, Tracer>>, TelemetryError>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let endpoint = endpoint.trim_end_matches('/');
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+
+    let spans = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint))
+        .build()?;
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(spans)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.trace_sample_ratio,
+        ))))
+        .with_resource(resource.clone())
+        .build();
+
+    let metrics = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/metrics", endpoint))
+        .build()?;
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(metrics)
+        .with_resource(resource)
+        .build();
+    global::set_meter_provider(meter_provider.clone());
+
+    let tracer = tracer_provider.tracer(METER_NAME);
+    if let Some((tracer, meter)) = PROVIDERS
+        .lock()
+        .unwrap()
+        .replace((tracer_provider, meter_provider))
+    {
+        let _ = tracer.shutdown();
+        let _ = meter.shutdown();
+    }
+
+    tracing::info!(endpoint = %endpoint, service = %config.service_name, "OpenTelemetry export enabled");
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Installs a global subscriber printing `racer=info` to the console and, when
+/// `otlp_endpoint` is set, exporting spans and metrics.
+pub fn init(config: &LogConfig) -> Result<(), TelemetryError> {
+    let filter = EnvFilter::from_default_env().add_directive(
+        "racer=info"
+            .parse()
+            .map_err(|e| TelemetryError::Subscriber(format!("{}", e)))?,
+    );
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+    let otel = layer(config)?;
+    registry
+        .with(otel)
+        .try_init()
+        .map_err(|e| TelemetryError::Subscriber(e.to_string()))
+}
+
+/// Flushes and stops the exporters installed by [`layer`] or [`init`].
+pub fn shutdown() {
+    if let Some((tracer, meter)) = PROVIDERS.lock().unwrap().take() {
+        if let Err(e) = tracer.shutdown() {
+            tracing::warn!(error = %e, "failed to flush traces");
+        }
+        if let Err(e) = meter.shutdown() {
+            tracing::warn!(error = %e, "failed to flush metrics");
+        }
+    }
+}
+
+/// Instrument name and how to read its value from the source.
+type Reading<T, V> = (&'static str, fn(&T) -> V);
+
+fn meter() -> Meter {
+    global::meter(METER_NAME)
+}
+
+/// Registers one node's PLATO gauges, pipeline counters and active round count
+/// with the global meter. Sources are held weakly, so callbacks report nothing
+/// once the node is dropped.
+pub(crate) fn register_node_metrics(
+    id: &str,
+    plato: Weak<RwLock<PlatoController>>,
+    pipeline: Weak<PipelineCounters>,
+    active_rounds: impl Fn() -> Option<usize> + Send + Sync + 'static,
+) {
+    let meter = meter();
+    let attrs = [KeyValue::new("node", id.to_string())];
+
+    let plato_gauges: [Reading<PlatoController, f64>; 3] = [
+        ("racer.plato.current_latency", PlatoController::current_latency),
+        ("racer.plato.publish_frequency", PlatoController::publish_frequency),
+        ("racer.plato.weighted_latency", PlatoController::weighted_latency),
+    ];
+    for (name, read) in plato_gauges {
+        let plato = plato.clone();
+        let attrs = attrs.clone();
+        meter
+            .f64_observable_gauge(name)
+            .with_unit("s")
+            .with_callback(move |observer| {
+                if let Some(plato) = plato.upgrade() {
+                    if let Ok(plato) = plato.try_read() {
+                        observer.observe(read(&plato), &attrs);
+                    }
+                }
+            })
+            .build();
+    }
+
+    let pipeline_counters: [Reading<PipelineCounters, u64>; 10] = [
+        ("racer.network.received", |c| c.snapshot().received),
+        ("racer.network.decode_errors", |c| c.snapshot().decode_errors),
+        ("racer.network.unknown_types", |c| c.snapshot().unknown_types),
+        ("racer.network.rejected", |c| c.snapshot().rejected),
+        ("racer.network.duplicates", |c| c.snapshot().duplicates),
+        ("racer.pipeline.processed", |c| c.snapshot().processed),
+        ("racer.pipeline.delivered", |c| c.snapshot().delivered),
+        ("racer.pipeline.expired", |c| c.snapshot().expired),
+        ("racer.pipeline.hop_limited", |c| c.snapshot().hop_limited),
+        ("racer.pipeline.app_duplicates", |c| c.snapshot().app_duplicates),
+    ];
+    for (name, read) in pipeline_counters {
+        let pipeline = pipeline.clone();
+        let attrs = attrs.clone();
+        meter
+            .u64_observable_counter(name)
+            .with_callback(move |observer| {
+                if let Some(pipeline) = pipeline.upgrade() {
+                    observer.observe(read(&pipeline), &attrs);
+                }
+            })
+            .build();
+    }
+
+    meter
+        .u64_observable_gauge("racer.gossip.active_rounds")
+        .with_callback(move |observer| {
+            if let Some(rounds) = active_rounds() {
+                observer.observe(rounds as u64, &attrs);
+            }
+        })
+        .build();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::Registry;
+
+    #[test]
+    fn test_layer_disabled_without_endpoint() {
+        let config = LogConfig::default();
+        assert!(layer::<Registry>(&config).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_layer_with_endpoint() {
+        let config = LogConfig {
+            otlp_endpoint: Some("http://127.0.0.1:4318/".into()),
+            trace_sample_ratio: 0.5,
+            ..Default::default()
+        };
+        assert!(layer::<Registry>(&config).unwrap().is_some());
+        shutdown();
+        assert!(PROVIDERS.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,232 @@
+//! In-process clusters for tests and examples.
+//!
+//! [`Cluster::new`] starts N nodes from one config template, each bound to
+//! `local://` endpoints of its own (see [`crate::network::LocalBus`]), and
+//! registers every node as a peer of every other. Each node gets a sink
+//! recording the batches it delivers, so a test can submit on one node and
+//! wait for the batch to land on all of them:
+//!
+//! ```rust,ignore
+//! let cluster = Cluster::<DefaultMessage>::new(4, RacerConfig::minimal()).await?;
+//! let batch_id = cluster.submit(0, DefaultMessage::new()).await?;
+//! cluster.await_all_delivered(&batch_id).await?;
+//! cluster.shutdown().await;
+//! ```
+//!
+//! Per-node files and listeners in the template (`key_file`, `store_path`,
+//! `admin_bind` and the like) are cleared, since the nodes would share them.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use racer_core::Message;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+use crate::config::RacerConfig;
+use crate::network::PeerInfo;
+use crate::node::sink::DeliverySink;
+use crate::node::{Node, NodeError};
+use crate::protocol::BatchedMessages;
+
+/// How long [`Cluster::await_all_delivered`] waits unless told otherwise.
+pub const DEFAULT_DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Keeps the endpoints of clusters in one process apart.
+static NEXT_CLUSTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClusterError {
+    #[error(transparent)]
+    Node(#[from] NodeError),
+    #[error("no node {index} in a cluster of {size}")]
+    NoSuchNode { index: usize, size: usize },
+    #[error("batch {batch_id} not delivered on {missing:?}")]
+    Undelivered { batch_id: String, missing: Vec<String> },
+}
+
+/// Batch ids a node delivered, shared with the cluster.
+#[derive(Clone)]
+struct DeliveryLog {
+    batch_ids: Arc<Mutex<HashSet<String>>>,
+    notify: Arc<Notify>,
+}
+
+impl DeliveryLog {
+    fn contains(&self, batch_id: &str) -> bool {
+        self.batch_ids.lock().unwrap_or_else(|e| e.into_inner()).contains(batch_id)
+    }
+
+    fn remove(&self, batch_id: &str) {
+        self.batch_ids.lock().unwrap_or_else(|e| e.into_inner()).remove(batch_id);
+    }
+}
+
+impl<M> DeliverySink<M> for DeliveryLog {
+    fn on_delivered(&self, batch: &BatchedMessages<M>) {
+        self.batch_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(batch.batch_id.clone());
+        self.notify.notify_waiters();
+    }
+}
+
+/// N started nodes, each a peer of every other.
+pub struct Cluster<M> {
+    nodes: Vec<Arc<Node<M>>>,
+    logs: Vec<DeliveryLog>,
+    notify: Arc<Notify>,
+    delivery_timeout: Duration,
+}
+
+impl<M> Cluster<M>
+where
+    M: Message + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Builds and starts `n` nodes from `template`, named `node-0` onwards,
+    /// and meshes them.
+    pub async fn new(n: usize, template: RacerConfig) -> Result<Self, ClusterError> {
+        let cluster = NEXT_CLUSTER.fetch_add(1, Ordering::Relaxed);
+        let notify = Arc::new(Notify::new());
+        let mut nodes = Vec::with_capacity(n);
+        let mut logs = Vec::with_capacity(n);
+        let mut peers = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let id = format!("node-{}", i);
+            let router = format!("local://cluster-{}/{}/router", cluster, id);
+            let publisher = format!("local://cluster-{}/{}/pub", cluster, id);
+
+            let mut config = template.clone();
+            config.node.id = Some(id.clone());
+            config.node.router_bind = router.clone();
+            config.node.publisher_bind = publisher.clone();
+            config.node.extra_router_binds.clear();
+            config.node.extra_publisher_binds.clear();
+            config.node.websocket_bind = None;
+            config.node.admin_bind = None;
+            config.node.key_file = None;
+            config.node.store_path = None;
+            config.node.outbox_path = None;
+            config.node.plato_state_path = None;
+            config.node.sequence_path = None;
+            config.node.relay_via = None;
+            config.plato.trace_file = None;
+            config.peers.routers.clear();
+            config.peers.pinned_keys.clear();
+            config.logging.enabled = false;
+            // Seeded clusters stay repeatable without every node picking alike.
+            config.node.rng_seed = template.node.rng_seed.map(|seed| seed.wrapping_add(i as u64));
+
+            let node = Node::<M>::new(config).await?;
+            let log = DeliveryLog {
+                batch_ids: Arc::new(Mutex::new(HashSet::new())),
+                notify: Arc::clone(&notify),
+            };
+            node.add_sink(log.clone());
+            node.start().await?;
+
+            peers.push(PeerInfo::new(id, node.public_key(), router, publisher).with_role(template.node.role));
+            nodes.push(Arc::new(node));
+            logs.push(log);
+        }
+
+        for node in &nodes {
+            for peer in &peers {
+                if peer.id != node.id() {
+                    node.add_peer(peer.clone()).await;
+                }
+            }
+        }
+
+        Ok(Self {
+            nodes,
+            logs,
+            notify,
+            delivery_timeout: DEFAULT_DELIVERY_TIMEOUT,
+        })
+    }
+
+    /// Sets how long [`await_all_delivered`](Self::await_all_delivered) waits.
+    pub fn with_delivery_timeout(mut self, timeout: Duration) -> Self {
+        self.delivery_timeout = timeout;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, index: usize) -> Result<&Arc<Node<M>>, ClusterError> {
+        self.nodes.get(index).ok_or(ClusterError::NoSuchNode {
+            index,
+            size: self.nodes.len(),
+        })
+    }
+
+    pub fn nodes(&self) -> &[Arc<Node<M>>] {
+        &self.nodes
+    }
+
+    /// Submits `message` on node `index`, returning the batch id.
+    pub async fn submit(&self, index: usize, message: M) -> Result<String, ClusterError> {
+        Ok(self.node(index)?.submit(message).await?)
+    }
+
+    /// Whether node `index` has delivered `batch_id`.
+    pub fn delivered(&self, index: usize, batch_id: &str) -> bool {
+        self.logs.get(index).is_some_and(|log| log.contains(batch_id))
+    }
+
+    /// Stops tracking `batch_id`, so long runs do not keep every id ever
+    /// delivered. A later delivery of it is recorded again.
+    pub fn forget(&self, batch_id: &str) {
+        for log in &self.logs {
+            log.remove(batch_id);
+        }
+    }
+
+    /// Waits until every node has delivered `batch_id`. Fails with the ids
+    /// of the nodes still missing it once the delivery timeout passes.
+    pub async fn await_all_delivered(&self, batch_id: &str) -> Result<(), ClusterError> {
+        let deadline = tokio::time::Instant::now() + self.delivery_timeout;
+        loop {
+            // Registered before checking, so a delivery in between still wakes us.
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let missing: Vec<String> = self
+                .logs
+                .iter()
+                .zip(&self.nodes)
+                .filter(|(log, _)| !log.contains(batch_id))
+                .map(|(_, node)| node.id().to_string())
+                .collect();
+            if missing.is_empty() {
+                return Ok(());
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return Err(ClusterError::Undelivered {
+                    batch_id: batch_id.to_string(),
+                    missing,
+                });
+            }
+        }
+    }
+
+    /// Stops every node, releasing its endpoints.
+    pub async fn shutdown(self) {
+        for node in &self.nodes {
+            node.stop().await;
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! File formats of the delivered batch log.
+//!
+//! [`DeliveredMessageLogger`](super::logging::DeliveredMessageLogger) hands
+//! each entry to an [`EntryWriter`] for `logging.delivered_format`. Rotation
+//! only renames whole files, so a reader never sees a half-rotated log;
+//! Parquet files are written under a `.tmp` name and renamed once complete.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::logging::{DeliveredEntry, DeliveredField, DeliveredFormat, LogConfig};
+
+pub(crate) trait EntryWriter: Send {
+    fn write(&mut self, entry: &DeliveredEntry) -> io::Result<()>;
+
+    /// Bytes in the current file, for size-based rotation.
+    fn bytes_written(&self) -> u64;
+
+    /// Flushes and completes the current file.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// The delivered log at `path`, rotating it once it reaches the size limit.
+pub(crate) struct DeliveredFile {
+    path: PathBuf,
+    format: DeliveredFormat,
+    fields: Vec<DeliveredField>,
+    max_bytes: Option<u64>,
+    max_files: usize,
+    writer: Option<Box<dyn EntryWriter>>,
+}
+
+impl DeliveredFile {
+    pub(crate) fn open(config: &LogConfig, path: PathBuf) -> io::Result<Self> {
+        let mut file = Self {
+            path,
+            format: config.delivered_format,
+            fields: config.delivered_fields.clone(),
+            max_bytes: config.rotation_enabled.then_some(config.max_file_size_mb * 1024 * 1024),
+            max_files: config.max_files,
+            writer: None,
+        };
+        file.writer = Some(file.open_writer()?);
+        Ok(file)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn write(&mut self, entry: &DeliveredEntry) -> io::Result<()> {
+        if self.writer.is_none() {
+            self.writer = Some(self.open_writer()?);
+        }
+        let writer = self.writer.as_mut().expect("opened above");
+        writer.write(entry)?;
+
+        if self.max_bytes.is_some_and(|max| writer.bytes_written() >= max) {
+            if let Some(writer) = self.writer.take() {
+                writer.finish()?;
+            }
+            rotate(&self.path, self.max_files)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn open_writer(&self) -> io::Result<Box<dyn EntryWriter>> {
+        Ok(match self.format {
+            DeliveredFormat::Jsonl => Box::new(JsonlWriter::open(&self.path)?),
+            DeliveredFormat::Csv => Box::new(CsvWriter::open(&self.path, self.fields.clone())?),
+            #[cfg(feature = "parquet")]
+            DeliveredFormat::Parquet => {
+                // A finished file cannot be appended to, so a previous run's log moves aside.
+                if self.path.exists() {
+                    rotate(&self.path, self.max_files)?;
+                }
+                Box::new(parquet_file::ParquetWriter::open(&self.path)?)
+            }
+            #[cfg(not(feature = "parquet"))]
+            DeliveredFormat::Parquet => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "delivered_format = \"parquet\" needs the `parquet` feature",
+                ))
+            }
+        })
+    }
+}
+
+/// Shifts `path` to `path.1`, `path.1` to `path.2` and so on, dropping the
+/// file beyond `max_files`. Each step is a rename, so it is atomic.
+pub(crate) fn rotate(path: &Path, max_files: usize) -> io::Result<()> {
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+
+    if max_files == 0 {
+        return remove_if_exists(path);
+    }
+    remove_if_exists(&numbered(max_files))?;
+    for n in (1..max_files).rev() {
+        rename_if_exists(&numbered(n), &numbered(n + 1))?;
+    }
+    rename_if_exists(path, &numbered(1))
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let len = file.metadata()?.len();
+    Ok((BufWriter::new(file), len))
+}
+
+/// One JSON object per line, payload included.
+struct JsonlWriter {
+    file: BufWriter<File>,
+    len: u64,
+}
+
+impl JsonlWriter {
+    fn open(path: &Path) -> io::Result<Self> {
+        let (file, len) = open_append(path)?;
+        Ok(Self { file, len })
+    }
+}
+
+impl EntryWriter for JsonlWriter {
+    fn write(&mut self, entry: &DeliveredEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.len
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// RFC 4180 CSV of the selected fields, with a header row per file.
+struct CsvWriter {
+    file: BufWriter<File>,
+    fields: Vec<DeliveredField>,
+    len: u64,
+}
+
+impl CsvWriter {
+    fn open(path: &Path, fields: Vec<DeliveredField>) -> io::Result<Self> {
+        let (file, len) = open_append(path)?;
+        let mut writer = Self { file, fields, len };
+        if len == 0 {
+            let header: Vec<String> = writer.fields.iter().map(|f| f.name().to_string()).collect();
+            writer.write_row(&header)?;
+        }
+        Ok(writer)
+    }
+
+    fn write_row(&mut self, cells: &[String]) -> io::Result<()> {
+        let mut row = cells.iter().map(|cell| csv_escape(cell)).collect::<Vec<_>>().join(",");
+        row.push_str("\r\n");
+        self.file.write_all(row.as_bytes())?;
+        self.file.flush()?;
+        self.len += row.len() as u64;
+        Ok(())
+    }
+}
+
+impl EntryWriter for CsvWriter {
+    fn write(&mut self, entry: &DeliveredEntry) -> io::Result<()> {
+        let cells: Vec<String> = self.fields.iter().map(|field| field.value(entry)).collect();
+        self.write_row(&cells)
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.len
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_file {
+    use std::fs::{self, File};
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::errors::ParquetError;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    use super::EntryWriter;
+    use crate::util::logging::DeliveredEntry;
+
+    /// Rows buffered before they are written out as one row group.
+    const ROW_GROUP_SIZE: usize = 1024;
+
+    const SCHEMA: &str = "
+        message delivered {
+            REQUIRED INT64 seq;
+            REQUIRED BINARY batch_id (UTF8);
+            REQUIRED BINARY creator (UTF8);
+            REQUIRED BINARY merkle_root (UTF8);
+            REQUIRED INT64 batch_size;
+            REQUIRED BINARY delivered_at (UTF8);
+            OPTIONAL BINARY payload (UTF8);
+        }
+    ";
+
+    fn io_error(e: ParquetError) -> io::Error {
+        io::Error::other(e)
+    }
+
+    /// Writes to `<path>.tmp` and renames it to `path` when finished.
+    pub(super) struct ParquetWriter {
+        path: PathBuf,
+        tmp: PathBuf,
+        writer: SerializedFileWriter<File>,
+        rows: Vec<DeliveredEntry>,
+        len: u64,
+    }
+
+    impl ParquetWriter {
+        pub(super) fn open(path: &Path) -> io::Result<Self> {
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".tmp");
+            let tmp = PathBuf::from(tmp);
+
+            let schema = Arc::new(parse_message_type(SCHEMA).map_err(io_error)?);
+            let properties = Arc::new(WriterProperties::builder().build());
+            let writer = SerializedFileWriter::new(File::create(&tmp)?, schema, properties)
+                .map_err(io_error)?;
+            Ok(Self { path: path.to_path_buf(), tmp, writer, rows: Vec::new(), len: 0 })
+        }
+
+        fn flush_rows(&mut self) -> Result<(), ParquetError> {
+            if self.rows.is_empty() {
+                return Ok(());
+            }
+            let rows = std::mem::take(&mut self.rows);
+            let text = |f: fn(&DeliveredEntry) -> &str| -> Vec<ByteArray> {
+                rows.iter().map(|row| ByteArray::from(f(row))).collect()
+            };
+            let payloads: Vec<Option<String>> =
+                rows.iter().map(|row| row.payload.as_ref().map(|p| p.to_string())).collect();
+            let definition: Vec<i16> = payloads.iter().map(|p| p.is_some() as i16).collect();
+            let present: Vec<ByteArray> =
+                payloads.iter().flatten().map(|p| ByteArray::from(p.as_str())).collect();
+
+            let mut group = self.writer.next_row_group()?;
+            let mut index = 0;
+            while let Some(mut column) = group.next_column()? {
+                match index {
+                    0 => {
+                        let seqs: Vec<i64> = rows.iter().map(|r| r.seq as i64).collect();
+                        column.typed::<Int64Type>().write_batch(&seqs, None, None)?;
+                    }
+                    1 => {
+                        column.typed::<ByteArrayType>().write_batch(&text(|r| r.batch_id.as_str()), None, None)?;
+                    }
+                    2 => {
+                        column.typed::<ByteArrayType>().write_batch(&text(|r| r.creator.as_str()), None, None)?;
+                    }
+                    3 => {
+                        column.typed::<ByteArrayType>().write_batch(&text(|r| r.merkle_root.as_str()), None, None)?;
+                    }
+                    4 => {
+                        let sizes: Vec<i64> = rows.iter().map(|r| r.batch_size as i64).collect();
+                        column.typed::<Int64Type>().write_batch(&sizes, None, None)?;
+                    }
+                    5 => {
+                        column.typed::<ByteArrayType>().write_batch(&text(|r| r.delivered_at.as_str()), None, None)?;
+                    }
+                    _ => {
+                        column.typed::<ByteArrayType>().write_batch(&present, Some(&definition), None)?;
+                    }
+                }
+                column.close()?;
+                index += 1;
+            }
+            group.close()?;
+            Ok(())
+        }
+    }
+
+    impl EntryWriter for ParquetWriter {
+        fn write(&mut self, entry: &DeliveredEntry) -> io::Result<()> {
+            self.len += (entry.batch_id.len()
+                + entry.creator.len()
+                + entry.merkle_root.len()
+                + entry.delivered_at.len()
+                + 16) as u64;
+            self.rows.push(entry.clone());
+            if self.rows.len() >= ROW_GROUP_SIZE {
+                self.flush_rows().map_err(io_error)?;
+            }
+            Ok(())
+        }
+
+        fn bytes_written(&self) -> u64 {
+            self.len
+        }
+
+        fn finish(mut self: Box<Self>) -> io::Result<()> {
+            self.flush_rows().map_err(io_error)?;
+            let ParquetWriter { path, tmp, writer, .. } = *self;
+            writer.close().map_err(io_error)?;
+            fs::rename(tmp, path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(seq: u64, payload: &str) -> DeliveredEntry {
+        DeliveredEntry {
+            seq,
+            batch_id: format!("batch-{}", seq),
+            creator: "02ab".into(),
+            merkle_root: "root".into(),
+            batch_size: 1,
+            delivered_at: "1700000000.000Z".into(),
+            payload: Some(serde_json::json!(payload)),
+        }
+    }
+
+    #[test]
+    fn test_csv_selected_fields_escaped() {
+        let dir = tempdir().unwrap();
+        let config = LogConfig {
+            delivered_format: DeliveredFormat::Csv,
+            delivered_fields: vec![DeliveredField::Seq, DeliveredField::Payload],
+            ..Default::default()
+        };
+        let path = dir.path().join("delivered.csv");
+        let mut file = DeliveredFile::open(&config, path.clone()).unwrap();
+        file.write(&entry(1, "a, \"quoted\" value")).unwrap();
+        file.finish().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let row = r#"1,"""a, \""quoted\"" value""""#;
+        assert_eq!(contents, format!("seq,payload\r\n{}\r\n", row));
+    }
+
+    #[test]
+    fn test_rotation_keeps_max_files() {
+        let dir = tempdir().unwrap();
+        let config = LogConfig {
+            rotation_enabled: true,
+            max_file_size_mb: 0,
+            max_files: 2,
+            ..Default::default()
+        };
+        let path = dir.path().join("delivered.jsonl");
+        let mut file = DeliveredFile::open(&config, path.clone()).unwrap();
+        for seq in 1..=4 {
+            file.write(&entry(seq, "x")).unwrap();
+        }
+        file.finish().unwrap();
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert!(read("delivered.jsonl.1").contains("batch-4"));
+        assert!(read("delivered.jsonl.2").contains("batch-3"));
+        assert!(!dir.path().join("delivered.jsonl.3").exists());
+    }
+}
@@ -1,11 +1,13 @@
-use std::fs::{self, File, OpenOptions};
-use std::io::{BufWriter, Write};
+use std::collections::BTreeMap;
+use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use serde::Serialize;
 use tokio::sync::mpsc;
 
+use super::delivered::DeliveredFile;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct LogConfig {
     #[serde(default = "default_enabled")]
@@ -17,6 +19,16 @@ pub struct LogConfig {
     #[serde(default = "default_delivered_file")]
     pub delivered_file: String,
 
+    /// Format of `delivered_file`.
+    #[serde(default)]
+    pub delivered_format: DeliveredFormat,
+
+    /// Columns of a CSV `delivered_file`, in order.
+    #[serde(default = "default_delivered_fields")]
+    pub delivered_fields: Vec<DeliveredField>,
+
+    /// Rotate `delivered_file` once it reaches `max_file_size_mb`, keeping
+    /// `max_files` rotated files as `<delivered_file>.1`, `.2`, ...
     #[serde(default)]
     pub rotation_enabled: bool,
 
@@ -25,6 +37,115 @@ pub struct LogConfig {
 
     #[serde(default = "default_max_files")]
     pub max_files: usize,
+
+    /// OTLP/HTTP collector base URL, e.g. `http://localhost:4318`; needs the
+    /// `telemetry` feature. Traces and metrics are exported when set.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// `service.name` reported with exported traces and metrics.
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+
+    /// Fraction of traces to sample, from 0.0 to 1.0.
+    #[serde(default = "default_trace_sample_ratio")]
+    pub trace_sample_ratio: f64,
+
+    /// Output of the console and of `file`.
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Level of `racer` events without an entry in `levels`.
+    #[serde(default = "default_level")]
+    pub level: String,
+
+    /// Levels per module, e.g. `"racer::plato" = "debug"`. `RUST_LOG`
+    /// directives still take precedence.
+    #[serde(default)]
+    pub levels: BTreeMap<String, String>,
+
+    /// File in the CLI's log directory that receives the console's events
+    /// in `format`. Unset writes no such file.
+    #[serde(default)]
+    pub file: Option<String>,
+
+    /// When `file` and the per-topic CLI logs rotate. `max_files` rotated
+    /// files are kept either way.
+    #[serde(default)]
+    pub rotation: LogRotation,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Pretty,
+    /// One JSON object per event, for log aggregation.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveredFormat {
+    /// One JSON object per batch, with the full message payload.
+    #[default]
+    Jsonl,
+    /// `delivered_fields` of each batch, with a header row.
+    Csv,
+    /// Columnar files for analytics; needs the `parquet` feature. Each file
+    /// becomes readable once rotated or when the node stops.
+    Parquet,
+}
+
+/// A column of the CSV delivered log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveredField {
+    Seq,
+    BatchId,
+    Creator,
+    MerkleRoot,
+    BatchSize,
+    DeliveredAt,
+    /// The messages as JSON.
+    Payload,
+}
+
+impl DeliveredField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Seq => "seq",
+            Self::BatchId => "batch_id",
+            Self::Creator => "creator",
+            Self::MerkleRoot => "merkle_root",
+            Self::BatchSize => "batch_size",
+            Self::DeliveredAt => "delivered_at",
+            Self::Payload => "payload",
+        }
+    }
+
+    pub fn value(&self, entry: &DeliveredEntry) -> String {
+        match self {
+            Self::Seq => entry.seq.to_string(),
+            Self::BatchId => entry.batch_id.clone(),
+            Self::Creator => entry.creator.clone(),
+            Self::MerkleRoot => entry.merkle_root.clone(),
+            Self::BatchSize => entry.batch_size.to_string(),
+            Self::DeliveredAt => entry.delivered_at.clone(),
+            Self::Payload => entry.payload.as_ref().map(ToString::to_string).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// Once a file reaches `max_file_size_mb`.
+    #[default]
+    Size,
+    Hourly,
+    Daily,
 }
 
 fn default_enabled() -> bool {
@@ -39,6 +160,17 @@ fn default_delivered_file() -> String {
     "delivered.jsonl".into()
 }
 
+fn default_delivered_fields() -> Vec<DeliveredField> {
+    vec![
+        DeliveredField::Seq,
+        DeliveredField::BatchId,
+        DeliveredField::Creator,
+        DeliveredField::MerkleRoot,
+        DeliveredField::BatchSize,
+        DeliveredField::DeliveredAt,
+    ]
+}
+
 fn default_max_size() -> u64 {
     100
 }
@@ -47,15 +179,37 @@ fn default_max_files() -> usize {
     5
 }
 
+fn default_service_name() -> String {
+    "racer".into()
+}
+
+fn default_trace_sample_ratio() -> f64 {
+    1.0
+}
+
+fn default_level() -> String {
+    "info".into()
+}
+
 impl Default for LogConfig {
     fn default() -> Self {
         Self {
             enabled: default_enabled(),
             log_dir: default_log_dir(),
             delivered_file: default_delivered_file(),
+            delivered_format: DeliveredFormat::default(),
+            delivered_fields: default_delivered_fields(),
             rotation_enabled: false,
             max_file_size_mb: default_max_size(),
             max_files: default_max_files(),
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+            trace_sample_ratio: default_trace_sample_ratio(),
+            format: LogFormat::default(),
+            level: default_level(),
+            levels: BTreeMap::new(),
+            file: None,
+            rotation: LogRotation::default(),
         }
     }
 }
@@ -68,6 +222,15 @@ impl LogConfig {
     pub fn delivered_path(&self, node_id: &str) -> PathBuf {
         self.resolve_log_dir(node_id).join(&self.delivered_file)
     }
+
+    /// `EnvFilter` directives for `level` and `levels`, e.g.
+    /// `racer=info,racer::plato=debug`.
+    pub fn directives(&self) -> String {
+        std::iter::once(format!("racer={}", self.level))
+            .chain(self.levels.iter().map(|(target, level)| format!("{}={}", target, level)))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -83,7 +246,7 @@ pub struct DeliveredEntry {
 }
 
 enum LogMessage {
-    Entry(String),
+    Entry(DeliveredEntry),
     Shutdown,
 }
 
@@ -107,11 +270,7 @@ impl DeliveredMessageLogger {
             return None;
         }
 
-        let file = match OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)
-        {
+        let file = match DeliveredFile::open(config, log_path.clone()) {
             Ok(f) => f,
             Err(e) => {
                 tracing::warn!(path = %log_path.display(), error = %e, "failed to open log file");
@@ -122,9 +281,8 @@ impl DeliveredMessageLogger {
         let (sender, receiver) = mpsc::unbounded_channel();
         let seq = std::sync::Arc::new(AtomicU64::new(0));
 
-        let path_for_task = log_path.clone();
         tokio::spawn(async move {
-            writer_task(receiver, file, path_for_task).await;
+            writer_task(receiver, file).await;
         });
 
         tracing::info!(path = %log_path.display(), "delivered message logger initialized");
@@ -151,15 +309,7 @@ impl DeliveredMessageLogger {
             payload,
         };
 
-        let line = match serde_json::to_string(&entry) {
-            Ok(l) => l,
-            Err(e) => {
-                tracing::warn!(error = %e, "failed to serialize log entry");
-                return;
-            }
-        };
-
-        if self.sender.send(LogMessage::Entry(line)).is_err() {
+        if self.sender.send(LogMessage::Entry(entry)).is_err() {
             tracing::warn!(seq, "log channel closed, entry dropped");
         }
     }
@@ -173,30 +323,21 @@ impl DeliveredMessageLogger {
     }
 }
 
-async fn writer_task(
-    mut receiver: mpsc::UnboundedReceiver<LogMessage>,
-    file: File,
-    path: PathBuf,
-) {
-    let mut writer = BufWriter::new(file);
-
+async fn writer_task(mut receiver: mpsc::UnboundedReceiver<LogMessage>, mut file: DeliveredFile) {
     while let Some(msg) = receiver.recv().await {
         match msg {
-            LogMessage::Entry(line) => {
-                if let Err(e) = writeln!(writer, "{}", line) {
-                    tracing::warn!(path = %path.display(), error = %e, "failed to write log entry");
+            LogMessage::Entry(entry) => {
+                if let Err(e) = file.write(&entry) {
+                    tracing::warn!(path = %file.path().display(), error = %e, "failed to write log entry");
                 }
-                if let Err(e) = writer.flush() {
-                    tracing::warn!(path = %path.display(), error = %e, "failed to flush log");
-                }
-            }
-            LogMessage::Shutdown => {
-                let _ = writer.flush();
-                tracing::debug!(path = %path.display(), "log writer shutting down");
-                break;
             }
+            LogMessage::Shutdown => break,
         }
     }
+    if let Err(e) = file.finish() {
+        tracing::warn!(path = %file.path().display(), error = %e, "failed to finish log");
+    }
+    tracing::debug!(path = %file.path().display(), "log writer shutting down");
 }
 
 fn chrono_now() -> String {
@@ -224,6 +365,18 @@ mod tests {
         assert_eq!(config.delivered_file, "delivered.jsonl");
     }
 
+    #[test]
+    fn test_level_directives() {
+        let mut config = LogConfig::default();
+        assert_eq!(config.directives(), "racer=info");
+
+        config.level = "warn".into();
+        config.levels.insert("racer::plato".into(), "debug".into());
+        config.levels.insert("zeromq".into(), "error".into());
+        assert_eq!(config.directives(), "racer=warn,racer::plato=debug,zeromq=error");
+        assert!(config.directives().parse::<tracing_subscriber::EnvFilter>().is_ok());
+    }
+
     #[test]
     fn test_path_templating() {
         let config = LogConfig::default();
@@ -1 +1,22 @@
+pub mod delivered;
 pub mod logging;
+
+/// Length of `value` serialized as JSON, counted without keeping the bytes.
+pub(crate) fn serialized_len<T: serde::Serialize + ?Sized>(value: &T) -> serde_json::Result<usize> {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value)?;
+    Ok(counter.0)
+}
+
+struct ByteCounter(usize);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+#![cfg(test)]
+
+use std::time::Duration;
+
+use racer::config::RacerConfig;
+use racer::testing::{Cluster, ClusterError};
+use racer_core::message::DefaultMessage;
+
+/// Thresholds a four-node mesh can meet.
+fn small_cluster_config() -> RacerConfig {
+    let mut config = RacerConfig::minimal();
+    config.consensus.echo_sample_size = 3;
+    config.consensus.ready_sample_size = 3;
+    config.consensus.delivery_sample_size = 3;
+    config.consensus.ready_threshold = 2;
+    config.consensus.feedback_threshold = 2;
+    config.consensus.delivery_threshold = 2;
+    config
+}
+
+mod cluster {
+    use super::*;
+
+    #[tokio::test]
+    async fn new_should_mesh_every_node() {
+        let cluster = Cluster::<DefaultMessage>::new(4, small_cluster_config()).await.unwrap();
+
+        assert_eq!(cluster.len(), 4);
+        for node in cluster.nodes() {
+            assert!(node.is_running());
+            assert_eq!(node.wait_for_peers(3, Duration::from_secs(1)).await.unwrap(), 3);
+        }
+        assert!(matches!(cluster.node(4), Err(ClusterError::NoSuchNode { index: 4, size: 4 })));
+
+        cluster.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn submitted_batch_should_be_delivered_everywhere() {
+        let cluster = Cluster::<DefaultMessage>::new(4, small_cluster_config())
+            .await
+            .unwrap()
+            .with_delivery_timeout(Duration::from_secs(20));
+
+        let batch_id = cluster.submit(0, DefaultMessage::new()).await.unwrap();
+        cluster.await_all_delivered(&batch_id).await.unwrap();
+        assert!((0..4).all(|i| cluster.delivered(i, &batch_id)));
+
+        cluster.forget(&batch_id);
+        assert!((0..4).all(|i| !cluster.delivered(i, &batch_id)));
+
+        cluster.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn unknown_batch_should_time_out_naming_every_node() {
+        let cluster = Cluster::<DefaultMessage>::new(2, small_cluster_config())
+            .await
+            .unwrap()
+            .with_delivery_timeout(Duration::from_millis(200));
+
+        match cluster.await_all_delivered("no-such-batch").await {
+            Err(ClusterError::Undelivered { missing, .. }) => assert_eq!(missing, vec!["node-0", "node-1"]),
+            other => panic!("expected Undelivered, got {:?}", other),
+        }
+
+        cluster.shutdown().await;
+    }
+}
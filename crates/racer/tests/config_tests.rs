@@ -161,6 +161,45 @@ mod racer_config_tests {
             let result = RacerConfig::from_file("/nonexistent/path/config.toml");
             assert!(result.is_err());
         }
+
+        #[test]
+        fn should_resolve_relative_key_file_against_config_dir() {
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(
+                file,
+                r#"
+                [node]
+                key_file = "node-0.key"
+                [consensus]
+                [plato]
+                [peers]
+            "#
+            )
+            .unwrap();
+
+            let config = RacerConfig::from_file(file.path()).unwrap();
+            let expected = file.path().parent().unwrap().join("node-0.key");
+            assert_eq!(config.node.key_file, Some(expected));
+        }
+
+        #[test]
+        fn should_keep_absolute_key_file() {
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(
+                file,
+                r#"
+                [node]
+                key_file = "/etc/racer/node.key"
+                [consensus]
+                [plato]
+                [peers]
+            "#
+            )
+            .unwrap();
+
+            let config = RacerConfig::from_file(file.path()).unwrap();
+            assert_eq!(config.node.key_file, Some("/etc/racer/node.key".into()));
+        }
     }
 
     mod validation {
@@ -226,6 +265,12 @@ mod selection_type_tests {
         assert_eq!(serde_json::from_str::<SelectionType>("\"poisson\"").unwrap(), SelectionType::Poisson);
     }
 
+    #[test]
+    fn round_robin_should_use_snake_case() {
+        assert_eq!(serde_json::to_string(&SelectionType::RoundRobin).unwrap(), "\"round_robin\"");
+        assert_eq!(serde_json::from_str::<SelectionType>("\"round_robin\"").unwrap(), SelectionType::RoundRobin);
+    }
+
     #[test]
     fn should_fail_on_invalid_value() {
         let result = serde_json::from_str::<SelectionType>("\"invalid\"");
@@ -0,0 +1,47 @@
+//! The conformance fixtures checked in under `tests/conformance`, as written
+//! by `racer conformance emit`. A change to the wire format or signing bytes
+//! fails here until the golden files are re-emitted on purpose.
+
+use std::path::PathBuf;
+
+use racer::conformance::{self, ConformanceError, Fixture};
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+fn golden(name: &str) -> Fixture {
+    let path = golden_dir().join(name);
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+#[test]
+fn test_golden_fixtures_verify() {
+    let mut names: Vec<_> = std::fs::read_dir(golden_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), conformance::fixtures().len());
+    for name in names {
+        conformance::verify(&golden(&name)).unwrap_or_else(|e| panic!("{}: {}", name, e));
+    }
+}
+
+#[test]
+fn test_changed_message_fails_the_merkle_root() {
+    let mut fixture = golden("batched_message.json");
+    // The signatures cover the merkle root, not the messages.
+    fixture.frame["payload"]["messages"][0]["padding"] = serde_json::json!(8);
+    assert!(matches!(conformance::verify(&fixture), Err(ConformanceError::MerkleRoot { .. })));
+}
+
+// With `bls` on, batch frames carry the BLS fields as well.
+#[cfg(not(feature = "bls"))]
+#[test]
+fn test_fixtures_match_golden_files() {
+    for fixture in conformance::fixtures() {
+        assert_eq!(golden(&fixture.file_name()), fixture, "{} differs from its golden file", fixture.name);
+    }
+}
@@ -0,0 +1,309 @@
+//! Property tests of SPDE rounds against a model cluster.
+//!
+//! Each simulated node keeps its rounds in a `RoundTable` and decides with
+//! `GossipRound::advance` and `is_ready`, the same calls the node's response
+//! handlers make. Messages sit in one pending pool; proptest picks which is
+//! delivered next and which are lost, so every interleaving the sockets
+//! could produce is fair game.
+
+use std::collections::{HashSet, VecDeque};
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use racer::config::Thresholds;
+use racer::protocol::RoundTable;
+
+const HASH: &str = "batch";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Batch,
+    EchoSubscribe,
+    ReadySubscribe,
+    EchoResponse,
+    ReadyResponse,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Msg {
+    kind: Kind,
+    from: usize,
+    to: usize,
+}
+
+#[derive(Default)]
+struct SimNode {
+    table: RoundTable,
+    has_batch: bool,
+    published_ready: usize,
+    delivered: usize,
+}
+
+struct Sim {
+    thresholds: Thresholds,
+    nodes: Vec<SimNode>,
+    pending: Vec<Msg>,
+    /// Kinds of message the network may drop.
+    lossy: HashSet<Kind>,
+    losses: VecDeque<bool>,
+    /// Nodes that published a ReadyResponse, to check what rounds counted.
+    ready_publishers: HashSet<String>,
+}
+
+fn id(node: usize) -> String {
+    format!("n{}", node)
+}
+
+impl Sim {
+    fn new(size: usize, thresholds: Thresholds, lossy: &[Kind], losses: Vec<bool>) -> Self {
+        Self {
+            thresholds,
+            nodes: (0..size).map(|_| SimNode::default()).collect(),
+            pending: Vec::new(),
+            lossy: lossy.iter().copied().collect(),
+            losses: losses.into(),
+            ready_publishers: HashSet::new(),
+        }
+    }
+
+    fn send(&mut self, kind: Kind, from: usize, to: usize) {
+        let lost = self.lossy.contains(&kind) && self.losses.pop_front().unwrap_or(false);
+        if !lost {
+            self.pending.push(Msg { kind, from, to });
+        }
+    }
+
+    fn broadcast(&mut self, kind: Kind, from: usize) {
+        for to in 0..self.nodes.len() {
+            if to != from {
+                self.send(kind, from, to);
+            }
+        }
+    }
+
+    /// Starts `node`'s round and gossips the batch, as `run_round` does.
+    fn gossip(&mut self, node: usize) {
+        self.nodes[node].has_batch = true;
+        self.nodes[node].table.start_round_with(HASH, self.thresholds);
+        self.broadcast(Kind::EchoSubscribe, node);
+        self.broadcast(Kind::ReadySubscribe, node);
+        self.broadcast(Kind::Batch, node);
+    }
+
+    fn run(&mut self, schedule: &[Index]) {
+        self.gossip(0);
+        let mut picks = schedule.iter();
+        while !self.pending.is_empty() {
+            let i = picks.next().map_or(0, |index| index.index(self.pending.len()));
+            let msg = self.pending.swap_remove(i);
+            self.handle(msg);
+        }
+    }
+
+    fn handle(&mut self, Msg { kind, from, to }: Msg) {
+        match kind {
+            Kind::Batch => {
+                // Dedup drops copies of a batch already held.
+                if !self.nodes[to].has_batch {
+                    self.nodes[to].has_batch = true;
+                    self.nodes[to].table.start_round_with(HASH, self.thresholds);
+                    self.broadcast(Kind::EchoResponse, to);
+                    self.gossip(to);
+                }
+            }
+            Kind::EchoSubscribe => {
+                if self.nodes[to].has_batch {
+                    self.broadcast(Kind::EchoResponse, to);
+                }
+            }
+            Kind::ReadySubscribe => {
+                let ready = self.nodes[to]
+                    .table
+                    .get_round(HASH)
+                    .is_some_and(|round| round.is_ready(&self.thresholds));
+                if ready {
+                    self.broadcast(Kind::ReadyResponse, to);
+                }
+            }
+            Kind::EchoResponse | Kind::ReadyResponse => self.respond(kind, from, to),
+        }
+    }
+
+    /// Records a response at `to`, if it holds the round, and acts on the
+    /// resulting step.
+    fn respond(&mut self, kind: Kind, from: usize, to: usize) {
+        let thresholds = self.thresholds;
+        let Some(round) = self.nodes[to].table.get_round_mut(HASH) else {
+            return;
+        };
+        if kind == Kind::EchoResponse {
+            round.record_echo(&id(from));
+        } else {
+            round.record_ready(&id(from));
+        }
+        let step = round.advance(&thresholds);
+
+        if step.publish_ready {
+            assert!(round.is_ready(&thresholds));
+        }
+        if step.deliver {
+            assert!(round.ready_received.len() >= thresholds.delivery);
+            assert!(round.ready_received.is_subset(&self.ready_publishers));
+            assert!(!round.ready_received.contains(&id(to)));
+        }
+
+        if step.publish_ready {
+            self.nodes[to].published_ready += 1;
+            self.ready_publishers.insert(id(to));
+            self.broadcast(Kind::ReadyResponse, to);
+        }
+        if step.deliver {
+            self.nodes[to].delivered += 1;
+            let _ = self.nodes[to].table.mark_delivered(HASH);
+        }
+    }
+}
+
+/// A cluster size and thresholds that pass config validation
+/// (`0 < ready < feedback < delivery`), with `delivery` at most the peers
+/// every node hears from.
+fn cluster() -> impl Strategy<Value = (usize, Thresholds)> {
+    (4usize..=10).prop_flat_map(|size| {
+        (1..=size - 3).prop_flat_map(move |ready| {
+            (ready + 1..=size - 2).prop_flat_map(move |feedback| {
+                (feedback + 1..=size - 1).prop_map(move |delivery| {
+                    (size, Thresholds { ready, feedback, delivery })
+                })
+            })
+        })
+    })
+}
+
+const ALL_KINDS: [Kind; 5] = [
+    Kind::Batch,
+    Kind::EchoSubscribe,
+    Kind::ReadySubscribe,
+    Kind::EchoResponse,
+    Kind::ReadyResponse,
+];
+
+proptest! {
+    /// Safety under any loss and order: every node vouches Ready and
+    /// delivers at most once, and only on responses from distinct peers that
+    /// really published them.
+    #[test]
+    fn steps_happen_at_most_once(
+        (size, thresholds) in cluster(),
+        schedule in prop::collection::vec(any::<Index>(), 0..512),
+        losses in prop::collection::vec(any::<bool>(), 0..512),
+    ) {
+        let mut sim = Sim::new(size, thresholds, &ALL_KINDS, losses);
+        sim.run(&schedule);
+
+        for node in &sim.nodes {
+            prop_assert!(node.published_ready <= 1);
+            prop_assert!(node.delivered <= 1);
+            if node.delivered == 1 {
+                prop_assert!(node.has_batch);
+            }
+        }
+    }
+
+    /// Validity: without loss, every node delivers the batch, whatever the
+    /// order messages arrive in.
+    #[test]
+    fn lossless_rounds_deliver_everywhere(
+        (size, thresholds) in cluster(),
+        schedule in prop::collection::vec(any::<Index>(), 0..512),
+    ) {
+        let mut sim = Sim::new(size, thresholds, &[], Vec::new());
+        sim.run(&schedule);
+
+        for node in &sim.nodes {
+            prop_assert_eq!(node.delivered, 1);
+        }
+    }
+
+    /// Agreement: once any node delivers, every node holding the batch
+    /// delivers too, as long as Ready traffic gets through. Batches and
+    /// echoes may be lost.
+    #[test]
+    fn delivery_spreads_to_every_holder(
+        (size, thresholds) in cluster(),
+        schedule in prop::collection::vec(any::<Index>(), 0..512),
+        losses in prop::collection::vec(any::<bool>(), 0..512),
+    ) {
+        let lossy = [Kind::Batch, Kind::EchoSubscribe, Kind::EchoResponse];
+        let mut sim = Sim::new(size, thresholds, &lossy, losses);
+        sim.run(&schedule);
+
+        if sim.nodes.iter().any(|node| node.delivered > 0) {
+            for node in sim.nodes.iter().filter(|node| node.has_batch) {
+                prop_assert_eq!(node.delivered, 1);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TableOp {
+    Start(u8),
+    Deliver(u8),
+}
+
+fn table_op() -> impl Strategy<Value = TableOp> {
+    prop_oneof![
+        (0u8..16).prop_map(TableOp::Start),
+        (0u8..16).prop_map(TableOp::Deliver),
+    ]
+}
+
+proptest! {
+    /// `RoundTable` against a model of its delivered window: rounds stay
+    /// active until delivered, and only the last `max_delivered` delivered
+    /// rounds are remembered, the rest evicted in order.
+    #[test]
+    fn round_table_matches_model(
+        max_delivered in 1usize..8,
+        ops in prop::collection::vec(table_op(), 0..128),
+    ) {
+        let mut table = RoundTable::new();
+        table.set_max_delivered(max_delivered);
+        let mut rounds: HashSet<String> = HashSet::new();
+        let mut delivered: HashSet<String> = HashSet::new();
+        let mut window: VecDeque<String> = VecDeque::new();
+
+        for op in ops {
+            match op {
+                TableOp::Start(n) => {
+                    let hash = format!("h{}", n);
+                    table.start_round(hash.as_str());
+                    rounds.insert(hash);
+                }
+                TableOp::Deliver(n) => {
+                    let hash = format!("h{}", n);
+                    if rounds.contains(&hash) {
+                        delivered.insert(hash.clone());
+                    }
+                    window.push_back(hash.clone());
+                    let mut expected = Vec::new();
+                    while window.len() > max_delivered {
+                        let old = window.pop_front().unwrap();
+                        rounds.remove(&old);
+                        delivered.remove(&old);
+                        expected.push(old);
+                    }
+                    prop_assert_eq!(table.mark_delivered(&hash), expected);
+                }
+            }
+
+            prop_assert_eq!(table.active_rounds(), rounds.difference(&delivered).count());
+            for hash in &window {
+                prop_assert!(table.was_recently_delivered(hash));
+            }
+            for hash in &rounds {
+                prop_assert_eq!(table.is_delivered(hash), delivered.contains(hash));
+            }
+        }
+    }
+}
@@ -37,3 +37,93 @@ async fn test_dealer_feedback_loop() {
     assert_eq!(from_peer, "peer-1");
     assert_eq!(received_content, feedback_content);
 }
+
+#[tokio::test]
+async fn test_congestion_update_fanout_to_connected_dealers() {
+    use racer::config::RacerConfig;
+    use racer::crypto::KeyPair;
+    use racer::node::Node;
+    use racer::protocol::{CongestionUpdate, HealthSummary, ProtocolMessage};
+    use racer_core::message::DefaultMessage;
+
+    let logs = tempfile::tempdir().unwrap();
+    let mut config = RacerConfig::minimal();
+    config.logging.log_dir = logs.path().join("{node_id}").to_string_lossy().into_owned();
+    config.node.router_bind = "tcp://127.0.0.1:27311".into();
+    config.node.publisher_bind = "tcp://127.0.0.1:27312".into();
+    config.consensus.health_interval_secs = 0;
+    config.plato.update_interval_secs = 0.2;
+    let expected_latency = config.plato.target_latency_secs;
+
+    let node = Node::<DefaultMessage>::new(config).await.unwrap();
+    node.start().await.unwrap();
+
+    let mut dealer = zeromq::DealerSocket::new();
+    dealer.connect("tcp://127.0.0.1:27311").await.expect("failed to connect");
+
+    // Any router message registers us as a connected dealer
+    let hello = ProtocolMessage::<DefaultMessage>::HealthSummary(HealthSummary::new(
+        KeyPair::generate().public_key(),
+        60,
+        0,
+        0,
+        0.0,
+    ));
+    dealer
+        .send(serde_json::to_vec(&hello).unwrap().into())
+        .await
+        .expect("failed to send");
+
+    let mut fanned_out = None;
+    for _ in 0..5 {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(2), dealer.recv())
+            .await
+            .expect("timed out waiting for CongestionUpdate")
+            .expect("dealer recv failed");
+        let update: CongestionUpdate = serde_json::from_slice(&frame.into_vec()[0]).unwrap();
+        if update.status == "CongestionUpdate" {
+            fanned_out = Some(update);
+            break;
+        }
+    }
+
+    let stats = node.pipeline_stats();
+    node.stop().await;
+
+    // The unsigned HealthSummary is rejected by the verify stage but still answered
+    assert_eq!(stats.received, 1);
+    assert_eq!(stats.rejected, 1);
+    assert_eq!(stats.processed, 1);
+
+    let update = fanned_out.expect("no periodic CongestionUpdate received");
+    assert!((update.current_latency - expected_latency).abs() < 0.001);
+}
+
+#[tokio::test]
+async fn test_dealer_connects_once_peer_router_appears() {
+    use racer::network::PeerEvent;
+    use std::time::Duration;
+
+    let network = RacerNetwork::new("tcp://127.0.0.1:0", "tcp://127.0.0.1:0");
+    let mut events = network.subscribe_peer_events();
+
+    // Nothing listens yet: the frame waits in the peer's send queue.
+    network.connect_to_peer("peer-1", "tcp://127.0.0.1:27391").await.expect("failed to connect");
+    network.send_to_peer("peer-1", b"queued".to_vec()).await.expect("failed to send");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let mut peer_router = zeromq::RouterSocket::new();
+    peer_router.bind("tcp://127.0.0.1:27391").await.expect("failed to bind peer router");
+
+    let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+        .await
+        .expect("no peer event")
+        .unwrap();
+    assert_eq!(event, PeerEvent::Up("peer-1".into()));
+
+    let msg = tokio::time::timeout(Duration::from_secs(5), peer_router.recv())
+        .await
+        .expect("queued frame not delivered")
+        .unwrap();
+    assert_eq!(msg.into_vec()[1], &b"queued"[..]);
+}
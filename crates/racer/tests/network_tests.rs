@@ -794,10 +794,248 @@ mod racer_network_topic_subscription {
         assert!(!network.is_subscribed("any_topic").await);
     }
 
+    #[tokio::test]
+    async fn stats_should_list_subscriptions_sorted() {
+        let network = RacerNetwork::new("tcp://127.0.0.1:6002", "tcp://127.0.0.1:6003");
+        network.subscribe_topic("b").await.unwrap();
+        network.subscribe_topic("a").await.unwrap();
+        network.unsubscribe_topic("b").await.unwrap();
+        network.subscribe_topic("c").await.unwrap();
+
+        assert_eq!(network.stats().await.subscriptions, vec!["a", "c"]);
+    }
+
     // Note: Full subscription tests require ZeroMQ connections.
     // These tests focus on internal state management that we can verify.
 }
 
+// =============================================================================
+// RACER NETWORK ENCRYPTION TESTS
+// =============================================================================
+
+mod racer_network_encryption {
+    use super::*;
+    use racer::config::EncryptionMode;
+    use racer::crypto::TransportKeys;
+    use std::time::Duration;
+    use zeromq::{Socket, SocketSend};
+
+    fn encrypted_network(router: &str, publisher: &str, keys: &KeyPair, mode: EncryptionMode) -> RacerNetwork {
+        RacerNetwork::new(router, publisher).with_encryption(TransportKeys::new(keys.clone()), mode)
+    }
+
+    #[tokio::test]
+    async fn sealed_request_and_reply_should_roundtrip() {
+        let a_keys = KeyPair::generate();
+        let b_keys = KeyPair::generate();
+        let a = encrypted_network("tcp://127.0.0.1:27321", "tcp://127.0.0.1:27322", &a_keys, EncryptionMode::Required);
+        let b = encrypted_network("tcp://127.0.0.1:27323", "tcp://127.0.0.1:27324", &b_keys, EncryptionMode::Required);
+        b.bind().await.unwrap();
+
+        a.set_peer_key("b", b_keys.public_key()).await;
+        b.set_peer_key("a", a_keys.public_key()).await;
+        a.connect_to_peer("b", "tcp://127.0.0.1:27323").await.unwrap();
+        a.send_to_peer("b", b"request".to_vec()).await.unwrap();
+
+        let (identity, content) = tokio::time::timeout(Duration::from_secs(5), b.recv_router())
+            .await
+            .expect("timed out waiting for request")
+            .unwrap();
+        assert_eq!(&content[..], b"request");
+
+        b.send_router_reply(identity, b"reply".to_vec()).await.unwrap();
+        let (peer_id, content) = tokio::time::timeout(Duration::from_secs(5), a.recv_dealer())
+            .await
+            .expect("timed out waiting for reply")
+            .unwrap();
+        assert_eq!(peer_id, "b");
+        assert_eq!(&content[..], b"reply");
+    }
+
+    #[tokio::test]
+    async fn required_mode_should_drop_plaintext_and_unknown_senders() {
+        let keys = KeyPair::generate();
+        let network = encrypted_network("tcp://127.0.0.1:27325", "tcp://127.0.0.1:27326", &keys, EncryptionMode::Required);
+        network.bind().await.unwrap();
+
+        let mut dealer = zeromq::DealerSocket::new();
+        dealer.connect("tcp://127.0.0.1:27325").await.unwrap();
+        dealer.send(b"plaintext".to_vec().into()).await.unwrap();
+        let stranger = TransportKeys::new(KeyPair::generate());
+        dealer.send(stranger.seal(&keys.public_key(), b"stranger").unwrap().into()).await.unwrap();
+
+        let sender_keys = KeyPair::generate();
+        network.set_peer_key("sender", sender_keys.public_key()).await;
+        let sender = TransportKeys::new(sender_keys);
+        let sealed = sender.seal(&keys.public_key(), b"sealed").unwrap();
+        dealer.send(sealed.into()).await.unwrap();
+
+        let (_, content) = tokio::time::timeout(Duration::from_secs(5), network.recv_router())
+            .await
+            .expect("timed out waiting for sealed frame")
+            .unwrap();
+        assert_eq!(&content[..], b"sealed");
+    }
+
+    #[tokio::test]
+    async fn required_mode_should_refuse_to_send_without_peer_key() {
+        let network = encrypted_network(
+            "tcp://127.0.0.1:27327",
+            "tcp://127.0.0.1:27328",
+            &KeyPair::generate(),
+            EncryptionMode::Required,
+        );
+
+        let result = network.send_to_peer("unknown", b"request".to_vec()).await;
+        assert!(matches!(result, Err(NetworkError::Encryption(_))));
+    }
+
+    #[tokio::test]
+    async fn new_network_should_default_to_plaintext() {
+        let network = RacerNetwork::new("tcp://127.0.0.1:27329", "tcp://127.0.0.1:27330");
+        assert_eq!(network.encryption(), EncryptionMode::Off);
+    }
+}
+
+// =============================================================================
+// RACER NETWORK ENDPOINT TESTS
+// =============================================================================
+
+mod racer_network_endpoints {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn dealer_should_fall_back_to_the_next_endpoint() {
+        let server = RacerNetwork::new("tcp://127.0.0.1:27361", "tcp://127.0.0.1:27362")
+            .with_extra_binds(vec!["tcp://127.0.0.1:27363".to_string()], Vec::new());
+        server.bind().await.unwrap();
+
+        // Nothing listens on the first endpoint.
+        let client = RacerNetwork::new("tcp://127.0.0.1:27364", "tcp://127.0.0.1:27365");
+        let endpoints = ["tcp://127.0.0.1:27369".to_string(), "tcp://127.0.0.1:27363".to_string()];
+        client.connect_to_peer_endpoints("server", &endpoints).await.unwrap();
+        client.send_to_peer("server", b"request".to_vec()).await.unwrap();
+
+        let (_, content) = tokio::time::timeout(Duration::from_secs(5), server.recv_router())
+            .await
+            .expect("timed out waiting for request")
+            .unwrap();
+        assert_eq!(&content[..], b"request");
+        assert_eq!(client.connected_endpoint("server").await.as_deref(), Some("tcp://127.0.0.1:27363"));
+    }
+
+    #[tokio::test]
+    async fn connecting_without_endpoints_should_fail() {
+        let network = RacerNetwork::new("tcp://127.0.0.1:27366", "tcp://127.0.0.1:27367");
+        let result = network.connect_to_peer_endpoints("nowhere", &[]).await;
+        assert!(matches!(result, Err(NetworkError::Connect(_))));
+        assert!(network.connected_endpoint("nowhere").await.is_none());
+    }
+}
+
+mod racer_network_heartbeat {
+    use super::*;
+    use racer::network::{HeartbeatPolicy, PeerEvent, SocketOptions};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn answered_heartbeats_should_keep_a_quiet_link_up() {
+        let server = RacerNetwork::new("tcp://127.0.0.1:27381", "tcp://127.0.0.1:27382");
+        server.bind().await.unwrap();
+
+        let options = SocketOptions {
+            heartbeat: Some(HeartbeatPolicy::new(Duration::from_millis(50), Duration::from_millis(200))),
+            ..SocketOptions::default()
+        };
+        let client = RacerNetwork::with_options("tcp://127.0.0.1:27383", "tcp://127.0.0.1:27384", options);
+        let mut events = client.subscribe_peer_events();
+        client.connect_to_peer("server", "tcp://127.0.0.1:27381").await.unwrap();
+
+        let up = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("timed out waiting for the link")
+            .unwrap();
+        assert!(matches!(up, PeerEvent::Up(_)));
+
+        // Several timeouts pass without application traffic.
+        let quiet = tokio::time::timeout(Duration::from_millis(800), events.recv()).await;
+        assert!(quiet.is_err(), "link went down: {:?}", quiet);
+
+        // Heartbeats are answered by the router, not handed to the node.
+        assert!(tokio::time::timeout(Duration::from_millis(100), server.recv_router()).await.is_err());
+        assert!(tokio::time::timeout(Duration::from_millis(100), client.recv_dealer()).await.is_err());
+    }
+}
+
+mod racer_network_local {
+    use super::*;
+    use racer::network::LocalBus;
+    use std::time::Duration;
+
+    fn local_network(name: &str, bus: &LocalBus) -> RacerNetwork {
+        RacerNetwork::new(format!("local://{}", name), format!("local://{}/pub", name)).with_local_bus(bus.clone())
+    }
+
+    #[tokio::test]
+    async fn request_and_reply_should_roundtrip_in_process() {
+        let bus = LocalBus::new();
+        let a = local_network("a", &bus);
+        let b = local_network("b", &bus);
+        b.bind().await.unwrap();
+
+        a.connect_to_peer("b", "local://b").await.unwrap();
+        a.send_to_peer("b", b"request".to_vec()).await.unwrap();
+        let (identity, content) = tokio::time::timeout(Duration::from_secs(5), b.recv_router())
+            .await
+            .expect("timed out waiting for request")
+            .unwrap();
+        assert_eq!(&content[..], b"request");
+
+        b.send_router_reply(identity, b"reply".to_vec()).await.unwrap();
+        let (peer_id, content) = tokio::time::timeout(Duration::from_secs(5), a.recv_dealer())
+            .await
+            .expect("timed out waiting for reply")
+            .unwrap();
+        assert_eq!(peer_id, "b");
+        assert_eq!(&content[..], b"reply");
+        assert_eq!(a.connected_endpoint("b").await.as_deref(), Some("local://b"));
+    }
+
+    #[tokio::test]
+    async fn published_frames_should_reach_local_subscribers() {
+        let bus = LocalBus::new();
+        let a = local_network("a", &bus);
+        let b = local_network("b", &bus);
+        b.bind().await.unwrap();
+
+        a.subscribe_to_peer("local://b/pub").await.unwrap();
+        a.subscribe_topic("echo").await.unwrap();
+        b.publish("ready-1", b"unwanted".to_vec()).await.unwrap();
+        b.publish("echo-1", b"response".to_vec()).await.unwrap();
+
+        let (topic, content) = tokio::time::timeout(Duration::from_secs(5), a.recv_subscriber())
+            .await
+            .expect("timed out waiting for response")
+            .unwrap();
+        assert_eq!(topic, "echo-1");
+        assert_eq!(&content[..], b"response");
+    }
+
+    #[tokio::test]
+    async fn local_endpoints_should_be_bound_once_until_dropped() {
+        let bus = LocalBus::new();
+        let first = local_network("a", &bus);
+        first.bind().await.unwrap();
+        assert_eq!(bus.endpoints(), vec!["local://a", "local://a/pub"]);
+        assert!(matches!(local_network("a", &bus).bind().await, Err(NetworkError::Bind(_))));
+
+        drop(first);
+        assert!(bus.endpoints().is_empty());
+        assert!(local_network("a", &bus).subscribe_to_peer("local://a/pub").await.is_err());
+    }
+}
+
 // =============================================================================
 // INTEGRATION TESTS
 // =============================================================================
@@ -10,9 +10,14 @@ use racer_core::message::DefaultMessage;
 // TEST HELPERS
 // =============================================================================
 
-/// Creates a minimal valid configuration for testing.
+/// Creates a minimal valid configuration for testing, logging deliveries
+/// under a temporary directory instead of `logs/` in the working tree.
 fn minimal_config() -> RacerConfig {
-    RacerConfig::minimal()
+    static LOG_DIR: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+    let dir = LOG_DIR.get_or_init(|| tempfile::tempdir().unwrap());
+    let mut config = RacerConfig::minimal();
+    config.logging.log_dir = dir.path().join("{node_id}").to_string_lossy().into_owned();
+    config
 }
 
 /// Creates a configuration with a specific node ID.
@@ -163,6 +168,33 @@ mod construction {
         let expected_id = format!("node-{}", &node.public_key().to_hex()[..8]);
         assert_eq!(node.id(), expected_id);
     }
+
+    // -------------------------------------------------------------------------
+    // Key File Tests
+    // -------------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn new_should_load_key_file() {
+        let keys = KeyPair::generate();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node.key");
+        std::fs::write(&path, hex::encode(keys.to_bytes())).unwrap();
+
+        let mut config = minimal_config();
+        config.node.key_file = Some(path);
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        assert_eq!(node.public_key(), keys.public_key());
+    }
+
+    #[tokio::test]
+    async fn new_should_fail_on_missing_key_file() {
+        let mut config = minimal_config();
+        config.node.key_file = Some("/nonexistent/node.key".into());
+
+        let result = Node::<DefaultMessage>::new(config).await;
+        assert!(matches!(result, Err(NodeError::Config(_))));
+    }
 }
 
 // =============================================================================
@@ -328,6 +360,9 @@ mod gossip_state_integration {
             stats.active_rounds, 0,
             "should have no active rounds initially"
         );
+        assert_eq!((stats.delivered, stats.timed_out), (0, 0));
+        assert!(stats.avg_echo_phase.is_none());
+        assert!(stats.recent_rounds.is_empty());
     }
 
     #[tokio::test]
@@ -338,6 +373,15 @@ mod gossip_state_integration {
         // GossipStats should be a valid struct
         assert!(stats.active_rounds >= 0);
     }
+
+    #[tokio::test]
+    async fn cluster_health_should_report_no_peers_initially() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let health = node.cluster_health().await;
+
+        assert_eq!(health.reporting_peers, 0);
+        assert!((health.delivery_rate - 1.0).abs() < f64::EPSILON);
+    }
 }
 
 // =============================================================================
@@ -392,6 +436,304 @@ mod plato_integration {
     }
 }
 
+// =============================================================================
+// DRY RUN TESTS
+// =============================================================================
+mod dry_run {
+    use super::*;
+
+    #[tokio::test]
+    async fn dry_run_should_report_batch_details() {
+        let node = Node::<DefaultMessage>::new(config_with_id("dry")).await.unwrap();
+        let msg = DefaultMessage::new();
+        let report = node.dry_run(msg.clone()).await.unwrap();
+
+        assert!(report.valid);
+        assert!(report.validation_error.is_none());
+        assert_eq!(report.batch_id, format!("dry-{}", msg.timestamp));
+        assert_eq!(report.batch_hash.len(), 64);
+        assert!(report.envelope_size > report.message_size);
+    }
+
+    #[tokio::test]
+    async fn dry_run_should_not_touch_vector_clock() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let before = node.vector_clock().await;
+
+        node.dry_run(DefaultMessage::new()).await.unwrap();
+
+        assert_eq!(node.vector_clock().await, before);
+        assert_eq!(node.gossip_stats().await.active_rounds, 0);
+    }
+
+    #[tokio::test]
+    async fn dry_run_should_estimate_timeout_from_plato() {
+        let mut config = minimal_config();
+        config.plato.target_latency_secs = 8.0;
+        config.plato.max_gossip_timeout_secs = 60.0;
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+
+        let report = node.dry_run(DefaultMessage::new()).await.unwrap();
+
+        assert!((report.phase_timeout_secs - 8.0).abs() < 1e-9);
+        assert!(report.estimated_round_timeout_secs > 16.0);
+        assert_eq!(report.known_peers, 0);
+        assert_eq!(report.echo_peers, 0);
+    }
+}
+
+// =============================================================================
+// PIPELINE TESTS
+// =============================================================================
+mod pipeline {
+    use super::*;
+
+    #[tokio::test]
+    async fn pipeline_stats_should_be_zero_initially() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let stats = node.pipeline_stats();
+
+        assert_eq!(stats.received, 0);
+        assert_eq!(stats.processed, 0);
+        assert_eq!(stats.delivered, 0);
+    }
+
+    #[tokio::test]
+    async fn network_stats_should_be_empty_initially() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let stats = node.network_stats().await;
+
+        assert_eq!(stats.bytes_sent, 0);
+        assert_eq!(stats.bytes_received, 0);
+        assert!(stats.messages_sent.is_empty());
+        assert!(stats.peers.is_empty());
+    }
+}
+
+// =============================================================================
+// ADMIN API TESTS
+// =============================================================================
+
+mod admin {
+    use super::*;
+    use racer::node::admin::{self, AdminRequest, AdminResponse};
+
+    fn admin_config(port: u16) -> RacerConfig {
+        let mut config = config_with_id("admin-node");
+        config.node.router_bind = format!("tcp://127.0.0.1:{}", port);
+        config.node.publisher_bind = format!("tcp://127.0.0.1:{}", port + 1);
+        config.node.admin_bind = Some(format!("127.0.0.1:{}", port + 2));
+        config.consensus.health_interval_secs = 0;
+        config.plato.update_interval_secs = 0.0;
+        config
+    }
+
+    #[tokio::test]
+    async fn status_should_report_node_over_admin_api() {
+        let node = Node::<DefaultMessage>::new(admin_config(27341)).await.unwrap();
+        node.start().await.unwrap();
+
+        let response = admin::request("127.0.0.1:27343", &AdminRequest::Status).await.unwrap();
+        let AdminResponse::Status(status) = response else {
+            panic!("expected status response, got {:?}", response);
+        };
+        assert_eq!(status.id, "admin-node");
+        assert!(status.running);
+        assert_eq!(status.known_peers, 0);
+
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn peers_should_list_added_peers() {
+        let node = Node::<DefaultMessage>::new(admin_config(27344)).await.unwrap();
+        node.start().await.unwrap();
+        node.add_peer(make_peer("peer-a")).await;
+
+        let response = admin::request("127.0.0.1:27346", &AdminRequest::Peers).await.unwrap();
+        let AdminResponse::Peers { peers } = response else {
+            panic!("expected peers response, got {:?}", response);
+        };
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].id, "peer-a");
+        assert!(peers[0].last_seen_secs.is_none());
+
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn topology_should_link_node_to_its_peers() {
+        let node = Node::<DefaultMessage>::new(admin_config(27374)).await.unwrap();
+        node.start().await.unwrap();
+        let peer = make_peer("peer-a");
+        node.add_peer(peer.clone()).await;
+
+        let response = admin::request("127.0.0.1:27376", &AdminRequest::Topology).await.unwrap();
+        let AdminResponse::Topology(report) = response else {
+            panic!("expected topology response, got {:?}", response);
+        };
+        assert_eq!(report.local, node.public_key().to_hex()[..10]);
+        assert_eq!(report.nodes.len(), 2);
+        assert_eq!(report.edges.len(), 1);
+        assert_eq!((&report.edges[0].from, &report.edges[0].to), (&report.local, &peer.key_id()));
+        assert!(report.to_dot().contains(&format!("\"{}\" -> \"{}\"", report.local, peer.key_id())));
+
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn submit_should_return_batch_id() {
+        let node = Node::<DefaultMessage>::new(admin_config(27350)).await.unwrap();
+        node.start().await.unwrap();
+
+        let message = DefaultMessage::new();
+        let request = AdminRequest::Submit {
+            message: serde_json::to_value(&message).unwrap(),
+            wait: false,
+        };
+        let response = admin::request("127.0.0.1:27352", &request).await.unwrap();
+        let AdminResponse::Submitted { batch_id, delivered } = response else {
+            panic!("expected submitted response, got {:?}", response);
+        };
+        assert_eq!(batch_id, format!("admin-node-{}", message.timestamp));
+        assert!(delivered.is_none());
+
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn submit_should_reject_malformed_message() {
+        let node = Node::<DefaultMessage>::new(admin_config(27353)).await.unwrap();
+        node.start().await.unwrap();
+
+        let request = AdminRequest::Submit {
+            message: serde_json::json!({ "unexpected": true }),
+            wait: false,
+        };
+        let response = admin::request("127.0.0.1:27355", &request).await.unwrap();
+        assert!(matches!(response, AdminResponse::Error { .. }));
+
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn status_should_report_zero_uptime_before_start() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let status = node.status().await;
+
+        assert!(!status.running);
+        assert_eq!(status.uptime_secs, 0.0);
+    }
+
+    #[tokio::test]
+    async fn request_should_fail_without_listener() {
+        let result = admin::request("127.0.0.1:27349", &AdminRequest::Status).await;
+        assert!(result.is_err());
+    }
+}
+
+// =============================================================================
+// IMPORT TESTS
+// =============================================================================
+
+mod import {
+    use super::*;
+    use racer::protocol::{merkle_root, BatchedMessages};
+
+    fn signed_batch(keys: &KeyPair, batch_id: &str) -> BatchedMessages<DefaultMessage> {
+        let messages = vec![DefaultMessage::new()];
+        let mut bm = BatchedMessages {
+            batch_id: batch_id.to_string(),
+            creator_ecdsa: keys.public_key(),
+            sender_ecdsa: keys.public_key(),
+            merkle_root: merkle_root(&messages),
+            batch_size: 1,
+            messages,
+            vector_clock: Default::default(),
+            creator_signature: None,
+            sender_signature: None,
+            created_at: 1_000,
+            membership: Vec::new(),
+            channel: Some("imported".to_string()),
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
+            #[cfg(feature = "bls")]
+            creator_bls: None,
+            #[cfg(feature = "bls")]
+            aggregated_signature: None,
+        };
+        bm.sign_as_creator(keys);
+        bm
+    }
+
+    fn import_config(port: u16) -> RacerConfig {
+        let mut config = config_with_id("import-node");
+        config.node.router_bind = format!("tcp://127.0.0.1:{}", port);
+        config.node.publisher_bind = format!("tcp://127.0.0.1:{}", port + 1);
+        config.consensus.health_interval_secs = 0;
+        config.plato.update_interval_secs = 0.0;
+        config
+    }
+
+    #[tokio::test]
+    async fn import_should_fail_before_start() {
+        let node = Node::<DefaultMessage>::new(minimal_config()).await.unwrap();
+        let keys = KeyPair::generate();
+
+        let result = node.import_batches(vec![signed_batch(&keys, "b-1")]).await;
+        assert!(matches!(result, Err(NodeError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn import_should_deliver_verified_batches_once() {
+        let node = Node::<DefaultMessage>::new(import_config(27370)).await.unwrap();
+        node.start().await.unwrap();
+        let mut channel = node.subscribe_channel("imported");
+        let keys = KeyPair::generate();
+
+        let good = signed_batch(&keys, "b-1");
+        let mut forged = signed_batch(&keys, "b-2");
+        forged.batch_id = "b-forged".to_string();
+
+        let report = node.import_batches(vec![good.clone(), forged, good.clone()]).await.unwrap();
+        assert_eq!((report.imported, report.duplicates, report.rejected), (1, 1, 1));
+
+        let delivery = tokio::time::timeout(std::time::Duration::from_secs(2), channel.recv())
+            .await
+            .expect("imported batch should be delivered")
+            .unwrap();
+        assert_eq!(delivery.batch.batch_id, "b-1");
+        assert_eq!(delivery.hash, good.compute_hash());
+
+        let again = node.import_batches(vec![good]).await.unwrap();
+        assert_eq!(again.duplicates, 1);
+
+        node.stop().await;
+    }
+
+    #[tokio::test]
+    async fn import_should_reject_batches_whose_messages_were_changed() {
+        let node = Node::<DefaultMessage>::new(import_config(27372)).await.unwrap();
+        node.start().await.unwrap();
+        let keys = KeyPair::generate();
+
+        // Edited the way a batch in a `.rcr` file would be, keeping its
+        // merkle root and creator signature.
+        let mut archived = serde_json::to_value(signed_batch(&keys, "b-1")).unwrap();
+        archived["messages"][0]["padding"] = serde_json::json!(99);
+        let tampered: BatchedMessages<DefaultMessage> = serde_json::from_value(archived).unwrap();
+        assert!(tampered.verify_creator_signature());
+
+        let report = node.import_batches(vec![tampered]).await.unwrap();
+        assert_eq!((report.imported, report.rejected), (0, 1));
+
+        node.stop().await;
+    }
+}
+
 // =============================================================================
 // NODE ERROR TESTS
 // =============================================================================
@@ -527,7 +869,7 @@ mod gossip_stats_struct {
 
     #[test]
     fn gossip_stats_should_be_cloneable() {
-        let stats = GossipStats { active_rounds: 5 };
+        let stats = GossipStats { active_rounds: 5, ..Default::default() };
         let cloned = stats.clone();
 
         assert_eq!(cloned.active_rounds, 5);
@@ -535,7 +877,7 @@ mod gossip_stats_struct {
 
     #[test]
     fn gossip_stats_should_be_debuggable() {
-        let stats = GossipStats { active_rounds: 3 };
+        let stats = GossipStats { active_rounds: 3, ..Default::default() };
         let debug = format!("{:?}", stats);
 
         assert!(debug.contains("3"), "debug should show active_rounds");
@@ -544,14 +886,14 @@ mod gossip_stats_struct {
 
     #[test]
     fn gossip_stats_should_store_active_rounds() {
-        let stats = GossipStats { active_rounds: 42 };
+        let stats = GossipStats { active_rounds: 42, ..Default::default() };
 
         assert_eq!(stats.active_rounds, 42);
     }
 
     #[test]
     fn gossip_stats_should_handle_zero_rounds() {
-        let stats = GossipStats { active_rounds: 0 };
+        let stats = GossipStats { active_rounds: 0, ..Default::default() };
 
         assert_eq!(stats.active_rounds, 0);
     }
@@ -560,6 +902,7 @@ mod gossip_stats_struct {
     fn gossip_stats_should_handle_large_round_count() {
         let stats = GossipStats {
             active_rounds: usize::MAX,
+            ..Default::default()
         };
 
         assert_eq!(stats.active_rounds, usize::MAX);
@@ -779,7 +1122,8 @@ mod edge_cases {
 
     #[tokio::test]
     async fn node_should_work_with_default_config() {
-        let config = RacerConfig::default();
+        let mut config = RacerConfig::default();
+        config.logging.log_dir = minimal_config().logging.log_dir;
         let result = Node::<DefaultMessage>::new(config).await;
 
         assert!(result.is_ok(), "should work with default config");
@@ -458,15 +458,15 @@ mod savgol_tests {
         }
 
         #[test]
-        fn new_should_handle_uncommon_window_sizes_with_fallback() {
-            // Sizes not in the pre-computed list should use moving average
+        fn new_should_fit_uncommon_window_sizes() {
+            // Coefficients are computed for any window, not just tabulated ones
             let mut filter = SavitzkyGolayFilter::new(13);
-            
+            assert!(filter.coefficients()[0] < 0.0, "quadratic fit weights the edges negatively");
+
             for _ in 0..15 {
                 filter.next(100.0);
             }
-            
-            // Should still produce valid output using fallback
+
             assert!((filter.value() - 100.0).abs() < 0.001);
         }
     }
@@ -1078,6 +1078,120 @@ mod controller_tests {
         }
     }
 
+    mod history {
+        use super::*;
+        use std::time::Duration;
+
+        #[test]
+        fn history_should_be_empty_before_any_sample() {
+            let controller = PlatoController::new(default_config());
+            assert!(controller.history(Duration::from_secs(3600)).is_empty());
+        }
+
+        #[test]
+        fn record_sample_should_capture_current_state() {
+            let config = custom_config(4.0, 1.0, 60.0);
+            let mut controller = PlatoController::new(config);
+
+            controller.record_sample();
+
+            let history = controller.history(Duration::from_secs(60));
+            assert_eq!(history.len(), 1);
+            assert!((history[0].current_latency - 4.0).abs() < 0.001);
+            assert!((history[0].our_rsi_up - 50.0).abs() < f64::EPSILON);
+            assert!(history[0].timestamp_ms > 0);
+        }
+
+        #[test]
+        fn history_should_drop_oldest_samples_beyond_capacity() {
+            let config = PlatoConfig {
+                history_size: 3,
+                ..Default::default()
+            };
+            let mut controller = PlatoController::new(config);
+
+            for i in 0..10 {
+                controller.record_our_latency(i as f64);
+                controller.record_sample();
+            }
+
+            assert_eq!(controller.history(Duration::from_secs(60)).len(), 3);
+        }
+
+        #[test]
+        fn zero_history_size_should_disable_recording() {
+            let config = PlatoConfig {
+                history_size: 0,
+                ..Default::default()
+            };
+            let mut controller = PlatoController::new(config);
+
+            controller.record_sample();
+
+            assert!(controller.history(Duration::from_secs(60)).is_empty());
+        }
+
+        #[test]
+        fn samples_should_serialize_to_json() {
+            let mut controller = PlatoController::new(default_config());
+            controller.record_sample();
+
+            let json = serde_json::to_string(&controller.history(Duration::from_secs(60))).unwrap();
+            assert!(json.contains("weighted_latency"));
+        }
+    }
+
+    mod estimators {
+        use super::*;
+        use racer::config::EstimatorKind;
+
+        fn config_with(estimator: EstimatorKind) -> PlatoConfig {
+            PlatoConfig {
+                estimator,
+                target_latency_secs: 30.0,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn default_estimator_should_be_rsi() {
+            let controller = PlatoController::new(default_config());
+            assert_eq!(controller.estimator_name(), "rsi");
+        }
+
+        #[test]
+        fn ewma_and_kalman_should_throttle_on_rising_latency() {
+            for kind in [EstimatorKind::Ewma, EstimatorKind::Kalman] {
+                let mut controller = PlatoController::new(config_with(kind));
+
+                for i in 0..40 {
+                    controller.record_our_latency(2.0 + i as f64 * 0.2);
+                    controller.record_peer_latency(2.0 + i as f64 * 0.2);
+                }
+                controller.check_increasing_congestion();
+
+                assert!(controller.timing_changed, "{:?} did not throttle", kind);
+                assert!(controller.current_latency() > 30.0);
+            }
+        }
+
+        #[test]
+        fn ewma_and_kalman_should_accelerate_on_falling_latency() {
+            for kind in [EstimatorKind::Ewma, EstimatorKind::Kalman] {
+                let mut controller = PlatoController::new(config_with(kind));
+
+                for i in 0..40 {
+                    controller.record_our_latency(12.0 - i as f64 * 0.2);
+                    controller.record_peer_latency(12.0 - i as f64 * 0.2);
+                }
+                controller.check_decreasing_congestion();
+
+                assert!(controller.timing_changed, "{:?} did not accelerate", kind);
+                assert!(controller.current_latency() < 30.0);
+            }
+        }
+    }
+
     mod timing_changed_flag {
         use super::*;
 
@@ -1056,6 +1056,34 @@ mod gossip_round_tests {
             assert!(round.echo_received.contains("peer1"));
             assert!(round.echo_received.contains("peer2"));
         }
+
+        #[test]
+        fn should_return_rtt_for_timestamped_peer() {
+            let mut round = GossipRound::new("hash");
+            round.record_echo_sent("peer1");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+
+            let rtt = round.record_echo("peer1").unwrap();
+
+            assert!(rtt >= std::time::Duration::from_millis(5));
+        }
+
+        #[test]
+        fn should_not_return_rtt_for_duplicate_response() {
+            let mut round = GossipRound::new("hash");
+            round.record_echo_sent("peer1");
+
+            round.record_echo("peer1");
+
+            assert!(round.record_echo("peer1").is_none());
+        }
+
+        #[test]
+        fn should_not_return_rtt_without_echo_sent() {
+            let mut round = GossipRound::new("hash");
+
+            assert!(round.record_echo("peer1").is_none());
+        }
     }
 
     mod record_ready {
@@ -1347,6 +1375,13 @@ mod batched_messages_tests {
             creator_signature: None,
             sender_signature: None,
             created_at: 1000,
+            membership: Vec::new(),
+            channel: None,
+            hops: 0,
+            max_hops: None,
+            ttl_ms: None,
+            priority: Default::default(),
+            sequence: 0,
             #[cfg(feature = "bls")]
             creator_bls: None,
             #[cfg(feature = "bls")]
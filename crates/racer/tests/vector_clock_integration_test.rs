@@ -8,7 +8,9 @@ use zeromq::{Socket, SocketSend};
 #[tokio::test]
 async fn test_vector_clock_merge_on_receive() {
     // 1. Setup Node
+    let logs = tempfile::tempdir().unwrap();
     let mut config = RacerConfig::minimal();
+    config.logging.log_dir = logs.path().join("{node_id}").to_string_lossy().into_owned();
     config.node.router_bind = "tcp://127.0.0.1:0".to_string(); // Random port
     config.node.publisher_bind = "tcp://127.0.0.1:0".to_string();
     
@@ -20,6 +22,7 @@ async fn test_vector_clock_merge_on_receive() {
     
     // Re-create node with known port
     let mut config = RacerConfig::minimal();
+    config.logging.log_dir = logs.path().join("{node_id}").to_string_lossy().into_owned();
     config.node.router_bind = router_addr.clone();
     config.node.publisher_bind = format!("tcp://127.0.0.1:{}", port + 1);
     
@@ -50,6 +53,13 @@ async fn test_vector_clock_merge_on_receive() {
         creator_signature: None,
         sender_signature: None,
         created_at: 1234567890,
+        membership: Vec::new(),
+        channel: None,
+        hops: 0,
+        max_hops: None,
+        ttl_ms: None,
+        priority: Default::default(),
+        sequence: 0,
         #[cfg(feature = "bls")]
         creator_bls: None,
         #[cfg(feature = "bls")]
@@ -0,0 +1,136 @@
+#![cfg(feature = "websocket")]
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use racer::network::RacerNetwork;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn next_text<S>(client: &mut S) -> String
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("timed out waiting for websocket message")
+            .expect("websocket closed")
+            .expect("websocket error");
+        if let Message::Text(text) = message {
+            return text;
+        }
+    }
+}
+
+// =============================================================================
+// RACER NETWORK WEBSOCKET TESTS
+// =============================================================================
+
+mod racer_network_websocket {
+    use super::*;
+
+    #[tokio::test]
+    async fn request_and_reply_should_roundtrip() {
+        let network = RacerNetwork::new("tcp://127.0.0.1:27331", "tcp://127.0.0.1:27332")
+            .with_websocket("127.0.0.1:27333");
+        network.bind().await.unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async("ws://127.0.0.1:27333").await.unwrap();
+        client.send(Message::Text("{\"hello\":1}".into())).await.unwrap();
+
+        let (identity, content) = tokio::time::timeout(Duration::from_secs(5), network.recv_router())
+            .await
+            .expect("timed out waiting for request")
+            .unwrap();
+        assert_eq!(&content[..], b"{\"hello\":1}");
+
+        network.send_router_reply(identity, b"{\"status\":\"OK\"}".to_vec()).await.unwrap();
+        assert_eq!(next_text(&mut client).await, "{\"status\":\"OK\"}");
+    }
+
+    #[tokio::test]
+    async fn broadcast_should_reach_connected_observers() {
+        let network = RacerNetwork::new("tcp://127.0.0.1:27334", "tcp://127.0.0.1:27335")
+            .with_websocket("127.0.0.1:27336");
+        network.bind().await.unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async("ws://127.0.0.1:27336").await.unwrap();
+        for _ in 0..50 {
+            if network.observer_count().await == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(network.observer_count().await, 1);
+
+        network.broadcast_observers(b"{\"delivered\":true}").await;
+        assert_eq!(next_text(&mut client).await, "{\"delivered\":true}");
+    }
+
+    #[tokio::test]
+    async fn oversized_messages_should_disconnect_the_client() {
+        let options = racer::network::SocketOptions {
+            max_frame_bytes: 1024,
+            ..Default::default()
+        };
+        let network = RacerNetwork::with_options("tcp://127.0.0.1:27386", "tcp://127.0.0.1:27387", options)
+            .with_websocket("127.0.0.1:27388");
+        network.bind().await.unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async("ws://127.0.0.1:27388").await.unwrap();
+        client.send(Message::Text("x".repeat(2048))).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(Ok(_)) = client.next().await {}
+        })
+        .await;
+        assert!(closed.is_ok(), "client was not disconnected");
+        assert_eq!(network.stats().await.oversized_frames, 1);
+        assert!(tokio::time::timeout(Duration::from_millis(100), network.recv_router()).await.is_err());
+    }
+}
+
+// =============================================================================
+// NODE WEBSOCKET TESTS
+// =============================================================================
+
+mod node_websocket {
+    use super::*;
+    use racer::config::RacerConfig;
+    use racer::crypto::KeyPair;
+    use racer::node::Node;
+    use racer::protocol::{CongestionUpdate, HealthSummary, ProtocolMessage};
+    use racer_core::message::DefaultMessage;
+
+    #[tokio::test]
+    async fn websocket_client_should_get_congestion_update_reply() {
+        let logs = tempfile::tempdir().unwrap();
+        let mut config = RacerConfig::minimal();
+        config.logging.log_dir = logs.path().join("{node_id}").to_string_lossy().into_owned();
+        config.node.router_bind = "tcp://127.0.0.1:27337".into();
+        config.node.publisher_bind = "tcp://127.0.0.1:27338".into();
+        config.node.websocket_bind = Some("127.0.0.1:27339".into());
+        config.consensus.health_interval_secs = 0;
+        config.plato.update_interval_secs = 0.0;
+
+        let node = Node::<DefaultMessage>::new(config).await.unwrap();
+        node.start().await.unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async("ws://127.0.0.1:27339").await.unwrap();
+        let hello = ProtocolMessage::<DefaultMessage>::HealthSummary(HealthSummary::new(
+            KeyPair::generate().public_key(),
+            60,
+            0,
+            0,
+            0.0,
+        ));
+        client
+            .send(Message::Text(serde_json::to_string(&hello).unwrap()))
+            .await
+            .unwrap();
+
+        let reply: CongestionUpdate = serde_json::from_str(&next_text(&mut client).await).unwrap();
+        assert_eq!(reply.status, "OK");
+
+        node.stop().await;
+    }
+}
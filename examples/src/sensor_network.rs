@@ -24,7 +24,7 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use racer::config::{
-    At2Config, LogConfig, NodeConfig, PeerConfig, PlatoConfig, RacerConfig, SelectionType,
+    At2Config, EncryptionMode, LogConfig, NodeConfig, PeerConfig, PlatoConfig, RacerConfig, SelectionType,
 };
 use racer::crypto::PublicKey;
 use racer::network::PeerInfo;
@@ -188,12 +188,20 @@ fn make_node_config(
     logging: &LogConfig,
 ) -> RacerConfig {
     RacerConfig {
+        profile: None,
         node: NodeConfig {
             id: Some(node_def.id.clone()),
             router_bind: format!("tcp://127.0.0.1:{}", network.base_router_port + idx as u16),
             publisher_bind: format!("tcp://127.0.0.1:{}", network.base_publisher_port + idx as u16),
             selection_type: SelectionType::Random,
+            encryption: EncryptionMode::Off,
+            websocket_bind: None,
+            admin_bind: None,
+            key_file: None,
+            store_path: None,
+            ..RacerConfig::minimal().node
         },
+        network: Default::default(),
         consensus: At2Config {
             echo_sample_size: consensus.echo_sample_size,
             ready_sample_size: consensus.ready_sample_size,
@@ -201,6 +209,7 @@ fn make_node_config(
             ready_threshold: consensus.ready_threshold,
             feedback_threshold: consensus.feedback_threshold,
             delivery_threshold: consensus.delivery_threshold,
+            ..Default::default()
         },
         plato: PlatoConfig {
             target_latency_secs: plato.target_latency_secs,
@@ -211,7 +220,7 @@ fn make_node_config(
             ..Default::default()
         },
         // Don't use config peers - we'll connect programmatically
-        peers: PeerConfig { routers: vec![] },
+        peers: PeerConfig::default(),
         logging: logging.clone(),
     }
 }